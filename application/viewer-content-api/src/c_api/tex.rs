@@ -15,6 +15,7 @@ pub extern "C" fn create_texture2d(
     data,
     format,
     size: Size::from_u32_pair_min_one((width, height)),
+    precomputed_mips: Vec::new(),
   };
   let data = MaybeUriData::Living(Arc::new(data));
   let data = ExternalRefPtr::new(data);
@@ -70,6 +71,7 @@ pub extern "C" fn update_texture2d_content(
     data,
     format,
     size: Size::from_u32_pair_min_one((width, height)),
+    precomputed_mips: Vec::new(),
   };
   let data = MaybeUriData::Living(Arc::new(data));
   let data = ExternalRefPtr::new(data);
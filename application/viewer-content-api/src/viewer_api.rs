@@ -231,6 +231,7 @@ impl ViewerAPI {
       data,
       format: result.info().format,
       size: result.info().size(),
+      precomputed_mips: Vec::new(),
     }
     .into()
   }
@@ -387,6 +387,16 @@ pub fn viewer_texture_input(
 
   access_cx!(cx.dyn_env(), s, ViewerDataScheduler);
   let scheduler = s.texture.clone();
+  let loader_creator = viewer_texture_uri_loader(cx);
+
+  use_uri_data_changes(cx, DBTextureUriInput, &scheduler, loader_creator)
+}
+
+/// load the texture from the uri backend without the scheduling
+pub fn viewer_texture_uri_loader(
+  cx: &mut QueryGPUHookCx<'_>,
+) -> LoaderCreator<Arc<String>, Arc<GPUBufferImage>> {
+  access_cx!(cx.dyn_env(), s, ViewerDataScheduler);
   let source = s.texture_uri_backend.clone();
 
   let loader_creator = move || {
@@ -397,7 +407,5 @@ pub fn viewer_texture_input(
         as Box<dyn Future<Output = Option<Arc<GPUBufferImage>>> + Send + Sync + Unpin>
     }) as Box<LoaderFunction<Arc<String>, Arc<GPUBufferImage>>>
   };
-  let loader_creator = Arc::new(loader_creator) as Arc<_>;
-
-  use_uri_data_changes(cx, DBTextureUriInput, &scheduler, loader_creator)
+  Arc::new(loader_creator) as Arc<_>
 }
@@ -6,6 +6,7 @@ pub struct GPUPlatformConfig {
   pub enable_backend_validation: Option<bool>,
   pub enable_hal_debug_info: Option<bool>,
   pub dx_compiler_dll_path: Option<String>,
+  pub persistent_pipeline_cache: Option<GPUPipelinePersistentCacheConfig>,
 }
 
 impl GPUPlatformConfig {
@@ -26,6 +27,7 @@ impl GPUPlatformConfig {
       enable_backend_validation: self.enable_backend_validation,
       enable_debug_info: self.enable_hal_debug_info,
       dx_compiler_dll_path: self.dx_compiler_dll_path.clone(),
+      persistent_pipeline_cache: self.persistent_pipeline_cache.clone(),
       ..Default::default()
     }
   }
@@ -39,6 +41,11 @@ impl ViewerInitConfig {
       enable_backend_validation: self.init_only.enable_backend_validation,
       enable_hal_debug_info: self.init_only.enable_hal_debug_info,
       dx_compiler_dll_path: self.init_only.dx_compiler_dll_path.clone(),
+      persistent_pipeline_cache: self
+        .init_only
+        .persistent_pipeline_cache_folder
+        .as_ref()
+        .map(GPUPipelinePersistentCacheConfig::new),
     }
   }
 }
@@ -17,6 +17,13 @@ pub struct ViewerInitConfig {
   pub using_host_driven_indirect_draw: bool,
   pub transparent_config: ViewerTransparentContentRenderStyle,
   pub enable_shadow: bool,
+  /// use the percentage-closer soft shadows as the shadow map filtering, the penumbra size is
+  /// estimated from the light size configured on each light
+  pub enable_pcss_shadow: bool,
+  /// shadow the area lights by the PCSS filtered shadow map, only take effect when the shadow is
+  /// enabled. the area lights are limited to the per scene uniform array when enabled, even in
+  /// the indirect rendering, see [use_area_light_uniform]
+  pub enable_area_light_shadow: bool,
   pub enable_taa: bool,
   pub enable_fxaa: bool,
   pub enable_msaa: bool,
@@ -39,11 +46,19 @@ pub struct ViewerStaticInitConfig {
   /// this config should be changed at runtime, but due to the implementation limitation, we have to put it here for now
   pub use_native_line_for_one_width_line: bool,
   pub texture_pool_source_init_config: TexturePoolSourceInit,
+  /// use the virtual texture system instead of the texture pool or bindless when the indirect
+  /// texture system is required, the texture memory is limited by the page atlas budget
+  pub use_virtual_texture_for_indirect_texture_system: bool,
+  pub virtual_texture_init_config: VirtualTextureSystemInit,
   /// None means use available parallelism, 1 means no parallelism
   pub thread_pool_thread_count: Option<usize>,
   pub occlusion_culling_max_scene_model_count: u32,
   pub indirect_attribute_mesh_init: IndirectAttributeMeshInitConfig,
   pub indirect_attribute_mesh_lod_config: AttributeLODConfig,
+  /// render the triangle meshes by the gpu driven meshlet lod selection, only available in the
+  /// device driven indirect backend without the multi draw indirect count downgrade
+  pub enable_indirect_mesh_lod_graph_renderer: bool,
+  pub indirect_mesh_lod_graph_config: MeshLODGraphRendererConfig,
   pub enable_indirect_storage_combine: bool,
   pub enable_reverse_z: bool,
   /// if not provided, the backend select will be automatically based on platform available
@@ -65,6 +80,10 @@ pub struct ViewerStaticInitConfig {
   /// the dxc dll path for dx12 backend, the dll must support shader model 6.7 at least
   /// if None, then using fxc compiler, which is buggy.
   pub dx_compiler_dll_path: Option<String>,
+
+  /// the folder to persist the built pipelines across runs, the recorded pipelines are prebuilt
+  /// at startup. if None, the pipelines are always built from scratch.
+  pub persistent_pipeline_cache_folder: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
@@ -93,9 +112,13 @@ impl Default for ViewerStaticInitConfig {
     Self {
       enable_reverse_z: true,
       texture_pool_source_init_config: init,
+      use_virtual_texture_for_indirect_texture_system: false,
+      virtual_texture_init_config: Default::default(),
       thread_pool_thread_count: None,
       indirect_attribute_mesh_init: Default::default(),
       indirect_attribute_mesh_lod_config: Default::default(),
+      enable_indirect_mesh_lod_graph_renderer: false,
+      indirect_mesh_lod_graph_config: Default::default(),
       wgpu_backend_select_override: None,
       enable_indirect_storage_combine: true,
       use_native_line_for_one_width_line: true,
@@ -108,6 +131,7 @@ impl Default for ViewerStaticInitConfig {
       enable_backend_validation: None,
       enable_hal_debug_info: None,
       dx_compiler_dll_path: None,
+      persistent_pipeline_cache_folder: None,
     }
   }
 }
@@ -169,6 +193,8 @@ impl Default for ViewerInitConfig {
       enable_debug_cull_result: false,
       enable_frustum_culling: true,
       enable_shadow: true,
+      enable_pcss_shadow: false,
+      enable_area_light_shadow: true,
       enable_taa: true,
       enable_msaa: false,
       enable_fxaa: false,
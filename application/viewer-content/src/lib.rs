@@ -48,8 +48,10 @@ use rendiation_scene_batch_extractor::*;
 pub use rendiation_scene_core::*;
 pub use rendiation_scene_geometry_query::*;
 use rendiation_scene_indirect_attribute_mesh_lod::*;
+use rendiation_scene_indirect_mesh_lod_graph::*;
 use rendiation_scene_rendering_gpu_gles::*;
 use rendiation_scene_rendering_gpu_indirect::*;
+pub use rendiation_scene_rendering_gpu_ray_tracing::LightProbeBakeConfig;
 use rendiation_scene_rendering_gpu_ray_tracing::*;
 use rendiation_scene_scheduler::*;
 use rendiation_shader_api::*;
@@ -125,11 +127,11 @@ pub struct ViewerSurfaceContent {
 pub fn register_viewer_content_data_model() {
   register_scene_core_data_model();
   register_selectable_data_model();
+  register_area_lighting_data_model();
   register_light_shadow_config();
   register_gui3d_extension_data_model(true);
   register_clipping_data_model();
   register_clipping_plane_array_data_model();
-  register_area_lighting_data_model();
   register_wide_styled_points_data_model(true);
   register_text3d_data_model(true);
   register_occ_style_view_dependent_data_model();
@@ -137,4 +139,5 @@ pub fn register_viewer_content_data_model() {
   register_occ_material_data_model(true);
   rendiation_transform_instanced_model::register_transform_instanced_model_data_model(true);
   rendiation_cell_mesh::register_cell_mesh_data_model(true);
+  register_mesh_lod_graph_data_model();
 }
@@ -14,6 +14,7 @@ pub struct Viewer3dRenderingCtx {
   pub(super) current_renderer_impl_ty: RasterizationRenderBackendType,
   pub(super) rtx_renderer_enabled: bool,
   pub lighting: LightSystem,
+  pub shader_debug_print: ViewerShaderDebugPrint,
   pub(super) gpu: GPU,
   pub(super) prefer_bindless_for_indirect_texture_system: bool,
 
@@ -51,6 +52,9 @@ impl Viewer3dRenderingCtx {
       self.prefer_bindless_for_indirect_texture_system;
     init_config.init_only = self.init_config.init_only.clone();
     init_config.enable_shadow = self.lighting.enable_shadow;
+    init_config.enable_pcss_shadow =
+      matches!(self.lighting.pcf_config.pcf_mode, ShadowPCFMode::PCSS);
+    init_config.enable_area_light_shadow = self.lighting.enable_area_light_shadow;
     init_config.light_surface_ty = self.lighting.lighting_surface_ty_value;
     init_config.use_array_clip = self.use_array_clip;
 
@@ -88,6 +92,7 @@ impl Viewer3dRenderingCtx {
       current_renderer_impl_ty: init_config.raster_backend_type,
       rtx_renderer_enabled: false,
       lighting: LightSystem::new(&gpu, init_config),
+      shader_debug_print: ViewerShaderDebugPrint::new(&gpu),
       gpu,
       surface_views: FastHashMap::default(),
       init_config: init_config.clone(),
@@ -120,19 +125,27 @@ impl Viewer3dRenderingCtx {
     let background = use_background(cx);
     let init_config = &self.init_config.init_only;
 
-    let ty = get_suitable_texture_system_ty(
-      cx.gpu,
-      matches!(
-        self.current_renderer_impl_ty,
-        RasterizationRenderBackendType::Indirect
-      ) || self.rtx_renderer_enabled,
-      self.prefer_bindless_for_indirect_texture_system,
-    );
+    let require_indirect = matches!(
+      self.current_renderer_impl_ty,
+      RasterizationRenderBackendType::Indirect
+    ) || self.rtx_renderer_enabled;
+    let ty = if require_indirect && init_config.use_virtual_texture_for_indirect_texture_system {
+      GPUTextureBindingSystemType::VirtualTexture
+    } else {
+      get_suitable_texture_system_ty(
+        cx.gpu,
+        require_indirect,
+        self.prefer_bindless_for_indirect_texture_system,
+      )
+    };
 
+    let virtual_texture_uri_loader = viewer_texture_uri_loader(cx);
     let texture_sys = use_texture_system(
       cx,
       ty,
       &init_config.texture_pool_source_init_config,
+      &init_config.virtual_texture_init_config,
+      &virtual_texture_uri_loader,
       viewer_texture_input,
     );
 
@@ -268,6 +281,19 @@ impl Viewer3dRenderingCtx {
         let (mesh_changes, mesh_changes_) = mesh_changes.fork();
         let (mesh_changes_, mesh_changes__) = mesh_changes_.fork();
 
+        let enable_mesh_lod_graph = init_config.enable_indirect_mesh_lod_graph_renderer
+          && !self.using_host_driven_indirect_draw
+          && is_mesh_lod_graph_renderer_supported(
+            &cx.gpu.info,
+            init_config.using_texture_as_storage_buffer_for_indirect_rendering,
+          );
+        let (mesh_changes__, mesh_lod_graph_input) = if enable_mesh_lod_graph {
+          let (mesh_changes__, mesh_lod_graph_input) = mesh_changes__.fork();
+          (mesh_changes__, Some(mesh_lod_graph_input.fork()))
+        } else {
+          (mesh_changes__, None)
+        };
+
         let mesh = use_attribute_lod_mesh_indirect_renderer(
           cx,
           &init_config.indirect_attribute_mesh_init,
@@ -282,6 +308,22 @@ impl Viewer3dRenderingCtx {
 
         scope.end(cx);
 
+        let (mesh_lod_graph, mesh_lod_graph_key_input) = match mesh_lod_graph_input {
+          Some((input, key_input)) => {
+            let renderer = cx.scope(|cx| {
+              use_mesh_lod_graph_indirect_renderer(
+                cx,
+                &init_config.indirect_mesh_lod_graph_config,
+                input,
+                node.clone(),
+                lod_camera_control.clone(),
+              )
+            });
+            (renderer, Some(key_input))
+          }
+          None => (None, None),
+        };
+
         if self.rtx_renderer_enabled {
           rtx_materials_support = cx.when_render(|| {
             Arc::new(vec![
@@ -307,10 +349,15 @@ impl Viewer3dRenderingCtx {
         let cell_mesh = use_cell_mesh_renderer(cx, self.using_host_driven_indirect_draw);
 
         let mesh = cx.when_render(|| {
-          Box::new(vec![
-            Box::new(mesh.unwrap()) as Box<dyn IndirectModelShapeRenderImpl>,
-            cell_mesh.unwrap(),
-          ]) as Box<dyn IndirectModelShapeRenderImpl>
+          let mut shapes = Vec::new();
+          // the mesh lod graph renderer must be selected before the attribute mesh renderer,
+          // because the converted meshes are also accepted by the attribute mesh renderer
+          if let Some(mesh_lod_graph) = mesh_lod_graph {
+            shapes.push(Box::new(mesh_lod_graph) as Box<dyn IndirectModelShapeRenderImpl>);
+          }
+          shapes.push(Box::new(mesh.unwrap()));
+          shapes.push(cell_mesh.unwrap());
+          Box::new(shapes) as Box<dyn IndirectModelShapeRenderImpl>
         });
 
         let model_buffer_merge = model_buffer_merge.restart(cx);
@@ -401,6 +448,19 @@ impl Viewer3dRenderingCtx {
 
             let cell_mesh = use_cell_mesh_group_key(cx);
 
+            let (mesh_key, cell_mesh) = if let Some(input) = mesh_lod_graph_key_input {
+              let (mesh_key, lod_graph_key) = use_mesh_lod_graph_group_key(
+                cx,
+                &init_config.indirect_mesh_lod_graph_config,
+                input,
+                mesh_key,
+              );
+              let foreign = cell_mesh.dual_query_select(lod_graph_key);
+              (mesh_key, foreign.dual_query_boxed())
+            } else {
+              (mesh_key, cell_mesh)
+            };
+
             let key_impl = GroupKeyForeignImpl {
               model: Some(impl_key),
               material: Some(occ_material),
@@ -437,7 +497,11 @@ impl Viewer3dRenderingCtx {
       }),
     };
 
-    let lighting = use_lighting(cx, &self.lighting, self.ndc, viewports);
+    let is_indirect_raster = matches!(
+      self.current_renderer_impl_ty,
+      RasterizationRenderBackendType::Indirect
+    );
+    let lighting = use_lighting(cx, &self.lighting, self.ndc, viewports, is_indirect_raster);
 
     let rtx_scene_renderer = if self.rtx_renderer_enabled {
       cx.scope(|cx| {
@@ -618,6 +682,8 @@ impl Viewer3dRenderingCtx {
     ctx: &mut FrameCtx,
     waker: &Waker,
   ) {
+    self.shader_debug_print.begin_frame(&mut ctx.encoder);
+
     let lighting_cx = self.lighting.prepare(
       light_preparer,
       ctx,
@@ -642,6 +708,10 @@ impl Viewer3dRenderingCtx {
         ctx.frame_size = viewport.render_pixel_size();
 
         let camera = renderer.camera.make_component(viewport.camera).unwrap().ubo;
+        let camera_transform = renderer
+          .camera_transforms
+          .access(&viewport.camera)
+          .unwrap_or_default();
         renderer.lod_camera_control.set(Some(LODCameraInfo {
           camera,
           view_resolution: create_uniform(
@@ -660,6 +730,11 @@ impl Viewer3dRenderingCtx {
             &ctx.gpu.device,
             "lod error threshold",
           ),
+          host: LODCameraHostInfo {
+            camera: camera_transform,
+            view_resolution: Vec2::new(viewport.viewport.z as u32, viewport.viewport.w as u32),
+            lod_error_threshold: self.init_config.attribute_mesh_lod_threshold_pixels,
+          },
         }));
         renderer.active_view_control.set(Some(viewport.id));
         // view dep view control is not set in shadow pass, we could but we didn't do it
@@ -692,6 +767,10 @@ impl Viewer3dRenderingCtx {
     }
 
     ctx.frame_size = size_backup;
+
+    self
+      .shader_debug_print
+      .end_frame(&mut ctx.encoder, &ctx.gpu.device);
   }
 }
 
@@ -1,5 +1,7 @@
+use futures::channel::oneshot::Sender;
 use rendiation_infinity_primitive::*;
 use rendiation_shader_library::plane::ShaderPlaneUniform;
+use rendiation_shader_library::sh::ShL2Rgb;
 use rendiation_texture_gpu_process::*;
 
 use super::{GridEffect, GridGround, outline::ViewerOutlineSourceProvider};
@@ -30,6 +32,15 @@ impl ViewerFrameRenderingExtension for () {
 
 pub const MSAA_SAMPLE_COUNT: u32 = 4;
 
+/// the baked radiance sh of each probe of the volume, see [LightProbeVolumeIrradianceLayout]
+pub struct LightProbeVolumeBakeResult {
+  pub volume: EntityHandle<LightProbeVolumeEntity>,
+  pub sh: Vec<ShL2Rgb>,
+}
+
+type LightProbeVolumeBakeFuture =
+  Pin<Box<dyn Future<Output = Option<Vec<LightProbeVolumeBakeResult>>>>>;
+
 pub struct Viewer3dViewportRenderingCtx {
   highlight: HighLighter,
   reproject: GPUReprojectInfo,
@@ -60,6 +71,9 @@ pub struct Viewer3dViewportRenderingCtx {
   oit: ViewerTransparentRenderer,
   pub rtx_ao: Option<SceneRayTracingAORenderer>,
   pub rtx_pt: Option<DeviceReferencePathTracingRenderer>,
+  rtx_light_probe_baker: Option<DeviceLightProbeBaker>,
+  light_probe_bake_requests: Vec<(LightProbeBakeConfig, Sender<LightProbeVolumeBakeFuture>)>,
+  request_reflection_probe_capture: bool,
 
   pub always_enable_caching_frame_for_direct_read: bool,
   pub(super) enable_on_demand_rendering: bool,
@@ -114,6 +128,9 @@ impl Viewer3dViewportRenderingCtx {
       oit: init_config.transparent_config.create_renderer(),
       rtx_ao: None,
       rtx_pt: None,
+      rtx_light_probe_baker: None,
+      light_probe_bake_requests: Vec::new(),
+      request_reflection_probe_capture: false,
       viewport_cache: None,
     }
   }
@@ -285,6 +302,87 @@ impl Viewer3dViewportRenderingCtx {
       .flatten()
   }
 
+  /// bake all light probe volumes in the scene of this viewport when it's rendered next time.
+  ///
+  /// the baking requires the ray tracing renderer to be enabled, resolved to None if the baking is
+  /// not possible or failed.
+  pub fn request_light_probe_bake(
+    &mut self,
+    config: LightProbeBakeConfig,
+  ) -> impl Future<Output = Option<Vec<LightProbeVolumeBakeResult>>> + use<> {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    self.light_probe_bake_requests.push((config, sender));
+    async { receiver.await.ok()?.await }
+  }
+
+  /// recapture all reflection probes in the scene of this viewport when it's rendered next time.
+  ///
+  /// the probes are captured automatically when they are changed, this is used to update the
+  /// probes when the other scene content is changed.
+  pub fn request_reflection_probe_capture(&mut self) {
+    self.request_reflection_probe_capture = true;
+  }
+
+  fn bake_requested_light_probes(
+    &mut self,
+    gpu: &GPU,
+    renderer: &ViewerRendererInstance,
+    lighting: &LightingRenderingCx,
+    scene: EntityHandle<SceneEntity>,
+  ) {
+    if self.light_probe_bake_requests.is_empty() {
+      return;
+    }
+
+    let Some((rtx_renderer, core)) = &renderer.rtx_system else {
+      log::error!("light probe baking requires the ray tracing renderer to be enabled");
+      self.light_probe_bake_requests.clear();
+      return;
+    };
+
+    let targets = lighting
+      .lighting
+      .light_probe_host
+      .read()
+      .bake_targets
+      .get(scene.raw_handle_ref())
+      .cloned()
+      .unwrap_or_default();
+    let positions = targets
+      .iter()
+      .flat_map(|target| target.positions.iter().copied())
+      .collect::<Vec<_>>();
+
+    let baker = self
+      .rtx_light_probe_baker
+      .get_or_insert_with(|| DeviceLightProbeBaker::new(core, gpu));
+
+    for (config, sender) in self.light_probe_bake_requests.drain(..) {
+      let result = baker.bake(
+        core.rtx_system.as_ref(),
+        &rtx_renderer.base.0,
+        scene,
+        &renderer.background,
+        &rtx_renderer.pt.0,
+        &positions,
+        config,
+      );
+      let targets = targets.clone();
+      let result = async move {
+        let mut sh = result.await?.into_iter();
+        let result = targets
+          .into_iter()
+          .map(|target| LightProbeVolumeBakeResult {
+            volume: target.volume,
+            sh: sh.by_ref().take(target.positions.len()).collect(),
+          })
+          .collect();
+        Some(result)
+      };
+      sender.send(Box::pin(result)).ok();
+    }
+  }
+
   /// read the last rendered frame result, return None if the viewer never rendered or sth wrong.
   ///
   /// the always_enable_caching_frame_for_direct_read must set true
@@ -380,6 +478,19 @@ impl Viewer3dViewportRenderingCtx {
     ctx.next_scope_index();
     let camera = viewport.camera;
 
+    self.bake_requested_light_probes(ctx.gpu, renderer, lighting, viewport.scene);
+
+    let force_capture = std::mem::take(&mut self.request_reflection_probe_capture);
+    use_capture_reflection_probes(
+      ctx,
+      &lighting.lighting.reflection_probe_host,
+      renderer,
+      lighting,
+      viewport.scene,
+      camera,
+      force_capture,
+    );
+
     let should_do_extra_copy = self.should_do_extra_copy(final_target, viewport);
     let render_target = if should_do_extra_copy {
       // we do extra copy in this case, so we have to make sure the copy source has correct usage
@@ -0,0 +1,838 @@
+use rendiation_device_parallel_compute::*;
+
+use crate::*;
+
+pub const CLUSTERED_LIGHT_KIND_POINT: u32 = 0;
+pub const CLUSTERED_LIGHT_KIND_SPOT: u32 = 1;
+pub const CLUSTERED_LIGHT_KIND_AREA: u32 = 2;
+
+/// the light index list entry packs the light kind in the highest two bits
+const CLUSTERED_LIGHT_KIND_SHIFT: u32 = 30;
+const CLUSTERED_LIGHT_INDEX_MASK: u32 = (1 << CLUSTERED_LIGHT_KIND_SHIFT) - 1;
+
+const CLUSTER_WORKGROUP_SIZE: u32 = 64;
+const MIN_CLUSTER_NEAR: f32 = 0.001;
+/// used when the projection has an infinite far plane
+const INFINITE_FAR_LAST_SLICE_SCALE: f32 = 10000.;
+
+/// the bit mask of the light kinds whose shadowed lights are not culled by the clustered lighting,
+/// they are shaded by the uniform light path with the shadow maps instead.
+pub fn clustered_shadowed_light_kinds(point_and_spot: bool, area: bool) -> u32 {
+  let mut kinds = 0;
+  if point_and_spot {
+    kinds |= 1 << CLUSTERED_LIGHT_KIND_POINT | 1 << CLUSTERED_LIGHT_KIND_SPOT;
+  }
+  if area {
+    kinds |= 1 << CLUSTERED_LIGHT_KIND_AREA;
+  }
+  kinds
+}
+
+pub fn pack_clustered_light(kind: u32, index: Node<u32>) -> Node<u32> {
+  val(kind << CLUSTERED_LIGHT_KIND_SHIFT) + index
+}
+
+/// return (kind, index)
+pub fn unpack_clustered_light(packed: Node<u32>) -> (Node<u32>, Node<u32>) {
+  (
+    packed >> val(CLUSTERED_LIGHT_KIND_SHIFT),
+    packed & val(CLUSTERED_LIGHT_INDEX_MASK),
+  )
+}
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Copy, Clone, ShaderStruct, Default, PartialEq)]
+pub struct ClusteredLightGridUniform {
+  pub grid_size: Vec3<u32>,
+  pub light_index_capacity: u32,
+  /// in pixel
+  pub view_size: Vec2<f32>,
+  pub near: f32,
+  /// the far bound of the last depth slice
+  pub last_slice_far: f32,
+  /// slice = log2(view_depth) * depth_slice_scale + depth_slice_bias
+  pub depth_slice_scale: f32,
+  pub depth_slice_bias: f32,
+  pub ndc_near_z: f32,
+  pub area_light_cutoff_illuminance: f32,
+  /// see [clustered_shadowed_light_kinds]
+  pub shadowed_light_kinds: u32,
+}
+
+impl ClusteredLightGridUniform {
+  pub fn new(
+    config: &ClusteredLightingConfig,
+    camera: &CameraTransform,
+    view_size: Size,
+    reversed_depth: bool,
+    shadowed_light_kinds: u32,
+  ) -> Self {
+    let ndc_near_z = if reversed_depth { 1. } else { 0. };
+    let view_depth = |ndc_z: f32| -(camera.projection_inv * Vec3::new(0., 0., ndc_z)).z;
+
+    let near = view_depth(ndc_near_z).max(MIN_CLUSTER_NEAR);
+    let projection_far = view_depth(1. - ndc_near_z);
+    let projection_far =
+      (projection_far.is_finite() && projection_far > near).then_some(projection_far);
+
+    let far = projection_far
+      .unwrap_or(f32::MAX)
+      .min(config.max_depth)
+      .max(near * 2.);
+    let last_slice_far = projection_far
+      .map(|f| f.max(far))
+      .unwrap_or(far * INFINITE_FAR_LAST_SLICE_SCALE);
+
+    let depth_slice_scale = config.grid_size.z as f32 / (far / near).log2();
+    let depth_slice_bias = -near.log2() * depth_slice_scale;
+
+    let (width, height) = view_size.into_f32();
+
+    Self {
+      grid_size: config.grid_size,
+      light_index_capacity: config.cluster_count() * config.light_index_capacity_per_cluster,
+      view_size: Vec2::new(width, height),
+      near,
+      last_slice_far,
+      depth_slice_scale,
+      depth_slice_bias,
+      ndc_near_z,
+      area_light_cutoff_illuminance: config.area_light_cutoff_illuminance,
+      shadowed_light_kinds,
+      ..Default::default()
+    }
+  }
+}
+
+/// the view space bounding box of a cluster
+#[repr(C)]
+#[std430_layout]
+#[derive(Copy, Clone, ShaderStruct, Default)]
+pub struct ClusterViewBounding {
+  pub min: Vec3<f32>,
+  pub max: Vec3<f32>,
+}
+
+#[derive(Clone)]
+pub struct ClusteredLightGrid {
+  pub camera: UniformBufferDataView<CameraGPUTransform>,
+  pub grid: UniformBufferDataView<ClusteredLightGridUniform>,
+  /// the light index list range of each cluster
+  pub ranges: StorageBufferReadonlyDataView<[GPURangeInfo]>,
+  pub indices: StorageBufferReadonlyDataView<[u32]>,
+}
+
+#[derive(Clone)]
+pub struct ClusteredLightGridInvocation {
+  pub camera: ShaderReadonlyPtrOf<CameraGPUTransform>,
+  pub grid: ShaderReadonlyPtrOf<ClusteredLightGridUniform>,
+  pub ranges: ShaderReadonlyPtrOf<[GPURangeInfo]>,
+  pub indices: ShaderReadonlyPtrOf<[u32]>,
+}
+
+impl ClusteredLightGrid {
+  pub fn build(&self, cx: &mut ShaderBindGroupBuilder) -> ClusteredLightGridInvocation {
+    ClusteredLightGridInvocation {
+      camera: cx.bind_by(&self.camera),
+      grid: cx.bind_by(&self.grid),
+      ranges: cx.bind_by(&self.ranges),
+      indices: cx.bind_by(&self.indices),
+    }
+  }
+
+  pub fn bind(&self, cx: &mut BindingBuilder) {
+    cx.bind(&self.camera);
+    cx.bind(&self.grid);
+    cx.bind(&self.ranges);
+    cx.bind(&self.indices);
+  }
+}
+
+impl ClusteredLightGridInvocation {
+  /// the render position is the world position relative to the camera position
+  pub fn cluster_index(
+    &self,
+    render_position: Node<Vec3<f32>>,
+    fragment_position: Node<Vec2<f32>>,
+  ) -> Node<u32> {
+    let grid = self.grid.load().expand();
+    let camera = self.camera.load().expand();
+
+    let view_position = camera.view_without_translation * (render_position, val(1.)).into();
+
+    let tile_count = grid.grid_size.xy();
+    let tile = (fragment_position / grid.view_size * tile_count.into_f32())
+      .floor()
+      .max(val(Vec2::zero()))
+      .into_u32()
+      .min(tile_count - val(Vec2::one()));
+
+    let slice = depth_slice(-view_position.z(), &grid);
+
+    tile.x() + (tile.y() + slice * tile_count.y()) * tile_count.x()
+  }
+}
+
+#[derive(Clone)]
+pub struct ClusteredLightSourceInvocation {
+  pub point_lights: ShaderReadonlyPtrOf<[PointLightStorage]>,
+  pub point_access: MultiAccessGPUInvocation,
+  pub spot_lights: ShaderReadonlyPtrOf<[SpotLightStorage]>,
+  pub spot_access: MultiAccessGPUInvocation,
+  pub area_lights: ShaderReadonlyPtrOf<[AreaLightStorage]>,
+  pub area_access: MultiAccessGPUInvocation,
+  pub point_shadowed: ShaderReadonlyPtrOf<[Bool]>,
+  pub spot_shadowed: ShaderReadonlyPtrOf<[Bool]>,
+  pub area_shadowed: ShaderReadonlyPtrOf<[Bool]>,
+}
+
+impl ClusteredLightSource {
+  pub fn build(&self, cx: &mut ShaderBindGroupBuilder) -> ClusteredLightSourceInvocation {
+    ClusteredLightSourceInvocation {
+      point_lights: cx.bind_by(&self.point_lights.0),
+      point_access: self.point_lights.1.build(cx),
+      spot_lights: cx.bind_by(&self.spot_lights.0),
+      spot_access: self.spot_lights.1.build(cx),
+      area_lights: cx.bind_by(&self.area_lights.0),
+      area_access: self.area_lights.1.build(cx),
+      point_shadowed: cx.bind_by(&self.point_shadowed),
+      spot_shadowed: cx.bind_by(&self.spot_shadowed),
+      area_shadowed: cx.bind_by(&self.area_shadowed),
+    }
+  }
+
+  pub fn bind(&self, cx: &mut BindingBuilder) {
+    cx.bind(&self.point_lights.0);
+    self.point_lights.1.bind(cx);
+    cx.bind(&self.spot_lights.0);
+    self.spot_lights.1.bind(cx);
+    cx.bind(&self.area_lights.0);
+    self.area_lights.1.bind(cx);
+    cx.bind(&self.point_shadowed);
+    cx.bind(&self.spot_shadowed);
+    cx.bind(&self.area_shadowed);
+  }
+}
+
+impl ClusteredLightSourceInvocation {
+  /// if the light is shaded by the clustered lighting, the shadowed lights of the given kinds are
+  /// excluded, see [clustered_shadowed_light_kinds]
+  pub fn is_clustered_light(
+    &self,
+    kind: u32,
+    index: Node<u32>,
+    enabled: Node<bool>,
+    shadowed_light_kinds: Node<u32>,
+  ) -> Node<bool> {
+    let shadowed = match kind {
+      CLUSTERED_LIGHT_KIND_POINT => &self.point_shadowed,
+      CLUSTERED_LIGHT_KIND_SPOT => &self.spot_shadowed,
+      _ => &self.area_shadowed,
+    };
+    let skip_shadowed = (shadowed_light_kinds & val(1 << kind)).not_equals(0);
+    enabled.and(
+      skip_shadowed
+        .and(shadowed.index(index).load().into_bool())
+        .not(),
+    )
+  }
+
+  /// visit every enabled light in the scene with its packed index, the view space center and
+  /// the radius of its influence bounding sphere.
+  pub fn for_each_light_sphere(
+    &self,
+    scene_id: Node<u32>,
+    camera: &ENode<CameraGPUTransform>,
+    area_light_cutoff_illuminance: Node<f32>,
+    shadowed_light_kinds: Node<u32>,
+    f: impl Fn(Node<u32>, Node<Vec3<f32>>, Node<f32>),
+  ) {
+    let camera_position = hpt_uniform_to_hpt(camera.world_position);
+    let view = camera.view_without_translation;
+    let to_view = |position: Node<HighPrecisionTranslationStorage>| {
+      let render_position = hpt_sub_hpt(hpt_storage_to_hpt(position), camera_position);
+      (view * (render_position, val(1.)).into()).xyz()
+    };
+
+    self
+      .point_access
+      .iter_refed_many_of(scene_id)
+      .for_each(|index, _| {
+        let light = self.point_lights.index(index).load().expand();
+        let enabled = light.enabled.into_bool();
+        let kind = CLUSTERED_LIGHT_KIND_POINT;
+        if_by(
+          self.is_clustered_light(kind, index, enabled, shadowed_light_kinds),
+          || {
+            let packed = pack_clustered_light(CLUSTERED_LIGHT_KIND_POINT, index);
+            f(packed, to_view(light.position), light.cutoff_distance);
+          },
+        );
+      });
+
+    self
+      .spot_access
+      .iter_refed_many_of(scene_id)
+      .for_each(|index, _| {
+        let light = self.spot_lights.index(index).load().expand();
+        let enabled = light.enabled.into_bool();
+        let kind = CLUSTERED_LIGHT_KIND_SPOT;
+        if_by(
+          self.is_clustered_light(kind, index, enabled, shadowed_light_kinds),
+          || {
+            let packed = pack_clustered_light(CLUSTERED_LIGHT_KIND_SPOT, index);
+            f(packed, to_view(light.position), light.cutoff_distance);
+          },
+        );
+      });
+
+    self
+      .area_access
+      .iter_refed_many_of(scene_id)
+      .for_each(|index, _| {
+        let light = self.area_lights.index(index).load().expand();
+        let half_x = (light.axis_x * light.half_size.x()).length();
+        let half_y = (light.axis_y * light.half_size.y()).length();
+        let area = half_x * half_y * val(4.);
+        // treat the light as a point light with the same power to estimate the falloff distance
+        let falloff = (light.intensity.max_channel() * area / area_light_cutoff_illuminance).sqrt();
+        let radius = (half_x * half_x + half_y * half_y).sqrt() + falloff;
+
+        let kind = CLUSTERED_LIGHT_KIND_AREA;
+        if_by(
+          self.is_clustered_light(kind, index, val(true), shadowed_light_kinds),
+          || {
+            let packed = pack_clustered_light(kind, index);
+            f(packed, to_view(light.position), radius);
+          },
+        );
+      });
+  }
+}
+
+#[derive(Clone)]
+pub struct ClusteredLightCullingInput {
+  pub source: ClusteredLightSource,
+  pub scene_id: UniformBufferDataView<Vec4<u32>>,
+  pub camera: UniformBufferDataView<CameraGPUTransform>,
+  pub grid: UniformBufferDataView<ClusteredLightGridUniform>,
+  pub cluster_count: u32,
+  pub light_index_capacity: u32,
+}
+
+/// build the view space bounding of each cluster, count the intersected lights of each cluster,
+/// then write the light index list by the prefix sum of the count.
+pub fn use_build_clustered_light_grid(
+  cx: &mut DeviceParallelComputeCtx,
+  input: &ClusteredLightCullingInput,
+) -> ClusteredLightGrid {
+  let bounding = ClusterBoundingCompute {
+    camera: input.camera.clone(),
+    grid: input.grid.clone(),
+    cluster_count: input.cluster_count,
+  }
+  .use_materialize_storage_buffer(cx)
+  .buffer;
+
+  let max_width = cx
+    .gpu
+    .info()
+    .supported_limits
+    .max_compute_invocations_per_workgroup;
+  let inclusive_offsets = ClusterLightCountCompute {
+    input: input.clone(),
+    bounding: bounding.clone(),
+  }
+  .use_segmented_prefix_scan_kogge_stone::<AdditionMonoid<u32>>(max_width, max_width, cx)
+  .buffer;
+
+  let ranges =
+    cx.use_rw_storage_buffer_array::<GPURangeInfo>(input.cluster_count as usize, "cluster ranges");
+  let indices = cx.use_rw_storage_buffer_array::<u32>(
+    input.light_index_capacity as usize,
+    "cluster light indices",
+  );
+
+  cx.record_pass(|pass, device| {
+    let hasher = shader_hasher_from_marker_ty!(ClusteredLightIndexWrite);
+    let pipeline = device.get_or_cache_create_compute_pipeline_by(hasher, |mut builder| {
+      builder.config_work_group_size(CLUSTER_WORKGROUP_SIZE);
+      let culling = ClusteredLightCullingInvocation::build(input, &bounding, &mut builder);
+      let inclusive_offsets = builder.bind_by(&inclusive_offsets);
+      let ranges = builder.bind_by(&ranges);
+      let indices = builder.bind_by(&indices);
+
+      let cluster = builder.global_invocation_id().x();
+      let grid = culling.grid.load().expand();
+      let cluster_count = grid.grid_size.x() * grid.grid_size.y() * grid.grid_size.z();
+
+      if_by(cluster.less_than(cluster_count), || {
+        let offset = cluster.equals(0).select_branched(
+          || val(0),
+          || inclusive_offsets.index(cluster - val(1)).load(),
+        );
+        let count = inclusive_offsets.index(cluster).load() - offset;
+        let capacity = grid.light_index_capacity;
+        let count = count.min(capacity - offset.min(capacity));
+
+        ranges.index(cluster).store(
+          ENode::<GPURangeInfo> {
+            start: offset,
+            len: count,
+          }
+          .construct(),
+        );
+
+        let written = val(0_u32).make_local_var();
+        culling.for_each_intersected_light(cluster, |packed| {
+          let current = written.load();
+          if_by(current.less_than(count), || {
+            indices.index(offset + current).store(packed);
+          });
+          written.store(current + val(1));
+        });
+      });
+
+      builder
+    });
+
+    let mut binding = BindingBuilder::default();
+    ClusteredLightCullingInvocation::bind(input, &bounding, &mut binding);
+    binding
+      .with_bind(&inclusive_offsets)
+      .with_bind(&ranges)
+      .with_bind(&indices)
+      .setup_compute_pass(pass, device, &pipeline);
+
+    pass.dispatch_workgroups(
+      compute_dispatch_size(input.cluster_count, CLUSTER_WORKGROUP_SIZE),
+      1,
+      1,
+    );
+  });
+
+  ClusteredLightGrid {
+    camera: input.camera.clone(),
+    grid: input.grid.clone(),
+    ranges: ranges.into_readonly_view(),
+    indices: indices.into_readonly_view(),
+  }
+}
+
+fn cluster_coord(cluster: Node<u32>, grid_size: Node<Vec3<u32>>) -> Node<Vec3<u32>> {
+  let layer = grid_size.x() * grid_size.y();
+  let x = cluster % grid_size.x();
+  let y = (cluster % layer) / grid_size.x();
+  let z = cluster / layer;
+  (x, y, z).into()
+}
+
+/// the exponential depth slice of the view depth(-z), clamped to the grid
+fn depth_slice(depth: Node<f32>, grid: &ENode<ClusteredLightGridUniform>) -> Node<u32> {
+  (depth.max(grid.near).log2() * grid.depth_slice_scale + grid.depth_slice_bias)
+    .floor()
+    .max(val(0.))
+    .into_u32()
+    .min(grid.grid_size.z() - val(1))
+}
+
+/// the near view depth of the depth slice
+fn slice_depth(slice: Node<u32>, grid: &ENode<ClusteredLightGridUniform>) -> Node<f32> {
+  ((slice.into_f32() - grid.depth_slice_bias) / grid.depth_slice_scale).exp2()
+}
+
+fn unproject(
+  projection_inv: Node<Mat4<f32>>,
+  ndc: Node<Vec2<f32>>,
+  z: Node<f32>,
+) -> Node<Vec3<f32>> {
+  let position = projection_inv * (ndc, z, val(1.)).into();
+  position.xyz() / position.w().splat()
+}
+
+#[derive(Clone)]
+struct ClusterBoundingCompute {
+  camera: UniformBufferDataView<CameraGPUTransform>,
+  grid: UniformBufferDataView<ClusteredLightGridUniform>,
+  cluster_count: u32,
+}
+
+impl ShaderHashProvider for ClusterBoundingCompute {
+  shader_hash_type_id! {}
+}
+
+impl ComputeComponentIO<ClusterViewBounding> for ClusterBoundingCompute {}
+
+impl ComputeComponent<Node<ClusterViewBounding>> for ClusterBoundingCompute {
+  fn clone_boxed(&self) -> Box<dyn ComputeComponent<Node<ClusterViewBounding>>> {
+    Box::new(self.clone())
+  }
+
+  fn work_size(&self) -> Option<u32> {
+    Some(self.cluster_count)
+  }
+
+  fn result_size(&self) -> u32 {
+    self.cluster_count
+  }
+
+  fn requested_workgroup_size(&self) -> Option<u32> {
+    Some(CLUSTER_WORKGROUP_SIZE)
+  }
+
+  fn build_shader(
+    &self,
+    builder: &mut ShaderComputePipelineBuilder,
+  ) -> Box<dyn DeviceInvocation<Node<ClusterViewBounding>>> {
+    Box::new(ClusterBoundingInvocation {
+      camera: builder.bind_by(&self.camera),
+      grid: builder.bind_by(&self.grid),
+    })
+  }
+
+  fn bind_input(&self, builder: &mut BindingBuilder) {
+    builder.bind(&self.camera);
+    builder.bind(&self.grid);
+  }
+}
+
+struct ClusterBoundingInvocation {
+  camera: ShaderReadonlyPtrOf<CameraGPUTransform>,
+  grid: ShaderReadonlyPtrOf<ClusteredLightGridUniform>,
+}
+
+impl DeviceInvocation<Node<ClusterViewBounding>> for ClusterBoundingInvocation {
+  fn invocation_logic(
+    &self,
+    logic_global_id: Node<Vec3<u32>>,
+  ) -> (Node<ClusterViewBounding>, Node<bool>) {
+    let grid = self.grid.load().expand();
+    let projection_inv = self.camera.load().expand().projection_inv;
+
+    let cluster_count = grid.grid_size.x() * grid.grid_size.y() * grid.grid_size.z();
+    let cluster = logic_global_id.x();
+    let in_bound = cluster.less_than(cluster_count);
+    let coord = cluster_coord(cluster.min(cluster_count - val(1)), grid.grid_size);
+
+    let tile_count = grid.grid_size.xy().into_f32();
+    let uv_min = coord.xy().into_f32() / tile_count;
+    let uv_max = (coord.xy() + val(Vec2::one())).into_f32() / tile_count;
+
+    let depth_near = slice_depth(coord.z(), &grid);
+    let next_slice = coord.z() + val(1);
+    let depth_far = next_slice
+      .equals(grid.grid_size.z())
+      .select(grid.last_slice_far, slice_depth(next_slice, &grid));
+
+    let min = val(Vec3::splat(f32::MAX)).make_local_var();
+    let max = val(Vec3::splat(f32::MIN)).make_local_var();
+
+    let corners = [
+      uv_min,
+      (uv_max.x(), uv_min.y()).into(),
+      (uv_min.x(), uv_max.y()).into(),
+      uv_max,
+    ];
+    for uv in corners {
+      // the tile y is in framebuffer order, which is flipped with the ndc y
+      let ndc: Node<Vec2<f32>> = (uv.x() * val(2.) - val(1.), val(1.) - uv.y() * val(2.)).into();
+      let a = unproject(projection_inv, ndc, grid.ndc_near_z);
+      let b = unproject(projection_inv, ndc, val(0.5));
+
+      // a and b are on the same view ray, find the point at the given view depth(-z)
+      for depth in [depth_near, depth_far] {
+        let t = (depth + a.z()) / (a.z() - b.z());
+        let position = a + (b - a) * t.splat::<Vec3<f32>>();
+        min.store(min.load().min(position));
+        max.store(max.load().max(position));
+      }
+    }
+
+    let bounding = ENode::<ClusterViewBounding> {
+      min: min.load(),
+      max: max.load(),
+    }
+    .construct();
+
+    (bounding, in_bound)
+  }
+
+  fn invocation_size(&self) -> Node<Vec3<u32>> {
+    let grid_size = self.grid.load().expand().grid_size;
+    (
+      grid_size.x() * grid_size.y() * grid_size.z(),
+      val(0),
+      val(0),
+    )
+      .into()
+  }
+}
+
+struct ClusteredLightCullingInvocation {
+  source: ClusteredLightSourceInvocation,
+  scene_id: ShaderReadonlyPtrOf<Vec4<u32>>,
+  camera: ShaderReadonlyPtrOf<CameraGPUTransform>,
+  grid: ShaderReadonlyPtrOf<ClusteredLightGridUniform>,
+  bounding: ShaderReadonlyPtrOf<[ClusterViewBounding]>,
+}
+
+impl ClusteredLightCullingInvocation {
+  fn build(
+    input: &ClusteredLightCullingInput,
+    bounding: &StorageBufferReadonlyDataView<[ClusterViewBounding]>,
+    builder: &mut ShaderComputePipelineBuilder,
+  ) -> Self {
+    Self {
+      source: input.source.build(builder.bindgroups()),
+      scene_id: builder.bind_by(&input.scene_id),
+      camera: builder.bind_by(&input.camera),
+      grid: builder.bind_by(&input.grid),
+      bounding: builder.bind_by(bounding),
+    }
+  }
+
+  fn bind(
+    input: &ClusteredLightCullingInput,
+    bounding: &StorageBufferReadonlyDataView<[ClusterViewBounding]>,
+    builder: &mut BindingBuilder,
+  ) {
+    input.source.bind(builder);
+    builder.bind(&input.scene_id);
+    builder.bind(&input.camera);
+    builder.bind(&input.grid);
+    builder.bind(bounding);
+  }
+
+  fn for_each_intersected_light(&self, cluster: Node<u32>, f: impl Fn(Node<u32>)) {
+    let bounding = self.bounding.index(cluster).load().expand();
+    let camera = self.camera.load().expand();
+    let grid = self.grid.load().expand();
+    let cutoff = grid.area_light_cutoff_illuminance;
+    let shadowed_light_kinds = grid.shadowed_light_kinds;
+    let scene_id = self.scene_id.load().x();
+
+    self.source.for_each_light_sphere(
+      scene_id,
+      &camera,
+      cutoff,
+      shadowed_light_kinds,
+      |packed, center, radius| {
+        let closest = center.max(bounding.min).min(bounding.max);
+        let offset = closest - center;
+        if_by(offset.dot(offset).less_equal_than(radius * radius), || {
+          f(packed)
+        });
+      },
+    );
+  }
+}
+
+#[derive(Clone)]
+struct ClusterLightCountCompute {
+  input: ClusteredLightCullingInput,
+  bounding: StorageBufferReadonlyDataView<[ClusterViewBounding]>,
+}
+
+impl ShaderHashProvider for ClusterLightCountCompute {
+  shader_hash_type_id! {}
+}
+
+impl ComputeComponentIO<u32> for ClusterLightCountCompute {}
+
+impl ComputeComponent<Node<u32>> for ClusterLightCountCompute {
+  fn clone_boxed(&self) -> Box<dyn ComputeComponent<Node<u32>>> {
+    Box::new(self.clone())
+  }
+
+  fn work_size(&self) -> Option<u32> {
+    Some(self.input.cluster_count)
+  }
+
+  fn result_size(&self) -> u32 {
+    self.input.cluster_count
+  }
+
+  fn requested_workgroup_size(&self) -> Option<u32> {
+    Some(CLUSTER_WORKGROUP_SIZE)
+  }
+
+  fn build_shader(
+    &self,
+    builder: &mut ShaderComputePipelineBuilder,
+  ) -> Box<dyn DeviceInvocation<Node<u32>>> {
+    Box::new(ClusterLightCountInvocation {
+      culling: ClusteredLightCullingInvocation::build(&self.input, &self.bounding, builder),
+    })
+  }
+
+  fn bind_input(&self, builder: &mut BindingBuilder) {
+    ClusteredLightCullingInvocation::bind(&self.input, &self.bounding, builder);
+  }
+}
+
+struct ClusterLightCountInvocation {
+  culling: ClusteredLightCullingInvocation,
+}
+
+impl DeviceInvocation<Node<u32>> for ClusterLightCountInvocation {
+  fn invocation_logic(&self, logic_global_id: Node<Vec3<u32>>) -> (Node<u32>, Node<bool>) {
+    let cluster = logic_global_id.x();
+    let in_bound = cluster.less_than(self.cluster_count());
+
+    let count = in_bound.select_branched(
+      || {
+        let count = val(0_u32).make_local_var();
+        self
+          .culling
+          .for_each_intersected_light(cluster, |_| count.store(count.load() + val(1)));
+        count.load()
+      },
+      || val(0),
+    );
+
+    (count, in_bound)
+  }
+
+  fn invocation_size(&self) -> Node<Vec3<u32>> {
+    (self.cluster_count(), val(0), val(0)).into()
+  }
+}
+
+impl ClusterLightCountInvocation {
+  fn cluster_count(&self) -> Node<u32> {
+    let grid_size = self.culling.grid.load().expand().grid_size;
+    grid_size.x() * grid_size.y() * grid_size.z()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use rendiation_shader_backend_cpu::*;
+
+  use super::*;
+
+  fn camera(reversed_depth: bool, far: f32) -> CameraTransform {
+    let projection = PerspectiveProjection {
+      near: 0.1,
+      far,
+      fov: Deg::by(60.),
+      aspect: 16. / 9.,
+    };
+    let ndc = ViewerNDC {
+      enable_reverse_z: reversed_depth,
+    };
+    CameraTransform::new(projection.compute_projection_mat(&ndc), Mat4::identity())
+  }
+
+  fn view_size() -> Size {
+    Size::from_u32_pair_min_one((1920, 1080))
+  }
+
+  fn assert_relative_eq(a: f32, b: f32) {
+    assert!((a - b).abs() <= b.abs() * 1e-3, "{a} != {b}");
+  }
+
+  #[test]
+  fn test_grid_uniform() {
+    let config = ClusteredLightingConfig::default();
+    let kinds = clustered_shadowed_light_kinds(true, false);
+
+    for reversed_depth in [false, true] {
+      let grid = ClusteredLightGridUniform::new(
+        &config,
+        &camera(reversed_depth, 100.),
+        view_size(),
+        reversed_depth,
+        kinds,
+      );
+      assert_eq!(grid.grid_size, config.grid_size);
+      assert_eq!(
+        grid.light_index_capacity,
+        16 * 9 * 24 * config.light_index_capacity_per_cluster
+      );
+      assert_eq!(grid.view_size, Vec2::new(1920., 1080.));
+      assert_eq!(grid.ndc_near_z, if reversed_depth { 1. } else { 0. });
+      assert_eq!(grid.shadowed_light_kinds, kinds);
+      assert_relative_eq(grid.near, 0.1);
+      // the projection far is nearer than the config max depth
+      assert_relative_eq(grid.last_slice_far, 100.);
+      // the near plane is mapped to slice 0, and the far plane to the slice count
+      assert!((grid.near.log2() * grid.depth_slice_scale + grid.depth_slice_bias).abs() < 1e-3);
+      let far_slice = 100_f32.log2() * grid.depth_slice_scale + grid.depth_slice_bias;
+      assert!((far_slice - 24.).abs() < 1e-2);
+    }
+
+    // the slicing stops at the max depth, the last slice covers the rest of the view
+    let grid =
+      ClusteredLightGridUniform::new(&config, &camera(false, 5000.), view_size(), false, 0);
+    let max_depth_slice = 1000_f32.log2() * grid.depth_slice_scale + grid.depth_slice_bias;
+    assert!((max_depth_slice - 24.).abs() < 1e-2);
+    // the far depth is unprojected in f32, which is imprecise with this near far ratio
+    assert!((grid.last_slice_far - 5000.).abs() < 5000. * 1e-2);
+  }
+
+  #[test]
+  fn test_shadowed_light_kinds() {
+    assert_eq!(clustered_shadowed_light_kinds(false, false), 0);
+    let kinds = clustered_shadowed_light_kinds(true, true);
+    for kind in [
+      CLUSTERED_LIGHT_KIND_POINT,
+      CLUSTERED_LIGHT_KIND_SPOT,
+      CLUSTERED_LIGHT_KIND_AREA,
+    ] {
+      assert_ne!(kinds & (1 << kind), 0);
+    }
+    let kinds = clustered_shadowed_light_kinds(false, true);
+    assert_eq!(kinds, 1 << CLUSTERED_LIGHT_KIND_AREA);
+  }
+
+  #[test]
+  fn test_depth_slice() {
+    let config = ClusteredLightingConfig::default();
+    let grid = ClusteredLightGridUniform::new(&config, &camera(false, 100.), view_size(), false, 0);
+    let near = grid.near;
+    let slice_count = config.grid_size.z;
+    // the depth at the given fraction of the exponential slicing
+    let depth_at = |slice: f32| near * (100. / near).powf(slice / slice_count as f32);
+
+    // the center of each slice, then out of range depths
+    let mut depths: Vec<f32> = (0..slice_count).map(|i| depth_at(i as f32 + 0.5)).collect();
+    depths.extend_from_slice(&[0.001, 100.5, 1e6]);
+
+    let uniform = CpuUniformBufferDataView::new(&grid);
+    let input = CpuStorageBufferReadonlyDataView::<[f32]>::new(depths.as_slice());
+    let slices = CpuStorageBufferDataView::<[u32]>::new(vec![0; depths.len()].as_slice());
+    let slice_near = CpuStorageBufferDataView::<[f32]>::new(vec![0.; depths.len()].as_slice());
+
+    let mut cx = cpu_compute_shader_builder().with_config_work_group_size(depths.len() as u32);
+    let grid_node = cx.bind_by(&uniform).load().expand();
+    let input_node = cx.bind_by(&input);
+    let slices_node = cx.bind_by(&slices);
+    let slice_near_node = cx.bind_by(&slice_near);
+    let id = cx.global_invocation_id().x();
+    let slice = depth_slice(input_node.index(id).load(), &grid_node);
+    slices_node.index(id).store(slice);
+    slice_near_node
+      .index(id)
+      .store(slice_depth(slice, &grid_node));
+
+    let module = cx.create_cpu_module().unwrap();
+    let bindings = CpuShaderBindingBuilder::default()
+      .with_bind(&uniform)
+      .with_bind(&input)
+      .with_bind(&slices)
+      .with_bind(&slice_near);
+    module.dispatch(&bindings, (1, 1, 1)).unwrap();
+
+    let slices = slices.read().into_vec();
+    let slice_near = slice_near.read().into_vec();
+    let n = slice_count as usize;
+    for i in 0..n {
+      assert_eq!(slices[i], i as u32);
+      assert_relative_eq(slice_near[i], depth_at(i as f32));
+    }
+    // clamped to the first and the last slice
+    assert_eq!(&slices[n..], &[0, slice_count - 1, slice_count - 1]);
+  }
+}
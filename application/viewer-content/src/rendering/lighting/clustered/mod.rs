@@ -0,0 +1,185 @@
+mod culling;
+mod shading;
+
+pub use culling::*;
+use rendiation_device_parallel_compute::*;
+pub use shading::*;
+
+use crate::*;
+
+/// Clustered lighting bins the point, spot and area lights into a view space froxel grid,
+/// so each fragment only iterates the lights that may affect it.
+///
+/// The clustered path has no shadow. If the shadow is enabled in the light system, the lights
+/// with their shadow map enabled are excluded from the clustering, and shaded by the uniform
+/// light path with the shadow maps as the none clustered mode does. So the clustering only
+/// speeds up the lights without shadow, disable the light's shadow to make it clustered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClusteredLightingConfig {
+  /// x and y are the screen tile count, z is the exponential depth slice count.
+  pub grid_size: Vec3<u32>,
+  /// the light index list is shared by all clusters, its capacity is cluster count * this value.
+  /// the lights that can not fit in the list are dropped.
+  pub light_index_capacity_per_cluster: u32,
+  /// the depth slicing stops at this distance, the last slice covers the rest of the view.
+  pub max_depth: f32,
+  /// area lights have no cutoff distance, the influence range is estimated by the distance
+  /// where the illuminance drops below this value.
+  pub area_light_cutoff_illuminance: f32,
+}
+
+impl Default for ClusteredLightingConfig {
+  fn default() -> Self {
+    Self {
+      grid_size: Vec3::new(16, 9, 24),
+      light_index_capacity_per_cluster: 32,
+      max_depth: 1000.,
+      area_light_cutoff_illuminance: 0.01,
+    }
+  }
+}
+
+impl ClusteredLightingConfig {
+  pub fn cluster_count(&self) -> u32 {
+    self.grid_size.x * self.grid_size.y * self.grid_size.z
+  }
+
+  pub fn egui(&mut self, ui: &mut UiWithChangeInfo) {
+    ui.add(egui::Slider::new(&mut self.grid_size.x, 1..=32).text("cluster grid x"));
+    ui.add(egui::Slider::new(&mut self.grid_size.y, 1..=32).text("cluster grid y"));
+    ui.add(egui::Slider::new(&mut self.grid_size.z, 1..=64).text("cluster depth slices"));
+    ui.add(
+      egui::Slider::new(&mut self.light_index_capacity_per_cluster, 1..=256)
+        .text("light index capacity per cluster"),
+    );
+    ui.add(
+      egui::Slider::new(&mut self.max_depth, 10.0..=10000.0)
+        .logarithmic(true)
+        .text("cluster max depth"),
+    );
+    ui.add(
+      egui::Slider::new(&mut self.area_light_cutoff_illuminance, 0.0001..=1.0)
+        .logarithmic(true)
+        .text("area light cutoff illuminance"),
+    );
+  }
+}
+
+#[derive(Clone)]
+pub struct ClusteredLightSource {
+  pub point_lights: LightGPUStorage<PointLightStorage>,
+  pub spot_lights: LightGPUStorage<SpotLightStorage>,
+  pub area_lights: AreaLightGPUStorage,
+  /// if the light's shadow map is enabled, indexed by the light entity
+  pub point_shadowed: AbstractReadonlyStorageBuffer<[Bool]>,
+  pub spot_shadowed: AbstractReadonlyStorageBuffer<[Bool]>,
+  pub area_shadowed: AbstractReadonlyStorageBuffer<[Bool]>,
+}
+
+pub fn use_clustered_light_source(cx: &mut QueryGPUHookCx) -> Option<ClusteredLightSource> {
+  let point_lights = use_point_light_storage(cx);
+  let spot_lights = use_spot_light_storage(cx);
+  let area_lights = use_area_light_storage(cx);
+
+  let point_shadowed = use_light_shadowed_flags::<PointLightBasicShadowInfo>(cx, "point");
+  let spot_shadowed = use_light_shadowed_flags::<SpotLightBasicShadowInfo>(cx, "spot");
+  let area_shadowed = use_light_shadowed_flags::<AreaLightBasicShadowInfo>(cx, "area");
+
+  cx.when_render(|| ClusteredLightSource {
+    point_lights: point_lights.unwrap(),
+    spot_lights: spot_lights.unwrap(),
+    area_lights: area_lights.unwrap(),
+    point_shadowed: point_shadowed.unwrap(),
+    spot_shadowed: spot_shadowed.unwrap(),
+    area_shadowed: area_shadowed.unwrap(),
+  })
+}
+
+fn use_light_shadowed_flags<T: BasicShadowMapConfigurable>(
+  cx: &mut QueryGPUHookCx,
+  light_kind: &str,
+) -> Option<AbstractReadonlyStorageBuffer<[Bool]>> {
+  let label = format!("clustered {light_kind} light shadowed flags");
+  let (cx, flags) = cx.use_storage_buffer(&label, 128, u32::MAX);
+
+  cx.use_changes::<BasicShadowMapEnabledOf<T>>()
+    .map_changes(Bool::from)
+    .update_storage_array(cx, flags, 0);
+
+  flags.use_max_item_count_by_db_entity::<T::Entity>(cx);
+  flags.use_update(cx);
+
+  cx.when_render(|| flags.get_gpu_buffer())
+}
+
+pub type ClusteredLightGridKey = (EntityHandle<SceneEntity>, EntityHandle<SceneCameraEntity>);
+
+#[derive(Clone)]
+pub struct ClusteredLightingProvider {
+  pub source: ClusteredLightSource,
+  pub ltc_1: GPU2DTextureView,
+  pub ltc_2: GPU2DTextureView,
+  pub config: ClusteredLightingConfig,
+  /// see [clustered_shadowed_light_kinds]
+  pub shadowed_light_kinds: u32,
+  pub debug_light_count: bool,
+  /// the grids are rebuilt every frame before the scene content is drawn. if the grid of the
+  /// given scene and camera does not exist, the lighting falls back to iterate all the lights.
+  pub grids: Arc<RwLock<FastHashMap<ClusteredLightGridKey, ClusteredLightGrid>>>,
+}
+
+impl ClusteredLightingProvider {
+  #[allow(clippy::too_many_arguments)]
+  pub fn use_update_grid(
+    &self,
+    cx: &mut DeviceParallelComputeCtx,
+    scene: EntityHandle<SceneEntity>,
+    scene_id: &UniformBufferDataView<Vec4<u32>>,
+    camera: EntityHandle<SceneCameraEntity>,
+    camera_gpu: &CameraGPU,
+    camera_transform: &CameraTransform,
+    view_size: Size,
+    reversed_depth: bool,
+  ) {
+    let grid = ClusteredLightGridUniform::new(
+      &self.config,
+      camera_transform,
+      view_size,
+      reversed_depth,
+      self.shadowed_light_kinds,
+    );
+    let grid = create_uniform(grid, &cx.gpu.device, "clustered light grid");
+
+    let grid = use_build_clustered_light_grid(
+      cx,
+      &ClusteredLightCullingInput {
+        source: self.source.clone(),
+        scene_id: scene_id.clone(),
+        camera: camera_gpu.ubo.clone(),
+        grid,
+        cluster_count: self.config.cluster_count(),
+        light_index_capacity: self.config.cluster_count()
+          * self.config.light_index_capacity_per_cluster,
+      },
+    );
+
+    self.grids.write().insert((scene, camera), grid);
+  }
+}
+
+impl LightSystemSceneProvider for ClusteredLightingProvider {
+  fn get_scene_lighting(
+    &self,
+    scene: EntityHandle<SceneEntity>,
+    camera: EntityHandle<SceneCameraEntity>,
+  ) -> Option<Box<dyn LightingComputeComponent>> {
+    Some(Box::new(ClusteredLightingComponent {
+      source: self.source.clone(),
+      ltc_1: self.ltc_1.clone(),
+      ltc_2: self.ltc_2.clone(),
+      grid: self.grids.read().get(&(scene, camera)).cloned(),
+      shadowed_light_kinds: self.shadowed_light_kinds,
+      debug_light_count: self.debug_light_count,
+    }))
+  }
+}
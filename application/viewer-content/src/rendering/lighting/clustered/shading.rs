@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+
+use rendiation_lighting_ltc::*;
+use rendiation_lighting_punctual::*;
+
+use crate::*;
+
+thread_local! {
+  /// the per fragment light count of the clustered lighting, consumed by the channel debugger
+  pub static CLUSTERED_LIGHT_COUNT_DEBUG: RefCell<Option<Node<u32>>> = const { RefCell::new(None) };
+}
+
+pub struct ClusteredLightingComponent {
+  pub source: ClusteredLightSource,
+  pub ltc_1: GPU2DTextureView,
+  pub ltc_2: GPU2DTextureView,
+  pub grid: Option<ClusteredLightGrid>,
+  /// see [clustered_shadowed_light_kinds]
+  pub shadowed_light_kinds: u32,
+  pub debug_light_count: bool,
+}
+
+impl ShaderHashProvider for ClusteredLightingComponent {
+  shader_hash_type_id! {}
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    hasher.hash(self.grid.is_some());
+    hasher.hash(self.shadowed_light_kinds);
+    hasher.hash(self.debug_light_count);
+  }
+}
+
+impl LightingComputeComponent for ClusteredLightingComponent {
+  fn build_light_compute_invocation(
+    &self,
+    binding: &mut ShaderBindGroupBuilder,
+    scene_id: Node<u32>,
+  ) -> Box<dyn LightingComputeInvocation> {
+    Box::new(ClusteredLightingInvocation {
+      scene_id,
+      source: self.source.build(binding),
+      lut: LTCxLUTxInvocation {
+        ltc_1: binding.bind_by(&self.ltc_1),
+        ltc_2: binding.bind_by(&self.ltc_2),
+        sampler: binding.bind_by(&ImmediateGPUSamplerViewBind),
+      },
+      grid: self.grid.as_ref().map(|grid| grid.build(binding)),
+      shadowed_light_kinds: self.shadowed_light_kinds,
+      debug_light_count: self.debug_light_count,
+    })
+  }
+
+  fn setup_pass(&self, ctx: &mut BindingBuilder) {
+    self.source.bind(ctx);
+    ctx.bind(&self.ltc_1);
+    ctx.bind(&self.ltc_2);
+    ctx.bind_immediate_sampler(&TextureSampler::default().with_double_linear().into_gpu());
+    if let Some(grid) = &self.grid {
+      grid.bind(ctx);
+    }
+  }
+}
+
+struct ClusteredLightingInvocation {
+  scene_id: Node<u32>,
+  source: ClusteredLightSourceInvocation,
+  lut: LTCxLUTxInvocation,
+  grid: Option<ClusteredLightGridInvocation>,
+  shadowed_light_kinds: u32,
+  debug_light_count: bool,
+}
+
+impl ClusteredLightingInvocation {
+  fn compute_point_light(
+    &self,
+    index: Node<u32>,
+    shading: &dyn LightableSurfaceShading,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> ENode<ShaderLightingResult> {
+    let light = self.source.point_lights.index(index).load().expand();
+    ENode::<PointLightShaderInfo> {
+      luminance_intensity: light.luminance_intensity,
+      position: hpt_storage_to_hpt(light.position),
+      cutoff_distance: light.cutoff_distance,
+    }
+    .construct()
+    .compute_lights(shading, geom_ctx)
+  }
+
+  fn compute_spot_light(
+    &self,
+    index: Node<u32>,
+    shading: &dyn LightableSurfaceShading,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> ENode<ShaderLightingResult> {
+    let light = self.source.spot_lights.index(index).load().expand();
+    ENode::<SpotLightShaderInfo> {
+      luminance_intensity: light.luminance_intensity,
+      position: hpt_storage_to_hpt(light.position),
+      direction: light.direction,
+      cutoff_distance: light.cutoff_distance,
+      half_cone_cos: light.half_cone_cos,
+      half_penumbra_cos: light.half_penumbra_cos,
+    }
+    .construct()
+    .compute_lights(shading, geom_ctx)
+  }
+
+  fn compute_area_light(
+    &self,
+    index: Node<u32>,
+    shading: &dyn LightableSurfaceShading,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> ENode<ShaderLightingResult> {
+    let light = self.source.area_lights.index(index).load().expand();
+    LTCRectLightingCompute {
+      light: area_light_storage_to_ltc_light(light),
+      lut: self.lut,
+    }
+    .compute_lights(shading, geom_ctx)
+  }
+}
+
+impl LightingComputeInvocation for ClusteredLightingInvocation {
+  fn compute_lights(
+    &self,
+    shading: &dyn LightableSurfaceShading,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> ENode<ShaderLightingResult> {
+    let specular = val(Vec3::<f32>::splat(0.)).make_local_var();
+    let diffuse = val(Vec3::<f32>::splat(0.)).make_local_var();
+    let accumulate = |r: ENode<ShaderLightingResult>| {
+      specular.store(specular.load() + r.specular);
+      diffuse.store(diffuse.load() + r.diffuse);
+    };
+
+    let light_count = if let Some(grid) = &self.grid {
+      let cluster = grid.cluster_index(geom_ctx.position, geom_ctx.fragment_position.xy());
+      let range = grid.ranges.index(cluster).load().expand();
+
+      range.len.into_shader_iter().for_each(|i, _| {
+        let packed = grid.indices.index(range.start + i).load();
+        let (kind, index) = unpack_clustered_light(packed);
+
+        if_by(kind.equals(CLUSTERED_LIGHT_KIND_POINT), || {
+          accumulate(self.compute_point_light(index, shading, geom_ctx))
+        })
+        .else_if(kind.equals(CLUSTERED_LIGHT_KIND_SPOT), || {
+          accumulate(self.compute_spot_light(index, shading, geom_ctx))
+        })
+        .else_by(|| accumulate(self.compute_area_light(index, shading, geom_ctx)));
+      });
+
+      range.len
+    } else {
+      // the grid is not built for this view, iterate all lights in the scene instead
+      let count = val(0_u32).make_local_var();
+      let source = &self.source;
+      let kinds = val(self.shadowed_light_kinds);
+
+      source
+        .point_access
+        .iter_refed_many_of(self.scene_id)
+        .for_each(|index, _| {
+          let enabled = source.point_lights.index(index).load().expand().enabled;
+          let kind = CLUSTERED_LIGHT_KIND_POINT;
+          let clustered = source.is_clustered_light(kind, index, enabled.into_bool(), kinds);
+          if_by(clustered, || {
+            accumulate(self.compute_point_light(index, shading, geom_ctx));
+            count.store(count.load() + val(1));
+          });
+        });
+
+      source
+        .spot_access
+        .iter_refed_many_of(self.scene_id)
+        .for_each(|index, _| {
+          let enabled = source.spot_lights.index(index).load().expand().enabled;
+          let kind = CLUSTERED_LIGHT_KIND_SPOT;
+          let clustered = source.is_clustered_light(kind, index, enabled.into_bool(), kinds);
+          if_by(clustered, || {
+            accumulate(self.compute_spot_light(index, shading, geom_ctx));
+            count.store(count.load() + val(1));
+          });
+        });
+
+      source
+        .area_access
+        .iter_refed_many_of(self.scene_id)
+        .for_each(|index, _| {
+          let kind = CLUSTERED_LIGHT_KIND_AREA;
+          let clustered = source.is_clustered_light(kind, index, val(true), kinds);
+          if_by(clustered, || {
+            accumulate(self.compute_area_light(index, shading, geom_ctx));
+            count.store(count.load() + val(1));
+          });
+        });
+
+      count.load()
+    };
+
+    if self.debug_light_count {
+      CLUSTERED_LIGHT_COUNT_DEBUG.with_borrow_mut(|v| *v = Some(light_count));
+    }
+
+    ENode::<ShaderLightingResult> {
+      specular: specular.load(),
+      diffuse: diffuse.load(),
+    }
+  }
+}
@@ -13,6 +13,7 @@ impl ScreenChannelDebugger {
       .push_debug_channel(ColorChannel)
       .push_debug_channel(RoughnessChannel)
       .push_debug_channel(MetallicChannel)
+      .push_debug_channel(ClusteredLightCountHeatmap)
   }
 }
 
@@ -115,3 +116,25 @@ impl ChannelVisualize for MetallicChannel {
     (value, val(1.)).into()
   }
 }
+
+/// visualize the per fragment light count of the clustered lighting, from blue(one light) to
+/// red(the max count or more), black if no light affects the fragment.
+pub struct ClusteredLightCountHeatmap;
+
+const CLUSTERED_LIGHT_HEATMAP_MAX_COUNT: f32 = 32.;
+
+impl ChannelVisualize for ClusteredLightCountHeatmap {
+  fn to_screen(&self, _: &mut ShaderFragmentBuilderView) -> Node<Vec4<f32>> {
+    let count = CLUSTERED_LIGHT_COUNT_DEBUG
+      .with_borrow_mut(|v| v.take())
+      .unwrap_or_else(|| val(0));
+
+    let t = (count.into_f32() / val(CLUSTERED_LIGHT_HEATMAP_MAX_COUNT)).min(val(1.));
+    let low = (t * val(2.)).min(val(1.));
+    let high = (t * val(2.) - val(1.)).max(val(0.));
+    let heat: Node<Vec3<f32>> = (high, low - high, val(1.) - low).into();
+    let heat = count.equals(val(0)).select(val(Vec3::zero()), heat);
+
+    (heat, val(1.)).into()
+  }
+}
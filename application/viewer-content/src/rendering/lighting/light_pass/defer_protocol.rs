@@ -10,6 +10,8 @@ pub struct FrameGeneralMaterialBuffer {
   pub channel_a: RenderTargetView,
   pub channel_b: RenderTargetView,
   pub channel_c: RenderTargetView,
+  /// the packed unorm values, for example the clearcoat and sheen layer of the pbr surface
+  pub channel_d: RenderTargetView,
 }
 
 impl FrameGeneralMaterialBuffer {
@@ -25,6 +27,7 @@ impl FrameGeneralMaterialBuffer {
       channel_c: attachment()
         .format(TextureFormat::Rgba8UnormSrgb)
         .request(cx),
+      channel_d: attachment().format(TextureFormat::Rg32Uint).request(cx),
     }
   }
 
@@ -40,6 +43,7 @@ impl FrameGeneralMaterialBuffer {
       channel_a: desc.push_color(&self.channel_a, clear_and_store(all_zero())),
       channel_b: desc.push_color(&self.channel_b, clear_and_store(all_zero())),
       channel_c: desc.push_color(&self.channel_c, clear_and_store(all_zero())),
+      channel_d: desc.push_color(&self.channel_d, clear_and_store(all_zero())),
     }
   }
 }
@@ -55,6 +59,7 @@ pub struct FrameGeneralMaterialBufferReadValue {
   pub channel_a: Node<Vec4<f32>>,
   pub channel_b: Node<Vec4<f32>>,
   pub channel_c: Node<Vec4<f32>>,
+  pub channel_d: Node<Vec2<u32>>,
 }
 
 #[derive(Hash, Debug)]
@@ -63,6 +68,7 @@ pub struct FrameGeneralMaterialChannelIndices {
   pub channel_a: usize,
   pub channel_b: usize,
   pub channel_c: usize,
+  pub channel_d: usize,
 }
 
 pub struct FrameGeneralMaterialBufferEncoder<'a> {
@@ -154,6 +160,11 @@ impl ShaderPassBuilder for FrameGeneralMaterialBufferReconstructSurface<'_> {
     self.m_buffer.channel_a.bind_pass(&mut cx.binding);
     self.m_buffer.channel_b.bind_pass(&mut cx.binding);
     self.m_buffer.channel_c.bind_pass(&mut cx.binding);
+    self
+      .m_buffer
+      .channel_d
+      .expect_texture_view::<u32>()
+      .bind_pass(&mut cx.binding);
     cx.bind_immediate_sampler(&TextureSampler::default().into_gpu());
   }
 }
@@ -167,6 +178,7 @@ impl LightableSurfaceProvider for FrameGeneralMaterialBufferReconstructSurface<'
     let channel_a = binding.bind_by(&self.m_buffer.channel_a);
     let channel_b = binding.bind_by(&self.m_buffer.channel_b);
     let channel_c = binding.bind_by(&self.m_buffer.channel_c);
+    let channel_d = binding.bind_by(&self.m_buffer.channel_d.expect_texture_view::<u32>());
     let sampler = binding.bind_by(&DisableFiltering(ImmediateGPUSamplerViewBind));
 
     let uv = builder.query::<FragmentUv>();
@@ -184,6 +196,7 @@ impl LightableSurfaceProvider for FrameGeneralMaterialBufferReconstructSurface<'
       channel_a: channel_a.sample_zero_level(sampler, uv),
       channel_b: channel_b.sample_zero_level(sampler, uv),
       channel_c: channel_c.sample_zero_level(sampler, uv),
+      channel_d: channel_d.load_texel(u32_uv, 0).xy(),
     };
 
     let alpha = val(1.).make_local_var();
@@ -270,6 +283,10 @@ impl DeferLightingMaterialBufferReadWrite for PbrSurfaceEncodeDecode {
         perceptual_roughness,
         f0,
         emissive,
+        clearcoat,
+        clearcoat_perceptual_roughness,
+        sheen_color,
+        sheen_perceptual_roughness,
       } = PhysicalShading::construct_shading_impl(builder.registry());
 
       let albedo_roughness: Node<Vec4<_>> = (albedo, perceptual_roughness).into();
@@ -280,6 +297,18 @@ impl DeferLightingMaterialBufferReadWrite for PbrSurfaceEncodeDecode {
       builder.frag_output[indices.channel_a].store(albedo_roughness);
       builder.frag_output[indices.channel_b].store(f0_emissive_x);
       builder.frag_output[indices.channel_c].store((emissive.yz(), alpha, val(1.)).into());
+
+      // the sheen color is stored in sqrt to keep the precision of the dark color
+      let layers: Node<Vec4<_>> = (
+        clearcoat,
+        clearcoat_perceptual_roughness,
+        sheen_perceptual_roughness,
+        val(0.),
+      )
+        .into();
+      let sheen_color: Node<Vec4<_>> = (sheen_color.sqrt(), val(0.)).into();
+      let layers: Node<Vec2<_>> = (layers.pack4x8unorm(), sheen_color.pack4x8unorm()).into();
+      builder.frag_output[indices.channel_d].store(layers);
       true
     } else {
       false
@@ -295,6 +324,8 @@ impl DeferLightingMaterialBufferReadWrite for PbrSurfaceEncodeDecode {
     let albedo_roughness = instance.channel_a;
     let f0_emissive_x = instance.channel_b;
     let emissive_yz_alpha = instance.channel_c;
+    let layers = instance.channel_d.x().unpack4x8unorm();
+    let sheen_color = instance.channel_d.y().unpack4x8unorm().xyz();
 
     let emissive = vec3_node((
       f0_emissive_x.w(),
@@ -307,6 +338,10 @@ impl DeferLightingMaterialBufferReadWrite for PbrSurfaceEncodeDecode {
       perceptual_roughness: albedo_roughness.w(),
       f0: f0_emissive_x.xyz(),
       emissive,
+      clearcoat: layers.x(),
+      clearcoat_perceptual_roughness: layers.y(),
+      sheen_color: sheen_color * sheen_color,
+      sheen_perceptual_roughness: layers.z(),
     });
 
     DeferLightingSurfaceReadBack::Lightable(surface)
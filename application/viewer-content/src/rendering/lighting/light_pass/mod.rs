@@ -72,6 +72,16 @@ pub fn use_render_lighting_scene_content(
     renderer.scene,
   );
 
+  let camera_transform = renderer.camera_transforms.access(&camera).unwrap();
+  lighting_cx.lighting.use_update_clustered_light_grid(
+    ctx,
+    scene,
+    camera,
+    camera_gpu,
+    &camera_transform,
+    renderer.reversed_depth,
+  );
+
   // always get forward lighting because we may use it in none forward case(transparent pass in defer mode)
   let forward_lighting = lighting_cx
     .lighting
@@ -1,6 +1,17 @@
 use crate::*;
 
-pub fn use_area_light_uniform(cx: &mut QueryGPUHookCx) -> Option<SceneAreaLightingProvider> {
+/// the area lights are collected from the storage buffer in the indirect raster when the area
+/// light shadow is disabled. when the shadow is enabled, the shadow info is indexed by the per
+/// scene uniform array, so all the area lights fall back to the uniform array, which holds at
+/// most [LIGHT_LIST_LEN] lights per scene, the lights beyond that are not rendered.
+pub fn use_area_light_uniform(
+  cx: &mut QueryGPUHookCx,
+  shadow_packer_config: &MultiLayerTexturePackerConfig,
+  lighting_sys: &LightSystem,
+  ndc: ViewerNDC,
+  is_indirect_raster: bool,
+) -> Option<SceneAreaLightingPreparer> {
+  cx.next_scope_index();
   let uniform = use_area_per_scene_uniform_array_buffers(cx);
 
   let (cx, lut) = cx.use_gpu_init(|gpu, _| {
@@ -11,6 +22,7 @@ pub fn use_area_light_uniform(cx: &mut QueryGPUHookCx) -> Option<SceneAreaLighti
         data: ltc_1.as_slice().to_vec(),
         format: TextureFormat::Rgba16Float,
         size: Size::from_u32_pair_min_one((64, 64)),
+        precomputed_mips: Vec::new(),
       },
     );
     let ltc_2 = include_bytes!("./ltc_2.bin");
@@ -20,16 +32,177 @@ pub fn use_area_light_uniform(cx: &mut QueryGPUHookCx) -> Option<SceneAreaLighti
         data: ltc_2.as_slice().to_vec(),
         format: TextureFormat::Rgba16Float,
         size: Size::from_u32_pair_min_one((64, 64)),
+        precomputed_mips: Vec::new(),
       },
     );
     (ltc_1, ltc_2)
   });
 
+  let enable_shadow = lighting_sys.enable_shadow && lighting_sys.enable_area_light_shadow;
+
+  // see the function doc for the fallback to the uniform array
+  let storage = if is_indirect_raster && !enable_shadow {
+    cx.scope(use_area_light_storage)
+  } else {
+    None
+  };
+
+  let shadow = if enable_shadow {
+    cx.scope(|cx| {
+      let shadow_info = use_area_light_shadow_map_uniform(cx, shadow_packer_config, ndc, &uniform);
+      // the area light is always shadowed by the PCSS, the penumbra is estimated by the light size
+      let pcf_config = ShadowPCFConfig {
+        pcf_mode: ShadowPCFMode::PCSS,
+        ..lighting_sys.pcf_config
+      };
+      let shadow_map = use_pcf_shadow_map(
+        cx,
+        pcf_config,
+        ndc.enable_reverse_z,
+        shadow_info.as_ref().map(|v| v.1),
+      );
+      shadow_info.map(|v| ShadowMapPreparerEntry {
+        preparer: v.0,
+        shadow_map,
+      })
+    })
+  } else {
+    None
+  };
+
   cx.when_render(|| -> _ {
-    SceneAreaLightingProvider {
+    SceneAreaLightingPreparer {
       ltc_1: lut.0.clone(),
       ltc_2: lut.1.clone(),
-      uniform: uniform.unwrap().make_read_holder(),
+      shadow,
+      storage,
+      light: uniform.unwrap(),
+      scene_ref: read_global_db_foreign_key(),
+      bias_behavior: lighting_sys.bias_behavior,
     }
   })
 }
+
+fn use_area_light_shadow_map_uniform(
+  cx: &mut QueryGPUHookCx,
+  atlas_config: &MultiLayerTexturePackerConfig,
+  ndc: ViewerNDC,
+  lights: &Option<SharedLightUniformInfo<LTCAreaLightUniform>>,
+) -> Option<(BasicShadowMapPreparer, SizeWithDepth)> {
+  let world_mat = use_global_node_world_mat_view(cx).use_assure_result(cx);
+
+  let gpu = cx.gpu;
+  let (cx, gpu_data) = cx.use_plain_state_default::<Option<BasicShadowMapInfoGPU>>();
+
+  cx.when_render(|| {
+    let light_ref_node = get_db_view::<AreaLightRefNode>();
+    let light_size = get_db_view::<AreaLightSize>();
+    let light_intensity = get_db_view::<AreaLightIntensity>();
+
+    let shadow_enabled = get_db_view::<BasicShadowMapEnabledOf<AreaLightBasicShadowInfo>>();
+    let shadow_map_size = get_db_view::<BasicShadowMapResolutionOf<AreaLightBasicShadowInfo>>();
+    let shadow_bias = get_db_view::<BasicShadowMapBiasOf<AreaLightBasicShadowInfo>>();
+    let world_mat = world_mat.expect_resolve_stage();
+
+    let shadow_info_access = |light_id: RawEntityHandle| {
+      let enabled = shadow_enabled.access(&light_id).unwrap();
+      if !enabled {
+        return None;
+      }
+      let node = light_ref_node.access(&light_id).unwrap().unwrap();
+      let light_world = world_mat.access(&node).unwrap();
+      let size = shadow_map_size.access(&light_id).unwrap();
+      let bias = shadow_bias.access(&light_id).unwrap();
+
+      let scale = light_world.get_scale().into_f32();
+      let area_size = light_size.access(&light_id).unwrap();
+      let world_size = Vec2::new(area_size.x * scale.x, area_size.y * scale.y);
+      let intensity = light_intensity.access(&light_id).unwrap();
+
+      // the shadow camera looks at the lit side of the light, which is the -z of the light node
+      let projection = area_light_shadow_projection(world_size, intensity);
+      let proj = ShadowCameraProjectionMatrixes {
+        render_matrix: projection.compute_projection_mat(&ndc),
+        opengl_ndc_matrix: projection.compute_projection_mat(&OpenGLxNDC),
+      };
+
+      BasicShadowMapInfoInput {
+        light_world,
+        proj,
+        map_size: Size::from_u32_pair_min_one(size.into()),
+        bias: bias.into(),
+        light_size: world_size.x.max(world_size.y),
+      }
+      .into()
+    };
+
+    let lights = lights.as_ref().unwrap().read();
+    prepare_basic_shadow_map_uniform(
+      atlas_config,
+      &lights.allocation_info,
+      &shadow_info_access,
+      gpu_data,
+      gpu,
+    )
+  })
+}
+
+pub struct SceneAreaLightingPreparer {
+  pub ltc_1: GPU2DTextureView,
+  pub ltc_2: GPU2DTextureView,
+  pub shadow: Option<ShadowMapPreparerEntry<BasicShadowMapPreparer>>,
+  /// if exist, the lights are collected from the storage buffer instead of the uniform array
+  pub storage: Option<AreaLightGPUStorage>,
+  pub light: SharedLightUniformInfo<LTCAreaLightUniform>,
+  pub scene_ref: ForeignKeyReadView<AreaLightRefScene>,
+  pub bias_behavior: ShadowBiasBehaviorConfig,
+}
+
+impl SceneAreaLightingPreparer {
+  pub fn update_shadow_maps(
+    self,
+    frame_ctx: &mut FrameCtx,
+    draw: &mut dyn FnMut(&mut FrameCtx, ShadowMapDrawRequest, EntityHandle<SceneEntity>),
+    reversed_depth: bool,
+    only_shadowed: bool,
+  ) -> Box<dyn LightSystemSceneProvider> {
+    if let Some(lights) = self.storage {
+      return Box::new(SceneAreaLightingStorageProvider {
+        ltc_1: self.ltc_1,
+        ltc_2: self.ltc_2,
+        lights,
+      });
+    }
+
+    let mut draw = |f_ctx: &mut FrameCtx<'_>, param: ShadowMapDrawRequest| {
+      let light_id = unsafe { EntityHandle::from_raw(param.light_id) };
+      let scene_id = self
+        .scene_ref
+        .get(light_id)
+        .expect("lighting missing scene ref");
+
+      draw(f_ctx, param, scene_id);
+    };
+
+    let shadow = self.shadow.map(|mut entry| {
+      let shadow_info =
+        entry
+          .preparer
+          .update_shadow_maps(frame_ctx, entry.shadow_map.as_mut(), &mut draw);
+      AreaLightShadowGPUData {
+        uniforms: shadow_info.uniforms,
+        shadow_computer: entry.shadow_map.create_abstract_shadow_computer(),
+        bias_behavior: self.bias_behavior,
+        reversed_depth,
+      }
+    });
+
+    Box::new(SceneAreaLightingProvider {
+      ltc_1: self.ltc_1,
+      ltc_2: self.ltc_2,
+      uniform: self.light.make_read_holder(),
+      shadow,
+      only_shadowed,
+    })
+  }
+}
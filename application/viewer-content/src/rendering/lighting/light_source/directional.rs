@@ -76,6 +76,7 @@ fn use_basic_shadow_map_uniform(
     let shadow_map_size =
       get_db_view::<BasicShadowMapResolutionOf<DirectionLightBasicShadowInfo>>();
     let shadow_bias = get_db_view::<BasicShadowMapBiasOf<DirectionLightBasicShadowInfo>>();
+    let light_size = get_db_view::<BasicShadowMapLightSizeOf<DirectionLightBasicShadowInfo>>();
     let shadow_proj = get_db_view::<DirectionLightShadowBound>();
     let world_mat = world_mat.expect_resolve_stage();
 
@@ -105,6 +106,7 @@ fn use_basic_shadow_map_uniform(
         proj,
         map_size: Size::from_u32_pair_min_one(size.into()),
         bias: bias.into(),
+        light_size: light_size.access(&light_id).unwrap(),
       }
       .into()
     };
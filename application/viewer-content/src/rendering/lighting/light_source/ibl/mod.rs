@@ -48,7 +48,7 @@ pub fn use_ibl(cx: &mut QueryGPUHookCx) -> Option<IBLLightingComponentProvider>
 pub struct IBLLightingComponentProvider {
   access: ForeignKeyReadView<SceneHDRxEnvBackgroundCubeMap>,
   prefiltered: LockReadGuardHolder<FastHashMap<RawEntityHandle, PreFilterMapGenerationResult>>,
-  brdf_lut: GPU2DTextureView,
+  pub(crate) brdf_lut: GPU2DTextureView,
   uniform: LockReadGuardHolder<IBLUniforms>,
 }
 
@@ -0,0 +1,358 @@
+use half::f16;
+use rendiation_shader_library::sh::*;
+
+use crate::*;
+
+/// the baked sh of all probes in one scene are packed into one texture, each probe takes 9
+/// continuous texels in a row.
+const PROBES_PER_TEXTURE_ROW: u32 = 64;
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Copy, Clone, ShaderStruct, Default, PartialEq)]
+pub struct LightProbeVolumeUniform {
+  pub center: HighPrecisionTranslationUniform,
+  /// transform the world space offset to the volume center into the volume local space
+  pub world_to_local: Mat4<f32>,
+  pub resolution: Vec3<u32>,
+  /// the index of the first probe of this volume in the sh texture
+  pub probe_offset: u32,
+  pub intensity: f32,
+  pub edge_fade: f32,
+}
+
+/// the world space probe positions of a volume, in the order of [LightProbeVolumeIrradianceLayout]
+#[derive(Clone)]
+pub struct LightProbeVolumeBakeTarget {
+  pub volume: EntityHandle<LightProbeVolumeEntity>,
+  pub positions: Vec<Vec3<f32>>,
+}
+
+#[derive(Default)]
+pub struct LightProbeVolumeHostData {
+  /// scene id -> the packed sh texture of the baked volumes in the scene
+  pub sh_textures: FastHashMap<RawEntityHandle, GPU2DTextureView>,
+  /// scene id -> all volumes in the scene, including the ones not baked yet
+  pub bake_targets: FastHashMap<RawEntityHandle, Vec<LightProbeVolumeBakeTarget>>,
+}
+
+pub type SharedLightProbeVolumeHostData = Arc<RwLock<LightProbeVolumeHostData>>;
+
+pub fn use_light_probe_volumes(cx: &mut QueryGPUHookCx) -> Option<LightProbeVolumeProvider> {
+  cx.next_scope_index();
+  let uniforms = use_shared_light_uniform_info(cx, "light probe volume");
+  let host = cx.use_sharable_plain_state(LightProbeVolumeHostData::default);
+
+  cx.skip_if_not_waked(|cx| {
+    cx.use_db_entity_any_change::<LightProbeVolumeEntity>();
+    let world_mat = use_global_node_world_mat_view(cx).use_assure_result(cx);
+
+    if cx.is_in_render() {
+      let world = world_mat.expect_resolve_stage();
+      let (r, host_data) =
+        create_light_probe_volume_data(&|node| world.access(&node).unwrap(), cx.gpu);
+
+      sync_per_scene_uniforms(&r, &uniforms, cx.gpu, "light probe volume");
+      *host.write() = host_data;
+    }
+  });
+
+  cx.when_render(|| LightProbeVolumeProvider {
+    uniforms: uniforms.make_read_holder(),
+    host: host.clone(),
+  })
+}
+
+fn create_light_probe_volume_data(
+  node_world_mat: &dyn Fn(RawEntityHandle) -> Mat4<f64>,
+  gpu: &GPU,
+) -> (
+  PerSceneLightUniformArray<LightProbeVolumeUniform>,
+  LightProbeVolumeHostData,
+) {
+  let ref_scene = get_db_view::<LightProbeVolumeRefScene>();
+  let ref_node = get_db_view::<LightProbeVolumeRefNode>();
+  let resolution = get_db_view::<LightProbeVolumeResolution>();
+  let intensity = get_db_view::<LightProbeVolumeIntensity>();
+  let edge_fade = get_db_view::<LightProbeVolumeEdgeFade>();
+  let baked = get_db_view::<LightProbeVolumeIrradianceData>();
+  let buffers = get_db_view::<BufferEntityData>();
+
+  let mut host = LightProbeVolumeHostData::default();
+  // scene id -> (probe count, packed texels)
+  let mut packed = FastHashMap::<RawEntityHandle, (u32, Vec<f16>)>::default();
+
+  let iter_volumes = ref_scene.iter_key_value().filter_map(|(volume, scene)| {
+    let scene = scene?;
+    let world = node_world_mat(ref_node.access(&volume)??);
+    let layout = LightProbeVolumeIrradianceLayout::new(resolution.access(&volume)?);
+
+    host
+      .bake_targets
+      .entry(scene)
+      .or_default()
+      .push(LightProbeVolumeBakeTarget {
+        volume: unsafe { EntityHandle::from_raw(volume) },
+        positions: (0..layout.probe_count())
+          .map(|i| {
+            let local = layout.probe_local_position(layout.probe_grid(i));
+            (world * local.into_f64()).into_f32()
+          })
+          .collect(),
+      });
+
+    let data = buffers.access(&baked.access(&volume)??)?;
+    let MaybeUriData::Living(data) = data.ptr.as_ref() else {
+      log::warn!("light probe volume {volume} data is not loaded, the volume is skipped");
+      return None;
+    };
+    if data.len() != layout.byte_size() {
+      log::warn!("light probe volume {volume} data size mismatch, the volume is skipped");
+      return None;
+    }
+
+    let (probe_count, texels) = packed.entry(scene).or_default();
+    let probe_offset = *probe_count;
+    *probe_count += layout.probe_count() as u32;
+    // the byte buffer is not guaranteed to be aligned for f32
+    let coefficients: Vec<f32> = data
+      .chunks_exact(4)
+      .map(|v| f32::from_le_bytes(v.try_into().unwrap()))
+      .collect();
+    texels.extend(
+      coefficients
+        .chunks_exact(3)
+        .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0.])
+        .map(f16::from_f32),
+    );
+
+    let uniform = LightProbeVolumeUniform {
+      center: into_hpt(world.position()).into_uniform(),
+      world_to_local: world.remove_position().inverse_or_identity().into_f32(),
+      resolution: layout.resolution,
+      probe_offset,
+      intensity: intensity.access(&volume)?,
+      edge_fade: edge_fade.access(&volume)?,
+      ..Default::default()
+    };
+
+    (volume, scene, uniform).into()
+  });
+
+  let uniforms = compute_light_list(iter_volumes);
+
+  for (scene, (probe_count, mut texels)) in packed {
+    let width = PROBES_PER_TEXTURE_ROW * SH_L2_COEFFICIENT_COUNT as u32;
+    let height = probe_count.div_ceil(PROBES_PER_TEXTURE_ROW);
+    texels.resize((width * height * 4) as usize, f16::ZERO);
+
+    let texture = create_gpu_texture2d(
+      gpu,
+      &GPUBufferImage {
+        data: cast_slice(&texels).to_vec(),
+        format: TextureFormat::Rgba16Float,
+        size: Size::from_u32_pair_min_one((width, height)),
+        precomputed_mips: Vec::new(),
+      },
+    );
+    host.sh_textures.insert(scene, texture);
+  }
+
+  (uniforms, host)
+}
+
+pub struct LightProbeVolumeProvider {
+  uniforms: LockReadGuardHolder<LightUniformInfo<LightProbeVolumeUniform>>,
+  pub host: SharedLightProbeVolumeHostData,
+}
+
+/// Replace the diffuse part of the environment lighting by the probe lighting inside the probe
+/// volumes, the specular part of the environment lighting is kept.
+pub struct LightProbeDiffuseLightingProvider {
+  pub environment: Box<dyn LightSystemSceneProvider>,
+  pub probes: LightProbeVolumeProvider,
+}
+
+impl LightSystemSceneProvider for LightProbeDiffuseLightingProvider {
+  fn get_scene_lighting(
+    &self,
+    scene: EntityHandle<SceneEntity>,
+    camera: EntityHandle<SceneCameraEntity>,
+  ) -> Option<Box<dyn LightingComputeComponent>> {
+    let environment = self.environment.get_scene_lighting(scene, camera);
+
+    let sh = self
+      .probes
+      .host
+      .read()
+      .sh_textures
+      .get(scene.raw_handle_ref())
+      .cloned();
+    let volumes = self.probes.uniforms.uniform.get(scene.raw_handle_ref());
+    let (Some(sh), Some(volumes)) = (sh, volumes) else {
+      return environment;
+    };
+
+    Some(Box::new(LightProbeLightingComponent {
+      environment,
+      volumes: volumes.clone(),
+      sh,
+    }))
+  }
+}
+
+type LightProbeVolumeUniformArray =
+  UniformArrayWithLengthInfo<LightProbeVolumeUniform, LIGHT_LIST_LEN>;
+
+pub struct LightProbeLightingComponent {
+  environment: Option<Box<dyn LightingComputeComponent>>,
+  volumes: UniformBufferCachedDataView<LightProbeVolumeUniformArray>,
+  sh: GPU2DTextureView,
+}
+
+impl ShaderHashProvider for LightProbeLightingComponent {
+  shader_hash_type_id! {}
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    hasher.hash(self.environment.is_some());
+    if let Some(environment) = &self.environment {
+      environment.hash_pipeline_with_type_info(hasher);
+    }
+  }
+}
+
+impl LightingComputeComponent for LightProbeLightingComponent {
+  fn build_light_compute_invocation(
+    &self,
+    binding: &mut ShaderBindGroupBuilder,
+    scene_id: Node<u32>,
+  ) -> Box<dyn LightingComputeInvocation> {
+    Box::new(LightProbeLightingInvocation {
+      environment: self
+        .environment
+        .as_ref()
+        .map(|e| e.build_light_compute_invocation(binding, scene_id)),
+      volumes: binding.bind_by(&self.volumes),
+      sh: binding.bind_by(&self.sh),
+    })
+  }
+
+  fn setup_pass(&self, ctx: &mut BindingBuilder) {
+    if let Some(environment) = &self.environment {
+      environment.setup_pass(ctx);
+    }
+    ctx.bind(&self.volumes);
+    ctx.bind(&self.sh);
+  }
+}
+
+struct LightProbeLightingInvocation {
+  environment: Option<Box<dyn LightingComputeInvocation>>,
+  volumes: ShaderReadonlyPtrOf<LightProbeVolumeUniformArray>,
+  sh: BindingNode<ShaderTexture2D>,
+}
+
+impl LightProbeLightingInvocation {
+  fn load_probe_irradiance(&self, probe: Node<u32>, normal: Node<Vec3<f32>>) -> Node<Vec3<f32>> {
+    let row = probe / val(PROBES_PER_TEXTURE_ROW);
+    let first = (probe % val(PROBES_PER_TEXTURE_ROW)) * val(SH_L2_COEFFICIENT_COUNT as u32);
+    let sh = std::array::from_fn(|i| {
+      let position: Node<Vec2<u32>> = (first + val(i as u32), row).into();
+      self.sh.load_texel(position, val(0)).xyz()
+    });
+    sh_l2_irradiance_device(sh, normal).max(Vec3::zero())
+  }
+
+  /// trilinear interpolate the irradiance of the nearest 8 probes
+  fn sample_volume_irradiance(
+    &self,
+    volume: &ENode<LightProbeVolumeUniform>,
+    local: Node<Vec3<f32>>,
+    normal: Node<Vec3<f32>>,
+  ) -> Node<Vec3<f32>> {
+    let resolution = volume.resolution;
+    let max_grid = resolution - val(Vec3::one());
+    let grid = (local * val(0.5) + val(Vec3::splat(0.5))).clamp(Vec3::zero(), Vec3::one());
+    let grid = grid * max_grid.into_f32();
+    let base = grid.floor();
+    let fract = grid - base;
+    let base = base.into_u32().min(max_grid);
+
+    let mut irradiance = val(Vec3::<f32>::zero());
+    for corner in 0..8_u32 {
+      let offset = Vec3::new(corner & 1, (corner >> 1) & 1, corner >> 2);
+      let probe = (base + val(offset)).min(max_grid);
+      let probe = volume.probe_offset
+        + probe.x()
+        + probe.y() * resolution.x()
+        + probe.z() * resolution.x() * resolution.y();
+
+      let weight = |axis: u32, fract: Node<f32>| {
+        if axis == 1 { fract } else { val(1.) - fract }
+      };
+      let weight =
+        weight(offset.x, fract.x()) * weight(offset.y, fract.y()) * weight(offset.z, fract.z());
+
+      irradiance += self.load_probe_irradiance(probe, normal) * weight;
+    }
+    irradiance
+  }
+}
+
+impl LightingComputeInvocation for LightProbeLightingInvocation {
+  fn compute_lights(
+    &self,
+    shading: &dyn LightableSurfaceShading,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> ENode<ShaderLightingResult> {
+    let environment = self
+      .environment
+      .as_ref()
+      .map(|e| e.compute_lights(shading, geom_ctx))
+      .unwrap_or_else(|| zeroed_val::<ShaderLightingResult>().expand());
+
+    let Some(physical) = shading
+      .as_any()
+      .downcast_ref::<ENode<ShaderPhysicalShading>>()
+    else {
+      return environment;
+    };
+
+    let weight = val(0_f32).make_local_var();
+    let irradiance = val(Vec3::<f32>::zero()).make_local_var();
+
+    self
+      .volumes
+      .clone()
+      .into_shader_iter()
+      .for_each(|(_, volume), _| {
+        let volume = volume.load().expand();
+        let center = hpt_sub_hpt(
+          hpt_uniform_to_hpt(volume.center),
+          geom_ctx.camera_world_position,
+        );
+        let offset = geom_ctx.position - center;
+        let local = (volume.world_to_local * vec4_node((offset, val(0.)))).xyz();
+
+        // fade out near the volume boundary, zero outside of the volume
+        let distance_to_edge = val(1.) - local.abs().max_channel();
+        let volume_weight = (distance_to_edge / volume.edge_fade.max(val(0.0001))).clamp(0., 1.);
+
+        // the overlapped volumes are not blended, the volume with the largest weight is used
+        if_by(volume_weight.greater_than(weight.load()), || {
+          weight.store(volume_weight);
+          let volume_irradiance = self.sample_volume_irradiance(&volume, local, geom_ctx.normal);
+          irradiance.store(volume_irradiance * volume.intensity);
+        });
+      });
+
+    let weight = weight.load();
+    let probe_diffuse =
+      physical.albedo * irradiance.load() / val(Vec3::splat(std::f32::consts::PI));
+    let diffuse = environment.diffuse * (val(1.) - weight) + probe_diffuse * weight;
+
+    ENode::<ShaderLightingResult> {
+      diffuse,
+      specular: environment.specular,
+    }
+  }
+}
@@ -25,6 +25,12 @@ pub use area::*;
 mod ibl;
 pub use ibl::*;
 
+mod light_probe;
+pub use light_probe::*;
+
+mod reflection_probe;
+pub use reflection_probe::*;
+
 pub fn use_shadow_map(
   cx: &mut QueryGPUHookCx,
   lighting_sys: &LightSystem,
@@ -32,26 +38,9 @@ pub fn use_shadow_map(
   rebuild: Option<SizeWithDepth>,
 ) -> Box<dyn AbstractShadowMapGPUData> {
   match lighting_sys.filter_ty {
-    ViewerShadowFilterType::PCF => cx.scope(|cx| {
-      let (cx, shadow) = cx.use_plain_state(|| PCFShadowMapGPUData {
-        atlas: None,
-        pcf_config_parameter: create_pcf_parameter(cx.gpu, lighting_sys.pcf_config),
-        pcf_config: lighting_sys.pcf_config,
-        reversed_depth,
-      });
-
-      if cx.is_in_render() {
-        // todo diff update
-        shadow.pcf_config_parameter = create_pcf_parameter(cx.gpu, lighting_sys.pcf_config);
-        shadow.pcf_config = lighting_sys.pcf_config;
-      }
-
-      if let Some(rebuild) = rebuild {
-        shadow.check_rebuild(rebuild, cx.gpu);
-      }
-
-      Box::new(shadow.clone())
-    }),
+    ViewerShadowFilterType::PCF => {
+      cx.scope(|cx| use_pcf_shadow_map(cx, lighting_sys.pcf_config, reversed_depth, rebuild))
+    }
     ViewerShadowFilterType::VSM => cx.scope(|cx| {
       let (cx, shadow) =
         cx.use_plain_state(|| VSMShadowMap::new(lighting_sys.vsm_config, reversed_depth, cx.gpu));
@@ -69,6 +58,32 @@ pub fn use_shadow_map(
   }
 }
 
+pub fn use_pcf_shadow_map(
+  cx: &mut QueryGPUHookCx,
+  pcf_config: ShadowPCFConfig,
+  reversed_depth: bool,
+  rebuild: Option<SizeWithDepth>,
+) -> Box<dyn AbstractShadowMapGPUData> {
+  let (cx, shadow) = cx.use_plain_state(|| PCFShadowMapGPUData {
+    atlas: None,
+    pcf_config_parameter: create_pcf_parameter(cx.gpu, pcf_config),
+    pcf_config,
+    reversed_depth,
+  });
+
+  if cx.is_in_render() {
+    // todo diff update
+    shadow.pcf_config_parameter = create_pcf_parameter(cx.gpu, pcf_config);
+    shadow.pcf_config = pcf_config;
+  }
+
+  if let Some(rebuild) = rebuild {
+    shadow.check_rebuild(rebuild, cx.gpu);
+  }
+
+  Box::new(shadow.clone())
+}
+
 pub fn use_shadow_map_entry<P>(
   cx: &mut QueryGPUHookCx,
   lighting_sys: &LightSystem,
@@ -48,6 +48,7 @@ fn use_cube_shadow_map_uniform(
     let shadow_enabled = get_db_view::<BasicShadowMapEnabledOf<PointLightBasicShadowInfo>>();
     let shadow_map_size = get_db_view::<BasicShadowMapResolutionOf<PointLightBasicShadowInfo>>();
     let shadow_bias = get_db_view::<BasicShadowMapBiasOf<PointLightBasicShadowInfo>>();
+    let light_size = get_db_view::<BasicShadowMapLightSizeOf<PointLightBasicShadowInfo>>();
     let cutoff_distance = get_db_view::<PointLightCutOffDistance>();
     let world_mat = world_mat.expect_resolve_stage();
 
@@ -77,6 +78,7 @@ fn use_cube_shadow_map_uniform(
         proj,
         map_size: Size::from_u32_pair_min_one(size.into()),
         bias: bias.into(),
+        light_size: light_size.access(&light_id).unwrap(),
       }
       .into()
     };
@@ -105,6 +107,7 @@ impl ScenePointLightingPreparer {
     frame_ctx: &mut FrameCtx,
     draw: &mut dyn FnMut(&mut FrameCtx, ShadowMapDrawRequest, EntityHandle<SceneEntity>),
     reversed_depth: bool,
+    only_shadowed: bool,
   ) -> ScenePointLightingProvider {
     let mut draw = |f_ctx: &mut FrameCtx<'_>, param: ShadowMapDrawRequest| {
       let light_id = unsafe { EntityHandle::from_raw(param.light_id) };
@@ -132,6 +135,7 @@ impl ScenePointLightingPreparer {
       shadow,
       reversed_depth,
       bias_behavior: self.bias_behavior,
+      only_shadowed,
     }
   }
 }
@@ -141,6 +145,8 @@ pub struct ScenePointLightingProvider {
   uniform: LockReadGuardHolder<LightUniformInfo<PointLightUniform>>,
   reversed_depth: bool,
   bias_behavior: ShadowBiasBehaviorConfig,
+  /// only shade the lights with shadow, the others are shaded by the clustered lighting
+  only_shadowed: bool,
 }
 
 impl LightSystemSceneProvider for ScenePointLightingProvider {
@@ -166,7 +172,11 @@ impl LightSystemSceneProvider for ScenePointLightingProvider {
       }
     });
 
-    Some(Box::new(PointLightShader { lights, shadow }))
+    Some(Box::new(PointLightShader {
+      lights,
+      shadow,
+      only_shadowed: self.only_shadowed,
+    }))
   }
 }
 
@@ -175,6 +185,7 @@ type UniformArray = UniformArrayWithLengthInfo<PointLightUniform, LIGHT_LIST_LEN
 #[derive(Clone)]
 struct PointLightShader {
   lights: UniformBufferCachedDataView<UniformArray>,
+  only_shadowed: bool,
   shadow: Option<CubeShadowMapComponent>,
 }
 
@@ -182,6 +193,7 @@ impl ShaderHashProvider for PointLightShader {
   shader_hash_type_id! {}
   fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
     hasher.hash(self.shadow.is_some());
+    hasher.hash(self.only_shadowed);
     if let Some(shadow) = &self.shadow {
       shadow.hash_pipeline(hasher);
     }
@@ -197,6 +209,7 @@ impl LightingComputeComponent for PointLightShader {
     Box::new(PointLightInvocation {
       lights: binding.bind_by(&self.lights),
       shadow: self.shadow.as_ref().map(|s| s.bind_shader(binding)),
+      only_shadowed: self.only_shadowed,
     })
   }
 
@@ -211,6 +224,7 @@ impl LightingComputeComponent for PointLightShader {
 struct PointLightInvocation {
   lights: ShaderReadonlyPtrOf<UniformArray>,
   shadow: Option<CubeShadowMapInvocation>,
+  only_shadowed: bool,
 }
 
 impl LightingComputeInvocation for PointLightInvocation {
@@ -219,36 +233,44 @@ impl LightingComputeInvocation for PointLightInvocation {
     shading: &dyn LightableSurfaceShading,
     geom_ctx: &ENode<ShaderLightingGeometricCtx>,
   ) -> ENode<ShaderLightingResult> {
-    light_iter_sum(self.lights.clone().into_shader_iter().map(
-      |(shadow_idx, light_ptr): (Node<u32>, ShaderReadonlyPtrOf<PointLightUniform>)| {
-        let uniform = light_ptr.load().expand();
-        let light = ENode::<PointLightShaderInfo> {
-          luminance_intensity: uniform.luminance_intensity,
-          position: hpt_uniform_to_hpt(uniform.position),
-          cutoff_distance: uniform.cutoff_distance,
-        }
-        .construct();
-        let incident = light.compute_incident_light(geom_ctx);
-
-        let occlusion = match &self.shadow {
-          Some(s) => s.query_shadow_occlusion_by_idx(
-            geom_ctx.position,
-            geom_ctx.normal,
-            shadow_idx,
-            geom_ctx.fragment_position.xy(),
-            geom_ctx.camera_world_position,
-          ),
-          None => val(1.0),
-        };
-
-        shading.compute_lighting_by_incident(
-          &ENode::<ShaderIncidentLight> {
-            color: incident.color * occlusion,
-            direction: incident.direction,
-          },
-          geom_ctx,
-        )
-      },
-    ))
+    let lights = self.lights.clone().into_shader_iter();
+    let shade = |(shadow_idx, light_ptr): (Node<u32>, ShaderReadonlyPtrOf<PointLightUniform>)| {
+      let uniform = light_ptr.load().expand();
+      let light = ENode::<PointLightShaderInfo> {
+        luminance_intensity: uniform.luminance_intensity,
+        position: hpt_uniform_to_hpt(uniform.position),
+        cutoff_distance: uniform.cutoff_distance,
+      }
+      .construct();
+      let incident = light.compute_incident_light(geom_ctx);
+
+      let occlusion = match &self.shadow {
+        Some(s) => s.query_shadow_occlusion_by_idx(
+          geom_ctx.position,
+          geom_ctx.normal,
+          shadow_idx,
+          geom_ctx.fragment_position.xy(),
+          geom_ctx.camera_world_position,
+        ),
+        None => val(1.0),
+      };
+
+      shading.compute_lighting_by_incident(
+        &ENode::<ShaderIncidentLight> {
+          color: incident.color * occlusion,
+          direction: incident.direction,
+        },
+        geom_ctx,
+      )
+    };
+
+    match &self.shadow {
+      Some(s) if self.only_shadowed => light_iter_sum(lights.map(|(shadow_idx, light_ptr)| {
+        s.is_shadow_enabled(shadow_idx)
+          .select_branched(|| shade((shadow_idx, light_ptr)).construct(), zeroed_val)
+          .expand()
+      })),
+      _ => light_iter_sum(lights.map(shade)),
+    }
   }
 }
@@ -0,0 +1,816 @@
+use rendiation_lighting_ibl::*;
+use rendiation_texture_core::TextureSampler;
+use rendiation_texture_gpu_base::SamplerConvertExt;
+
+use crate::*;
+
+/// the face size of the scene capture before the prefiltering
+const CAPTURE_RESOLUTION: u32 = 256;
+/// the captured scene is prefiltered into cube array with this face size
+const PREFILTER_CONFIG: PreFilterMapGenerationConfig = PreFilterMapGenerationConfig {
+  specular_resolution: 128,
+  specular_sample_count: 32,
+  diffuse_sample_count: 0, // not used
+  diffuse_resolution: 0,   // not used
+};
+const CAPTURE_NEAR: f32 = 0.05;
+const CAPTURE_FAR: f32 = 2000.;
+
+const REFLECTION_PROBE_SHAPE_BOX: u32 = 0;
+const REFLECTION_PROBE_SHAPE_SPHERE: u32 = 1;
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Copy, Clone, ShaderStruct, Default, PartialEq)]
+pub struct ReflectionProbeUniform {
+  pub center: HighPrecisionTranslationUniform,
+  /// transform the world space offset to the probe center into the influence volume local space
+  pub world_to_local: Mat4<f32>,
+  pub local_to_world: Mat4<f32>,
+  /// the world space length of the influence volume local axes
+  pub half_extent: Vec3<f32>,
+  pub shape: u32,
+  pub blend_distance: f32,
+  pub intensity: f32,
+  /// the cube index in the scene's captured cube array
+  pub cube_index: u32,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ReflectionProbeInfo {
+  pub probe: RawEntityHandle,
+  pub world: Mat4<f64>,
+  pub shape: ReflectionProbeShapeType,
+  pub blend_distance: f32,
+  pub intensity: f32,
+}
+
+impl ReflectionProbeInfo {
+  fn half_extent(&self) -> Vec3<f32> {
+    let world = self.world.into_f32();
+    Vec3::new(
+      Vec3::new(world.a1, world.a2, world.a3).length(),
+      Vec3::new(world.b1, world.b2, world.b3).length(),
+      Vec3::new(world.c1, world.c2, world.c3).length(),
+    )
+  }
+
+  fn influence_volume(&self) -> f32 {
+    let extent = self.half_extent();
+    let unit_volume = match self.shape {
+      ReflectionProbeShapeType::Box => 8.,
+      ReflectionProbeShapeType::Sphere => std::f32::consts::PI * 4. / 3.,
+    };
+    extent.x * extent.y * extent.z * unit_volume
+  }
+
+  fn uniform(&self, cube_index: u32) -> ReflectionProbeUniform {
+    let local_to_world = self.world.remove_position();
+    ReflectionProbeUniform {
+      center: into_hpt(self.world.position()).into_uniform(),
+      world_to_local: local_to_world.inverse_or_identity().into_f32(),
+      local_to_world: local_to_world.into_f32(),
+      half_extent: self.half_extent(),
+      shape: match self.shape {
+        ReflectionProbeShapeType::Box => REFLECTION_PROBE_SHAPE_BOX,
+        ReflectionProbeShapeType::Sphere => REFLECTION_PROBE_SHAPE_SPHERE,
+      },
+      blend_distance: self.blend_distance,
+      intensity: self.intensity,
+      cube_index,
+      ..Default::default()
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct ReflectionProbeSceneGPUData {
+  pub cubes: GPUCubeArrayTextureView,
+  pub uniforms: UniformBufferDataView<ReflectionProbeUniformArray>,
+}
+
+#[derive(Default)]
+pub struct ReflectionProbeHostData {
+  /// scene id -> probes in the scene, sorted by the influence volume from small to large
+  pub probes: FastHashMap<RawEntityHandle, Vec<ReflectionProbeInfo>>,
+  /// the scenes that the probes are changed since the last capture
+  pub dirty_scenes: FastHashSet<RawEntityHandle>,
+  /// scene id -> the captured probes
+  pub captured: FastHashMap<RawEntityHandle, ReflectionProbeSceneGPUData>,
+}
+
+pub type SharedReflectionProbeHostData = Arc<RwLock<ReflectionProbeHostData>>;
+
+pub fn use_reflection_probes(cx: &mut QueryGPUHookCx) -> Option<SharedReflectionProbeHostData> {
+  cx.next_scope_index();
+  let host = cx.use_sharable_plain_state(ReflectionProbeHostData::default);
+
+  cx.skip_if_not_waked(|cx| {
+    cx.use_db_entity_any_change::<ReflectionProbeEntity>();
+    let world_mat = use_global_node_world_mat_view(cx).use_assure_result(cx);
+
+    if cx.is_in_render() {
+      let world = world_mat.expect_resolve_stage();
+      let probes = collect_reflection_probes(&|node| world.access(&node).unwrap());
+
+      let mut host = host.write();
+      let host = &mut *host;
+      for scene in probes.keys().chain(host.probes.keys()) {
+        if probes.get(scene) != host.probes.get(scene) {
+          host.dirty_scenes.insert(*scene);
+        }
+      }
+      host.probes = probes;
+    }
+  });
+
+  cx.when_render(|| host.clone())
+}
+
+fn collect_reflection_probes(
+  node_world_mat: &dyn Fn(RawEntityHandle) -> Mat4<f64>,
+) -> FastHashMap<RawEntityHandle, Vec<ReflectionProbeInfo>> {
+  let ref_scene = get_db_view::<ReflectionProbeRefScene>();
+  let ref_node = get_db_view::<ReflectionProbeRefNode>();
+  let shape = get_db_view::<ReflectionProbeShape>();
+  let blend_distance = get_db_view::<ReflectionProbeBlendDistance>();
+  let intensity = get_db_view::<ReflectionProbeIntensity>();
+
+  let mut probes = FastHashMap::<RawEntityHandle, Vec<ReflectionProbeInfo>>::default();
+  for (probe, scene) in ref_scene.iter_key_value() {
+    let (Some(scene), Some(Some(node))) = (scene, ref_node.access(&probe)) else {
+      continue;
+    };
+    probes.entry(scene).or_default().push(ReflectionProbeInfo {
+      probe,
+      world: node_world_mat(node),
+      shape: shape.access(&probe).unwrap(),
+      blend_distance: blend_distance.access(&probe).unwrap(),
+      intensity: intensity.access(&probe).unwrap(),
+    });
+  }
+
+  // the smaller probe is more local, so it has the higher priority in blending
+  for probes in probes.values_mut() {
+    probes.sort_by(|a, b| a.influence_volume().total_cmp(&b.influence_volume()));
+  }
+
+  probes
+}
+
+/// render the scene into the cube maps at the probe positions and prefilter them, the
+/// capture is done when the probes in the scene are changed, or the `force` is true.
+///
+/// the probe lighting of the last capture is included in the capture.
+pub fn use_capture_reflection_probes(
+  ctx: &mut FrameCtx,
+  host: &SharedReflectionProbeHostData,
+  renderer: &ViewerRendererInstance,
+  lighting: &LightingRenderingCx,
+  scene: EntityHandle<SceneEntity>,
+  camera: EntityHandle<SceneCameraEntity>,
+  force: bool,
+) {
+  ctx.next_scope_index();
+  let probes = {
+    let mut host = host.write();
+    if !host.dirty_scenes.remove(scene.raw_handle_ref()) && !force {
+      return;
+    }
+    host.probes.get(scene.raw_handle_ref()).cloned()
+  };
+
+  let Some(mut probes) = probes.filter(|probes| !probes.is_empty()) else {
+    host.write().captured.remove(scene.raw_handle_ref());
+    return;
+  };
+  if probes.len() > LIGHT_LIST_LEN {
+    log::warn!(
+      "reflection probe count exceeds {LIGHT_LIST_LEN}, the largest probes will not be rendered"
+    );
+    probes.truncate(LIGHT_LIST_LEN);
+  }
+
+  let capture = create_cube_like_texture(
+    ctx.gpu,
+    CAPTURE_RESOLUTION,
+    CUBE_FACE_COUNT as u32,
+    MipLevelCount::EmptyMipMap,
+    TextureViewDimension::Cube,
+  );
+  let capture = GPUCubeTextureView::try_from(capture).unwrap();
+
+  let cubes = create_cube_like_texture(
+    ctx.gpu,
+    PREFILTER_CONFIG.specular_resolution,
+    (CUBE_FACE_COUNT * probes.len()) as u32,
+    MipLevelCount::BySize,
+    TextureViewDimension::CubeArray,
+  );
+  let cubes = GPUCubeArrayTextureView::try_from(cubes).unwrap();
+
+  let ndc = ViewerNDC {
+    enable_reverse_z: renderer.reversed_depth,
+  };
+  let projection = PerspectiveProjection {
+    near: CAPTURE_NEAR,
+    far: CAPTURE_FAR,
+    fov: Deg::from_rad(std::f32::consts::FRAC_PI_2),
+    aspect: 1.,
+  }
+  .compute_projection_mat(&ndc);
+
+  let mut uniforms = PerSceneLightArray::<ReflectionProbeUniform>::default();
+  for (cube_index, probe) in probes.iter().enumerate() {
+    let face_worlds = build_cube_face_world_matrices(probe.world);
+    for (face, world) in face_worlds.into_iter().enumerate() {
+      ctx.keyed_scope(&(probe.probe, face), |ctx| {
+        let camera_gpu = CameraGPU {
+          ubo: create_uniform(
+            CameraGPUTransform::from(CameraTransform::new(projection, world)),
+            &ctx.gpu.device,
+            "reflection probe capture camera",
+          ),
+        };
+        let face_target = cube_face_view(&capture.resource, face as u32, 0);
+        capture_scene_face(
+          ctx,
+          renderer,
+          lighting,
+          scene,
+          camera,
+          &camera_gpu,
+          face_target,
+        );
+      });
+    }
+
+    generate_pre_filter_specular_map_into(
+      &mut ctx.encoder,
+      ctx.gpu,
+      &capture,
+      &cubes.resource,
+      (cube_index * CUBE_FACE_COUNT) as u32,
+      &PREFILTER_CONFIG,
+    );
+    uniforms.push(probe.probe, probe.uniform(cube_index as u32));
+  }
+
+  let uniforms = create_uniform(uniforms.buffer, &ctx.gpu.device, "reflection probes");
+  host.write().captured.insert(
+    scene.into_raw(),
+    ReflectionProbeSceneGPUData { cubes, uniforms },
+  );
+}
+
+fn create_cube_like_texture(
+  gpu: &GPU,
+  resolution: u32,
+  layer_count: u32,
+  level: MipLevelCount,
+  dimension: TextureViewDimension,
+) -> GPUTextureView {
+  let size = Size::from_u32_pair_min_one((resolution, resolution));
+  let texture = GPUTexture::create(
+    TextureDescriptor {
+      label: "reflection probe".into(),
+      size: Extent3d {
+        width: resolution,
+        height: resolution,
+        depth_or_array_layers: layer_count,
+      },
+      mip_level_count: level.get_level_count_wgpu(size),
+      sample_count: 1,
+      dimension: TextureDimension::D2,
+      format: TextureFormat::Rgba16Float,
+      usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
+      view_formats: &[],
+    },
+    &gpu.device,
+  );
+  texture.create_view(TextureViewDescriptor {
+    dimension: Some(dimension),
+    ..Default::default()
+  })
+}
+
+fn capture_scene_face(
+  ctx: &mut FrameCtx,
+  renderer: &ViewerRendererInstance,
+  lighting: &LightingRenderingCx,
+  scene: EntityHandle<SceneEntity>,
+  camera: EntityHandle<SceneCameraEntity>,
+  camera_gpu: &CameraGPU,
+  face_target: GPU2DTextureView,
+) {
+  let sizer = |_| Size::from_u32_pair_min_one((CAPTURE_RESOLUTION, CAPTURE_RESOLUTION));
+  let color = attachment()
+    .format(TextureFormat::Rgba16Float)
+    .sizer(sizer)
+    .request(ctx);
+  let depth = depth_attachment().sizer(sizer).request(ctx);
+
+  // the capture target stores the linear radiance
+  let (color_ops, depth_ops) = renderer
+    .background
+    .init_clear(scene, renderer.reversed_depth, true);
+  let mut background = renderer.background.draw(scene, camera_gpu, &NoneToneMap);
+
+  let batch = renderer.batch_extractor.extract_scene_batch(
+    scene,
+    SceneContentKey::only_opaque_objects(),
+    renderer.raster_scene_renderer.as_ref(),
+  );
+  let content = renderer
+    .raster_scene_renderer
+    .use_make_scene_batch_pass_content(batch, ctx);
+
+  let lighting = lighting
+    .lighting
+    .get_scene_capture_lighting_component(scene, camera);
+
+  let mut pass_base =
+    pass("reflection probe capture").with_depth(&depth, depth_ops, load_and_store());
+  let color_writer = DefaultDisplayWriter::extend_pass_desc(&mut pass_base, &color, color_ops);
+  let dispatcher = RenderArray([
+    &DisableAllChannelBlend as &dyn RenderComponent,
+    &color_writer,
+    lighting.as_ref(),
+  ]);
+
+  pass_base
+    .render_ctx(ctx)
+    .by(&mut content.as_pass_content(camera_gpu, &dispatcher))
+    .by(&mut background);
+
+  // the cube map face is mirrored compared with the camera image, see the cube map face
+  // convention in the webgpu spec.
+  pass("reflection probe capture to cube face")
+    .with_color(&RenderTargetView::from(face_target), store_full_frame())
+    .render_ctx(ctx)
+    .by(
+      &mut MirroredCopy {
+        source: color.expect_texture_view(),
+      }
+      .draw_quad(),
+    );
+}
+
+struct MirroredCopy {
+  source: GPU2DTextureView,
+}
+
+impl ShaderHashProvider for MirroredCopy {
+  shader_hash_type_id! {}
+}
+
+impl ShaderPassBuilder for MirroredCopy {
+  fn setup_pass(&self, ctx: &mut GPURenderPassCtx) {
+    ctx.binding.bind(&self.source);
+    ctx.bind_immediate_sampler(&TextureSampler::default().into_gpu());
+  }
+}
+
+impl GraphicsShaderProvider for MirroredCopy {
+  fn build(&self, builder: &mut ShaderRenderPipelineBuilder) {
+    builder.fragment(|builder, binder| {
+      let source = binder.bind_by(&self.source);
+      let sampler = binder.bind_by(&ImmediateGPUSamplerViewBind);
+
+      let uv = builder.query::<FragmentUv>();
+      let uv: Node<Vec2<f32>> = (val(1.) - uv.x(), uv.y()).into();
+      let color = source.sample_zero_level(sampler, uv);
+
+      builder.store_fragment_out_vec4f(0, color);
+    });
+  }
+}
+
+/// Replace the specular part of the environment lighting by the captured probes inside the probe
+/// influence volumes, the diffuse part of the environment lighting is kept.
+pub struct ReflectionProbeSpecularLightingProvider {
+  pub environment: Box<dyn LightSystemSceneProvider>,
+  pub probes: SharedReflectionProbeHostData,
+  pub brdf_lut: GPU2DTextureView,
+}
+
+impl LightSystemSceneProvider for ReflectionProbeSpecularLightingProvider {
+  fn get_scene_lighting(
+    &self,
+    scene: EntityHandle<SceneEntity>,
+    camera: EntityHandle<SceneCameraEntity>,
+  ) -> Option<Box<dyn LightingComputeComponent>> {
+    let environment = self.environment.get_scene_lighting(scene, camera);
+
+    let Some(probes) = self
+      .probes
+      .read()
+      .captured
+      .get(scene.raw_handle_ref())
+      .cloned()
+    else {
+      return environment;
+    };
+
+    Some(Box::new(ReflectionProbeLightingComponent {
+      environment,
+      probes,
+      brdf_lut: self.brdf_lut.clone(),
+    }))
+  }
+}
+
+pub type ReflectionProbeUniformArray =
+  UniformArrayWithLengthInfo<ReflectionProbeUniform, LIGHT_LIST_LEN>;
+
+pub struct ReflectionProbeLightingComponent {
+  environment: Option<Box<dyn LightingComputeComponent>>,
+  probes: ReflectionProbeSceneGPUData,
+  brdf_lut: GPU2DTextureView,
+}
+
+impl ShaderHashProvider for ReflectionProbeLightingComponent {
+  shader_hash_type_id! {}
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    hasher.hash(self.environment.is_some());
+    if let Some(environment) = &self.environment {
+      environment.hash_pipeline_with_type_info(hasher);
+    }
+  }
+}
+
+impl LightingComputeComponent for ReflectionProbeLightingComponent {
+  fn build_light_compute_invocation(
+    &self,
+    binding: &mut ShaderBindGroupBuilder,
+    scene_id: Node<u32>,
+  ) -> Box<dyn LightingComputeInvocation> {
+    Box::new(ReflectionProbeLightingInvocation {
+      environment: self
+        .environment
+        .as_ref()
+        .map(|e| e.build_light_compute_invocation(binding, scene_id)),
+      probes: binding.bind_by(&self.probes.uniforms),
+      cubes: binding.bind_by(&self.probes.cubes),
+      brdf_lut: binding.bind_by(&self.brdf_lut),
+      sampler: binding.bind_by(&ImmediateGPUSamplerViewBind),
+    })
+  }
+
+  fn setup_pass(&self, ctx: &mut BindingBuilder) {
+    if let Some(environment) = &self.environment {
+      environment.setup_pass(ctx);
+    }
+    ctx.bind(&self.probes.uniforms);
+    ctx.bind(&self.probes.cubes);
+    ctx.bind(&self.brdf_lut);
+    ctx.bind_immediate_sampler(&TextureSampler::default().with_double_linear().into_gpu());
+  }
+}
+
+struct ReflectionProbeLightingInvocation {
+  environment: Option<Box<dyn LightingComputeInvocation>>,
+  probes: ShaderReadonlyPtrOf<ReflectionProbeUniformArray>,
+  cubes: BindingNode<ShaderTextureCubeArray>,
+  brdf_lut: BindingNode<ShaderTexture2D>,
+  sampler: BindingNode<ShaderSampler>,
+}
+
+impl LightingComputeInvocation for ReflectionProbeLightingInvocation {
+  fn compute_lights(
+    &self,
+    shading: &dyn LightableSurfaceShading,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> ENode<ShaderLightingResult> {
+    let environment = self
+      .environment
+      .as_ref()
+      .map(|e| e.compute_lights(shading, geom_ctx))
+      .unwrap_or_else(|| zeroed_val::<ShaderLightingResult>().expand());
+
+    let Some(physical) = shading
+      .as_any()
+      .downcast_ref::<ENode<ShaderPhysicalShading>>()
+    else {
+      return environment;
+    };
+
+    let n_dot_v = geom_ctx.normal.dot(geom_ctx.view_dir);
+    let reflect = val(2.) * n_dot_v * geom_ctx.normal - geom_ctx.view_dir;
+    let lod =
+      physical.perceptual_roughness * (self.cubes.texture_number_levels() - val(1)).into_f32();
+
+    let weight_sum = val(0_f32).make_local_var();
+    let radiance = val(Vec3::<f32>::zero()).make_local_var();
+
+    self
+      .probes
+      .clone()
+      .into_shader_iter()
+      .for_each(|(_, probe), _| {
+        let probe = probe.load().expand();
+        let center = hpt_sub_hpt(
+          hpt_uniform_to_hpt(probe.center),
+          geom_ctx.camera_world_position,
+        );
+        let offset = geom_ctx.position - center;
+        let position = (probe.world_to_local * vec4_node((offset, val(0.)))).xyz();
+        let direction = (probe.world_to_local * vec4_node((reflect, val(0.)))).xyz();
+        let is_box = probe.shape.equals(REFLECTION_PROBE_SHAPE_BOX);
+
+        let distance = influence_volume_distance(position, probe.half_extent, is_box);
+
+        // the probes are sorted from small to large, the smaller ones take the weight first
+        let weight = (distance / probe.blend_distance.max(val(0.0001))).clamp(0., 1.);
+        let weight = weight.min(val(1.) - weight_sum.load());
+
+        if_by(weight.greater_than(0.), || {
+          // parallax correction: sample the probe by the direction from the capture position
+          // to the point where the reflection ray leaves the influence volume.
+          let hit = intersect_influence_volume(position, direction, is_box);
+          let sample_direction = (probe.local_to_world * vec4_node((hit, val(0.)))).xyz();
+
+          let sampled = self
+            .cubes
+            .build_sample_call(self.sampler, sample_direction)
+            .with_array_index(probe.cube_index)
+            .with_level(lod)
+            .sample()
+            .xyz();
+
+          radiance.store(radiance.load() + sampled * probe.intensity * weight);
+          weight_sum.store(weight_sum.load() + weight);
+        });
+      });
+
+    // the lut layout: x axis is perceptual roughness, y axis is n dot v
+    let brdf_lut = self
+      .brdf_lut
+      .sample_zero_level(self.sampler, (physical.perceptual_roughness, n_dot_v));
+    let probe_specular = (physical.f0 * brdf_lut.x() + brdf_lut.y().splat()) * radiance.load();
+
+    let weight_sum = weight_sum.load();
+    let specular = environment.specular * (val(1.) - weight_sum) + probe_specular;
+
+    ENode::<ShaderLightingResult> {
+      diffuse: environment.diffuse,
+      specular,
+    }
+  }
+}
+
+/// the world space distance from the local space `position` to the influence volume boundary,
+/// negative if outside
+fn influence_volume_distance(
+  position: Node<Vec3<f32>>,
+  half_extent: Node<Vec3<f32>>,
+  is_box: Node<bool>,
+) -> Node<f32> {
+  let box_distance = ((val(Vec3::one()) - position.abs()) * half_extent).min_channel();
+  let sphere_distance = (val(1.) - position.length()) * half_extent.min_channel();
+  is_box.select(box_distance, sphere_distance)
+}
+
+/// intersect the local space ray starting inside the influence volume with the volume
+/// boundary, return the local space hit position
+fn intersect_influence_volume(
+  position: Node<Vec3<f32>>,
+  direction: Node<Vec3<f32>>,
+  is_box: Node<bool>,
+) -> Node<Vec3<f32>> {
+  let inv_direction = val(Vec3::one()) / direction;
+  let t_positive = (val(Vec3::one()) - position) * inv_direction;
+  let t_negative = (-val(Vec3::one()) - position) * inv_direction;
+  let box_t = t_positive.max(t_negative).min_channel();
+
+  let a = direction.dot(direction);
+  let b = position.dot(direction);
+  let c = position.dot(position) - val(1.);
+  let sphere_t = (-b + (b * b - a * c).max(0.).sqrt()) / a;
+
+  let t = is_box.select(box_t, sphere_t);
+  position + direction * t
+}
+
+#[cfg(test)]
+mod tests {
+  use rendiation_shader_backend_cpu::*;
+
+  use super::*;
+
+  #[test]
+  fn test_collect_reflection_probes() {
+    setup_global_database(Default::default());
+    register_scene_core_data_model();
+
+    let mut writer = SceneWriter::from_global();
+    let scene_a = writer.scene_writer.new_entity(|w| w);
+    let scene_b = writer.scene_writer.new_entity(|w| w);
+
+    let mut node_world = FastHashMap::default();
+    let mut write_probe = |writer: &mut SceneWriter, scene, world, shape| {
+      let node = writer.create_root_child();
+      node_world.insert(node.into_raw(), world);
+      ReflectionProbeDataView {
+        shape,
+        blend_distance: 0.5,
+        intensity: 1.,
+        node,
+        scene,
+      }
+      .write(&mut writer.reflection_probe_writer)
+      .into_raw()
+    };
+
+    let large_box = write_probe(
+      &mut writer,
+      scene_a,
+      Mat4::translate((1., 2., 3.)) * Mat4::scale((4., 4., 4.)),
+      ReflectionProbeShapeType::Box,
+    );
+    let sphere = write_probe(
+      &mut writer,
+      scene_a,
+      Mat4::scale((2., 2., 2.)),
+      ReflectionProbeShapeType::Sphere,
+    );
+    let small_box = write_probe(
+      &mut writer,
+      scene_a,
+      Mat4::scale((1., 2., 0.5)),
+      ReflectionProbeShapeType::Box,
+    );
+    let other_scene_probe = write_probe(
+      &mut writer,
+      scene_b,
+      Mat4::identity(),
+      ReflectionProbeShapeType::Sphere,
+    );
+
+    // the probe without scene is ignored
+    let node = writer.create_root_child();
+    node_world.insert(node.into_raw(), Mat4::identity());
+    writer
+      .reflection_probe_writer
+      .new_entity(|w| w.write::<ReflectionProbeRefNode>(&node.some_handle()));
+    drop(writer);
+
+    let probes = collect_reflection_probes(&|node| node_world[&node]);
+    assert_eq!(probes.len(), 2);
+
+    // sorted by the influence volume: 8, 33.5, 512
+    let scene_a_probes: Vec<_> = probes[&scene_a.into_raw()]
+      .iter()
+      .map(|p| p.probe)
+      .collect();
+    assert_eq!(scene_a_probes, vec![small_box, sphere, large_box]);
+    let scene_b_probes = &probes[&scene_b.into_raw()];
+    assert_eq!(scene_b_probes.len(), 1);
+    assert_eq!(scene_b_probes[0].probe, other_scene_probe);
+
+    let large_box = &probes[&scene_a.into_raw()][2];
+    let uniform = large_box.uniform(3);
+    assert_eq!(uniform.cube_index, 3);
+    assert_eq!(uniform.shape, REFLECTION_PROBE_SHAPE_BOX);
+    assert_eq!(uniform.half_extent, Vec3::splat(4.));
+    assert_eq!(uniform.local_to_world, Mat4::scale((4., 4., 4.)));
+    assert_eq!(uniform.world_to_local, Mat4::scale((0.25, 0.25, 0.25)));
+    let small_box = &probes[&scene_a.into_raw()][0];
+    assert_eq!(small_box.uniform(0).half_extent, Vec3::new(1., 2., 0.5));
+  }
+
+  #[test]
+  fn test_influence_volume_distance_and_intersection() {
+    // (position, direction, half extent, is box, expected distance, expected hit)
+    let cases = [
+      (
+        Vec3::new(0., 0., 0.),
+        Vec3::new(1., 0., 0.),
+        Vec3::new(2., 1., 4.),
+        true,
+        1.,
+        Vec3::new(1., 0., 0.),
+      ),
+      (
+        Vec3::new(0.5, 0.5, 0.),
+        Vec3::new(3., 3., 0.),
+        Vec3::new(2., 1., 4.),
+        true,
+        0.5,
+        Vec3::new(1., 1., 0.),
+      ),
+      (
+        Vec3::new(0.75, 0., 0.),
+        Vec3::new(2., 1., 0.5),
+        Vec3::new(2., 1., 4.),
+        true,
+        0.5,
+        Vec3::new(1., 0.125, 0.0625),
+      ),
+      (
+        Vec3::new(0., 0., 0.),
+        Vec3::new(2., 1., 0.5),
+        Vec3::new(2., 1., 4.),
+        true,
+        1.,
+        Vec3::new(1., 0.5, 0.25),
+      ),
+      (
+        Vec3::new(1.5, 0., 0.),
+        Vec3::new(1., 0., 0.),
+        Vec3::new(2., 1., 4.),
+        true,
+        -1.,
+        Vec3::new(1., 0., 0.),
+      ),
+      (
+        Vec3::new(0., 0., 0.),
+        Vec3::new(0., 3., 0.),
+        Vec3::splat(2.),
+        false,
+        2.,
+        Vec3::new(0., 1., 0.),
+      ),
+      (
+        Vec3::new(0.5, 0., 0.),
+        Vec3::new(-1., 0., 0.),
+        Vec3::splat(2.),
+        false,
+        1.,
+        Vec3::new(-1., 0., 0.),
+      ),
+      (
+        Vec3::new(0., 0.6, 0.),
+        Vec3::new(1., 0., 0.),
+        Vec3::splat(2.),
+        false,
+        0.8,
+        Vec3::new(0.8, 0.6, 0.),
+      ),
+      (
+        Vec3::new(0., 2., 0.),
+        Vec3::new(0., -1., 0.),
+        Vec3::splat(2.),
+        false,
+        -2.,
+        Vec3::new(0., -1., 0.),
+      ),
+    ];
+
+    // each case is packed as position, direction, and the half extent with the box flag in w
+    let input: Vec<Vec4<f32>> = cases
+      .iter()
+      .flat_map(|&(position, direction, half_extent, is_box, _, _)| {
+        [
+          position.expand_with(0.),
+          direction.expand_with(0.),
+          half_extent.expand_with(if is_box { 1. } else { 0. }),
+        ]
+      })
+      .collect();
+
+    let input = CpuStorageBufferReadonlyDataView::<[Vec4<f32>]>::new(input.as_slice());
+    let distance = CpuStorageBufferDataView::<[f32]>::new(vec![0.; cases.len()].as_slice());
+    let hit =
+      CpuStorageBufferDataView::<[Vec4<f32>]>::new(vec![Vec4::zero(); cases.len()].as_slice());
+
+    let mut cx = cpu_compute_shader_builder().with_config_work_group_size(cases.len() as u32);
+    let input_node = cx.bind_by(&input);
+    let distance_node = cx.bind_by(&distance);
+    let hit_node = cx.bind_by(&hit);
+    let id = cx.global_invocation_id().x();
+    let position = input_node.index(id * val(3)).load().xyz();
+    let direction = input_node.index(id * val(3) + val(1)).load().xyz();
+    let half_extent = input_node.index(id * val(3) + val(2)).load();
+    let is_box = half_extent.w().greater_than(0.5);
+    distance_node.index(id).store(influence_volume_distance(
+      position,
+      half_extent.xyz(),
+      is_box,
+    ));
+    let hit_position = intersect_influence_volume(position, direction, is_box);
+    hit_node.index(id).store(vec4_node((hit_position, val(0.))));
+
+    let module = cx.create_cpu_module().unwrap();
+    let bindings = CpuShaderBindingBuilder::default()
+      .with_bind(&input)
+      .with_bind(&distance)
+      .with_bind(&hit);
+    module.dispatch(&bindings, (1, 1, 1)).unwrap();
+
+    let distance = distance.read().into_vec();
+    let hit = hit.read().into_vec();
+    for (i, (.., expect_distance, expect_hit)) in cases.iter().enumerate() {
+      assert!(
+        (distance[i] - expect_distance).abs() < 1e-5,
+        "case {i}: {}",
+        distance[i]
+      );
+      assert!(
+        (hit[i].xyz() - *expect_hit).length() < 1e-5,
+        "case {i}: {:?}",
+        hit[i]
+      );
+    }
+  }
+}
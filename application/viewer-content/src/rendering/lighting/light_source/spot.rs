@@ -48,6 +48,7 @@ fn use_basic_shadow_map_uniform(
     let shadow_enabled = get_db_view::<BasicShadowMapEnabledOf<SpotLightBasicShadowInfo>>();
     let shadow_map_size = get_db_view::<BasicShadowMapResolutionOf<SpotLightBasicShadowInfo>>();
     let shadow_bias = get_db_view::<BasicShadowMapBiasOf<SpotLightBasicShadowInfo>>();
+    let light_size = get_db_view::<BasicShadowMapLightSizeOf<SpotLightBasicShadowInfo>>();
     let world_mat = world_mat.expect_resolve_stage();
     let half_cone = get_db_view::<SpotLightHalfConeAngle>();
 
@@ -78,6 +79,7 @@ fn use_basic_shadow_map_uniform(
         proj,
         map_size: Size::from_u32_pair_min_one(size.into()),
         bias: bias.into(),
+        light_size: light_size.access(&light_id).unwrap(),
       }
       .into()
     };
@@ -106,6 +108,7 @@ impl SceneSpotLightingPreparer {
     frame_ctx: &mut FrameCtx,
     draw: &mut dyn FnMut(&mut FrameCtx, ShadowMapDrawRequest, EntityHandle<SceneEntity>),
     reversed_depth: bool,
+    only_shadowed: bool,
   ) -> SceneSpotLightingProvider {
     let mut draw = |f_ctx: &mut FrameCtx<'_>, param: ShadowMapDrawRequest| {
       let light_id = unsafe { EntityHandle::from_raw(param.light_id) };
@@ -133,6 +136,7 @@ impl SceneSpotLightingPreparer {
       shadow,
       reversed_depth,
       bias_behavior: self.bias_behavior,
+      only_shadowed,
     }
   }
 }
@@ -142,6 +146,8 @@ pub struct SceneSpotLightingProvider {
   uniform: LockReadGuardHolder<LightUniformInfo<SpotLightUniform>>,
   reversed_depth: bool,
   bias_behavior: ShadowBiasBehaviorConfig,
+  /// only shade the lights with shadow, the others are shaded by the clustered lighting
+  only_shadowed: bool,
 }
 
 impl LightSystemSceneProvider for SceneSpotLightingProvider {
@@ -167,7 +173,11 @@ impl LightSystemSceneProvider for SceneSpotLightingProvider {
       }
     });
 
-    Some(Box::new(SpotLightShader { lights, shadow }))
+    Some(Box::new(SpotLightShader {
+      lights,
+      shadow,
+      only_shadowed: self.only_shadowed,
+    }))
   }
 }
 
@@ -176,6 +186,7 @@ type UniformArray = UniformArrayWithLengthInfo<SpotLightUniform, LIGHT_LIST_LEN>
 #[derive(Clone)]
 struct SpotLightShader {
   lights: UniformBufferCachedDataView<UniformArray>,
+  only_shadowed: bool,
   shadow: Option<BasicShadowMapComponent>,
 }
 
@@ -183,6 +194,7 @@ impl ShaderHashProvider for SpotLightShader {
   shader_hash_type_id! {}
   fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
     hasher.hash(self.shadow.is_some());
+    hasher.hash(self.only_shadowed);
     if let Some(shadow) = &self.shadow {
       shadow.hash_pipeline(hasher);
     }
@@ -198,6 +210,7 @@ impl LightingComputeComponent for SpotLightShader {
     Box::new(SpotLightInvocation {
       lights: binding.bind_by(&self.lights),
       shadow: self.shadow.as_ref().map(|s| s.bind_shader(binding)),
+      only_shadowed: self.only_shadowed,
     })
   }
 
@@ -212,6 +225,7 @@ impl LightingComputeComponent for SpotLightShader {
 struct SpotLightInvocation {
   lights: ShaderReadonlyPtrOf<UniformArray>,
   shadow: Option<BasicShadowMapInvocation>,
+  only_shadowed: bool,
 }
 
 impl LightingComputeInvocation for SpotLightInvocation {
@@ -220,39 +234,47 @@ impl LightingComputeInvocation for SpotLightInvocation {
     shading: &dyn LightableSurfaceShading,
     geom_ctx: &ENode<ShaderLightingGeometricCtx>,
   ) -> ENode<ShaderLightingResult> {
-    light_iter_sum(self.lights.clone().into_shader_iter().map(
-      |(shadow_idx, light_ptr): (Node<u32>, ShaderReadonlyPtrOf<SpotLightUniform>)| {
-        let uniform = light_ptr.load().expand();
-        let light = ENode::<SpotLightShaderInfo> {
-          luminance_intensity: uniform.luminance_intensity,
-          position: hpt_uniform_to_hpt(uniform.position),
-          direction: uniform.direction,
-          cutoff_distance: uniform.cutoff_distance,
-          half_cone_cos: uniform.half_cone_cos,
-          half_penumbra_cos: uniform.half_penumbra_cos,
-        }
-        .construct();
-        let incident = light.compute_incident_light(geom_ctx);
+    let lights = self.lights.clone().into_shader_iter();
+    let shade = |(shadow_idx, light_ptr): (Node<u32>, ShaderReadonlyPtrOf<SpotLightUniform>)| {
+      let uniform = light_ptr.load().expand();
+      let light = ENode::<SpotLightShaderInfo> {
+        luminance_intensity: uniform.luminance_intensity,
+        position: hpt_uniform_to_hpt(uniform.position),
+        direction: uniform.direction,
+        cutoff_distance: uniform.cutoff_distance,
+        half_cone_cos: uniform.half_cone_cos,
+        half_penumbra_cos: uniform.half_penumbra_cos,
+      }
+      .construct();
+      let incident = light.compute_incident_light(geom_ctx);
+
+      let occlusion = match &self.shadow {
+        Some(s) => s.query_shadow_occlusion_by_idx(
+          geom_ctx.position,
+          geom_ctx.normal,
+          shadow_idx,
+          geom_ctx.fragment_position.xy(),
+          geom_ctx.camera_world_position,
+        ),
+        None => val(1.0),
+      };
 
-        let occlusion = match &self.shadow {
-          Some(s) => s.query_shadow_occlusion_by_idx(
-            geom_ctx.position,
-            geom_ctx.normal,
-            shadow_idx,
-            geom_ctx.fragment_position.xy(),
-            geom_ctx.camera_world_position,
-          ),
-          None => val(1.0),
-        };
+      shading.compute_lighting_by_incident(
+        &ENode::<ShaderIncidentLight> {
+          color: incident.color * occlusion,
+          direction: incident.direction,
+        },
+        geom_ctx,
+      )
+    };
 
-        shading.compute_lighting_by_incident(
-          &ENode::<ShaderIncidentLight> {
-            color: incident.color * occlusion,
-            direction: incident.direction,
-          },
-          geom_ctx,
-        )
-      },
-    ))
+    match &self.shadow {
+      Some(s) if self.only_shadowed => light_iter_sum(lights.map(|(shadow_idx, light_ptr)| {
+        s.is_shadow_enabled(shadow_idx)
+          .select_branched(|| shade((shadow_idx, light_ptr)).construct(), zeroed_val)
+          .expand()
+      })),
+      _ => light_iter_sum(lights.map(shade)),
+    }
   }
 }
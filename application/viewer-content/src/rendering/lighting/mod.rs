@@ -1,9 +1,11 @@
+mod clustered;
 mod debug_channels;
 mod light_pass;
 mod light_source;
 mod shadow;
 mod shadow_cascade;
 
+pub use clustered::*;
 use debug_channels::*;
 pub use light_pass::*;
 pub use light_source::*;
@@ -17,6 +19,7 @@ pub fn use_lighting(
   lighting_sys: &LightSystem,
   ndc: ViewerNDC,
   viewports: &[ViewerViewPort],
+  is_indirect_raster: bool,
 ) -> Option<LightingRenderingCxPrepareCtx> {
   let size = Size::from_u32_pair_min_one((2048, 2048));
   // note: the atlas is packed without padding, and the comparison sampler clamps
@@ -37,8 +40,16 @@ pub fn use_lighting(
   let dir_lights = use_directional_light_uniform(cx, &config, viewports, lighting_sys, ndc);
   let spot_lights = use_scene_spot_light_uniform(cx, &config, lighting_sys, ndc);
   let point_lights = use_scene_point_light_uniform(cx, &config, lighting_sys, ndc);
-  let area_lights = use_area_light_uniform(cx);
+  let area_lights = use_area_light_uniform(cx, &config, lighting_sys, ndc, is_indirect_raster);
   let ibl = use_ibl(cx);
+  let light_probes = use_light_probe_volumes(cx);
+  let reflection_probes = use_reflection_probes(cx);
+
+  let clustered = if lighting_sys.enable_clustered_lighting {
+    cx.scope(use_clustered_light_source)
+  } else {
+    None
+  };
 
   let scene_ids = use_scene_id_provider(cx);
 
@@ -48,6 +59,9 @@ pub fn use_lighting(
     point_lights: point_lights.unwrap(),
     area_lights: area_lights.unwrap(),
     ibl: ibl.unwrap(),
+    light_probes: light_probes.unwrap(),
+    reflection_probes: reflection_probes.unwrap(),
+    clustered,
     scene_ids,
   })
 }
@@ -56,8 +70,11 @@ pub struct LightingRenderingCxPrepareCtx {
   dir_lights: SceneDirectionalLightingPreparer,
   spot_lights: SceneSpotLightingPreparer,
   point_lights: ScenePointLightingPreparer,
-  area_lights: SceneAreaLightingProvider,
+  area_lights: SceneAreaLightingPreparer,
   ibl: IBLLightingComponentProvider,
+  light_probes: LightProbeVolumeProvider,
+  reflection_probes: SharedReflectionProbeHostData,
+  clustered: Option<ClusteredLightSource>,
   scene_ids: SceneIdUniformBufferAccess,
 }
 
@@ -91,12 +108,10 @@ impl LightSystem {
         ..
       } = request;
 
+      let camera_transform = CameraTransform::new(shadow_camera_proj, shadow_camera_world);
       let camera_uniform = UniformBufferDataView::create(
         &frame_ctx.gpu.device,
-        CameraGPUTransform::from(CameraTransform::new(
-          shadow_camera_proj,
-          shadow_camera_world,
-        )),
+        CameraGPUTransform::from(camera_transform),
         "camera for shadow",
       );
 
@@ -126,6 +141,14 @@ impl LightSystem {
             &frame_ctx.gpu.device,
             "lod error threshold",
           ),
+          host: LODCameraHostInfo {
+            camera: camera_transform,
+            view_resolution: Vec2::new(
+              map_desc.address.size.x as u32,
+              map_desc.address.size.y as u32,
+            ),
+            lod_error_threshold: lod_error_threshold_pixels,
+          },
         }));
 
         let content = renderer.use_make_scene_batch_pass_content(batch, frame_ctx);
@@ -143,28 +166,89 @@ impl LightSystem {
       .dir_lights
       .update_shadow_maps(frame_ctx, &mut content, reversed_depth);
 
-    let ss = instance
-      .spot_lights
-      .update_shadow_maps(frame_ctx, &mut content, reversed_depth);
+    let light_probe_host = instance.light_probes.host.clone();
+    let reflection_probe_host = instance.reflection_probes.clone();
+    let brdf_lut = instance.ibl.brdf_lut.clone();
+    let environment = Box::new(LightProbeDiffuseLightingProvider {
+      environment: Box::new(instance.ibl),
+      probes: instance.light_probes,
+    });
+    let environment = Box::new(ReflectionProbeSpecularLightingProvider {
+      environment,
+      probes: instance.reflection_probes,
+      brdf_lut,
+    });
 
-    let ps = instance
-      .point_lights
-      .update_shadow_maps(frame_ctx, &mut content, reversed_depth);
+    let (imp, clustered) = if let Some(source) = instance.clustered {
+      let area_light_shadowed = self.enable_shadow && self.enable_area_light_shadow;
+      let shadowed_light_kinds =
+        clustered_shadowed_light_kinds(self.enable_shadow, area_light_shadowed);
+      let clustered = ClusteredLightingProvider {
+        source,
+        ltc_1: instance.area_lights.ltc_1.clone(),
+        ltc_2: instance.area_lights.ltc_2.clone(),
+        config: self.clustered_lighting_config,
+        shadowed_light_kinds,
+        debug_light_count: self.enable_channel_debugger,
+        grids: Default::default(),
+      };
+
+      // the shadowed lights are excluded from the clustering, and shaded with their shadow maps
+      let mut lights: Vec<Box<dyn LightSystemSceneProvider>> =
+        vec![ds, Box::new(clustered.clone())];
+      if self.enable_shadow {
+        let ss =
+          instance
+            .spot_lights
+            .update_shadow_maps(frame_ctx, &mut content, reversed_depth, true);
+        let ps =
+          instance
+            .point_lights
+            .update_shadow_maps(frame_ctx, &mut content, reversed_depth, true);
+        lights.push(Box::new(ss));
+        lights.push(Box::new(ps));
+      }
+      if area_light_shadowed {
+        lights.push(instance.area_lights.update_shadow_maps(
+          frame_ctx,
+          &mut content,
+          reversed_depth,
+          true,
+        ));
+      }
+      lights.push(environment);
 
-    let imp = Box::new(LightingComputeComponentGroupProvider {
-      lights: vec![
-        ds,
-        Box::new(ss),
-        Box::new(ps),
-        Box::new(instance.area_lights),
-        Box::new(instance.ibl),
-      ],
-    });
+      let imp = Box::new(LightingComputeComponentGroupProvider { lights });
+      (imp, Some(clustered))
+    } else {
+      let ss =
+        instance
+          .spot_lights
+          .update_shadow_maps(frame_ctx, &mut content, reversed_depth, false);
+
+      let ps =
+        instance
+          .point_lights
+          .update_shadow_maps(frame_ctx, &mut content, reversed_depth, false);
+
+      let as_ =
+        instance
+          .area_lights
+          .update_shadow_maps(frame_ctx, &mut content, reversed_depth, false);
+
+      let imp = Box::new(LightingComputeComponentGroupProvider {
+        lights: vec![ds, Box::new(ss), Box::new(ps), as_, environment],
+      });
+      (imp, None)
+    };
 
     let sys = SceneLightSystem {
       scene_ids: instance.scene_ids,
       system: self,
       imp,
+      clustered,
+      light_probe_host,
+      reflection_probe_host,
     };
 
     LightingRenderingCx {
@@ -211,6 +295,8 @@ pub struct LightSystem {
   pub material_defer_lighting_supports: DeferLightingMaterialRegistry,
   pub opaque_scene_content_lighting_technique: LightingTechniqueKind,
   pub enable_shadow: bool,
+  /// see [ViewerInitConfig::enable_area_light_shadow]
+  pub enable_area_light_shadow: bool,
   pub use_cascade_shadowmap_for_directional_lights: bool,
   pub cascade_shadow_split_linear_log_blend_ratio: f32,
   pub filter_ty: ViewerShadowFilterType,
@@ -218,6 +304,9 @@ pub struct LightSystem {
   pub pcf_config: ShadowPCFConfig,
   pub bias_behavior: ShadowBiasBehaviorConfig,
   pub filter_across_cascades: bool,
+  /// see [ClusteredLightingConfig]
+  pub enable_clustered_lighting: bool,
+  pub clustered_lighting_config: ClusteredLightingConfig,
 }
 
 impl LightSystem {
@@ -226,11 +315,19 @@ impl LightSystem {
       lighting_surface_ty: init_config.light_surface_ty.create_impl(),
       lighting_surface_ty_value: init_config.light_surface_ty,
       enable_shadow: init_config.enable_shadow,
+      enable_area_light_shadow: init_config.enable_area_light_shadow,
       enable_channel_debugger: false,
       cascade_shadow_split_linear_log_blend_ratio: 0.95,
       channel_debugger: ScreenChannelDebugger::default_useful(),
       use_cascade_shadowmap_for_directional_lights: false,
-      pcf_config: ShadowPCFConfig::default(),
+      pcf_config: ShadowPCFConfig {
+        pcf_mode: if init_config.enable_pcss_shadow {
+          ShadowPCFMode::PCSS
+        } else {
+          ShadowPCFConfig::default().pcf_mode
+        },
+        ..Default::default()
+      },
       vsm_config: Default::default(),
       bias_behavior: ShadowBiasBehaviorConfig::default(),
       filter_across_cascades: false,
@@ -241,6 +338,8 @@ impl LightSystem {
         .register_material_impl::<PhongSurfaceEncodeDecode>(),
       opaque_scene_content_lighting_technique: LightingTechniqueKind::Forward,
       filter_ty: Default::default(),
+      enable_clustered_lighting: false,
+      clustered_lighting_config: Default::default(),
     }
   }
 
@@ -313,6 +412,7 @@ impl LightSystem {
             ShadowPCFMode::RandomDiscPCF,
             "RandomDiscPCF",
           );
+          ui.selectable_value(&mut self.pcf_config.pcf_mode, ShadowPCFMode::PCSS, "PCSS");
         });
 
       if matches!(self.pcf_config.pcf_mode, ShadowPCFMode::FixedSizePCF) {
@@ -353,7 +453,10 @@ impl LightSystem {
         );
       }
 
-      if matches!(self.pcf_config.pcf_mode, ShadowPCFMode::RandomDiscPCF) {
+      if matches!(
+        self.pcf_config.pcf_mode,
+        ShadowPCFMode::RandomDiscPCF | ShadowPCFMode::PCSS
+      ) {
         ui.add(
           egui::Slider::new(&mut self.pcf_config.num_disc_samples, 4..=64)
             .step_by(4.0)
@@ -372,6 +475,19 @@ impl LightSystem {
       "nDotL normal offset",
     );
 
+    ui.checkbox(
+      &mut self.enable_area_light_shadow,
+      format!("area light shadow (PCSS, at most {LIGHT_LIST_LEN} area lights per scene)"),
+    );
+
+    ui.checkbox(
+      &mut self.enable_clustered_lighting,
+      "clustered lighting (the shadowed lights are not clustered)",
+    );
+    if self.enable_clustered_lighting {
+      self.clustered_lighting_config.egui(ui);
+    }
+
     let old = self.lighting_surface_ty_value;
     egui::ComboBox::from_label("Light surface ty")
       .selected_text(format!("{:?}", self.lighting_surface_ty_value))
@@ -423,9 +539,47 @@ pub struct SceneLightSystem<'a> {
   pub(crate) system: &'a LightSystem,
   scene_ids: SceneIdUniformBufferAccess,
   imp: Box<dyn LightSystemSceneProvider>,
+  clustered: Option<ClusteredLightingProvider>,
+  /// the probe volumes of each scene, used to schedule the light probe baking
+  pub light_probe_host: SharedLightProbeVolumeHostData,
+  /// the reflection probes of each scene, used to schedule the probe capturing
+  pub reflection_probe_host: SharedReflectionProbeHostData,
 }
 
 impl SceneLightSystem<'_> {
+  /// should be called before the scene content of this view is drawn
+  pub fn use_update_clustered_light_grid(
+    &self,
+    ctx: &mut FrameCtx,
+    scene: EntityHandle<SceneEntity>,
+    camera: EntityHandle<SceneCameraEntity>,
+    camera_gpu: &CameraGPU,
+    camera_transform: &CameraTransform,
+    reversed_depth: bool,
+  ) {
+    ctx.next_scope_index();
+    let Some(clustered) = &self.clustered else {
+      return;
+    };
+    let scene_id = self.scene_ids.get(&scene.into_raw()).unwrap().clone();
+    let view_size = ctx.frame_size;
+
+    ctx.access_parallel_compute(|cx| {
+      cx.scope(|cx| {
+        clustered.use_update_grid(
+          cx,
+          scene,
+          &scene_id,
+          camera,
+          camera_gpu,
+          camera_transform,
+          view_size,
+          reversed_depth,
+        );
+      })
+    });
+  }
+
   pub fn get_scene_forward_lighting_component(
     &self,
     scene: EntityHandle<SceneEntity>,
@@ -439,6 +593,26 @@ impl SceneLightSystem<'_> {
     )
   }
 
+  /// the forward lighting for capturing the scene radiance, for example the reflection probe
+  /// capture. the output is not tone mapped.
+  pub fn get_scene_capture_lighting_component(
+    &self,
+    scene: EntityHandle<SceneEntity>,
+    camera: EntityHandle<SceneCameraEntity>,
+  ) -> Box<dyn RenderComponent + '_> {
+    let mut light = RenderVec::default();
+    light.push(LDROutput);
+    self.push_lighting(
+      &mut light,
+      scene,
+      camera,
+      &NoneToneMap,
+      Box::new(DirectGeometryProvider),
+      self.system.lighting_surface_ty.as_ref(),
+    );
+    Box::new(light)
+  }
+
   pub fn get_scene_lighting_component<'a>(
     &'a self,
     scene: EntityHandle<SceneEntity>,
@@ -456,10 +630,31 @@ impl SceneLightSystem<'_> {
       light.push(LDROutput);
     }
 
+    self.push_lighting(
+      &mut light,
+      scene,
+      camera,
+      &system.tonemap,
+      geometry_constructor,
+      surface_constructor,
+    );
+
+    Box::new(light)
+  }
+
+  fn push_lighting<'a>(
+    &'a self,
+    light: &mut RenderVec<'a>,
+    scene: EntityHandle<SceneEntity>,
+    camera: EntityHandle<SceneCameraEntity>,
+    tonemap: &'a dyn RenderComponent,
+    geometry_constructor: Box<dyn GeometryCtxProvider + 'a>,
+    surface_constructor: &'a (dyn LightableSurfaceProvider + 'a),
+  ) {
     let scene_id = self.scene_ids.get(&scene.into_raw()).unwrap().clone();
 
     light
-      .push(&system.tonemap as &dyn RenderComponent)
+      .push(tonemap)
       // if we can not do a single draw for all light, this should not be used!
       // because the emissive will be added multiple times
       .push(&ForwardLightingEmissiveAdd as &dyn RenderComponent)
@@ -469,8 +664,22 @@ impl SceneLightSystem<'_> {
         surface_constructor,
         lighting: self.imp.get_scene_lighting(scene, camera).unwrap(),
       });
+  }
+}
 
-    Box::new(light)
+/// output the hdr result directly as the ldr result
+pub struct NoneToneMap;
+
+impl ShaderHashProvider for NoneToneMap {
+  shader_hash_type_id! {}
+}
+impl ShaderPassBuilder for NoneToneMap {}
+impl GraphicsShaderProvider for NoneToneMap {
+  fn post_build(&self, builder: &mut ShaderRenderPipelineBuilder) {
+    builder.fragment(|builder, _| {
+      let hdr = builder.query::<HDRLightResult>();
+      builder.register::<LDRLightResult>(hdr);
+    })
   }
 }
 
@@ -1,6 +1,9 @@
 use crate::*;
 
-pub trait BasicShadowMapConfigurable: EntityAssociateSemantic {}
+pub trait BasicShadowMapConfigurable: EntityAssociateSemantic {
+  /// see [BasicShadowMapLightSizeOf]
+  const DEFAULT_LIGHT_SIZE: f32 = 0.1;
+}
 
 pub struct BasicShadowMapResolutionOf<T>(T);
 impl<T: BasicShadowMapConfigurable> EntityAssociateSemantic for BasicShadowMapResolutionOf<T> {
@@ -59,6 +62,20 @@ impl<T: BasicShadowMapConfigurable> ComponentSemantic for BasicShadowMapEnabledO
   }
 }
 
+/// the light source size used by the PCSS filtering to estimate the penumbra size. for the
+/// directional light it's the tangent of the angular diameter of the light source, for the other
+/// lights it's the world space width of the light source.
+pub struct BasicShadowMapLightSizeOf<T>(T);
+impl<T: BasicShadowMapConfigurable> EntityAssociateSemantic for BasicShadowMapLightSizeOf<T> {
+  type Entity = T::Entity;
+}
+impl<T: BasicShadowMapConfigurable> ComponentSemantic for BasicShadowMapLightSizeOf<T> {
+  type Data = f32;
+  fn default_override() -> Self::Data {
+    T::DEFAULT_LIGHT_SIZE
+  }
+}
+
 pub fn register_basic_shadow_map_for_light<T: BasicShadowMapConfigurable>(
   table: TypedArcTable<T::Entity>,
 ) -> TypedArcTable<T::Entity> {
@@ -66,6 +83,7 @@ pub fn register_basic_shadow_map_for_light<T: BasicShadowMapConfigurable>(
     .declare_component::<BasicShadowMapResolutionOf<T>>()
     .declare_component::<BasicShadowMapBiasOf<T>>()
     .declare_component::<BasicShadowMapEnabledOf<T>>()
+    .declare_component::<BasicShadowMapLightSizeOf<T>>()
 }
 
 declare_component!(
@@ -75,7 +93,10 @@ declare_component!(
 ); // in meter
 
 declare_entity_associated!(DirectionLightBasicShadowInfo, DirectionalLightEntity);
-impl BasicShadowMapConfigurable for DirectionLightBasicShadowInfo {}
+impl BasicShadowMapConfigurable for DirectionLightBasicShadowInfo {
+  // roughly the angular diameter of the sun
+  const DEFAULT_LIGHT_SIZE: f32 = 0.01;
+}
 
 declare_entity_associated!(SpotLightBasicShadowInfo, SpotLightEntity);
 impl BasicShadowMapConfigurable for SpotLightBasicShadowInfo {}
@@ -83,6 +104,11 @@ impl BasicShadowMapConfigurable for SpotLightBasicShadowInfo {}
 declare_entity_associated!(PointLightBasicShadowInfo, PointLightEntity);
 impl BasicShadowMapConfigurable for PointLightBasicShadowInfo {}
 
+// the area light is shadowed by a perspective shadow map rendered from the light center, the
+// light size is not used because the PCSS light size is derived from the area light size.
+declare_entity_associated!(AreaLightBasicShadowInfo, AreaLightEntity);
+impl BasicShadowMapConfigurable for AreaLightBasicShadowInfo {}
+
 pub fn register_light_shadow_config() {
   let directional_light =
     global_entity_of::<DirectionalLightEntity>().declare_component::<DirectionLightShadowBound>();
@@ -93,4 +119,7 @@ pub fn register_light_shadow_config() {
 
   let point_light = global_entity_of::<PointLightEntity>();
   register_basic_shadow_map_for_light::<PointLightBasicShadowInfo>(point_light);
+
+  let area_light = global_entity_of::<AreaLightEntity>();
+  register_basic_shadow_map_for_light::<AreaLightBasicShadowInfo>(area_light);
 }
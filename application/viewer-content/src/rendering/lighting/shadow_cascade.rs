@@ -42,6 +42,7 @@ pub fn use_cascade_shadow_map(
     let shadow_map_size =
       get_db_view::<BasicShadowMapResolutionOf<DirectionLightBasicShadowInfo>>();
     let shadow_bias = get_db_view::<BasicShadowMapBiasOf<DirectionLightBasicShadowInfo>>();
+    let light_size = get_db_view::<BasicShadowMapLightSizeOf<DirectionLightBasicShadowInfo>>();
     let shadow_proj = get_db_view::<DirectionLightShadowBound>();
     let source_world = source_world.expect_resolve_stage();
 
@@ -65,6 +66,7 @@ pub fn use_cascade_shadow_map(
         size: Size::from_u32_pair_min_one(size.into()),
         bias: bias.into(),
         shadow_enabled: true,
+        light_size: light_size.access(&light_id).unwrap(),
       })
     };
 
@@ -8,6 +8,7 @@ mod lighting;
 mod ndc;
 mod outline;
 mod ray_tracing;
+mod shader_debug_print;
 mod transparent;
 
 mod g_buffer;
@@ -16,6 +17,7 @@ pub use culling::*;
 pub use frame_all::*;
 pub use g_buffer::*;
 pub use ray_tracing::*;
+pub use shader_debug_print::*;
 pub use transparent::*;
 
 mod post;
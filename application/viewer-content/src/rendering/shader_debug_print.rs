@@ -0,0 +1,87 @@
+use std::task::Context;
+
+use rendiation_shader_debug_print::*;
+
+use crate::*;
+
+type ShaderDebugPrintReadBack =
+  Pin<Box<dyn Future<Output = Result<ShaderDebugPrintResult, BufferAsyncError>>>>;
+
+/// The frame level shader debug print buffer. The renderer use [Self::device] to build the
+/// printer and bind the buffer, the decoded records are collected and drained by the console.
+pub struct ViewerShaderDebugPrint {
+  print: ShaderDebugPrint,
+  filter: ShaderDebugPrintFilter,
+  pending: Option<ShaderDebugPrintReadBack>,
+  output: Vec<String>,
+}
+
+impl ViewerShaderDebugPrint {
+  pub fn new(gpu: &GPU) -> Self {
+    let filter = ShaderDebugPrintFilter::Disabled;
+    Self {
+      print: ShaderDebugPrint::new(&gpu.device, 64 * 1024, filter),
+      filter,
+      pending: None,
+      output: Vec::new(),
+    }
+  }
+
+  pub fn device(&self) -> &ShaderDebugPrint {
+    &self.print
+  }
+
+  pub fn set_filter(&mut self, filter: ShaderDebugPrintFilter, gpu: &GPU) {
+    self.filter = filter;
+    self.print.set_filter(filter, &gpu.queue);
+  }
+
+  pub(crate) fn begin_frame(&mut self, encoder: &mut GPUCommandEncoder) {
+    if self.filter != ShaderDebugPrintFilter::Disabled && self.pending.is_none() {
+      self.print.reset(encoder);
+    }
+  }
+
+  pub(crate) fn end_frame(&mut self, encoder: &mut GPUCommandEncoder, device: &GPUDevice) {
+    if self.filter != ShaderDebugPrintFilter::Disabled && self.pending.is_none() {
+      self.pending = Some(Box::pin(self.print.read_back(encoder, device)));
+    }
+  }
+
+  pub fn poll(&mut self, cx: &mut Context) {
+    if let Some(pending) = &mut self.pending
+      && let Poll::Ready(result) = pending.poll_unpin(cx)
+    {
+      self.pending = None;
+      match result {
+        Ok(result) => {
+          self
+            .output
+            .extend(result.records.iter().map(|r| r.to_string()));
+          if result.truncated {
+            self
+              .output
+              .push("shader debug print buffer is full, some records are dropped".to_string());
+          }
+        }
+        Err(e) => log::error!("failed to read back shader debug print: {e:?}"),
+      }
+    }
+  }
+
+  pub fn take_output(&mut self) -> Vec<String> {
+    std::mem::take(&mut self.output)
+  }
+}
+
+/// parse the terminal parameters: `off`, `all`, `pixel x y` or `invocation x y z`
+pub fn parse_shader_debug_print_filter(parameters: &[String]) -> Option<ShaderDebugPrintFilter> {
+  let numbers: Option<Vec<u32>> = parameters.iter().skip(1).map(|v| v.parse().ok()).collect();
+  Some(match (parameters.first()?.as_str(), numbers?.as_slice()) {
+    ("off", []) => ShaderDebugPrintFilter::Disabled,
+    ("all", []) => ShaderDebugPrintFilter::All,
+    ("pixel", [x, y]) => ShaderDebugPrintFilter::Pixel(Vec2::new(*x, *y)),
+    ("invocation", [x, y, z]) => ShaderDebugPrintFilter::Invocation(Vec3::new(*x, *y, *z)),
+    _ => return None,
+  })
+}
@@ -204,6 +204,7 @@ impl RenderingRoot {
 
     noop_ctx!(cx);
     self.statistics.poll(cx);
+    rendering.shader_debug_print.poll(cx);
   }
 
   pub fn egui(
@@ -208,4 +208,13 @@ pub fn register_default_commands(terminal: &mut Terminal) {
     );
     gpu.clear_resource_cache();
   });
+
+  terminal.register_sync_command("shader-debug-print", |ctx, parameters| {
+    if let Some(filter) = parse_shader_debug_print_filter(&parameters[1..]) {
+      let gpu = ctx.renderer.gpu().clone();
+      ctx.renderer.shader_debug_print.set_filter(filter, &gpu);
+    } else {
+      log::error!("usage: shader-debug-print off | all | pixel <x> <y> | invocation <x> <y> <z>");
+    }
+  });
 }
@@ -18,6 +18,7 @@ pub fn create_gpu_tex_from_png_buffer(
       data: buf,
       format,
       size: Size::from_u32_pair_min_one((width, height)),
+      precomputed_mips: Vec::new(),
     },
   )
 }
@@ -42,6 +43,7 @@ pub fn create_gpu_texture_by_fn(
     data,
     format: TextureFormat::Rgba8UnormSrgb,
     size,
+    precomputed_mips: Vec::new(),
   }
 }
 
@@ -153,6 +153,7 @@ impl winit::application::ApplicationHandler for WinitAppImpl {
         },
         enable_backend_validation: self.config.enable_backend_validation,
         dx_compiler_dll_path: self.config.dx_compiler_dll_path.clone(),
+        persistent_pipeline_cache: self.config.persistent_pipeline_cache.clone(),
         display: Some(Box::new(event_loop.owned_display_handle())),
         ..Default::default()
       };
@@ -50,6 +50,8 @@ fn main() {
     use_viewer_egui(cx);
 
     use_enable_screenshot(cx);
+    use_enable_light_probe_bake(cx);
+    use_enable_reflection_probe_capture(cx);
 
     stage_of_update(cx, 2, |cx| {
       // todo, support group
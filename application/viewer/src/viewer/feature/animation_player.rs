@@ -7,6 +7,10 @@ pub fn use_animation_player(cx: &mut ViewerCx) {
     .use_db_rev_ref::<SceneAnimationChannelBelongToAnimation>()
     .use_assure_result(cx);
 
+  let model_of_node = cx
+    .use_db_rev_ref::<SceneModelRefNode>()
+    .use_assure_result(cx);
+
   let (cx, mutation) = cx.use_plain_state::<Option<SceneAnimationMutation>>();
 
   let (cx, active_animations) =
@@ -15,10 +19,12 @@ pub fn use_animation_player(cx: &mut ViewerCx) {
   match &mut cx.stage {
     ViewerCxStage::EventHandling { .. } => {
       let channel_of_animation = channel_of_animation.expect_resolve_stage();
+      let model_of_node = model_of_node.expect_resolve_stage();
 
       let m = compute_mutation(
         active_animations,
         channel_of_animation,
+        model_of_node,
         cx.absolute_seconds_from_start,
       );
       *mutation = Some(m);
@@ -68,10 +74,13 @@ pub fn use_animation_player(cx: &mut ViewerCx) {
 fn compute_mutation(
   active_animations: &mut FastHashSet<EntityHandle<SceneAnimationEntity>>,
   channel_of_animation: RevRefForeignKeyRead,
+  model_of_node: RevRefForeignKeyRead,
   absolute_world_time_in_sec: f32,
 ) -> SceneAnimationMutation {
   let channel_of_animation =
     channel_of_animation.mark_foreign_key::<SceneAnimationChannelBelongToAnimation>();
+  let model_of_node = model_of_node.mark_foreign_key::<SceneModelRefNode>();
+  let std_model_of_model = read_global_db_foreign_key::<SceneModelStdModelRenderPayload>();
 
   let channel_reader = global_entity_of::<SceneAnimationChannelEntity>().entity_reader();
   let buffer_reader = read_global_db_component::<BufferEntityData>();
@@ -79,6 +88,7 @@ fn compute_mutation(
   let output_read = SceneBufferViewReadView::<SceneAnimationChannelOutput>::new_from_global();
 
   let mut mutations = Vec::new();
+  let mut morph_target_weights = Vec::new();
   let target = read_global_db_foreign_key::<SceneAnimationChannelTargetNode>();
   let mut to_remove = Vec::new();
   for animation in active_animations.iter() {
@@ -103,6 +113,19 @@ fn compute_mutation(
         let action = new_sampler
           .sample_animation(absolute_world_time_in_sec)
           .unwrap();
+
+        // the morph target weights is applied to the models of the target node
+        if let InterpolationItem::MorphTargetWeights(weights) = action {
+          if let Some(models) = model_of_node.access_multi(&target) {
+            for model in models {
+              if let Some(std_model) = std_model_of_model.get(model) {
+                morph_target_weights.push((weights, std_model));
+              }
+            }
+          }
+          continue;
+        }
+
         mutations.push((action, target))
       }
     } else {
@@ -113,14 +136,26 @@ fn compute_mutation(
     active_animations.remove(&animation);
   }
 
-  SceneAnimationMutation(mutations)
+  SceneAnimationMutation {
+    node_transforms: mutations,
+    morph_target_weights,
+  }
 }
 
-struct SceneAnimationMutation(Vec<(InterpolationItem, EntityHandle<SceneNodeEntity>)>);
+struct SceneAnimationMutation {
+  node_transforms: Vec<(InterpolationItem, EntityHandle<SceneNodeEntity>)>,
+  morph_target_weights: Vec<(MorphTargetWeights, EntityHandle<StandardModelEntity>)>,
+}
 
 impl SceneAnimationMutation {
   pub fn apply(self, scene: &mut SceneWriter) {
-    for (action, target) in self.0 {
+    for (weights, target) in self.morph_target_weights {
+      scene
+        .std_model_writer
+        .write::<StandardModelMorphTargetWeights>(target, Some(weights));
+    }
+
+    for (action, target) in self.node_transforms {
       let target_node_mat = scene
         .node_writer
         .try_read::<SceneNodeLocalMatrixComponent>(target)
@@ -136,9 +171,7 @@ impl SceneAnimationMutation {
         InterpolationItem::Quaternion(quat) => {
           rotation = quat.into_f64();
         }
-        InterpolationItem::MorphTargetWeights(_) => {
-          // not supported yet
-        }
+        InterpolationItem::MorphTargetWeights(_) => continue,
       }
       let new_mat = Mat4::compose(position, rotation, scale);
       scene.set_local_matrix(target, new_mat);
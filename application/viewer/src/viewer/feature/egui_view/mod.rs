@@ -4,12 +4,14 @@ mod console;
 mod db_view;
 mod inspector;
 mod object_inspect;
+mod query_graph;
 mod tile;
 
 pub use console::*;
 use db_view::*;
 pub use inspector::*;
 pub use object_inspect::*;
+use query_graph::*;
 pub use tile::*;
 
 pub struct ViewerUIState {
@@ -19,8 +21,10 @@ pub struct ViewerUIState {
   show_gpu_info: bool,
   show_memory_stat: bool,
   show_frame_info: bool,
+  show_query_graph: bool,
   object_inspection: bool,
   egui_db_inspector: DBInspector,
+  query_graph_panel: QueryGraphDebugPanel,
 }
 
 impl Default for ViewerUIState {
@@ -32,8 +36,10 @@ impl Default for ViewerUIState {
       show_frame_info: false,
       show_gpu_info: false,
       show_memory_stat: false,
+      show_query_graph: false,
       object_inspection: false,
       egui_db_inspector: Default::default(),
+      query_graph_panel: Default::default(),
     }
   }
 }
@@ -69,6 +75,7 @@ pub fn use_viewer_egui(cx: &mut ViewerCx) {
         ui.checkbox(&mut ui_state.show_gpu_info, "gpu info");
         ui.checkbox(&mut ui_state.show_frame_info, "frame info");
         ui.checkbox(&mut ui_state.show_memory_stat, "heap stat");
+        ui.checkbox(&mut ui_state.show_query_graph, "query graph");
       });
     });
 
@@ -243,6 +250,9 @@ pub fn use_viewer_egui(cx: &mut ViewerCx) {
         console.writeln(output);
       },
     );
+    for output in viewer.rendering.shader_debug_print.take_output() {
+      console.writeln(output);
+    }
 
     if ui_state.object_inspection {
       egui::Window::new("Object Inspection")
@@ -259,6 +269,13 @@ pub fn use_viewer_egui(cx: &mut ViewerCx) {
       &mut ui_state.egui_db_inspector,
       &mut ui_state.show_db_inspector,
     );
+
+    egui_query_graph_gui(
+      ui,
+      &mut ui_state.query_graph_panel,
+      cx.viewer.shared_ctx.query_graph_recorder(),
+      &mut ui_state.show_query_graph,
+    );
   }
 }
 
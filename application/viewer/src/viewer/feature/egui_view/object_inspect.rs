@@ -139,6 +139,13 @@ pub fn inspect_selected(ui: &mut egui::Ui, selection: &mut ViewerSelectionStates
       target,
       "shadow enabled",
     );
+    ui.label("shadow light size (PCSS):");
+    modify_ranged_value_like_slider_com::<BasicShadowMapLightSizeOf<DirectionLightBasicShadowInfo>>(
+      ui,
+      w,
+      target,
+      0.0..=0.1,
+    );
     //
   } else if let Some(target) = selection.selected_spot_light {
     let w = &mut scene_writer.spot_light_writer;
@@ -171,6 +178,13 @@ pub fn inspect_selected(ui: &mut egui::Ui, selection: &mut ViewerSelectionStates
       target,
       "shadow enabled",
     );
+    ui.label("shadow light size (PCSS):");
+    modify_ranged_value_like_slider_com::<BasicShadowMapLightSizeOf<SpotLightBasicShadowInfo>>(
+      ui,
+      w,
+      target,
+      0.0..=1.,
+    );
     //
   } else if let Some(target) = selection.selected_point_light {
     let w = &mut scene_writer.point_light_writer;
@@ -181,6 +195,20 @@ pub fn inspect_selected(ui: &mut egui::Ui, selection: &mut ViewerSelectionStates
 
     ui.label("spotlight cutoff distance:");
     modify_ranged_value_like_slider_com::<PointLightCutOffDistance>(ui, w, target, 0.0..=10.);
+
+    modify_bool_com::<BasicShadowMapEnabledOf<PointLightBasicShadowInfo>>(
+      ui,
+      w,
+      target,
+      "shadow enabled",
+    );
+    ui.label("shadow light size (PCSS):");
+    modify_ranged_value_like_slider_com::<BasicShadowMapLightSizeOf<PointLightBasicShadowInfo>>(
+      ui,
+      w,
+      target,
+      0.0..=1.,
+    );
   } else {
     ui.label("No target selected");
   }
@@ -0,0 +1,210 @@
+use egui_extras::{Column, TableBuilder};
+
+use crate::*;
+
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+enum QueryGraphSortBy {
+  #[default]
+  Label,
+  LogicTime,
+  TaskTime,
+  DeltaSize,
+}
+
+#[derive(Default)]
+pub struct QueryGraphDebugPanel {
+  only_executed: bool,
+  sort_by: QueryGraphSortBy,
+  label_filter: String,
+  /// the focused node, show its dependencies and consumers
+  focused: Option<String>,
+  /// the result message of the last export
+  export_message: Option<String>,
+}
+
+/// Return the message of the export result, the failure is also logged.
+fn export_query_graph(file_name: &str, content: String) -> String {
+  #[cfg(not(target_family = "wasm"))]
+  {
+    let result = std::env::current_dir().and_then(|dir| {
+      let path = dir.join(file_name);
+      std::fs::write(&path, content).map(|_| path)
+    });
+    match result {
+      Ok(path) => {
+        log::info!("query graph exported to {:?}", path);
+        format!("exported to {}", path.display())
+      }
+      Err(e) => {
+        log::error!("failed to export query graph {file_name}: {e}");
+        format!("failed to export {file_name}: {e}")
+      }
+    }
+  }
+  #[cfg(target_family = "wasm")]
+  {
+    log::info!("{file_name}:\n{content}");
+    format!("{file_name} is printed to the log")
+  }
+}
+
+pub fn egui_query_graph_gui(
+  ui: &mut egui::Ui,
+  panel: &mut QueryGraphDebugPanel,
+  recorder: &QueryGraphRecorder,
+  opened: &mut bool,
+) {
+  // the recording has cost, only enable it when the panel is opened
+  if recorder.is_enabled() != *opened {
+    recorder.set_enabled(*opened);
+  }
+
+  egui::Window::new("Query Graph")
+    .open(opened)
+    .vscroll(true)
+    .default_width(600.)
+    .show(ui, |ui| {
+      let Some(frame) = recorder.last_frame() else {
+        ui.label("nothing recorded yet");
+        return;
+      };
+
+      ui.horizontal_wrapped(|ui| {
+        ui.label(format!("frame: {}", frame.frame_index));
+        ui.label(format!("shared ctx: {}", frame.nodes.len()));
+        let executed = frame.nodes.iter().filter(|n| n.execute_count > 0).count();
+        ui.label(format!("executed: {}", executed));
+        let logic_time: f32 = frame.nodes.iter().map(|n| n.logic_time_in_ms).sum();
+        ui.label(format!("logic time: {:.3}ms", logic_time));
+      });
+
+      ui.horizontal_wrapped(|ui| {
+        if ui.button("export dot").clicked() {
+          panel.export_message = Some(export_query_graph("query_graph.dot", frame.to_dot()));
+        }
+        if ui.button("export json").clicked() {
+          panel.export_message = Some(export_query_graph("query_graph.json", frame.to_json()));
+        }
+        if let Some(message) = &panel.export_message {
+          ui.label(message);
+        }
+      });
+
+      ui.horizontal_wrapped(|ui| {
+        ui.checkbox(&mut panel.only_executed, "only executed");
+        egui::ComboBox::from_label("sort by")
+          .selected_text(format!("{:?}", panel.sort_by))
+          .show_ui(ui, |ui| {
+            ui.selectable_value(&mut panel.sort_by, QueryGraphSortBy::Label, "Label");
+            ui.selectable_value(&mut panel.sort_by, QueryGraphSortBy::LogicTime, "LogicTime");
+            ui.selectable_value(&mut panel.sort_by, QueryGraphSortBy::TaskTime, "TaskTime");
+            ui.selectable_value(&mut panel.sort_by, QueryGraphSortBy::DeltaSize, "DeltaSize");
+          });
+      });
+      ui.horizontal(|ui| {
+        ui.label("filter:");
+        ui.text_edit_singleline(&mut panel.label_filter);
+      });
+
+      let mut nodes: Vec<_> = frame
+        .nodes
+        .iter()
+        .filter(|n| !panel.only_executed || n.execute_count > 0)
+        .filter(|n| n.label.contains(panel.label_filter.as_str()))
+        .collect();
+      match panel.sort_by {
+        QueryGraphSortBy::Label => {}
+        QueryGraphSortBy::LogicTime => {
+          nodes.sort_by(|a, b| b.logic_time_in_ms.total_cmp(&a.logic_time_in_ms))
+        }
+        QueryGraphSortBy::TaskTime => nodes.sort_by(|a, b| {
+          let a = a.task_time_in_ms.unwrap_or(0.);
+          let b = b.task_time_in_ms.unwrap_or(0.);
+          b.total_cmp(&a)
+        }),
+        QueryGraphSortBy::DeltaSize => {
+          nodes.sort_by_key(|n| std::cmp::Reverse(n.delta_size.unwrap_or(0)))
+        }
+      }
+
+      TableBuilder::new(ui)
+        .id_salt("query graph nodes")
+        .striped(true)
+        .column(Column::remainder().at_least(200.).clip(true))
+        .columns(Column::auto(), 4)
+        .max_scroll_height(400.)
+        .header(20.0, |mut header| {
+          header.col(|ui| {
+            ui.strong("shared ctx");
+          });
+          header.col(|ui| {
+            ui.strong("executed");
+          });
+          header.col(|ui| {
+            ui.strong("logic(ms)");
+          });
+          header.col(|ui| {
+            ui.strong("task(ms)");
+          });
+          header.col(|ui| {
+            ui.strong("delta");
+          });
+        })
+        .body(|mut body| {
+          for node in nodes {
+            body.row(18.0, |mut row| {
+              row.col(|ui| {
+                let label = disqualified::ShortName(&node.label).to_string();
+                let focused = panel.focused.as_ref() == Some(&node.id);
+                if ui
+                  .selectable_label(focused, label)
+                  .on_hover_text(&node.label)
+                  .clicked()
+                {
+                  panel.focused = (!focused).then(|| node.id.clone());
+                }
+              });
+              row.col(|ui| {
+                ui.label(node.execute_count.to_string());
+              });
+              row.col(|ui| {
+                ui.label(format!("{:.3}", node.logic_time_in_ms));
+              });
+              row.col(|ui| {
+                if let Some(task_time) = node.task_time_in_ms {
+                  ui.label(format!("{:.3}", task_time));
+                }
+              });
+              row.col(|ui| {
+                if let Some(delta_size) = node.delta_size {
+                  ui.label(delta_size.to_string());
+                }
+              });
+            });
+          }
+        });
+
+      if let Some(focused) = &panel.focused {
+        let label_of = |id: &str| {
+          frame
+            .nodes
+            .iter()
+            .find(|n| n.id == id)
+            .map(|n| disqualified::ShortName(&n.label).to_string())
+            .unwrap_or_else(|| id.to_string())
+        };
+        ui.separator();
+        ui.heading(label_of(focused));
+        ui.collapsing("dependencies", |ui| {
+          for edge in frame.edges.iter().filter(|e| &e.to == focused) {
+            ui.label(label_of(&edge.from));
+          }
+        });
+        ui.collapsing("consumers", |ui| {
+          for edge in frame.edges.iter().filter(|e| &e.from == focused) {
+            ui.label(label_of(&edge.to));
+          }
+        });
+      }
+    });
+}
@@ -136,6 +136,9 @@ pub fn use_enable_gltf_io(cx: &mut ViewerCx) {
                     load_result.used_but_not_supported_extensions
                   );
                 }
+                for warning in &load_result.warnings {
+                  println!("warning: gltf load: {warning}");
+                }
 
                 sender.unbounded_send(load_result).ok();
               })
@@ -0,0 +1,67 @@
+use crate::*;
+
+pub const CMD_BAKE_LIGHT_PROBES: &str = "bake-light-probes";
+
+/// bake all light probe volumes in the scene of the given viewport by the path tracer, the
+/// ray tracing renderer must be enabled.
+///
+/// usage: bake-light-probes <surface_id> <viewport_id> [sample_count]
+pub fn use_enable_light_probe_bake(cx: &mut ViewerCx) {
+  cx.use_state_init(|cx| {
+    cx.terminal
+      .register_command(CMD_BAKE_LIGHT_PROBES, |ctx, parameters, tcx| {
+        let surface_id = parameters
+          .get(1)
+          .and_then(|v| v.parse::<u32>().ok())
+          .expect("missing surface id");
+        let viewport_id = parameters
+          .get(2)
+          .and_then(|v| v.parse::<u64>().ok())
+          .expect("missing viewport id");
+
+        let mut config = LightProbeBakeConfig::default();
+        if let Some(sample_count) = parameters.get(3).and_then(|v| v.parse::<u32>().ok()) {
+          config.sample_count = sample_count;
+        }
+
+        let surface_view = ctx.renderer.surface_views.get_mut(&surface_id).unwrap();
+
+        let result = surface_view
+          .get_mut(&viewport_id)
+          .unwrap()
+          .request_light_probe_bake(config);
+        let tcx = tcx.clone();
+
+        async move {
+          let Some(result) = result.await else {
+            log::error!("failed to bake light probes");
+            return;
+          };
+
+          tcx
+            .spawn_main_thread(move || {
+              let mut writer = SceneWriter::from_global();
+              let volume_count = result.len();
+              for LightProbeVolumeBakeResult { volume, sh } in result {
+                let data = bytemuck::cast_slice::<_, u8>(&sh).to_vec();
+                let data = data.write(&mut writer.buffer_writer);
+                writer
+                  .light_probe_volume_writer
+                  .write_foreign_key::<LightProbeVolumeIrradianceData>(volume, Some(data));
+              }
+              log::info!("{volume_count} light probe volumes baked");
+            })
+            .await;
+        }
+      });
+
+    ViewerLightProbeBake
+  });
+}
+
+struct ViewerLightProbeBake;
+impl CanCleanUpFrom<ViewerDropCx<'_>> for ViewerLightProbeBake {
+  fn drop_from_cx(&mut self, cx: &mut ViewerDropCx) {
+    cx.terminal.unregister_command(CMD_BAKE_LIGHT_PROBES);
+  }
+}
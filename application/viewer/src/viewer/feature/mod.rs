@@ -22,6 +22,10 @@ mod obj_io;
 pub use obj_io::*;
 mod screenshot;
 pub use screenshot::*;
+mod light_probe_bake;
+pub use light_probe_bake::*;
+mod reflection_probe_capture;
+pub use reflection_probe_capture::*;
 mod egui_view;
 pub use egui_view::*;
 mod mesh_tools;
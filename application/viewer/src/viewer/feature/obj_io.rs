@@ -56,6 +56,7 @@ pub fn use_enable_obj_io(cx: &mut ViewerCx) {
                   load_target_node,
                   target_scene,
                   default_mat,
+                  None,
                   &mut writer,
                 )
                 .unwrap();
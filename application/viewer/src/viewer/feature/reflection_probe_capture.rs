@@ -0,0 +1,41 @@
+use crate::*;
+
+pub const CMD_CAPTURE_REFLECTION_PROBES: &str = "capture-reflection-probes";
+
+/// recapture all reflection probes in the scene of the given viewport, the probes are only
+/// captured automatically when they are changed.
+///
+/// usage: capture-reflection-probes <surface_id> <viewport_id>
+pub fn use_enable_reflection_probe_capture(cx: &mut ViewerCx) {
+  cx.use_state_init(|cx| {
+    cx.terminal
+      .register_command(CMD_CAPTURE_REFLECTION_PROBES, |ctx, parameters, _| {
+        let surface_id = parameters
+          .get(1)
+          .and_then(|v| v.parse::<u32>().ok())
+          .expect("missing surface id");
+        let viewport_id = parameters
+          .get(2)
+          .and_then(|v| v.parse::<u64>().ok())
+          .expect("missing viewport id");
+
+        let surface_view = ctx.renderer.surface_views.get_mut(&surface_id).unwrap();
+        surface_view
+          .get_mut(&viewport_id)
+          .unwrap()
+          .request_reflection_probe_capture();
+
+        async {}
+      });
+
+    ViewerReflectionProbeCapture
+  });
+}
+
+struct ViewerReflectionProbeCapture;
+impl CanCleanUpFrom<ViewerDropCx<'_>> for ViewerReflectionProbeCapture {
+  fn drop_from_cx(&mut self, cx: &mut ViewerDropCx) {
+    cx.terminal
+      .unregister_command(CMD_CAPTURE_REFLECTION_PROBES);
+  }
+}
@@ -446,6 +446,8 @@ pub fn use_viewer<'a>(
   };
 
   viewer.update_view_ty_immediate();
+  // the last frame's logic and rendering are all finished here
+  viewer.shared_ctx.query_graph_recorder().begin_frame();
 
   #[cfg(all(feature = "dhat-heap-profiling", not(target_family = "wasm")))]
   let _dhat_profiler = if viewer.should_trace_next_frame_allocation_info {
@@ -9,6 +9,8 @@ pub struct BasicShadowMapInfoInput {
   pub proj: ShadowCameraProjectionMatrixes,
   pub map_size: Size,
   pub bias: ShadowBias,
+  /// the light source size for the PCSS, see [compute_pcss_light_size]
+  pub light_size: f32,
 }
 
 #[derive(Clone, Default)]
@@ -75,6 +77,10 @@ pub fn prepare_basic_shadow_map_uniform(
             shadow_center_without_translation_to_shadowmap_ndc,
             shadow_proj_linear_depth_recover_helper:
               extract_shadow_proj_linear_depth_recover_helper(shadow_info.proj.opengl_ndc_matrix),
+            pcss_light_size: compute_pcss_light_size(
+              shadow_info.light_size,
+              shadow_info.proj.opengl_ndc_matrix,
+            ),
             ..Default::default()
           }
         } else {
@@ -214,6 +220,7 @@ pub struct BasicShadowMapInfo {
   pub shadow_world_position: HighPrecisionTranslationUniform,
   pub bias: ShadowBias,
   pub map_info: ShadowMapAddressInfo,
+  pub pcss_light_size: f32,
 }
 
 #[derive(Clone)]
@@ -260,6 +267,34 @@ pub struct BasicShadowMapInvocation {
 }
 
 impl BasicShadowMapInvocation {
+  /// if the shadow map of the light at the given index is allocated and enabled
+  pub fn is_shadow_enabled(&self, shadow_idx: Node<u32>) -> Node<bool> {
+    self.info.index(shadow_idx).enabled().load().into_bool()
+  }
+
+  /// if the render position is inside the shadow camera frustum of the light at the given index,
+  /// the positions outside of the frustum can not be shadowed by the shadow map
+  pub fn is_in_shadow_frustum(
+    &self,
+    render_position: Node<Vec3<f32>>,
+    shadow_idx: Node<u32>,
+    camera_world_position: Node<HighPrecisionTranslation>,
+  ) -> Node<bool> {
+    let shadow_info = self.info.index(shadow_idx);
+    let shadow_center = hpt_sub_hpt(
+      hpt_uniform_to_hpt(shadow_info.shadow_world_position().load()),
+      camera_world_position,
+    );
+    let clip = shadow_info
+      .shadow_center_without_translation_to_shadowmap_ndc()
+      .load()
+      * (render_position - shadow_center, val(1.)).into();
+    let w = clip.w();
+    w.greater_than(val(0.))
+      .and(clip.x().abs().less_equal_than(w))
+      .and(clip.y().abs().less_equal_than(w))
+  }
+
   pub fn query_shadow_occlusion_by_idx(
     &self,
     render_position: Node<Vec3<f32>>,
@@ -294,6 +329,7 @@ impl BasicShadowMapInvocation {
           map_info,
           val(1.),
           shadow_info.shadow_proj_linear_depth_recover_helper(),
+          shadow_info.pcss_light_size().load(),
         )
       },
       || val(1.),
@@ -16,6 +16,8 @@ pub struct CascadeShadowMapLightInput {
   pub size: Size,
   pub bias: ShadowBias,
   pub shadow_enabled: bool,
+  /// the light source size for the PCSS, see [compute_pcss_light_size]
+  pub light_size: f32,
 }
 
 type CascadeShadowPackerImpl = MultiLayerTexturePackerRaw<EtagerePacker>;
@@ -95,6 +97,7 @@ pub fn generate_cascade_shadow_info(
                 proj_linear_depth_recover_helper: extract_shadow_proj_linear_depth_recover_helper(
                   opengl_ndc_matrix,
                 ),
+                pcss_light_size: compute_pcss_light_size(input.light_size, opengl_ndc_matrix),
                 ..Default::default()
               });
               splits[idx] = *split;
@@ -251,6 +254,7 @@ pub struct SingleShadowMapInfo {
   /// used to scale the PCF filter size so that it covers the same world space area
   pub cascade_scale: f32,
   pub proj_linear_depth_recover_helper: ProjLinearDepthRecoverHelper,
+  pub pcss_light_size: f32,
 }
 
 /// return per sub frustum orth proj and split distance in light space
@@ -446,6 +450,7 @@ impl CascadeShadowMapInvocation {
           map_info,
           cascade_info.cascade_scale().load(),
           cascade_info.proj_linear_depth_recover_helper(),
+          cascade_info.pcss_light_size().load(),
         );
 
         if DEBUG_SHADOW_UV {
@@ -501,6 +506,7 @@ impl CascadeShadowMapInvocation {
                 map_info_next,
                 next_cascade_info.cascade_scale().load(),
                 next_cascade_info.proj_linear_depth_recover_helper(),
+                next_cascade_info.pcss_light_size().load(),
               );
 
               let lerp_amt = fade_factor.smoothstep(val(0.), blend_threshold);
@@ -12,6 +12,8 @@ pub struct CubeShadowMapInfoInput {
   /// the size of one cube face
   pub map_size: Size,
   pub bias: ShadowBias,
+  /// the light source size for the PCSS, see [compute_pcss_light_size]
+  pub light_size: f32,
 }
 
 /// the six faces of one light are packed in a 2 x 3 grid
@@ -138,6 +140,11 @@ pub fn prepare_cube_shadow_map_uniform(
             shadow_world_position,
             bias: shadow_info.bias,
             faces: faces.into(),
+            // all faces share the same projection
+            pcss_light_size: compute_pcss_light_size(
+              shadow_info.light_size,
+              shadow_info.proj.opengl_ndc_matrix,
+            ),
             ..Default::default()
           }
         } else {
@@ -233,6 +240,7 @@ pub struct CubeShadowMapInfo {
   pub shadow_world_position: HighPrecisionTranslationUniform,
   pub bias: ShadowBias,
   pub faces: Shader140Array<CubeFaceShadowMapInfo, CUBE_FACE_COUNT>,
+  pub pcss_light_size: f32,
 }
 
 #[repr(C)]
@@ -288,6 +296,11 @@ pub struct CubeShadowMapInvocation {
 }
 
 impl CubeShadowMapInvocation {
+  /// if the shadow map of the light at the given index is allocated and enabled
+  pub fn is_shadow_enabled(&self, shadow_idx: Node<u32>) -> Node<bool> {
+    self.info.index(shadow_idx).enabled().load().into_bool()
+  }
+
   pub fn query_shadow_occlusion_by_idx(
     &self,
     render_position: Node<Vec3<f32>>,
@@ -333,6 +346,7 @@ impl CubeShadowMapInvocation {
           map_info,
           val(1.),
           face_info.proj_linear_depth_recover_helper(),
+          shadow_info.pcss_light_size().load(),
         )
       },
       || val(1.),
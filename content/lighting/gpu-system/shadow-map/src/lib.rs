@@ -53,6 +53,8 @@ pub trait AbstractShadowComputerInvocation {
     map_info: Node<ShadowMapAddressInfo>,
     cascade_scale: Node<f32>,
     proj_linear_depth_recover_helper: ShaderReadonlyPtrOf<ProjLinearDepthRecoverHelper>,
+    // see compute_pcss_light_size
+    pcss_light_size: Node<f32>,
   ) -> Node<f32>;
 }
 
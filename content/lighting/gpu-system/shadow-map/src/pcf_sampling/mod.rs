@@ -3,10 +3,12 @@ use crate::*;
 mod fixed_size;
 mod grid;
 mod optimized;
+mod pcss;
 mod random_disc;
 pub use fixed_size::*;
 pub use grid::*;
 pub use optimized::*;
+pub use pcss::*;
 pub use random_disc::*;
 
 pub struct PCFComputer {
@@ -61,7 +63,8 @@ impl AbstractShadowComputerInvocation for PCFComputerInvocation {
     screen_position: Node<Vec2<f32>>,
     map_info: Node<ShadowMapAddressInfo>,
     cascade_scale: Node<f32>,
-    _proj_linear_depth_recover_helper: ShaderReadonlyPtrOf<ProjLinearDepthRecoverHelper>,
+    proj_linear_depth_recover_helper: ShaderReadonlyPtrOf<ProjLinearDepthRecoverHelper>,
+    pcss_light_size: Node<f32>,
   ) -> Node<f32> {
     self.pcf_config.sample_shadow_pcf(
       self.shadow_map_atlas,
@@ -71,6 +74,8 @@ impl AbstractShadowComputerInvocation for PCFComputerInvocation {
       map_info,
       self.pcf_config_parameter.pcf_filter_size * cascade_scale,
       self.pcf_config_parameter.pcf_num_disc_samples,
+      pcss_light_size,
+      proj_linear_depth_recover_helper,
       val(self.reversed_depth),
     )
   }
@@ -86,6 +91,9 @@ pub enum ShadowPCFMode {
   GridPCF,
   /// PCF with a kernel made up from random points on a disc
   RandomDiscPCF,
+  /// percentage-closer soft shadows, the random disc PCF kernel size is estimated from the
+  /// blocker search and the per light source size
+  PCSS,
 }
 
 #[repr(C)]
@@ -94,7 +102,7 @@ pub enum ShadowPCFMode {
 pub struct PCFConfigParameter {
   /// the PCF filter size in texels
   pub pcf_filter_size: f32,
-  /// the sample count of the random disc PCF
+  /// the sample count of the random disc PCF and the PCSS filtering
   pub pcf_num_disc_samples: u32,
 }
 
@@ -125,7 +133,7 @@ pub struct ShadowPCFConfig {
   /// the filter size in texels for GridPCF and RandomDiscPCF,
   /// passed as uniform so it can be adjusted at runtime without recompiling the shader
   pub filter_size: f32,
-  /// the sample count for RandomDiscPCF, passed as uniform
+  /// the sample count for RandomDiscPCF and PCSS, passed as uniform
   pub num_disc_samples: u32,
   /// use receiver plane depth bias
   pub use_receiver_plane_depth_bias: bool,
@@ -161,6 +169,8 @@ impl ShadowPCFConfig {
     map_info: Node<ShadowMapAddressInfo>,
     filter_size: Node<f32>,
     num_disc_samples: Node<u32>,
+    pcss_light_size: Node<f32>,
+    proj_linear_depth_recover_helper: ShaderReadonlyPtrOf<ProjLinearDepthRecoverHelper>,
     reversed_depth: Node<bool>,
   ) -> Node<f32> {
     // the receiver plane depth bias is derived from the shadow map uv
@@ -212,6 +222,18 @@ impl ShadowPCFConfig {
         receiver_plane_depth_bias,
         reversed_depth,
       ),
+      ShadowPCFMode::PCSS => sample_shadow_pcss_fn(
+        map,
+        sampler,
+        shadow_position,
+        random_seed,
+        map_info,
+        pcss_light_size,
+        proj_linear_depth_recover_helper.load(),
+        num_disc_samples,
+        receiver_plane_depth_bias,
+        reversed_depth,
+      ),
     }
   }
 }
@@ -0,0 +1,412 @@
+use core::f32;
+
+use rendiation_shader_library::sampling::random_fn;
+
+use super::*;
+
+/// the maximum penumbra size of the PCSS, in texels
+pub const MAX_PCSS_FILTER_SIZE: f32 = 32.0;
+/// the maximum blocker search region size of the PCSS, in texels
+pub const MAX_PCSS_SEARCH_SIZE: f32 = 32.0;
+/// the sample count of the PCSS blocker search, the samples are strided from [POISSON_SAMPLES]
+pub const PCSS_BLOCKER_SEARCH_SAMPLE_COUNT: u32 = 16;
+
+/// convert the light source size into the light size parameter of the PCSS.
+///
+/// for the perspective shadow projection the light_size is the world space width of the light
+/// source, and the result is the light width in shadow map uv units at unit view depth. for the
+/// orthographic shadow projection the light_size is the tangent of the angular diameter of the
+/// light source, and the result is the penumbra width in uv units per unit view depth distance
+/// between the blocker and the receiver.
+///
+/// the shadow_proj is any projection of the shadow camera, only the x scale is used.
+pub fn compute_pcss_light_size(light_size: f32, shadow_proj: Mat4<f32>) -> f32 {
+  // the x scale maps the view space into the ndc, and the uv is half of the ndc
+  light_size * shadow_proj.a1 * 0.5
+}
+
+/// Samples the shadow map with the percentage-closer soft shadows. The blocker search estimates
+/// the average blocker depth, then the penumbra size is derived from the receiver and blocker
+/// depth by similar triangles, and used as the kernel size of the random disc PCF.
+/// from "Percentage-Closer Soft Shadows" by Randima Fernando
+///
+/// see [compute_pcss_light_size] for the light_size, the num_disc_samples is clamped to the
+/// length of [POISSON_SAMPLES]
+#[shader_fn]
+pub fn sample_shadow_pcss(
+  map: BindingNode<ShaderDepthTexture2DArray>,
+  d_sampler: BindingNode<ShaderCompareSampler>,
+  shadow_position: Node<Vec3<f32>>,
+  random_seed: Node<Vec2<f32>>,
+  info: Node<ShadowMapAddressInfo>,
+  light_size: Node<f32>,
+  proj_linear_depth_recover_helper: Node<ProjLinearDepthRecoverHelper>,
+  num_disc_samples: Node<u32>,
+  receiver_plane_depth_bias: Node<Vec2<f32>>,
+  reversed_depth: Node<bool>,
+) -> Node<f32> {
+  let map_size = map.texture_dimension_2d(None).into_f32();
+
+  let info_node = info;
+  let info = info_node.expand();
+  let layer = info.layer_index;
+  let helper = proj_linear_depth_recover_helper.expand();
+  let num_disc_samples = num_disc_samples.clamp(val(1), val(POISSON_SAMPLES.len() as u32));
+
+  let shadow_depth = shadow_position.z();
+
+  // static depth biasing to make up for incorrect fractional sampling on the shadow map grid
+  let fractional_sampling_error =
+    fractional_sampling_error_fn(info_node, receiver_plane_depth_bias);
+  let shadow_depth =
+    apply_fractional_sampling_error_fn(shadow_depth, fractional_sampling_error, reversed_depth);
+
+  let is_perspective = is_perspective_shadow(proj_linear_depth_recover_helper);
+  let receiver_depth = shadow_view_depth(
+    shadow_depth,
+    proj_linear_depth_recover_helper,
+    reversed_depth,
+  );
+
+  // get a value to randomly rotate the kernels by, see sample_shadow_pcf_random_disc
+  let theta = random_fn(random_seed) * val(f32::consts::TAU);
+  let rotation = vec2_node((theta.cos(), theta.sin()));
+
+  // the blockers can only be found in the region that the light source covers on the shadow
+  // map when viewed from the receiver, the region is largest at the near plane
+  let search_size = pcss_penumbra_size(light_size, receiver_depth, helper.near, is_perspective);
+  let search_size = (search_size * info.size).clamp(
+    val(Vec2::new(1., 1.)),
+    val(Vec2::new(MAX_PCSS_SEARCH_SIZE, MAX_PCSS_SEARCH_SIZE)),
+  );
+  let search_scale = (val(0.5) * search_size) / info.size;
+
+  let blocker = pcss_search_blocker_depth_fn(
+    map,
+    shadow_position.xy(),
+    shadow_depth,
+    rotation,
+    info_node,
+    search_scale,
+    proj_linear_depth_recover_helper,
+    receiver_plane_depth_bias,
+    reversed_depth,
+  );
+
+  blocker.y().greater_than(val(0.)).select_branched(
+    || {
+      let penumbra_size =
+        pcss_penumbra_size(light_size, receiver_depth, blocker.x(), is_perspective);
+      let filter_size = (penumbra_size * info.size).clamp(
+        val(Vec2::new(1., 1.)),
+        val(Vec2::new(MAX_PCSS_FILTER_SIZE, MAX_PCSS_FILTER_SIZE)),
+      );
+      let sample_scale = (val(0.5) * filter_size) / info.size;
+
+      let result = val(0.).make_local_var();
+
+      let samples = global_const_val(POISSON_SAMPLES);
+      let i = val(0_u32).make_local_var();
+      loop_by(|cx| {
+        let idx = i.load();
+        if_by(idx.greater_equal_than(num_disc_samples), || cx.do_break());
+        let sample_offset = rotate_by(samples.index(idx), rotation) * sample_scale;
+
+        let sample_pos = shadow_position.xy() + sample_offset;
+
+        // compute offset and apply planar depth bias
+        let sample_depth = shadow_depth + sample_offset.dot(receiver_plane_depth_bias);
+
+        let sample = map
+          .build_compare_sample_call(
+            d_sampler,
+            map_uv_to_atlas_uv_fn(sample_pos, info_node, map_size),
+            sample_depth,
+          )
+          .with_array_index(layer)
+          .sample();
+        result.store(result.load() + sample);
+
+        i.store(idx + val(1));
+      });
+
+      result.load() / num_disc_samples.into_f32()
+    },
+    // no blocker is found, the receiver is fully lit
+    || val(1.),
+  )
+}
+
+/// Searches the blockers in the given region around the shadow map uv, returns the average view
+/// depth of the blockers in x, and the blocker count in y. Each blocker depth is linearized
+/// before averaging, as the average of the non-linear perspective depth is biased to the near
+/// blockers.
+///
+/// the search_scale is the search region half size in shadow map uv units, and the rotation is
+/// the (cos, sin) of the random kernel rotation
+#[shader_fn]
+pub fn pcss_search_blocker_depth(
+  map: BindingNode<ShaderDepthTexture2DArray>,
+  shadow_uv: Node<Vec2<f32>>,
+  shadow_depth: Node<f32>,
+  rotation: Node<Vec2<f32>>,
+  info: Node<ShadowMapAddressInfo>,
+  search_scale: Node<Vec2<f32>>,
+  proj_linear_depth_recover_helper: Node<ProjLinearDepthRecoverHelper>,
+  receiver_plane_depth_bias: Node<Vec2<f32>>,
+  reversed_depth: Node<bool>,
+) -> Node<Vec2<f32>> {
+  let info = info.expand();
+  let layer = info.layer_index;
+
+  // the depth is directly loaded, so the search is clamped into the shadow map region
+  // instead of reading the neighbor regions in the atlas
+  let region_min = info.offset;
+  let region_max = info.offset + info.size - val(Vec2::new(1., 1.));
+  let sample_stride = POISSON_SAMPLES.len() as u32 / PCSS_BLOCKER_SEARCH_SAMPLE_COUNT;
+  let samples = global_const_val(POISSON_SAMPLES);
+
+  let blocker_depth_sum = val(0.).make_local_var();
+  let blocker_count = val(0.).make_local_var();
+
+  let i = val(0_u32).make_local_var();
+  loop_by(|cx| {
+    let idx = i.load();
+    if_by(
+      idx.greater_equal_than(val(PCSS_BLOCKER_SEARCH_SAMPLE_COUNT)),
+      || cx.do_break(),
+    );
+    let sample_offset = rotate_by(samples.index(idx * val(sample_stride)), rotation) * search_scale;
+    let texel = ((shadow_uv + sample_offset) * info.size + info.offset)
+      .floor()
+      .clamp(region_min, region_max)
+      .into_u32();
+
+    let depth = map.load_texel_layer(texel, layer.into_u32(), val(0));
+    let receiver_depth = shadow_depth + sample_offset.dot(receiver_plane_depth_bias);
+    let is_blocker = reversed_depth.select(
+      depth.greater_than(receiver_depth),
+      depth.less_than(receiver_depth),
+    );
+
+    if_by(is_blocker, || {
+      let depth = shadow_view_depth(depth, proj_linear_depth_recover_helper, reversed_depth);
+      blocker_depth_sum.store(blocker_depth_sum.load() + depth);
+      blocker_count.store(blocker_count.load() + val(1.));
+    });
+
+    i.store(idx + val(1));
+  });
+
+  let blocker_count = blocker_count.load();
+  let blocker_depth = blocker_depth_sum.load() / blocker_count.max(val(1.));
+  vec2_node((blocker_depth, blocker_count))
+}
+
+fn rotate_by(offset: Node<Vec2<f32>>, rotation: Node<Vec2<f32>>) -> Node<Vec2<f32>> {
+  let (cos_theta, sin_theta) = (rotation.x(), rotation.y());
+  vec2_node((
+    offset.x() * cos_theta - offset.y() * sin_theta,
+    offset.x() * sin_theta + offset.y() * cos_theta,
+  ))
+}
+
+/// the perspective projection has m33 = 0, see recover_linear_depth
+fn is_perspective_shadow(helper: Node<ProjLinearDepthRecoverHelper>) -> Node<bool> {
+  helper.expand().w_row.y().less_than(val(0.5))
+}
+
+/// recover the view space depth of the shadow camera from the shadow map depth
+fn shadow_view_depth(
+  depth: Node<f32>,
+  helper: Node<ProjLinearDepthRecoverHelper>,
+  reversed_depth: Node<bool>,
+) -> Node<f32> {
+  let linear = recover_linear_depth_fn(depth, helper, reversed_depth);
+  let helper = helper.expand();
+  helper.near + linear * (helper.far - helper.near)
+}
+
+/// the penumbra width in shadow map uv units by similar triangles, see [compute_pcss_light_size]
+fn pcss_penumbra_size(
+  light_size: Node<f32>,
+  receiver_depth: Node<f32>,
+  blocker_depth: Node<f32>,
+  is_perspective: Node<bool>,
+) -> Node<f32> {
+  is_perspective.select(
+    light_size * (receiver_depth - blocker_depth) / (blocker_depth * receiver_depth),
+    light_size * (receiver_depth - blocker_depth),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use rendiation_shader_backend_cpu::*;
+
+  use super::*;
+
+  const MAP_SIZE: u32 = 16;
+
+  fn projection() -> Mat4<f32> {
+    PerspectiveProjection {
+      near: 0.1,
+      far: 100.,
+      fov: Deg::by(90.),
+      aspect: 1.,
+    }
+    .compute_projection_mat(&OpenGLxNDC)
+  }
+
+  /// the webgpu ndc depth of the given view depth
+  fn depth_of(view_depth: f32) -> f32 {
+    let clip = projection() * Vec4::new(0., 0., -view_depth, 1.);
+    (clip.z / clip.w + 1.) * 0.5
+  }
+
+  fn create_map(
+    depth: impl Fn(u32, u32) -> f32,
+  ) -> CpuTextureView<TextureDimension2Array, TextureSampleDepth> {
+    let texels: Vec<[f32; 4]> = (0..MAP_SIZE * MAP_SIZE)
+      .map(|i| [depth(i % MAP_SIZE, i / MAP_SIZE), 0., 0., 0.])
+      .collect();
+    let texture = CpuShaderTexture::from_texels(ScalarType::F32, MAP_SIZE, MAP_SIZE, &texels);
+    CpuTextureView::new(texture)
+  }
+
+  fn address_info() -> ShadowMapAddressInfo {
+    ShadowMapAddressInfo {
+      layer_index: 0,
+      size: Vec2::splat(MAP_SIZE as f32),
+      offset: Vec2::zero(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_blocker_search_averages_linear_depth() {
+    let (near_blocker, far_blocker) = (0.2, 50.);
+    let map = create_map(|x, y| {
+      let view_depth = if (x + y) % 2 == 0 {
+        near_blocker
+      } else {
+        far_blocker
+      };
+      depth_of(view_depth)
+    });
+    let helper = CpuUniformBufferDataView::new(&extract_shadow_proj_linear_depth_recover_helper(
+      projection(),
+    ));
+    let info = CpuUniformBufferDataView::new(&address_info());
+    let output = CpuStorageBufferDataView::<[Vec2<f32>]>::new([Vec2::zero(); 2].as_slice());
+
+    let mut cx = cpu_compute_shader_builder().with_config_work_group_size(1);
+    let map_node = cx.bind_by(&map);
+    let helper_node = cx.bind_by(&helper).load();
+    let info_node = cx.bind_by(&info).load();
+    let output_node = cx.bind_by(&output);
+    let search = |receiver_view_depth: f32| {
+      pcss_search_blocker_depth_fn(
+        map_node,
+        val(Vec2::splat(0.5)),
+        val(depth_of(receiver_view_depth)),
+        val(Vec2::new(0.6, 0.8)),
+        info_node,
+        val(Vec2::splat(0.25)),
+        helper_node,
+        val(Vec2::zero()),
+        val(false),
+      )
+    };
+    output_node.index(0).store(search(80.));
+    // the receiver is in front of all blockers
+    output_node.index(1).store(search(0.15));
+
+    let module = cx.create_cpu_module().unwrap();
+    let bindings = CpuShaderBindingBuilder::default()
+      .with_bind(&map)
+      .with_bind(&helper)
+      .with_bind(&info)
+      .with_bind(&output);
+    module.dispatch(&bindings, (1, 1, 1)).unwrap();
+    let result = output.read().into_vec();
+
+    let count = PCSS_BLOCKER_SEARCH_SAMPLE_COUNT;
+    assert_eq!(result[0].y, count as f32);
+    // the average of the linear depth is a weighted sum of the two blocker depths, the average
+    // of the ndc depth is far away from it as the ndc depth is dominated by the near blocker
+    let near_count = (0..=count).find(|near_count| {
+      let near_count = *near_count as f32;
+      let expect =
+        (near_blocker * near_count + far_blocker * (count as f32 - near_count)) / count as f32;
+      (result[0].x - expect).abs() < 1e-2
+    });
+    let near_count = near_count.expect("the blocker depth is not averaged in linear depth");
+    assert!(
+      near_count > 0 && near_count < count,
+      "both blockers are found"
+    );
+
+    assert_eq!(result[1].y, 0.);
+  }
+
+  #[test]
+  fn test_pcss_sampling() {
+    // the left half of the map is blocked
+    let map = create_map(|x, _| if x < MAP_SIZE / 2 { depth_of(10.) } else { 1. });
+    let sampler = CpuShaderComparisonSampler(CpuShaderSampler {
+      mag_filter: rendiation_webgpu::FilterMode::Linear,
+      min_filter: rendiation_webgpu::FilterMode::Linear,
+      compare: Some(CompareFunction::Less),
+      ..Default::default()
+    });
+    let helper = CpuUniformBufferDataView::new(&extract_shadow_proj_linear_depth_recover_helper(
+      projection(),
+    ));
+    let info = CpuUniformBufferDataView::new(&address_info());
+    let output = CpuStorageBufferDataView::<[f32]>::new([0.; 4].as_slice());
+
+    let mut cx = cpu_compute_shader_builder().with_config_work_group_size(1);
+    let map_node = cx.bind_by(&map);
+    let sampler_node = cx.bind_by(&sampler);
+    let helper_node = cx.bind_by(&helper).load();
+    let info_node = cx.bind_by(&info).load();
+    let output_node = cx.bind_by(&output);
+    let sample = |uv: Vec2<f32>, num_disc_samples: u32| {
+      sample_shadow_pcss_fn(
+        map_node,
+        sampler_node,
+        val(Vec3::new(uv.x, uv.y, depth_of(50.))),
+        val(Vec2::new(0.3, 0.7)),
+        info_node,
+        val(0.02),
+        helper_node,
+        val(num_disc_samples),
+        val(Vec2::zero()),
+        val(false),
+      )
+    };
+    // in the penumbra
+    output_node.index(0).store(sample(Vec2::splat(0.5), 64));
+    // the sample count is clamped to the poisson samples
+    output_node.index(1).store(sample(Vec2::splat(0.5), 1000));
+    // fully lit and fully shadowed
+    output_node.index(2).store(sample(Vec2::new(0.9, 0.5), 64));
+    output_node.index(3).store(sample(Vec2::new(0.1, 0.5), 64));
+
+    let module = cx.create_cpu_module().unwrap();
+    let bindings = CpuShaderBindingBuilder::default()
+      .with_bind(&map)
+      .with_bind(&sampler)
+      .with_bind(&helper)
+      .with_bind(&info)
+      .with_bind(&output);
+    module.dispatch(&bindings, (1, 1, 1)).unwrap();
+    let result = output.read().into_vec();
+
+    assert!(result[0] > 0.1 && result[0] < 0.9, "{}", result[0]);
+    assert_eq!(result[0], result[1]);
+    assert_eq!(result[2], 1.);
+    assert_eq!(result[3], 0.);
+  }
+}
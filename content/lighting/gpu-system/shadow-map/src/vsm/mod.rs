@@ -5,7 +5,7 @@ mod sample;
 pub use sample::*;
 
 mod depth_convert;
-use depth_convert::*;
+pub(crate) use depth_convert::*;
 
 mod pre_filter;
 use pre_filter::*;
@@ -92,6 +92,7 @@ impl AbstractShadowComputerInvocation for VSMComputerInvocation {
     map_info: Node<ShadowMapAddressInfo>,
     _cascade_scale: Node<f32>,
     proj_linear_depth_recover_helper: ShaderReadonlyPtrOf<ProjLinearDepthRecoverHelper>,
+    _pcss_light_size: Node<f32>,
   ) -> Node<f32> {
     sample_shadow_map_vsm_fn(
       self.vsm_map_atlas,
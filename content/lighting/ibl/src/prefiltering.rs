@@ -53,7 +53,7 @@ pub fn generate_pre_filter_map(
     MipLevelCount::EmptyMipMap,
   );
   for (idx, direction) in face_direction_iter() {
-    let target = cube_face_view(&diffuse, idx as u32, 0);
+    let target = cube_face_view(&diffuse.resource, idx as u32, 0);
     let config = create_uniform(
       DiffuseTaskGenerationConfig {
         direction,
@@ -81,13 +81,30 @@ pub fn generate_pre_filter_map(
     config.specular_resolution,
     MipLevelCount::BySize,
   );
+  generate_pre_filter_specular_map_into(encoder, gpu, input, &specular.resource, 0, config);
+
+  PreFilterMapGenerationResult { diffuse, specular }
+}
+
+/// prefilter the specular env map into the six layers starting from the base_layer of the target,
+/// the target is expected to have the size of specular_resolution and a full mip chain.
+///
+/// this is useful to write the result into a cube array texture.
+pub fn generate_pre_filter_specular_map_into(
+  encoder: &mut GPUCommandEncoder,
+  gpu: &GPU,
+  input: &GPUCubeTextureView,
+  target: &GPUTexture,
+  base_layer: u32,
+  config: &PreFilterMapGenerationConfig,
+) {
   let spec_res = config.specular_resolution;
   let res = Size::from_u32_pair_min_one((spec_res, spec_res));
   let mip_level_count = MipLevelCount::BySize.get_level_count_wgpu(res);
 
   for (idx, direction) in face_direction_iter() {
     for level in 0..mip_level_count {
-      let target = cube_face_view(&specular, idx as u32, level);
+      let target = cube_face_view(target, base_layer + idx as u32, level);
       let config = create_uniform(
         SpecularGenerationConfig {
           direction,
@@ -111,12 +128,11 @@ pub fn generate_pre_filter_map(
         );
     }
   }
-
-  PreFilterMapGenerationResult { diffuse, specular }
 }
 
-fn cube_face_view(cube: &GPUCubeTextureView, face_idx: u32, level: u32) -> GPU2DTextureView {
-  let view = cube.resource.create_view(TextureViewDescriptor {
+/// create the 2d view of one face(array layer) of the cube like texture at the given mip level
+pub fn cube_face_view(texture: &GPUTexture, face_idx: u32, level: u32) -> GPU2DTextureView {
+  let view = texture.create_view(TextureViewDescriptor {
     label: None,
     format: None,
     dimension: Some(TextureViewDimension::D2),
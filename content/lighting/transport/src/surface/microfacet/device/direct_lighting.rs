@@ -8,6 +8,25 @@ both!(RoughnessChannel, f32);
 both!(MetallicChannel, f32);
 both!(ReflectanceChannel, f32);
 
+// the following channels are optional extensions of the metallic workflow, they follow the
+// gltf pbr material extensions' definition.
+
+// the index of refraction of the dielectric, override the reflectance if provided
+both!(IorChannel, f32);
+// the strength of the dielectric specular reflection
+both!(SpecularFactorChannel, f32);
+// the tint of the dielectric f0
+both!(SpecularColorChannel, Vec3<f32>);
+// the transmission is approximated by removing the diffuse part, the background behind the surface
+// is not refracted.
+both!(TransmissionChannel, f32);
+both!(ClearcoatChannel, f32);
+// perceptual roughness
+both!(ClearcoatRoughnessChannel, f32);
+both!(SheenColorChannel, Vec3<f32>);
+// perceptual roughness
+both!(SheenRoughnessChannel, f32);
+
 pub struct PhysicalShading;
 
 impl PhysicalShading {
@@ -30,26 +49,59 @@ impl PhysicalShading {
         .try_query_fragment_stage::<MetallicChannel>()
         .unwrap_or_else(|_| val(0.0));
 
-      let reflectance = builder
-        .try_query_fragment_stage::<ReflectanceChannel>()
-        .unwrap_or_else(|_| val(0.5));
+      let dielectric_f0 = if let Ok(ior) = builder.try_query_fragment_stage::<IorChannel>() {
+        compute_dielectric_f0_by_ior(ior)
+      } else {
+        let reflectance = builder
+          .try_query_fragment_stage::<ReflectanceChannel>()
+          .unwrap_or_else(|_| val(0.5));
+        compute_dielectric_f0(reflectance)
+      };
+
+      let mut dielectric_f0 = dielectric_f0.splat::<Vec3<f32>>();
+      if let Ok(color) = builder.try_query_fragment_stage::<SpecularColorChannel>() {
+        dielectric_f0 = (dielectric_f0 * color).min(Vec3::one());
+      }
+      if let Ok(factor) = builder.try_query_fragment_stage::<SpecularFactorChannel>() {
+        dielectric_f0 = dielectric_f0 * factor;
+      }
 
-      let dielectric_f0 = compute_dielectric_f0(reflectance);
+      let f0 = base_color * metallic + dielectric_f0 * (val(1.) - metallic);
 
-      let f0 = base_color * metallic + (dielectric_f0 * (val(1.) - metallic)).splat();
+      let mut albedo = base_color * (val(1.) - metallic);
+      if let Ok(transmission) = builder.try_query_fragment_stage::<TransmissionChannel>() {
+        albedo = albedo * (val(1.) - transmission);
+      }
 
-      (base_color * (val(1.) - metallic), f0)
+      (albedo, f0)
     };
 
     let emissive = builder
       .try_query_fragment_stage::<EmissiveChannel>()
       .unwrap_or_else(|_| val(Vec3::zero()));
 
+    let clearcoat = builder
+      .try_query_fragment_stage::<ClearcoatChannel>()
+      .unwrap_or_else(|_| val(0.));
+    let clearcoat_perceptual_roughness = builder
+      .try_query_fragment_stage::<ClearcoatRoughnessChannel>()
+      .unwrap_or_else(|_| val(0.));
+    let sheen_color = builder
+      .try_query_fragment_stage::<SheenColorChannel>()
+      .unwrap_or_else(|_| val(Vec3::zero()));
+    let sheen_perceptual_roughness = builder
+      .try_query_fragment_stage::<SheenRoughnessChannel>()
+      .unwrap_or_else(|_| val(0.));
+
     ENode::<ShaderPhysicalShading> {
       albedo,
       f0,
       perceptual_roughness,
       emissive,
+      clearcoat,
+      clearcoat_perceptual_roughness,
+      sheen_color,
+      sheen_perceptual_roughness,
     }
   }
 }
@@ -58,6 +110,20 @@ pub fn compute_dielectric_f0(reflectance: Node<f32>) -> Node<f32> {
   val(0.16) * reflectance * reflectance
 }
 
+/// the f0 of the dielectric surface in the air
+pub fn compute_dielectric_f0_by_ior(ior: Node<f32>) -> Node<f32> {
+  let r = (ior - val(1.)) / (ior + val(1.));
+  r * r
+}
+
+/// the clearcoat layer is a dielectric with ior 1.5
+const CLEARCOAT_F0: f32 = 0.04;
+/// the charlie distribution is not defined when roughness is zero
+const MIN_SHEEN_PERCEPTUAL_ROUGHNESS: f32 = 0.07;
+/// the ggx distribution of the clearcoat lobe produces NaN when roughness is zero, the NaN is
+/// not masked even if the clearcoat intensity is zero
+const MIN_CLEARCOAT_PERCEPTUAL_ROUGHNESS: f32 = 0.045;
+
 impl LightableSurfaceShadingLogicProvider for PhysicalShading {
   fn construct_shading(
     &self,
@@ -120,9 +186,49 @@ fn physical_shading_fn(
       }
       .bsdf(geometry.view_dir, -light.direction, geometry.normal);
 
+      let n_dot_v = geometry.normal.dot(geometry.view_dir).max(0.0001);
+      let half = (geometry.view_dir - light.direction).normalize();
+      let n_dot_h = geometry.normal.dot(half).saturate();
+
+      // https://github.com/KhronosGroup/glTF/tree/main/extensions/2.0/Khronos/KHR_materials_sheen
+      let sheen_roughness = shading
+        .sheen_perceptual_roughness
+        .max(MIN_SHEEN_PERCEPTUAL_ROUGHNESS);
+      let sheen_alpha = sheen_roughness * sheen_roughness;
+      let sin_theta = (val(1.) - n_dot_h * n_dot_h).max(0.).sqrt();
+      let inv_alpha = val(1.) / sheen_alpha;
+      let sheen_d = (val(2.) + inv_alpha) * sin_theta.pow(inv_alpha) / val(2. * f32::PI());
+      let sheen_v = val(1.) / (val(4.) * (n_dot_l + n_dot_v - n_dot_l * n_dot_v));
+      let sheen = shading.sheen_color * sheen_d * sheen_v;
+      // the albedo scaling, approximated by the max directional albedo of the sheen lobe
+      let sheen_scaling = val(1.) - shading.sheen_color.max_channel() * val(0.157);
+
+      // https://github.com/KhronosGroup/glTF/tree/main/extensions/2.0/Khronos/KHR_materials_clearcoat
+      let clearcoat_roughness = shading
+        .clearcoat_perceptual_roughness
+        .max(MIN_CLEARCOAT_PERCEPTUAL_ROUGHNESS);
+      let clearcoat_alpha = clearcoat_roughness * clearcoat_roughness;
+      let clearcoat_brdf = ShaderSpecular {
+        f0: val(Vec3::splat(CLEARCOAT_F0)),
+        normal_distribution_model: ShaderGGX {
+          roughness: clearcoat_alpha,
+        },
+        geometric_shadow_model: ShaderSmithGGXCorrelatedGeometryShadow {
+          roughness: clearcoat_alpha,
+        },
+        fresnel_model: ShaderSchlick,
+      }
+      .bsdf(geometry.view_dir, -light.direction, geometry.normal);
+      let clearcoat_fresnel =
+        val(CLEARCOAT_F0) + val(1. - CLEARCOAT_F0) * (val(1.) - n_dot_v).pow(5.);
+      let base_scaling = (val(1.) - shading.clearcoat * clearcoat_fresnel) * sheen_scaling;
+
+      let specular =
+        direct_specular_brdf * base_scaling + sheen + clearcoat_brdf * shading.clearcoat;
+
       cx.do_return(ENode::<ShaderLightingResult> {
-        diffuse: light.color * direct_diffuse_brdf * n_dot_l,
-        specular: light.color * direct_specular_brdf * n_dot_l,
+        diffuse: light.color * direct_diffuse_brdf * base_scaling * n_dot_l,
+        specular: light.color * specular * n_dot_l,
       })
     })
     .prepare_parameters()
@@ -138,6 +244,12 @@ pub struct ShaderPhysicalShading {
   pub perceptual_roughness: f32,
   pub f0: Vec3<f32>,
   pub emissive: Vec3<f32>,
+  /// the clearcoat layer intensity, zero for no clearcoat
+  pub clearcoat: f32,
+  pub clearcoat_perceptual_roughness: f32,
+  /// zero for no sheen
+  pub sheen_color: Vec3<f32>,
+  pub sheen_perceptual_roughness: f32,
 }
 
 pub fn bias_n_dot_l(n_dot_l: Node<f32>) -> Node<f32> {
@@ -0,0 +1,194 @@
+use crate::*;
+
+/// The decode filters of the `EXT_meshopt_compression`, the filter is applied to the decoded
+/// vertex data in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeshoptFilter {
+  #[default]
+  None,
+  /// the octahedral encoded unit vector in snorm8 or snorm16 xyzw, byte stride should be 4 or 8
+  Octahedral,
+  /// the quaternion encoded by the three smallest components in snorm16, byte stride should be 8
+  Quaternion,
+  /// the float encoded by the 24-bit mantissa and 8-bit exponent, byte stride should be multiple
+  /// of 4
+  Exponential,
+}
+
+impl MeshoptFilter {
+  pub fn from_gltf_name(name: &str) -> Option<Self> {
+    match name {
+      "NONE" => Some(Self::None),
+      "OCTAHEDRAL" => Some(Self::Octahedral),
+      "QUATERNION" => Some(Self::Quaternion),
+      "EXPONENTIAL" => Some(Self::Exponential),
+      _ => None,
+    }
+  }
+
+  pub fn gltf_name(&self) -> &'static str {
+    match self {
+      Self::None => "NONE",
+      Self::Octahedral => "OCTAHEDRAL",
+      Self::Quaternion => "QUATERNION",
+      Self::Exponential => "EXPONENTIAL",
+    }
+  }
+}
+
+/// Apply the decode filter to the decoded vertex data in place.
+pub fn decode_filter(
+  filter: MeshoptFilter,
+  data: &mut [u8],
+  byte_stride: usize,
+) -> Result<(), MeshCodecError> {
+  match filter {
+    MeshoptFilter::None => {}
+    MeshoptFilter::Octahedral => match byte_stride {
+      4 => data.chunks_exact_mut(4).for_each(|v| {
+        let decoded =
+          decode_octahedral([v[0] as i8, v[1] as i8, v[2] as i8].map(|v| v as f32), 127.);
+        v[..3].copy_from_slice(&decoded.map(|v| v as i8 as u8));
+      }),
+      8 => data.chunks_exact_mut(8).for_each(|v| {
+        let decoded = decode_octahedral(
+          read_i16x4(v).map(|v| v as f32)[..3].try_into().unwrap(),
+          32767.,
+        );
+        for (i, d) in decoded.iter().enumerate() {
+          v[i * 2..i * 2 + 2].copy_from_slice(&(*d as i16).to_le_bytes());
+        }
+      }),
+      _ => {
+        return Err(MeshCodecError::InvalidParameter(
+          "the octahedral filter requires the byte stride of 4 or 8",
+        ));
+      }
+    },
+    MeshoptFilter::Quaternion => {
+      if byte_stride != 8 {
+        return Err(MeshCodecError::InvalidParameter(
+          "the quaternion filter requires the byte stride of 8",
+        ));
+      }
+      data.chunks_exact_mut(8).for_each(|v| {
+        let decoded = decode_quaternion(read_i16x4(v));
+        for (i, d) in decoded.iter().enumerate() {
+          v[i * 2..i * 2 + 2].copy_from_slice(&d.to_le_bytes());
+        }
+      });
+    }
+    MeshoptFilter::Exponential => {
+      if !byte_stride.is_multiple_of(4) {
+        return Err(MeshCodecError::InvalidParameter(
+          "the exponential filter requires the byte stride of multiple of 4",
+        ));
+      }
+      data.chunks_exact_mut(4).for_each(|v| {
+        let v: &mut [u8; 4] = v.try_into().unwrap();
+        *v = decode_exponential(u32::from_le_bytes(*v)).to_le_bytes();
+      });
+    }
+  }
+  Ok(())
+}
+
+fn read_i16x4(v: &[u8]) -> [i16; 4] {
+  std::array::from_fn(|i| i16::from_le_bytes([v[i * 2], v[i * 2 + 1]]))
+}
+
+fn round_to_i32(v: f32) -> i32 {
+  (v + if v >= 0. { 0.5 } else { -0.5 }) as i32
+}
+
+fn decode_octahedral([mut x, mut y, z]: [f32; 3], max: f32) -> [i32; 3] {
+  // the z component encodes 1.0 in the same precision as x and y
+  let z = z - x.abs() - y.abs();
+
+  // fixup the octahedral coordinates for z < 0
+  let t = z.min(0.);
+  x += if x >= 0. { t } else { -t };
+  y += if y >= 0. { t } else { -t };
+
+  let s = max / (x * x + y * y + z * z).sqrt();
+  [x, y, z].map(|v| round_to_i32(v * s))
+}
+
+fn decode_quaternion(v: [i16; 4]) -> [i16; 4] {
+  let scale = std::f32::consts::FRAC_1_SQRT_2;
+
+  // the scale is stored in the high bits of the w component
+  let sf = (v[3] | 3) as f32;
+  let ss = scale / sf;
+
+  let x = v[0] as f32 * ss;
+  let y = v[1] as f32 * ss;
+  let z = v[2] as f32 * ss;
+
+  // reconstruct w as the square root, clamp to 0 to avoid NaN due to the precision errors
+  let w = (1. - x * x - y * y - z * z).max(0.).sqrt();
+
+  // the max component index is stored in the low bits of the w component
+  let qc = (v[3] & 3) as usize;
+  let mut result = [0; 4];
+  result[(qc + 1) & 3] = round_to_i32(x * 32767.) as i16;
+  result[(qc + 2) & 3] = round_to_i32(y * 32767.) as i16;
+  result[(qc + 3) & 3] = round_to_i32(z * 32767.) as i16;
+  result[qc] = round_to_i32(w * 32767.) as i16;
+  result
+}
+
+fn decode_exponential(v: u32) -> u32 {
+  let m = ((v << 8) as i32) >> 8;
+  let e = (v as i32) >> 24;
+  // ldexp(m, e)
+  let scale = f32::from_bits(((e + 127) as u32) << 23);
+  (scale * m as f32).to_bits()
+}
+
+fn quantize_snorm(v: f32, bits: u32) -> i32 {
+  let scale = ((1 << (bits - 1)) - 1) as f32;
+  round_to_i32(v.clamp(-1., 1.) * scale)
+}
+
+/// Encode the unit vectors(the w component is kept as snorm) to be decoded by the
+/// [MeshoptFilter::Octahedral]. The bits is the precision of the octahedral coordinates, the
+/// output is snorm8 if the bits is not larger than 8, otherwise snorm16.
+pub fn encode_filter_octahedral(data: &[Vec4<f32>], bits: u32) -> Vec<u8> {
+  assert!((2..=16).contains(&bits));
+  let is_16 = bits > 8;
+
+  let mut result = Vec::with_capacity(data.len() * if is_16 { 8 } else { 4 });
+  for n in data {
+    let l = n.x.abs() + n.y.abs() + n.z.abs();
+    let s = if l == 0. { 0. } else { 1. / l };
+    let (nx, ny) = (n.x * s, n.y * s);
+
+    let sign = |v: f32| if v >= 0. { 1. } else { -1. };
+    let u = if n.z >= 0. {
+      nx
+    } else {
+      (1. - ny.abs()) * sign(nx)
+    };
+    let v = if n.z >= 0. {
+      ny
+    } else {
+      (1. - nx.abs()) * sign(ny)
+    };
+
+    let encoded = [
+      quantize_snorm(u, bits),
+      quantize_snorm(v, bits),
+      quantize_snorm(1., bits),
+      quantize_snorm(n.w, if is_16 { 16 } else { 8 }),
+    ];
+    for e in encoded {
+      if is_16 {
+        result.extend_from_slice(&(e as i16).to_le_bytes());
+      } else {
+        result.push(e as i8 as u8);
+      }
+    }
+  }
+  result
+}
@@ -0,0 +1,433 @@
+use crate::*;
+
+const INDEX_HEADER: u8 = 0xe0;
+const SEQUENCE_HEADER: u8 = 0xd0;
+const INDEX_CODEC_VERSION: u8 = 1;
+
+const TRIANGLE_INDEX_ORDER: [[usize; 3]; 3] = [[0, 1, 2], [1, 2, 0], [2, 0, 1]];
+
+/// the code aux table is generated by the meshoptimizer based on the symbol frequency of a
+/// training mesh set, the last two entries are not used for encoding.
+const CODE_AUX_ENCODING_TABLE: [u8; 16] = [
+  0x00, 0x76, 0x87, 0x56, 0x67, 0x78, 0xa9, 0x86, 0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0, 0,
+];
+
+struct EdgeFifo {
+  edges: [[u32; 2]; 16],
+  offset: usize,
+}
+
+impl EdgeFifo {
+  fn new() -> Self {
+    Self {
+      edges: [[u32::MAX; 2]; 16],
+      offset: 0,
+    }
+  }
+
+  fn get(&self, i: usize) -> [u32; 2] {
+    self.edges[(self.offset.wrapping_sub(1 + i)) & 15]
+  }
+
+  /// return the fifo position and the matched edge in the triangle
+  fn find(&self, a: u32, b: u32, c: u32) -> Option<(usize, usize)> {
+    (0..16).find_map(|i| match self.get(i) {
+      [e0, e1] if e0 == a && e1 == b => Some((i, 0)),
+      [e0, e1] if e0 == b && e1 == c => Some((i, 1)),
+      [e0, e1] if e0 == c && e1 == a => Some((i, 2)),
+      _ => None,
+    })
+  }
+
+  fn push(&mut self, a: u32, b: u32) {
+    self.edges[self.offset] = [a, b];
+    self.offset = (self.offset + 1) & 15;
+  }
+}
+
+struct VertexFifo {
+  vertices: [u32; 16],
+  offset: usize,
+}
+
+impl VertexFifo {
+  fn new() -> Self {
+    Self {
+      vertices: [u32::MAX; 16],
+      offset: 0,
+    }
+  }
+
+  fn reset(&mut self) {
+    self.vertices = [u32::MAX; 16];
+  }
+
+  fn get(&self, i: usize) -> u32 {
+    self.vertices[(self.offset.wrapping_sub(1 + i)) & 15]
+  }
+
+  fn find(&self, v: u32) -> Option<usize> {
+    (0..16).find(|i| self.get(*i) == v)
+  }
+
+  fn push(&mut self, v: u32, cond: bool) {
+    self.vertices[self.offset] = v;
+    self.offset = (self.offset + cond as usize) & 15;
+  }
+}
+
+/// The worst case encoded size of the triangle list index buffer.
+pub fn encode_index_buffer_bound(index_count: usize, vertex_count: usize) -> usize {
+  let vertex_bits = vertex_bits(vertex_count);
+  // worst-case encoding is 2 header bytes + 3 varint-7 encoded index deltas
+  let vertex_groups = (vertex_bits + 1).div_ceil(7);
+  1 + (index_count / 3) * (2 + 3 * vertex_groups) + 16
+}
+
+fn vertex_bits(vertex_count: usize) -> usize {
+  let mut vertex_bits = 1;
+  while vertex_bits < 32 && vertex_count > 1 << vertex_bits {
+    vertex_bits += 1;
+  }
+  vertex_bits
+}
+
+/// Encode the triangle list index buffer. The triangles are encoded by the edge and vertex fifo,
+/// so the output is much smaller if the index buffer is optimized for the vertex cache first.
+///
+/// The triangle winding is preserved, but the vertex order of each triangle may be rotated.
+pub fn encode_index_buffer(indices: &[u32]) -> Vec<u8> {
+  assert!(indices.len().is_multiple_of(3));
+  let face_count = indices.len() / 3;
+
+  let mut code = Vec::with_capacity(face_count);
+  let mut data = Vec::with_capacity(face_count * 2);
+
+  let mut edge_fifo = EdgeFifo::new();
+  let mut vertex_fifo = VertexFifo::new();
+  let mut next = 0_u32;
+  let mut last = 0_u32;
+  let fec_max = 13;
+
+  for triangle in indices.chunks_exact(3) {
+    if let Some((fe, edge)) = edge_fifo.find(triangle[0], triangle[1], triangle[2])
+      && fe < 15
+    {
+      // the triangle is rotated by matching a/b to the existing edge
+      let order = TRIANGLE_INDEX_ORDER[edge];
+      let (a, b, c) = (triangle[order[0]], triangle[order[1]], triangle[order[2]]);
+
+      let mut fec = match vertex_fifo.find(c) {
+        Some(fc) if (1..fec_max).contains(&fc) => fc,
+        _ if c == next => {
+          next += 1;
+          0
+        }
+        _ => 15,
+      };
+
+      // encode last-1 and last+1 to optimize the strip like sequences
+      if fec == 15 {
+        if c.wrapping_add(1) == last {
+          fec = 13;
+          last = c;
+        }
+        if c == last.wrapping_add(1) {
+          fec = 14;
+          last = c;
+        }
+      }
+
+      code.push(((fe << 4) | fec) as u8);
+
+      // the free indices are delta encoded
+      if fec == 15 {
+        encode_vbyte(&mut data, zigzag_encode_delta(c, last));
+        last = c;
+      }
+
+      // only the third vertex is pushed, the first two are likely already in the vertex fifo
+      if fec == 0 || fec >= fec_max {
+        vertex_fifo.push(c, true);
+      }
+
+      // the third edge is already in the fifo
+      edge_fifo.push(c, b);
+      edge_fifo.push(a, c);
+    } else {
+      let rotation = if triangle[1] == next {
+        1
+      } else if triangle[2] == next {
+        2
+      } else {
+        0
+      };
+      let order = TRIANGLE_INDEX_ORDER[rotation];
+      let (a, b, c) = (triangle[order[0]], triangle[order[1]], triangle[order[2]]);
+
+      // if a/b/c are 0/1/2, emit a reset code
+      let reset = a == 0 && b == 1 && c == 2 && next > 0;
+      if reset {
+        next = 0;
+        // make sure the vertices before the reset are never referenced
+        vertex_fifo.reset();
+      }
+
+      let fb = vertex_fifo.find(b);
+      let fc = vertex_fifo.find(c);
+
+      // after the rotation, a is almost always equal to next, so we don't waste bits on the fifo
+      // encoding for a. the decoder assumes that if feb=fec=0, then fea=0 (the reset code),
+      // this is enforced by the rotation
+      let mut encode_free = |v: u32, fifo: Option<usize>, min: usize| match fifo {
+        Some(f) if f < 14 => f + min,
+        _ if v == next => {
+          next += 1;
+          0
+        }
+        _ => 15,
+      };
+      let fea = encode_free(a, None, 0);
+      // feb and fec use 1-15, as 0 is reserved for the reset code
+      let feb = encode_free(b, fb, 1);
+      let fec = encode_free(c, fc, 1);
+
+      // feb and fec are encoded in 4 bits using the table if possible, and as a full byte
+      // otherwise
+      let code_aux = ((feb << 4) | fec) as u8;
+      let code_aux_index = CODE_AUX_ENCODING_TABLE.iter().position(|v| *v == code_aux);
+
+      // < 14 encodes an index into the code aux table, 14 encodes fea=0, 15 encodes fea=15
+      match code_aux_index {
+        Some(index) if fea == 0 && index < 14 && !reset => code.push(0xf0 | index as u8),
+        _ => {
+          code.push(0xf0 | 14 | fea as u8);
+          data.push(code_aux);
+        }
+      }
+
+      for (v, fe) in [(a, fea), (b, feb), (c, fec)] {
+        if fe == 15 {
+          encode_vbyte(&mut data, zigzag_encode_delta(v, last));
+          last = v;
+        }
+      }
+
+      // only push the vertices that weren't already in the fifo
+      for (v, fe) in [(a, fea), (b, feb), (c, fec)] {
+        if fe == 0 || fe == 15 {
+          vertex_fifo.push(v, true);
+        }
+      }
+
+      // push all the edges so that they can be matched by the later triangles
+      edge_fifo.push(b, a);
+      edge_fifo.push(c, b);
+      edge_fifo.push(a, c);
+    }
+  }
+
+  // the code aux table is used for decoding the code aux, and as the padding that makes sure
+  // each triangle can be decoded without the extra bound check.
+  let mut result = Vec::with_capacity(1 + code.len() + data.len() + 16);
+  result.push(INDEX_HEADER | INDEX_CODEC_VERSION);
+  result.extend_from_slice(&code);
+  result.extend_from_slice(&data);
+  result.extend_from_slice(&CODE_AUX_ENCODING_TABLE);
+  result
+}
+
+/// Decode the index buffer encoded by [encode_index_buffer].
+pub fn decode_index_buffer(index_count: usize, buffer: &[u8]) -> Result<Vec<u32>, MeshCodecError> {
+  if !index_count.is_multiple_of(3) {
+    return Err(MeshCodecError::InvalidParameter(
+      "the index count is not multiple of 3",
+    ));
+  }
+  let face_count = index_count / 3;
+
+  // the minimum valid encoding is header, 1 byte per triangle and a 16-byte code aux table
+  if buffer.len() < 1 + face_count + 16 {
+    return Err(MeshCodecError::UnexpectedEnd);
+  }
+  if buffer[0] & 0xf0 != INDEX_HEADER {
+    return Err(MeshCodecError::InvalidHeader);
+  }
+  let version = buffer[0] & 0x0f;
+  if version > 1 {
+    return Err(MeshCodecError::UnsupportedVersion(version));
+  }
+  let fec_max = if version >= 1 { 13 } else { 15 };
+
+  let code = &buffer[1..1 + face_count];
+  let data_safe_end = buffer.len() - 16;
+  let code_aux_table = &buffer[data_safe_end..];
+  let mut data = 1 + face_count;
+
+  let mut edge_fifo = EdgeFifo::new();
+  let mut vertex_fifo = VertexFifo::new();
+  let mut next = 0_u32;
+  let mut last = 0_u32;
+
+  let mut result = Vec::with_capacity(index_count);
+
+  for code_tri in code.iter().copied() {
+    // each triangle reads at most 16 bytes of data: 1b for code aux and 5b for each free index,
+    // the code aux table acts as the padding
+    if data > data_safe_end {
+      return Err(MeshCodecError::UnexpectedEnd);
+    }
+
+    if code_tri < 0xf0 {
+      let fe = (code_tri >> 4) as usize;
+      let [a, b] = edge_fifo.get(fe);
+
+      let fec = (code_tri & 15) as usize;
+      let c = if fec < fec_max {
+        let c = if fec == 0 { next } else { vertex_fifo.get(fec) };
+        next += (fec == 0) as u32;
+        vertex_fifo.push(c, fec == 0);
+        c
+      } else {
+        last = match fec {
+          13 => last.wrapping_sub(1),
+          14 => last.wrapping_add(1),
+          _ => zigzag_decode_delta(decode_vbyte(buffer, &mut data), last),
+        };
+        vertex_fifo.push(last, true);
+        last
+      };
+
+      edge_fifo.push(c, b);
+      edge_fifo.push(a, c);
+      result.extend_from_slice(&[a, b, c]);
+    } else {
+      let (fea, code_aux) = if code_tri < 0xfe {
+        (0, code_aux_table[(code_tri & 15) as usize])
+      } else {
+        let code_aux = buffer[data];
+        data += 1;
+        let fea = if code_tri == 0xfe { 0 } else { 15 };
+        // reset: the code aux is 0 but encoded as not-a-table
+        if code_aux == 0 {
+          next = 0;
+        }
+        (fea, code_aux)
+      };
+      let feb = (code_aux >> 4) as usize;
+      let fec = (code_aux & 15) as usize;
+
+      // next is incremented for all three vertices before decoding the free indices, this
+      // matches the encoder behavior
+      let mut decode_fifo = |fe: usize| match fe {
+        0 => {
+          next += 1;
+          next - 1
+        }
+        15 => 0,
+        _ => vertex_fifo.get(fe - 1),
+      };
+      let mut a = decode_fifo(fea);
+      let mut b = decode_fifo(feb);
+      let mut c = decode_fifo(fec);
+
+      for (v, fe) in [(&mut a, fea), (&mut b, feb), (&mut c, fec)] {
+        if fe == 15 {
+          last = zigzag_decode_delta(decode_vbyte(buffer, &mut data), last);
+          *v = last;
+        }
+      }
+
+      result.extend_from_slice(&[a, b, c]);
+
+      vertex_fifo.push(a, true);
+      vertex_fifo.push(b, feb == 0 || feb == 15);
+      vertex_fifo.push(c, fec == 0 || fec == 15);
+
+      edge_fifo.push(b, a);
+      edge_fifo.push(c, b);
+      edge_fifo.push(a, c);
+    }
+  }
+
+  // all the data bytes should be read and stopped at the boundary of the code aux table
+  if data != data_safe_end {
+    return Err(MeshCodecError::TrailingData);
+  }
+
+  Ok(result)
+}
+
+/// The worst case encoded size of the index sequence.
+pub fn encode_index_sequence_bound(index_count: usize, vertex_count: usize) -> usize {
+  // worst-case encoding is 1 varint-7 encoded index delta for a K bit value and an extra bit
+  let vertex_groups = (vertex_bits(vertex_count) + 2).div_ceil(7);
+  1 + index_count * vertex_groups + 4
+}
+
+/// Encode the index sequence that is not the triangle list, for example the line list or the
+/// triangle strip. The order of the indices is preserved.
+pub fn encode_index_sequence(indices: &[u32]) -> Vec<u8> {
+  let mut data = Vec::with_capacity(1 + indices.len() + 4);
+  data.push(SEQUENCE_HEADER | INDEX_CODEC_VERSION);
+
+  let mut last = [0_u32; 2];
+  let mut current = 0;
+
+  for index in indices.iter().copied() {
+    // switch the baseline when the delta grows too large, we want the encoded delta to fit into
+    // one byte (7 bits), but 2 bits are used for the sign and the baseline index
+    let cd = index.wrapping_sub(last[current]) as i32;
+    current ^= (cd.unsigned_abs() >= 30) as usize;
+
+    let v = zigzag_encode_delta(index, last[current]);
+    // the low bit encodes the index of the baseline used for the reconstruction
+    encode_vbyte(&mut data, (v << 1) | current as u32);
+    last[current] = index;
+  }
+
+  // the tail makes sure each index can be decoded without the extra bound check
+  data.extend_from_slice(&[0; 4]);
+  data
+}
+
+/// Decode the index sequence encoded by [encode_index_sequence].
+pub fn decode_index_sequence(
+  index_count: usize,
+  buffer: &[u8],
+) -> Result<Vec<u32>, MeshCodecError> {
+  // the minimum valid encoding is header, 1 byte per index and a 4-byte tail
+  if buffer.len() < 1 + index_count + 4 {
+    return Err(MeshCodecError::UnexpectedEnd);
+  }
+  if buffer[0] & 0xf0 != SEQUENCE_HEADER {
+    return Err(MeshCodecError::InvalidHeader);
+  }
+  let version = buffer[0] & 0x0f;
+  if version > 1 {
+    return Err(MeshCodecError::UnsupportedVersion(version));
+  }
+
+  let data_safe_end = buffer.len() - 4;
+  let mut data = 1;
+  let mut last = [0_u32; 2];
+
+  let mut result = Vec::with_capacity(index_count);
+  for _ in 0..index_count {
+    // each index reads at most 5 bytes of data, there's a 4 byte tail after data_safe_end
+    if data >= data_safe_end {
+      return Err(MeshCodecError::UnexpectedEnd);
+    }
+    let v = decode_vbyte(buffer, &mut data);
+    let current = (v & 1) as usize;
+    let index = zigzag_decode_delta(v >> 1, last[current]);
+    last[current] = index;
+    result.push(index);
+  }
+
+  if data != data_safe_end {
+    return Err(MeshCodecError::TrailingData);
+  }
+
+  Ok(result)
+}
@@ -0,0 +1,79 @@
+//! The mesh index and vertex stream codec, the bitstream is compatible with the meshoptimizer
+//! (index codec version 1, vertex codec version 0) so the encoded data can be used as the
+//! `EXT_meshopt_compression` buffer view directly.
+//!
+//! The codec is lossless, to improve the compression ratio the vertex attributes can be quantized
+//! first, see [quantize_positions] and [quantize_normals_octahedral]. The quantized data is
+//! decodable both on the cpu and in shader.
+
+use rendiation_algebra::*;
+use rendiation_geometry::*;
+use rendiation_shader_api::*;
+
+mod filter;
+mod index_codec;
+mod quantization;
+mod vertex_codec;
+
+pub use filter::*;
+pub use index_codec::*;
+pub use quantization::*;
+pub use vertex_codec::*;
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshCodecError {
+  #[error("the encoded stream header is not matched")]
+  InvalidHeader,
+  #[error("unsupported codec version: {0}")]
+  UnsupportedVersion(u8),
+  #[error("unexpected end of the encoded stream")]
+  UnexpectedEnd,
+  #[error("the encoded stream has unexpected trailing data")]
+  TrailingData,
+  #[error("invalid decode parameter: {0}")]
+  InvalidParameter(&'static str),
+}
+
+fn encode_vbyte(data: &mut Vec<u8>, mut v: u32) {
+  // encode 32-bit value in up to 5 7-bit groups
+  loop {
+    data.push((v & 127) as u8 | if v > 127 { 128 } else { 0 });
+    v >>= 7;
+    if v == 0 {
+      break;
+    }
+  }
+}
+
+/// the caller make sure there are at least 5 bytes readable
+fn decode_vbyte(data: &[u8], cursor: &mut usize) -> u32 {
+  let lead = data[*cursor];
+  *cursor += 1;
+  if lead < 128 {
+    return lead as u32;
+  }
+
+  // the loop always terminates, which is important for the malformed data
+  let mut result = (lead & 127) as u32;
+  let mut shift = 7;
+  for _ in 0..4 {
+    let group = data[*cursor];
+    *cursor += 1;
+    result |= ((group & 127) as u32) << shift;
+    shift += 7;
+    if group < 128 {
+      break;
+    }
+  }
+  result
+}
+
+fn zigzag_encode_delta(index: u32, last: u32) -> u32 {
+  let d = index.wrapping_sub(last);
+  (d << 1) ^ (((d as i32) >> 31) as u32)
+}
+
+fn zigzag_decode_delta(v: u32, last: u32) -> u32 {
+  let d = (v >> 1) ^ (v & 1).wrapping_neg();
+  last.wrapping_add(d)
+}
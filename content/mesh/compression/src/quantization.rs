@@ -0,0 +1,122 @@
+use rendiation_shader_library::octahedral::*;
+
+use crate::*;
+
+/// The positions quantized to the unorm grid inside the bounding box, each position is stored in
+/// 8 bytes: the low and high 16 bits of x are the x and y component, the low 16 bits of y are the
+/// z component. The compact layout is friendly for both the vertex codec and the storage buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedPositions {
+  pub data: Vec<Vec2<u32>>,
+  /// the bounding box min
+  pub offset: Vec3<f32>,
+  /// the size of one quantization step on each axis
+  pub scale: Vec3<f32>,
+  /// the max distance between the original and the dequantized position
+  pub max_error: f32,
+}
+
+impl QuantizedPositions {
+  pub fn dequantize(&self, index: usize) -> Vec3<f32> {
+    dequantize_position_cpu(self.data[index], self.offset, self.scale)
+  }
+
+  /// the theoretical error bound of the quantization grid, the reported max_error never exceeds
+  /// this value
+  pub fn error_bound(&self) -> f32 {
+    (self.scale * 0.5).length()
+  }
+}
+
+/// Quantize the positions with the given bits(1..=16) on each axis.
+pub fn quantize_positions(positions: &[Vec3<f32>], bits: u32) -> QuantizedPositions {
+  assert!((1..=16).contains(&bits));
+  let max = ((1_u32 << bits) - 1) as f32;
+
+  let bbox: Box3 = positions.iter().copied().collect();
+  let (offset, size) = if positions.is_empty() {
+    (Vec3::zero(), Vec3::zero())
+  } else {
+    (bbox.min, bbox.size())
+  };
+  // the zero extent axis keeps the zero scale, all the positions on it are exactly the offset
+  let scale = size / max;
+
+  let quantize = |v: f32, offset: f32, scale: f32| {
+    if scale > 0. {
+      ((v - offset) / scale).round().clamp(0., max) as u32
+    } else {
+      0
+    }
+  };
+
+  let mut max_error: f32 = 0.;
+  let data = positions
+    .iter()
+    .map(|p| {
+      let x = quantize(p.x, offset.x, scale.x);
+      let y = quantize(p.y, offset.y, scale.y);
+      let z = quantize(p.z, offset.z, scale.z);
+      let packed = Vec2::new(x | (y << 16), z);
+      let error = (dequantize_position_cpu(packed, offset, scale) - *p).length();
+      max_error = max_error.max(error);
+      packed
+    })
+    .collect();
+
+  QuantizedPositions {
+    data,
+    offset,
+    scale,
+    max_error,
+  }
+}
+
+pub fn dequantize_position_cpu(
+  packed: Vec2<u32>,
+  offset: Vec3<f32>,
+  scale: Vec3<f32>,
+) -> Vec3<f32> {
+  let q = Vec3::new(packed.x & 0xffff, packed.x >> 16, packed.y & 0xffff).map(|v| v as f32);
+  offset + q * scale
+}
+
+#[shader_fn]
+pub fn dequantize_position(
+  packed: Node<Vec2<u32>>,
+  offset: Node<Vec3<f32>>,
+  scale: Node<Vec3<f32>>,
+) -> Node<Vec3<f32>> {
+  let x = (packed.x() & val(0xffff)).into_f32();
+  let y = (packed.x() >> val(16)).into_f32();
+  let z = (packed.y() & val(0xffff)).into_f32();
+  offset + vec3_node((x, y, z)) * scale
+}
+
+/// The unit normals encoded as the octahedral 16:16 format, decodable by the
+/// [decode_octahedral_normal] in shader and [decode_octahedral_normal_cpu] on cpu.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedNormals {
+  pub data: Vec<u32>,
+  /// the max angle in radians between the original and the decoded normal
+  pub max_angle_error: f32,
+}
+
+pub fn quantize_normals_octahedral(normals: &[Vec3<f32>]) -> QuantizedNormals {
+  let mut max_angle_error: f32 = 0.;
+  let data = normals
+    .iter()
+    .map(|n| {
+      let packed = encode_octahedral_normal(*n);
+      let decoded = decode_octahedral_normal_cpu(packed);
+      let cos = n.normalize().dot(decoded).clamp(-1., 1.);
+      max_angle_error = max_angle_error.max(cos.acos());
+      packed
+    })
+    .collect();
+
+  QuantizedNormals {
+    data,
+    max_angle_error,
+  }
+}
@@ -0,0 +1,265 @@
+use crate::*;
+
+const VERTEX_HEADER: u8 = 0xa0;
+const VERTEX_BLOCK_SIZE_BYTES: usize = 8192;
+const VERTEX_BLOCK_MAX_SIZE: usize = 256;
+const BYTE_GROUP_SIZE: usize = 16;
+const BYTE_GROUP_DECODE_LIMIT: usize = 24;
+const TAIL_MAX_SIZE: usize = 32;
+
+fn vertex_block_size(vertex_size: usize) -> usize {
+  // make sure the entire block fits into the scratch buffer, and align to the byte group size
+  let result = (VERTEX_BLOCK_SIZE_BYTES / vertex_size) & !(BYTE_GROUP_SIZE - 1);
+  result.min(VERTEX_BLOCK_MAX_SIZE)
+}
+
+fn check_vertex_size(vertex_size: usize) -> Result<(), MeshCodecError> {
+  if vertex_size == 0 || vertex_size > 256 || !vertex_size.is_multiple_of(4) {
+    return Err(MeshCodecError::InvalidParameter(
+      "the vertex size should be multiple of 4 and not larger than 256",
+    ));
+  }
+  Ok(())
+}
+
+fn zigzag8(v: u8) -> u8 {
+  (((v as i8) >> 7) as u8) ^ (v << 1)
+}
+
+fn unzigzag8(v: u8) -> u8 {
+  (v & 1).wrapping_neg() ^ (v >> 1)
+}
+
+/// the encoded size of the byte group, None if the group can not be encoded by the bits
+fn measure_bytes_group(group: &[u8], bits: usize) -> Option<usize> {
+  match bits {
+    1 => group.iter().all(|v| *v == 0).then_some(0),
+    8 => Some(BYTE_GROUP_SIZE),
+    _ => {
+      let sentinel = (1 << bits) - 1;
+      let fixed = BYTE_GROUP_SIZE * bits / 8;
+      Some(fixed + group.iter().filter(|v| **v >= sentinel).count())
+    }
+  }
+}
+
+fn encode_bytes_group(data: &mut Vec<u8>, group: &[u8], bits: usize) {
+  match bits {
+    1 => {}
+    8 => data.extend_from_slice(group),
+    _ => {
+      // fixed portion: the bits for each value, the out of range value is encoded as sentinel
+      // variable portion: the full byte for each out of range value
+      let sentinel = (1 << bits) - 1;
+      for chunk in group.chunks_exact(8 / bits) {
+        let byte = chunk
+          .iter()
+          .fold(0, |byte, v| (byte << bits) | (*v).min(sentinel));
+        data.push(byte);
+      }
+      data.extend(group.iter().filter(|v| **v >= sentinel));
+    }
+  }
+}
+
+fn encode_bytes(data: &mut Vec<u8>, buffer: &[u8]) {
+  assert!(buffer.len().is_multiple_of(BYTE_GROUP_SIZE));
+
+  // each group use 2 bits in the header
+  let header_offset = data.len();
+  let header_size = (buffer.len() / BYTE_GROUP_SIZE).div_ceil(4);
+  data.resize(header_offset + header_size, 0);
+
+  for (i, group) in buffer.chunks_exact(BYTE_GROUP_SIZE).enumerate() {
+    // prefer the full byte and then the fewer bits when the encoded size is same
+    let (bits_log2, _) = [3, 0, 1, 2]
+      .into_iter()
+      .filter_map(|bits_log2| Some((bits_log2, measure_bytes_group(group, 1 << bits_log2)?)))
+      .min_by_key(|(_, size)| *size)
+      .unwrap();
+
+    data[header_offset + i / 4] |= (bits_log2 << ((i % 4) * 2)) as u8;
+    encode_bytes_group(data, group, 1 << bits_log2);
+  }
+}
+
+/// The worst case encoded size of the vertex buffer.
+pub fn encode_vertex_buffer_bound(vertex_count: usize, vertex_size: usize) -> usize {
+  let block_size = vertex_block_size(vertex_size);
+  let block_count = vertex_count.div_ceil(block_size);
+  let block_header_size = (block_size / BYTE_GROUP_SIZE).div_ceil(4);
+  let tail_size = vertex_size.max(TAIL_MAX_SIZE);
+  1 + block_count * vertex_size * (block_header_size + block_size) + tail_size
+}
+
+/// Encode the vertex buffer, each vertex has vertex_size bytes that should be multiple of 4 and
+/// not larger than 256. The vertices are delta encoded byte by byte, so the output is much smaller
+/// if the vertex buffer is optimized for the vertex fetch and the attributes are quantized.
+pub fn encode_vertex_buffer(
+  vertices: &[u8],
+  vertex_size: usize,
+) -> Result<Vec<u8>, MeshCodecError> {
+  check_vertex_size(vertex_size)?;
+  if !vertices.len().is_multiple_of(vertex_size) {
+    return Err(MeshCodecError::InvalidParameter(
+      "the vertex data is not multiple of the vertex size",
+    ));
+  }
+  let vertex_count = vertices.len() / vertex_size;
+
+  let mut data = Vec::with_capacity(vertices.len() / 2 + TAIL_MAX_SIZE);
+  data.push(VERTEX_HEADER);
+
+  let mut first_vertex = [0; 256];
+  if vertex_count > 0 {
+    first_vertex[..vertex_size].copy_from_slice(&vertices[..vertex_size]);
+  }
+  let mut last_vertex = first_vertex;
+
+  let mut buffer = [0_u8; VERTEX_BLOCK_MAX_SIZE];
+  for block in vertices.chunks(vertex_block_size(vertex_size) * vertex_size) {
+    let block_vertex_count = block.len() / vertex_size;
+    let aligned_count = block_vertex_count.next_multiple_of(BYTE_GROUP_SIZE);
+
+    for k in 0..vertex_size {
+      // the elements not filled when rounding to the group size are encoded as zero
+      buffer.fill(0);
+      let mut p = last_vertex[k];
+      for (i, vertex) in block.chunks_exact(vertex_size).enumerate() {
+        buffer[i] = zigzag8(vertex[k].wrapping_sub(p));
+        p = vertex[k];
+      }
+      encode_bytes(&mut data, &buffer[..aligned_count]);
+    }
+
+    let last = &block[block.len() - vertex_size..];
+    last_vertex[..vertex_size].copy_from_slice(last);
+  }
+
+  // the first vertex is written to the end of the stream and padded to 32 bytes, this
+  // simplifies the bound checks in the decoder
+  if vertex_size < TAIL_MAX_SIZE {
+    data.resize(data.len() + TAIL_MAX_SIZE - vertex_size, 0);
+  }
+  data.extend_from_slice(&first_vertex[..vertex_size]);
+
+  Ok(data)
+}
+
+fn decode_bytes_group(data: &[u8], cursor: &mut usize, group: &mut [u8], bits_log2: u8) {
+  match bits_log2 {
+    0 => group.fill(0),
+    3 => {
+      group.copy_from_slice(&data[*cursor..*cursor + BYTE_GROUP_SIZE]);
+      *cursor += BYTE_GROUP_SIZE;
+    }
+    _ => {
+      let bits = 1 << bits_log2;
+      let sentinel = (1 << bits) - 1;
+      let per_byte = 8 / bits;
+      let fixed = &data[*cursor..*cursor + BYTE_GROUP_SIZE * bits / 8];
+      let mut variable = *cursor + fixed.len();
+
+      for (i, v) in group.iter_mut().enumerate() {
+        let shift = 8 - bits * (i % per_byte + 1);
+        let enc = (fixed[i / per_byte] >> shift) & sentinel;
+        *v = if enc == sentinel {
+          variable += 1;
+          data[variable - 1]
+        } else {
+          enc
+        };
+      }
+      *cursor = variable;
+    }
+  }
+}
+
+fn decode_bytes(
+  data: &[u8],
+  cursor: &mut usize,
+  data_end: usize,
+  buffer: &mut [u8],
+) -> Result<(), MeshCodecError> {
+  let header_size = (buffer.len() / BYTE_GROUP_SIZE).div_ceil(4);
+  if data_end - *cursor < header_size {
+    return Err(MeshCodecError::UnexpectedEnd);
+  }
+  let header = *cursor;
+  *cursor += header_size;
+
+  for (i, group) in buffer.chunks_exact_mut(BYTE_GROUP_SIZE).enumerate() {
+    // a group reads at most 24 bytes, the tail acts as the padding
+    if data_end - *cursor < BYTE_GROUP_DECODE_LIMIT {
+      return Err(MeshCodecError::UnexpectedEnd);
+    }
+    let bits_log2 = (data[header + i / 4] >> ((i % 4) * 2)) & 3;
+    decode_bytes_group(data, cursor, group, bits_log2);
+  }
+  Ok(())
+}
+
+/// Decode the vertex buffer encoded by [encode_vertex_buffer].
+pub fn decode_vertex_buffer(
+  vertex_count: usize,
+  vertex_size: usize,
+  buffer: &[u8],
+) -> Result<Vec<u8>, MeshCodecError> {
+  check_vertex_size(vertex_size)?;
+  if buffer.len() < 1 + vertex_size {
+    return Err(MeshCodecError::UnexpectedEnd);
+  }
+  if buffer[0] & 0xf0 != VERTEX_HEADER {
+    return Err(MeshCodecError::InvalidHeader);
+  }
+  let version = buffer[0] & 0x0f;
+  if version > 0 {
+    return Err(MeshCodecError::UnsupportedVersion(version));
+  }
+
+  let result_size =
+    vertex_count
+      .checked_mul(vertex_size)
+      .ok_or(MeshCodecError::InvalidParameter(
+        "the decoded vertex buffer size overflows",
+      ))?;
+  // the minimum valid encoding is header, the byte group headers of each block and the tail
+  let block_size = vertex_block_size(vertex_size);
+  let group_header_size = |count: usize| count.div_ceil(BYTE_GROUP_SIZE).div_ceil(4);
+  let min_blocks_size = (vertex_count / block_size) * group_header_size(block_size)
+    + group_header_size(vertex_count % block_size);
+  if buffer.len() < 1 + min_blocks_size * vertex_size + vertex_size.max(TAIL_MAX_SIZE) {
+    return Err(MeshCodecError::UnexpectedEnd);
+  }
+
+  let mut last_vertex = [0; 256];
+  last_vertex[..vertex_size].copy_from_slice(&buffer[buffer.len() - vertex_size..]);
+
+  let mut result = vec![0; result_size];
+  let mut cursor = 1;
+  let data_end = buffer.len();
+
+  let mut scratch = [0_u8; VERTEX_BLOCK_MAX_SIZE];
+  for block in result.chunks_mut(block_size * vertex_size) {
+    let block_vertex_count = block.len() / vertex_size;
+    let aligned_count = block_vertex_count.next_multiple_of(BYTE_GROUP_SIZE);
+
+    for k in 0..vertex_size {
+      decode_bytes(buffer, &mut cursor, data_end, &mut scratch[..aligned_count])?;
+
+      let mut p = last_vertex[k];
+      for (i, vertex) in block.chunks_exact_mut(vertex_size).enumerate() {
+        p = unzigzag8(scratch[i]).wrapping_add(p);
+        vertex[k] = p;
+      }
+    }
+
+    last_vertex[..vertex_size].copy_from_slice(&block[block.len() - vertex_size..]);
+  }
+
+  if data_end - cursor != vertex_size.max(TAIL_MAX_SIZE) {
+    return Err(MeshCodecError::TrailingData);
+  }
+
+  Ok(result)
+}
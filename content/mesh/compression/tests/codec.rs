@@ -0,0 +1,195 @@
+use rendiation_algebra::*;
+use rendiation_mesh_compression::*;
+
+fn grid(size: u32) -> (Vec<Vec3<f32>>, Vec<u32>) {
+  let mut positions = Vec::new();
+  for y in 0..=size {
+    for x in 0..=size {
+      let h = ((x * 7 + y * 13) % 5) as f32 * 0.1;
+      positions.push(Vec3::new(x as f32, y as f32, h));
+    }
+  }
+
+  let mut indices = Vec::new();
+  for y in 0..size {
+    for x in 0..size {
+      let a = y * (size + 1) + x;
+      let b = a + 1;
+      let c = a + size + 1;
+      let d = c + 1;
+      indices.extend_from_slice(&[a, b, c, b, d, c]);
+    }
+  }
+  (positions, indices)
+}
+
+fn sorted_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+  let mut triangles: Vec<[u32; 3]> = indices
+    .chunks_exact(3)
+    .map(|t| {
+      // rotate to the smallest index first to keep the winding
+      let min = (0..3).min_by_key(|i| t[*i]).unwrap();
+      [t[min], t[(min + 1) % 3], t[(min + 2) % 3]]
+    })
+    .collect();
+  triangles.sort();
+  triangles
+}
+
+#[test]
+fn index_buffer_round_trip() {
+  let (positions, mut indices) = grid(32);
+  // some random triangles to cover the free index encoding
+  indices.extend_from_slice(&[1000, 5, 700, 3, 3, 900, 0, 1, 2, 0, 1, 2]);
+
+  let encoded = encode_index_buffer(&indices);
+  assert!(encoded.len() <= encode_index_buffer_bound(indices.len(), positions.len() + 1000));
+  assert!(encoded.len() < indices.len());
+
+  let decoded = decode_index_buffer(indices.len(), &encoded).unwrap();
+  assert_eq!(sorted_triangles(&indices), sorted_triangles(&decoded));
+  // the triangle order is kept
+  for (a, b) in indices.chunks_exact(3).zip(decoded.chunks_exact(3)) {
+    assert_eq!(sorted_triangles(a), sorted_triangles(b));
+  }
+
+  assert!(decode_index_buffer(indices.len(), &encoded[..encoded.len() - 1]).is_err());
+  assert!(decode_index_buffer(indices.len() + 3, &encoded).is_err());
+  assert_eq!(
+    decode_index_buffer(indices.len(), &encode_index_sequence(&indices)),
+    Err(MeshCodecError::InvalidHeader)
+  );
+}
+
+#[test]
+fn index_sequence_round_trip() {
+  let indices: Vec<u32> = (0..1000).map(|i| (i * 7919) % 1500).collect();
+  let encoded = encode_index_sequence(&indices);
+  assert!(encoded.len() <= encode_index_sequence_bound(indices.len(), 1500));
+  assert_eq!(
+    decode_index_sequence(indices.len(), &encoded).unwrap(),
+    indices
+  );
+
+  let strip: Vec<u32> = (0..1000).collect();
+  let encoded = encode_index_sequence(&strip);
+  assert_eq!(encoded.len(), 1 + strip.len() + 4);
+  assert_eq!(decode_index_sequence(strip.len(), &encoded).unwrap(), strip);
+}
+
+#[test]
+fn vertex_buffer_round_trip() {
+  let (positions, _) = grid(40);
+  let bytes: &[u8] = bytemuck::cast_slice(&positions);
+  let encoded = encode_vertex_buffer(bytes, 12).unwrap();
+  assert!(encoded.len() <= encode_vertex_buffer_bound(positions.len(), 12));
+  let decoded = decode_vertex_buffer(positions.len(), 12, &encoded).unwrap();
+  assert_eq!(decoded, bytes);
+
+  // the quantized data is much more compressible
+  let quantized = quantize_positions(&positions, 12);
+  let quantized_bytes: &[u8] = bytemuck::cast_slice(&quantized.data);
+  let encoded_quantized = encode_vertex_buffer(quantized_bytes, 8).unwrap();
+  assert!(encoded_quantized.len() < quantized_bytes.len() / 2);
+  let decoded = decode_vertex_buffer(positions.len(), 8, &encoded_quantized).unwrap();
+  assert_eq!(decoded, quantized_bytes);
+
+  let empty = encode_vertex_buffer(&[], 16).unwrap();
+  assert!(decode_vertex_buffer(0, 16, &empty).unwrap().is_empty());
+  assert!(encode_vertex_buffer(bytes, 6).is_err());
+  assert!(decode_vertex_buffer(positions.len(), 12, &encoded[..encoded.len() / 2]).is_err());
+  // the vertex count is untrusted, reject the one the input can not encode before allocating
+  assert_eq!(
+    decode_vertex_buffer(usize::MAX / 4, 12, &encoded),
+    Err(MeshCodecError::InvalidParameter(
+      "the decoded vertex buffer size overflows"
+    ))
+  );
+  assert_eq!(
+    decode_vertex_buffer(u32::MAX as usize, 12, &encoded),
+    Err(MeshCodecError::UnexpectedEnd)
+  );
+}
+
+#[test]
+fn position_quantization_error_bound() {
+  let (positions, _) = grid(20);
+  for bits in [4, 10, 16] {
+    let quantized = quantize_positions(&positions, bits);
+    assert!(quantized.max_error <= quantized.error_bound() + f32::EPSILON * 32.);
+    for (i, p) in positions.iter().enumerate() {
+      assert!((quantized.dequantize(i) - *p).length() <= quantized.max_error + f32::EPSILON);
+    }
+  }
+  // the grid positions are exactly representable
+  let quantized = quantize_positions(&[Vec3::zero(), Vec3::new(4., 2., 0.)], 2);
+  assert_eq!(quantized.max_error, 0.);
+}
+
+#[test]
+fn normal_quantization_error_bound() {
+  let normals: Vec<_> = (0..100)
+    .map(|i| {
+      let t = i as f32 * 0.37;
+      Vec3::new(
+        t.sin() * (t * 0.5).cos(),
+        t.cos(),
+        t.sin() * (t * 0.5).sin(),
+      )
+      .normalize()
+    })
+    .collect();
+  let quantized = quantize_normals_octahedral(&normals);
+  assert!(quantized.max_angle_error < 0.001);
+}
+
+#[test]
+fn filters() {
+  let normals = [
+    Vec4::new(0., 0., 1., 1.),
+    Vec4::new(0.6, -0.8, 0., 0.),
+    Vec4::new(-0.3, 0.5, -0.7, -1.),
+  ];
+  for bits in [8, 12] {
+    let mut encoded = encode_filter_octahedral(&normals, bits);
+    let stride = if bits > 8 { 8 } else { 4 };
+    decode_filter(MeshoptFilter::Octahedral, &mut encoded, stride).unwrap();
+    for (n, v) in normals.iter().zip(encoded.chunks_exact(stride)) {
+      let (d, max) = if stride == 4 {
+        let v = Vec4::new(v[0], v[1], v[2], v[3]).map(|v| v as i8 as f32);
+        (v, 127.)
+      } else {
+        let v = Vec4::new(
+          i16::from_le_bytes([v[0], v[1]]),
+          i16::from_le_bytes([v[2], v[3]]),
+          i16::from_le_bytes([v[4], v[5]]),
+          i16::from_le_bytes([v[6], v[7]]),
+        )
+        .map(|v| v as f32);
+        (v, 32767.)
+      };
+      let d = d / max;
+      let expect = n.xyz().normalize();
+      assert!(d.xyz().dot(expect) > 0.99, "{n:?} {d:?}");
+      assert!((d.w - n.w).abs() < 0.01);
+    }
+  }
+
+  // identity quaternion, the low bits of the last component store the max component index(3),
+  // the other bits store the scale
+  let mut quaternion: Vec<u8> = [0_i16, 0, 0, 32767]
+    .iter()
+    .flat_map(|v| v.to_le_bytes())
+    .collect();
+  decode_filter(MeshoptFilter::Quaternion, &mut quaternion, 8).unwrap();
+  let q: Vec<_> = quaternion
+    .chunks_exact(2)
+    .map(|v| i16::from_le_bytes([v[0], v[1]]))
+    .collect();
+  assert_eq!(q, [0, 0, 0, 32767]);
+
+  // 3 * 2^-1
+  let mut exponential = ((0xff_u32 << 24) | 3).to_le_bytes().to_vec();
+  decode_filter(MeshoptFilter::Exponential, &mut exponential, 4).unwrap();
+  assert_eq!(f32::from_le_bytes(exponential.try_into().unwrap()), 1.5);
+}
@@ -0,0 +1,121 @@
+//! The golden vectors produced by the reference meshoptimizer implementation (v0.12, the version
+//! vendored by the meshopt 0.1.9 crate).
+//!
+//! The index codec v1 is not available in that version, its vector is produced by the same
+//! encoder with the v1 changes applied: the vertex fifo is limited to 13 entries, the 13/14
+//! codes encode the last-1/last+1 free index, and the 0/1/2 triangle emits the reset code.
+//!
+//! The input of the index vectors is a 3x3 grid followed by some free and degenerate triangles:
+//! `[1000, 5, 700, 3, 3, 900, 100, 101, 102, 102, 101, 103, 103, 101, 104, 0, 1, 2, 2, 1, 3]`
+
+use rendiation_mesh_compression::*;
+
+const INDICES: [u32; 75] = [
+  0, 1, 4, 1, 5, 4, 1, 2, 5, 2, 6, 5, 2, 3, 6, 3, 7, 6, 4, 5, 8, 5, 9, 8, 5, 6, 9, 6, 10, 9, 6, 7,
+  10, 7, 11, 10, 8, 9, 12, 9, 13, 12, 9, 10, 13, 10, 14, 13, 10, 11, 14, 11, 15, 14, 1000, 5, 700,
+  3, 3, 900, 100, 101, 102, 102, 101, 103, 103, 101, 104, 0, 1, 2, 2, 1, 3,
+];
+
+/// the reference decoder output, the triangles are rotated by the encoder
+const DECODED_INDICES: [u32; 75] = [
+  0, 1, 4, 4, 1, 5, 5, 1, 2, 5, 2, 6, 6, 2, 3, 6, 3, 7, 4, 5, 8, 8, 5, 9, 9, 5, 6, 9, 6, 10, 10, 6,
+  7, 10, 7, 11, 8, 9, 12, 12, 9, 13, 13, 9, 10, 13, 10, 14, 14, 10, 11, 14, 11, 15, 1000, 5, 700,
+  3, 3, 900, 100, 101, 102, 102, 101, 103, 103, 101, 104, 0, 1, 2, 2, 1, 3,
+];
+
+const INDEX_V0: [u8; 77] = [
+  224, 254, 31, 16, 15, 16, 15, 143, 31, 20, 15, 19, 15, 143, 31, 19, 15, 19, 15, 255, 255, 255,
+  31, 31, 255, 25, 15, 8, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 223, 178, 15, 215, 4, 207, 241, 10, 130,
+  14, 255, 191, 12, 2, 2, 2, 2, 255, 207, 1, 2, 2, 0, 118, 135, 86, 103, 120, 169, 134, 101, 137,
+  104, 152, 1, 105, 0, 0,
+];
+
+const INDEX_V1: [u8; 60] = [
+  225, 254, 30, 16, 14, 16, 14, 142, 30, 20, 14, 19, 14, 142, 30, 19, 14, 19, 14, 255, 255, 255,
+  30, 30, 254, 16, 15, 8, 223, 178, 15, 215, 4, 207, 241, 10, 130, 14, 255, 191, 12, 2, 2, 0, 0,
+  118, 135, 86, 103, 120, 169, 134, 101, 137, 104, 152, 1, 105, 0, 0,
+];
+
+/// the 4x4 grid positions in f32, see [grid_positions]
+const VERTEX_POSITION_V0: [u8; 136] = [
+  160, 0, 0, 3, 0, 255, 255, 128, 127, 255, 255, 128, 127, 255, 255, 128, 127, 255, 255, 128, 1,
+  56, 248, 248, 248, 126, 127, 126, 127, 126, 127, 126, 0, 0, 1, 0, 192, 192, 192, 255, 255, 128,
+  1, 0, 192, 128, 0, 126, 1, 48, 252, 63, 15, 101, 101, 204, 101, 101, 204, 101, 101, 204, 1, 48,
+  252, 63, 15, 103, 101, 206, 103, 101, 206, 103, 101, 206, 3, 0, 152, 255, 0, 101, 206, 152, 255,
+  0, 101, 206, 152, 255, 0, 101, 206, 1, 49, 188, 111, 27, 124, 123, 124, 123, 124, 123, 0, 0, 0,
+  0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// 260 vertices of 4 bytes, so two blocks are encoded, see [attributes]
+const VERTEX_ATTRIBUTE_V0: [u8; 257] = [
+  160, 85, 85, 85, 85, 0, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 85, 85, 85, 85, 0, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 85, 85, 85, 85, 0, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128,
+  128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 0, 0, 0, 0, 1, 128, 0, 0, 0, 1, 128,
+  0, 0, 0, 1, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+  0, 0, 0, 0, 0, 0, 40, 80, 255,
+];
+
+fn grid_positions() -> Vec<u8> {
+  let mut positions = Vec::new();
+  for y in 0..=3_u32 {
+    for x in 0..=3_u32 {
+      let h = ((x * 7 + y * 13) % 5) as f32 * 0.1;
+      positions.extend_from_slice(&[x as f32, y as f32, h]);
+    }
+  }
+  bytemuck::cast_slice(&positions).to_vec()
+}
+
+fn attributes() -> Vec<u8> {
+  (0..260_usize)
+    .flat_map(|i| (0..4).map(move |k| if k == 3 { 255 } else { (i / 4 + k * 40) as u8 }))
+    .collect()
+}
+
+#[test]
+fn index_codec_v0_golden() {
+  assert_eq!(
+    decode_index_buffer(INDICES.len(), &INDEX_V0).unwrap(),
+    DECODED_INDICES
+  );
+}
+
+#[test]
+fn index_codec_v1_golden() {
+  assert_eq!(encode_index_buffer(&INDICES), INDEX_V1);
+  assert_eq!(
+    decode_index_buffer(INDICES.len(), &INDEX_V1).unwrap(),
+    DECODED_INDICES
+  );
+}
+
+#[test]
+fn vertex_codec_v0_golden() {
+  let positions = grid_positions();
+  assert_eq!(
+    encode_vertex_buffer(&positions, 12).unwrap(),
+    VERTEX_POSITION_V0
+  );
+  assert_eq!(
+    decode_vertex_buffer(16, 12, &VERTEX_POSITION_V0).unwrap(),
+    positions
+  );
+
+  let attributes = attributes();
+  assert_eq!(
+    encode_vertex_buffer(&attributes, 4).unwrap(),
+    VERTEX_ATTRIBUTE_V0
+  );
+  assert_eq!(
+    decode_vertex_buffer(260, 4, &VERTEX_ATTRIBUTE_V0).unwrap(),
+    attributes
+  );
+}
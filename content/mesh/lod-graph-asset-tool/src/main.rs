@@ -0,0 +1,64 @@
+//! Build the mesh lod graph offline and write it as the streamable asset.
+//!
+//! usage: rendiation-mesh-lod-graph-asset-tool <input.obj> <output> [--max-meshlet-per-page N] [--compression-level N]
+//!
+//! The renderer streams the pages from the output file if the mesh references it by the
+//! `AttributesMeshPrebuiltLODGraphAsset` component.
+
+use anyhow::*;
+use humansize::{DECIMAL, format_size};
+use pico_args::Arguments;
+use rendiation_mesh_lod_graph::*;
+use rendiation_mesh_test_util::load_common_mesh;
+
+fn main() -> anyhow::Result<()> {
+  let mut args = Arguments::from_env();
+
+  let mut config = MeshLODGraphAssetBuildConfig::default();
+  if let Some(count) = args.opt_value_from_str("--max-meshlet-per-page")? {
+    config.max_meshlet_count_per_page = count;
+  }
+  if let Some(level) = args.opt_value_from_str("--compression-level")? {
+    config.compression_level = level;
+  }
+
+  let input: String = args.free_from_str().context("Expected input mesh path")?;
+  let output: String = args.free_from_str().context("Expected output path")?;
+
+  let mesh = load_common_mesh(input.as_str()).context("Failed to load input mesh")?;
+  println!(
+    "input mesh: vertex count: {}, triangle count: {}",
+    mesh.vertices.len(),
+    mesh.indices.len() / 3
+  );
+
+  let graph = DefaultMeshLODBuilder {}
+    .build_from_mesh(mesh)
+    .context("Failed to build the mesh lod graph")?;
+  let data = write_mesh_lod_graph_asset(&graph, &config);
+
+  // validate the output before writing
+  let asset = MeshLODGraphAssetInfo::parse(&data)?;
+  let mut uncompressed_size = 0;
+  for page in 0..asset.header.page_count {
+    let range = asset.page_byte_range(page);
+    let decoded = asset.decode_page(page, &data[range.start as usize..range.end as usize])?;
+    uncompressed_size += decoded.byte_size();
+  }
+
+  for (i, level) in asset.levels.iter().enumerate() {
+    println!(
+      "level {i}: meshlet count: {}, group count: {}, page count: {}",
+      level.meshlet_count, level.group_count, level.page_count
+    );
+  }
+  println!(
+    "page count: {}, asset size: {}, uncompressed page size: {}",
+    asset.header.page_count,
+    format_size(data.len(), DECIMAL),
+    format_size(uncompressed_size, DECIMAL)
+  );
+
+  std::fs::write(&output, data).context("Failed to write output")?;
+  Ok(())
+}
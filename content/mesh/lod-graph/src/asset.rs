@@ -0,0 +1,700 @@
+//! The on disk container of the mesh lod graph.
+//!
+//! Building the lod graph is expensive, so it should be done offline and stored in this format.
+//! The layout is:
+//!
+//! | header | level table | page table | page dependency list | compressed pages |
+//!
+//! Everything before the compressed pages is small and should be loaded at once, the pages are
+//! loaded on demand. Each page contains whole meshlet groups of one level, so the lod selection
+//! is always consistent inside the page. The page carries its own vertices and indices, and the
+//! meshlets in the page have all the error and bounding info required by the lod selection.
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::*;
+
+pub const MESH_LOD_GRAPH_ASSET_MAGIC: [u8; 4] = *b"MLGA";
+pub const MESH_LOD_GRAPH_ASSET_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum MeshLODGraphAssetError {
+  #[error("not a mesh lod graph asset")]
+  InvalidMagic,
+  #[error("unsupported mesh lod graph asset version: {0}")]
+  UnsupportedVersion(u32),
+  #[error(
+    "unexpected end of data, expect at least {expect} bytes, but only {actual} bytes provided"
+  )]
+  UnexpectedEnd { expect: usize, actual: usize },
+  #[error("page decompress failed: {0}")]
+  Decompress(String),
+  #[error("page content does not match the page table")]
+  PageContentMismatch,
+  #[error("invalid page table entry {page}: {reason}")]
+  InvalidPageInfo { page: u32, reason: &'static str },
+  #[error("invalid level table entry {level}: {reason}")]
+  InvalidLevelInfo { level: u32, reason: &'static str },
+  #[error("asset file read failed: {0}")]
+  Io(#[from] std::io::Error),
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MeshLODGraphAssetHeader {
+  pub magic: [u8; 4],
+  pub version: u32,
+  pub level_count: u32,
+  pub page_count: u32,
+  pub page_dependency_count: u32,
+  pub _padding: u32,
+  /// the bounding sphere of the entire mesh, (center, radius)
+  pub bounding: [f32; 4],
+  /// the byte offset of the first compressed page, from the start of the asset.
+  /// all data before this offset is required to parse the [MeshLODGraphAssetInfo]
+  pub page_data_offset: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MeshLODGraphAssetLevelInfo {
+  pub first_page: u32,
+  pub page_count: u32,
+  pub meshlet_count: u32,
+  pub group_count: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MeshLODGraphAssetPageInfo {
+  pub level: u32,
+  pub meshlet_count: u32,
+  pub vertex_count: u32,
+  pub index_count: u32,
+  /// relative to the [MeshLODGraphAssetHeader::page_data_offset]
+  pub byte_offset: u64,
+  pub compressed_byte_size: u32,
+  pub uncompressed_byte_size: u32,
+  /// the max error of the coarser representation of the meshlets in this page. if the projected
+  /// parent error is under the threshold, the coarser level is good enough and this page is not
+  /// required. f32::MAX in the coarsest level.
+  pub parent_error: f32,
+  /// range in the page dependency list, the pages in the coarser level that contain the meshlets
+  /// simplified from the meshlets in this page.
+  pub parent_pages_offset: u32,
+  pub parent_pages_count: u32,
+  pub _padding: u32,
+  /// the union of the parent lod spheres of the meshlets in this page
+  pub parent_lod_sphere: [f32; 4],
+}
+
+/// The meshlet stored in page, the index range is based on the page's index buffer,
+/// and the error and sphere semantics are same as the lod graph.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct MeshLODGraphPageMeshlet {
+  pub index_offset: u32,
+  pub index_count: u32,
+  /// zero in the finest level
+  pub self_error: f32,
+  /// f32::MAX in the coarsest level
+  pub parent_error: f32,
+  pub bounding: [f32; 4],
+  pub self_lod_sphere: [f32; 4],
+  pub parent_lod_sphere: [f32; 4],
+  /// the page that contains the finer meshlet group this meshlet is simplified from,
+  /// u32::MAX in the finest level
+  pub source_page: u32,
+}
+
+pub struct MeshLODGraphPage {
+  pub meshlets: Vec<MeshLODGraphPageMeshlet>,
+  pub vertices: Vec<CommonVertex>,
+  /// the index is based on the page itself
+  pub indices: Vec<u32>,
+}
+
+impl MeshLODGraphPage {
+  /// the uncompressed size, used as the streaming cost
+  pub fn byte_size(&self) -> usize {
+    std::mem::size_of_val(self.meshlets.as_slice())
+      + std::mem::size_of_val(self.vertices.as_slice())
+      + std::mem::size_of_val(self.indices.as_slice())
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshLODGraphAssetBuildConfig {
+  /// the page is filled by whole meshlet groups, so a page may exceed this limitation
+  /// if a single group is larger than it.
+  pub max_meshlet_count_per_page: u32,
+  /// 0-10, see miniz_oxide
+  pub compression_level: u8,
+}
+
+impl Default for MeshLODGraphAssetBuildConfig {
+  fn default() -> Self {
+    Self {
+      max_meshlet_count_per_page: 128,
+      compression_level: 6,
+    }
+  }
+}
+
+pub fn sphere_to_array(sphere: Sphere) -> [f32; 4] {
+  [
+    sphere.center.x,
+    sphere.center.y,
+    sphere.center.z,
+    sphere.radius,
+  ]
+}
+
+pub fn array_to_sphere(v: [f32; 4]) -> Sphere {
+  Sphere::new(Vec3::new(v[0], v[1], v[2]), v[3])
+}
+
+pub fn write_mesh_lod_graph_asset(
+  graph: &MeshLODGraph,
+  config: &MeshLODGraphAssetBuildConfig,
+) -> Vec<u8> {
+  let level_count = graph.levels.len();
+
+  // assign whole groups to pages
+  let mut level_infos = Vec::with_capacity(level_count);
+  let mut group_to_page: Vec<Vec<u32>> = Vec::with_capacity(level_count);
+  let mut page_groups: Vec<Range<usize>> = Vec::new(); // group range in its level
+  let mut page_levels: Vec<usize> = Vec::new();
+  for (level_idx, level) in graph.levels.iter().enumerate() {
+    let first_page = page_groups.len() as u32;
+    let mut mapping = Vec::with_capacity(level.groups.len());
+    let mut page_start = 0;
+    let mut page_meshlet_count = 0;
+    for (group_idx, group) in level.groups.iter().enumerate() {
+      if page_meshlet_count > 0
+        && page_meshlet_count + group.meshlets.size > config.max_meshlet_count_per_page
+      {
+        page_groups.push(page_start..group_idx);
+        page_levels.push(level_idx);
+        page_start = group_idx;
+        page_meshlet_count = 0;
+      }
+      page_meshlet_count += group.meshlets.size;
+      mapping.push(page_groups.len() as u32);
+    }
+    if page_start < level.groups.len() {
+      page_groups.push(page_start..level.groups.len());
+      page_levels.push(level_idx);
+    }
+
+    level_infos.push(MeshLODGraphAssetLevelInfo {
+      first_page,
+      page_count: page_groups.len() as u32 - first_page,
+      meshlet_count: level.meshlets.len() as u32,
+      group_count: level.groups.len() as u32,
+    });
+    group_to_page.push(mapping);
+  }
+
+  // the coarser pages that depend on each page
+  let mut parent_pages: Vec<FastHashSet<u32>> = vec![Default::default(); page_groups.len()];
+  for (level_idx, level) in graph.levels.iter().enumerate().skip(1) {
+    for meshlet in &level.meshlets {
+      let source_page =
+        group_to_page[level_idx - 1][meshlet.group_index_in_previous_level as usize];
+      let page = group_to_page[level_idx][meshlet.group_index as usize];
+      parent_pages[source_page as usize].insert(page);
+    }
+  }
+
+  let mut page_infos = Vec::with_capacity(page_groups.len());
+  let mut page_dependencies = Vec::new();
+  let mut page_data = Vec::new();
+  for (page_idx, (groups, &level_idx)) in page_groups.iter().zip(&page_levels).enumerate() {
+    let page = extract_page(graph, level_idx, groups.clone(), &group_to_page);
+
+    let level = &graph.levels[level_idx];
+    let is_coarsest = level_idx + 1 == level_count;
+    let page_group_infos = &level.groups[groups.clone()];
+    let parent_error = if is_coarsest {
+      f32::MAX
+    } else {
+      page_group_infos.iter().fold(0., |e: f32, g| {
+        e.max(g.max_meshlet_simplification_error_among_meshlet_in_their_parent_group)
+      })
+    };
+    let parent_lod_sphere = Sphere::from_spheres(
+      page_group_infos
+        .iter()
+        .map(|g| g.union_meshlet_bounding_among_meshlet_in_their_parent_group),
+    );
+
+    let mut parents = parent_pages[page_idx].iter().copied().collect::<Vec<_>>();
+    parents.sort_unstable();
+    let parent_pages_offset = page_dependencies.len() as u32;
+    page_dependencies.extend_from_slice(&parents);
+
+    let uncompressed = encode_page(&page);
+    let compressed = miniz_oxide::deflate::compress_to_vec(&uncompressed, config.compression_level);
+
+    page_infos.push(MeshLODGraphAssetPageInfo {
+      level: level_idx as u32,
+      meshlet_count: page.meshlets.len() as u32,
+      vertex_count: page.vertices.len() as u32,
+      index_count: page.indices.len() as u32,
+      byte_offset: page_data.len() as u64,
+      compressed_byte_size: compressed.len() as u32,
+      uncompressed_byte_size: uncompressed.len() as u32,
+      parent_error,
+      parent_pages_offset,
+      parent_pages_count: parents.len() as u32,
+      _padding: 0,
+      parent_lod_sphere: sphere_to_array(parent_lod_sphere),
+    });
+    page_data.extend_from_slice(&compressed);
+  }
+
+  let bounding = graph
+    .levels
+    .last()
+    .map(|level| Sphere::from_spheres(level.meshlets.iter().map(|m| m.bounding_in_local)))
+    .unwrap_or(Sphere::new(Vec3::zero(), 0.));
+
+  let page_data_offset = std::mem::size_of::<MeshLODGraphAssetHeader>()
+    + std::mem::size_of_val(level_infos.as_slice())
+    + std::mem::size_of_val(page_infos.as_slice())
+    + std::mem::size_of_val(page_dependencies.as_slice());
+
+  let header = MeshLODGraphAssetHeader {
+    magic: MESH_LOD_GRAPH_ASSET_MAGIC,
+    version: MESH_LOD_GRAPH_ASSET_VERSION,
+    level_count: level_count as u32,
+    page_count: page_infos.len() as u32,
+    page_dependency_count: page_dependencies.len() as u32,
+    _padding: 0,
+    bounding: sphere_to_array(bounding),
+    page_data_offset: page_data_offset as u64,
+  };
+
+  let mut output = Vec::with_capacity(page_data_offset + page_data.len());
+  output.extend_from_slice(bytemuck::bytes_of(&header));
+  output.extend_from_slice(bytemuck::cast_slice(&level_infos));
+  output.extend_from_slice(bytemuck::cast_slice(&page_infos));
+  output.extend_from_slice(bytemuck::cast_slice(&page_dependencies));
+  output.extend_from_slice(&page_data);
+  output
+}
+
+fn extract_page(
+  graph: &MeshLODGraph,
+  level_idx: usize,
+  groups: Range<usize>,
+  group_to_page: &[Vec<u32>],
+) -> MeshLODGraphPage {
+  let level = &graph.levels[level_idx];
+  let is_coarsest = level_idx + 1 == graph.levels.len();
+
+  let mut meshlets = Vec::new();
+  let mut indices = Vec::new();
+  let mut vertices = Vec::new();
+  let mut vertex_remap = FastHashMap::default();
+
+  for group in &level.groups[groups] {
+    for meshlet in &level.meshlets[group.meshlets.into_range()] {
+      let index_offset = indices.len() as u32;
+      for &index in &level.mesh.indices[meshlet.index_range.into_range()] {
+        let local = *vertex_remap.entry(index).or_insert_with(|| {
+          vertices.push(level.mesh.vertices[index as usize]);
+          vertices.len() as u32 - 1
+        });
+        indices.push(local);
+      }
+
+      let (self_error, self_lod_sphere, source_page) = if level_idx == 0 {
+        (0., meshlet.bounding_in_local, u32::MAX)
+      } else {
+        let source_group = meshlet.group_index_in_previous_level as usize;
+        let source = graph.levels[level_idx - 1].groups[source_group];
+        (
+          source.max_meshlet_simplification_error_among_meshlet_in_their_parent_group,
+          source.union_meshlet_bounding_among_meshlet_in_their_parent_group,
+          group_to_page[level_idx - 1][source_group],
+        )
+      };
+
+      let parent_error = if is_coarsest {
+        f32::MAX
+      } else {
+        group.max_meshlet_simplification_error_among_meshlet_in_their_parent_group
+      };
+
+      meshlets.push(MeshLODGraphPageMeshlet {
+        index_offset,
+        index_count: meshlet.index_range.size,
+        self_error,
+        parent_error,
+        bounding: sphere_to_array(meshlet.bounding_in_local),
+        self_lod_sphere: sphere_to_array(self_lod_sphere),
+        parent_lod_sphere: sphere_to_array(
+          group.union_meshlet_bounding_among_meshlet_in_their_parent_group,
+        ),
+        source_page,
+      });
+    }
+  }
+
+  MeshLODGraphPage {
+    meshlets,
+    vertices,
+    indices,
+  }
+}
+
+fn encode_page(page: &MeshLODGraphPage) -> Vec<u8> {
+  let mut data = Vec::with_capacity(page.byte_size());
+  data.extend_from_slice(bytemuck::cast_slice(&page.meshlets));
+  data.extend_from_slice(bytemuck::cast_slice(&page.vertices));
+  data.extend_from_slice(bytemuck::cast_slice(&page.indices));
+  data
+}
+
+/// The non page part of the asset, it should be fully loaded before any page loading.
+#[derive(Clone)]
+pub struct MeshLODGraphAssetInfo {
+  pub header: MeshLODGraphAssetHeader,
+  pub levels: Vec<MeshLODGraphAssetLevelInfo>,
+  pub pages: Vec<MeshLODGraphAssetPageInfo>,
+  pub page_dependencies: Vec<u32>,
+}
+
+fn read_bytes(bytes: &[u8], range: Range<usize>) -> Result<&[u8], MeshLODGraphAssetError> {
+  bytes
+    .get(range.clone())
+    .ok_or(MeshLODGraphAssetError::UnexpectedEnd {
+      expect: range.end,
+      actual: bytes.len(),
+    })
+}
+
+impl MeshLODGraphAssetHeader {
+  pub fn parse(bytes: &[u8]) -> Result<Self, MeshLODGraphAssetError> {
+    let header = read_bytes(bytes, 0..std::mem::size_of::<Self>())?;
+    let header: Self = bytemuck::pod_read_unaligned(header);
+    if header.magic != MESH_LOD_GRAPH_ASSET_MAGIC {
+      return Err(MeshLODGraphAssetError::InvalidMagic);
+    }
+    if header.version != MESH_LOD_GRAPH_ASSET_VERSION {
+      return Err(MeshLODGraphAssetError::UnsupportedVersion(header.version));
+    }
+    Ok(header)
+  }
+}
+
+impl MeshLODGraphAssetInfo {
+  /// the bytes should at least contains the data before [MeshLODGraphAssetHeader::page_data_offset]
+  pub fn parse(bytes: &[u8]) -> Result<Self, MeshLODGraphAssetError> {
+    let header = MeshLODGraphAssetHeader::parse(bytes)?;
+
+    let mut offset = std::mem::size_of::<MeshLODGraphAssetHeader>();
+    let mut read_array = |count: u32, item_size: usize| {
+      let range = offset..offset + count as usize * item_size;
+      offset = range.end;
+      read_bytes(bytes, range)
+    };
+
+    let levels = read_array(
+      header.level_count,
+      std::mem::size_of::<MeshLODGraphAssetLevelInfo>(),
+    )?;
+    let levels = bytemuck::pod_collect_to_vec(levels);
+    let pages = read_array(
+      header.page_count,
+      std::mem::size_of::<MeshLODGraphAssetPageInfo>(),
+    )?;
+    let pages = bytemuck::pod_collect_to_vec(pages);
+    let page_dependencies = read_array(header.page_dependency_count, std::mem::size_of::<u32>())?;
+    let page_dependencies = bytemuck::pod_collect_to_vec(page_dependencies);
+
+    let info = Self {
+      header,
+      levels,
+      pages,
+      page_dependencies,
+    };
+    info.validate()?;
+    Ok(info)
+  }
+
+  /// read the non page part from the asset file, the pages are left in the file and should be
+  /// loaded by [crate::create_mesh_lod_graph_file_page_loader]
+  pub fn read_from_file(path: &std::path::Path) -> Result<Self, MeshLODGraphAssetError> {
+    use std::io::Read;
+    let mut file = std::fs::File::open(path)?;
+    let mut header = vec![0; std::mem::size_of::<MeshLODGraphAssetHeader>()];
+    file.read_exact(&mut header)?;
+    let header_info = MeshLODGraphAssetHeader::parse(&header)?;
+
+    // the offset is not trusted, the take limits the read by the real file content
+    let rest = header_info
+      .page_data_offset
+      .saturating_sub(header.len() as u64);
+    file.take(rest).read_to_end(&mut header)?;
+    Self::parse(&header)
+  }
+
+  /// the tables are used to index the other tables and the page data directly, so any
+  /// inconsistency is reported here instead of panicking in the later access.
+  fn validate(&self) -> Result<(), MeshLODGraphAssetError> {
+    let page_count = self.header.page_count;
+
+    for (level, info) in self.levels.iter().enumerate() {
+      let invalid = |reason| MeshLODGraphAssetError::InvalidLevelInfo {
+        level: level as u32,
+        reason,
+      };
+      let page_end = info.first_page.checked_add(info.page_count);
+      if page_end.is_none_or(|end| end > page_count) {
+        return Err(invalid("page range out of the page table"));
+      }
+    }
+
+    for (page, info) in self.pages.iter().enumerate() {
+      let page = page as u32;
+      let invalid = |reason| MeshLODGraphAssetError::InvalidPageInfo { page, reason };
+
+      if info.level >= self.header.level_count {
+        return Err(invalid("level out of the level table"));
+      }
+
+      let byte_end = self
+        .header
+        .page_data_offset
+        .checked_add(info.byte_offset)
+        .and_then(|start| start.checked_add(info.compressed_byte_size as u64));
+      if byte_end.is_none() {
+        return Err(invalid("byte range overflow"));
+      }
+
+      let dependency_end = info
+        .parent_pages_offset
+        .checked_add(info.parent_pages_count);
+      if dependency_end.is_none_or(|end| end as usize > self.page_dependencies.len()) {
+        return Err(invalid("parent pages out of the page dependency list"));
+      }
+
+      for parent in self.parent_pages(page) {
+        if *parent >= page_count {
+          return Err(invalid("parent page out of the page table"));
+        }
+        // the pages are sorted from the finest level, this also guarantees the dependency
+        // is acyclic
+        if *parent <= page {
+          return Err(invalid("parent page is not after the page"));
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// the byte range in the asset
+  pub fn page_byte_range(&self, page: u32) -> Range<u64> {
+    let info = &self.pages[page as usize];
+    let start = self.header.page_data_offset + info.byte_offset;
+    start..start + info.compressed_byte_size as u64
+  }
+
+  pub fn parent_pages(&self, page: u32) -> &[u32] {
+    let info = &self.pages[page as usize];
+    let start = info.parent_pages_offset as usize;
+    &self.page_dependencies[start..start + info.parent_pages_count as usize]
+  }
+
+  pub fn is_coarsest_level_page(&self, page: u32) -> bool {
+    self.pages[page as usize].level + 1 == self.header.level_count
+  }
+
+  /// decode the page from the compressed bytes, which is the data in [Self::page_byte_range]
+  pub fn decode_page(
+    &self,
+    page: u32,
+    compressed: &[u8],
+  ) -> Result<MeshLODGraphPage, MeshLODGraphAssetError> {
+    let info = &self.pages[page as usize];
+    let data = miniz_oxide::inflate::decompress_to_vec_with_limit(
+      compressed,
+      info.uncompressed_byte_size as usize,
+    )
+    .map_err(|e| MeshLODGraphAssetError::Decompress(format!("{e:?}")))?;
+
+    let meshlet_size = info.meshlet_count as usize * std::mem::size_of::<MeshLODGraphPageMeshlet>();
+    let vertex_size = info.vertex_count as usize * std::mem::size_of::<CommonVertex>();
+    let index_size = info.index_count as usize * std::mem::size_of::<u32>();
+    if data.len() != meshlet_size + vertex_size + index_size {
+      return Err(MeshLODGraphAssetError::PageContentMismatch);
+    }
+
+    let (meshlets, rest) = data.split_at(meshlet_size);
+    let (vertices, indices) = rest.split_at(vertex_size);
+
+    Ok(MeshLODGraphPage {
+      meshlets: bytemuck::pod_collect_to_vec(meshlets),
+      vertices: bytemuck::pod_collect_to_vec(vertices),
+      indices: bytemuck::pod_collect_to_vec(indices),
+    })
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+
+  fn triangle(offset: f32) -> [CommonVertex; 3] {
+    [
+      Vec3::new(0., 0., 0.),
+      Vec3::new(1., 0., 0.),
+      Vec3::new(0., 1., 0.),
+    ]
+    .map(|p| CommonVertex {
+      position: p + Vec3::new(offset, 0., 0.),
+      normal: Vec3::new(0., 0., 1.),
+      uv: p.xy(),
+    })
+  }
+
+  /// two disjoint triangles in the finest level, and each of them is "simplified" to one
+  /// triangle in the coarsest level
+  pub(crate) fn create_test_lod_graph() -> MeshLODGraph {
+    let mesh = || CommonMeshBuffer {
+      vertices: triangle(0.).into_iter().chain(triangle(10.)).collect(),
+      indices: (0..6).collect(),
+    };
+    let meshlet = |group_index, group_index_in_previous_level, offset, center: f32| Meshlet {
+      group_index,
+      group_index_in_previous_level,
+      index_range: OffsetSize { offset, size: 3 },
+      bounding_in_local: Sphere::new(Vec3::new(center, 0., 0.), 1.),
+    };
+    let group = |meshlets: Range<u32>, error, center: f32| MeshletGroup {
+      meshlets: meshlets.into(),
+      lod_error_simplify_to_next_level: error,
+      max_meshlet_simplification_error_among_meshlet_in_their_parent_group: error,
+      union_meshlet_bounding_among_meshlet_in_their_parent_group: Sphere::new(
+        Vec3::new(center, 0., 0.),
+        1.,
+      ),
+    };
+
+    MeshLODGraph {
+      levels: vec![
+        MeshLODGraphLevel {
+          groups: vec![group(0..1, 0.5, 0.), group(1..2, 0.5, 10.)],
+          meshlets: vec![meshlet(0, 0, 0, 0.), meshlet(1, 0, 3, 10.)],
+          mesh: mesh(),
+        },
+        MeshLODGraphLevel {
+          groups: vec![group(0..2, 0., 5.)],
+          meshlets: vec![meshlet(0, 0, 0, 0.), meshlet(0, 1, 3, 10.)],
+          mesh: mesh(),
+        },
+      ],
+    }
+  }
+
+  #[test]
+  fn test_asset_round_trip() {
+    let graph = create_test_lod_graph();
+    let config = MeshLODGraphAssetBuildConfig {
+      max_meshlet_count_per_page: 1,
+      ..Default::default()
+    };
+    let data = write_mesh_lod_graph_asset(&graph, &config);
+    let asset = MeshLODGraphAssetInfo::parse(&data).unwrap();
+
+    assert_eq!(asset.header.level_count, 2);
+    // the group in the coarsest level is not split even if it exceeds the page limitation
+    assert_eq!(asset.header.page_count, 3);
+    assert_eq!(asset.parent_pages(0), &[2]);
+    assert_eq!(asset.parent_pages(1), &[2]);
+    assert!(asset.parent_pages(2).is_empty());
+    assert!(asset.is_coarsest_level_page(2));
+
+    let range = asset.page_byte_range(1);
+    let page = asset
+      .decode_page(1, &data[range.start as usize..range.end as usize])
+      .unwrap();
+    assert_eq!(page.vertices.as_slice(), &triangle(10.));
+    assert_eq!(page.indices, vec![0, 1, 2]);
+    assert_eq!(page.meshlets[0].self_error, 0.);
+    assert_eq!(page.meshlets[0].parent_error, 0.5);
+    assert_eq!(page.meshlets[0].source_page, u32::MAX);
+
+    let range = asset.page_byte_range(2);
+    let page = asset
+      .decode_page(2, &data[range.start as usize..range.end as usize])
+      .unwrap();
+    assert_eq!(page.meshlets.len(), 2);
+    assert_eq!(page.meshlets[1].index_offset, 3);
+    assert_eq!(page.meshlets[1].self_error, 0.5);
+    assert_eq!(page.meshlets[1].parent_error, f32::MAX);
+    assert_eq!(page.meshlets[1].source_page, 1);
+
+    assert!(matches!(
+      MeshLODGraphAssetInfo::parse(&data[..10]),
+      Err(MeshLODGraphAssetError::UnexpectedEnd { .. })
+    ));
+  }
+
+  #[test]
+  fn test_asset_invalid_page_dependency() {
+    let graph = create_test_lod_graph();
+    let config = MeshLODGraphAssetBuildConfig {
+      max_meshlet_count_per_page: 1,
+      ..Default::default()
+    };
+    let data = write_mesh_lod_graph_asset(&graph, &config);
+    let asset = MeshLODGraphAssetInfo::parse(&data).unwrap();
+
+    let page_table_offset = std::mem::size_of::<MeshLODGraphAssetHeader>()
+      + asset.levels.len() * std::mem::size_of::<MeshLODGraphAssetLevelInfo>();
+    let dependency_offset =
+      page_table_offset + asset.pages.len() * std::mem::size_of::<MeshLODGraphAssetPageInfo>();
+
+    let modify_page = |page: usize, f: &dyn Fn(&mut MeshLODGraphAssetPageInfo)| {
+      let mut data = data.clone();
+      let size = std::mem::size_of::<MeshLODGraphAssetPageInfo>();
+      let range = page_table_offset + page * size..page_table_offset + (page + 1) * size;
+      let mut info: MeshLODGraphAssetPageInfo = bytemuck::pod_read_unaligned(&data[range.clone()]);
+      f(&mut info);
+      data[range].copy_from_slice(bytemuck::bytes_of(&info));
+      data
+    };
+
+    let expect_invalid_page = |data: &[u8], expect_page: u32| {
+      assert!(matches!(
+        MeshLODGraphAssetInfo::parse(data),
+        Err(MeshLODGraphAssetError::InvalidPageInfo { page, .. }) if page == expect_page
+      ));
+    };
+
+    // the dependency range is out of the list
+    expect_invalid_page(&modify_page(0, &|info| info.parent_pages_offset = 100), 0);
+    expect_invalid_page(
+      &modify_page(0, &|info| info.parent_pages_offset = u32::MAX),
+      0,
+    );
+    expect_invalid_page(&modify_page(1, &|info| info.parent_pages_count = 5), 1);
+
+    // the parent page index out of the page table
+    let mut out_of_range = data.clone();
+    out_of_range[dependency_offset..dependency_offset + 4].copy_from_slice(&7_u32.to_le_bytes());
+    expect_invalid_page(&out_of_range, 0);
+
+    // the parent page index is not greater than the page itself
+    let self_dependency = modify_page(2, &|info| {
+      info.parent_pages_offset = 0;
+      info.parent_pages_count = 1;
+    });
+    expect_invalid_page(&self_dependency, 2);
+  }
+}
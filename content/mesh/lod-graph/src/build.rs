@@ -1,5 +1,13 @@
 use crate::*;
 
+#[derive(thiserror::Error, Debug)]
+pub enum MeshLODGraphBuildError {
+  #[error("the mesh has no triangle")]
+  EmptyMesh,
+  #[error("meshlet segmentation failed: {0}")]
+  MeshletSegmentation(String),
+}
+
 pub trait MeshLodGraphBuilder {
   fn simplify(
     &self,
@@ -14,13 +22,21 @@ pub trait MeshLodGraphBuilder {
     vertices: &[CommonVertex],
     indices: &[u32],
   ) -> (Vec<Meshlet>, Vec<u32>);
-  fn segment_meshlets(&self, input: &[Meshlet], adj: &MeshletAdjacencyInfo) -> SegmentResult;
+  fn segment_meshlets(
+    &self,
+    input: &[Meshlet],
+    adj: &MeshletAdjacencyInfo,
+  ) -> Result<SegmentResult, MeshLODGraphBuildError>;
 
-  fn build_from_mesh(&self, mesh: CommonMeshBuffer) -> MeshLODGraph
+  fn build_from_mesh(&self, mesh: CommonMeshBuffer) -> Result<MeshLODGraph, MeshLODGraphBuildError>
   where
     Self: Sized,
   {
-    let mut last_level = MeshLODGraphLevel::build_base_from_mesh(self, mesh);
+    if mesh.indices.len() < 3 {
+      return Err(MeshLODGraphBuildError::EmptyMesh);
+    }
+
+    let mut last_level = MeshLODGraphLevel::build_base_from_mesh(self, mesh)?;
 
     if DEBUG_LOG {
       last_level.print_debug();
@@ -31,18 +47,26 @@ pub trait MeshLodGraphBuilder {
     // if the last level is single meshlet, we will have nothing to do
     // and finish build
     while last_level.meshlets.len() != 1 {
-      let new_last_level = MeshLODGraphLevel::build_from_finer_level(self, &mut last_level);
+      let new_last_level = MeshLODGraphLevel::build_from_finer_level(self, &mut last_level)?;
       if DEBUG_LOG {
         new_last_level.print_debug();
       }
 
+      // the simplification may stall(for example all vertices are locked), stop here to
+      // avoid infinite loop, the new level is still valid and is treated as the coarsest level
+      let is_stalled = new_last_level.meshlets.len() >= last_level.meshlets.len();
+
       let last_last_level = std::mem::replace(&mut last_level, new_last_level);
       levels.push(last_last_level);
+
+      if is_stalled {
+        break;
+      }
     }
 
     levels.push(last_level);
 
-    MeshLODGraph { levels }
+    Ok(MeshLODGraph { levels })
   }
 }
 
@@ -55,7 +79,7 @@ impl MeshLODGraphLevel {
   fn build_from_finer_level(
     builder: &dyn MeshLodGraphBuilder,
     previous_level: &mut MeshLODGraphLevel,
-  ) -> Self {
+  ) -> Result<Self, MeshLODGraphBuildError> {
     let mut all_simplified_indices: Vec<u32> =
       Vec::with_capacity(previous_level.mesh.indices.len());
     let mut all_meshlets: Vec<Meshlet> = Vec::with_capacity(previous_level.meshlets.len());
@@ -121,7 +145,7 @@ impl MeshLODGraphLevel {
     let meshlet_adjacency = MeshletAdjacencyInfo::build(&edges);
 
     let (mut groups, mut reordered_meshlets, reorder) =
-      build_groups_from_meshlets(builder, &all_meshlets, meshlet_adjacency, false);
+      build_groups_from_meshlets(builder, &all_meshlets, meshlet_adjacency, false)?;
 
     for (group_id, simplified_meshlet_range) in ranges.finish().iter().enumerate() {
       for simplified_meshlet_idx in simplified_meshlet_range.into_range() {
@@ -161,26 +185,35 @@ impl MeshLODGraphLevel {
         .map(|i| previous_level.mesh.vertices[*i as usize]),
     );
 
-    Self {
+    Ok(Self {
       groups,
       meshlets: reordered_meshlets,
       mesh: CommonMeshBuffer { indices, vertices },
-    }
+    })
   }
 
-  fn build_base_from_mesh(builder: &dyn MeshLodGraphBuilder, mesh: CommonMeshBuffer) -> Self {
+  fn build_base_from_mesh(
+    builder: &dyn MeshLodGraphBuilder,
+    mesh: CommonMeshBuffer,
+  ) -> Result<Self, MeshLODGraphBuildError> {
     let (meshlets, reordered_indices) = builder.segment_triangles(&mesh.vertices, &mesh.indices);
 
     let edges = compute_all_meshlet_boundary_edges(&meshlets, &reordered_indices);
     let meshlet_adjacency = MeshletAdjacencyInfo::build(&edges);
     let (groups, meshlets, _) =
-      build_groups_from_meshlets(builder, &meshlets, meshlet_adjacency, true);
+      build_groups_from_meshlets(builder, &meshlets, meshlet_adjacency, true)?;
 
-    Self {
+    // the meshlet's index range is based on the reordered indices
+    let mesh = CommonMeshBuffer {
+      indices: reordered_indices,
+      vertices: mesh.vertices,
+    };
+
+    Ok(Self {
       groups,
       meshlets,
       mesh,
-    }
+    })
   }
 }
 
@@ -189,8 +222,8 @@ fn build_groups_from_meshlets(
   meshlets: &[Meshlet],
   adj: MeshletAdjacencyInfo,
   is_level_0: bool,
-) -> (Vec<MeshletGroup>, Vec<Meshlet>, Vec<u32>) {
-  let meshlet_segmentation = builder.segment_meshlets(meshlets, &adj);
+) -> Result<(Vec<MeshletGroup>, Vec<Meshlet>, Vec<u32>), MeshLODGraphBuildError> {
+  let meshlet_segmentation = builder.segment_meshlets(meshlets, &adj)?;
 
   let mut meshlets = reorder_meshlet(meshlets, &meshlet_segmentation.reordered_idx);
 
@@ -221,7 +254,7 @@ fn build_groups_from_meshlets(
       .for_each(|meshlet| meshlet.group_index = i as u32)
   });
 
-  (groups, meshlets, meshlet_segmentation.reordered_idx)
+  Ok((groups, meshlets, meshlet_segmentation.reordered_idx))
 }
 
 /// reorder indices by given triangle order
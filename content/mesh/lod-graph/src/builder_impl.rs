@@ -153,12 +153,26 @@ impl MeshLodGraphBuilder for DefaultMeshLODBuilder {
 
   /// we have compiling issue one metis in wasm target. disable it for now
   #[cfg(target_family = "wasm")]
-  fn segment_meshlets(&self, _input: &[Meshlet], _adj: &MeshletAdjacencyInfo) -> SegmentResult {
-    unimplemented!()
+  fn segment_meshlets(
+    &self,
+    _input: &[Meshlet],
+    _adj: &MeshletAdjacencyInfo,
+  ) -> Result<SegmentResult, MeshLODGraphBuildError> {
+    Err(MeshLODGraphBuildError::MeshletSegmentation(
+      "metis is not supported in wasm target".to_string(),
+    ))
   }
 
   #[cfg(not(target_family = "wasm"))]
-  fn segment_meshlets(&self, input: &[Meshlet], adj: &MeshletAdjacencyInfo) -> SegmentResult {
+  fn segment_meshlets(
+    &self,
+    input: &[Meshlet],
+    adj: &MeshletAdjacencyInfo,
+  ) -> Result<SegmentResult, MeshLODGraphBuildError> {
+    let metis_error = |e: &dyn std::fmt::Display| {
+      MeshLODGraphBuildError::MeshletSegmentation(format!("metis: {e}"))
+    };
+
     let mut xadj = Vec::with_capacity(input.len() + 1);
     let mut adjncy = Vec::new();
     let mut adjwgt = Vec::new();
@@ -175,10 +189,10 @@ impl MeshLodGraphBuilder for DefaultMeshLODBuilder {
     let mut group_per_meshlet = vec![0; input.len()];
     let partition_count = (input.len().div_ceil(4)) as i32;
     metis::Graph::new(1, partition_count, &xadj, &adjncy)
-      .unwrap()
+      .map_err(|e| metis_error(&e))?
       .set_adjwgt(&adjwgt)
       .part_kway(&mut group_per_meshlet)
-      .unwrap();
+      .map_err(|e| metis_error(&e))?;
 
     let mut groups = FastHashMap::default();
     for (i, meshlet_group) in group_per_meshlet.into_iter().enumerate() {
@@ -197,9 +211,9 @@ impl MeshLodGraphBuilder for DefaultMeshLODBuilder {
       reordered_idx.extend(meshlet_ids);
     }
 
-    SegmentResult {
+    Ok(SegmentResult {
       reordered_idx,
       ranges,
-    }
+    })
   }
 }
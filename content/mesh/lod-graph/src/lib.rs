@@ -2,22 +2,26 @@
 
 use std::{fmt::Debug, ops::Range};
 
-use fast_hash_collection::FastHashSet;
+use fast_hash_collection::{FastHashMap, FastHashSet};
 use rendiation_algebra::*;
 use rendiation_geometry::Sphere;
 use rendiation_mesh_core::*;
-use rendiation_mesh_segmentation::SegmentResult;
+pub use rendiation_mesh_segmentation::SegmentResult;
 
 mod build;
 pub use build::*;
 mod meshlet_adjacency;
-use meshlet_adjacency::*;
+pub use meshlet_adjacency::*;
 mod util;
 use facet::*;
 use serde::*;
 pub use util::*;
 mod builder_impl;
 pub use builder_impl::*;
+mod asset;
+pub use asset::*;
+mod streaming;
+pub use streaming::*;
 
 const DEBUG_LOG: bool = true;
 
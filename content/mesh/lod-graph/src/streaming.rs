@@ -0,0 +1,489 @@
+use std::{sync::Arc, task::Context};
+
+use rendiation_uri_streaming::{LoadFuture, LoaderFunction, LoadingThrottler};
+
+use crate::*;
+
+/// The view info used to decide which pages are required, all in the mesh local space.
+#[derive(Clone, Copy, Debug)]
+pub struct MeshLODGraphStreamingView {
+  pub camera_position: Vec3<f32>,
+  /// the factor to convert the local space error at unit distance to pixels, for the perspective
+  /// camera it's viewport_height * projection\[1\]\[1\] / 2, multiplied by the max world scale
+  /// of the model.
+  pub pixel_scale: f32,
+  /// in pixels
+  pub error_threshold: f32,
+}
+
+impl MeshLODGraphStreamingView {
+  /// same as the projected error computation in the meshlet selection, the closest distance
+  /// to the sphere is used so the error is never underestimated.
+  pub fn project_error(&self, sphere: Sphere, error: f32) -> f32 {
+    if error == f32::MAX {
+      return f32::MAX;
+    }
+    let distance = ((sphere.center - self.camera_position).length() - sphere.radius).max(1e-6);
+    error * self.pixel_scale / distance
+  }
+}
+
+/// The uri like data to load a page, the byte range is in the asset.
+#[derive(Clone, Debug)]
+pub struct MeshLODGraphPageRequest {
+  pub page: u32,
+  pub byte_range: Range<u64>,
+}
+
+/// Load the pages from the asset fully in memory, mainly for the test and the small asset.
+pub fn create_mesh_lod_graph_in_memory_page_loader(
+  asset: Arc<MeshLODGraphAssetInfo>,
+  data: Arc<Vec<u8>>,
+) -> Box<LoaderFunction<MeshLODGraphPageRequest, MeshLODGraphPage>> {
+  Box::new(move |request: &MeshLODGraphPageRequest| {
+    let range = request.byte_range.start as usize..request.byte_range.end as usize;
+    let page = data
+      .get(range)
+      .and_then(|bytes| asset.decode_page(request.page, bytes).ok());
+    Box::new(std::future::ready(page)) as LoadFuture<MeshLODGraphPage>
+  })
+}
+
+/// Load the pages from the asset file, only the requested page's byte range is read. The read is
+/// synchronous, the same as the uri disk sync source.
+pub fn create_mesh_lod_graph_file_page_loader(
+  asset: Arc<MeshLODGraphAssetInfo>,
+  path: Arc<std::path::PathBuf>,
+) -> Box<LoaderFunction<MeshLODGraphPageRequest, MeshLODGraphPage>> {
+  Box::new(move |request: &MeshLODGraphPageRequest| {
+    let page = read_file_range(&path, request.byte_range.clone())
+      .map_err(MeshLODGraphAssetError::from)
+      .and_then(|bytes| asset.decode_page(request.page, &bytes));
+    let page = match page {
+      Ok(page) => Some(page),
+      Err(e) => {
+        log::error!(
+          "failed to load mesh lod graph page {} from {}: {e}",
+          request.page,
+          path.display()
+        );
+        None
+      }
+    };
+    Box::new(std::future::ready(page)) as LoadFuture<MeshLODGraphPage>
+  })
+}
+
+fn read_file_range(path: &std::path::Path, range: Range<u64>) -> std::io::Result<Vec<u8>> {
+  use std::io::{Read, Seek, SeekFrom};
+  let mut file = std::fs::File::open(path)?;
+  file.seek(SeekFrom::Start(range.start))?;
+  let mut bytes = vec![0; (range.end - range.start) as usize];
+  file.read_exact(&mut bytes)?;
+  Ok(bytes)
+}
+
+/// Page granular streaming of the mesh lod graph asset.
+///
+/// The coarsest level is always required. A finer page is required if its parent error is
+/// not acceptable in current view, and it will only be requested after all its parent pages
+/// are resident, so the coarse levels are loaded and rendered first, and the finer pages are
+/// streamed in by the screen space error.
+pub struct MeshLODGraphPageStreaming {
+  asset: Arc<MeshLODGraphAssetInfo>,
+  throttler: LoadingThrottler<u32, MeshLODGraphPage, MeshLODGraphPageRequest>,
+  resident: FastHashMap<u32, Arc<MeshLODGraphPage>>,
+  failed: FastHashSet<u32>,
+  required: Vec<bool>,
+  /// the larger is requested first
+  priority: Vec<f32>,
+  resident_changed: bool,
+}
+
+impl MeshLODGraphPageStreaming {
+  /// the bandwidth limitation is the max compressed bytes in loading at the same time
+  pub fn new(asset: Arc<MeshLODGraphAssetInfo>, bandwidth_limitation: u64) -> Self {
+    let page_count = asset.pages.len();
+    let required = (0..page_count as u32)
+      .map(|page| asset.is_coarsest_level_page(page))
+      .collect();
+    let mut s = Self {
+      asset,
+      throttler: LoadingThrottler::new(bandwidth_limitation),
+      resident: Default::default(),
+      failed: Default::default(),
+      required,
+      priority: vec![f32::MAX; page_count],
+      resident_changed: false,
+    };
+    s.request_pages();
+    s
+  }
+
+  pub fn asset(&self) -> &MeshLODGraphAssetInfo {
+    &self.asset
+  }
+
+  pub fn is_page_resident(&self, page: u32) -> bool {
+    self.resident.contains_key(&page)
+  }
+
+  pub fn resident_page_count(&self) -> usize {
+    self.resident.len()
+  }
+
+  /// if any page is waiting to be loaded or in loading
+  pub fn is_loading(&self) -> bool {
+    self.throttler.has_pending_request()
+  }
+
+  pub fn update_view(&mut self, view: &MeshLODGraphStreamingView) {
+    self.update_views(std::slice::from_ref(view));
+  }
+
+  /// the page is required if it's required by any of the views, for example the same mesh
+  /// is used by multiple models or rendered in multiple viewports.
+  pub fn update_views(&mut self, views: &[MeshLODGraphStreamingView]) {
+    let asset = self.asset.clone();
+    // the projected error relative to the threshold, the page is required if it's above one
+    for (info, priority) in asset.pages.iter().zip(self.priority.iter_mut()) {
+      let sphere = array_to_sphere(info.parent_lod_sphere);
+      *priority = views
+        .iter()
+        .map(|view| view.project_error(sphere, info.parent_error) / view.error_threshold)
+        .fold(0., f32::max);
+    }
+
+    // the pages are stored from the finest level to the coarsest level, so the requirement
+    // can be propagated to the coarser level in one pass
+    self.required.iter_mut().for_each(|r| *r = false);
+    for page in 0..asset.pages.len() as u32 {
+      if self.priority[page as usize] > 1. {
+        self.required[page as usize] = true;
+      }
+      if self.required[page as usize] {
+        for &parent in asset.parent_pages(page) {
+          self.required[parent as usize] = true;
+        }
+      }
+    }
+
+    for page in 0..asset.pages.len() as u32 {
+      if !self.required[page as usize] {
+        self.throttler.cancel_not_dispatched_load(&page);
+        if self.resident.remove(&page).is_some() {
+          self.resident_changed = true;
+        }
+      }
+    }
+
+    self.request_pages();
+  }
+
+  /// require all the pages regardless of the error, for example the error can not be projected
+  /// by the view or the full mesh is required
+  pub fn require_all_pages(&mut self) {
+    self.required.iter_mut().for_each(|r| *r = true);
+    // the coarse pages first
+    for (page, priority) in self.priority.iter_mut().enumerate() {
+      *priority = page as f32;
+    }
+    self.request_pages();
+  }
+
+  /// request the required pages whose parent pages are all resident
+  fn request_pages(&mut self) {
+    let mut to_request = (0..self.asset.pages.len() as u32)
+      .filter(|&page| {
+        self.required[page as usize]
+          && !self.failed.contains(&page)
+          && !self.throttler.is_in_request(&page)
+          && self
+            .asset
+            .parent_pages(page)
+            .iter()
+            .all(|parent| self.resident.contains_key(parent))
+      })
+      .map(|page| (page, self.priority[page as usize]))
+      .collect::<Vec<_>>();
+
+    // the most important page first
+    to_request.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    for (page, _) in to_request {
+      let request = MeshLODGraphPageRequest {
+        page,
+        byte_range: self.asset.page_byte_range(page),
+      };
+      let cost = self.asset.pages[page as usize].compressed_byte_size as u64;
+      self.throttler.request_load(page, request, cost);
+    }
+  }
+
+  /// return if the resident pages changed since the last poll
+  pub fn poll_loading(
+    &mut self,
+    cx: &mut Context,
+    loader: &mut LoaderFunction<MeshLODGraphPageRequest, MeshLODGraphPage>,
+  ) -> bool {
+    let mut new_resident = false;
+    for (page, loaded) in self.throttler.poll_loading(cx, loader) {
+      if let Some(loaded) = loaded {
+        self.resident.insert(page, Arc::new(loaded));
+        new_resident = true;
+      } else {
+        log::warn!("failed to load mesh lod graph page {page}");
+        self.throttler.cancel_not_dispatched_load(&page);
+        self.failed.insert(page);
+      }
+    }
+    if new_resident {
+      // the finer pages become requestable after their parent pages are resident
+      self.request_pages();
+      self.resident_changed = true;
+    }
+    std::mem::take(&mut self.resident_changed)
+  }
+
+  /// a page is usable if itself and all its parent pages are usable, if a page is not usable,
+  /// the coarser meshlets simplified from it will be treated as acceptable in any view
+  fn compute_usable_pages(&self) -> Vec<bool> {
+    let mut usable = vec![false; self.asset.pages.len()];
+    for page in (0..self.asset.pages.len() as u32).rev() {
+      usable[page as usize] = self.resident.contains_key(&page)
+        && self
+          .asset
+          .parent_pages(page)
+          .iter()
+          .all(|parent| usable[*parent as usize]);
+    }
+    usable
+  }
+
+  /// merge all usable pages into one mesh, the lod selection on the merged meshlets is
+  /// always crack free
+  pub fn build_resident_mesh(&self) -> MeshLODGraphResidentMesh {
+    let usable = self.compute_usable_pages();
+
+    // the finest level that all its pages are usable, the coarsest level is complete as soon
+    // as it's resident
+    let complete_level = self.asset.levels.iter().position(|level| {
+      let pages = level.first_page as usize..(level.first_page + level.page_count) as usize;
+      level.page_count > 0 && usable[pages].iter().all(|u| *u)
+    });
+    let complete_pages = complete_level
+      .map(|level| {
+        let level = &self.asset.levels[level];
+        level.first_page..level.first_page + level.page_count
+      })
+      .unwrap_or_default();
+
+    let mut meshlets = Vec::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    // the complete level is placed at the start
+    let pages = complete_pages.clone().chain(
+      (0..self.asset.pages.len() as u32)
+        .filter(|page| usable[*page as usize] && !complete_pages.contains(page)),
+    );
+
+    let mut complete_level_index_count = 0;
+    for page in pages {
+      let data = self.resident.get(&page).unwrap();
+      if complete_pages.contains(&page) {
+        complete_level_index_count += data.indices.len() as u32;
+      }
+      let vertex_base = vertices.len() as u32;
+      let index_base = indices.len() as u32;
+
+      vertices.extend_from_slice(&data.vertices);
+      indices.extend(data.indices.iter().map(|i| i + vertex_base));
+      meshlets.extend(data.meshlets.iter().map(|meshlet| {
+        let mut meshlet = *meshlet;
+        meshlet.index_offset += index_base;
+        if meshlet.source_page != u32::MAX && !usable[meshlet.source_page as usize] {
+          meshlet.self_error = 0.;
+        }
+        meshlet
+      }));
+    }
+
+    MeshLODGraphResidentMesh {
+      mesh: MeshLODGraphPage {
+        meshlets,
+        vertices,
+        indices,
+      },
+      complete_level: complete_level.map(|level| level as u32),
+      complete_level_index_count,
+    }
+  }
+}
+
+pub struct MeshLODGraphResidentMesh {
+  /// the merged mesh of all usable pages
+  pub mesh: MeshLODGraphPage,
+  /// the finest level that all its pages are usable, None if nothing is resident
+  pub complete_level: Option<u32>,
+  /// the complete level's indices are placed at the start of the merged mesh, so it can be
+  /// drawn directly without lod selection
+  pub complete_level_index_count: u32,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::asset::tests::create_test_lod_graph;
+
+  #[test]
+  fn test_page_streaming() {
+    let graph = create_test_lod_graph();
+    let config = MeshLODGraphAssetBuildConfig {
+      max_meshlet_count_per_page: 1,
+      ..Default::default()
+    };
+    let data = Arc::new(write_mesh_lod_graph_asset(&graph, &config));
+    let asset = Arc::new(MeshLODGraphAssetInfo::parse(&data).unwrap());
+    let mut loader = create_mesh_lod_graph_in_memory_page_loader(asset.clone(), data);
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+
+    let mut streaming = MeshLODGraphPageStreaming::new(asset, u64::MAX);
+
+    // near the first triangle, far away from the second one
+    let view = MeshLODGraphStreamingView {
+      camera_position: Vec3::new(0., 0., 2.),
+      pixel_scale: 10.,
+      error_threshold: 1.,
+    };
+
+    // the first poll dispatches the coarsest page loading, the finer pages are not requested
+    // before the coarsest page is resident
+    streaming.update_view(&view);
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.poll_loading(&mut cx, &mut loader));
+    assert!(streaming.is_page_resident(2));
+    assert_eq!(streaming.resident_page_count(), 1);
+
+    // the finer data is not available, the coarse meshlets should always be acceptable
+    let resident = streaming.build_resident_mesh();
+    assert_eq!(resident.complete_level, Some(1));
+    assert_eq!(resident.complete_level_index_count, 6);
+    let mesh = resident.mesh;
+    assert_eq!(mesh.meshlets.len(), 2);
+    assert!(mesh.meshlets.iter().all(|m| m.self_error == 0.));
+
+    streaming.update_view(&view);
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.poll_loading(&mut cx, &mut loader));
+    assert!(streaming.is_page_resident(0));
+    assert!(!streaming.is_page_resident(1));
+
+    // the finest level is not complete, the coarsest level is still placed at the start
+    let resident = streaming.build_resident_mesh();
+    assert_eq!(resident.complete_level, Some(1));
+    assert_eq!(resident.complete_level_index_count, 6);
+    let mesh = resident.mesh;
+    assert_eq!(mesh.meshlets.len(), 3);
+    assert_eq!(mesh.vertices.len(), 9);
+    assert_eq!(mesh.indices[6..], [6, 7, 8]);
+    assert_eq!(mesh.meshlets[2].index_offset, 6);
+    // the coarse meshlet simplified from the resident page follows the normal selection
+    assert_eq!(mesh.meshlets[0].self_error, 0.5);
+    assert_eq!(mesh.meshlets[1].self_error, 0.);
+    assert_eq!(mesh.meshlets[2].self_error, 0.);
+
+    // the finer page is released when it's not required anymore
+    let far_away = MeshLODGraphStreamingView {
+      camera_position: Vec3::new(0., 0., 1000.),
+      ..view
+    };
+    let page_0 = &streaming.asset().pages[0];
+    let far_away_error = far_away.project_error(
+      array_to_sphere(page_0.parent_lod_sphere),
+      page_0.parent_error,
+    );
+    assert!(far_away_error < far_away.error_threshold);
+
+    streaming.update_view(&far_away);
+    assert!(streaming.poll_loading(&mut cx, &mut loader));
+    assert!(!streaming.is_page_resident(0));
+    assert!(streaming.is_page_resident(2));
+    assert_eq!(streaming.resident_page_count(), 1);
+    assert!(!streaming.poll_loading(&mut cx, &mut loader));
+
+    let mesh = streaming.build_resident_mesh().mesh;
+    assert_eq!(mesh.meshlets.len(), 2);
+    assert!(mesh.meshlets.iter().all(|m| m.self_error == 0.));
+
+    // and loaded again when the view comes back
+    streaming.update_view(&view);
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.poll_loading(&mut cx, &mut loader));
+    assert!(streaming.is_page_resident(0));
+
+    // the finest level is complete after all its pages are loaded
+    let near_both = MeshLODGraphStreamingView {
+      camera_position: Vec3::new(5., 0., 2.),
+      ..view
+    };
+    streaming.update_views(&[view, near_both]);
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.poll_loading(&mut cx, &mut loader));
+    assert!(!streaming.is_loading());
+    let resident = streaming.build_resident_mesh();
+    assert_eq!(resident.complete_level, Some(0));
+    assert_eq!(resident.complete_level_index_count, 6);
+    assert_eq!(resident.mesh.indices[..6], [0, 1, 2, 3, 4, 5]);
+  }
+
+  #[test]
+  fn test_file_page_loading() {
+    let graph = create_test_lod_graph();
+    let config = MeshLODGraphAssetBuildConfig {
+      max_meshlet_count_per_page: 1,
+      ..Default::default()
+    };
+    let data = write_mesh_lod_graph_asset(&graph, &config);
+    let path = std::env::temp_dir().join("mesh_lod_graph_file_page_loading_test.lodgraph");
+    std::fs::write(&path, &data).unwrap();
+
+    // only the non page part is read for the info
+    let asset = Arc::new(MeshLODGraphAssetInfo::read_from_file(&path).unwrap());
+    let in_memory = MeshLODGraphAssetInfo::parse(&data).unwrap();
+    assert_eq!(asset.pages.len(), in_memory.pages.len());
+
+    let path = Arc::new(path);
+    let mut loader = create_mesh_lod_graph_file_page_loader(asset.clone(), path.clone());
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    for page in 0..asset.pages.len() as u32 {
+      let byte_range = asset.page_byte_range(page);
+      let range = byte_range.start as usize..byte_range.end as usize;
+      let expect = asset.decode_page(page, &data[range]).unwrap();
+
+      let mut future = loader(&MeshLODGraphPageRequest { page, byte_range });
+      let std::task::Poll::Ready(Some(loaded)) = std::pin::Pin::new(&mut future).poll(&mut cx)
+      else {
+        panic!("page {page} should be loaded");
+      };
+      assert_eq!(loaded.indices, expect.indices);
+      assert_eq!(loaded.vertices.len(), expect.vertices.len());
+    }
+
+    // the range out of the file fails the load instead of panicking
+    let byte_range = data.len() as u64..data.len() as u64 + 4;
+    let mut future = loader(&MeshLODGraphPageRequest {
+      page: 0,
+      byte_range,
+    });
+    let result = std::pin::Pin::new(&mut future).poll(&mut cx);
+    assert!(matches!(result, std::task::Poll::Ready(None)));
+
+    // the truncated file is rejected when reading the info
+    let header_size = std::mem::size_of::<MeshLODGraphAssetHeader>();
+    std::fs::write(path.as_ref(), &data[..header_size + 1]).unwrap();
+    assert!(MeshLODGraphAssetInfo::read_from_file(&path).is_err());
+
+    std::fs::remove_file(path.as_ref()).unwrap();
+  }
+}
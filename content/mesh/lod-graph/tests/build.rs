@@ -0,0 +1,133 @@
+use rendiation_algebra::*;
+use rendiation_geometry::Sphere;
+use rendiation_mesh_core::*;
+use rendiation_mesh_lod_graph::*;
+
+/// every triangle is its own meshlet, the triangle order is reversed to make sure the meshlet
+/// index range is based on the reordered indices. every two meshlets form a group.
+struct ReversingBuilder;
+
+impl MeshLodGraphBuilder for ReversingBuilder {
+  /// never simplify anything, this is what happens when all vertices are locked.
+  fn simplify(
+    &self,
+    _vertices: &[CommonVertex],
+    indices: &[u32],
+    _locked_edges: &EdgeFinder,
+    _target_tri_num: u32,
+  ) -> MeshLODGraphSimplificationResult {
+    MeshLODGraphSimplificationResult {
+      simplified_indices: indices.to_vec(),
+      error: 0.,
+    }
+  }
+
+  fn segment_triangles(
+    &self,
+    vertices: &[CommonVertex],
+    indices: &[u32],
+  ) -> (Vec<Meshlet>, Vec<u32>) {
+    let reordered: Vec<u32> = indices.chunks_exact(3).rev().flatten().copied().collect();
+    let meshlets = reordered
+      .chunks_exact(3)
+      .enumerate()
+      .map(|(i, tri)| Meshlet {
+        group_index: 0,
+        group_index_in_previous_level: u32::MAX,
+        index_range: OffsetSize {
+          offset: i as u32 * 3,
+          size: 3,
+        },
+        bounding_in_local: Sphere::from_points(tri.iter().map(|i| vertices[*i as usize].position)),
+      })
+      .collect();
+    (meshlets, reordered)
+  }
+
+  fn segment_meshlets(
+    &self,
+    input: &[Meshlet],
+    _adj: &MeshletAdjacencyInfo,
+  ) -> Result<SegmentResult, MeshLODGraphBuildError> {
+    let count = input.len() as u32;
+    Ok(SegmentResult {
+      reordered_idx: (0..count).collect(),
+      ranges: (0..count)
+        .step_by(2)
+        .map(|start| start..(start + 2).min(count))
+        .collect(),
+    })
+  }
+}
+
+/// disconnected triangles placed far away from each other along the x axis
+fn separated_triangles(count: u32) -> CommonMeshBuffer {
+  let vertices = (0..count)
+    .flat_map(|i| {
+      let x = i as f32 * 100.;
+      [
+        Vec3::new(x, 0., 0.),
+        Vec3::new(x + 1., 0., 0.),
+        Vec3::new(x, 1., 0.),
+      ]
+    })
+    .map(|position| CommonVertex {
+      position,
+      normal: Vec3::new(0., 0., 1.),
+      uv: Vec2::zero(),
+    })
+    .collect();
+  CommonMeshBuffer {
+    indices: (0..count * 3).collect(),
+    vertices,
+  }
+}
+
+#[test]
+fn build_stops_when_simplification_stalls() {
+  let graph = ReversingBuilder
+    .build_from_mesh(separated_triangles(4))
+    .unwrap();
+
+  assert_eq!(graph.levels.len(), 2);
+  let base = &graph.levels[0];
+  let coarsest = graph.levels.last().unwrap();
+  assert_eq!(base.meshlets.len(), 4);
+  assert_eq!(coarsest.meshlets.len(), base.meshlets.len());
+}
+
+#[test]
+fn base_level_meshlet_range_is_based_on_reordered_indices() {
+  let mesh = separated_triangles(4);
+  let graph = ReversingBuilder.build_from_mesh(mesh.clone()).unwrap();
+  let base = &graph.levels[0];
+
+  let reversed: Vec<u32> = mesh
+    .indices
+    .chunks_exact(3)
+    .rev()
+    .flatten()
+    .copied()
+    .collect();
+  assert_eq!(base.mesh.indices, reversed);
+
+  for meshlet in &base.meshlets {
+    let sphere = meshlet.bounding_in_local;
+    for index in &base.mesh.indices[meshlet.index_range.into_range()] {
+      let position = base.mesh.vertices[*index as usize].position;
+      assert!((position - sphere.center).length() <= sphere.radius + 1e-4);
+    }
+  }
+}
+
+#[test]
+fn build_empty_mesh_returns_error() {
+  let mesh = CommonMeshBuffer {
+    indices: Vec::new(),
+    vertices: Vec::new(),
+  };
+  assert!(matches!(
+    ReversingBuilder.build_from_mesh(mesh),
+    Err(MeshLODGraphBuildError::EmptyMesh)
+  ));
+}
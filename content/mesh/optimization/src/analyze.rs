@@ -0,0 +1,286 @@
+use crate::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VertexCacheStatistics {
+  pub vertices_transformed: u32,
+  pub warps_executed: u32,
+  /// average cache miss ratio, the transformed vertices per triangle, in 0.5..=3
+  pub acmr: f32,
+  /// average transformed vertex ratio, the transformed vertices per referenced vertex, in 1..=6
+  pub atvr: f32,
+}
+
+/// Simulate the fifo post transform cache of the given size. The warp_size and primgroup_size
+/// simulate the hardware that flushes the cache when the vertices of a triangle not fit into the
+/// current warp or the primitive group is full, zero means not limited.
+///
+/// For the nvidia like hardware, cache_size = 32, warp_size = 32, primgroup_size = 32;
+/// For the amd like hardware, cache_size = 14, warp_size = 64, primgroup_size = 128.
+pub fn analyze_vertex_cache(
+  indices: &[u32],
+  vertex_count: usize,
+  cache_size: u32,
+  warp_size: u32,
+  primgroup_size: u32,
+) -> VertexCacheStatistics {
+  assert!(indices.len().is_multiple_of(3));
+  let mut result = VertexCacheStatistics::default();
+
+  let mut warp_offset = 0;
+  let mut primgroup_offset = 0;
+
+  let mut timestamps = vec![0_u32; vertex_count];
+  let mut timestamp = cache_size + 1;
+
+  for triangle in indices.chunks_exact(3) {
+    let misses = triangle
+      .iter()
+      .filter(|v| timestamp - timestamps[**v as usize] > cache_size)
+      .count() as u32;
+
+    // flush cache if triangle doesn't fit into warp or into the primitive buffer
+    if (primgroup_size > 0 && primgroup_offset == primgroup_size)
+      || (warp_size > 0 && warp_offset + misses > warp_size)
+    {
+      result.warps_executed += (warp_offset > 0) as u32;
+      warp_offset = 0;
+      primgroup_offset = 0;
+      timestamp += cache_size + 1;
+    }
+
+    for v in triangle {
+      let v = *v as usize;
+      if timestamp - timestamps[v] > cache_size {
+        timestamps[v] = timestamp;
+        timestamp += 1;
+        result.vertices_transformed += 1;
+        warp_offset += 1;
+      }
+    }
+
+    primgroup_offset += 1;
+  }
+
+  let unique_vertex_count = timestamps.iter().filter(|t| **t > 0).count();
+
+  result.warps_executed += (warp_offset > 0) as u32;
+  result.acmr = if indices.is_empty() {
+    0.
+  } else {
+    result.vertices_transformed as f32 / (indices.len() / 3) as f32
+  };
+  result.atvr = if unique_vertex_count == 0 {
+    0.
+  } else {
+    result.vertices_transformed as f32 / unique_vertex_count as f32
+  };
+
+  result
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VertexFetchStatistics {
+  pub bytes_fetched: u32,
+  /// the fetched bytes / the referenced vertex bytes, 1 is the best
+  pub overfetch: f32,
+}
+
+/// Simulate the vertex fetch by a 128KB direct mapped cache with the 64 bytes cache line.
+pub fn analyze_vertex_fetch(
+  indices: &[u32],
+  vertex_count: usize,
+  vertex_size: usize,
+) -> VertexFetchStatistics {
+  assert!(vertex_size > 0);
+  const CACHE_LINE: usize = 64;
+  const CACHE_SIZE: usize = 128 * 1024;
+
+  let mut result = VertexFetchStatistics::default();
+  let mut vertex_visited = vec![false; vertex_count];
+  // we store tag + 1 since cache is filled with 0 by default
+  let mut cache = vec![0_usize; CACHE_SIZE / CACHE_LINE];
+
+  for index in indices {
+    let index = *index as usize;
+    vertex_visited[index] = true;
+
+    let start_address = index * vertex_size;
+    let end_address = start_address + vertex_size;
+
+    let start_tag = start_address / CACHE_LINE;
+    let end_tag = end_address.div_ceil(CACHE_LINE);
+
+    for tag in start_tag..end_tag {
+      let line = &mut cache[tag % (CACHE_SIZE / CACHE_LINE)];
+      if *line != tag + 1 {
+        result.bytes_fetched += CACHE_LINE as u32;
+      }
+      *line = tag + 1;
+    }
+  }
+
+  let unique_vertex_count = vertex_visited.iter().filter(|v| **v).count();
+  result.overfetch = if unique_vertex_count == 0 {
+    0.
+  } else {
+    result.bytes_fetched as f32 / (unique_vertex_count * vertex_size) as f32
+  };
+
+  result
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OverdrawStatistics {
+  pub pixels_covered: u32,
+  pub pixels_shaded: u32,
+  /// the shaded pixels / the covered pixels, 1 is the best
+  pub overdraw: f32,
+}
+
+const VIEWPORT: usize = 256;
+
+/// the depth and shaded count of the front and back facing triangles
+struct OverdrawBuffer {
+  z: Vec<[f32; 2]>,
+  overdraw: Vec<[u32; 2]>,
+}
+
+impl OverdrawBuffer {
+  fn new() -> Self {
+    Self {
+      z: vec![[0.; 2]; VIEWPORT * VIEWPORT],
+      overdraw: vec![[0; 2]; VIEWPORT * VIEWPORT],
+    }
+  }
+
+  fn clear(&mut self) {
+    self.z.fill([0.; 2]);
+    self.overdraw.fill([0; 2]);
+  }
+
+  /// half space fixed point triangle rasterizer with the depth test, the vertex is in the
+  /// viewport space.
+  fn rasterize(&mut self, v1: Vec3<f32>, v2: Vec3<f32>, v3: Vec3<f32>) {
+    // solve the depth gradients by the Cramer's rule
+    let det = (v2.x - v1.x) * (v3.y - v1.y) - (v2.y - v1.y) * (v3.x - v1.x);
+    let inv_det = if det == 0. { 0. } else { 1. / det };
+    let dz_dx = ((v2.z - v1.z) * (v3.y - v1.y) - (v2.y - v1.y) * (v3.z - v1.z)) * inv_det;
+    let dz_dy = ((v2.x - v1.x) * (v3.z - v1.z) - (v2.z - v1.z) * (v3.x - v1.x)) * inv_det;
+
+    // flip the back facing triangle to simplify the rasterization, the depth gradients are based
+    // on v1 so they are kept.
+    let side = (det > 0.) as usize;
+    let (v2, v3) = if side == 1 { (v3, v2) } else { (v2, v3) };
+
+    // 28.4 fixed point coordinates
+    let fixed = |v: f32| (16. * v + 0.5) as i32;
+    let (x1, x2, x3) = (fixed(v1.x), fixed(v2.x), fixed(v3.x));
+    let (y1, y2, y3) = (fixed(v1.y), fixed(v2.y), fixed(v3.y));
+
+    let (dx12, dx23, dx31) = (x1 - x2, x2 - x3, x3 - x1);
+    let (dy12, dy23, dy31) = (y1 - y2, y2 - y3, y3 - y1);
+
+    let (fdx12, fdx23, fdx31) = (dx12 << 4, dx23 << 4, dx31 << 4);
+    let (fdy12, fdy23, fdy31) = (dy12 << 4, dy23 << 4, dy31 << 4);
+
+    let min_x = ((x1.min(x2).min(x3) + 0xF) >> 4).max(0);
+    let max_x = ((x1.max(x2).max(x3) + 0xF) >> 4).min(VIEWPORT as i32);
+    let min_y = ((y1.min(y2).min(y3) + 0xF) >> 4).max(0);
+    let max_y = ((y1.max(y2).max(y3) + 0xF) >> 4).min(VIEWPORT as i32);
+
+    // degenerated triangle
+    if max_x <= min_x || max_y <= min_y {
+      return;
+    }
+
+    // half edge constants, corrected for the fill convention
+    let c1 = dy12 * x1 - dx12 * y1 + (dy12 < 0 || (dy12 == 0 && dx12 > 0)) as i32;
+    let c2 = dy23 * x2 - dx23 * y2 + (dy23 < 0 || (dy23 == 0 && dx23 > 0)) as i32;
+    let c3 = dy31 * x3 - dx31 * y3 + (dy31 < 0 || (dy31 == 0 && dx31 > 0)) as i32;
+
+    let mut cy1 = c1 + dx12 * (min_y << 4) - dy12 * (min_x << 4);
+    let mut cy2 = c2 + dx23 * (min_y << 4) - dy23 * (min_x << 4);
+    let mut cy3 = c3 + dx31 * (min_y << 4) - dy31 * (min_x << 4);
+    let mut zy = v1.z + dz_dx * (min_x as f32 - v1.x) + dz_dy * (min_y as f32 - v1.y);
+
+    for y in min_y..max_y {
+      let (mut cx1, mut cx2, mut cx3) = (cy1, cy2, cy3);
+      let mut zx = zy;
+
+      for x in min_x..max_x {
+        if (cx1 | cx2 | cx3) >= 0 {
+          let pixel = y as usize * VIEWPORT + x as usize;
+          if zx >= self.z[pixel][side] {
+            self.z[pixel][side] = zx;
+            self.overdraw[pixel][side] += 1;
+          }
+        }
+
+        cx1 = cx1.wrapping_sub(fdy12);
+        cx2 = cx2.wrapping_sub(fdy23);
+        cx3 = cx3.wrapping_sub(fdy31);
+        zx += dz_dx;
+      }
+
+      cy1 = cy1.wrapping_add(fdx12);
+      cy2 = cy2.wrapping_add(fdx23);
+      cy3 = cy3.wrapping_add(fdx31);
+      zy += dz_dy;
+    }
+  }
+}
+
+/// Estimate the overdraw by rasterizing the mesh from the three axis directions into a 256x256
+/// viewport, the front and back facing triangles are counted separately.
+pub fn analyze_overdraw<V: Positioned<Position = Vec3<f32>>>(
+  indices: &[u32],
+  vertices: &[V],
+) -> OverdrawStatistics {
+  assert!(indices.len().is_multiple_of(3));
+  let mut result = OverdrawStatistics::default();
+
+  let bbox: Box3 = vertices.iter().map(|v| v.position()).collect();
+  let size = bbox.size();
+  let extent = size.x.max(size.y).max(size.z);
+  if indices.is_empty() || extent <= 0. {
+    return result;
+  }
+  let scale = VIEWPORT as f32 / extent;
+
+  let triangles: Vec<_> = indices
+    .iter()
+    .map(|i| (vertices[*i as usize].position() - bbox.min) * scale)
+    .collect();
+
+  let mut buffer = OverdrawBuffer::new();
+  for axis in 0..3 {
+    buffer.clear();
+
+    let project = |v: Vec3<f32>| match axis {
+      0 => Vec3::new(v.z, v.y, v.x),
+      1 => Vec3::new(v.x, v.z, v.y),
+      _ => Vec3::new(v.y, v.x, v.z),
+    };
+
+    for triangle in triangles.chunks_exact(3) {
+      buffer.rasterize(
+        project(triangle[0]),
+        project(triangle[1]),
+        project(triangle[2]),
+      );
+    }
+
+    for overdraw in buffer.overdraw.iter().flatten() {
+      result.pixels_covered += (*overdraw > 0) as u32;
+      result.pixels_shaded += overdraw;
+    }
+  }
+
+  result.overdraw = if result.pixels_covered > 0 {
+    result.pixels_shaded as f32 / result.pixels_covered as f32
+  } else {
+    0.
+  };
+
+  result
+}
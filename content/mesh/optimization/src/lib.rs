@@ -0,0 +1,168 @@
+#![feature(iter_array_chunks)]
+
+//! The mesh index and vertex reordering for the gpu efficiency, the algorithms are ported from
+//! the meshoptimizer.
+//!
+//! The recommended order is: vertex cache -> overdraw -> vertex fetch, see [optimize_mesh_buffer].
+
+use rendiation_algebra::*;
+use rendiation_geometry::*;
+use rendiation_mesh_core::*;
+
+mod analyze;
+mod overdraw;
+mod vertex_cache;
+mod vertex_fetch;
+
+pub use analyze::*;
+pub use overdraw::*;
+pub use vertex_cache::*;
+pub use vertex_fetch::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshOptimizationConfig {
+  pub vertex_cache: bool,
+  /// the allowed vertex cache efficiency trade off for the overdraw reduction, None to disable
+  /// the overdraw optimization. see [optimize_overdraw]
+  pub overdraw_threshold: Option<f32>,
+  /// reorder the vertices and remove the unused vertices
+  pub vertex_fetch: bool,
+}
+
+impl Default for MeshOptimizationConfig {
+  fn default() -> Self {
+    Self {
+      vertex_cache: true,
+      overdraw_threshold: Some(1.05),
+      vertex_fetch: true,
+    }
+  }
+}
+
+impl MeshOptimizationConfig {
+  /// only reorder the triangles, this is useful when the vertices are referenced by other data
+  /// (for example the morph targets) that can not be remapped.
+  pub fn triangle_order_only(self) -> Self {
+    Self {
+      vertex_fetch: false,
+      ..self
+    }
+  }
+}
+
+/// Reorder the triangles of the triangle list index buffer in place.
+pub fn optimize_triangle_order<V: Positioned<Position = Vec3<f32>>>(
+  indices: &mut [u32],
+  vertices: &[V],
+  config: &MeshOptimizationConfig,
+) {
+  let mut scratch = vec![0; indices.len()];
+  if config.vertex_cache {
+    optimize_vertex_cache(&mut scratch, indices, vertices.len());
+    indices.copy_from_slice(&scratch);
+  }
+  if let Some(threshold) = config.overdraw_threshold {
+    optimize_overdraw(&mut scratch, indices, vertices, threshold);
+    indices.copy_from_slice(&scratch);
+  }
+}
+
+/// Apply all the enabled optimizations to the triangle list index and vertex buffer.
+pub fn optimize_mesh_buffer<V: Positioned<Position = Vec3<f32>> + Copy>(
+  indices: &mut [u32],
+  vertices: &mut Vec<V>,
+  config: &MeshOptimizationConfig,
+) {
+  optimize_triangle_order(indices, vertices, config);
+  if config.vertex_fetch {
+    *vertices = optimize_vertex_fetch(indices, vertices);
+  }
+}
+
+pub fn optimize_common_mesh(
+  mut mesh: CommonMeshBuffer,
+  config: &MeshOptimizationConfig,
+) -> CommonMeshBuffer {
+  optimize_mesh_buffer(&mut mesh.indices, &mut mesh.vertices, config);
+  mesh
+}
+
+pub fn optimize_indexed_mesh<V: Positioned<Position = Vec3<f32>> + Copy>(
+  mesh: IndexedMesh<TriangleList, Vec<V>, Vec<u32>>,
+  config: &MeshOptimizationConfig,
+) -> IndexedMesh<TriangleList, Vec<V>, Vec<u32>> {
+  let IndexedMesh {
+    mut vertex,
+    mut index,
+    ..
+  } = mesh;
+  optimize_mesh_buffer(&mut index, &mut vertex, config);
+  IndexedMesh::new(vertex, index)
+}
+
+/// Optimize the indexed triangle list attribute mesh, all the attributes are remapped if the
+/// vertex fetch optimization is enabled. Return None if the mesh is not supported(not indexed
+/// or not triangle list), or the data is not accessible.
+pub fn optimize_attributes_mesh(
+  mesh: &AttributesMesh,
+  config: &MeshOptimizationConfig,
+) -> Option<AttributesMesh> {
+  if mesh.mode != MeshPrimitiveTopology::TriangleList {
+    return None;
+  }
+  let (format, index) = mesh.indices.as_ref()?;
+  let mut indices: Vec<u32> = match format {
+    AttributeIndexFormat::Uint16 => index
+      .visit_slice::<u16>()?
+      .iter()
+      .map(|i| *i as u32)
+      .collect(),
+    AttributeIndexFormat::Uint32 => index.visit_slice::<u32>()?.to_vec(),
+  };
+  if !indices.len().is_multiple_of(3) {
+    return None;
+  }
+
+  let positions = mesh
+    .get_attribute(&AttributeSemantic::Positions)?
+    .visit_slice::<Vec3<f32>>()?;
+  if indices.iter().any(|i| *i as usize >= positions.len()) {
+    return None;
+  }
+
+  optimize_triangle_order(&mut indices, positions, config);
+
+  let attributes = if config.vertex_fetch {
+    let (remap, unique_count) = optimize_vertex_fetch_remap(&indices, positions.len());
+    remap_index_buffer(&mut indices, &remap);
+
+    mesh
+      .attributes
+      .iter()
+      .map(|(semantic, accessor)| {
+        let bytes = accessor
+          .visit_bytes()?
+          .get(..accessor.count * accessor.item_byte_size)?;
+        let bytes = remap_vertex_bytes(bytes, accessor.item_byte_size, &remap, unique_count);
+        let accessor = AttributeAccessor::create_owned(bytes, accessor.item_byte_size);
+        Some((semantic.clone(), accessor))
+      })
+      .collect::<Option<_>>()?
+  } else {
+    mesh.attributes.clone()
+  };
+
+  let index = match format {
+    AttributeIndexFormat::Uint16 => {
+      let indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+      AttributeAccessor::create_owned(indices, 2)
+    }
+    AttributeIndexFormat::Uint32 => AttributeAccessor::create_owned(indices, 4),
+  };
+
+  Some(AttributesMesh {
+    attributes,
+    indices: Some((*format, index)),
+    mode: mesh.mode,
+  })
+}
@@ -0,0 +1,199 @@
+use crate::*;
+
+const CACHE_SIZE: u32 = 16;
+
+/// the timestamp based fifo cache simulation, return the cache miss count of the triangle
+struct CacheSimulation {
+  timestamps: Vec<u32>,
+  timestamp: u32,
+}
+
+impl CacheSimulation {
+  fn new(vertex_count: usize, timestamp: u32) -> Self {
+    Self {
+      timestamps: vec![0; vertex_count],
+      timestamp,
+    }
+  }
+
+  fn reset(&mut self) {
+    self.timestamp += CACHE_SIZE + 1;
+  }
+
+  fn update(&mut self, triangle: &[u32]) -> u32 {
+    let mut misses = 0;
+    for v in triangle {
+      let v = *v as usize;
+      if self.timestamp - self.timestamps[v] > CACHE_SIZE {
+        self.timestamps[v] = self.timestamp;
+        self.timestamp += 1;
+        misses += 1;
+      }
+    }
+    misses
+  }
+}
+
+/// the cluster start triangle index when all three vertices are not in the cache, this usually
+/// means a new disjoint patch of the mesh.
+fn generate_hard_boundaries(indices: &[u32], vertex_count: usize) -> Vec<usize> {
+  let mut cache = CacheSimulation::new(vertex_count, CACHE_SIZE + 1);
+  indices
+    .chunks_exact(3)
+    .enumerate()
+    .filter_map(|(i, triangle)| {
+      let misses = cache.update(triangle);
+      (i == 0 || misses == 3).then_some(i)
+    })
+    .collect()
+}
+
+/// split the hard clusters into the smaller ones as long as the acmr of the split cluster is not
+/// worse than the threshold scaled acmr of the hard cluster.
+fn generate_soft_boundaries(
+  indices: &[u32],
+  vertex_count: usize,
+  hard_clusters: &[usize],
+  threshold: f32,
+) -> Vec<usize> {
+  let face_count = indices.len() / 3;
+  let mut cache = CacheSimulation::new(vertex_count, 0);
+  let mut result = Vec::with_capacity(hard_clusters.len());
+
+  for (it, start) in hard_clusters.iter().copied().enumerate() {
+    let end = hard_clusters.get(it + 1).copied().unwrap_or(face_count);
+    let cluster = &indices[start * 3..end * 3];
+
+    cache.reset();
+    let cluster_misses: u32 = cluster.chunks_exact(3).map(|t| cache.update(t)).sum();
+    let cluster_threshold = threshold * (cluster_misses as f32 / (end - start) as f32);
+
+    result.push(start);
+
+    cache.reset();
+    let mut running_misses = 0;
+    let mut running_faces = 0;
+    for (i, triangle) in cluster.chunks_exact(3).enumerate() {
+      running_misses += cache.update(triangle);
+      running_faces += 1;
+
+      if running_misses as f32 / running_faces as f32 <= cluster_threshold {
+        result.push(start + i + 1);
+        cache.reset();
+        running_misses = 0;
+        running_faces = 0;
+      }
+    }
+
+    // the last split cluster is usually bad(or empty if it just reached the end), so we merge
+    // it with the previous one.
+    if *result.last().unwrap() != start {
+      result.pop();
+    }
+  }
+
+  result
+}
+
+/// the dot product of the cluster normal and the vector from the mesh centroid to the cluster
+/// centroid, the cluster that has larger value is more likely to occlude the others.
+fn compute_sort_data(indices: &[u32], positions: &[Vec3<f32>], clusters: &[usize]) -> Vec<f32> {
+  let mesh_centroid = indices
+    .iter()
+    .fold(Vec3::zero(), |sum, i| sum + positions[*i as usize])
+    / indices.len() as f32;
+
+  let face_count = indices.len() / 3;
+  clusters
+    .iter()
+    .enumerate()
+    .map(|(it, begin)| {
+      let end = clusters.get(it + 1).copied().unwrap_or(face_count);
+
+      let mut cluster_area = 0.;
+      let mut cluster_centroid = Vec3::zero();
+      let mut cluster_normal = Vec3::zero();
+      for triangle in indices[begin * 3..end * 3].chunks_exact(3) {
+        let p0 = positions[triangle[0] as usize];
+        let p1 = positions[triangle[1] as usize];
+        let p2 = positions[triangle[2] as usize];
+
+        let normal = (p1 - p0).cross(p2 - p0);
+        let area = normal.length();
+
+        cluster_centroid += (p0 + p1 + p2) * (area / 3.);
+        cluster_normal += normal;
+        cluster_area += area;
+      }
+
+      let cluster_centroid = cluster_centroid * inverse_or_zeroed(cluster_area);
+      let cluster_normal = cluster_normal * inverse_or_zeroed(cluster_normal.length());
+
+      (cluster_centroid - mesh_centroid).dot(cluster_normal)
+    })
+    .collect()
+}
+
+fn inverse_or_zeroed(v: f32) -> f32 {
+  if v == 0. { 0. } else { 1. / v }
+}
+
+/// Reorder the triangles to reduce the overdraw, the algorithm is same as the meshoptimizer's
+/// optimizeOverdraw(based on "Fast Triangle Reordering for Vertex Locality and Reduced
+/// Overdraw"): the index buffer is split into clusters at the vertex cache boundaries and the
+/// clusters are sorted by the front facing heuristic.
+///
+/// The input should be optimized for vertex cache first. The threshold controls how much the
+/// vertex cache efficiency can be traded for the overdraw, for example 1.05 means the resulting
+/// ACMR should be at most 5% worse than before.
+///
+/// The destination should have the same length as the indices.
+pub fn optimize_overdraw<V: Positioned<Position = Vec3<f32>>>(
+  destination: &mut [u32],
+  indices: &[u32],
+  vertices: &[V],
+  threshold: f32,
+) {
+  assert!(indices.len().is_multiple_of(3));
+  assert_eq!(destination.len(), indices.len());
+
+  if indices.is_empty() {
+    return;
+  }
+
+  let positions: Vec<_> = vertices.iter().map(|v| v.position()).collect();
+
+  let hard_clusters = generate_hard_boundaries(indices, vertices.len());
+  let clusters = generate_soft_boundaries(indices, vertices.len(), &hard_clusters, threshold);
+
+  let sort_data = compute_sort_data(indices, &positions, &clusters);
+
+  // quantize the sort key so the near equal clusters keep the input order, the distribution is
+  // flipped because the high dot product should come first.
+  const SORT_BITS: u32 = 11;
+  let sort_data_max = sort_data.iter().fold(1e-3_f32, |max, v| max.max(v.abs()));
+  let sort_keys: Vec<_> = sort_data
+    .iter()
+    .map(|v| {
+      let key = (0.5 - 0.5 * (v / sort_data_max)).clamp(0., 1.);
+      (key * ((1 << SORT_BITS) - 1) as f32 + 0.5) as u32
+    })
+    .collect();
+
+  let mut sort_order: Vec<_> = (0..clusters.len()).collect();
+  sort_order.sort_by_key(|cluster| sort_keys[*cluster]);
+
+  let face_count = indices.len() / 3;
+  let mut offset = 0;
+  for cluster in sort_order {
+    let begin = clusters[cluster] * 3;
+    let end = clusters
+      .get(cluster + 1)
+      .map(|v| v * 3)
+      .unwrap_or(face_count * 3);
+    destination[offset..offset + end - begin].copy_from_slice(&indices[begin..end]);
+    offset += end - begin;
+  }
+
+  assert_eq!(offset, indices.len());
+}
@@ -0,0 +1,171 @@
+const CACHE_SIZE_MAX: usize = 16;
+const VALENCE_MAX: usize = 8;
+
+/// the score tables are tuned by the meshoptimizer for the modern gpu, see
+/// "Vertex cache optimization for modern gpus"
+struct VertexScoreTable {
+  cache: [f32; 1 + CACHE_SIZE_MAX],
+  live: [f32; 1 + VALENCE_MAX],
+}
+
+const VERTEX_SCORE_TABLE: VertexScoreTable = VertexScoreTable {
+  cache: [
+    0., 0.779, 0.791, 0.789, 0.981, 0.843, 0.726, 0.847, 0.882, 0.867, 0.799, 0.642, 0.613, 0.600,
+    0.568, 0.372, 0.234,
+  ],
+  live: [0., 0.995, 0.713, 0.450, 0.404, 0.059, 0.005, 0.147, 0.006],
+};
+
+/// cache_position is None if the vertex is not in the cache
+fn vertex_score(cache_position: Option<usize>, live_triangles: u32) -> f32 {
+  let live_triangles = (live_triangles as usize).min(VALENCE_MAX);
+  let cache_score = cache_position.map_or(0., |p| VERTEX_SCORE_TABLE.cache[1 + p]);
+  cache_score + VERTEX_SCORE_TABLE.live[live_triangles]
+}
+
+/// the triangles that use each vertex, stored in the compact form
+struct TriangleAdjacency {
+  counts: Vec<u32>,
+  offsets: Vec<u32>,
+  data: Vec<u32>,
+}
+
+impl TriangleAdjacency {
+  fn build(indices: &[u32], vertex_count: usize) -> Self {
+    let mut counts = vec![0_u32; vertex_count];
+    indices.iter().for_each(|i| counts[*i as usize] += 1);
+
+    let mut offset = 0;
+    let offsets: Vec<_> = counts
+      .iter()
+      .map(|count| {
+        let current = offset;
+        offset += count;
+        current
+      })
+      .collect();
+
+    let mut data = vec![0_u32; indices.len()];
+    let mut fill = offsets.clone();
+    for (triangle, [a, b, c]) in indices.iter().copied().array_chunks().enumerate() {
+      for v in [a, b, c] {
+        data[fill[v as usize] as usize] = triangle as u32;
+        fill[v as usize] += 1;
+      }
+    }
+
+    Self {
+      counts,
+      offsets,
+      data,
+    }
+  }
+}
+
+/// Reorder the triangles to improve the post transform vertex cache hit rate, the algorithm is
+/// same as the meshoptimizer's optimizeVertexCache: greedily emit the triangle of the best
+/// score, the score is computed by the vertex cache position and the remaining valence.
+///
+/// The destination should have the same length as the indices.
+pub fn optimize_vertex_cache(destination: &mut [u32], indices: &[u32], vertex_count: usize) {
+  assert!(indices.len().is_multiple_of(3));
+  assert_eq!(destination.len(), indices.len());
+
+  let face_count = indices.len() / 3;
+  if face_count == 0 {
+    return;
+  }
+
+  let mut adjacency = TriangleAdjacency::build(indices, vertex_count);
+  // the live triangles also act as the valid length of the adjacency list of each vertex
+  let mut live_triangles = adjacency.counts.clone();
+  let mut emitted = vec![false; face_count];
+
+  let mut vertex_scores: Vec<_> = live_triangles
+    .iter()
+    .map(|live| vertex_score(None, *live))
+    .collect();
+
+  let mut triangle_scores: Vec<_> = indices
+    .iter()
+    .array_chunks()
+    .map(|[a, b, c]| {
+      vertex_scores[*a as usize] + vertex_scores[*b as usize] + vertex_scores[*c as usize]
+    })
+    .collect();
+
+  // the extra space is used to hold the newly added vertices before the cache trimmed
+  let mut cache = Vec::with_capacity(CACHE_SIZE_MAX + 3);
+  let mut cache_new = Vec::with_capacity(CACHE_SIZE_MAX + 3);
+
+  let mut current_triangle = Some(0);
+  let mut input_cursor = 1;
+  let mut output_triangle = 0;
+
+  while let Some(triangle) = current_triangle {
+    let abc: [u32; 3] = indices[triangle * 3..triangle * 3 + 3].try_into().unwrap();
+    destination[output_triangle * 3..output_triangle * 3 + 3].copy_from_slice(&abc);
+    output_triangle += 1;
+
+    emitted[triangle] = true;
+    triangle_scores[triangle] = 0.;
+
+    // the emitted vertices move to the cache front, the rest keep the order
+    cache_new.clear();
+    cache_new.extend_from_slice(&abc);
+    cache_new.extend(cache.iter().copied().filter(|v| !abc.contains(v)));
+    std::mem::swap(&mut cache, &mut cache_new);
+
+    // remove the emitted triangle from the adjacency
+    for v in abc {
+      let offset = adjacency.offsets[v as usize] as usize;
+      let count = live_triangles[v as usize] as usize;
+      let neighbors = &mut adjacency.data[offset..offset + count];
+      if let Some(position) = neighbors.iter().position(|t| *t as usize == triangle) {
+        neighbors[position] = neighbors[count - 1];
+      }
+      live_triangles[v as usize] -= 1;
+    }
+
+    // update the vertex and triangle scores of the vertices in cache(including the ones just
+    // evicted), and find the next best triangle
+    let mut best_triangle = None;
+    let mut best_score = 0.;
+    for (i, v) in cache.iter().copied().enumerate() {
+      let v = v as usize;
+      if live_triangles[v] == 0 {
+        continue;
+      }
+
+      let cache_position = (i < CACHE_SIZE_MAX).then_some(i);
+      let score = vertex_score(cache_position, live_triangles[v]);
+      let score_diff = score - vertex_scores[v];
+      vertex_scores[v] = score;
+
+      let offset = adjacency.offsets[v] as usize;
+      for t in &adjacency.data[offset..offset + live_triangles[v] as usize] {
+        let t = *t as usize;
+        triangle_scores[t] += score_diff;
+        if best_score < triangle_scores[t] {
+          best_triangle = Some(t);
+          best_score = triangle_scores[t];
+        }
+      }
+    }
+
+    cache.truncate(CACHE_SIZE_MAX);
+
+    // dead end, pick the next triangle in the input order
+    current_triangle = best_triangle.or_else(|| {
+      while input_cursor < face_count {
+        if !emitted[input_cursor] {
+          return Some(input_cursor);
+        }
+        input_cursor += 1;
+      }
+      None
+    });
+  }
+
+  assert_eq!(output_triangle, face_count);
+}
@@ -0,0 +1,63 @@
+pub const UNUSED_VERTEX: u32 = u32::MAX;
+
+/// Generate the vertex remap table that reorders the vertices in the order they are first
+/// referenced by the indices, to improve the vertex fetch memory locality. The unused vertices
+/// are mapped to [UNUSED_VERTEX].
+///
+/// Return the remap table and the referenced vertex count, the remap table can be applied by
+/// [remap_index_buffer] and [remap_vertex_buffer].
+pub fn optimize_vertex_fetch_remap(indices: &[u32], vertex_count: usize) -> (Vec<u32>, usize) {
+  let mut remap = vec![UNUSED_VERTEX; vertex_count];
+  let mut next_vertex = 0;
+
+  for index in indices {
+    let target = &mut remap[*index as usize];
+    if *target == UNUSED_VERTEX {
+      *target = next_vertex;
+      next_vertex += 1;
+    }
+  }
+
+  (remap, next_vertex as usize)
+}
+
+pub fn remap_index_buffer(indices: &mut [u32], remap: &[u32]) {
+  indices.iter_mut().for_each(|i| *i = remap[*i as usize]);
+}
+
+/// the unused vertices(mapped to [UNUSED_VERTEX]) are dropped
+pub fn remap_vertex_buffer<V: Copy>(vertices: &[V], remap: &[u32], unique_count: usize) -> Vec<V> {
+  let mut result: Vec<Option<V>> = vec![None; unique_count];
+  for (vertex, target) in vertices.iter().zip(remap) {
+    if *target != UNUSED_VERTEX {
+      result[*target as usize] = Some(*vertex);
+    }
+  }
+  result.into_iter().map(|v| v.unwrap()).collect()
+}
+
+/// same as [remap_vertex_buffer] but for the untyped vertex data, each vertex has item_byte_size
+pub fn remap_vertex_bytes(
+  vertices: &[u8],
+  item_byte_size: usize,
+  remap: &[u32],
+  unique_count: usize,
+) -> Vec<u8> {
+  let mut result = vec![0; unique_count * item_byte_size];
+  for (vertex, target) in vertices.chunks_exact(item_byte_size).zip(remap) {
+    if *target != UNUSED_VERTEX {
+      let offset = *target as usize * item_byte_size;
+      result[offset..offset + item_byte_size].copy_from_slice(vertex);
+    }
+  }
+  result
+}
+
+/// Reorder the vertices in the order they are first referenced by the indices and rewrite the
+/// indices, the unused vertices are removed. This should be called after the triangle order is
+/// finalized.
+pub fn optimize_vertex_fetch<V: Copy>(indices: &mut [u32], vertices: &[V]) -> Vec<V> {
+  let (remap, unique_count) = optimize_vertex_fetch_remap(indices, vertices.len());
+  remap_index_buffer(indices, &remap);
+  remap_vertex_buffer(vertices, &remap, unique_count)
+}
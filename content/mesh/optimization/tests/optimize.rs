@@ -0,0 +1,162 @@
+use rendiation_algebra::*;
+use rendiation_mesh_core::*;
+use rendiation_mesh_optimization::*;
+
+/// the triangles are shuffled to simulate the badly ordered input
+fn shuffled_grid(size: u32) -> (Vec<Vec3<f32>>, Vec<u32>) {
+  let mut positions = Vec::new();
+  for y in 0..=size {
+    for x in 0..=size {
+      positions.push(Vec3::new(x as f32, y as f32, 0.));
+    }
+  }
+
+  let mut triangles = Vec::new();
+  for y in 0..size {
+    for x in 0..size {
+      let a = y * (size + 1) + x;
+      let b = a + 1;
+      let c = a + size + 1;
+      let d = c + 1;
+      triangles.push([a, b, c]);
+      triangles.push([b, d, c]);
+    }
+  }
+
+  let mut seed = 12345_u32;
+  for i in (1..triangles.len()).rev() {
+    seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+    triangles.swap(i, (seed >> 8) as usize % (i + 1));
+  }
+
+  (positions, triangles.into_iter().flatten().collect())
+}
+
+fn sorted_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+  let mut triangles: Vec<[u32; 3]> = indices
+    .chunks_exact(3)
+    .map(|t| {
+      // rotate to the smallest index first to keep the winding
+      let min = (0..3).min_by_key(|i| t[*i]).unwrap();
+      [t[min], t[(min + 1) % 3], t[(min + 2) % 3]]
+    })
+    .collect();
+  triangles.sort();
+  triangles
+}
+
+#[test]
+fn vertex_cache_optimization() {
+  let (positions, indices) = shuffled_grid(32);
+  let before = analyze_vertex_cache(&indices, positions.len(), 16, 0, 0);
+
+  let mut optimized = vec![0; indices.len()];
+  optimize_vertex_cache(&mut optimized, &indices, positions.len());
+  let after = analyze_vertex_cache(&optimized, positions.len(), 16, 0, 0);
+
+  assert_eq!(sorted_triangles(&indices), sorted_triangles(&optimized));
+  assert!(after.acmr < before.acmr * 0.5);
+  assert!(after.acmr < 1.);
+  assert!(after.atvr >= 1.);
+}
+
+#[test]
+fn overdraw_optimization() {
+  // two overlapped layers, the far one is emitted first
+  let (mut positions, near_indices) = shuffled_grid(8);
+  let layer_size = positions.len() as u32;
+  let far: Vec<_> = positions
+    .iter()
+    .map(|p| *p + Vec3::new(0., 0., -1.))
+    .collect();
+  let far_indices: Vec<_> = near_indices.iter().map(|i| i + layer_size).collect();
+  positions.extend(far);
+
+  let mut input = vec![0; far_indices.len()];
+  optimize_vertex_cache(&mut input, &far_indices, positions.len());
+  let mut near = near_indices.clone();
+  optimize_vertex_cache(&mut near, &near_indices, positions.len());
+  input.extend(near);
+
+  let before = analyze_overdraw(&input, &positions);
+  let mut optimized = vec![0; input.len()];
+  optimize_overdraw(&mut optimized, &input, &positions, 1.05);
+  let after = analyze_overdraw(&optimized, &positions);
+
+  assert_eq!(sorted_triangles(&input), sorted_triangles(&optimized));
+  assert!(after.overdraw < before.overdraw);
+  assert!(after.pixels_covered == before.pixels_covered);
+}
+
+#[test]
+fn vertex_fetch_optimization() {
+  let (mut positions, indices) = shuffled_grid(16);
+  // add some unused vertices
+  positions.push(Vec3::splat(100.));
+
+  let mut optimized_indices = indices.clone();
+  let optimized = optimize_vertex_fetch(&mut optimized_indices, &positions);
+
+  assert_eq!(optimized.len(), positions.len() - 1);
+  for (a, b) in indices.iter().zip(&optimized_indices) {
+    assert_eq!(positions[*a as usize], optimized[*b as usize]);
+  }
+
+  let vertex_size = std::mem::size_of::<Vec3<f32>>();
+  let before = analyze_vertex_fetch(&indices, positions.len(), vertex_size);
+  let after = analyze_vertex_fetch(&optimized_indices, optimized.len(), vertex_size);
+  assert!(after.bytes_fetched <= before.bytes_fetched);
+}
+
+#[test]
+fn attributes_mesh_optimization() {
+  let (positions, indices) = shuffled_grid(8);
+  let uvs: Vec<_> = positions.iter().map(|p| Vec2::new(p.x, p.y)).collect();
+  let indices_u16: Vec<_> = indices.iter().map(|i| *i as u16).collect();
+
+  let mesh = AttributesMesh {
+    attributes: [
+      (
+        AttributeSemantic::Positions,
+        AttributeAccessor::create_owned(positions.clone(), 3 * 4),
+      ),
+      (
+        AttributeSemantic::TexCoords(0),
+        AttributeAccessor::create_owned(uvs, 2 * 4),
+      ),
+    ]
+    .into_iter()
+    .collect(),
+    indices: Some((
+      AttributeIndexFormat::Uint16,
+      AttributeAccessor::create_owned(indices_u16, 2),
+    )),
+    mode: MeshPrimitiveTopology::TriangleList,
+  };
+
+  let optimized = optimize_attributes_mesh(&mesh, &Default::default()).unwrap();
+  let optimized_positions = optimized.get_position_slice();
+  let optimized_uvs = optimized
+    .get_attribute(&AttributeSemantic::TexCoords(0))
+    .unwrap()
+    .visit_slice::<Vec2<f32>>()
+    .unwrap();
+  let (format, optimized_indices) = optimized.indices.as_ref().unwrap();
+  assert_eq!(*format, AttributeIndexFormat::Uint16);
+
+  for t in optimized_indices.visit_slice::<u16>().unwrap() {
+    let p = optimized_positions[*t as usize];
+    assert_eq!(optimized_uvs[*t as usize], Vec2::new(p.x, p.y));
+  }
+
+  let before = analyze_vertex_cache(&indices, positions.len(), 16, 0, 0);
+  let optimized_indices: Vec<_> = optimized_indices
+    .visit_slice::<u16>()
+    .unwrap()
+    .iter()
+    .map(|i| *i as u32)
+    .collect();
+  let after = analyze_vertex_cache(&optimized_indices, positions.len(), 16, 0, 0);
+  assert!(after.acmr < before.acmr);
+  assert_eq!(optimized_indices.len(), indices.len());
+}
@@ -33,7 +33,8 @@ pub fn simplify_sloppy<V: Positioned<Position = Vec3<f32>>>(
   let mut vertex_ids = vec![0_u32; vertices.len()];
 
   // invariant: # of triangles in min_grid <= target_count
-  let mut min_grid = (1. / target_error.max(1e-3)) as u32;
+  // the error is clamped to make sure the grid size is at least 1 even if the error is not limited
+  let mut min_grid = (1. / target_error.clamp(1e-3, 1.)) as u32;
   let mut max_grid = 1025;
 
   let mut min_triangles = 0;
@@ -0,0 +1,60 @@
+use rendiation_algebra::*;
+use rendiation_mesh_simplification::*;
+
+fn uv_sphere(slices: u32, stacks: u32) -> (Vec<Vec3<f32>>, Vec<u32>) {
+  let mut positions = Vec::new();
+  let mut indices = Vec::new();
+  for j in 0..=stacks {
+    let theta = std::f32::consts::PI * j as f32 / stacks as f32;
+    for i in 0..=slices {
+      let phi = 2. * std::f32::consts::PI * i as f32 / slices as f32;
+      positions.push(Vec3::new(
+        theta.sin() * phi.cos(),
+        theta.cos(),
+        theta.sin() * phi.sin(),
+      ));
+    }
+  }
+  for j in 0..stacks {
+    for i in 0..slices {
+      let a = j * (slices + 1) + i;
+      let b = a + 1;
+      let c = a + slices + 1;
+      let d = c + 1;
+      indices.extend_from_slice(&[a, b, c, b, d, c]);
+    }
+  }
+  (positions, indices)
+}
+
+/// the error larger than the normalized mesh extent should not produce an invalid grid size.
+#[test]
+fn sloppy_with_unlimited_error() {
+  let (positions, indices) = uv_sphere(64, 32);
+  let mut dst = vec![0; indices.len()];
+  let lock: Vec<_> = positions.iter().map(|p| p.y > 0.99).collect();
+
+  for target_error in [f32::MAX, 10., 1.] {
+    for (use_absolute_error, vertex_lock) in
+      [(false, None), (true, None), (false, Some(lock.as_slice()))]
+    {
+      let target = (indices.len() / 8) as u32;
+      let result = simplify_sloppy(
+        &mut dst,
+        &indices,
+        &positions,
+        vertex_lock,
+        target,
+        target_error,
+        use_absolute_error,
+      );
+      assert!(result.result_count > 0);
+      assert!(result.result_count <= target as usize);
+      assert!(
+        dst[..result.result_count]
+          .iter()
+          .all(|i| (*i as usize) < positions.len())
+      );
+    }
+  }
+}
@@ -0,0 +1,1280 @@
+//! ASTC decoding, see the ASTC chapter of the Khronos data format spec. Only the 2D blocks are
+//! supported.
+
+/// The decode mode of the ASTC block. The LDR profile outputs rgba8, and decodes the HDR blocks
+/// as the error color. The HDR profile outputs rgba16 float.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AstcProfile {
+  Ldr,
+  LdrSrgb,
+  Hdr,
+}
+
+/// (value count, 3 for trits or 5 for quints or 1, bit count)
+const ISE_RANGES: [(u32, u32, u32); 21] = [
+  (2, 1, 1),
+  (3, 3, 0),
+  (4, 1, 2),
+  (5, 5, 0),
+  (6, 3, 1),
+  (8, 1, 3),
+  (10, 5, 1),
+  (12, 3, 2),
+  (16, 1, 4),
+  (20, 5, 2),
+  (24, 3, 3),
+  (32, 1, 5),
+  (40, 5, 3),
+  (48, 3, 4),
+  (64, 1, 6),
+  (80, 5, 4),
+  (96, 3, 5),
+  (128, 1, 7),
+  (160, 5, 5),
+  (192, 3, 6),
+  (256, 1, 8),
+];
+
+/// the smallest range the color endpoints can use, which is 0..5
+const MIN_COLOR_RANGE: usize = 4;
+const MAX_WEIGHT_COUNT: usize = 64;
+const MAX_COLOR_VALUE_COUNT: usize = 18;
+
+fn bits(v: u128, start: u32, count: u32) -> u32 {
+  ((v >> start) & ((1 << count) - 1)) as u32
+}
+
+fn bit(v: u32, i: u32) -> u32 {
+  (v >> i) & 1
+}
+
+fn ise_bit_count(count: u32, range: usize) -> u32 {
+  let (_, kind, bits) = ISE_RANGES[range];
+  count * bits
+    + match kind {
+      3 => (count * 8).div_ceil(5),
+      5 => (count * 7).div_ceil(3),
+      _ => 0,
+    }
+}
+
+fn decode_trits(t: u32) -> [u32; 5] {
+  let (c, t4, t3) = if (t >> 2) & 7 == 7 {
+    (((t >> 5) & 7) << 2 | (t & 3), 2, 2)
+  } else if (t >> 5) & 3 == 3 {
+    (t & 0x1f, 2, bit(t, 7))
+  } else {
+    (t & 0x1f, bit(t, 7), (t >> 5) & 3)
+  };
+  let (t2, t1, t0) = if c & 3 == 3 {
+    (2, bit(c, 4), bit(c, 3) << 1 | (bit(c, 2) & !bit(c, 3) & 1))
+  } else if (c >> 2) & 3 == 3 {
+    (2, 2, c & 3)
+  } else {
+    (
+      bit(c, 4),
+      (c >> 2) & 3,
+      bit(c, 1) << 1 | (bit(c, 0) & !bit(c, 1) & 1),
+    )
+  };
+  [t0, t1, t2, t3, t4]
+}
+
+fn decode_quints(q: u32) -> [u32; 3] {
+  if (q >> 1) & 3 == 3 && (q >> 5) & 3 == 0 {
+    let not_q0 = !bit(q, 0) & 1;
+    let q2 = bit(q, 0) << 2 | (bit(q, 4) & not_q0) << 1 | (bit(q, 3) & not_q0);
+    return [4, 4, q2];
+  }
+  let (q2, c) = if (q >> 1) & 3 == 3 {
+    (4, ((q >> 3) & 3) << 3 | (!(q >> 5) & 3) << 1 | bit(q, 0))
+  } else {
+    ((q >> 5) & 3, q & 0x1f)
+  };
+  let (q1, q0) = if c & 7 == 5 {
+    (4, (c >> 3) & 3)
+  } else {
+    ((c >> 3) & 3, c & 7)
+  };
+  [q0, q1, q2]
+}
+
+/// decode the integer sequence, the output is the (low bits, trit or quint) pairs. The bits
+/// after the sequence are treated as zero.
+fn decode_ise(data: u128, start: u32, range: usize, output: &mut [(u32, u32)]) {
+  let (_, kind, bit_count) = ISE_RANGES[range];
+  let end = start + ise_bit_count(output.len() as u32, range);
+  let data = if end >= 128 {
+    data
+  } else {
+    data & ((1 << end) - 1)
+  };
+
+  let mut position = start;
+  let mut read = |count: u32| {
+    let v = if position >= 128 {
+      0
+    } else {
+      bits(data, position, count.min(128 - position))
+    };
+    position += count;
+    v
+  };
+
+  match kind {
+    3 => {
+      for chunk in output.chunks_mut(5) {
+        let mut low = [0; 5];
+        let mut t = 0;
+        for (i, (offset, count)) in [(0, 2), (2, 2), (4, 1), (5, 2), (7, 1)].iter().enumerate() {
+          low[i] = read(bit_count);
+          t |= read(*count) << offset;
+        }
+        let trits = decode_trits(t);
+        for (i, v) in chunk.iter_mut().enumerate() {
+          *v = (low[i], trits[i]);
+        }
+      }
+    }
+    5 => {
+      for chunk in output.chunks_mut(3) {
+        let mut low = [0; 3];
+        let mut q = 0;
+        for (i, (offset, count)) in [(0, 3), (3, 2), (5, 2)].iter().enumerate() {
+          low[i] = read(bit_count);
+          q |= read(*count) << offset;
+        }
+        let quints = decode_quints(q);
+        for (i, v) in chunk.iter_mut().enumerate() {
+          *v = (low[i], quints[i]);
+        }
+      }
+    }
+    _ => {
+      for v in output.iter_mut() {
+        *v = (read(bit_count), 0);
+      }
+    }
+  }
+}
+
+/// repeat the bits to the target bit count
+fn replicate(v: u32, from: u32, to: u32) -> u32 {
+  let mut result = 0;
+  let mut count = 0;
+  while count < to {
+    result = (result << from) | v;
+    count += from;
+  }
+  result >> (count - to)
+}
+
+/// the pattern is from the msb to the lsb, the letter b is the bit 1 of the value and so on
+fn bit_pattern(pattern: &[u8], v: u32) -> u32 {
+  pattern.iter().fold(0, |result, p| {
+    let b = if *p == b'0' {
+      0
+    } else {
+      bit(v, (*p - b'a') as u32)
+    };
+    (result << 1) | b
+  })
+}
+
+fn unquantize_color(range: usize, (low, tq): (u32, u32)) -> i32 {
+  let (_, kind, bit_count) = ISE_RANGES[range];
+  if kind == 1 {
+    return replicate(low, bit_count, 8) as i32;
+  }
+  let (pattern, c): (&[u8], u32) = match (kind, bit_count) {
+    (3, 1) => (b"000000000", 204),
+    (5, 1) => (b"000000000", 113),
+    (3, 2) => (b"b000b0bb0", 93),
+    (5, 2) => (b"b0000bb00", 54),
+    (3, 3) => (b"cb000cbcb", 44),
+    (5, 3) => (b"cb0000cbc", 26),
+    (3, 4) => (b"dcb000dcb", 22),
+    (5, 4) => (b"dcb0000dc", 13),
+    (3, 5) => (b"edcb000ed", 11),
+    (5, 5) => (b"edcb0000e", 6),
+    _ => (b"fedcb000f", 5),
+  };
+  let a = if low & 1 != 0 { 0x1ff } else { 0 };
+  let t = (tq * c + bit_pattern(pattern, low)) ^ a;
+  ((a & 0x80) | (t >> 2)) as i32
+}
+
+/// the unquantized weight is in 0..=64
+fn unquantize_weight(range: usize, (low, tq): (u32, u32)) -> u32 {
+  let (_, kind, bit_count) = ISE_RANGES[range];
+  let w = if kind == 1 {
+    replicate(low, bit_count, 6)
+  } else if bit_count == 0 {
+    return if kind == 3 { tq * 32 } else { tq * 16 };
+  } else {
+    let (pattern, c): (&[u8], u32) = match (kind, bit_count) {
+      (3, 1) => (b"0000000", 50),
+      (5, 1) => (b"0000000", 28),
+      (3, 2) => (b"b000b0b", 23),
+      (5, 2) => (b"b0000b0", 13),
+      _ => (b"cb000cb", 11),
+    };
+    let a = if low & 1 != 0 { 0x7f } else { 0 };
+    let t = (tq * c + bit_pattern(pattern, low)) ^ a;
+    (a & 0x20) | (t >> 2)
+  };
+  if w > 32 { w + 1 } else { w }
+}
+
+struct BlockMode {
+  grid_width: u32,
+  grid_height: u32,
+  dual_plane: bool,
+  weight_range: usize,
+}
+
+fn decode_block_mode(mode: u32) -> Option<BlockMode> {
+  let a = (mode >> 5) & 3;
+  let mut dual_plane = bit(mode, 10) == 1;
+  let mut high_precision = bit(mode, 9);
+
+  let (r, (grid_width, grid_height)) = if mode & 3 != 0 {
+    let b = (mode >> 7) & 3;
+    let size = match (mode >> 2) & 3 {
+      0 => (b + 4, a + 2),
+      1 => (b + 8, a + 2),
+      2 => (a + 2, b + 8),
+      _ if bit(mode, 8) == 0 => (a + 2, (b & 1) + 6),
+      _ => ((b & 1) + 2, a + 2),
+    };
+    (bit(mode, 4) | (mode & 3) << 1, size)
+  } else {
+    let size = match (mode >> 7) & 3 {
+      0 => (12, a + 2),
+      1 => (a + 2, 12),
+      2 => {
+        dual_plane = false;
+        high_precision = 0;
+        (a + 6, ((mode >> 9) & 3) + 6)
+      }
+      _ => match a {
+        0 => (6, 10),
+        1 => (10, 6),
+        _ => return None,
+      },
+    };
+    (bit(mode, 4) | ((mode >> 2) & 3) << 1, size)
+  };
+
+  if r < 2 {
+    return None;
+  }
+  Some(BlockMode {
+    grid_width,
+    grid_height,
+    dual_plane,
+    weight_range: (r - 2 + 6 * high_precision) as usize,
+  })
+}
+
+fn hash52(mut p: u32) -> u32 {
+  p ^= p >> 15;
+  p = p.wrapping_sub(p << 17);
+  p = p.wrapping_add(p << 7);
+  p = p.wrapping_add(p << 4);
+  p ^= p >> 5;
+  p = p.wrapping_add(p << 16);
+  p ^= p >> 7;
+  p ^= p >> 3;
+  p ^= p << 6;
+  p ^= p >> 17;
+  p
+}
+
+fn select_partition(seed: u32, x: u32, y: u32, count: u32, small_block: bool) -> usize {
+  let (x, y, z) = if small_block {
+    (x << 1, y << 1, 0)
+  } else {
+    (x, y, 0)
+  };
+  let seed = seed + (count - 1) * 1024;
+  let rnum = hash52(seed);
+
+  let mut s = [
+    rnum,
+    rnum >> 4,
+    rnum >> 8,
+    rnum >> 12,
+    rnum >> 16,
+    rnum >> 20,
+    rnum >> 24,
+    rnum >> 28,
+    rnum >> 18,
+    rnum >> 22,
+    rnum >> 26,
+    rnum.rotate_left(2),
+  ]
+  .map(|v| (v & 0xf) * (v & 0xf));
+
+  let (sh1, sh2) = if seed & 1 != 0 {
+    (
+      if seed & 2 != 0 { 4 } else { 5 },
+      if count == 3 { 6 } else { 5 },
+    )
+  } else {
+    (
+      if count == 3 { 6 } else { 5 },
+      if seed & 2 != 0 { 4 } else { 5 },
+    )
+  };
+  let sh3 = if seed & 0x10 != 0 { sh1 } else { sh2 };
+  for (i, v) in s.iter_mut().enumerate() {
+    *v >>= match i {
+      0..8 if i % 2 == 0 => sh1,
+      0..8 => sh2,
+      _ => sh3,
+    };
+  }
+
+  let a = (s[0] * x + s[1] * y + s[10] * z + (rnum >> 14)) & 0x3f;
+  let b = (s[2] * x + s[3] * y + s[11] * z + (rnum >> 10)) & 0x3f;
+  let c = if count < 3 {
+    0
+  } else {
+    (s[4] * x + s[5] * y + s[8] * z + (rnum >> 6)) & 0x3f
+  };
+  let d = if count < 4 {
+    0
+  } else {
+    (s[6] * x + s[7] * y + s[9] * z + (rnum >> 2)) & 0x3f
+  };
+
+  if a >= b && a >= c && a >= d {
+    0
+  } else if b >= c && b >= d {
+    1
+  } else if c >= d {
+    2
+  } else {
+    3
+  }
+}
+
+/// The endpoint pair, the LDR channel is in 0..=255 and the HDR channel is in the 12 bits
+/// logarithmic representation.
+#[derive(Clone, Copy, Default)]
+struct Endpoints {
+  e0: [i32; 4],
+  e1: [i32; 4],
+  rgb_hdr: bool,
+  alpha_hdr: bool,
+}
+
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+  let b = (b >> 1) | (a & 0x80);
+  let a = (a >> 1) & 0x3f;
+  let a = if a & 0x20 != 0 { a - 0x40 } else { a };
+  (a, b)
+}
+
+fn blue_contract([r, g, b, a]: [i32; 4]) -> [i32; 4] {
+  [(r + b) >> 1, (g + b) >> 1, b, a]
+}
+
+fn clamp_unorm8(v: [i32; 4]) -> [i32; 4] {
+  v.map(|v| v.clamp(0, 255))
+}
+
+fn ldr(e0: [i32; 4], e1: [i32; 4]) -> Endpoints {
+  Endpoints {
+    e0: clamp_unorm8(e0),
+    e1: clamp_unorm8(e1),
+    rgb_hdr: false,
+    alpha_hdr: false,
+  }
+}
+
+fn decode_endpoints(cem: u32, v: &[i32]) -> Endpoints {
+  match cem {
+    0 => ldr([v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]),
+    1 => {
+      let l0 = (v[0] >> 2) | (v[1] & 0xc0);
+      let l1 = (l0 + (v[1] & 0x3f)).min(255);
+      ldr([l0, l0, l0, 255], [l1, l1, l1, 255])
+    }
+    4 => ldr([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+    5 => {
+      let (v1, v0) = bit_transfer_signed(v[1], v[0]);
+      let (v3, v2) = bit_transfer_signed(v[3], v[2]);
+      let l = v0 + v1;
+      ldr([v0, v0, v0, v2], [l, l, l, v2 + v3])
+    }
+    6 => ldr(
+      [
+        (v[0] * v[3]) >> 8,
+        (v[1] * v[3]) >> 8,
+        (v[2] * v[3]) >> 8,
+        255,
+      ],
+      [v[0], v[1], v[2], 255],
+    ),
+    8 | 12 => {
+      let (a0, a1) = if cem == 12 { (v[6], v[7]) } else { (255, 255) };
+      let e0 = [v[0], v[2], v[4], a0];
+      let e1 = [v[1], v[3], v[5], a1];
+      if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+        ldr(e0, e1)
+      } else {
+        ldr(blue_contract(e1), blue_contract(e0))
+      }
+    }
+    9 | 13 => {
+      let (v1, v0) = bit_transfer_signed(v[1], v[0]);
+      let (v3, v2) = bit_transfer_signed(v[3], v[2]);
+      let (v5, v4) = bit_transfer_signed(v[5], v[4]);
+      let (v7, v6) = if cem == 13 {
+        bit_transfer_signed(v[7], v[6])
+      } else {
+        (0, 255)
+      };
+      let e0 = [v0, v2, v4, v6];
+      let e1 = [v0 + v1, v2 + v3, v4 + v5, v6 + v7];
+      if v1 + v3 + v5 >= 0 {
+        ldr(e0, e1)
+      } else {
+        ldr(blue_contract(e1), blue_contract(e0))
+      }
+    }
+    10 => ldr(
+      [
+        (v[0] * v[3]) >> 8,
+        (v[1] * v[3]) >> 8,
+        (v[2] * v[3]) >> 8,
+        v[4],
+      ],
+      [v[0], v[1], v[2], v[5]],
+    ),
+    2 => {
+      let (y0, y1) = if v[1] >= v[0] {
+        (v[0] << 4, v[1] << 4)
+      } else {
+        ((v[1] << 4) + 8, (v[0] << 4) - 8)
+      };
+      hdr_luminance(y0, y1)
+    }
+    3 => {
+      let (y0, d) = if v[0] & 0x80 != 0 {
+        (
+          ((v[1] & 0xe0) << 4) | ((v[0] & 0x7f) << 2),
+          (v[1] & 0x1f) << 2,
+        )
+      } else {
+        (
+          ((v[1] & 0xf0) << 4) | ((v[0] & 0x7f) << 1),
+          (v[1] & 0x0f) << 1,
+        )
+      };
+      hdr_luminance(y0, (y0 + d).min(0xfff))
+    }
+    7 => {
+      let (e0, e1) = decode_hdr_rgb_base_scale(v);
+      Endpoints {
+        e0,
+        e1,
+        rgb_hdr: true,
+        alpha_hdr: true,
+      }
+    }
+    11 => {
+      let (e0, e1) = decode_hdr_rgb(v);
+      Endpoints {
+        e0,
+        e1,
+        rgb_hdr: true,
+        alpha_hdr: true,
+      }
+    }
+    14 => {
+      let (mut e0, mut e1) = decode_hdr_rgb(v);
+      e0[3] = v[6];
+      e1[3] = v[7];
+      Endpoints {
+        e0,
+        e1,
+        rgb_hdr: true,
+        alpha_hdr: false,
+      }
+    }
+    _ => {
+      let (mut e0, mut e1) = decode_hdr_rgb(v);
+      (e0[3], e1[3]) = decode_hdr_alpha(v[6], v[7]);
+      Endpoints {
+        e0,
+        e1,
+        rgb_hdr: true,
+        alpha_hdr: true,
+      }
+    }
+  }
+}
+
+/// the alpha of the HDR modes without alpha is 1.0
+const HDR_ONE: i32 = 0x780;
+
+fn hdr_luminance(y0: i32, y1: i32) -> Endpoints {
+  Endpoints {
+    e0: [y0, y0, y0, HDR_ONE],
+    e1: [y1, y1, y1, HDR_ONE],
+    rgb_hdr: true,
+    alpha_hdr: true,
+  }
+}
+
+fn decode_hdr_rgb_base_scale(v: &[i32]) -> ([i32; 4], [i32; 4]) {
+  let mode_value = ((v[0] & 0xc0) >> 6) | ((v[1] & 0x80) >> 5) | ((v[2] & 0x80) >> 4);
+  let (major, mode) = if mode_value & 0xc != 0xc {
+    (mode_value >> 2, mode_value & 3)
+  } else if mode_value != 0xf {
+    (mode_value & 3, 4)
+  } else {
+    (0, 5)
+  };
+
+  let mut red = v[0] & 0x3f;
+  let mut green = v[1] & 0x1f;
+  let mut blue = v[2] & 0x1f;
+  let mut scale = v[3] & 0x1f;
+
+  let x0 = (v[1] >> 6) & 1;
+  let x1 = (v[1] >> 5) & 1;
+  let x2 = (v[2] >> 6) & 1;
+  let x3 = (v[2] >> 5) & 1;
+  let x4 = (v[3] >> 7) & 1;
+  let x5 = (v[3] >> 6) & 1;
+  let x6 = (v[3] >> 5) & 1;
+
+  let one_hot = 1 << mode;
+  if one_hot & 0x30 != 0 {
+    green |= x0 << 6;
+    blue |= x2 << 6;
+  }
+  if one_hot & 0x3a != 0 {
+    green |= x1 << 5;
+    blue |= x3 << 5;
+  }
+  if one_hot & 0x3d != 0 {
+    scale |= x6 << 5;
+  }
+  if one_hot & 0x2d != 0 {
+    scale |= x5 << 6;
+  }
+  if one_hot & 0x04 != 0 {
+    scale |= x4 << 7;
+  }
+  if one_hot & 0x3b != 0 {
+    red |= x4 << 6;
+  }
+  if one_hot & 0x04 != 0 {
+    red |= x3 << 6;
+  }
+  if one_hot & 0x10 != 0 {
+    red |= x5 << 7;
+  }
+  if one_hot & 0x0f != 0 {
+    red |= x2 << 7;
+  }
+  if one_hot & 0x05 != 0 {
+    red |= x1 << 8;
+  }
+  if one_hot & 0x0a != 0 {
+    red |= x0 << 8;
+  }
+  if one_hot & 0x05 != 0 {
+    red |= x0 << 9;
+  }
+  if one_hot & 0x02 != 0 {
+    red |= x6 << 9;
+  }
+  if one_hot & 0x01 != 0 {
+    red |= x3 << 10;
+  }
+  if one_hot & 0x02 != 0 {
+    red |= x5 << 10;
+  }
+
+  let shift = [1, 1, 2, 3, 4, 5][mode as usize];
+  red <<= shift;
+  green <<= shift;
+  blue <<= shift;
+  scale <<= shift;
+  if mode != 5 {
+    green = red - green;
+    blue = red - blue;
+  }
+
+  let mut e1 = [red, green, blue, HDR_ONE];
+  if major != 0 {
+    e1.swap(0, major as usize);
+  }
+  let e0 = [e1[0] - scale, e1[1] - scale, e1[2] - scale, HDR_ONE];
+  (e0.map(|v| v.max(0)), e1.map(|v| v.max(0)))
+}
+
+fn decode_hdr_rgb(v: &[i32]) -> ([i32; 4], [i32; 4]) {
+  let mode_value = ((v[1] & 0x80) >> 7) | ((v[2] & 0x80) >> 6) | ((v[3] & 0x80) >> 5);
+  let major = ((v[4] & 0x80) >> 7) | ((v[5] & 0x80) >> 6);
+  if major == 3 {
+    return (
+      [v[0] << 4, v[2] << 4, (v[4] & 0x7f) << 5, HDR_ONE],
+      [v[1] << 4, v[3] << 4, (v[5] & 0x7f) << 5, HDR_ONE],
+    );
+  }
+
+  let mut a = v[0] | ((v[1] & 0x40) << 2);
+  let mut b0 = v[2] & 0x3f;
+  let mut b1 = v[3] & 0x3f;
+  let mut c = v[1] & 0x3f;
+  let mut d0 = v[4] & 0x1f;
+  let mut d1 = v[5] & 0x1f;
+
+  let x0 = (v[2] >> 6) & 1;
+  let x1 = (v[3] >> 6) & 1;
+  let x2 = (v[4] >> 6) & 1;
+  let x3 = (v[5] >> 6) & 1;
+  let x4 = (v[4] >> 5) & 1;
+  let x5 = (v[5] >> 5) & 1;
+
+  let one_hot = 1 << mode_value;
+  if one_hot & 0xa4 != 0 {
+    a |= x0 << 9;
+  }
+  if one_hot & 0x8 != 0 {
+    a |= x2 << 9;
+  }
+  if one_hot & 0x50 != 0 {
+    a |= x4 << 9;
+    a |= x5 << 10;
+  }
+  if one_hot & 0xa0 != 0 {
+    a |= x1 << 10;
+  }
+  if one_hot & 0xc0 != 0 {
+    a |= x2 << 11;
+  }
+  if one_hot & 0x4 != 0 {
+    c |= x1 << 6;
+  }
+  if one_hot & 0xe8 != 0 {
+    c |= x3 << 6;
+  }
+  if one_hot & 0x20 != 0 {
+    c |= x2 << 7;
+  }
+  if one_hot & 0x5b != 0 {
+    b0 |= x0 << 6;
+    b1 |= x1 << 6;
+  }
+  if one_hot & 0x12 != 0 {
+    b0 |= x2 << 7;
+    b1 |= x3 << 7;
+  }
+  if one_hot & 0xaf != 0 {
+    d0 |= x4 << 5;
+    d1 |= x5 << 5;
+  }
+  if one_hot & 0x5 != 0 {
+    d0 |= x2 << 6;
+    d1 |= x3 << 6;
+  }
+
+  // sign extend the d values
+  let d_bits = [7, 6, 7, 6, 5, 6, 5, 6][mode_value as usize];
+  let d0 = (d0 << (32 - d_bits)) >> (32 - d_bits);
+  let d1 = (d1 << (32 - d_bits)) >> (32 - d_bits);
+
+  let shift = (mode_value >> 1) ^ 3;
+  let [a, b0, b1, c, d0, d1] = [a, b0, b1, c, d0, d1].map(|v| v << shift);
+
+  let mut e0 = [a - c, a - b0 - c - d0, a - b1 - c - d1, HDR_ONE];
+  let mut e1 = [a, a - b0, a - b1, HDR_ONE];
+  if major != 0 {
+    e0.swap(0, major as usize);
+    e1.swap(0, major as usize);
+  }
+  (e0.map(|v| v.clamp(0, 0xfff)), e1.map(|v| v.clamp(0, 0xfff)))
+}
+
+fn decode_hdr_alpha(v6: i32, v7: i32) -> (i32, i32) {
+  let selector = ((v6 >> 7) & 1) | ((v7 >> 6) & 2);
+  let mut v6 = v6 & 0x7f;
+  let mut v7 = v7 & 0x7f;
+  if selector == 3 {
+    return (v6 << 5, v7 << 5);
+  }
+  v6 |= (v7 << (selector + 1)) & 0x780;
+  v7 &= 0x3f >> selector;
+  v7 ^= 32 >> selector;
+  v7 -= 32 >> selector;
+  v6 <<= 4 - selector;
+  v7 <<= 4 - selector;
+  (v6, (v6 + v7).clamp(0, 0xfff))
+}
+
+/// convert the 16 bits logarithmic value to the fp16
+fn lns_to_f16(c: u32) -> u16 {
+  let e = (c >> 11) & 0x1f;
+  let m = c & 0x7ff;
+  let mt = if m < 512 {
+    3 * m
+  } else if m < 1536 {
+    4 * m - 512
+  } else {
+    5 * m - 2048
+  };
+  ((e << 10) + (mt >> 3)).min(0x7bff) as u16
+}
+
+fn unorm16_to_f16(c: u32) -> u16 {
+  half::f16::from_f32(c as f32 / 65535.).to_bits()
+}
+
+fn write_error_color(profile: AstcProfile, texels: &mut [[u8; 8]]) {
+  let one = half::f16::ONE.to_bits().to_le_bytes();
+  for t in texels.iter_mut() {
+    match profile {
+      AstcProfile::Hdr => *t = [one[0], one[1], 0, 0, one[0], one[1], one[0], one[1]],
+      _ => t[..4].copy_from_slice(&[255, 0, 255, 255]),
+    }
+  }
+}
+
+fn write_texel(profile: AstcProfile, texel: &mut [u8; 8], color: [u32; 4], hdr: [bool; 4]) {
+  for (c, (v, hdr)) in color.into_iter().zip(hdr).enumerate() {
+    match profile {
+      AstcProfile::Hdr => {
+        let v = if hdr {
+          lns_to_f16(v)
+        } else {
+          unorm16_to_f16(v)
+        };
+        texel[c * 2..c * 2 + 2].copy_from_slice(&v.to_le_bytes());
+      }
+      _ => texel[c] = (v >> 8) as u8,
+    }
+  }
+}
+
+/// Decode one ASTC block to the texels in row major order, the texel is rgba8 in the LDR
+/// profile and rgba16 float in the HDR profile. The block that is invalid, or uses the HDR
+/// endpoints in the LDR profile, is decoded as the error color magenta.
+pub fn decode_astc(
+  block: &[u8],
+  (block_width, block_height): (u32, u32),
+  profile: AstcProfile,
+  texels: &mut [[u8; 8]],
+) {
+  let data = u128::from_le_bytes(block[..16].try_into().unwrap());
+  let texels = &mut texels[..(block_width * block_height) as usize];
+  if !decode_astc_impl(data, (block_width, block_height), profile, texels) {
+    write_error_color(profile, texels);
+  }
+}
+
+fn decode_astc_impl(
+  data: u128,
+  (block_width, block_height): (u32, u32),
+  profile: AstcProfile,
+  texels: &mut [[u8; 8]],
+) -> bool {
+  let mode = bits(data, 0, 11);
+  if mode & 0x1ff == 0x1fc {
+    return decode_void_extent(data, profile, texels);
+  }
+
+  let Some(mode) = decode_block_mode(mode) else {
+    return false;
+  };
+  let plane_count = if mode.dual_plane { 2 } else { 1 };
+  let weight_count = (mode.grid_width * mode.grid_height * plane_count) as usize;
+  if mode.grid_width > block_width
+    || mode.grid_height > block_height
+    || weight_count > MAX_WEIGHT_COUNT
+  {
+    return false;
+  }
+  let weight_bits = ise_bit_count(weight_count as u32, mode.weight_range);
+  if !(24..=96).contains(&weight_bits) {
+    return false;
+  }
+
+  let partition_count = bits(data, 11, 2) + 1;
+  if mode.dual_plane && partition_count == 4 {
+    return false;
+  }
+
+  // the color endpoint modes
+  let mut cem = [0; 4];
+  let mut partition_seed = 0;
+  let (config_start, extra_cem_bits) = if partition_count == 1 {
+    cem[0] = bits(data, 13, 4);
+    (17, 0)
+  } else {
+    partition_seed = bits(data, 13, 10);
+    let field = bits(data, 23, 6);
+    if field & 3 == 0 {
+      cem = [field >> 2; 4];
+      (29, 0)
+    } else {
+      let extra = 3 * partition_count - 4;
+      let v = (field >> 2) | (bits(data, 128 - weight_bits - extra, extra) << 4);
+      let base_class = (field & 3) - 1;
+      for (i, cem) in cem.iter_mut().take(partition_count as usize).enumerate() {
+        let class = base_class + bit(v, i as u32);
+        let m = (v >> (partition_count + 2 * i as u32)) & 3;
+        *cem = (class << 2) | m;
+      }
+      (29, extra)
+    }
+  };
+  let cem = &cem[..partition_count as usize];
+
+  let below_weight = 128 - weight_bits - extra_cem_bits;
+  let (color_end, ccs) = if mode.dual_plane {
+    (below_weight - 2, bits(data, below_weight - 2, 2) as usize)
+  } else {
+    (below_weight, usize::MAX)
+  };
+  let Some(color_bits) = color_end.checked_sub(config_start) else {
+    return false;
+  };
+
+  let color_value_count: usize = cem.iter().map(|m| ((m >> 2) as usize + 1) * 2).sum();
+  if color_value_count > MAX_COLOR_VALUE_COUNT {
+    return false;
+  }
+  let Some(color_range) = (MIN_COLOR_RANGE..ISE_RANGES.len())
+    .rev()
+    .find(|r| ise_bit_count(color_value_count as u32, *r) <= color_bits)
+  else {
+    return false;
+  };
+
+  let mut color_values = [(0, 0); MAX_COLOR_VALUE_COUNT];
+  let color_values = &mut color_values[..color_value_count];
+  decode_ise(data, config_start, color_range, color_values);
+  let mut endpoints = [Endpoints::default(); 4];
+  let mut offset = 0;
+  for (endpoints, cem) in endpoints.iter_mut().zip(cem) {
+    let count = ((cem >> 2) as usize + 1) * 2;
+    let mut v = [0; 8];
+    for (v, c) in v.iter_mut().zip(&color_values[offset..offset + count]) {
+      *v = unquantize_color(color_range, *c);
+    }
+    *endpoints = decode_endpoints(*cem, &v);
+    offset += count;
+  }
+  let endpoints = &endpoints[..cem.len()];
+  if profile != AstcProfile::Hdr && endpoints.iter().any(|e| e.rgb_hdr || e.alpha_hdr) {
+    return false;
+  }
+
+  // the weights are stored from the top of the block in the reversed bit order
+  let mut weights = [(0, 0); MAX_WEIGHT_COUNT];
+  decode_ise(
+    data.reverse_bits(),
+    0,
+    mode.weight_range,
+    &mut weights[..weight_count],
+  );
+  let weights = weights.map(|w| unquantize_weight(mode.weight_range, w));
+
+  let small_block = block_width * block_height < 31;
+  for y in 0..block_height {
+    for x in 0..block_width {
+      let partition = if partition_count == 1 {
+        0
+      } else {
+        select_partition(partition_seed, x, y, partition_count, small_block)
+      };
+      let e = endpoints[partition];
+
+      let mut plane_weights = [0; 2];
+      for (plane, w) in plane_weights
+        .iter_mut()
+        .enumerate()
+        .take(plane_count as usize)
+      {
+        *w = infill_weight(
+          &weights,
+          (mode.grid_width, mode.grid_height),
+          (block_width, block_height),
+          (x, y),
+          plane,
+          plane_count as usize,
+        );
+      }
+
+      let hdr = [e.rgb_hdr, e.rgb_hdr, e.rgb_hdr, e.alpha_hdr];
+      let color = std::array::from_fn(|c| {
+        let expand = |v: i32| {
+          let v = v as u32;
+          if hdr[c] {
+            v << 4
+          } else if profile == AstcProfile::LdrSrgb {
+            (v << 8) | 0x80
+          } else {
+            (v << 8) | v
+          }
+        };
+        let w = if c == ccs {
+          plane_weights[1]
+        } else {
+          plane_weights[0]
+        };
+        (expand(e.e0[c]) * (64 - w) + expand(e.e1[c]) * w + 32) >> 6
+      });
+      write_texel(
+        profile,
+        &mut texels[(y * block_width + x) as usize],
+        color,
+        hdr,
+      );
+    }
+  }
+  true
+}
+
+/// bilinear infill the weight grid to the texel
+fn infill_weight(
+  weights: &[u32],
+  (grid_width, grid_height): (u32, u32),
+  (block_width, block_height): (u32, u32),
+  (x, y): (u32, u32),
+  plane: usize,
+  plane_count: usize,
+) -> u32 {
+  let ds = (1024 + block_width / 2) / (block_width - 1);
+  let dt = (1024 + block_height / 2) / (block_height - 1);
+  let gs = (ds * x * (grid_width - 1) + 32) >> 6;
+  let gt = (dt * y * (grid_height - 1) + 32) >> 6;
+  let (js, fs) = (gs >> 4, gs & 0xf);
+  let (jt, ft) = (gt >> 4, gt & 0xf);
+
+  let w11 = (fs * ft + 8) >> 4;
+  let w10 = ft - w11;
+  let w01 = fs - w11;
+  let w00 = 16 - fs - ft + w11;
+
+  let weight = |s: u32, t: u32| {
+    if s < grid_width && t < grid_height {
+      weights[(t * grid_width + s) as usize * plane_count + plane]
+    } else {
+      0
+    }
+  };
+  (weight(js, jt) * w00
+    + weight(js + 1, jt) * w01
+    + weight(js, jt + 1) * w10
+    + weight(js + 1, jt + 1) * w11
+    + 8)
+    >> 4
+}
+
+fn decode_void_extent(data: u128, profile: AstcProfile, texels: &mut [[u8; 8]]) -> bool {
+  let is_hdr = bit(bits(data, 0, 12), 9) == 1;
+  if bits(data, 10, 2) != 3 {
+    return false;
+  }
+  let coords = [12, 25, 38, 51].map(|start| bits(data, start, 13));
+  let no_extent = coords.iter().all(|c| *c == 0x1fff);
+  if !no_extent && (coords[0] >= coords[1] || coords[2] >= coords[3]) {
+    return false;
+  }
+  if is_hdr && profile != AstcProfile::Hdr {
+    return false;
+  }
+
+  let color = [64, 80, 96, 112].map(|start| bits(data, start, 16));
+  for texel in texels.iter_mut() {
+    match profile {
+      AstcProfile::Hdr => {
+        for (c, v) in color.iter().enumerate() {
+          let v = if is_hdr {
+            *v as u16
+          } else {
+            unorm16_to_f16(*v)
+          };
+          texel[c * 2..c * 2 + 2].copy_from_slice(&v.to_le_bytes());
+        }
+      }
+      _ => write_texel(profile, texel, color, [false; 4]),
+    }
+  }
+  true
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// write the bits in the same order of the decoder
+  struct BitWriter {
+    bits: u128,
+    offset: u32,
+  }
+
+  impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+      self.bits |= (value as u128 & ((1 << count) - 1)) << self.offset;
+      self.offset += count;
+    }
+  }
+
+  fn decode_ldr(block: u128, size: (u32, u32)) -> Vec<[u8; 4]> {
+    let mut texels = [[0; 8]; 144];
+    decode_astc(&block.to_le_bytes(), size, AstcProfile::Ldr, &mut texels);
+    texels[..(size.0 * size.1) as usize]
+      .iter()
+      .map(|t| [t[0], t[1], t[2], t[3]])
+      .collect()
+  }
+
+  #[test]
+  fn test_astc_void_extent() {
+    let mut w = BitWriter { bits: 0, offset: 0 };
+    w.write(0xdfc, 12);
+    w.write(u32::MAX, 26);
+    w.write(u32::MAX, 26);
+    for c in [0xffff, 0x8000, 0, 0xffff] {
+      w.write(c, 16);
+    }
+    let texels = decode_ldr(w.bits, (6, 6));
+    assert!(texels.iter().all(|t| *t == [255, 128, 0, 255]));
+
+    let mut texels = [[0; 8]; 16];
+    decode_astc(&w.bits.to_le_bytes(), (4, 4), AstcProfile::Hdr, &mut texels);
+    let red = half::f16::from_le_bytes([texels[0][0], texels[0][1]]);
+    assert_eq!(red, half::f16::ONE);
+
+    // the hdr void extent is an error in the ldr profile
+    let hdr = w.bits | (1 << 9);
+    let texels = decode_ldr(hdr, (4, 4));
+    assert!(texels.iter().all(|t| *t == [255, 0, 255, 255]));
+  }
+
+  /// 4x4 single partition block with the ldr rgb direct endpoints, the 4x4 weight grid uses
+  /// the 0..=3 range, the unlisted weights are zero.
+  fn rgb_direct_block(weights: &[(usize, u32)], endpoints: [u32; 6]) -> u128 {
+    let mut w = BitWriter { bits: 0, offset: 0 };
+    // R = 4 is stored in the bits 4, 1, 0, the grid is (B + 4)x(A + 2) with A = 2, B = 0
+    w.write(0b10 | 2 << 5, 11);
+    // single partition, cem 8
+    w.write(0, 2);
+    w.write(8, 4);
+    for e in endpoints {
+      w.write(e, 8);
+    }
+    let mut bits = w.bits;
+    // the weights are stored reversed from the top of the block
+    for (i, weight) in weights {
+      let v = (weight & 1) << 1 | (weight >> 1);
+      bits |= (v as u128) << (126 - i * 2);
+    }
+    bits
+  }
+
+  #[test]
+  // the digits are grouped by the block mode fields
+  #[allow(clippy::unusual_byte_groupings)]
+  fn test_astc_block_mode() {
+    // 12x(A+2) layout with the bits 1:0 = 00
+    let mode = decode_block_mode(0b0_0_00_01_0_01_00).unwrap();
+    assert_eq!((mode.grid_width, mode.grid_height), (12, 3));
+    assert_eq!(mode.weight_range, 0);
+    // 6x10 layout with the high precision and the dual plane
+    let mode = decode_block_mode(0b1_1_11_00_1_01_00).unwrap();
+    assert_eq!((mode.grid_width, mode.grid_height), (6, 10));
+    assert!(mode.dual_plane);
+    assert_eq!(mode.weight_range, 3 - 2 + 6);
+    // reserved
+    assert!(decode_block_mode(0b0_0_11_10_0_00_00).is_none());
+    assert!(decode_block_mode(0b0_0_00_00_0_00_00).is_none());
+  }
+
+  #[test]
+  fn test_astc_ise() {
+    // the trits of 0..=2 with the 5 values packed in 8 bits
+    for t in 0..243_u32 {
+      let expected = [t % 3, t / 3 % 3, t / 9 % 3, t / 27 % 3, t / 81 % 3];
+      let found = (0..256).find(|packed| decode_trits(*packed) == expected);
+      assert!(found.is_some(), "trits {expected:?} are not encodable");
+    }
+    for q in 0..125_u32 {
+      let expected = [q % 5, q / 5 % 5, q / 25 % 5];
+      let found = (0..128).find(|packed| decode_quints(*packed) == expected);
+      assert!(found.is_some(), "quints {expected:?} are not encodable");
+    }
+    assert_eq!(ise_bit_count(5, 1), 8);
+    assert_eq!(ise_bit_count(3, 3), 7);
+    assert_eq!(ise_bit_count(16, 2), 32);
+  }
+
+  fn ise_value(range: usize, v: u32) -> (u32, u32) {
+    let (_, _, bit_count) = ISE_RANGES[range];
+    (v & ((1 << bit_count) - 1), v >> bit_count)
+  }
+
+  #[test]
+  fn test_astc_unquantize() {
+    let colors = |range: usize| -> Vec<i32> {
+      (0..ISE_RANGES[range].0)
+        .map(|v| unquantize_color(range, ise_value(range, v)))
+        .collect()
+    };
+    assert_eq!(colors(4), [0, 255, 51, 204, 102, 153]);
+    assert_eq!(
+      colors(7),
+      [0, 255, 69, 186, 23, 232, 92, 163, 46, 209, 116, 139]
+    );
+    let weights = |range: usize| -> Vec<u32> {
+      (0..ISE_RANGES[range].0)
+        .map(|v| unquantize_weight(range, ise_value(range, v)))
+        .collect()
+    };
+    assert_eq!(weights(1), [0, 32, 64]);
+    assert_eq!(weights(4), [0, 64, 12, 52, 25, 39]);
+
+    // the trit and quint ranges place the max value at 1, and the others at the end
+    for (range, (count, kind, bit_count)) in ISE_RANGES.into_iter().enumerate() {
+      let max = match (kind, bit_count) {
+        (1, _) => count - 1,
+        (_, 0) => count - 1,
+        _ => 1,
+      };
+      if range >= MIN_COLOR_RANGE {
+        assert_eq!(unquantize_color(range, ise_value(range, 0)), 0);
+        assert_eq!(unquantize_color(range, ise_value(range, max)), 255);
+      }
+      if range < 12 {
+        assert_eq!(unquantize_weight(range, ise_value(range, 0)), 0);
+        assert_eq!(unquantize_weight(range, ise_value(range, max)), 64);
+      }
+    }
+  }
+
+  #[test]
+  fn test_astc_rgb_direct() {
+    // the weights of the corners are 0, 1, 2, 3, which are unquantized to 0, 21, 43, 64
+    let block = rgb_direct_block(&[(3, 1), (12, 2), (15, 3)], [0, 255, 0, 255, 0, 255]);
+    let texels = decode_ldr(block, (4, 4));
+    assert_eq!(texels[0], [0, 0, 0, 255]);
+    assert_eq!(texels[3], [84, 84, 84, 255]);
+    assert_eq!(texels[12], [171, 171, 171, 255]);
+    assert_eq!(texels[15], [255, 255, 255, 255]);
+
+    // blue contract and swap the endpoints if the sum of the first endpoint is larger
+    let block = rgb_direct_block(&[(15, 3)], [200, 0, 100, 0, 50, 0]);
+    let texels = decode_ldr(block, (4, 4));
+    assert_eq!(texels[0], [0, 0, 0, 255]);
+    assert_eq!(texels[15], [125, 75, 50, 255]);
+  }
+
+  #[test]
+  fn test_astc_dual_plane() {
+    let mut w = BitWriter { bits: 0, offset: 0 };
+    // R = 2 is stored in the bits 4, 1, 0, the 4x4 grid with the dual plane
+    w.write(0b01 | 2 << 5 | 1 << 10, 11);
+    // single partition, cem 12
+    w.write(0, 2);
+    w.write(12, 4);
+    for e in [10, 20, 30, 40, 50, 60, 70, 80] {
+      w.write(e, 8);
+    }
+    let mut bits = w.bits;
+    // the alpha uses the second plane
+    bits |= 3 << 94;
+    // the weights of the two planes are interleaved, the texel 0 only set the second plane
+    // weight, the texel 1 set both.
+    bits |= 1 << (127 - 1);
+    bits |= 1 << (127 - 2);
+    bits |= 1 << (127 - 3);
+
+    let texels = decode_ldr(bits, (4, 4));
+    assert_eq!(texels[0], [10, 30, 50, 80]);
+    assert_eq!(texels[1], [20, 40, 60, 80]);
+    assert_eq!(texels[2], [10, 30, 50, 70]);
+  }
+
+  #[test]
+  fn test_astc_two_partitions() {
+    let seed = 3;
+    let mut w = BitWriter { bits: 0, offset: 0 };
+    w.write(0b10 | 2 << 5, 11);
+    w.write(1, 2);
+    w.write(seed, 10);
+    // the shared cem 8
+    w.write(8 << 2, 6);
+    // 12 values in the 0..40 range with 3 bits and the zero quints, 1 is unquantized to 255
+    let values = [0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0];
+    for chunk in values.chunks(3) {
+      for (v, quint_bits) in chunk.iter().zip([3, 2, 2]) {
+        w.write(*v, 3);
+        w.write(0, quint_bits);
+      }
+    }
+    // all weights are 3, which selects the second endpoint
+    let bits = w.bits | (u32::MAX as u128) << 96;
+
+    let texels = decode_ldr(bits, (4, 4));
+    let mut partition_used = [false; 2];
+    for y in 0..4 {
+      for x in 0..4 {
+        let partition = select_partition(seed, x, y, 2, true);
+        partition_used[partition] = true;
+        let expected = if partition == 0 { 255 } else { 0 };
+        assert_eq!(
+          texels[(y * 4 + x) as usize],
+          [expected, expected, expected, 255]
+        );
+      }
+    }
+    assert_eq!(partition_used, [true; 2]);
+  }
+
+  #[test]
+  fn test_astc_partition() {
+    // the partition selection covers all partitions and is stable for the seed
+    let mut used = [false; 4];
+    for y in 0..4 {
+      for x in 0..4 {
+        let p = select_partition(5, x, y, 4, true);
+        used[p] = true;
+        assert_eq!(p, select_partition(5, x, y, 4, true));
+      }
+    }
+    assert!(used.iter().filter(|u| **u).count() > 1);
+    assert_eq!(select_partition(0, 0, 0, 1, true), 0);
+  }
+
+  #[test]
+  fn test_astc_hdr_in_ldr_profile() {
+    // cem 11 is the hdr rgb direct mode, which is an error in the ldr profile
+    let block = rgb_direct_block(&[], [0; 6]);
+    let block = (block & !(0xf << 13)) | (11 << 13);
+    let texels = decode_ldr(block, (4, 4));
+    assert!(texels.iter().all(|t| *t == [255, 0, 255, 255]));
+
+    let mut texels = [[0; 8]; 16];
+    decode_astc(&block.to_le_bytes(), (4, 4), AstcProfile::Hdr, &mut texels);
+    let alpha = half::f16::from_le_bytes([texels[0][6], texels[0][7]]);
+    assert_eq!(alpha, half::f16::ONE);
+  }
+
+  #[test]
+  fn test_lns_to_f16() {
+    // 0x780 << 4 is 1.0 in the logarithmic representation
+    assert_eq!(lns_to_f16(0x7800), half::f16::ONE.to_bits());
+    assert_eq!(lns_to_f16(0), 0);
+    assert_eq!(lns_to_f16(0xffff), 0x7bff);
+  }
+}
@@ -0,0 +1,152 @@
+//! BC1-BC5 decoding, see the D3D11 block compression spec.
+
+fn unpack_565(c: u16) -> [u8; 3] {
+  let r = ((c >> 11) & 0x1f) as u8;
+  let g = ((c >> 5) & 0x3f) as u8;
+  let b = (c & 0x1f) as u8;
+  [
+    (r << 3) | (r >> 2),
+    (g << 2) | (g >> 4),
+    (b << 3) | (b >> 2),
+  ]
+}
+
+/// the color block of BC2 and BC3 always use the four color mode
+pub fn decode_bc1_color(block: &[u8], force_four_color: bool, output: &mut [[u8; 4]; 16]) {
+  let c0 = u16::from_le_bytes([block[0], block[1]]);
+  let c1 = u16::from_le_bytes([block[2], block[3]]);
+  let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+  let e0 = unpack_565(c0);
+  let e1 = unpack_565(c1);
+  let mix = |w0: u32, w1: u32, div: u32| -> [u8; 4] {
+    let mut c = [0, 0, 0, 255];
+    for i in 0..3 {
+      c[i] = ((e0[i] as u32 * w0 + e1[i] as u32 * w1) / div) as u8;
+    }
+    c
+  };
+
+  let palette = if c0 > c1 || force_four_color {
+    [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)]
+  } else {
+    [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2), [0, 0, 0, 0]]
+  };
+
+  for (i, texel) in output.iter_mut().enumerate() {
+    *texel = palette[((indices >> (2 * i)) & 0b11) as usize];
+  }
+}
+
+pub fn decode_bc1(block: &[u8], output: &mut [[u8; 4]; 16]) {
+  decode_bc1_color(block, false, output);
+}
+
+pub fn decode_bc2(block: &[u8], output: &mut [[u8; 4]; 16]) {
+  decode_bc1_color(&block[8..16], true, output);
+  let alpha = u64::from_le_bytes(block[0..8].try_into().unwrap());
+  for (i, texel) in output.iter_mut().enumerate() {
+    let a = ((alpha >> (4 * i)) & 0xf) as u8;
+    texel[3] = a * 17;
+  }
+}
+
+pub fn decode_bc3(block: &[u8], output: &mut [[u8; 4]; 16]) {
+  decode_bc1_color(&block[8..16], true, output);
+  let mut alpha = [0; 16];
+  decode_bc4_channel_unorm(&block[0..8], &mut alpha);
+  for (texel, a) in output.iter_mut().zip(alpha) {
+    texel[3] = a;
+  }
+}
+
+pub fn decode_bc4_channel_unorm(block: &[u8], output: &mut [u8; 16]) {
+  let e0 = block[0] as u32;
+  let e1 = block[1] as u32;
+  let mut palette = [0_u8; 8];
+  palette[0] = e0 as u8;
+  palette[1] = e1 as u8;
+  if e0 > e1 {
+    for i in 1..7 {
+      palette[i + 1] = (((7 - i as u32) * e0 + i as u32 * e1) / 7) as u8;
+    }
+  } else {
+    for i in 1..5 {
+      palette[i + 1] = (((5 - i as u32) * e0 + i as u32 * e1) / 5) as u8;
+    }
+    palette[6] = 0;
+    palette[7] = 255;
+  }
+
+  let indices = read_48bit_indices(block);
+  for (i, v) in output.iter_mut().enumerate() {
+    *v = palette[((indices >> (3 * i)) & 0b111) as usize];
+  }
+}
+
+pub fn decode_bc4_channel_snorm(block: &[u8], output: &mut [i8; 16]) {
+  // -128 is treated as -127
+  let e0 = (block[0] as i8).max(-127) as i32;
+  let e1 = (block[1] as i8).max(-127) as i32;
+  let mut palette = [0_i8; 8];
+  palette[0] = e0 as i8;
+  palette[1] = e1 as i8;
+  if e0 > e1 {
+    for i in 1..7 {
+      palette[i + 1] = (((7 - i as i32) * e0 + i as i32 * e1) / 7) as i8;
+    }
+  } else {
+    for i in 1..5 {
+      palette[i + 1] = (((5 - i as i32) * e0 + i as i32 * e1) / 5) as i8;
+    }
+    palette[6] = -127;
+    palette[7] = 127;
+  }
+
+  let indices = read_48bit_indices(block);
+  for (i, v) in output.iter_mut().enumerate() {
+    *v = palette[((indices >> (3 * i)) & 0b111) as usize];
+  }
+}
+
+fn read_48bit_indices(block: &[u8]) -> u64 {
+  let mut bytes = [0; 8];
+  bytes[..6].copy_from_slice(&block[2..8]);
+  u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_bc1() {
+    // c0 = pure red, c1 = pure blue, all texels use index 2 (2/3 red + 1/3 blue)
+    let block = [0x00, 0xf8, 0x1f, 0x00, 0xaa, 0xaa, 0xaa, 0xaa];
+    let mut output = [[0; 4]; 16];
+    decode_bc1(&block, &mut output);
+    assert!(output.iter().all(|t| *t == [170, 0, 85, 255]));
+
+    // c0 <= c1, index 3 is transparent black
+    let block = [0x1f, 0x00, 0x00, 0xf8, 0xff, 0xff, 0xff, 0xff];
+    decode_bc1(&block, &mut output);
+    assert!(output.iter().all(|t| *t == [0, 0, 0, 0]));
+  }
+
+  #[test]
+  fn test_bc4() {
+    // six interpolated values, the first texel use endpoint 1, others use index 7
+    let mut block = [200, 100, 0, 0, 0, 0, 0, 0];
+    block[2] = 0b111_001;
+    let mut output = [0; 16];
+    decode_bc4_channel_unorm(&block, &mut output);
+    assert_eq!(output[0], 100);
+    assert_eq!(output[1], ((200 + 6 * 100) / 7) as u8);
+
+    let block = [0x80, 0x7f, 0b110, 0, 0, 0, 0, 0];
+    let mut output = [0; 16];
+    decode_bc4_channel_snorm(&block, &mut output);
+    assert_eq!(output[0], -127);
+    assert_eq!(output[1], -127);
+  }
+}
@@ -0,0 +1,525 @@
+//! BC6H decoding, see the D3D11 BC6H format spec. The output is the half float bits.
+
+use crate::partition::*;
+
+// the endpoint component slots, rw gw bw for each endpoint and the partition
+const R: u8 = 0;
+const G: u8 = 4;
+const B: u8 = 8;
+const D: u8 = 12;
+
+/// (slot, hi, lo), if hi < lo the bits are stored in the reversed order
+type Field = (u8, u8, u8);
+
+const MODE_0: &[Field] = &[
+  (G + 2, 4, 4),
+  (B + 2, 4, 4),
+  (B + 3, 4, 4),
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 4, 0),
+  (G + 3, 4, 4),
+  (G + 2, 3, 0),
+  (G + 1, 4, 0),
+  (B + 3, 0, 0),
+  (G + 3, 3, 0),
+  (B + 1, 4, 0),
+  (B + 3, 1, 1),
+  (B + 2, 3, 0),
+  (R + 2, 4, 0),
+  (B + 3, 2, 2),
+  (R + 3, 4, 0),
+  (B + 3, 3, 3),
+  (D, 4, 0),
+];
+
+const MODE_1: &[Field] = &[
+  (G + 2, 5, 5),
+  (G + 3, 4, 4),
+  (G + 3, 5, 5),
+  (R, 6, 0),
+  (B + 3, 0, 0),
+  (B + 3, 1, 1),
+  (B + 2, 4, 4),
+  (G, 6, 0),
+  (B + 2, 5, 5),
+  (B + 3, 2, 2),
+  (G + 2, 4, 4),
+  (B, 6, 0),
+  (B + 3, 3, 3),
+  (B + 3, 5, 5),
+  (B + 3, 4, 4),
+  (R + 1, 5, 0),
+  (G + 2, 3, 0),
+  (G + 1, 5, 0),
+  (G + 3, 3, 0),
+  (B + 1, 5, 0),
+  (B + 2, 3, 0),
+  (R + 2, 5, 0),
+  (R + 3, 5, 0),
+  (D, 4, 0),
+];
+
+const MODE_2: &[Field] = &[
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 4, 0),
+  (R, 10, 10),
+  (G + 2, 3, 0),
+  (G + 1, 3, 0),
+  (G, 10, 10),
+  (B + 3, 0, 0),
+  (G + 3, 3, 0),
+  (B + 1, 3, 0),
+  (B, 10, 10),
+  (B + 3, 1, 1),
+  (B + 2, 3, 0),
+  (R + 2, 4, 0),
+  (B + 3, 2, 2),
+  (R + 3, 4, 0),
+  (B + 3, 3, 3),
+  (D, 4, 0),
+];
+
+const MODE_3: &[Field] = &[
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 3, 0),
+  (R, 10, 10),
+  (G + 3, 4, 4),
+  (G + 2, 3, 0),
+  (G + 1, 4, 0),
+  (G, 10, 10),
+  (G + 3, 3, 0),
+  (B + 1, 3, 0),
+  (B, 10, 10),
+  (B + 3, 1, 1),
+  (B + 2, 3, 0),
+  (R + 2, 3, 0),
+  (B + 3, 0, 0),
+  (B + 3, 2, 2),
+  (R + 3, 3, 0),
+  (G + 2, 4, 4),
+  (B + 3, 3, 3),
+  (D, 4, 0),
+];
+
+const MODE_4: &[Field] = &[
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 3, 0),
+  (R, 10, 10),
+  (B + 2, 4, 4),
+  (G + 2, 3, 0),
+  (G + 1, 3, 0),
+  (G, 10, 10),
+  (B + 3, 0, 0),
+  (G + 3, 3, 0),
+  (B + 1, 4, 0),
+  (B, 10, 10),
+  (B + 2, 3, 0),
+  (R + 2, 3, 0),
+  (B + 3, 1, 1),
+  (B + 3, 2, 2),
+  (R + 3, 3, 0),
+  (B + 3, 4, 4),
+  (B + 3, 3, 3),
+  (D, 4, 0),
+];
+
+const MODE_5: &[Field] = &[
+  (R, 8, 0),
+  (B + 2, 4, 4),
+  (G, 8, 0),
+  (G + 2, 4, 4),
+  (B, 8, 0),
+  (B + 3, 4, 4),
+  (R + 1, 4, 0),
+  (G + 3, 4, 4),
+  (G + 2, 3, 0),
+  (G + 1, 4, 0),
+  (B + 3, 0, 0),
+  (G + 3, 3, 0),
+  (B + 1, 4, 0),
+  (B + 3, 1, 1),
+  (B + 2, 3, 0),
+  (R + 2, 4, 0),
+  (B + 3, 2, 2),
+  (R + 3, 4, 0),
+  (B + 3, 3, 3),
+  (D, 4, 0),
+];
+
+const MODE_6: &[Field] = &[
+  (R, 7, 0),
+  (G + 3, 4, 4),
+  (B + 2, 4, 4),
+  (G, 7, 0),
+  (B + 3, 2, 2),
+  (G + 2, 4, 4),
+  (B, 7, 0),
+  (B + 3, 3, 3),
+  (B + 3, 4, 4),
+  (R + 1, 5, 0),
+  (G + 2, 3, 0),
+  (G + 1, 4, 0),
+  (B + 3, 0, 0),
+  (G + 3, 3, 0),
+  (B + 1, 4, 0),
+  (B + 3, 1, 1),
+  (B + 2, 3, 0),
+  (R + 2, 5, 0),
+  (R + 3, 5, 0),
+  (D, 4, 0),
+];
+
+const MODE_7: &[Field] = &[
+  (R, 7, 0),
+  (B + 3, 0, 0),
+  (B + 2, 4, 4),
+  (G, 7, 0),
+  (G + 2, 5, 5),
+  (G + 2, 4, 4),
+  (B, 7, 0),
+  (G + 3, 5, 5),
+  (B + 3, 4, 4),
+  (R + 1, 4, 0),
+  (G + 3, 4, 4),
+  (G + 2, 3, 0),
+  (G + 1, 5, 0),
+  (G + 3, 3, 0),
+  (B + 1, 4, 0),
+  (B + 3, 1, 1),
+  (B + 2, 3, 0),
+  (R + 2, 4, 0),
+  (B + 3, 2, 2),
+  (R + 3, 4, 0),
+  (B + 3, 3, 3),
+  (D, 4, 0),
+];
+
+const MODE_8: &[Field] = &[
+  (R, 7, 0),
+  (B + 3, 1, 1),
+  (B + 2, 4, 4),
+  (G, 7, 0),
+  (B + 2, 5, 5),
+  (G + 2, 4, 4),
+  (B, 7, 0),
+  (B + 3, 5, 5),
+  (B + 3, 4, 4),
+  (R + 1, 4, 0),
+  (G + 3, 4, 4),
+  (G + 2, 3, 0),
+  (G + 1, 4, 0),
+  (B + 3, 0, 0),
+  (G + 3, 3, 0),
+  (B + 1, 5, 0),
+  (B + 2, 3, 0),
+  (R + 2, 4, 0),
+  (B + 3, 2, 2),
+  (R + 3, 4, 0),
+  (B + 3, 3, 3),
+  (D, 4, 0),
+];
+
+const MODE_9: &[Field] = &[
+  (R, 5, 0),
+  (G + 3, 4, 4),
+  (B + 3, 0, 0),
+  (B + 3, 1, 1),
+  (B + 2, 4, 4),
+  (G, 5, 0),
+  (G + 2, 5, 5),
+  (B + 2, 5, 5),
+  (B + 3, 2, 2),
+  (G + 2, 4, 4),
+  (B, 5, 0),
+  (G + 3, 5, 5),
+  (B + 3, 3, 3),
+  (B + 3, 5, 5),
+  (B + 3, 4, 4),
+  (R + 1, 5, 0),
+  (G + 2, 3, 0),
+  (G + 1, 5, 0),
+  (G + 3, 3, 0),
+  (B + 1, 5, 0),
+  (B + 2, 3, 0),
+  (R + 2, 5, 0),
+  (R + 3, 5, 0),
+  (D, 4, 0),
+];
+
+const MODE_10: &[Field] = &[
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 9, 0),
+  (G + 1, 9, 0),
+  (B + 1, 9, 0),
+];
+
+const MODE_11: &[Field] = &[
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 8, 0),
+  (R, 10, 10),
+  (G + 1, 8, 0),
+  (G, 10, 10),
+  (B + 1, 8, 0),
+  (B, 10, 10),
+];
+
+const MODE_12: &[Field] = &[
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 7, 0),
+  (R, 10, 11),
+  (G + 1, 7, 0),
+  (G, 10, 11),
+  (B + 1, 7, 0),
+  (B, 10, 11),
+];
+
+const MODE_13: &[Field] = &[
+  (R, 9, 0),
+  (G, 9, 0),
+  (B, 9, 0),
+  (R + 1, 3, 0),
+  (R, 10, 15),
+  (G + 1, 3, 0),
+  (G, 10, 15),
+  (B + 1, 3, 0),
+  (B, 10, 15),
+];
+
+struct BC6HModeInfo {
+  fields: &'static [Field],
+  transformed: bool,
+  /// the endpoint precision, and the delta precision of r g b
+  bits: [u32; 4],
+}
+
+const MODES: [BC6HModeInfo; 14] = [
+  info(MODE_0, true, [10, 5, 5, 5]),
+  info(MODE_1, true, [7, 6, 6, 6]),
+  info(MODE_2, true, [11, 5, 4, 4]),
+  info(MODE_3, true, [11, 4, 5, 4]),
+  info(MODE_4, true, [11, 4, 4, 5]),
+  info(MODE_5, true, [9, 5, 5, 5]),
+  info(MODE_6, true, [8, 6, 5, 5]),
+  info(MODE_7, true, [8, 5, 6, 5]),
+  info(MODE_8, true, [8, 5, 5, 6]),
+  info(MODE_9, false, [6, 6, 6, 6]),
+  info(MODE_10, false, [10, 10, 10, 10]),
+  info(MODE_11, true, [11, 9, 9, 9]),
+  info(MODE_12, true, [12, 8, 8, 8]),
+  info(MODE_13, true, [16, 4, 4, 4]),
+];
+
+const fn info(fields: &'static [Field], transformed: bool, bits: [u32; 4]) -> BC6HModeInfo {
+  BC6HModeInfo {
+    fields,
+    transformed,
+    bits,
+  }
+}
+
+fn mode_index(mode_bits: u32) -> Option<usize> {
+  Some(match mode_bits {
+    0b00 => 0,
+    0b01 => 1,
+    0b00010 => 2,
+    0b00110 => 3,
+    0b01010 => 4,
+    0b01110 => 5,
+    0b10010 => 6,
+    0b10110 => 7,
+    0b11010 => 8,
+    0b11110 => 9,
+    0b00011 => 10,
+    0b00111 => 11,
+    0b01011 => 12,
+    0b01111 => 13,
+    _ => return None,
+  })
+}
+
+fn extend_sign(v: i32, bits: u32) -> i32 {
+  (v << (32 - bits)) >> (32 - bits)
+}
+
+fn unquantize(v: i32, bits: u32, signed: bool) -> i32 {
+  if !signed {
+    if bits >= 15 || v == 0 {
+      v
+    } else if v == (1 << bits) - 1 {
+      0xffff
+    } else {
+      ((v << 16) + 0x8000) >> bits
+    }
+  } else {
+    if bits >= 16 {
+      return v;
+    }
+    let magnitude = v.abs();
+    let q = if magnitude == 0 {
+      0
+    } else if magnitude >= (1 << (bits - 1)) - 1 {
+      0x7fff
+    } else {
+      ((magnitude << 15) + 0x4000) >> (bits - 1)
+    };
+    if v < 0 { -q } else { q }
+  }
+}
+
+fn finish_unquantize(v: i32, signed: bool) -> u16 {
+  if !signed {
+    ((v * 31) >> 6) as u16
+  } else {
+    let v = if v < 0 {
+      -(((-v) * 31) >> 5)
+    } else {
+      (v * 31) >> 5
+    };
+    if v < 0 {
+      0x8000 | (-v) as u16
+    } else {
+      v as u16
+    }
+  }
+}
+
+/// output the rgb half float bits
+pub fn decode_bc6h(block: &[u8], signed: bool, output: &mut [[u16; 3]; 16]) {
+  let mut reader = BlockBitReader::new(block);
+  let mut mode_bits = reader.read(2);
+  if mode_bits > 1 {
+    mode_bits |= reader.read(3) << 2;
+  }
+  let Some(mode_index) = mode_index(mode_bits) else {
+    // reserved mode
+    *output = [[0; 3]; 16];
+    return;
+  };
+  let mode = &MODES[mode_index];
+
+  let mut slots = [0_i32; 13];
+  for &(slot, hi, lo) in mode.fields {
+    let v = if hi >= lo {
+      reader.read((hi - lo + 1) as u32) << lo
+    } else {
+      reader.read_reversed((lo - hi + 1) as u32) << hi
+    };
+    slots[slot as usize] |= v as i32;
+  }
+
+  let subset_count = if mode_index >= 10 { 1 } else { 2 };
+  let endpoint_count = subset_count * 2;
+  let partition = slots[D as usize] as usize;
+  let endpoint_bits = mode.bits[0];
+
+  // [endpoint][channel]
+  let mut endpoints = [[0_i32; 3]; 4];
+  for (endpoint, e) in endpoints.iter_mut().enumerate().take(endpoint_count) {
+    for (channel, base) in [R, G, B].into_iter().enumerate() {
+      e[channel] = slots[(base as usize) + endpoint];
+    }
+  }
+
+  if signed {
+    for c in endpoints[0].iter_mut() {
+      *c = extend_sign(*c, endpoint_bits);
+    }
+  }
+  if mode.transformed || signed {
+    for e in endpoints.iter_mut().take(endpoint_count).skip(1) {
+      for (c, bits) in e.iter_mut().zip(&mode.bits[1..]) {
+        *c = extend_sign(*c, *bits);
+      }
+    }
+  }
+  if mode.transformed {
+    let base = endpoints[0];
+    for e in endpoints.iter_mut().take(endpoint_count).skip(1) {
+      for channel in 0..3 {
+        let v = (e[channel] + base[channel]) & ((1 << endpoint_bits) - 1);
+        e[channel] = if signed {
+          extend_sign(v, endpoint_bits)
+        } else {
+          v
+        };
+      }
+    }
+  }
+  for e in endpoints.iter_mut().take(endpoint_count) {
+    for c in e.iter_mut() {
+      *c = unquantize(*c, endpoint_bits, signed);
+    }
+  }
+
+  let index_bits = if subset_count == 1 { 4 } else { 3 };
+  let weights = weights(index_bits);
+  for (texel, out) in output.iter_mut().enumerate() {
+    let (subset, anchor) = partition_subset(subset_count, partition, texel);
+    let index = reader.read(index_bits - anchor as u32) as usize;
+    let w = weights[index] as i32;
+    let e0 = endpoints[subset * 2];
+    let e1 = endpoints[subset * 2 + 1];
+    for channel in 0..3 {
+      let v = (e0[channel] * (64 - w) + e1[channel] * w + 32) >> 6;
+      out[channel] = finish_unquantize(v, signed);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_bc6h_mode_layout() {
+    // every mode header plus the index bits should fill the whole block
+    for (i, mode) in MODES.iter().enumerate() {
+      let mode_bits = if i < 2 { 2 } else { 5 };
+      let header: u32 = mode
+        .fields
+        .iter()
+        .map(|(_, hi, lo)| hi.abs_diff(*lo) as u32 + 1)
+        .sum();
+      let index_bits = if i >= 10 { 16 * 4 - 1 } else { 16 * 3 - 2 };
+      assert_eq!(mode_bits + header + index_bits, 128, "mode {i}");
+    }
+  }
+
+  #[test]
+  fn test_bc6h_mode_10() {
+    // mode 10 (0b00011) stores two 10 bits endpoints without the transform
+    let mut bits: u128 = 0b00011;
+    let mut offset = 5;
+    for v in [0, 0, 0, 1023, 1023, 1023] {
+      bits |= (v as u128) << offset;
+      offset += 10;
+    }
+    // the first texel use the endpoint 0, others use the endpoint 1
+    offset += 3;
+    for _ in 1..16 {
+      bits |= 15 << offset;
+      offset += 4;
+    }
+    assert_eq!(offset, 128);
+
+    let mut output = [[0; 3]; 16];
+    decode_bc6h(&bits.to_le_bytes(), false, &mut output);
+    assert_eq!(output[0], [0; 3]);
+    // the max value maps to 0xffff * 31 / 64, which is the max finite half float
+    assert_eq!(output[1], [0x7bff; 3]);
+  }
+}
@@ -0,0 +1,225 @@
+//! BC7 decoding, see the D3D11 BC7 format spec.
+
+use crate::partition::*;
+
+struct BC7ModeInfo {
+  subset_count: usize,
+  partition_bits: u32,
+  rotation_bits: u32,
+  index_selection_bits: u32,
+  color_bits: u32,
+  alpha_bits: u32,
+  endpoint_p_bits: bool,
+  shared_p_bits: bool,
+  index_bits: u32,
+  secondary_index_bits: u32,
+}
+
+const fn mode(
+  subset_count: usize,
+  partition_bits: u32,
+  rotation_bits: u32,
+  index_selection_bits: u32,
+  color_bits: u32,
+  alpha_bits: u32,
+  endpoint_p_bits: bool,
+  shared_p_bits: bool,
+  index_bits: u32,
+  secondary_index_bits: u32,
+) -> BC7ModeInfo {
+  BC7ModeInfo {
+    subset_count,
+    partition_bits,
+    rotation_bits,
+    index_selection_bits,
+    color_bits,
+    alpha_bits,
+    endpoint_p_bits,
+    shared_p_bits,
+    index_bits,
+    secondary_index_bits,
+  }
+}
+
+const MODES: [BC7ModeInfo; 8] = [
+  mode(3, 4, 0, 0, 4, 0, true, false, 3, 0),
+  mode(2, 6, 0, 0, 6, 0, false, true, 3, 0),
+  mode(3, 6, 0, 0, 5, 0, false, false, 2, 0),
+  mode(2, 6, 0, 0, 7, 0, true, false, 2, 0),
+  mode(1, 0, 2, 1, 5, 6, false, false, 2, 3),
+  mode(1, 0, 2, 0, 7, 8, false, false, 2, 2),
+  mode(1, 0, 0, 0, 7, 7, true, false, 4, 0),
+  mode(2, 6, 0, 0, 5, 5, true, false, 2, 0),
+];
+
+fn expand(v: u32, bits: u32) -> u32 {
+  let v = v << (8 - bits);
+  v | (v >> bits)
+}
+
+fn interpolate(e0: u32, e1: u32, weight: u32) -> u8 {
+  (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+pub fn decode_bc7(block: &[u8], output: &mut [[u8; 4]; 16]) {
+  let mode_index = block[0].trailing_zeros() as usize;
+  if mode_index >= 8 {
+    // reserved mode, the spec requires to output transparent black
+    *output = [[0; 4]; 16];
+    return;
+  }
+  let mode = &MODES[mode_index];
+
+  let mut reader = BlockBitReader::new(block);
+  reader.read(mode_index as u32 + 1);
+
+  let partition = reader.read(mode.partition_bits) as usize;
+  let rotation = reader.read(mode.rotation_bits);
+  let index_selection = reader.read(mode.index_selection_bits);
+
+  let endpoint_count = mode.subset_count * 2;
+  // [endpoint][channel]
+  let mut endpoints = [[0_u32; 4]; 6];
+  for channel in 0..3 {
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+      endpoint[channel] = reader.read(mode.color_bits);
+    }
+  }
+  for endpoint in endpoints.iter_mut().take(endpoint_count) {
+    endpoint[3] = reader.read(mode.alpha_bits);
+  }
+
+  let mut color_bits = mode.color_bits;
+  let mut alpha_bits = mode.alpha_bits;
+  if mode.endpoint_p_bits || mode.shared_p_bits {
+    let mut p_bits = [0; 6];
+    if mode.endpoint_p_bits {
+      for p in p_bits.iter_mut().take(endpoint_count) {
+        *p = reader.read(1);
+      }
+    } else {
+      for subset in 0..mode.subset_count {
+        let p = reader.read(1);
+        p_bits[subset * 2] = p;
+        p_bits[subset * 2 + 1] = p;
+      }
+    }
+    for (endpoint, p) in endpoints.iter_mut().zip(p_bits).take(endpoint_count) {
+      for c in endpoint[0..3].iter_mut() {
+        *c = (*c << 1) | p;
+      }
+      if mode.alpha_bits > 0 {
+        endpoint[3] = (endpoint[3] << 1) | p;
+      }
+    }
+    color_bits += 1;
+    if mode.alpha_bits > 0 {
+      alpha_bits += 1;
+    }
+  }
+
+  for endpoint in endpoints.iter_mut().take(endpoint_count) {
+    for c in endpoint[0..3].iter_mut() {
+      *c = expand(*c, color_bits);
+    }
+    endpoint[3] = if alpha_bits > 0 {
+      expand(endpoint[3], alpha_bits)
+    } else {
+      255
+    };
+  }
+
+  let mut primary = [0_u32; 16];
+  for (texel, index) in primary.iter_mut().enumerate() {
+    let (_, anchor) = partition_subset(mode.subset_count, partition, texel);
+    *index = reader.read(mode.index_bits - anchor as u32);
+  }
+  let mut secondary = [0_u32; 16];
+  if mode.secondary_index_bits > 0 {
+    for (texel, index) in secondary.iter_mut().enumerate() {
+      let anchor = texel == 0;
+      *index = reader.read(mode.secondary_index_bits - anchor as u32);
+    }
+  }
+
+  for (texel, out) in output.iter_mut().enumerate() {
+    let (subset, _) = partition_subset(mode.subset_count, partition, texel);
+    let e0 = endpoints[subset * 2];
+    let e1 = endpoints[subset * 2 + 1];
+
+    let (color_weight, alpha_weight) = if mode.secondary_index_bits == 0 {
+      let w = weights(mode.index_bits)[primary[texel] as usize];
+      (w, w)
+    } else {
+      let primary_weight = weights(mode.index_bits)[primary[texel] as usize];
+      let secondary_weight = weights(mode.secondary_index_bits)[secondary[texel] as usize];
+      if index_selection == 0 {
+        (primary_weight, secondary_weight)
+      } else {
+        (secondary_weight, primary_weight)
+      }
+    };
+
+    for channel in 0..3 {
+      out[channel] = interpolate(e0[channel], e1[channel], color_weight);
+    }
+    out[3] = interpolate(e0[3], e1[3], alpha_weight);
+
+    match rotation {
+      1 => out.swap(0, 3),
+      2 => out.swap(1, 3),
+      3 => out.swap(2, 3),
+      _ => {}
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// write the bits in the same order of the decoder
+  struct BitWriter {
+    bits: u128,
+    offset: u32,
+  }
+
+  impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+      self.bits |= (value as u128 & ((1 << count) - 1)) << self.offset;
+      self.offset += count;
+    }
+  }
+
+  #[test]
+  fn test_bc7_mode6() {
+    let mut w = BitWriter { bits: 0, offset: 0 };
+    w.write(1 << 6, 7);
+    // r, g, b, a endpoints with 7 bits
+    for (e0, e1) in [(0, 127), (127, 0), (0, 0), (127, 127)] {
+      w.write(e0, 7);
+      w.write(e1, 7);
+    }
+    // p bits
+    w.write(1, 1);
+    w.write(1, 1);
+    // the first texel use the endpoint 0, others use the endpoint 1
+    w.write(0, 3);
+    for _ in 1..16 {
+      w.write(15, 4);
+    }
+    assert_eq!(w.offset, 128);
+
+    let mut output = [[0; 4]; 16];
+    decode_bc7(&w.bits.to_le_bytes(), &mut output);
+    assert_eq!(output[0], [1, 255, 1, 255]);
+    assert_eq!(output[1], [255, 1, 1, 255]);
+  }
+
+  #[test]
+  fn test_bc7_reserved_mode() {
+    let mut output = [[1; 4]; 16];
+    decode_bc7(&[0; 16], &mut output);
+    assert!(output.iter().all(|t| *t == [0; 4]));
+  }
+}
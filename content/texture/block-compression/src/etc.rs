@@ -0,0 +1,338 @@
+//! ETC2 and EAC decoding, see the Khronos data format spec. The texel index of the ETC
+//! blocks is column major, the output is converted to row major.
+
+const MODIFIERS: [[i32; 4]; 8] = [
+  [2, 8, -2, -8],
+  [5, 17, -5, -17],
+  [9, 29, -9, -29],
+  [13, 42, -13, -42],
+  [18, 60, -18, -60],
+  [24, 80, -24, -80],
+  [33, 106, -33, -106],
+  [47, 183, -47, -183],
+];
+
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+  [-3, -6, -9, -15, 2, 5, 8, 14],
+  [-3, -7, -10, -13, 2, 6, 9, 12],
+  [-2, -5, -8, -13, 1, 4, 7, 12],
+  [-2, -4, -6, -13, 1, 3, 5, 12],
+  [-3, -6, -8, -12, 2, 5, 7, 11],
+  [-3, -7, -9, -11, 2, 6, 8, 10],
+  [-4, -7, -8, -11, 3, 6, 7, 10],
+  [-3, -5, -8, -11, 2, 4, 7, 10],
+  [-2, -6, -8, -10, 1, 5, 7, 9],
+  [-2, -5, -8, -10, 1, 4, 7, 9],
+  [-2, -4, -8, -10, 1, 3, 7, 9],
+  [-2, -5, -7, -10, 1, 4, 6, 9],
+  [-3, -4, -7, -10, 2, 3, 6, 9],
+  [-1, -2, -3, -10, 0, 1, 2, 9],
+  [-4, -6, -8, -9, 3, 5, 7, 8],
+  [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn bits(v: u64, hi: u32, lo: u32) -> i32 {
+  ((v >> lo) & ((1 << (hi - lo + 1)) - 1)) as i32
+}
+
+fn clamp_u8(v: i32) -> u8 {
+  v.clamp(0, 255) as u8
+}
+
+fn extend_4(v: i32) -> i32 {
+  (v << 4) | v
+}
+
+fn extend_5(v: i32) -> i32 {
+  (v << 3) | (v >> 2)
+}
+
+fn extend_6(v: i32) -> i32 {
+  (v << 2) | (v >> 4)
+}
+
+fn extend_7(v: i32) -> i32 {
+  (v << 1) | (v >> 6)
+}
+
+fn add(c: [i32; 3], d: i32) -> [u8; 4] {
+  [
+    clamp_u8(c[0] + d),
+    clamp_u8(c[1] + d),
+    clamp_u8(c[2] + d),
+    255,
+  ]
+}
+
+/// the 2 bits index of the texel at the column major position
+fn etc_index(v: u64, i: usize) -> usize {
+  let msb = (v >> (16 + i)) & 1;
+  let lsb = (v >> i) & 1;
+  ((msb << 1) | lsb) as usize
+}
+
+fn write_column_major(output: &mut [[u8; 4]; 16], i: usize, c: [u8; 4]) {
+  let x = i / 4;
+  let y = i % 4;
+  output[y * 4 + x] = c;
+}
+
+/// decode the ETC2 rgb block, if punch_through is true, it's the rgb a1 block
+pub fn decode_etc2_rgb(block: &[u8], punch_through: bool, output: &mut [[u8; 4]; 16]) {
+  let v = u64::from_be_bytes(block[0..8].try_into().unwrap());
+  let diff_or_opaque = bits(v, 33, 33) == 1;
+  let flip = bits(v, 32, 32) == 1;
+
+  // in the punch through mode, the differential mode is always used, and the bit means opaque
+  let differential = punch_through || diff_or_opaque;
+  let opaque = !punch_through || diff_or_opaque;
+  let transparent = [0, 0, 0, 0];
+
+  if differential {
+    let r = bits(v, 63, 59);
+    let dr = (bits(v, 58, 56) << 29) >> 29;
+    let g = bits(v, 55, 51);
+    let dg = (bits(v, 50, 48) << 29) >> 29;
+    let b = bits(v, 47, 43);
+    let db = (bits(v, 42, 40) << 29) >> 29;
+
+    if !(0..32).contains(&(r + dr)) {
+      decode_t_mode(v, opaque, output);
+      return;
+    }
+    if !(0..32).contains(&(g + dg)) {
+      decode_h_mode(v, opaque, output);
+      return;
+    }
+    if !(0..32).contains(&(b + db)) {
+      decode_planar_mode(v, output);
+      return;
+    }
+
+    let c0 = [extend_5(r), extend_5(g), extend_5(b)];
+    let c1 = [extend_5(r + dr), extend_5(g + dg), extend_5(b + db)];
+    decode_etc1_sub_blocks(v, flip, c0, c1, output);
+
+    if !opaque {
+      for i in 0..16 {
+        let index = etc_index(v, i);
+        if index == 2 {
+          write_column_major(output, i, transparent);
+        } else if index == 0 {
+          // the modifier is zero in the non opaque mode
+          let sub_block = if flip { i % 4 >= 2 } else { i / 4 >= 2 };
+          let c = if sub_block { c1 } else { c0 };
+          write_column_major(output, i, add(c, 0));
+        }
+      }
+    }
+  } else {
+    let c0 = [
+      extend_4(bits(v, 63, 60)),
+      extend_4(bits(v, 55, 52)),
+      extend_4(bits(v, 47, 44)),
+    ];
+    let c1 = [
+      extend_4(bits(v, 59, 56)),
+      extend_4(bits(v, 51, 48)),
+      extend_4(bits(v, 43, 40)),
+    ];
+    decode_etc1_sub_blocks(v, flip, c0, c1, output);
+  }
+}
+
+fn decode_etc1_sub_blocks(
+  v: u64,
+  flip: bool,
+  c0: [i32; 3],
+  c1: [i32; 3],
+  output: &mut [[u8; 4]; 16],
+) {
+  let table0 = bits(v, 39, 37) as usize;
+  let table1 = bits(v, 36, 34) as usize;
+  for i in 0..16 {
+    let x = i / 4;
+    let y = i % 4;
+    let second = if flip { y >= 2 } else { x >= 2 };
+    let (c, table) = if second { (c1, table1) } else { (c0, table0) };
+    let modifier = MODIFIERS[table][etc_index(v, i)];
+    write_column_major(output, i, add(c, modifier));
+  }
+}
+
+fn decode_paint_colors(v: u64, opaque: bool, paint: [[u8; 4]; 4], output: &mut [[u8; 4]; 16]) {
+  for i in 0..16 {
+    let index = etc_index(v, i);
+    let c = if !opaque && index == 2 {
+      [0, 0, 0, 0]
+    } else {
+      paint[index]
+    };
+    write_column_major(output, i, c);
+  }
+}
+
+fn decode_t_mode(v: u64, opaque: bool, output: &mut [[u8; 4]; 16]) {
+  let c0 = [
+    extend_4((bits(v, 60, 59) << 2) | bits(v, 57, 56)),
+    extend_4(bits(v, 55, 52)),
+    extend_4(bits(v, 51, 48)),
+  ];
+  let c1 = [
+    extend_4(bits(v, 47, 44)),
+    extend_4(bits(v, 43, 40)),
+    extend_4(bits(v, 39, 36)),
+  ];
+  let d = DISTANCES[((bits(v, 35, 34) << 1) | bits(v, 32, 32)) as usize];
+  let paint = [add(c0, 0), add(c1, d), add(c1, 0), add(c1, -d)];
+  decode_paint_colors(v, opaque, paint, output);
+}
+
+fn decode_h_mode(v: u64, opaque: bool, output: &mut [[u8; 4]; 16]) {
+  let r0 = bits(v, 62, 59);
+  let g0 = (bits(v, 58, 56) << 1) | bits(v, 52, 52);
+  let b0 = (bits(v, 51, 51) << 3) | bits(v, 49, 47);
+  let r1 = bits(v, 46, 43);
+  let g1 = bits(v, 42, 39);
+  let b1 = bits(v, 38, 35);
+
+  let order = ((r0 << 8) | (g0 << 4) | b0) >= ((r1 << 8) | (g1 << 4) | b1);
+  let d_index = (bits(v, 34, 34) << 2) | (bits(v, 32, 32) << 1) | order as i32;
+  let d = DISTANCES[d_index as usize];
+
+  let c0 = [extend_4(r0), extend_4(g0), extend_4(b0)];
+  let c1 = [extend_4(r1), extend_4(g1), extend_4(b1)];
+  let paint = [add(c0, d), add(c0, -d), add(c1, d), add(c1, -d)];
+  decode_paint_colors(v, opaque, paint, output);
+}
+
+fn decode_planar_mode(v: u64, output: &mut [[u8; 4]; 16]) {
+  let o = [
+    extend_6(bits(v, 62, 57)),
+    extend_7((bits(v, 56, 56) << 6) | bits(v, 54, 49)),
+    extend_6((bits(v, 48, 48) << 5) | (bits(v, 44, 43) << 3) | bits(v, 41, 39)),
+  ];
+  let h = [
+    extend_6((bits(v, 38, 34) << 1) | bits(v, 32, 32)),
+    extend_7(bits(v, 31, 25)),
+    extend_6(bits(v, 24, 19)),
+  ];
+  let vv = [
+    extend_6(bits(v, 18, 13)),
+    extend_7(bits(v, 12, 6)),
+    extend_6(bits(v, 5, 0)),
+  ];
+  for y in 0..4 {
+    for x in 0..4 {
+      let mut c = [0, 0, 0, 255];
+      for i in 0..3 {
+        c[i] = clamp_u8((x * (h[i] - o[i]) + y * (vv[i] - o[i]) + 4 * o[i] + 2) >> 2);
+      }
+      output[(y * 4 + x) as usize] = c;
+    }
+  }
+}
+
+pub fn decode_etc2_rgba(block: &[u8], output: &mut [[u8; 4]; 16]) {
+  decode_etc2_rgb(&block[8..16], false, output);
+  let mut alpha = [0; 16];
+  decode_eac_alpha(&block[0..8], &mut alpha);
+  for (texel, a) in output.iter_mut().zip(alpha) {
+    texel[3] = a;
+  }
+}
+
+/// the index, multiplier and the modifier table of the texel in row major order
+fn eac_texels(block: &[u8]) -> (i32, i32, [i32; 16]) {
+  let v = u64::from_be_bytes(block[0..8].try_into().unwrap());
+  let base = bits(v, 63, 56);
+  let multiplier = bits(v, 55, 52);
+  let table = &EAC_MODIFIERS[bits(v, 51, 48) as usize];
+  let mut modifiers = [0; 16];
+  for i in 0..16 {
+    let index = (v >> (45 - 3 * i)) & 0b111;
+    let x = i / 4;
+    let y = i % 4;
+    modifiers[y * 4 + x] = table[index as usize];
+  }
+  (base, multiplier, modifiers)
+}
+
+pub fn decode_eac_alpha(block: &[u8], output: &mut [u8; 16]) {
+  let (base, multiplier, modifiers) = eac_texels(block);
+  for (out, modifier) in output.iter_mut().zip(modifiers) {
+    *out = clamp_u8(base + modifier * multiplier);
+  }
+}
+
+/// decode the 11 bits EAC block into normalized value
+pub fn decode_eac_r11(block: &[u8], signed: bool, output: &mut [f32; 16]) {
+  let (base, multiplier, modifiers) = eac_texels(block);
+  for (out, modifier) in output.iter_mut().zip(modifiers) {
+    let delta = if multiplier == 0 {
+      modifier
+    } else {
+      modifier * multiplier * 8
+    };
+    *out = if signed {
+      let base = base as u8 as i8 as i32;
+      // -128 is treated as -127
+      let v = (base.max(-127) * 8 + delta).clamp(-1023, 1023);
+      v as f32 / 1023.
+    } else {
+      let v = (base * 8 + 4 + delta).clamp(0, 2047);
+      v as f32 / 2047.
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_etc1_individual_mode() {
+    // r0 = 8, r1 = 4, other channels are zero, table 0 for both, no flip
+    // all texel indices are 0, so the modifier is +2
+    let v: u64 = (8 << 60) | (4 << 56);
+    let mut output = [[0; 4]; 16];
+    decode_etc2_rgb(&v.to_be_bytes(), false, &mut output);
+    // left two columns use the first sub block
+    assert_eq!(output[0], [0x88 + 2, 2, 2, 255]);
+    assert_eq!(output[2], [0x44 + 2, 2, 2, 255]);
+    assert_eq!(output[12], [0x88 + 2, 2, 2, 255]);
+  }
+
+  #[test]
+  fn test_etc2_planar_mode() {
+    // differential mode with the blue channel overflow(0 + -1), the red origin is 63, and
+    // the blue origin is 6, the horizontal and vertical colors are zero
+    let v: u64 = (1 << 33) | (63 << 57) | (0b111 << 40);
+    let mut output = [[0; 4]; 16];
+    decode_etc2_rgb(&v.to_be_bytes(), false, &mut output);
+    assert_eq!(output[0], [255, 0, 24, 255]);
+    assert_eq!(output[15], [0, 0, 0, 255]);
+  }
+
+  #[test]
+  fn test_eac() {
+    // base 128, multiplier 1, table 0, all index 7 (+14)
+    let mut v: u64 = (128 << 56) | (1 << 52);
+    for i in 0..16 {
+      v |= 0b111 << (45 - 3 * i);
+    }
+    let mut output = [0; 16];
+    decode_eac_alpha(&v.to_be_bytes(), &mut output);
+    assert!(output.iter().all(|a| *a == 142));
+
+    let mut output = [0.; 16];
+    decode_eac_r11(&v.to_be_bytes(), false, &mut output);
+    assert!(
+      output
+        .iter()
+        .all(|r| *r == (128 * 8 + 4 + 14 * 8) as f32 / 2047.)
+    );
+  }
+}
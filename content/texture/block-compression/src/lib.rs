@@ -0,0 +1,276 @@
+//! CPU decoders of the block compressed texture formats, used as the fallback when the
+//! adapter lacks the support of the compressed format.
+//!
+//! BC1-BC7, ETC2/EAC and 2D ASTC are supported.
+
+use rendiation_texture_core::*;
+use wgpu_types::AstcChannel;
+
+mod astc;
+mod bc;
+mod bc6h;
+mod bc7;
+mod etc;
+mod partition;
+
+pub use astc::*;
+pub use bc::*;
+pub use bc6h::*;
+pub use bc7::*;
+pub use etc::*;
+
+/// the uncompressed format the compressed format will be decoded into, return None if the
+/// format is not supported.
+pub fn decoded_format(format: TextureFormat) -> Option<TextureFormat> {
+  use TextureFormat::*;
+  Some(match format {
+    Bc1RgbaUnorm | Bc2RgbaUnorm | Bc3RgbaUnorm | Bc7RgbaUnorm | Bc4RUnorm | Bc5RgUnorm
+    | Etc2Rgb8Unorm | Etc2Rgb8A1Unorm | Etc2Rgba8Unorm => Rgba8Unorm,
+    Bc1RgbaUnormSrgb | Bc2RgbaUnormSrgb | Bc3RgbaUnormSrgb | Bc7RgbaUnormSrgb
+    | Etc2Rgb8UnormSrgb | Etc2Rgb8A1UnormSrgb | Etc2Rgba8UnormSrgb => Rgba8UnormSrgb,
+    Bc4RSnorm | Bc5RgSnorm => Rgba8Snorm,
+    Bc6hRgbUfloat | Bc6hRgbFloat | EacR11Unorm | EacR11Snorm | EacRg11Unorm | EacRg11Snorm => {
+      Rgba16Float
+    }
+    Astc { channel, .. } => match channel {
+      AstcChannel::Unorm => Rgba8Unorm,
+      AstcChannel::UnormSrgb => Rgba8UnormSrgb,
+      AstcChannel::Hdr => Rgba16Float,
+    },
+    _ => return None,
+  })
+}
+
+/// decode all levels of the block compressed image, return None if the format is not
+/// compressed or not supported.
+pub fn decode_block_compressed_image(image: &GPUBufferImage) -> Option<GPUBufferImage> {
+  let target_format = decoded_format(image.format)?;
+  let decode_block: Box<dyn Fn(&[u8], &mut [[u8; 8]])> = match image.format {
+    TextureFormat::Astc { channel, .. } => {
+      let block_dimensions = image.format.block_dimensions();
+      let profile = match channel {
+        AstcChannel::Unorm => AstcProfile::Ldr,
+        AstcChannel::UnormSrgb => AstcProfile::LdrSrgb,
+        AstcChannel::Hdr => AstcProfile::Hdr,
+      };
+      Box::new(move |data, texels| decode_astc(data, block_dimensions, profile, texels))
+    }
+    format => Box::new(block_decoder(format)?),
+  };
+  let block_size = image.format.block_copy_size(None)? as usize;
+  let (block_width, block_height) = image.format.block_dimensions();
+  let (block_width, block_height) = (block_width as usize, block_height as usize);
+
+  let mut levels = (0..image.mip_level_count()).map(|level| {
+    let size = image.level_size(level);
+    let (width, height) = size.into_usize();
+    let texel_size = target_format.block_copy_size(None).unwrap() as usize;
+    let mut output = vec![0_u8; width * height * texel_size];
+
+    let blocks_per_row = width.div_ceil(block_width);
+    let data = image.level_data(level);
+    let mut texels = vec![[0_u8; 8]; block_width * block_height];
+    for (block_index, block) in data.chunks_exact(block_size).enumerate() {
+      let block_x = (block_index % blocks_per_row) * block_width;
+      let block_y = (block_index / blocks_per_row) * block_height;
+      if block_y >= height {
+        break;
+      }
+
+      decode_block(block, &mut texels);
+
+      for y in 0..block_height.min(height - block_y) {
+        for x in 0..block_width.min(width - block_x) {
+          let offset = ((block_y + y) * width + block_x + x) * texel_size;
+          output[offset..offset + texel_size]
+            .copy_from_slice(&texels[y * block_width + x][..texel_size]);
+        }
+      }
+    }
+    output
+  });
+
+  Some(GPUBufferImage {
+    data: levels.next().unwrap(),
+    format: target_format,
+    size: image.size,
+    precomputed_mips: levels.collect(),
+  })
+}
+
+/// decode one 4x4 block to 16 texels in row major order, the texel is in the decoded format
+type BlockDecoder = fn(&[u8], &mut [[u8; 8]]);
+
+fn write_rgba8(texels: &mut [[u8; 8]], decoded: [[u8; 4]; 16]) {
+  for (t, d) in texels.iter_mut().zip(decoded) {
+    t[..4].copy_from_slice(&d);
+  }
+}
+
+fn write_rgba16f(texels: &mut [[u8; 8]], decoded: impl Fn(usize) -> [f32; 4]) {
+  for (i, t) in texels.iter_mut().enumerate() {
+    for (c, v) in decoded(i).into_iter().enumerate() {
+      let bits = half::f16::from_f32(v).to_bits();
+      t[c * 2..c * 2 + 2].copy_from_slice(&bits.to_le_bytes());
+    }
+  }
+}
+
+fn block_decoder(format: TextureFormat) -> Option<BlockDecoder> {
+  use TextureFormat::*;
+  Some(match format {
+    Bc1RgbaUnorm | Bc1RgbaUnormSrgb => |block, texels| {
+      let mut decoded = [[0; 4]; 16];
+      decode_bc1(block, &mut decoded);
+      write_rgba8(texels, decoded);
+    },
+    Bc2RgbaUnorm | Bc2RgbaUnormSrgb => |block, texels| {
+      let mut decoded = [[0; 4]; 16];
+      decode_bc2(block, &mut decoded);
+      write_rgba8(texels, decoded);
+    },
+    Bc3RgbaUnorm | Bc3RgbaUnormSrgb => |block, texels| {
+      let mut decoded = [[0; 4]; 16];
+      decode_bc3(block, &mut decoded);
+      write_rgba8(texels, decoded);
+    },
+    Bc7RgbaUnorm | Bc7RgbaUnormSrgb => |block, texels| {
+      let mut decoded = [[0; 4]; 16];
+      decode_bc7(block, &mut decoded);
+      write_rgba8(texels, decoded);
+    },
+    Bc4RUnorm => |block, texels| {
+      let mut r = [0; 16];
+      decode_bc4_channel_unorm(block, &mut r);
+      write_rgba8(texels, r.map(|r| [r, 0, 0, 255]));
+    },
+    Bc5RgUnorm => |block, texels| {
+      let mut r = [0; 16];
+      let mut g = [0; 16];
+      decode_bc4_channel_unorm(&block[0..8], &mut r);
+      decode_bc4_channel_unorm(&block[8..16], &mut g);
+      write_rgba8(texels, std::array::from_fn(|i| [r[i], g[i], 0, 255]));
+    },
+    Bc4RSnorm => |block, texels| {
+      let mut r = [0; 16];
+      decode_bc4_channel_snorm(block, &mut r);
+      write_rgba8(texels, r.map(|r| [r as u8, 0, 0, 127]));
+    },
+    Bc5RgSnorm => |block, texels| {
+      let mut r = [0; 16];
+      let mut g = [0; 16];
+      decode_bc4_channel_snorm(&block[0..8], &mut r);
+      decode_bc4_channel_snorm(&block[8..16], &mut g);
+      write_rgba8(
+        texels,
+        std::array::from_fn(|i| [r[i] as u8, g[i] as u8, 0, 127]),
+      );
+    },
+    Bc6hRgbUfloat => |block, texels| decode_bc6h_texels(block, false, texels),
+    Bc6hRgbFloat => |block, texels| decode_bc6h_texels(block, true, texels),
+    Etc2Rgb8Unorm | Etc2Rgb8UnormSrgb => |block, texels| {
+      let mut decoded = [[0; 4]; 16];
+      decode_etc2_rgb(block, false, &mut decoded);
+      write_rgba8(texels, decoded);
+    },
+    Etc2Rgb8A1Unorm | Etc2Rgb8A1UnormSrgb => |block, texels| {
+      let mut decoded = [[0; 4]; 16];
+      decode_etc2_rgb(block, true, &mut decoded);
+      write_rgba8(texels, decoded);
+    },
+    Etc2Rgba8Unorm | Etc2Rgba8UnormSrgb => |block, texels| {
+      let mut decoded = [[0; 4]; 16];
+      decode_etc2_rgba(block, &mut decoded);
+      write_rgba8(texels, decoded);
+    },
+    EacR11Unorm => |block, texels| decode_eac_texels(block, false, false, texels),
+    EacR11Snorm => |block, texels| decode_eac_texels(block, true, false, texels),
+    EacRg11Unorm => |block, texels| decode_eac_texels(block, false, true, texels),
+    EacRg11Snorm => |block, texels| decode_eac_texels(block, true, true, texels),
+    _ => return None,
+  })
+}
+
+fn decode_bc6h_texels(block: &[u8], signed: bool, texels: &mut [[u8; 8]]) {
+  let mut decoded = [[0; 3]; 16];
+  decode_bc6h(block, signed, &mut decoded);
+  let one = half::f16::ONE.to_bits();
+  for (t, [r, g, b]) in texels.iter_mut().zip(decoded) {
+    for (c, bits) in [r, g, b, one].into_iter().enumerate() {
+      t[c * 2..c * 2 + 2].copy_from_slice(&bits.to_le_bytes());
+    }
+  }
+}
+
+fn decode_eac_texels(block: &[u8], signed: bool, two_channel: bool, texels: &mut [[u8; 8]]) {
+  let mut r = [0.; 16];
+  let mut g = [0.; 16];
+  decode_eac_r11(&block[0..8], signed, &mut r);
+  if two_channel {
+    decode_eac_r11(&block[8..16], signed, &mut g);
+  }
+  write_rgba16f(texels, |i| [r[i], g[i], 0., 1.]);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_decode_image() {
+    // 6x6 bc1 image with 2 levels, the base level has 2x2 blocks
+    let red = [0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0];
+    let blue = [0x1f, 0x00, 0x1f, 0x00, 0, 0, 0, 0];
+    let image = GPUBufferImage {
+      data: [red, blue, blue, red].concat(),
+      format: TextureFormat::Bc1RgbaUnorm,
+      size: Size::from_usize_pair_min_one((6, 6)),
+      precomputed_mips: vec![blue.to_vec()],
+    };
+    assert_eq!(image.level_byte_size(0), image.data.len());
+
+    let decoded = decode_block_compressed_image(&image).unwrap();
+    assert_eq!(decoded.format, TextureFormat::Rgba8Unorm);
+    assert_eq!(decoded.data.len(), 6 * 6 * 4);
+    assert_eq!(decoded.precomputed_mips.len(), 1);
+    assert_eq!(decoded.precomputed_mips[0].len(), 3 * 3 * 4);
+
+    let texel = |x: usize, y: usize| &decoded.data[(y * 6 + x) * 4..(y * 6 + x) * 4 + 4];
+    assert_eq!(texel(0, 0), [255, 0, 0, 255]);
+    assert_eq!(texel(5, 0), [0, 0, 255, 255]);
+    assert_eq!(texel(0, 5), [0, 0, 255, 255]);
+    assert_eq!(texel(5, 5), [255, 0, 0, 255]);
+  }
+
+  #[test]
+  fn test_decode_astc_image() {
+    // 8x8 astc 6x6 srgb image, the base level has 2x2 void extent blocks
+    let void_extent = |rgba: [u16; 4]| {
+      let mut block = [0xfc, 0xfd, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff].to_vec();
+      rgba.iter().for_each(|c| block.extend(c.to_le_bytes()));
+      block
+    };
+    let red = void_extent([0xffff, 0, 0, 0xffff]);
+    let blue = void_extent([0, 0, 0xffff, 0xffff]);
+    let format = TextureFormat::Astc {
+      block: wgpu_types::AstcBlock::B6x6,
+      channel: AstcChannel::UnormSrgb,
+    };
+    let image = GPUBufferImage {
+      data: [red.clone(), blue.clone(), blue, red.clone()].concat(),
+      format,
+      size: Size::from_usize_pair_min_one((8, 8)),
+      precomputed_mips: vec![red],
+    };
+
+    let decoded = decode_block_compressed_image(&image).unwrap();
+    assert_eq!(decoded.format, TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(decoded.precomputed_mips[0].len(), 4 * 4 * 4);
+
+    let texel = |x: usize, y: usize| &decoded.data[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4];
+    assert_eq!(texel(5, 5), [255, 0, 0, 255]);
+    assert_eq!(texel(6, 0), [0, 0, 255, 255]);
+    assert_eq!(texel(0, 7), [0, 0, 255, 255]);
+    assert_eq!(texel(7, 7), [255, 0, 0, 255]);
+  }
+}
@@ -0,0 +1,196 @@
+//! The partition tables shared by BC6H and BC7.
+
+/// bit i is the subset of the texel i
+pub const PARTITION_2: [u16; 64] = [
+  0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, //
+  0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000, //
+  0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce, //
+  0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, //
+  0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a, //
+  0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660, //
+  0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, //
+  0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22, //
+];
+
+pub const PARTITION_3: [[u8; 16]; 64] = [
+  [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+  [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+  [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+  [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+  [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+  [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+  [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+  [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+  [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+  [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+  [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+  [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+  [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+  [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+  [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+  [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+  [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+  [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+  [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+  [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+  [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+  [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+  [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+  [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+  [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+  [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+  [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+  [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+  [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+  [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+  [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+  [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+  [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+  [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+  [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+  [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+  [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+  [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+  [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+  [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+  [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+  [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+  [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+  [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+  [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+  [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+  [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+  [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+  [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+  [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+  [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+  [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+  [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+  [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+  [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+  [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+  [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+  [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+  [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+  [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+  [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+  [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+  [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+  [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+/// the anchor index of the second subset in the two subsets partitions
+pub const ANCHOR_2_OF_2: [u8; 64] = [
+  15, 15, 15, 15, 15, 15, 15, 15, //
+  15, 15, 15, 15, 15, 15, 15, 15, //
+  15, 2, 8, 2, 2, 8, 8, 15, //
+  2, 8, 2, 2, 8, 8, 2, 2, //
+  15, 15, 6, 8, 2, 8, 15, 15, //
+  2, 8, 2, 2, 2, 15, 15, 6, //
+  6, 2, 6, 8, 15, 15, 2, 2, //
+  15, 15, 15, 15, 15, 2, 2, 15, //
+];
+
+/// the anchor index of the second subset in the three subsets partitions
+pub const ANCHOR_2_OF_3: [u8; 64] = [
+  3, 3, 15, 15, 8, 3, 15, 15, //
+  8, 8, 6, 6, 6, 5, 3, 3, //
+  3, 3, 8, 15, 3, 3, 6, 10, //
+  5, 8, 8, 6, 8, 5, 15, 15, //
+  8, 15, 3, 5, 6, 10, 8, 15, //
+  15, 3, 15, 5, 15, 15, 15, 15, //
+  3, 15, 5, 5, 5, 8, 5, 10, //
+  5, 10, 8, 13, 15, 12, 3, 3, //
+];
+
+/// the anchor index of the third subset in the three subsets partitions
+pub const ANCHOR_3_OF_3: [u8; 64] = [
+  15, 8, 8, 3, 15, 15, 3, 8, //
+  15, 15, 15, 15, 15, 15, 15, 8, //
+  15, 8, 15, 3, 15, 8, 15, 8, //
+  3, 15, 6, 10, 15, 15, 10, 8, //
+  15, 3, 15, 10, 10, 8, 9, 10, //
+  6, 15, 8, 15, 3, 6, 6, 8, //
+  15, 3, 15, 15, 15, 15, 15, 15, //
+  15, 15, 15, 15, 3, 15, 15, 8, //
+];
+
+/// return the subset of the texel, and if the texel is an anchor(the index is stored with one
+/// less bit)
+pub fn partition_subset(subset_count: usize, partition: usize, texel: usize) -> (usize, bool) {
+  match subset_count {
+    1 => (0, texel == 0),
+    2 => {
+      let subset = ((PARTITION_2[partition] >> texel) & 1) as usize;
+      let anchor = texel == 0 || texel == ANCHOR_2_OF_2[partition] as usize;
+      (subset, anchor)
+    }
+    _ => {
+      let subset = PARTITION_3[partition][texel] as usize;
+      let anchor = texel == 0
+        || texel == ANCHOR_2_OF_3[partition] as usize
+        || texel == ANCHOR_3_OF_3[partition] as usize;
+      (subset, anchor)
+    }
+  }
+}
+
+pub const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+pub const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+pub const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+pub fn weights(index_bits: u32) -> &'static [u32] {
+  match index_bits {
+    2 => &WEIGHTS_2,
+    3 => &WEIGHTS_3,
+    _ => &WEIGHTS_4,
+  }
+}
+
+/// little endian bit reader of a 128 bits block
+pub struct BlockBitReader {
+  bits: u128,
+}
+
+impl BlockBitReader {
+  pub fn new(block: &[u8]) -> Self {
+    Self {
+      bits: u128::from_le_bytes(block[0..16].try_into().unwrap()),
+    }
+  }
+
+  pub fn read(&mut self, count: u32) -> u32 {
+    if count == 0 {
+      return 0;
+    }
+    let v = (self.bits & ((1 << count) - 1)) as u32;
+    self.bits >>= count;
+    v
+  }
+
+  /// read the bits and reverse the order
+  pub fn read_reversed(&mut self, count: u32) -> u32 {
+    let v = self.read(count);
+    v.reverse_bits() >> (32 - count)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_partition_table() {
+    for partition in 0..64 {
+      assert_eq!(PARTITION_2[partition] & 1, 0);
+      assert_eq!(
+        partition_subset(2, partition, ANCHOR_2_OF_2[partition] as usize).0,
+        1
+      );
+
+      assert_eq!(PARTITION_3[partition][0], 0);
+      assert_eq!(PARTITION_3[partition][ANCHOR_2_OF_3[partition] as usize], 1);
+      assert_eq!(PARTITION_3[partition][ANCHOR_3_OF_3[partition] as usize], 2);
+    }
+  }
+}
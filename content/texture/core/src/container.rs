@@ -74,12 +74,59 @@ pub struct GPUBufferImage {
   #[facet(opaque)]
   pub format: TextureFormat,
   pub size: Size,
+  /// the mip levels after the base level, each level has the same layout as the base level.
+  /// if empty, the mipmap will be generated at runtime if required and possible.
+  #[serde(default)]
+  pub precomputed_mips: Vec<Vec<u8>>,
 }
 
 impl GPUBufferImage {
   pub fn bytes_per_row(&self) -> u32 {
-    usize::from(self.size.width) as u32 * self.format.block_copy_size(None).unwrap()
+    self.level_bytes_per_row(0)
   }
+
+  /// the base level is included
+  pub fn mip_level_count(&self) -> usize {
+    self.precomputed_mips.len() + 1
+  }
+
+  pub fn level_data(&self, level: usize) -> &[u8] {
+    if level == 0 {
+      &self.data
+    } else {
+      &self.precomputed_mips[level - 1]
+    }
+  }
+
+  pub fn level_size(&self, level: usize) -> Size {
+    mip_level_size(self.size, level)
+  }
+
+  /// for the block compressed format, the row is the row of blocks
+  pub fn level_bytes_per_row(&self, level: usize) -> u32 {
+    let (block_width, _) = self.format.block_dimensions();
+    let width = self.level_size(level).width_usize() as u32;
+    width.div_ceil(block_width) * self.format.block_copy_size(None).unwrap()
+  }
+
+  pub fn level_rows(&self, level: usize) -> u32 {
+    let (_, block_height) = self.format.block_dimensions();
+    let height = self.level_size(level).height_usize() as u32;
+    height.div_ceil(block_height)
+  }
+
+  pub fn level_byte_size(&self, level: usize) -> usize {
+    self.level_bytes_per_row(level) as usize * self.level_rows(level) as usize
+  }
+}
+
+pub fn mip_level_size(size: Size, level: usize) -> Size {
+  let (width, height) = size.into_usize();
+  let shift = |v: usize| {
+    v.checked_shr(level.try_into().unwrap_or(u32::MAX))
+      .unwrap_or(0)
+  };
+  Size::from_usize_pair_min_one((shift(width), shift(height)))
 }
 
 pub fn create_padding_buffer(
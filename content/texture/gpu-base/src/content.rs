@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use crate::*;
 
 pub fn create_gpu_sampler(cx: &GPU, s: &TextureSampler) -> GPUSamplerView {
@@ -5,22 +7,84 @@ pub fn create_gpu_sampler(cx: &GPU, s: &TextureSampler) -> GPUSamplerView {
   gpu_sampler.create_default_view()
 }
 
+/// The block compressed image is decoded on the CPU if the device lacks the format or the
+/// base size is not aligned to the block size(which is not allowed by webgpu).
+pub fn prepare_gpu_buffer_image<'a>(
+  cx: &GPU,
+  image: &'a GPUBufferImage,
+) -> Cow<'a, GPUBufferImage> {
+  let format = image.format;
+  if !format.is_compressed() {
+    return Cow::Borrowed(image);
+  }
+
+  let (block_width, block_height) = format.block_dimensions();
+  let (width, height) = image.size.into_u32();
+  let is_aligned = width % block_width == 0 && height % block_height == 0;
+  let is_supported = cx
+    .info()
+    .supported_features
+    .contains(format.required_features());
+
+  if is_aligned && is_supported {
+    return Cow::Borrowed(image);
+  }
+
+  if let Some(decoded) = decode_block_compressed_image(image) {
+    log::info!("decode block compressed texture {format:?} on cpu");
+    Cow::Owned(decoded)
+  } else {
+    log::error!(
+      "block compressed texture {format:?} is not supported by the device or the cpu decoder"
+    );
+    Cow::Owned(GPUBufferImage {
+      data: vec![255, 0, 255, 255],
+      format: TextureFormat::Rgba8Unorm,
+      size: Size::from_u32_pair_min_one((1, 1)),
+      precomputed_mips: Vec::new(),
+    })
+  }
+}
+
+fn upload_all_levels(cx: &GPU, texture: &GPU2DTexture, image: &GPUBufferImage) {
+  for level in 0..image.mip_level_count() {
+    let source = GPUBufferImageLevelForeignImpl {
+      inner: image,
+      level,
+    };
+    texture.upload(&cx.queue, &source, level);
+  }
+}
+
 pub fn create_gpu_texture2d(cx: &GPU, texture: &GPUBufferImage) -> GPU2DTextureView {
-  let texture = GPUBufferImageForeignImpl { inner: texture };
+  let texture = prepare_gpu_buffer_image(cx, texture);
+  let source = GPUBufferImageForeignImpl { inner: &texture };
 
-  let desc = texture.create_tex2d_desc(MipLevelCount::BySize, cx.info().downgrade_info.flags);
+  // the compressed texture can not generate mipmap at runtime
+  let mip = if texture.precomputed_mips.is_empty() && !texture.format.is_compressed() {
+    MipLevelCount::BySize
+  } else {
+    MipLevelCount::Fixed(texture.mip_level_count())
+  };
+  let desc = source.create_tex2d_desc(mip, cx.info().downgrade_info.flags);
   let gpu_texture = GPUTexture::create(desc, &cx.device);
   let gpu_texture: GPU2DTexture = gpu_texture.try_into().unwrap();
-  let gpu_texture = gpu_texture.upload_into(&cx.queue, &texture, 0);
+  upload_all_levels(cx, &gpu_texture, &texture);
 
   gpu_texture.create_default_view().try_into().unwrap()
 }
 
+/// if the image has precomputed mips, they are used directly, otherwise the mipmap is
+/// generated on the GPU.
 pub fn create_gpu_texture2d_with_mipmap(
   cx: &GPU,
   encoder: &mut GPUCommandEncoder,
   texture: &GPUBufferImage,
 ) -> GPU2DTextureView {
+  if !texture.precomputed_mips.is_empty() || texture.format.is_compressed() {
+    return create_gpu_texture2d(cx, texture);
+  }
+
   let texture = GPUBufferImageForeignImpl { inner: texture };
 
   let desc = texture.create_tex2d_desc(MipLevelCount::BySize, cx.info().downgrade_info.flags);
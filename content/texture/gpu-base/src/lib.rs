@@ -1,5 +1,6 @@
 use rendiation_algebra::*;
 use rendiation_shader_api::*;
+use rendiation_texture_block_compression::*;
 use rendiation_texture_core::*;
 use rendiation_webgpu::*;
 
@@ -69,3 +70,35 @@ impl WebGPU2DTextureSource for GPUBufferImageForeignImpl<'_> {
     self.inner.size
   }
 }
+
+/// the mip level of the GPUBufferImage as the upload source
+pub struct GPUBufferImageLevelForeignImpl<'a> {
+  pub inner: &'a GPUBufferImage,
+  pub level: usize,
+}
+
+impl WebGPU2DTextureSource for GPUBufferImageLevelForeignImpl<'_> {
+  fn format(&self) -> TextureFormat {
+    self.inner.format
+  }
+
+  fn as_bytes(&self) -> &[u8] {
+    self.inner.level_data(self.level)
+  }
+
+  fn size(&self) -> Size {
+    self.inner.level_size(self.level)
+  }
+
+  /// the copy extent of the block compressed format should be the physical size, which is
+  /// rounded up to the block size
+  fn gpu_size(&self) -> Extent3d {
+    let (block_width, block_height) = self.inner.format.block_dimensions();
+    let (width, height) = self.size().into_u32();
+    Extent3d {
+      width: width.next_multiple_of(block_width),
+      height: height.next_multiple_of(block_height),
+      depth_or_array_layers: 1,
+    }
+  }
+}
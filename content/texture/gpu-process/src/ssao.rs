@@ -78,6 +78,7 @@ impl SSAO {
       data: noise_pixels,
       format: TextureFormat::Rgba8Unorm,
       size: Size::from_u32_pair_min_one((NOISE_TEX_SIZE, NOISE_TEX_SIZE)),
+      precomputed_mips: Vec::new(),
     };
     let noise_texture = create_gpu_texture2d(gpu, &noise_image);
 
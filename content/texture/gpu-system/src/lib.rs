@@ -18,6 +18,8 @@ use std::sync::Arc;
 
 pub use pool::*;
 use query::*;
+mod virtual_texture;
+pub use virtual_texture::*;
 
 pub trait AbstractIndirectGPUTextureSystem {
   fn bind_system_self(&self, collector: &mut BindingBuilder);
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 
 use rendiation_shader_library::color::shader_srgb_to_linear_convert_fn;
+use rendiation_texture_block_compression::decode_block_compressed_image;
 use rendiation_texture_core::*;
 pub use rendiation_texture_packer::pack_2d_to_3d::MultiLayerTexturePackerConfig;
 use rendiation_texture_packer::{pack_2d_to_2d::PackResult2d, pack_2d_to_3d::*};
@@ -73,7 +74,7 @@ const CLAMP_TO_EDGE: u32 = 0;
 const REPEAT: u32 = 1;
 const MIRRORED_REPEAT: u32 = 2;
 
-const LINEAR: u32 = 1;
+pub(crate) const LINEAR: u32 = 1;
 const NEAREST: u32 = 0;
 
 fn map_address(mode: rendiation_texture_core::AddressMode) -> u32 {
@@ -262,31 +263,40 @@ fn srgb_to_linear_convert_per_channel(c: f32) -> f32 {
 }
 
 fn normalize_format(tex: &GPUBufferImage, normalize_srgb: bool) -> Option<Cow<'_, GPUBufferImage>> {
+  // the atlas is not block compressed, so the compressed texture is decoded first
+  if tex.format.is_compressed() {
+    let Some(decoded) = decode_block_compressed_image(tex) else {
+      log::warn!(
+        "texture pool not support decode compressed format {:?}",
+        tex.format
+      );
+      return None;
+    };
+    let normalized = normalize_format(&decoded, normalize_srgb)?.into_owned();
+    return Cow::<'_, GPUBufferImage>::Owned(normalized).into();
+  }
+
   if tex.format == TEXTURE_POOL_FORMAT {
-    return Cow::Borrowed(tex).into();
+    return strip_incomplete_mips(Cow::Borrowed(tex)).into();
   }
 
   if !normalize_srgb && tex.format.remove_srgb_suffix() == TEXTURE_POOL_FORMAT.remove_srgb_suffix()
   {
-    return Cow::Borrowed(tex).into();
+    return strip_incomplete_mips(Cow::Borrowed(tex)).into();
   }
 
   log::warn!("texture pool try normalize texture");
 
-  let data = match tex.format {
-    TextureFormat::Rgba8UnormSrgb => {
-      let data: &[u8] = cast_slice(&tex.data);
+  let convert: fn(&[u8]) -> Vec<u8> = match tex.format {
+    TextureFormat::Rgba8UnormSrgb => |data| {
       data
         .iter()
         .map(|v| *v as f32 / 255.)
         .map(srgb_to_linear_convert_per_channel)
         .map(|v| (v * 255.) as u8)
         .collect()
-    }
-    TextureFormat::R8Unorm => {
-      let data: &[u8] = cast_slice(&tex.data);
-      data.iter().flat_map(|v| [*v, 0, 0, 0]).collect()
-    }
+    },
+    TextureFormat::R8Unorm => |data| data.iter().flat_map(|v| [*v, 0, 0, 0]).collect(),
     _ => {
       log::warn!(
         "texture pool not support normalize format {:?} to pool format {:?}",
@@ -297,12 +307,26 @@ fn normalize_format(tex: &GPUBufferImage, normalize_srgb: bool) -> Option<Cow<'_
     }
   };
 
-  Cow::<'_, GPUBufferImage>::Owned(GPUBufferImage {
-    data,
+  let normalized = GPUBufferImage {
+    data: convert(&tex.data),
     format: TEXTURE_POOL_FORMAT,
     size: tex.size,
-  })
-  .into()
+    precomputed_mips: tex.precomputed_mips.iter().map(|m| convert(m)).collect(),
+  };
+  strip_incomplete_mips(Cow::Owned(normalized)).into()
+}
+
+/// the atlas copies all mip levels by the smaller side of the texture, if the precomputed
+/// mips are not enough, they are dropped and the mipmap is generated at runtime
+fn strip_incomplete_mips(tex: Cow<'_, GPUBufferImage>) -> Cow<'_, GPUBufferImage> {
+  let (width, height) = tex.size.into_u32();
+  let required = (32 - width.min(height).leading_zeros()) as usize;
+  if tex.precomputed_mips.is_empty() || tex.mip_level_count() >= required {
+    return tex;
+  }
+  let mut tex = tex.into_owned();
+  tex.precomputed_mips.clear();
+  Cow::Owned(tex)
 }
 
 fn copy_tex(
@@ -586,7 +610,7 @@ fn texture_pool_sample_impl(
 }
 
 #[shader_fn]
-fn shader_should_use_linear_filter(
+pub(crate) fn shader_should_use_linear_filter(
   mag_filter: Node<u32>,
   min_filter: Node<u32>,
   level: Node<f32>,
@@ -670,7 +694,7 @@ fn sample_texture_impl(
 }
 
 #[shader_fn]
-fn shader_address_mode(mode: Node<u32>, uv: Node<f32>) -> Node<f32> {
+pub(crate) fn shader_address_mode(mode: Node<u32>, uv: Node<f32>) -> Node<f32> {
   let result = uv.make_local_var();
   switch_by(mode)
     .case(CLAMP_TO_EDGE, || result.store(uv.max(0.0).min(1.0)))
@@ -735,7 +759,7 @@ fn calculate_mip_level_impl(uv: Node<Vec2<f32>>) -> Node<f32> {
 
 // https://bgolus.medium.com/distinctive-derivative-differences-cce38d36797b
 #[shader_fn]
-fn calculate_mip_level(uv: Node<Vec2<f32>>, size: Node<Vec2<f32>>) -> Node<f32> {
+pub(crate) fn calculate_mip_level(uv: Node<Vec2<f32>>, size: Node<Vec2<f32>>) -> Node<f32> {
   let uv_00 = uv;
   let uv_10: Node<Vec2<f32>> = ((uv.x() - val(0.5)).fract(), uv.y()).into();
   let uv_01: Node<Vec2<f32>> = (uv.x(), (uv.y() - val(0.5)).fract()).into();
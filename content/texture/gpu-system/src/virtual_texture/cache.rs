@@ -0,0 +1,170 @@
+#[allow(clippy::disallowed_types)]
+use std::collections::BTreeSet;
+
+use fast_hash_collection::FastHashMap;
+
+use crate::*;
+
+#[derive(Clone, Copy, Default)]
+struct PhysicalPageSlot {
+  page: Option<VirtualTexturePageId>,
+  last_used_frame: u64,
+  pinned: bool,
+}
+
+/// The physical page slots with the least recently used eviction.
+///
+/// The slot index is row major in the page atlas grid. The pinned slots are never evicted,
+/// they are used for the coarsest level of each virtual texture so there is always something
+/// to sample.
+pub struct VirtualTexturePhysicalPageCache {
+  slots: Vec<PhysicalPageSlot>,
+  page_to_slot: FastHashMap<VirtualTexturePageId, u32>,
+  /// (last used frame, slot) of all evictable slots, the free slots are with the frame zero
+  #[allow(clippy::disallowed_types)]
+  lru: BTreeSet<(u64, u32)>,
+}
+
+impl VirtualTexturePhysicalPageCache {
+  pub fn new(slot_count: u32) -> Self {
+    Self {
+      slots: vec![Default::default(); slot_count as usize],
+      page_to_slot: Default::default(),
+      lru: (0..slot_count).map(|slot| (0, slot)).collect(),
+    }
+  }
+
+  pub fn slot_count(&self) -> u32 {
+    self.slots.len() as u32
+  }
+
+  pub fn resident_page_count(&self) -> usize {
+    self.page_to_slot.len()
+  }
+
+  pub fn get(&self, page: &VirtualTexturePageId) -> Option<u32> {
+    self.page_to_slot.get(page).copied()
+  }
+
+  /// mark the page used in this frame, return if the page is resident
+  pub fn touch(&mut self, page: &VirtualTexturePageId, frame: u64) -> bool {
+    let Some(&slot) = self.page_to_slot.get(page) else {
+      return false;
+    };
+    let s = &mut self.slots[slot as usize];
+    if !s.pinned && s.last_used_frame != frame {
+      self.lru.remove(&(s.last_used_frame, slot));
+      self.lru.insert((frame, slot));
+    }
+    s.last_used_frame = frame;
+    true
+  }
+
+  /// Allocate a slot for the page, the least recently used page is evicted if there is no
+  /// free slot. The pages used in current frame will not be evicted, so if all slots are
+  /// used in current frame the allocation fails.
+  ///
+  /// return the slot and the evicted page
+  pub fn allocate(
+    &mut self,
+    page: VirtualTexturePageId,
+    frame: u64,
+    pinned: bool,
+  ) -> Option<(u32, Option<VirtualTexturePageId>)> {
+    if let Some(slot) = self.get(&page) {
+      return Some((slot, None));
+    }
+
+    let &(last_used_frame, slot) = self.lru.first()?;
+    let s = &mut self.slots[slot as usize];
+    if s.page.is_some() && last_used_frame >= frame {
+      return None;
+    }
+    self.lru.pop_first();
+
+    let evicted = s.page.take();
+    if let Some(evicted) = &evicted {
+      self.page_to_slot.remove(evicted);
+    }
+
+    *s = PhysicalPageSlot {
+      page: Some(page),
+      last_used_frame: frame,
+      pinned,
+    };
+    if !pinned {
+      self.lru.insert((frame, slot));
+    }
+    self.page_to_slot.insert(page, slot);
+
+    Some((slot, evicted))
+  }
+
+  /// release all pages of the texture, return the released pages
+  pub fn release_texture(&mut self, texture: Texture2DHandle) -> Vec<VirtualTexturePageId> {
+    let pages: Vec<_> = self
+      .page_to_slot
+      .keys()
+      .filter(|page| page.texture == texture)
+      .copied()
+      .collect();
+
+    for page in &pages {
+      let slot = self.page_to_slot.remove(page).unwrap();
+      let s = &mut self.slots[slot as usize];
+      if !s.pinned {
+        self.lru.remove(&(s.last_used_frame, slot));
+      }
+      *s = Default::default();
+      self.lru.insert((0, slot));
+    }
+    pages
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn page(x: u32) -> VirtualTexturePageId {
+    VirtualTexturePageId {
+      texture: 0,
+      level: 0,
+      x,
+      y: 0,
+    }
+  }
+
+  #[test]
+  fn test_lru_eviction() {
+    let mut cache = VirtualTexturePhysicalPageCache::new(3);
+    let pinned = VirtualTexturePageId {
+      level: 1,
+      ..page(0)
+    };
+
+    assert_eq!(cache.allocate(pinned, 1, true), Some((0, None)));
+    assert_eq!(cache.allocate(page(0), 1, false), Some((1, None)));
+    assert_eq!(cache.allocate(page(1), 2, false), Some((2, None)));
+
+    // all slots are used in current frame
+    assert!(cache.touch(&page(0), 2));
+    assert_eq!(cache.allocate(page(2), 2, false), None);
+
+    // page 0 is the least recently used one, the pinned page is never evicted
+    assert_eq!(cache.allocate(page(2), 3, false), Some((1, Some(page(0)))));
+    assert!(cache.touch(&page(1), 4));
+    assert!(!cache.touch(&page(0), 4));
+    assert_eq!(cache.allocate(page(3), 4, false), Some((1, Some(page(2)))));
+    assert_eq!(cache.get(&pinned), Some(0));
+
+    let released = cache.release_texture(0);
+    assert_eq!(released.len(), 3);
+    assert_eq!(cache.resident_page_count(), 0);
+    let other = VirtualTexturePageId {
+      texture: 1,
+      ..page(0)
+    };
+    assert!(cache.allocate(other, 4, false).is_some());
+  }
+}
@@ -0,0 +1,726 @@
+use std::{pin::Pin, task::Context, task::Poll};
+
+use fast_hash_collection::FastHashMap;
+use futures::FutureExt;
+use parking_lot::RwLock;
+use rendiation_shader_library::color::shader_srgb_to_linear_convert_fn;
+use rendiation_texture_core::{FilterMode, GPUBufferImage, TextureSampler};
+use rendiation_uri_streaming::LoaderFunction;
+use serde::*;
+
+use crate::*;
+
+mod cache;
+mod page;
+mod producer;
+mod streaming;
+
+pub use cache::*;
+pub use page::*;
+pub use producer::*;
+pub use streaming::*;
+
+pub const VIRTUAL_TEXTURE_ATLAS_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Copy)]
+#[serde(default)]
+pub struct VirtualTextureSystemInit {
+  /// the texel count of the page side, not include the border
+  pub page_size: u32,
+  /// the texel count of the border on each side of the page, the border is required for the
+  /// bilinear filtering at the page edge
+  pub page_border: u32,
+  /// the page atlas is a square grid of the physical pages, this decides the memory budget
+  pub physical_page_count_per_side: u32,
+  /// the page tables are linear allocated into the rows of the indirection texture
+  pub indirection_texture_width: u32,
+  /// the feedback buffer is a hash table of the requested pages, the collision is fine
+  /// because the missed page will be requested again in later frames.
+  pub feedback_slot_count: u32,
+  pub init_sampler_count_capacity: u32,
+  /// the max page bytes in loading at the same time
+  pub loading_bandwidth: u64,
+  /// the max bytes of the Rgba8 mip chains cached in the host memory to produce the pages
+  pub source_cache_budget: u64,
+}
+
+impl Default for VirtualTextureSystemInit {
+  fn default() -> Self {
+    Self {
+      page_size: 128,
+      page_border: 4,
+      physical_page_count_per_side: 32,
+      indirection_texture_width: 1024,
+      feedback_slot_count: 16384,
+      init_sampler_count_capacity: 128,
+      loading_bandwidth: 16 * 1024 * 1024,
+      source_cache_budget: 256 * 1024 * 1024,
+    }
+  }
+}
+
+impl VirtualTextureSystemInit {
+  pub fn atlas_size(&self) -> u32 {
+    self.physical_page_count_per_side * (self.page_size + self.page_border * 2)
+  }
+}
+
+#[repr(C)]
+#[std430_layout]
+#[derive(Clone, Copy, Default, ShaderStruct, Debug, PartialEq)]
+pub struct VirtualTextureMeta {
+  pub size: Vec2<f32>,
+  /// u32::MAX means the texture is not registered
+  pub page_table_offset: u32,
+  pub level_count: u32,
+  pub require_srgb_to_linear_convert: Bool,
+}
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Clone, Copy, Default, ShaderStruct, Debug, PartialEq)]
+pub struct VirtualTextureShaderInfo {
+  pub page_size: f32,
+  pub page_border: f32,
+  pub atlas_size: f32,
+  pub indirection_width: u32,
+  pub feedback_slot_count: u32,
+}
+
+/// The gpu resources of the virtual texture system, see [VirtualTextureStreaming] for the
+/// host side logic.
+///
+/// The sampling shader writes the required pages into the feedback buffer, the feedback is
+/// read back to the host and consumed by the streaming to load the pages, then the loaded
+/// pages and the page table changes are uploaded here.
+pub struct VirtualTextureGPUResource {
+  config: VirtualTextureSystemInit,
+  atlas: GPU2DTextureView,
+  atlas_sampler: GPUSamplerView,
+  indirection: GPUTypedTextureView<TextureDimension2, u32>,
+  meta: StorageBufferReadonlyDataView<[VirtualTextureMeta]>,
+  info: UniformBufferDataView<VirtualTextureShaderInfo>,
+  feedback: StorageBufferDataView<[Vec2<u32>]>,
+  feedback_reading: Option<Pin<ReadBufferFromStagingBuffer>>,
+}
+
+impl VirtualTextureGPUResource {
+  pub fn new(gpu: &GPU, config: VirtualTextureSystemInit) -> Self {
+    let atlas_size = config.atlas_size();
+    let atlas = GPUTexture::create(
+      TextureDescriptor {
+        label: "virtual-texture-page-atlas".into(),
+        size: Extent3d {
+          width: atlas_size,
+          height: atlas_size,
+          depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: VIRTUAL_TEXTURE_ATLAS_FORMAT,
+        view_formats: &[],
+        usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+      },
+      &gpu.device,
+    )
+    .create_default_view()
+    .try_into()
+    .unwrap();
+
+    let atlas_sampler = create_gpu_sampler(
+      gpu,
+      &TextureSampler {
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..Default::default()
+      },
+    );
+
+    let info = VirtualTextureShaderInfo {
+      page_size: config.page_size as f32,
+      page_border: config.page_border as f32,
+      atlas_size: atlas_size as f32,
+      indirection_width: config.indirection_texture_width,
+      feedback_slot_count: config.feedback_slot_count,
+      ..Zeroable::zeroed()
+    };
+
+    Self {
+      config,
+      atlas,
+      atlas_sampler,
+      indirection: create_indirection_texture(gpu, config.indirection_texture_width, 1),
+      meta: create_gpu_readonly_storage(
+        [VirtualTextureMeta::default()].as_slice(),
+        &gpu.device,
+        "virtual texture meta",
+      ),
+      info: create_uniform(info, &gpu.device, "virtual texture info"),
+      feedback: create_gpu_read_write_storage(
+        ZeroedArrayByArrayLength(config.feedback_slot_count as usize),
+        &gpu.device,
+        "virtual texture feedback",
+      ),
+      feedback_reading: None,
+    }
+  }
+
+  /// upload the loaded pages, the page table and the metadata changes
+  pub fn update(&mut self, gpu: &GPU, streaming: &mut VirtualTextureStreaming) {
+    let padded = self.config.page_size + self.config.page_border * 2;
+    for (slot, page) in streaming.pending_uploads.drain(..) {
+      let per_side = self.config.physical_page_count_per_side;
+      let (x, y) = (slot % per_side, slot / per_side);
+      gpu.queue.write_texture(
+        TexelCopyTextureInfo {
+          texture: self.atlas.resource.gpu_resource(),
+          mip_level: 0,
+          origin: Origin3d {
+            x: x * padded,
+            y: y * padded,
+            z: 0,
+          },
+          aspect: TextureAspect::All,
+        },
+        &page.data,
+        TexelCopyBufferLayout {
+          offset: 0,
+          bytes_per_row: Some(padded * 4),
+          rows_per_image: None,
+        },
+        Extent3d {
+          width: padded,
+          height: padded,
+          depth_or_array_layers: 1,
+        },
+      );
+    }
+
+    self.update_indirection(gpu, &mut streaming.page_table);
+
+    if std::mem::take(&mut streaming.meta_changed) {
+      let count = streaming.max_texture_handle().map(|h| h + 1).unwrap_or(1);
+      let metas: Vec<_> = (0..count).map(|h| streaming.texture_meta(h)).collect();
+      if self.meta.item_count() < count {
+        self.meta =
+          create_gpu_readonly_storage(metas.as_slice(), &gpu.device, "virtual texture meta");
+      } else {
+        self
+          .meta
+          .write_at(0, bytemuck::cast_slice(&metas), &gpu.queue);
+      }
+    }
+  }
+
+  fn update_indirection(&mut self, gpu: &GPU, table: &mut VirtualTexturePageTable) {
+    let width = self.config.indirection_texture_width;
+    let required_height = (table.entries.len() as u32).div_ceil(width).max(1);
+    let current_height = self.indirection.resource.desc.size.height;
+
+    let rows: Vec<u32> = if required_height > current_height {
+      let height = required_height.next_power_of_two();
+      self.indirection = create_indirection_texture(gpu, width, height);
+      table.dirty_entries.clear();
+      (0..required_height).collect()
+    } else {
+      let mut rows: Vec<_> = table.dirty_entries.drain().map(|e| e / width).collect();
+      rows.sort_unstable();
+      rows.dedup();
+      rows
+    };
+
+    for row in rows {
+      let start = (row * width) as usize;
+      let end = (start + width as usize).min(table.entries.len());
+      let mut data = table.entries[start..end].to_vec();
+      data.resize(width as usize, 0);
+      gpu.queue.write_texture(
+        TexelCopyTextureInfo {
+          texture: self.indirection.resource.gpu_resource(),
+          mip_level: 0,
+          origin: Origin3d { x: 0, y: row, z: 0 },
+          aspect: TextureAspect::All,
+        },
+        bytemuck::cast_slice(&data),
+        TexelCopyBufferLayout {
+          offset: 0,
+          bytes_per_row: None,
+          rows_per_image: None,
+        },
+        Extent3d {
+          width,
+          height: 1,
+          depth_or_array_layers: 1,
+        },
+      );
+    }
+  }
+
+  /// Poll the previous feedback reading, and dispatch the next reading if no reading is in
+  /// flight. The feedback buffer is cleared after copied, so the feedback of the frames
+  /// between two readings are accumulated.
+  pub fn poll_feedback(
+    &mut self,
+    gpu: &GPU,
+    encoder: &mut GPUCommandEncoder,
+    cx: &mut Context,
+  ) -> Option<Vec<Vec2<u32>>> {
+    let mut result = None;
+    if let Some(reading) = &mut self.feedback_reading
+      && let Poll::Ready(r) = reading.as_mut().poll(cx)
+    {
+      self.feedback_reading = None;
+      match r {
+        Ok(buffer) => {
+          result = <[Vec2<u32>]>::from_bytes_into_boxed(&buffer.read_raw())
+            .into_vec()
+            .into()
+        }
+        Err(e) => log::error!("failed to read virtual texture feedback: {e:?}"),
+      }
+    }
+
+    if self.feedback_reading.is_none() {
+      let reading = encoder.read_buffer(&gpu.device, &self.feedback.gpu);
+      self.feedback_reading = Some(Box::into_pin(reading));
+      encoder.clear_buffer(self.feedback.buffer.gpu(), 0, None);
+    }
+
+    result
+  }
+
+  pub fn create_system(
+    &self,
+    samplers: AbstractReadonlyStorageBuffer<[TextureSamplerShaderInfo]>,
+  ) -> VirtualTextureSystem {
+    VirtualTextureSystem {
+      atlas: self.atlas.clone(),
+      atlas_sampler: self.atlas_sampler.clone(),
+      indirection: self.indirection.clone(),
+      meta: self.meta.clone(),
+      info: self.info.clone(),
+      feedback: self.feedback.clone(),
+      samplers,
+    }
+  }
+}
+
+/// The virtual texture system that produces the pages from the [VirtualTextureSource]s, see
+/// [VirtualTexturePageProducer] for the host memory usage.
+pub struct StreamingVirtualTexture {
+  streaming: VirtualTextureStreaming,
+  resource: VirtualTextureGPUResource,
+  producer: Arc<RwLock<VirtualTexturePageProducer>>,
+  loader: Box<LoaderFunction<VirtualTexturePageId, VirtualTexturePageData>>,
+  /// the size and the format of the texture are known after its source is loaded
+  registering: FastHashMap<Texture2DHandle, ImageSourceLoading>,
+}
+
+impl StreamingVirtualTexture {
+  pub fn new(
+    gpu: &GPU,
+    config: VirtualTextureSystemInit,
+    uri_loader: Box<LoaderFunction<Arc<String>, Arc<GPUBufferImage>>>,
+  ) -> Self {
+    let producer = VirtualTexturePageProducer::new(config.source_cache_budget, uri_loader);
+    let producer = Arc::new(RwLock::new(producer));
+    Self {
+      streaming: VirtualTextureStreaming::new(config),
+      resource: VirtualTextureGPUResource::new(gpu, config),
+      loader: create_virtual_texture_page_loader(
+        producer.clone(),
+        config.page_size,
+        config.page_border,
+      ),
+      producer,
+      registering: Default::default(),
+    }
+  }
+
+  /// the none source means the texture is removed
+  pub fn set_texture(&mut self, texture: Texture2DHandle, source: Option<VirtualTextureSource>) {
+    self.streaming.remove_texture(texture);
+    self.registering.remove(&texture);
+    let mut producer = self.producer.write();
+    producer.set_source(texture, source);
+    if let Some(registering) = producer.request_image_source(texture) {
+      self.registering.insert(texture, registering);
+    }
+  }
+
+  /// consume the feedback, load the pages and upload the changes, should be called once per
+  /// frame before the rendering.
+  pub fn update(&mut self, gpu: &GPU, encoder: &mut GPUCommandEncoder) {
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+    self.registering.retain(|texture, registering| {
+      let Poll::Ready(source) = registering.poll_unpin(&mut cx) else {
+        return true;
+      };
+      if let Some(source) = source {
+        self
+          .streaming
+          .add_texture(*texture, source.size(), source.is_srgb());
+      } else {
+        log::warn!("failed to load the source of virtual texture {texture}");
+      }
+      false
+    });
+
+    if let Some(feedback) = self.resource.poll_feedback(gpu, encoder, &mut cx) {
+      self.streaming.process_feedback(&feedback);
+    }
+    self.streaming.poll_loading(&mut cx, &mut self.loader);
+    self.resource.update(gpu, &mut self.streaming);
+  }
+
+  pub fn streaming(&self) -> &VirtualTextureStreaming {
+    &self.streaming
+  }
+
+  pub fn producer(&self) -> &Arc<RwLock<VirtualTexturePageProducer>> {
+    &self.producer
+  }
+
+  pub fn create_system(
+    &self,
+    samplers: AbstractReadonlyStorageBuffer<[TextureSamplerShaderInfo]>,
+  ) -> VirtualTextureSystem {
+    self.resource.create_system(samplers)
+  }
+}
+
+fn create_indirection_texture(
+  gpu: &GPU,
+  width: u32,
+  height: u32,
+) -> GPUTypedTextureView<TextureDimension2, u32> {
+  GPUTexture::create(
+    TextureDescriptor {
+      label: "virtual-texture-indirection".into(),
+      size: Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: TextureDimension::D2,
+      format: TextureFormat::R32Uint,
+      view_formats: &[],
+      usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+    },
+    &gpu.device,
+  )
+  .create_default_view()
+  .try_into()
+  .unwrap()
+}
+
+/// The texture system that samples the virtual textures through the indirection texture.
+#[derive(Clone)]
+pub struct VirtualTextureSystem {
+  pub atlas: GPU2DTextureView,
+  pub atlas_sampler: GPUSamplerView,
+  pub indirection: GPUTypedTextureView<TextureDimension2, u32>,
+  pub meta: StorageBufferReadonlyDataView<[VirtualTextureMeta]>,
+  pub info: UniformBufferDataView<VirtualTextureShaderInfo>,
+  pub feedback: StorageBufferDataView<[Vec2<u32>]>,
+  pub samplers: AbstractReadonlyStorageBuffer<[TextureSamplerShaderInfo]>,
+}
+
+both!(VirtualTextureAtlasInShader, ShaderBinding<ShaderTexture2D>);
+both!(
+  VirtualTextureAtlasSamplerInShader,
+  ShaderBinding<ShaderSampler>
+);
+both!(
+  VirtualTextureIndirectionInShader,
+  ShaderBinding<ShaderTexture2DUint>
+);
+pub struct VirtualTextureMetaInShader(pub ShaderReadonlyPtrOf<[VirtualTextureMeta]>);
+pub struct VirtualTextureInfoInShader(pub ShaderReadonlyPtrOf<VirtualTextureShaderInfo>);
+pub struct VirtualTextureFeedbackInShader(pub ShaderPtrOf<[Vec2<u32>]>);
+
+impl AbstractIndirectGPUTextureSystem for VirtualTextureSystem {
+  fn bind_system_self(&self, collector: &mut BindingBuilder) {
+    collector.bind(&self.atlas);
+    collector.bind(&self.atlas_sampler);
+    collector.bind(&self.indirection);
+    collector.bind(&self.meta);
+    collector.bind(&self.info);
+    collector.bind(&self.samplers);
+    collector.bind(&self.feedback);
+  }
+
+  fn register_system_self(&self, builder: &mut ShaderRenderPipelineBuilder) {
+    BindingPreparer::new(&self.atlas).using_graphics_pair(builder, |r, atlas| {
+      r.register_typed_both_stage::<VirtualTextureAtlasInShader>(*atlas);
+    });
+    BindingPreparer::new(&self.atlas_sampler).using_graphics_pair(builder, |r, sampler| {
+      r.register_typed_both_stage::<VirtualTextureAtlasSamplerInShader>(*sampler);
+    });
+    BindingPreparer::new(&self.indirection).using_graphics_pair(builder, |r, indirection| {
+      r.register_typed_both_stage::<VirtualTextureIndirectionInShader>(*indirection);
+    });
+    BindingPreparer::new(&self.meta).using_graphics_pair(builder, |r, meta| {
+      r.any_map.register(VirtualTextureMetaInShader(meta.clone()));
+    });
+    BindingPreparer::new(&self.info).using_graphics_pair(builder, |r, info| {
+      r.any_map.register(VirtualTextureInfoInShader(info.clone()));
+    });
+    BindingPreparer::new(&self.samplers).using_graphics_pair(builder, |r, samplers| {
+      r.any_map.register(SamplerPoolInShader(samplers.clone()));
+    });
+    // the feedback is only written in the fragment stage, and the writable storage buffer is
+    // not allowed in the vertex stage.
+    BindingPreparer::new(&self.feedback).using_fragment(builder, |r, feedback| {
+      r.any_map
+        .register(VirtualTextureFeedbackInShader(feedback.clone()));
+    });
+  }
+
+  fn register_system_self_for_compute(
+    &self,
+    builder: &mut ShaderBindGroupBuilder,
+    reg: &mut SemanticRegistry,
+  ) {
+    let atlas = builder.bind_by(&self.atlas);
+    reg.register_typed_both_stage::<VirtualTextureAtlasInShader>(atlas);
+    let sampler = builder.bind_by(&self.atlas_sampler);
+    reg.register_typed_both_stage::<VirtualTextureAtlasSamplerInShader>(sampler);
+    let indirection = builder.bind_by(&self.indirection);
+    reg.register_typed_both_stage::<VirtualTextureIndirectionInShader>(indirection);
+    let meta = builder.bind_by(&self.meta);
+    reg.any_map.register(VirtualTextureMetaInShader(meta));
+    let info = builder.bind_by(&self.info);
+    reg.any_map.register(VirtualTextureInfoInShader(info));
+    let samplers = builder.bind_by(&self.samplers);
+    reg.any_map.register(SamplerPoolInShader(samplers));
+    let feedback = builder.bind_by(&self.feedback);
+    reg
+      .any_map
+      .register(VirtualTextureFeedbackInShader(feedback));
+  }
+
+  fn compute_base_level(
+    &self,
+    reg: &SemanticRegistry,
+    uv: Node<Vec2<f32>>,
+    shader_texture_handle: Node<Texture2DHandle>,
+    _shader_sampler_handle: Node<SamplerHandle>,
+  ) -> Node<f32> {
+    if get_current_stage() == Some(ShaderStage::Fragment) {
+      let metas = reg.any_map.get::<VirtualTextureMetaInShader>().unwrap();
+      let size = metas.0.index(shader_texture_handle).size().load();
+      calculate_mip_level_fn(uv, size)
+    } else {
+      val(0.)
+    }
+  }
+
+  fn sample_texture2d_indirect(
+    &self,
+    reg: &SemanticRegistry,
+    shader_texture_handle: Node<Texture2DHandle>,
+    shader_sampler_handle: Node<SamplerHandle>,
+    uv: Node<Vec2<f32>>,
+    base_level: Node<f32>,
+  ) -> Node<Vec4<f32>> {
+    let textures = VirtualTextureShaderAccess {
+      atlas: reg
+        .try_query_typed_both_stage::<VirtualTextureAtlasInShader>()
+        .unwrap(),
+      atlas_sampler: reg
+        .try_query_typed_both_stage::<VirtualTextureAtlasSamplerInShader>()
+        .unwrap(),
+      indirection: reg
+        .try_query_typed_both_stage::<VirtualTextureIndirectionInShader>()
+        .unwrap(),
+      info: reg
+        .any_map
+        .get::<VirtualTextureInfoInShader>()
+        .unwrap()
+        .0
+        .load()
+        .expand(),
+    };
+    let metas = reg.any_map.get::<VirtualTextureMetaInShader>().unwrap();
+    let samplers = reg.any_map.get::<SamplerPoolInShader>().unwrap();
+    let feedback = reg.any_map.get::<VirtualTextureFeedbackInShader>();
+
+    let meta = metas.0.index(shader_texture_handle).load().expand();
+
+    let tex = meta.page_table_offset.equals(u32::MAX).select_branched(
+      || val(Vec4::zero()),
+      || {
+        let sampler = samplers.0.index(shader_sampler_handle).load().expand();
+        let correct_u = shader_address_mode_fn(sampler.address_mode_u, uv.x());
+        let correct_v = shader_address_mode_fn(sampler.address_mode_v, uv.y());
+        let uv: Node<Vec2<_>> = (correct_u, correct_v).into();
+
+        let max_level = (meta.level_count - val(1)).into_f32();
+        let level = base_level.max(0.).min(max_level);
+
+        if let Some(feedback) = feedback {
+          let page = textures.page_of(&meta, uv, level.floor().into_u32());
+          write_virtual_texture_feedback(
+            &feedback.0,
+            textures.info.feedback_slot_count,
+            shader_texture_handle,
+            level.floor().into_u32(),
+            page,
+          );
+        }
+
+        let sample_level = |level: Node<f32>| {
+          let linear =
+            shader_should_use_linear_filter_fn(sampler.mag_filter, sampler.min_filter, level);
+          textures.sample_level(&meta, uv, level.into_u32(), linear)
+        };
+
+        sampler.mipmap_filter.equals(LINEAR).select_branched(
+          || {
+            let base = sample_level(level.floor());
+            let next = sample_level(level.ceil());
+            level.fract().mix(base, next)
+          },
+          || sample_level(level.round()),
+        )
+      },
+    );
+
+    meta
+      .require_srgb_to_linear_convert
+      .into_bool()
+      .select_branched(
+        || {
+          let a = tex.w();
+          let rgb = tex.xyz();
+          let linear = shader_srgb_to_linear_convert_fn(rgb);
+          (linear, a).into()
+        },
+        || tex,
+      )
+  }
+}
+
+struct VirtualTextureShaderAccess {
+  atlas: BindingNode<ShaderTexture2D>,
+  atlas_sampler: BindingNode<ShaderSampler>,
+  indirection: BindingNode<ShaderTexture2DUint>,
+  info: ENode<VirtualTextureShaderInfo>,
+}
+
+impl VirtualTextureShaderAccess {
+  fn level_size(&self, meta: &ENode<VirtualTextureMeta>, level: Node<u32>) -> Node<Vec2<f32>> {
+    let width = (meta.size.x().into_u32() >> level).max(val(1));
+    let height = (meta.size.y().into_u32() >> level).max(val(1));
+    (width.into_f32(), height.into_f32()).into()
+  }
+
+  fn page_of(
+    &self,
+    meta: &ENode<VirtualTextureMeta>,
+    uv: Node<Vec2<f32>>,
+    level: Node<u32>,
+  ) -> Node<Vec2<u32>> {
+    let level_size = self.level_size(meta, level);
+    let page_count = (level_size / self.info.page_size.splat::<Vec2<f32>>()).ceil();
+    let page = (uv * level_size / self.info.page_size.splat::<Vec2<f32>>()).floor();
+    page.min(page_count - val(Vec2::one())).into_u32()
+  }
+
+  fn load_indirection(&self, index: Node<u32>) -> Node<u32> {
+    let width = self.info.indirection_width;
+    let position: Node<Vec2<u32>> = (index % width, index / width).into();
+    self.indirection.load_texel(position, val(0)).x()
+  }
+
+  /// sample the level, if the page is not resident, the coarser level is used
+  fn sample_level(
+    &self,
+    meta: &ENode<VirtualTextureMeta>,
+    uv: Node<Vec2<f32>>,
+    level: Node<u32>,
+    linear: Node<bool>,
+  ) -> Node<Vec4<f32>> {
+    let level = level.make_local_var();
+    let result = val(Vec4::zero()).make_local_var();
+    let page_size = self.info.page_size;
+
+    loop_by(|cx| {
+      let current_level = level.load();
+      if_by(current_level.greater_equal_than(meta.level_count), || {
+        cx.do_break()
+      });
+
+      let level_size = self.level_size(meta, current_level);
+      let page_count_x = (level_size.x() / page_size).ceil().into_u32();
+      let page = self.page_of(meta, uv, current_level);
+
+      let level_offset = self.load_indirection(meta.page_table_offset + current_level);
+      let entry_index = meta.page_table_offset + level_offset + page.y() * page_count_x + page.x();
+      let entry = self.load_indirection(entry_index);
+
+      if_by(
+        (entry & val(INDIRECTION_RESIDENT_BIT)).not_equals(0),
+        || {
+          let slot_x = entry & val(INDIRECTION_SLOT_COORD_MASK);
+          let slot_y =
+            (entry >> val(INDIRECTION_SLOT_COORD_BITS)) & val(INDIRECTION_SLOT_COORD_MASK);
+          let slot: Node<Vec2<f32>> = (slot_x.into_f32(), slot_y.into_f32()).into();
+
+          let in_page = uv * level_size - page.into_f32() * page_size.splat::<Vec2<f32>>();
+          // the nearest filter is emulated by sampling the texel center
+          let in_page = linear.select(in_page, in_page.floor() + val(Vec2::splat(0.5)));
+
+          let padded_page_size = page_size + self.info.page_border * val(2.);
+          let physical = slot * padded_page_size.splat::<Vec2<f32>>()
+            + self.info.page_border.splat::<Vec2<f32>>()
+            + in_page;
+          let sampled = self
+            .atlas
+            .build_sample_call(
+              self.atlas_sampler,
+              physical / self.info.atlas_size.splat::<Vec2<f32>>(),
+            )
+            .with_level(val(0.))
+            .sample();
+          result.store(sampled);
+          cx.do_break();
+        },
+      );
+
+      level.store(current_level + val(1));
+    });
+
+    result.load()
+  }
+}
+
+/// The feedback buffer is a hash table, the entry is overwritten by the later request, the
+/// store is skipped if the same request is already written to reduce the memory traffic.
+fn write_virtual_texture_feedback(
+  feedback: &ShaderPtrOf<[Vec2<u32>]>,
+  slot_count: Node<u32>,
+  texture: Node<Texture2DHandle>,
+  level: Node<u32>,
+  page: Node<Vec2<u32>>,
+) {
+  let packed =
+    (level << val(FEEDBACK_LEVEL_SHIFT)) | (page.y() << val(FEEDBACK_PAGE_COORD_BITS)) | page.x();
+  let entry: Node<Vec2<u32>> = (texture + val(1), packed).into();
+
+  let hash = (packed ^ (texture * val(0x9E3779B9))) * val(0x85EBCA6B);
+  let hash = hash ^ (hash >> val(16));
+  let slot = feedback.index(hash % slot_count);
+
+  let previous = slot.load();
+  let same = previous
+    .x()
+    .equals(entry.x())
+    .and(previous.y().equals(entry.y()));
+  if_by(same.not(), || slot.store(entry));
+}
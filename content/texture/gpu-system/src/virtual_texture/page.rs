@@ -0,0 +1,207 @@
+use crate::*;
+
+/// The page id in the virtual address space, the level is the mip level of the virtual
+/// texture, the x and y are the page coordinates in that level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VirtualTexturePageId {
+  pub texture: Texture2DHandle,
+  pub level: u32,
+  pub x: u32,
+  pub y: u32,
+}
+
+pub(crate) const FEEDBACK_PAGE_COORD_BITS: u32 = 14;
+pub(crate) const FEEDBACK_PAGE_COORD_MASK: u32 = (1 << FEEDBACK_PAGE_COORD_BITS) - 1;
+pub(crate) const FEEDBACK_LEVEL_SHIFT: u32 = FEEDBACK_PAGE_COORD_BITS * 2;
+/// the level is stored in the rest 4 bits of the feedback entry
+pub const VIRTUAL_TEXTURE_MAX_LEVEL_COUNT: u32 = 16;
+
+impl VirtualTexturePageId {
+  /// The feedback entry is (texture handle + 1, level | page y | page x), the zero entry means
+  /// nothing requested, so the feedback buffer can be reset by clearing.
+  pub fn encode_feedback(&self) -> Vec2<u32> {
+    Vec2::new(
+      self.texture + 1,
+      (self.level << FEEDBACK_LEVEL_SHIFT) | (self.y << FEEDBACK_PAGE_COORD_BITS) | self.x,
+    )
+  }
+
+  pub fn decode_feedback(entry: Vec2<u32>) -> Option<Self> {
+    if entry.x == 0 {
+      return None;
+    }
+    Self {
+      texture: entry.x - 1,
+      level: entry.y >> FEEDBACK_LEVEL_SHIFT,
+      x: entry.y & FEEDBACK_PAGE_COORD_MASK,
+      y: (entry.y >> FEEDBACK_PAGE_COORD_BITS) & FEEDBACK_PAGE_COORD_MASK,
+    }
+    .into()
+  }
+
+  pub fn parent(&self) -> Self {
+    Self {
+      texture: self.texture,
+      level: self.level + 1,
+      x: self.x / 2,
+      y: self.y / 2,
+    }
+  }
+}
+
+pub(crate) const INDIRECTION_RESIDENT_BIT: u32 = 1 << 31;
+pub(crate) const INDIRECTION_SLOT_COORD_BITS: u32 = 15;
+pub(crate) const INDIRECTION_SLOT_COORD_MASK: u32 = (1 << INDIRECTION_SLOT_COORD_BITS) - 1;
+
+/// The indirection entry maps the virtual page to the physical page slot in the page atlas.
+pub fn encode_indirection_entry(slot: Option<(u32, u32)>) -> u32 {
+  slot
+    .map(|(x, y)| INDIRECTION_RESIDENT_BIT | (y << INDIRECTION_SLOT_COORD_BITS) | x)
+    .unwrap_or(0)
+}
+
+pub fn decode_indirection_entry(entry: u32) -> Option<(u32, u32)> {
+  (entry & INDIRECTION_RESIDENT_BIT != 0).then_some((
+    entry & INDIRECTION_SLOT_COORD_MASK,
+    (entry >> INDIRECTION_SLOT_COORD_BITS) & INDIRECTION_SLOT_COORD_MASK,
+  ))
+}
+
+/// The page table layout of one virtual texture.
+///
+/// The page table is a continuous range in the indirection texture. The first `level_count`
+/// entries are the offsets of each level's page table relative to the range start, then each
+/// level's pages are stored in row major order. The coarsest level always has only one page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VirtualTextureLayout {
+  pub size: (u32, u32),
+  pub page_size: u32,
+  pub level_page_counts: Vec<(u32, u32)>,
+  pub level_offsets: Vec<u32>,
+  pub entry_count: u32,
+}
+
+impl VirtualTextureLayout {
+  pub fn new(size: Size, page_size: u32) -> Self {
+    let size = size.into_u32();
+    let mut level_page_counts = Vec::new();
+    loop {
+      let level = level_page_counts.len() as u32;
+      let width = (size.0 >> level).max(1);
+      let height = (size.1 >> level).max(1);
+      let counts = (width.div_ceil(page_size), height.div_ceil(page_size));
+      level_page_counts.push(counts);
+      if counts == (1, 1) || level_page_counts.len() as u32 == VIRTUAL_TEXTURE_MAX_LEVEL_COUNT {
+        break;
+      }
+    }
+
+    let mut offset = level_page_counts.len() as u32;
+    let level_offsets = level_page_counts
+      .iter()
+      .map(|(x, y)| {
+        let level_offset = offset;
+        offset += x * y;
+        level_offset
+      })
+      .collect();
+
+    Self {
+      size,
+      page_size,
+      level_page_counts,
+      level_offsets,
+      entry_count: offset,
+    }
+  }
+
+  pub fn level_count(&self) -> u32 {
+    self.level_page_counts.len() as u32
+  }
+
+  pub fn coarsest_page(&self, texture: Texture2DHandle) -> VirtualTexturePageId {
+    VirtualTexturePageId {
+      texture,
+      level: self.level_count() - 1,
+      x: 0,
+      y: 0,
+    }
+  }
+
+  pub fn contains(&self, page: &VirtualTexturePageId) -> bool {
+    self
+      .level_page_counts
+      .get(page.level as usize)
+      .is_some_and(|(x, y)| page.x < *x && page.y < *y)
+  }
+
+  /// the entry index relative to the page table start
+  pub fn entry_index(&self, page: &VirtualTexturePageId) -> u32 {
+    let (width, _) = self.level_page_counts[page.level as usize];
+    self.level_offsets[page.level as usize] + page.y * width + page.x
+  }
+
+  /// the texel size of the level
+  pub fn level_size(&self, level: u32) -> (u32, u32) {
+    ((self.size.0 >> level).max(1), (self.size.1 >> level).max(1))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_layout() {
+    let layout = VirtualTextureLayout::new(Size::from_u32_pair_min_one((1000, 300)), 128);
+    assert_eq!(layout.level_page_counts, [(8, 3), (4, 2), (2, 1), (1, 1)]);
+    assert_eq!(layout.level_offsets, [4, 28, 36, 38]);
+    assert_eq!(layout.entry_count, 39);
+    assert_eq!(layout.coarsest_page(0).level, 3);
+
+    let page = VirtualTexturePageId {
+      texture: 0,
+      level: 1,
+      x: 3,
+      y: 1,
+    };
+    assert!(layout.contains(&page));
+    assert_eq!(layout.entry_index(&page), 28 + 4 + 3);
+    assert!(!layout.contains(&VirtualTexturePageId { x: 4, ..page }));
+    assert_eq!(
+      page.parent(),
+      VirtualTexturePageId {
+        level: 2,
+        x: 1,
+        y: 0,
+        ..page
+      }
+    );
+
+    let small = VirtualTextureLayout::new(Size::from_u32_pair_min_one((64, 64)), 128);
+    assert_eq!(small.level_count(), 1);
+  }
+
+  #[test]
+  fn test_encoding() {
+    let page = VirtualTexturePageId {
+      texture: 42,
+      level: 15,
+      x: FEEDBACK_PAGE_COORD_MASK,
+      y: 3,
+    };
+    let entry = page.encode_feedback();
+    assert_eq!(VirtualTexturePageId::decode_feedback(entry), Some(page));
+    assert_eq!(VirtualTexturePageId::decode_feedback(Vec2::zero()), None);
+
+    assert_eq!(
+      decode_indirection_entry(encode_indirection_entry(None)),
+      None
+    );
+    let slot = Some((INDIRECTION_SLOT_COORD_MASK, 7));
+    assert_eq!(
+      decode_indirection_entry(encode_indirection_entry(slot)),
+      slot
+    );
+  }
+}
@@ -0,0 +1,357 @@
+use std::{future::Future, pin::Pin};
+
+use fast_hash_collection::FastHashMap;
+use futures::{FutureExt, future::Shared};
+use parking_lot::RwLock;
+use rendiation_texture_block_compression::decode_block_compressed_image;
+use rendiation_texture_core::*;
+use rendiation_uri_streaming::{LoadFuture, LoaderCreator, LoaderFunction, MaybeUriData};
+
+use crate::*;
+
+/// The texels of the page with the border, in [VIRTUAL_TEXTURE_ATLAS_FORMAT]
+pub struct VirtualTexturePageData {
+  pub data: Vec<u8>,
+}
+
+/// The Rgba8 mip chain of the image, used to produce the pages.
+pub struct VirtualTextureImageSource {
+  levels: Vec<((u32, u32), Vec<u8>)>,
+  srgb: bool,
+}
+
+impl VirtualTextureImageSource {
+  /// return None if the format is not supported
+  pub fn new(image: &GPUBufferImage) -> Option<Self> {
+    if image.format.is_compressed() {
+      let Some(decoded) = decode_block_compressed_image(image) else {
+        log::warn!(
+          "virtual texture not support decode compressed format {:?}",
+          image.format
+        );
+        return None;
+      };
+      return Self::new(&decoded);
+    }
+
+    let convert: fn(&[u8]) -> Vec<u8> = match image.format.remove_srgb_suffix() {
+      TextureFormat::Rgba8Unorm => |data| data.to_vec(),
+      TextureFormat::R8Unorm => |data| data.iter().flat_map(|v| [*v, 0, 0, 255]).collect(),
+      _ => {
+        log::warn!("virtual texture not support format {:?}", image.format);
+        return None;
+      }
+    };
+
+    let srgb = image.format.is_srgb();
+    let size = image.size.into_u32();
+    let mut levels = vec![(size, convert(&image.data))];
+    for level in 1..image.mip_level_count() {
+      let size = mip_level_size(image.size, level).into_u32();
+      levels.push((size, convert(image.level_data(level))));
+    }
+
+    // generate the rest levels by the box filter
+    while let Some((size, data)) = levels.last()
+      && *size != (1, 1)
+    {
+      let next = downsample_rgba8(*size, data);
+      levels.push(next);
+    }
+
+    Some(Self { levels, srgb })
+  }
+
+  pub fn size(&self) -> Size {
+    Size::from_u32_pair_min_one(self.levels[0].0)
+  }
+
+  pub fn is_srgb(&self) -> bool {
+    self.srgb
+  }
+
+  pub fn byte_size(&self) -> u64 {
+    self.levels.iter().map(|(_, data)| data.len() as u64).sum()
+  }
+
+  /// Copy the page with the border out of the level, the texels outside the level are clamped
+  /// to the edge. If the level not exist, the coarsest level is used.
+  pub fn produce_page(&self, page: &VirtualTexturePageId, page_size: u32, border: u32) -> Vec<u8> {
+    let ((width, height), data) = &self.levels[(page.level as usize).min(self.levels.len() - 1)];
+    let padded = page_size + border * 2;
+    let origin_x = (page.x * page_size) as i64 - border as i64;
+    let origin_y = (page.y * page_size) as i64 - border as i64;
+
+    let mut result = Vec::with_capacity((padded * padded * 4) as usize);
+    for y in 0..padded as i64 {
+      let src_y = (origin_y + y).clamp(0, *height as i64 - 1) as usize;
+      for x in 0..padded as i64 {
+        let src_x = (origin_x + x).clamp(0, *width as i64 - 1) as usize;
+        let offset = (src_y * *width as usize + src_x) * 4;
+        result.extend_from_slice(&data[offset..offset + 4]);
+      }
+    }
+    result
+  }
+}
+
+fn downsample_rgba8((width, height): (u32, u32), data: &[u8]) -> ((u32, u32), Vec<u8>) {
+  let new_width = (width / 2).max(1);
+  let new_height = (height / 2).max(1);
+  let mut result = Vec::with_capacity((new_width * new_height * 4) as usize);
+  for y in 0..new_height {
+    for x in 0..new_width {
+      let mut sum = [0u32; 4];
+      let mut count = 0;
+      for sy in (y * 2)..(y * 2 + 2).min(height) {
+        for sx in (x * 2)..(x * 2 + 2).min(width) {
+          let offset = ((sy * width + sx) * 4) as usize;
+          sum
+            .iter_mut()
+            .zip(&data[offset..offset + 4])
+            .for_each(|(s, v)| *s += *v as u32);
+          count += 1;
+        }
+      }
+      result.extend(sum.map(|s| ((s + count / 2) / count) as u8));
+    }
+  }
+  ((new_width, new_height), result)
+}
+
+/// The source of the virtual texture pages, the uri source is loaded only when its pages are
+/// required.
+pub type VirtualTextureSource = MaybeUriData<Arc<GPUBufferImage>>;
+pub type VirtualTextureUriLoaderCreator = LoaderCreator<Arc<String>, Arc<GPUBufferImage>>;
+
+pub(crate) type ImageSourceLoading =
+  Shared<Pin<Box<dyn Future<Output = Option<Arc<VirtualTextureImageSource>>> + Send>>>;
+
+struct CachedImageSource {
+  loading: ImageSourceLoading,
+  last_used: u64,
+}
+
+/// Produce the pages from the [VirtualTextureSource]s.
+///
+/// The mip chain of the source is generated when its page is requested, the recently used mip
+/// chains are cached within the byte budget, the others are dropped and reloaded from the
+/// source when required again. So the host memory is bounded by the budget besides the living
+/// textures owned by the scene.
+pub struct VirtualTexturePageProducer {
+  sources: FastHashMap<Texture2DHandle, VirtualTextureSource>,
+  cache: FastHashMap<Texture2DHandle, CachedImageSource>,
+  cache_budget: u64,
+  use_counter: u64,
+  uri_loader: Box<LoaderFunction<Arc<String>, Arc<GPUBufferImage>>>,
+}
+
+impl VirtualTexturePageProducer {
+  pub fn new(
+    cache_budget: u64,
+    uri_loader: Box<LoaderFunction<Arc<String>, Arc<GPUBufferImage>>>,
+  ) -> Self {
+    Self {
+      sources: Default::default(),
+      cache: Default::default(),
+      cache_budget,
+      use_counter: 0,
+      uri_loader,
+    }
+  }
+
+  /// the none source means the texture is removed
+  pub fn set_source(&mut self, texture: Texture2DHandle, source: Option<VirtualTextureSource>) {
+    self.cache.remove(&texture);
+    if let Some(source) = source {
+      self.sources.insert(texture, source);
+    } else {
+      self.sources.remove(&texture);
+    }
+  }
+
+  /// the byte size of the loaded mip chains in the cache
+  pub fn cached_byte_size(&self) -> u64 {
+    self
+      .cache
+      .values()
+      .map(|c| loaded_byte_size(&c.loading))
+      .sum()
+  }
+
+  /// return the loading of the mip chain, the output is none if the source failed to load or
+  /// the format is not supported. Return none if the texture not exist.
+  pub(crate) fn request_image_source(
+    &mut self,
+    texture: Texture2DHandle,
+  ) -> Option<ImageSourceLoading> {
+    self.use_counter += 1;
+    if let Some(cached) = self.cache.get_mut(&texture) {
+      cached.last_used = self.use_counter;
+      return Some(cached.loading.clone());
+    }
+
+    let image: LoadFuture<Arc<GPUBufferImage>> = match self.sources.get(&texture)? {
+      MaybeUriData::Living(image) => Box::new(std::future::ready(Some(image.clone()))),
+      MaybeUriData::Uri(uri) => (self.uri_loader)(uri),
+    };
+    let loading = image
+      .map(|image| VirtualTextureImageSource::new(image?.as_ref()).map(Arc::new))
+      .boxed()
+      .shared();
+
+    self.evict_over_budget();
+    self.cache.insert(
+      texture,
+      CachedImageSource {
+        loading: loading.clone(),
+        last_used: self.use_counter,
+      },
+    );
+    Some(loading)
+  }
+
+  /// drop the least recently used mip chains, the loading ones are kept because their size is
+  /// unknown yet.
+  fn evict_over_budget(&mut self) {
+    let mut loaded: Vec<_> = self
+      .cache
+      .iter()
+      .map(|(texture, c)| (c.last_used, *texture, loaded_byte_size(&c.loading)))
+      .filter(|(_, _, size)| *size > 0)
+      .collect();
+    loaded.sort_unstable();
+
+    let mut total: u64 = loaded.iter().map(|(_, _, size)| size).sum();
+    for (_, texture, size) in loaded {
+      if total <= self.cache_budget {
+        break;
+      }
+      self.cache.remove(&texture);
+      total -= size;
+    }
+  }
+}
+
+fn loaded_byte_size(loading: &ImageSourceLoading) -> u64 {
+  match loading.peek() {
+    Some(Some(source)) => source.byte_size(),
+    _ => 0,
+  }
+}
+
+/// Produce the pages by the producer, the producer can be updated at any time.
+pub fn create_virtual_texture_page_loader(
+  producer: Arc<RwLock<VirtualTexturePageProducer>>,
+  page_size: u32,
+  border: u32,
+) -> Box<LoaderFunction<VirtualTexturePageId, VirtualTexturePageData>> {
+  Box::new(move |page: &VirtualTexturePageId| {
+    let page = *page;
+    let Some(loading) = producer.write().request_image_source(page.texture) else {
+      return Box::new(std::future::ready(None)) as LoadFuture<VirtualTexturePageData>;
+    };
+    Box::new(loading.map(move |source| {
+      Some(VirtualTexturePageData {
+        data: source?.produce_page(&page, page_size, border),
+      })
+    })) as LoadFuture<VirtualTexturePageData>
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_produce_page() {
+    // 4x2 texture, each texel's red channel is its index
+    let image = GPUBufferImage {
+      data: (0..8u8).flat_map(|i| [i * 10, 0, 0, 255]).collect(),
+      format: TextureFormat::Rgba8Unorm,
+      size: Size::from_u32_pair_min_one((4, 2)),
+      precomputed_mips: Vec::new(),
+    };
+    let source = VirtualTextureImageSource::new(&image).unwrap();
+    assert_eq!(source.levels.len(), 3);
+    assert_eq!(source.levels[1].0, (2, 1));
+    assert_eq!(source.levels[1].1, [25, 0, 0, 255, 45, 0, 0, 255]);
+
+    let page = VirtualTexturePageId {
+      texture: 0,
+      level: 0,
+      x: 1,
+      y: 0,
+    };
+    let data = source.produce_page(&page, 2, 1);
+    let red: Vec<u8> = data.chunks(4).map(|t| t[0]).collect();
+    // the border is clamped to the texture edge
+    assert_eq!(
+      red,
+      [
+        10, 20, 30, 30, 10, 20, 30, 30, 50, 60, 70, 70, 50, 60, 70, 70
+      ]
+    );
+  }
+
+  #[test]
+  fn test_page_producer_cache() {
+    let image = |size| {
+      Arc::new(GPUBufferImage {
+        data: vec![255; size * size * 4],
+        format: TextureFormat::Rgba8Unorm,
+        size: Size::from_usize_pair_min_one((size, size)),
+        precomputed_mips: Vec::new(),
+      })
+    };
+    let uri_load_count = Arc::new(RwLock::new(0));
+    let count = uri_load_count.clone();
+    let uri_loader = Box::new(move |uri: &Arc<String>| {
+      *count.write() += 1;
+      let image = (uri.as_str() == "exist").then(|| image(4));
+      Box::new(std::future::ready(image)) as LoadFuture<_>
+    });
+
+    // the mip chain of the 4x4 texture is 84 bytes, so only one is kept
+    let producer = Arc::new(RwLock::new(VirtualTexturePageProducer::new(
+      100, uri_loader,
+    )));
+    let uri = |uri: &str| Some(MaybeUriData::Uri(Arc::new(uri.to_string())));
+    producer.write().set_source(0, uri("exist"));
+    producer
+      .write()
+      .set_source(1, Some(MaybeUriData::Living(image(4))));
+    producer.write().set_source(2, uri("missing"));
+
+    let mut loader = create_virtual_texture_page_loader(producer.clone(), 2, 0);
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    let mut load = |texture| {
+      let page = VirtualTexturePageId {
+        texture,
+        level: 0,
+        x: 0,
+        y: 0,
+      };
+      match loader(&page).poll_unpin(&mut cx) {
+        std::task::Poll::Ready(page) => page,
+        std::task::Poll::Pending => panic!("the test sources are loaded immediately"),
+      }
+    };
+
+    assert_eq!(load(0).unwrap().data.len(), 2 * 2 * 4);
+    assert!(load(0).is_some());
+    assert_eq!(*uri_load_count.read(), 1);
+    assert_eq!(producer.read().cached_byte_size(), 84);
+
+    // the budget is checked before the new source is loaded
+    assert!(load(1).is_some());
+    assert_eq!(producer.read().cached_byte_size(), 84 * 2);
+    assert!(load(2).is_none());
+    assert_eq!(producer.read().cached_byte_size(), 84);
+
+    // the evicted source is reloaded
+    assert!(load(0).is_some());
+    assert_eq!(*uri_load_count.read(), 3);
+    assert!(load(3).is_none());
+  }
+}
@@ -0,0 +1,371 @@
+use std::task::Context;
+
+use fast_hash_collection::{FastHashMap, FastHashSet};
+use rendiation_uri_streaming::{LoaderFunction, LoadingThrottler};
+
+use crate::*;
+
+struct RegisteredVirtualTexture {
+  layout: VirtualTextureLayout,
+  page_table_offset: u32,
+  require_srgb_to_linear_convert: bool,
+}
+
+/// The host side page table, all page tables are linear allocated into one entry array which
+/// is uploaded into the indirection texture row by row.
+#[derive(Default)]
+pub struct VirtualTexturePageTable {
+  pub entries: Vec<u32>,
+  /// sorted free ranges of (offset, len)
+  free_ranges: Vec<(u32, u32)>,
+  pub dirty_entries: FastHashSet<u32>,
+}
+
+impl VirtualTexturePageTable {
+  fn allocate(&mut self, count: u32) -> u32 {
+    if let Some(i) = self.free_ranges.iter().position(|(_, len)| *len >= count) {
+      let (offset, len) = &mut self.free_ranges[i];
+      let result = *offset;
+      *offset += count;
+      *len -= count;
+      if *len == 0 {
+        self.free_ranges.remove(i);
+      }
+      return result;
+    }
+    let offset = self.entries.len() as u32;
+    self.entries.resize(self.entries.len() + count as usize, 0);
+    offset
+  }
+
+  fn free(&mut self, offset: u32, count: u32) {
+    let i = self.free_ranges.partition_point(|(o, _)| *o < offset);
+    self.free_ranges.insert(i, (offset, count));
+    // merge with the next and the previous range
+    if i + 1 < self.free_ranges.len() && offset + count == self.free_ranges[i + 1].0 {
+      self.free_ranges[i].1 += self.free_ranges.remove(i + 1).1;
+    }
+    if i > 0 && self.free_ranges[i - 1].0 + self.free_ranges[i - 1].1 == offset {
+      self.free_ranges[i - 1].1 += self.free_ranges.remove(i).1;
+    }
+  }
+
+  fn write(&mut self, index: u32, entry: u32) {
+    if self.entries[index as usize] != entry {
+      self.entries[index as usize] = entry;
+      self.dirty_entries.insert(index);
+    }
+  }
+}
+
+/// The host side state of the virtual texture system.
+///
+/// The pages requested by the feedback are loaded through the [LoadingThrottler], the loaded
+/// pages are placed into the physical page slots with the LRU eviction, and the page table is
+/// updated accordingly. The coarsest page of each texture is requested when the texture is
+/// added and it's never evicted.
+pub struct VirtualTextureStreaming {
+  config: VirtualTextureSystemInit,
+  textures: FastHashMap<Texture2DHandle, RegisteredVirtualTexture>,
+  cache: VirtualTexturePhysicalPageCache,
+  throttler: LoadingThrottler<VirtualTexturePageId, VirtualTexturePageData, VirtualTexturePageId>,
+  failed: FastHashSet<VirtualTexturePageId>,
+  frame: u64,
+  pub page_table: VirtualTexturePageTable,
+  /// the texture metadata changed since the last take
+  pub meta_changed: bool,
+  /// the loaded pages waiting for uploading into the atlas
+  pub pending_uploads: Vec<(u32, VirtualTexturePageData)>,
+}
+
+impl VirtualTextureStreaming {
+  pub fn new(config: VirtualTextureSystemInit) -> Self {
+    Self {
+      cache: VirtualTexturePhysicalPageCache::new(config.physical_page_count_per_side.pow(2)),
+      throttler: LoadingThrottler::new(config.loading_bandwidth),
+      config,
+      textures: Default::default(),
+      failed: Default::default(),
+      frame: 1,
+      page_table: Default::default(),
+      meta_changed: false,
+      pending_uploads: Default::default(),
+    }
+  }
+
+  pub fn config(&self) -> &VirtualTextureSystemInit {
+    &self.config
+  }
+
+  pub fn cache(&self) -> &VirtualTexturePhysicalPageCache {
+    &self.cache
+  }
+
+  pub fn slot_coord(&self, slot: u32) -> (u32, u32) {
+    let per_side = self.config.physical_page_count_per_side;
+    (slot % per_side, slot / per_side)
+  }
+
+  fn page_byte_size(&self) -> u64 {
+    let padded = (self.config.page_size + self.config.page_border * 2) as u64;
+    padded * padded * 4
+  }
+
+  /// add or replace the texture, the pages of the old one are released
+  pub fn add_texture(&mut self, texture: Texture2DHandle, size: Size, srgb: bool) {
+    self.remove_texture(texture);
+
+    let layout = VirtualTextureLayout::new(size, self.config.page_size);
+    let page_table_offset = self.page_table.allocate(layout.entry_count);
+    for (level, level_offset) in layout.level_offsets.iter().enumerate() {
+      self
+        .page_table
+        .write(page_table_offset + level as u32, *level_offset);
+    }
+    for index in layout.level_count()..layout.entry_count {
+      self.page_table.write(page_table_offset + index, 0);
+    }
+
+    let coarsest = layout.coarsest_page(texture);
+    self
+      .throttler
+      .request_load(coarsest, coarsest, self.page_byte_size());
+
+    self.textures.insert(
+      texture,
+      RegisteredVirtualTexture {
+        layout,
+        page_table_offset,
+        require_srgb_to_linear_convert: srgb,
+      },
+    );
+    self.meta_changed = true;
+  }
+
+  pub fn remove_texture(&mut self, texture: Texture2DHandle) {
+    let Some(removed) = self.textures.remove(&texture) else {
+      return;
+    };
+    for page in self.cache.release_texture(texture) {
+      self.throttler.cancel_not_dispatched_load(&page);
+    }
+    self.failed.retain(|page| page.texture != texture);
+    self
+      .page_table
+      .free(removed.page_table_offset, removed.layout.entry_count);
+    self.meta_changed = true;
+  }
+
+  /// return the metadata of the texture, the none registered texture has the
+  /// u32::MAX page table offset
+  pub fn texture_meta(&self, texture: Texture2DHandle) -> VirtualTextureMeta {
+    self
+      .textures
+      .get(&texture)
+      .map(|t| VirtualTextureMeta {
+        size: Vec2::new(t.layout.size.0 as f32, t.layout.size.1 as f32),
+        page_table_offset: t.page_table_offset,
+        level_count: t.layout.level_count(),
+        require_srgb_to_linear_convert: Bool::from(t.require_srgb_to_linear_convert),
+        ..Zeroable::zeroed()
+      })
+      .unwrap_or(VirtualTextureMeta {
+        page_table_offset: u32::MAX,
+        ..Zeroable::zeroed()
+      })
+  }
+
+  pub fn max_texture_handle(&self) -> Option<Texture2DHandle> {
+    self.textures.keys().max().copied()
+  }
+
+  /// Process the feedback entries of one frame. The requested pages and their parents are
+  /// marked as used, and the not resident ones are requested, the coarser page first, so the
+  /// sampling quality is progressively improved.
+  pub fn process_feedback(&mut self, feedback: &[Vec2<u32>]) {
+    self.frame += 1;
+
+    let mut to_request = Vec::new();
+    let mut visited = FastHashSet::default();
+    for page in feedback
+      .iter()
+      .filter_map(|entry| VirtualTexturePageId::decode_feedback(*entry))
+    {
+      let Some(texture) = self.textures.get(&page.texture) else {
+        continue;
+      };
+      if !texture.layout.contains(&page) {
+        continue;
+      }
+
+      let mut page = page;
+      loop {
+        if !visited.insert(page) {
+          break;
+        }
+        if !self.cache.touch(&page, self.frame)
+          && !self.failed.contains(&page)
+          && !self.throttler.is_in_request(&page)
+        {
+          to_request.push(page);
+        }
+        if page.level + 1 >= texture.layout.level_count() {
+          break;
+        }
+        page = page.parent();
+      }
+    }
+
+    to_request.sort_by_key(|page| std::cmp::Reverse(page.level));
+    let cost = self.page_byte_size();
+    for page in to_request {
+      self.throttler.request_load(page, page, cost);
+    }
+  }
+
+  /// return if any page become resident
+  pub fn poll_loading(
+    &mut self,
+    cx: &mut Context,
+    loader: &mut LoaderFunction<VirtualTexturePageId, VirtualTexturePageData>,
+  ) -> bool {
+    let mut changed = false;
+    for (page, loaded) in self.throttler.poll_loading(cx, loader) {
+      let Some(texture) = self.textures.get(&page.texture) else {
+        continue;
+      };
+      // the dispatched loading of the replaced texture is not cancelable, the page may not
+      // exist in the new layout
+      if !texture.layout.contains(&page) {
+        continue;
+      }
+      let Some(loaded) = loaded else {
+        log::warn!("failed to load virtual texture page {page:?}");
+        self.throttler.cancel_not_dispatched_load(&page);
+        self.failed.insert(page);
+        continue;
+      };
+      let is_coarsest = page.level + 1 == texture.layout.level_count();
+      let Some((slot, evicted)) = self.cache.allocate(page, self.frame, is_coarsest) else {
+        // all slots are in use, the page will be requested by the later feedback again
+        continue;
+      };
+
+      let page_index = texture.page_table_offset + texture.layout.entry_index(&page);
+      let entry = encode_indirection_entry(Some(self.slot_coord(slot)));
+      self.page_table.write(page_index, entry);
+
+      if let Some(evicted) = evicted
+        && let Some(texture) = self.textures.get(&evicted.texture)
+      {
+        let index = texture.page_table_offset + texture.layout.entry_index(&evicted);
+        self.page_table.write(index, encode_indirection_entry(None));
+      }
+
+      self.pending_uploads.push((slot, loaded));
+      changed = true;
+    }
+    changed
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use rendiation_texture_core::GPUBufferImage;
+  use rendiation_uri_streaming::MaybeUriData;
+
+  use super::*;
+
+  #[test]
+  fn test_feedback_driven_streaming() {
+    let config = VirtualTextureSystemInit {
+      page_size: 2,
+      page_border: 1,
+      physical_page_count_per_side: 2,
+      ..Default::default()
+    };
+    let image = GPUBufferImage {
+      data: vec![255; 8 * 4 * 4],
+      format: TextureFormat::Rgba8Unorm,
+      size: Size::from_u32_pair_min_one((8, 4)),
+      precomputed_mips: Vec::new(),
+    };
+    let uri_loader = Box::new(|_: &Arc<String>| Box::new(std::future::ready(None)) as _);
+    let mut producer = VirtualTexturePageProducer::new(u64::MAX, uri_loader);
+    let image = Arc::new(image);
+    producer.set_source(3, Some(MaybeUriData::Living(image.clone())));
+    let producer = Arc::new(parking_lot::RwLock::new(producer));
+    let mut loader = create_virtual_texture_page_loader(producer, 2, 1);
+    let mut cx = Context::from_waker(std::task::Waker::noop());
+
+    let mut streaming = VirtualTextureStreaming::new(config);
+    streaming.add_texture(3, image.size, false);
+    assert_eq!(streaming.texture_meta(3).level_count, 3);
+    assert_eq!(streaming.texture_meta(0).page_table_offset, u32::MAX);
+
+    // the coarsest page is loaded first
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.poll_loading(&mut cx, &mut loader));
+    let coarsest = VirtualTexturePageId {
+      texture: 3,
+      level: 2,
+      x: 0,
+      y: 0,
+    };
+    assert_eq!(streaming.cache().get(&coarsest), Some(0));
+    assert_eq!(streaming.pending_uploads.len(), 1);
+    assert_eq!(streaming.pending_uploads[0].1.data.len(), 4 * 4 * 4);
+    let level_offset = streaming.page_table.entries[2];
+    assert_eq!(
+      decode_indirection_entry(streaming.page_table.entries[level_offset as usize]),
+      Some((0, 0))
+    );
+
+    // request the finest page, its parent is requested too
+    let page = VirtualTexturePageId {
+      texture: 3,
+      level: 0,
+      x: 3,
+      y: 1,
+    };
+    let invalid = VirtualTexturePageId { x: 4, ..page };
+    streaming.process_feedback(&[
+      page.encode_feedback(),
+      invalid.encode_feedback(),
+      Vec2::zero(),
+    ]);
+    streaming.poll_loading(&mut cx, &mut loader);
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.cache().get(&page).is_some());
+    assert!(streaming.cache().get(&page.parent()).is_some());
+    assert_eq!(streaming.cache().resident_page_count(), 3);
+
+    // the least recently used page is evicted, and its page table entry is cleared
+    let other = VirtualTexturePageId { x: 0, ..page };
+    streaming.process_feedback(&[other.encode_feedback()]);
+    streaming.poll_loading(&mut cx, &mut loader);
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.cache().get(&other).is_some());
+    assert!(streaming.cache().get(&page).is_none());
+    assert!(streaming.cache().get(&page.parent()).is_some());
+    assert_eq!(streaming.cache().resident_page_count(), 4);
+    let page_index = streaming.page_table.entries[0] + 3 + 4;
+    assert_eq!(streaming.page_table.entries[page_index as usize], 0);
+
+    // the used pages are kept
+    streaming.process_feedback(&[other.encode_feedback()]);
+    let another = VirtualTexturePageId { y: 0, ..other };
+    streaming.process_feedback(&[another.encode_feedback()]);
+    streaming.poll_loading(&mut cx, &mut loader);
+    streaming.poll_loading(&mut cx, &mut loader);
+    assert!(streaming.cache().get(&another).is_some());
+    assert!(streaming.cache().get(&other).is_some());
+    assert!(streaming.cache().get(&page.parent()).is_none());
+
+    streaming.remove_texture(3);
+    assert_eq!(streaming.cache().resident_page_count(), 0);
+    assert_eq!(streaming.texture_meta(3).page_table_offset, u32::MAX);
+  }
+}
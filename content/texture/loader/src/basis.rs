@@ -0,0 +1,897 @@
+//! The ETC1S(BasisLZ) transcoder of the Basis Universal texture in the KTX2 container, the
+//! UASTC is not supported.
+
+use std::ops::Range;
+
+/// the etc1 modifier tables, the modifiers are in the linear order which is the order of the
+/// ETC1S selector value
+const ETC1_INTEN_TABLES: [[i32; 4]; 8] = [
+  [-8, -2, 2, 8],
+  [-17, -5, 5, 17],
+  [-29, -9, 9, 29],
+  [-42, -13, 13, 42],
+  [-60, -18, 18, 60],
+  [-80, -24, 24, 80],
+  [-106, -33, 33, 106],
+  [-183, -47, 47, 183],
+];
+
+/// map the linear selector to the etc1 pixel index
+const LINEAR_TO_ETC1_SELECTOR: [u8; 4] = [3, 2, 0, 1];
+
+/// the order of the code sizes of the code length huffman table
+const HUFFMAN_CODE_LENGTH_ORDER: [usize; 21] = [
+  17, 18, 19, 20, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15, 16,
+];
+const HUFFMAN_MAX_CODE_SIZE: usize = 16;
+
+const ENDPOINT_PRED_REPEAT_LAST_SYMBOL: u32 = 256;
+const ENDPOINT_PRED_MIN_REPEAT_COUNT: u32 = 3;
+const SELECTOR_HISTORY_RLE_COUNT_THRESHOLD: u32 = 3;
+const SELECTOR_HISTORY_RLE_COUNT_TOTAL: u32 = 64;
+
+const IMAGE_FLAG_P_FRAME: u32 = 0x02;
+
+struct BitReader<'a> {
+  data: &'a [u8],
+  bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+  fn new(data: &'a [u8]) -> Self {
+    Self { data, bit: 0 }
+  }
+
+  /// the bits are read from the least significant bit of each byte
+  fn read(&mut self, count: u32) -> Option<u32> {
+    let mut value = 0;
+    for i in 0..count {
+      let byte = *self.data.get(self.bit / 8)?;
+      value |= (((byte >> (self.bit % 8)) & 1) as u32) << i;
+      self.bit += 1;
+    }
+    Some(value)
+  }
+
+  fn read_vlc(&mut self, chunk_bits: u32) -> Option<u32> {
+    let mut value = 0;
+    let mut offset = 0;
+    loop {
+      let chunk = self.read(chunk_bits + 1)?;
+      value |= (chunk & ((1 << chunk_bits) - 1)) << offset;
+      offset += chunk_bits;
+      if chunk & (1 << chunk_bits) == 0 {
+        return Some(value);
+      }
+      if offset >= 32 {
+        return None;
+      }
+    }
+  }
+
+  fn read_huffman_table(&mut self) -> Option<HuffmanTable> {
+    let symbol_count = self.read(14)? as usize;
+    if symbol_count == 0 {
+      return HuffmanTable::new(&[]);
+    }
+
+    let code_length_code_count = self.read(5)? as usize;
+    if !(1..=HUFFMAN_CODE_LENGTH_ORDER.len()).contains(&code_length_code_count) {
+      return None;
+    }
+    let mut code_length_sizes = [0; 21];
+    for symbol in &HUFFMAN_CODE_LENGTH_ORDER[..code_length_code_count] {
+      code_length_sizes[*symbol] = self.read(3)? as u8;
+    }
+    let code_length_table = HuffmanTable::new(&code_length_sizes)?;
+
+    let mut sizes = vec![0; symbol_count];
+    let mut cursor = 0;
+    while cursor < symbol_count {
+      let (run, size) = match code_length_table.decode(self)? {
+        size @ 0..=16 => (1, size as u8),
+        17 => (self.read(3)? as usize + 3, 0),
+        18 => (self.read(7)? as usize + 11, 0),
+        code => {
+          let previous = *sizes.get(cursor.checked_sub(1)?)?;
+          if previous == 0 {
+            return None;
+          }
+          let run = if code == 19 {
+            self.read(2)? + 3
+          } else {
+            self.read(7)? + 7
+          };
+          (run as usize, previous)
+        }
+      };
+      sizes.get_mut(cursor..cursor + run)?.fill(size);
+      cursor += run;
+    }
+    HuffmanTable::new(&sizes)
+  }
+}
+
+/// The canonical huffman table, the code is read from the most significant bit.
+struct HuffmanTable {
+  /// the code count of each code size
+  counts: [u16; HUFFMAN_MAX_CODE_SIZE + 1],
+  /// the symbols sorted by the code size
+  symbols: Vec<u16>,
+}
+
+impl HuffmanTable {
+  fn new(sizes: &[u8]) -> Option<Self> {
+    let mut counts = [0; HUFFMAN_MAX_CODE_SIZE + 1];
+    for size in sizes {
+      *counts.get_mut(*size as usize)? += 1;
+    }
+
+    // reject the over subscribed code
+    let mut left = 1_i32;
+    for count in &counts[1..] {
+      left = (left << 1) - *count as i32;
+      if left < 0 {
+        return None;
+      }
+    }
+
+    let mut offsets = [0; HUFFMAN_MAX_CODE_SIZE + 1];
+    for size in 1..HUFFMAN_MAX_CODE_SIZE {
+      offsets[size + 1] = offsets[size] + counts[size];
+    }
+    let mut symbols = vec![0; sizes.len()];
+    for (symbol, size) in sizes.iter().enumerate() {
+      if *size != 0 {
+        symbols[offsets[*size as usize] as usize] = symbol as u16;
+        offsets[*size as usize] += 1;
+      }
+    }
+
+    Some(Self { counts, symbols })
+  }
+
+  fn decode(&self, reader: &mut BitReader) -> Option<u32> {
+    let mut code = 0;
+    let mut first = 0;
+    let mut index = 0;
+    for count in &self.counts[1..] {
+      code |= reader.read(1)? as i32;
+      let count = *count as i32;
+      if code - count < first {
+        return self
+          .symbols
+          .get((index + code - first) as usize)
+          .map(|s| *s as u32);
+      }
+      index += count;
+      first = (first + count) << 1;
+      code <<= 1;
+    }
+    None
+  }
+}
+
+#[derive(Clone, Copy)]
+struct Etc1sEndpoint {
+  color5: [u8; 3],
+  inten: u8,
+}
+
+/// the 2 bit linear selectors in the raster order
+type Etc1sSelector = [u8; 16];
+
+struct ImageDesc {
+  flags: u32,
+  rgb_slice: Range<usize>,
+  alpha_slice: Range<usize>,
+}
+
+/// approximate move to front selector history
+struct SelectorHistory {
+  values: Vec<u32>,
+  rover: usize,
+}
+
+impl SelectorHistory {
+  fn add(&mut self, value: u32) {
+    self.values[self.rover] = value;
+    self.rover += 1;
+    if self.rover == self.values.len() {
+      self.rover = self.values.len() / 2;
+    }
+  }
+
+  fn use_index(&mut self, index: usize) {
+    self.values.swap(index / 2, index);
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Etc1sTranscodeTarget {
+  Rgba8,
+  /// the etc1 block is also the valid etc2 rgb block
+  Etc1,
+}
+
+/// The BasisLZ supercompression global data, contains the codebooks and the huffman tables
+/// shared by all images.
+pub(crate) struct BasisLzGlobalData {
+  endpoints: Vec<Etc1sEndpoint>,
+  selectors: Vec<Etc1sSelector>,
+  endpoint_pred_model: HuffmanTable,
+  delta_endpoint_model: HuffmanTable,
+  selector_model: HuffmanTable,
+  selector_history_rle_model: HuffmanTable,
+  selector_history_size: usize,
+  images: Vec<ImageDesc>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+  data
+    .get(offset..offset + 2)
+    .map(|v| u16::from_le_bytes(v.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+  data
+    .get(offset..offset + 4)
+    .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+}
+
+impl BasisLzGlobalData {
+  /// the images are ordered by level, then by face
+  pub(crate) fn parse(data: &[u8], image_count: usize) -> Option<Self> {
+    let endpoint_count = read_u16(data, 0)? as usize;
+    let selector_count = read_u16(data, 2)? as usize;
+    let endpoints_length = read_u32(data, 4)? as usize;
+    let selectors_length = read_u32(data, 8)? as usize;
+    let tables_length = read_u32(data, 12)? as usize;
+
+    let images = (0..image_count)
+      .map(|image| {
+        let offset = 20 + image * 20;
+        let slice = |offset| {
+          let start = read_u32(data, offset)? as usize;
+          let length = read_u32(data, offset + 4)? as usize;
+          Some(start..start.checked_add(length)?)
+        };
+        Some(ImageDesc {
+          flags: read_u32(data, offset)?,
+          rgb_slice: slice(offset + 4)?,
+          alpha_slice: slice(offset + 12)?,
+        })
+      })
+      .collect::<Option<Vec<_>>>()?;
+
+    let mut offset = 20 + image_count * 20;
+    let mut next_section = |length: usize| {
+      let section = data.get(offset..offset.checked_add(length)?);
+      offset += length;
+      section
+    };
+    let endpoints_data = next_section(endpoints_length)?;
+    let selectors_data = next_section(selectors_length)?;
+    let tables_data = next_section(tables_length)?;
+
+    let mut reader = BitReader::new(tables_data);
+    let endpoint_pred_model = reader.read_huffman_table()?;
+    let delta_endpoint_model = reader.read_huffman_table()?;
+    let selector_model = reader.read_huffman_table()?;
+    let selector_history_rle_model = reader.read_huffman_table()?;
+    let selector_history_size = reader.read(13)? as usize;
+
+    Some(Self {
+      endpoints: decode_endpoints(endpoints_data, endpoint_count)?,
+      selectors: decode_selectors(selectors_data, selector_count)?,
+      endpoint_pred_model,
+      delta_endpoint_model,
+      selector_model,
+      selector_history_rle_model,
+      selector_history_size,
+      images,
+    })
+  }
+
+  pub(crate) fn has_alpha(&self) -> bool {
+    self
+      .images
+      .iter()
+      .any(|image| !image.alpha_slice.is_empty())
+  }
+
+  /// transcode the image of the level data, the result is appended to the output
+  pub(crate) fn transcode_image(
+    &self,
+    image: usize,
+    level_data: &[u8],
+    (width, height): (usize, usize),
+    target: Etc1sTranscodeTarget,
+    output: &mut Vec<u8>,
+  ) -> Option<()> {
+    let desc = self.images.get(image)?;
+    if desc.flags & IMAGE_FLAG_P_FRAME != 0 {
+      return None;
+    }
+    let blocks_x = width.div_ceil(4);
+    let blocks_y = height.div_ceil(4);
+
+    let rgb = self.decode_slice(level_data.get(desc.rgb_slice.clone())?, blocks_x, blocks_y)?;
+    let alpha = if desc.alpha_slice.is_empty() {
+      None
+    } else {
+      let slice = level_data.get(desc.alpha_slice.clone())?;
+      Some(self.decode_slice(slice, blocks_x, blocks_y)?)
+    };
+
+    match target {
+      Etc1sTranscodeTarget::Etc1 => {
+        for (endpoint, selector) in rgb {
+          output.extend(self.etc1_block(endpoint, selector));
+        }
+      }
+      Etc1sTranscodeTarget::Rgba8 => {
+        let start = output.len();
+        output.resize(start + width * height * 4, 0);
+        let image = &mut output[start..];
+        for (block, (endpoint, selector)) in rgb.iter().enumerate() {
+          let (block_x, block_y) = (block % blocks_x * 4, block / blocks_x * 4);
+          for texel in 0..16 {
+            let (x, y) = (block_x + texel % 4, block_y + texel / 4);
+            if x >= width || y >= height {
+              continue;
+            }
+            let rgb = self.texel(*endpoint, *selector, texel);
+            let alpha = alpha.as_ref().map_or(255, |alpha| {
+              let (endpoint, selector) = alpha[block];
+              self.texel(endpoint, selector, texel)[1]
+            });
+            let offset = (y * width + x) * 4;
+            image[offset..offset + 4].copy_from_slice(&[rgb[0], rgb[1], rgb[2], alpha]);
+          }
+        }
+      }
+    }
+    Some(())
+  }
+
+  fn texel(&self, endpoint: usize, selector: usize, texel: usize) -> [u8; 3] {
+    let endpoint = self.endpoints[endpoint];
+    let modifier =
+      ETC1_INTEN_TABLES[endpoint.inten as usize][self.selectors[selector][texel] as usize];
+    endpoint.color5.map(|c| {
+      let c = ((c << 3) | (c >> 2)) as i32;
+      (c + modifier).clamp(0, 255) as u8
+    })
+  }
+
+  /// the differential mode etc1 block with zero delta
+  fn etc1_block(&self, endpoint: usize, selector: usize) -> [u8; 8] {
+    let endpoint = self.endpoints[endpoint];
+    let mut msb = 0_u16;
+    let mut lsb = 0_u16;
+    for (texel, selector) in self.selectors[selector].iter().enumerate() {
+      let index = LINEAR_TO_ETC1_SELECTOR[*selector as usize] as u16;
+      // the etc1 pixel index is in the column major order
+      let bit = (texel % 4) * 4 + texel / 4;
+      msb |= (index >> 1) << bit;
+      lsb |= (index & 1) << bit;
+    }
+    let [r, g, b] = endpoint.color5.map(|c| c << 3);
+    let [msb0, msb1] = msb.to_be_bytes();
+    let [lsb0, lsb1] = lsb.to_be_bytes();
+    let inten = endpoint.inten;
+    [
+      r,
+      g,
+      b,
+      (inten << 5) | (inten << 2) | 0b10,
+      msb0,
+      msb1,
+      lsb0,
+      lsb1,
+    ]
+  }
+
+  /// return the endpoint and selector index of each block in the raster order
+  fn decode_slice(
+    &self,
+    data: &[u8],
+    blocks_x: usize,
+    blocks_y: usize,
+  ) -> Option<Vec<(usize, usize)>> {
+    let endpoint_count = self.endpoints.len();
+    let selector_count = self.selectors.len();
+    let history_rle_symbol = (selector_count + self.selector_history_size) as u32;
+    let total_blocks = blocks_x * blocks_y;
+
+    let mut reader = BitReader::new(data);
+    let mut history = SelectorHistory {
+      values: vec![0; self.selector_history_size],
+      rover: 0,
+    };
+    let mut selector_rle_count = 0;
+
+    // the endpoint index and the prediction bits of the odd row of each 2x2 block group, for
+    // the current and the upper row
+    let mut preds = [vec![(0, 0); blocks_x], vec![(0, 0); blocks_x]];
+    let mut previous_pred_symbol = 0;
+    let mut pred_repeat_count = 0;
+    let mut previous_endpoint = 0;
+
+    let mut blocks = Vec::with_capacity(total_blocks);
+    for block_y in 0..blocks_y {
+      let current = block_y & 1;
+      let mut pred_bits = 0;
+      for block_x in 0..blocks_x {
+        if block_x & 1 == 0 {
+          if current == 0 {
+            if pred_repeat_count > 0 {
+              pred_repeat_count -= 1;
+              pred_bits = previous_pred_symbol;
+            } else {
+              pred_bits = self.endpoint_pred_model.decode(&mut reader)?;
+              if pred_bits == ENDPOINT_PRED_REPEAT_LAST_SYMBOL {
+                pred_repeat_count = reader.read_vlc(4)? + ENDPOINT_PRED_MIN_REPEAT_COUNT - 1;
+                pred_bits = previous_pred_symbol;
+              } else {
+                previous_pred_symbol = pred_bits;
+              }
+            }
+            preds[current ^ 1][block_x].1 = pred_bits >> 4;
+          } else {
+            pred_bits = preds[current][block_x].1;
+          }
+        }
+
+        let pred = pred_bits & 3;
+        pred_bits >>= 2;
+        let endpoint = match pred {
+          // left
+          0 if block_x > 0 => previous_endpoint,
+          // upper
+          1 if block_y > 0 => preds[current ^ 1][block_x].0,
+          // upper left
+          2 if block_x > 0 && block_y > 0 => preds[current ^ 1][block_x - 1].0,
+          3 => {
+            let delta = self.delta_endpoint_model.decode(&mut reader)? as usize;
+            let endpoint = previous_endpoint + delta;
+            if endpoint >= endpoint_count {
+              endpoint - endpoint_count
+            } else {
+              endpoint
+            }
+          }
+          _ => return None,
+        };
+        if endpoint >= endpoint_count {
+          return None;
+        }
+        preds[current][block_x].0 = endpoint;
+        previous_endpoint = endpoint;
+
+        // the history rle repeats the first history entry
+        let selector_symbol = if selector_rle_count > 0 {
+          selector_rle_count -= 1;
+          selector_count as u32
+        } else {
+          let symbol = self.selector_model.decode(&mut reader)?;
+          if symbol == history_rle_symbol {
+            let run = self.selector_history_rle_model.decode(&mut reader)?;
+            let run = if run == SELECTOR_HISTORY_RLE_COUNT_TOTAL - 1 {
+              reader.read_vlc(7)?
+            } else {
+              run
+            };
+            selector_rle_count = (run + SELECTOR_HISTORY_RLE_COUNT_THRESHOLD) as usize;
+            if selector_rle_count > total_blocks {
+              return None;
+            }
+            selector_rle_count -= 1;
+            selector_count as u32
+          } else {
+            symbol
+          }
+        };
+
+        let selector = if selector_symbol as usize >= selector_count {
+          let index = selector_symbol as usize - selector_count;
+          let selector = *history.values.get(index)?;
+          history.use_index(index);
+          selector as usize
+        } else {
+          if self.selector_history_size > 0 {
+            history.add(selector_symbol);
+          }
+          selector_symbol as usize
+        };
+        if selector >= selector_count {
+          return None;
+        }
+
+        blocks.push((endpoint, selector));
+      }
+    }
+    Some(blocks)
+  }
+}
+
+fn decode_endpoints(data: &[u8], endpoint_count: usize) -> Option<Vec<Etc1sEndpoint>> {
+  let mut reader = BitReader::new(data);
+  let color5_delta_models = [
+    reader.read_huffman_table()?,
+    reader.read_huffman_table()?,
+    reader.read_huffman_table()?,
+  ];
+  let inten_delta_model = reader.read_huffman_table()?;
+  let grayscale = reader.read(1)? == 1;
+
+  let mut previous = Etc1sEndpoint {
+    color5: [16; 3],
+    inten: 0,
+  };
+  (0..endpoint_count)
+    .map(|_| {
+      let inten = (inten_delta_model.decode(&mut reader)? as u8).wrapping_add(previous.inten) & 7;
+      let mut color5 = previous.color5;
+      let channel_count = if grayscale { 1 } else { 3 };
+      for c in &mut color5[..channel_count] {
+        let model = match *c {
+          0..=9 => &color5_delta_models[0],
+          10..=21 => &color5_delta_models[1],
+          _ => &color5_delta_models[2],
+        };
+        *c = c.wrapping_add(model.decode(&mut reader)? as u8) & 31;
+      }
+      if grayscale {
+        color5 = [color5[0]; 3];
+      }
+      previous = Etc1sEndpoint { color5, inten };
+      Some(previous)
+    })
+    .collect()
+}
+
+fn decode_selectors(data: &[u8], selector_count: usize) -> Option<Vec<Etc1sSelector>> {
+  let mut reader = BitReader::new(data);
+  let global_codebook = reader.read(1)? == 1;
+  let hybrid_codebook = reader.read(1)? == 1;
+  if global_codebook || hybrid_codebook {
+    return None;
+  }
+  let raw = reader.read(1)? == 1;
+  let delta_model = if raw {
+    None
+  } else {
+    Some(reader.read_huffman_table()?)
+  };
+
+  let mut previous_rows = [0_u8; 4];
+  (0..selector_count)
+    .map(|index| {
+      let mut selector = [0; 16];
+      for (y, previous_row) in previous_rows.iter_mut().enumerate() {
+        // each row is a byte, the first selector of the delta coding is stored in raw
+        let row = match &delta_model {
+          Some(model) if index > 0 => model.decode(&mut reader)? as u8 ^ *previous_row,
+          _ => reader.read(8)? as u8,
+        };
+        *previous_row = row;
+        for x in 0..4 {
+          selector[y * 4 + x] = (row >> (x * 2)) & 3;
+        }
+      }
+      Some(selector)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+
+  #[derive(Default)]
+  struct BitWriter {
+    data: Vec<u8>,
+    bit: usize,
+  }
+
+  impl BitWriter {
+    fn write(&mut self, value: u32, count: u32) {
+      for i in 0..count {
+        if self.bit.is_multiple_of(8) {
+          self.data.push(0);
+        }
+        self.data[self.bit / 8] |= (((value >> i) & 1) as u8) << (self.bit % 8);
+        self.bit += 1;
+      }
+    }
+
+    /// write the symbol of the table written by the [Self::write_fixed_huffman_table]
+    fn write_symbol(&mut self, symbol: u32, symbol_count: usize) {
+      let size = fixed_code_size(symbol_count);
+      for i in (0..size).rev() {
+        self.write(symbol >> i, 1);
+      }
+    }
+
+    /// write the table which all symbols have the same code size, so the code is the symbol
+    fn write_fixed_huffman_table(&mut self, symbol_count: usize) {
+      self.write(symbol_count as u32, 14);
+      self.write(21, 5);
+      // all code length codes are 5 bits
+      (0..21).for_each(|_| self.write(5, 3));
+      (0..symbol_count).for_each(|_| self.write_symbol(fixed_code_size(symbol_count), 21));
+    }
+  }
+
+  fn fixed_code_size(symbol_count: usize) -> u32 {
+    symbol_count.next_power_of_two().trailing_zeros().max(1)
+  }
+
+  pub(crate) const ENDPOINTS: [([u8; 3], u8); 2] = [([16, 8, 4], 1), ([31, 0, 20], 5)];
+  pub(crate) const SELECTORS: [[u8; 16]; 2] = [
+    [0, 1, 2, 3, 1, 2, 3, 0, 2, 3, 0, 1, 3, 0, 1, 2],
+    [3, 3, 2, 2, 1, 1, 0, 0, 3, 2, 1, 0, 0, 0, 0, 3],
+  ];
+  const HISTORY_SIZE: usize = 2;
+  const SELECTOR_SYMBOL_COUNT: usize = SELECTORS.len() + HISTORY_SIZE + 1;
+
+  /// the size of each level
+  pub(crate) const LEVEL_SIZES: [(usize, usize); 3] = [(16, 8), (8, 4), (4, 2)];
+  /// the endpoint and selector index of each block of each level
+  pub(crate) const LEVEL_BLOCKS: [&[(usize, usize)]; 3] = [
+    &[
+      (0, 0),
+      (1, 1),
+      (0, 0),
+      (1, 0),
+      (0, 0),
+      (0, 1),
+      (0, 1),
+      (0, 0),
+    ],
+    &[(1, 1), (1, 1)],
+    &[(0, 0)],
+  ];
+
+  /// the expected rgb of the texel computed by the etc1s definition
+  pub(crate) fn expected_texel((endpoint, selector): (usize, usize), texel: usize) -> [u8; 3] {
+    let (color5, inten) = ENDPOINTS[endpoint];
+    let modifier = ETC1_INTEN_TABLES[inten as usize][SELECTORS[selector][texel] as usize];
+    color5.map(|c| (((c as i32) << 3 | (c as i32) >> 2) + modifier).clamp(0, 255) as u8)
+  }
+
+  fn write_global_data(images: &[[u32; 5]]) -> Vec<u8> {
+    let mut endpoints = BitWriter::default();
+    (0..3).for_each(|_| endpoints.write_fixed_huffman_table(32));
+    endpoints.write_fixed_huffman_table(8);
+    endpoints.write(0, 1);
+    let (mut previous_color, mut previous_inten) = ([16_u8; 3], 0_u8);
+    for (color5, inten) in ENDPOINTS {
+      endpoints.write_symbol(inten.wrapping_sub(previous_inten) as u32 & 7, 8);
+      for c in 0..3 {
+        let delta = color5[c].wrapping_sub(previous_color[c]) & 31;
+        endpoints.write_symbol(delta as u32, 32);
+      }
+      (previous_color, previous_inten) = (color5, inten);
+    }
+
+    let mut selectors = BitWriter::default();
+    selectors.write(0, 3);
+    selectors.write_fixed_huffman_table(256);
+    let mut previous_rows = [0; 4];
+    for (index, selector) in SELECTORS.iter().enumerate() {
+      for (y, previous_row) in previous_rows.iter_mut().enumerate() {
+        let row = (0..4).fold(0, |row, x| row | (selector[y * 4 + x] as u32) << (x * 2));
+        if index == 0 {
+          selectors.write(row, 8);
+        } else {
+          selectors.write_symbol(row ^ *previous_row, 256);
+        }
+        *previous_row = row;
+      }
+    }
+
+    let mut tables = BitWriter::default();
+    tables.write_fixed_huffman_table(257);
+    tables.write_fixed_huffman_table(ENDPOINTS.len());
+    tables.write_fixed_huffman_table(SELECTOR_SYMBOL_COUNT);
+    tables.write_fixed_huffman_table(64);
+    tables.write(HISTORY_SIZE as u32, 13);
+
+    let mut data = Vec::new();
+    data.extend((ENDPOINTS.len() as u16).to_le_bytes());
+    data.extend((SELECTORS.len() as u16).to_le_bytes());
+    for section in [&endpoints, &selectors, &tables] {
+      data.extend((section.data.len() as u32).to_le_bytes());
+    }
+    data.extend(0_u32.to_le_bytes());
+    images
+      .iter()
+      .flatten()
+      .for_each(|v| data.extend(v.to_le_bytes()));
+    for section in [endpoints, selectors, tables] {
+      data.extend(section.data);
+    }
+    data
+  }
+
+  /// the slices of [LEVEL_BLOCKS], cover the endpoint prediction repeat, the selector history
+  /// and the selector history rle
+  fn write_slices() -> Vec<Vec<u8>> {
+    let pred = |w: &mut BitWriter, symbol| w.write_symbol(symbol, 257);
+    let delta = |w: &mut BitWriter, symbol| w.write_symbol(symbol, ENDPOINTS.len());
+    let selector = |w: &mut BitWriter, symbol| w.write_symbol(symbol, SELECTOR_SYMBOL_COUNT);
+    let history = |index| (SELECTORS.len() + index) as u32;
+    // delta, delta, upper, upper left
+    let group = 3 | (3 << 2) | (1 << 4) | (2 << 6);
+
+    let mut level0 = BitWriter::default();
+    // (0, 0)
+    pred(&mut level0, group);
+    delta(&mut level0, 0);
+    selector(&mut level0, 0);
+    // (1, 0)
+    delta(&mut level0, 1);
+    selector(&mut level0, 1);
+    // (2, 0), repeat the last group for 2 times
+    pred(&mut level0, ENDPOINT_PRED_REPEAT_LAST_SYMBOL);
+    level0.write(0, 5);
+    // the endpoint index wraps
+    delta(&mut level0, 1);
+    // the history rle covers 3 blocks
+    selector(&mut level0, history(HISTORY_SIZE));
+    level0.write_symbol(0, 64);
+    // (3, 0)
+    delta(&mut level0, 1);
+    // (1, 1), the history is [0, 1], then swapped to [1, 0]
+    selector(&mut level0, history(1));
+    // (2, 1)
+    selector(&mut level0, history(0));
+    // (3, 1)
+    selector(&mut level0, 0);
+
+    let mut level1 = BitWriter::default();
+    // delta, left
+    pred(&mut level1, 3);
+    delta(&mut level1, 1);
+    selector(&mut level1, 1);
+    selector(&mut level1, history(0));
+
+    let mut level2 = BitWriter::default();
+    pred(&mut level2, 3);
+    delta(&mut level2, 0);
+    selector(&mut level2, 0);
+
+    vec![level0.data, level1.data, level2.data]
+  }
+
+  /// write the global data and the level data of the [LEVEL_SIZES] image, the alpha slice
+  /// reuses the rgb slice
+  pub(crate) fn write_test_etc1s(alpha: bool) -> (Vec<u8>, Vec<Vec<u8>>) {
+    let slices = write_slices();
+    let images: Vec<_> = slices
+      .iter()
+      .map(|slice| {
+        let length = slice.len() as u32;
+        [0, 0, length, 0, if alpha { length } else { 0 }]
+      })
+      .collect();
+    (write_global_data(&images), slices)
+  }
+
+  fn parse_test_etc1s(alpha: bool) -> (BasisLzGlobalData, Vec<Vec<u8>>) {
+    let (global_data, slices) = write_test_etc1s(alpha);
+    let global_data = BasisLzGlobalData::parse(&global_data, slices.len()).unwrap();
+    (global_data, slices)
+  }
+
+  #[test]
+  fn test_huffman_table() {
+    // sizes 1, 2, 3, 3: the codes are 0, 10, 110, 111
+    let table = HuffmanTable::new(&[2, 1, 3, 3]).unwrap();
+    let mut writer = BitWriter::default();
+    for bit in [0, 1, 0, 1, 1, 0, 1, 1, 1] {
+      writer.write(bit, 1);
+    }
+    let mut reader = BitReader::new(&writer.data);
+    let symbols: Vec<_> = (0..4).map(|_| table.decode(&mut reader).unwrap()).collect();
+    assert_eq!(symbols, [1, 0, 2, 3]);
+
+    assert!(HuffmanTable::new(&[1, 1, 1]).is_none());
+  }
+
+  #[test]
+  fn test_vlc() {
+    let mut writer = BitWriter::default();
+    // 0b10_0101 in 4 bit chunks
+    writer.write(0b1_0101, 5);
+    writer.write(0b0_0010, 5);
+    assert_eq!(BitReader::new(&writer.data).read_vlc(4), Some(0b10_0101));
+  }
+
+  #[test]
+  fn test_codebooks() {
+    let (global_data, _) = parse_test_etc1s(false);
+    for (endpoint, (color5, inten)) in global_data.endpoints.iter().zip(ENDPOINTS) {
+      assert_eq!(endpoint.color5, color5);
+      assert_eq!(endpoint.inten, inten);
+    }
+    assert_eq!(global_data.selectors, SELECTORS);
+    assert_eq!(global_data.selector_history_size, HISTORY_SIZE);
+    assert!(!global_data.has_alpha());
+  }
+
+  #[test]
+  fn test_decode_slices() {
+    let (global_data, slices) = parse_test_etc1s(false);
+    for ((slice, (width, height)), expected) in slices.iter().zip(LEVEL_SIZES).zip(LEVEL_BLOCKS) {
+      let blocks = global_data
+        .decode_slice(slice, width.div_ceil(4), height.div_ceil(4))
+        .unwrap();
+      assert_eq!(blocks, expected);
+    }
+  }
+
+  #[test]
+  fn test_transcode_rgba8() {
+    let (global_data, slices) = parse_test_etc1s(true);
+    assert!(global_data.has_alpha());
+
+    let (width, height) = LEVEL_SIZES[2];
+    let mut output = Vec::new();
+    global_data
+      .transcode_image(
+        2,
+        &slices[2],
+        (width, height),
+        Etc1sTranscodeTarget::Rgba8,
+        &mut output,
+      )
+      .unwrap();
+    assert_eq!(output.len(), width * height * 4);
+    for (texel, rgba) in output.chunks(4).enumerate() {
+      let [r, g, b] = expected_texel(LEVEL_BLOCKS[2][0], texel);
+      assert_eq!(rgba, [r, g, b, g]);
+    }
+  }
+
+  #[test]
+  fn test_transcode_etc1() {
+    let (global_data, slices) = parse_test_etc1s(false);
+    let mut output = Vec::new();
+    global_data
+      .transcode_image(
+        1,
+        &slices[1],
+        LEVEL_SIZES[1],
+        Etc1sTranscodeTarget::Etc1,
+        &mut output,
+      )
+      .unwrap();
+
+    for (block, expected) in output.chunks(8).zip(LEVEL_BLOCKS[1]) {
+      let mut texels = [[0; 4]; 16];
+      rendiation_texture_block_compression::decode_etc2_rgb(block, false, &mut texels);
+      for (texel, rgba) in texels.iter().enumerate() {
+        let [r, g, b] = expected_texel(*expected, texel);
+        assert_eq!(*rgba, [r, g, b, 255]);
+      }
+    }
+  }
+
+  #[test]
+  fn test_invalid_global_data() {
+    let (global_data, slices) = write_test_etc1s(false);
+    assert!(
+      BasisLzGlobalData::parse(&global_data[..global_data.len() - 8], slices.len()).is_none()
+    );
+
+    // the upper prediction is invalid in the first row
+    let (global_data, _) = parse_test_etc1s(false);
+    let mut writer = BitWriter::default();
+    writer.write_symbol(1, 257);
+    assert!(global_data.decode_slice(&writer.data, 1, 1).is_none());
+  }
+}
@@ -0,0 +1,257 @@
+use crate::*;
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 4 + 124;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+fn four_cc(code: &[u8; 4]) -> u32 {
+  u32::from_le_bytes(*code)
+}
+
+/// parse the dds file, the legacy header and the DX10 header are supported
+pub fn parse_dds(data: &[u8]) -> Result<LoadedTexture, TextureContainerLoadError> {
+  if data.get(0..4) != Some(DDS_MAGIC) {
+    return Err(TextureContainerLoadError::InvalidIdentifier);
+  }
+
+  let height = read_u32(data, 12)?;
+  let width = read_u32(data, 16)?;
+  let level_count = read_u32(data, 28)?.max(1) as usize;
+  let pixel_format_flags = read_u32(data, 80)?;
+  let pixel_four_cc = read_u32(data, 84)?;
+  let caps2 = read_u32(data, 112)?;
+
+  if caps2 & DDSCAPS2_VOLUME != 0 {
+    return Err(TextureContainerLoadError::UnsupportedDimension);
+  }
+  let mut is_cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+
+  let mut data_offset = DDS_HEADER_SIZE;
+  let format = if pixel_format_flags & DDPF_FOURCC != 0 && pixel_four_cc == four_cc(b"DX10") {
+    let dxgi_format = read_u32(data, DDS_HEADER_SIZE)?;
+    let misc_flag = read_u32(data, DDS_HEADER_SIZE + 8)?;
+    let array_size = read_u32(data, DDS_HEADER_SIZE + 12)?;
+    if array_size > 1 {
+      return Err(TextureContainerLoadError::UnsupportedDimension);
+    }
+    is_cube |= misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0;
+    data_offset += DX10_HEADER_SIZE;
+    dxgi_format_to_wgpu(dxgi_format).ok_or_else(|| {
+      TextureContainerLoadError::UnsupportedFormat(format!("dxgi format {dxgi_format}"))
+    })?
+  } else if pixel_format_flags & DDPF_FOURCC != 0 {
+    legacy_four_cc_to_wgpu(pixel_four_cc).ok_or_else(|| {
+      let code = pixel_four_cc.to_le_bytes();
+      TextureContainerLoadError::UnsupportedFormat(String::from_utf8_lossy(&code).into())
+    })?
+  } else if pixel_format_flags & DDPF_RGB != 0 {
+    let bit_count = read_u32(data, 88)?;
+    let r_mask = read_u32(data, 92)?;
+    match (bit_count, r_mask) {
+      (32, 0xff) => TextureFormat::Rgba8Unorm,
+      (32, 0xff0000) => TextureFormat::Bgra8Unorm,
+      _ => {
+        return Err(TextureContainerLoadError::UnsupportedFormat(format!(
+          "{bit_count} bits rgb with red mask {r_mask:#x}"
+        )));
+      }
+    }
+  } else {
+    return Err(TextureContainerLoadError::UnsupportedFormat(
+      "unknown pixel format".into(),
+    ));
+  };
+
+  let size = Size::from_u32_pair_min_one((width, height));
+  let face_count = if is_cube { 6 } else { 1 };
+  check_level_count(size, level_count)?;
+
+  // the dds stores all mips of a face, then the next face
+  let mut offset = data_offset;
+  let mut faces = Vec::with_capacity(face_count);
+  for _ in 0..face_count {
+    let mut levels = Vec::with_capacity(level_count);
+    for level in 0..level_count {
+      let byte_size = level_byte_size(format, size, level);
+      let level_data = data
+        .get(offset..offset + byte_size)
+        .ok_or(TextureContainerLoadError::UnexpectedEof)?;
+      levels.push(level_data.to_vec());
+      offset += byte_size;
+    }
+    let mut levels = levels.into_iter();
+    faces.push(GPUBufferImage {
+      data: levels.next().unwrap(),
+      format,
+      size,
+      precomputed_mips: levels.collect(),
+    });
+  }
+
+  if is_cube {
+    Ok(LoadedTexture::Cube(Box::new(faces.try_into().unwrap())))
+  } else {
+    Ok(LoadedTexture::D2(faces.pop().unwrap()))
+  }
+}
+
+fn legacy_four_cc_to_wgpu(code: u32) -> Option<TextureFormat> {
+  use TextureFormat::*;
+  let code = code.to_le_bytes();
+  Some(match &code {
+    b"DXT1" => Bc1RgbaUnorm,
+    b"DXT2" | b"DXT3" => Bc2RgbaUnorm,
+    b"DXT4" | b"DXT5" => Bc3RgbaUnorm,
+    b"ATI1" | b"BC4U" => Bc4RUnorm,
+    b"BC4S" => Bc4RSnorm,
+    b"ATI2" | b"BC5U" => Bc5RgUnorm,
+    b"BC5S" => Bc5RgSnorm,
+    _ => return None,
+  })
+}
+
+fn dxgi_format_to_wgpu(dxgi_format: u32) -> Option<TextureFormat> {
+  use TextureFormat::*;
+  Some(match dxgi_format {
+    2 => Rgba32Float,
+    10 => Rgba16Float,
+    28 => Rgba8Unorm,
+    29 => Rgba8UnormSrgb,
+    41 => R32Float,
+    49 => Rg8Unorm,
+    54 => R16Float,
+    61 => R8Unorm,
+    71 => Bc1RgbaUnorm,
+    72 => Bc1RgbaUnormSrgb,
+    74 => Bc2RgbaUnorm,
+    75 => Bc2RgbaUnormSrgb,
+    77 => Bc3RgbaUnorm,
+    78 => Bc3RgbaUnormSrgb,
+    80 => Bc4RUnorm,
+    81 => Bc4RSnorm,
+    83 => Bc5RgUnorm,
+    84 => Bc5RgSnorm,
+    87 => Bgra8Unorm,
+    91 => Bgra8UnormSrgb,
+    95 => Bc6hRgbUfloat,
+    96 => Bc6hRgbFloat,
+    98 => Bc7RgbaUnorm,
+    99 => Bc7RgbaUnormSrgb,
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn write_test_dds(
+    size: (u32, u32),
+    level_count: u32,
+    pixel_format: impl FnOnce(&mut Vec<u8>),
+    caps2: u32,
+    payload: &[u8],
+  ) -> Vec<u8> {
+    let mut data = DDS_MAGIC.to_vec();
+    data.resize(DDS_HEADER_SIZE, 0);
+    data[4..8].copy_from_slice(&124_u32.to_le_bytes());
+    data[12..16].copy_from_slice(&size.1.to_le_bytes());
+    data[16..20].copy_from_slice(&size.0.to_le_bytes());
+    data[28..32].copy_from_slice(&level_count.to_le_bytes());
+    data[112..116].copy_from_slice(&caps2.to_le_bytes());
+    pixel_format(&mut data);
+    data.extend(payload);
+    data
+  }
+
+  #[test]
+  fn test_dds_legacy_dxt5() {
+    // 8x4 texture with 2 levels, 2 blocks in base and 1 block in the next
+    let payload = [vec![1; 32], vec![2; 16]].concat();
+    let data = write_test_dds(
+      (8, 4),
+      2,
+      |data| {
+        data[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        data[84..88].copy_from_slice(b"DXT5");
+      },
+      0,
+      &payload,
+    );
+
+    let LoadedTexture::D2(image) = parse_dds(&data).unwrap() else {
+      panic!("expect 2d texture");
+    };
+    assert_eq!(image.format, TextureFormat::Bc3RgbaUnorm);
+    assert_eq!(image.data, vec![1; 32]);
+    assert_eq!(image.precomputed_mips, vec![vec![2; 16]]);
+  }
+
+  #[test]
+  fn test_dds_dx10_cube() {
+    // 2x2 rgba8 srgb cube with 2 levels, each face is filled with the face index
+    let payload: Vec<u8> = (0..6)
+      .flat_map(|face| vec![face as u8; 2 * 2 * 4 + 4])
+      .collect();
+    let data = write_test_dds(
+      (2, 2),
+      2,
+      |data| {
+        data[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        data[84..88].copy_from_slice(b"DX10");
+        let dx10 = [29, 3, DDS_RESOURCE_MISC_TEXTURECUBE, 1, 0];
+        dx10.iter().for_each(|v| data.extend(v.to_le_bytes()));
+      },
+      0,
+      &payload,
+    );
+
+    let LoadedTexture::Cube(faces) = parse_dds(&data).unwrap() else {
+      panic!("expect cube texture");
+    };
+    for (face, image) in faces.iter().enumerate() {
+      assert_eq!(image.format, TextureFormat::Rgba8UnormSrgb);
+      assert!(image.data.iter().all(|v| *v as usize == face));
+      assert_eq!(image.precomputed_mips[0], vec![face as u8; 4]);
+    }
+  }
+
+  #[test]
+  fn test_dds_truncated() {
+    let data = write_test_dds(
+      (8, 8),
+      1,
+      |data| {
+        data[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        data[84..88].copy_from_slice(b"DXT1");
+      },
+      0,
+      &[0; 8],
+    );
+    assert!(matches!(
+      parse_dds(&data),
+      Err(TextureContainerLoadError::UnexpectedEof)
+    ));
+  }
+
+  #[test]
+  fn test_dds_level_count_exceeds_mip_chain() {
+    let dxt1 = |data: &mut Vec<u8>| {
+      data[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+      data[84..88].copy_from_slice(b"DXT1");
+    };
+    for level_count in [5, u32::MAX] {
+      let data = write_test_dds((8, 8), level_count, dxt1, 0, &[0; 32]);
+      assert!(matches!(
+        parse_dds(&data),
+        Err(TextureContainerLoadError::UnsupportedDimension)
+      ));
+    }
+  }
+}
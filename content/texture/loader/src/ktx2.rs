@@ -0,0 +1,511 @@
+use crate::*;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+  0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+#[cfg(feature = "basis-universal")]
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+#[cfg(feature = "zstd")]
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+const SUPERCOMPRESSION_ZLIB: u32 = 3;
+
+/// the color model in the data format descriptor
+#[cfg(feature = "basis-universal")]
+const KHR_DF_MODEL_ETC1S: u8 = 163;
+#[cfg(feature = "basis-universal")]
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+
+#[derive(Default)]
+pub struct Ktx2LoadOptions {
+  /// transcode the opaque ETC1S(BasisLZ) texture into the ETC2 instead of the Rgba8, the
+  /// texture with alpha is always transcoded into the Rgba8.
+  #[cfg(feature = "basis-universal")]
+  pub basis_universal_etc2_supported: bool,
+}
+
+struct Ktx2Header {
+  vk_format: u32,
+  width: u32,
+  height: u32,
+  depth: u32,
+  layer_count: u32,
+  face_count: u32,
+  level_count: u32,
+  supercompression: u32,
+  #[cfg(feature = "basis-universal")]
+  dfd_offset: u32,
+  #[cfg(feature = "basis-universal")]
+  sgd_offset: u64,
+  #[cfg(feature = "basis-universal")]
+  sgd_length: u64,
+}
+
+impl Ktx2Header {
+  fn parse(data: &[u8]) -> Result<Self, TextureContainerLoadError> {
+    if data.get(0..12) != Some(KTX2_IDENTIFIER.as_slice()) {
+      return Err(TextureContainerLoadError::InvalidIdentifier);
+    }
+    Ok(Self {
+      vk_format: read_u32(data, 12)?,
+      width: read_u32(data, 20)?,
+      height: read_u32(data, 24)?,
+      depth: read_u32(data, 28)?,
+      layer_count: read_u32(data, 32)?,
+      face_count: read_u32(data, 36)?,
+      level_count: read_u32(data, 40)?,
+      supercompression: read_u32(data, 44)?,
+      #[cfg(feature = "basis-universal")]
+      dfd_offset: read_u32(data, 48)?,
+      #[cfg(feature = "basis-universal")]
+      sgd_offset: read_u64(data, 64)?,
+      #[cfg(feature = "basis-universal")]
+      sgd_length: read_u64(data, 72)?,
+    })
+  }
+}
+
+pub fn parse_ktx2(
+  data: &[u8],
+  options: &Ktx2LoadOptions,
+) -> Result<LoadedTexture, TextureContainerLoadError> {
+  let header = Ktx2Header::parse(data)?;
+
+  if header.depth > 1 || header.layer_count > 1 {
+    return Err(TextureContainerLoadError::UnsupportedDimension);
+  }
+  let face_count = header.face_count as usize;
+  if face_count != 1 && face_count != 6 {
+    return Err(TextureContainerLoadError::UnsupportedDimension);
+  }
+  let size = Size::from_u32_pair_min_one((header.width, header.height));
+  // zero level count means the mipmap should be generated at runtime
+  let level_count = header.level_count.max(1) as usize;
+  check_level_count(size, level_count)?;
+
+  if header.vk_format == 0 {
+    return parse_basis_universal(data, &header, options, size, face_count, level_count);
+  }
+  let format = vk_format_to_wgpu(header.vk_format).ok_or_else(|| {
+    TextureContainerLoadError::UnsupportedFormat(format!("vk format {}", header.vk_format))
+  })?;
+
+  let mut levels: Vec<Vec<u8>> = Vec::with_capacity(level_count);
+  for level in 0..level_count {
+    let (level_data, uncompressed_length) = read_level_data(data, level)?;
+    // the uncompressed length is untrusted, it's used as the decompression limit
+    if uncompressed_length > level_byte_size(format, size, level) * face_count {
+      return Err(TextureContainerLoadError::DecompressFailed(level));
+    }
+
+    let level_data = match header.supercompression {
+      SUPERCOMPRESSION_NONE => level_data.to_vec(),
+      SUPERCOMPRESSION_ZLIB => {
+        miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(level_data, uncompressed_length)
+          .map_err(|_| TextureContainerLoadError::DecompressFailed(level))?
+      }
+      #[cfg(feature = "zstd")]
+      SUPERCOMPRESSION_ZSTD => decompress_zstd(level_data, uncompressed_length)
+        .ok_or(TextureContainerLoadError::DecompressFailed(level))?,
+      scheme => {
+        return Err(TextureContainerLoadError::UnsupportedSupercompression(
+          scheme,
+        ));
+      }
+    };
+
+    levels.push(level_data);
+  }
+
+  split_level_major_images(format, size, face_count, levels)
+}
+
+/// return the level data and its uncompressed byte length
+fn read_level_data(data: &[u8], level: usize) -> Result<(&[u8], usize), TextureContainerLoadError> {
+  let index_offset = 80 + level * 24;
+  let offset = read_u64(data, index_offset)? as usize;
+  let length = read_u64(data, index_offset + 8)? as usize;
+  let uncompressed_length = read_u64(data, index_offset + 16)? as usize;
+  let level_data = data
+    .get(offset..offset.saturating_add(length))
+    .ok_or(TextureContainerLoadError::UnexpectedEof)?;
+  Ok((level_data, uncompressed_length))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(data: &[u8], uncompressed_length: usize) -> Option<Vec<u8>> {
+  // the frame decoder only writes into the spare capacity
+  let mut output = Vec::with_capacity(uncompressed_length);
+  ruzstd::decoding::FrameDecoder::new()
+    .decode_all_to_vec(data, &mut output)
+    .ok()?;
+  (output.len() == uncompressed_length).then_some(output)
+}
+
+#[cfg(feature = "basis-universal")]
+fn parse_basis_universal(
+  data: &[u8],
+  header: &Ktx2Header,
+  options: &Ktx2LoadOptions,
+  size: Size,
+  face_count: usize,
+  level_count: usize,
+) -> Result<LoadedTexture, TextureContainerLoadError> {
+  let dfd_offset = header.dfd_offset as usize;
+  let color_model = *data
+    .get(dfd_offset + 12)
+    .ok_or(TextureContainerLoadError::UnexpectedEof)?;
+  let transfer = *data
+    .get(dfd_offset + 14)
+    .ok_or(TextureContainerLoadError::UnexpectedEof)?;
+
+  if color_model != KHR_DF_MODEL_ETC1S || header.supercompression != SUPERCOMPRESSION_BASIS_LZ {
+    return Err(TextureContainerLoadError::UnsupportedFormat(format!(
+      "basis universal color model {color_model}, only the ETC1S is supported"
+    )));
+  }
+
+  let sgd_start = header.sgd_offset as usize;
+  let global_data = data
+    .get(sgd_start..sgd_start.saturating_add(header.sgd_length as usize))
+    .ok_or(TextureContainerLoadError::UnexpectedEof)?;
+  let global_data = BasisLzGlobalData::parse(global_data, level_count * face_count)
+    .ok_or(TextureContainerLoadError::TranscodeFailed)?;
+
+  use TextureFormat::*;
+  let is_srgb = transfer == KHR_DF_TRANSFER_SRGB;
+  let (format, target) = match (
+    global_data.has_alpha() || !options.basis_universal_etc2_supported,
+    is_srgb,
+  ) {
+    (true, true) => (Rgba8UnormSrgb, Etc1sTranscodeTarget::Rgba8),
+    (true, false) => (Rgba8Unorm, Etc1sTranscodeTarget::Rgba8),
+    (false, true) => (Etc2Rgb8UnormSrgb, Etc1sTranscodeTarget::Etc1),
+    (false, false) => (Etc2Rgb8Unorm, Etc1sTranscodeTarget::Etc1),
+  };
+
+  let mut levels = Vec::with_capacity(level_count);
+  for level in 0..level_count {
+    let (level_data, _) = read_level_data(data, level)?;
+    let level_size = mip_level_size(size, level).into_usize();
+    let mut transcoded = Vec::new();
+    for face in 0..face_count {
+      let image = level * face_count + face;
+      global_data
+        .transcode_image(image, level_data, level_size, target, &mut transcoded)
+        .ok_or(TextureContainerLoadError::TranscodeFailed)?;
+    }
+    levels.push(transcoded);
+  }
+
+  split_level_major_images(format, size, face_count, levels)
+}
+
+#[cfg(not(feature = "basis-universal"))]
+fn parse_basis_universal(
+  _: &[u8],
+  _: &Ktx2Header,
+  _: &Ktx2LoadOptions,
+  _: Size,
+  _: usize,
+  _: usize,
+) -> Result<LoadedTexture, TextureContainerLoadError> {
+  Err(TextureContainerLoadError::TranscoderRequired)
+}
+
+fn vk_format_to_wgpu(vk_format: u32) -> Option<TextureFormat> {
+  use TextureFormat::*;
+  use wgpu_types::{AstcBlock::*, AstcChannel};
+  let astc = |block, srgb: bool| Astc {
+    block,
+    channel: if srgb {
+      AstcChannel::UnormSrgb
+    } else {
+      AstcChannel::Unorm
+    },
+  };
+  Some(match vk_format {
+    9 => R8Unorm,
+    16 => Rg8Unorm,
+    37 => Rgba8Unorm,
+    43 => Rgba8UnormSrgb,
+    44 => Bgra8Unorm,
+    50 => Bgra8UnormSrgb,
+    76 => R16Float,
+    83 => Rg16Float,
+    97 => Rgba16Float,
+    100 => R32Float,
+    109 => Rgba32Float,
+    // wgpu has no bc1 rgb format, the rgba one is compatible with it
+    131 | 133 => Bc1RgbaUnorm,
+    132 | 134 => Bc1RgbaUnormSrgb,
+    135 => Bc2RgbaUnorm,
+    136 => Bc2RgbaUnormSrgb,
+    137 => Bc3RgbaUnorm,
+    138 => Bc3RgbaUnormSrgb,
+    139 => Bc4RUnorm,
+    140 => Bc4RSnorm,
+    141 => Bc5RgUnorm,
+    142 => Bc5RgSnorm,
+    143 => Bc6hRgbUfloat,
+    144 => Bc6hRgbFloat,
+    145 => Bc7RgbaUnorm,
+    146 => Bc7RgbaUnormSrgb,
+    147 => Etc2Rgb8Unorm,
+    148 => Etc2Rgb8UnormSrgb,
+    149 => Etc2Rgb8A1Unorm,
+    150 => Etc2Rgb8A1UnormSrgb,
+    151 => Etc2Rgba8Unorm,
+    152 => Etc2Rgba8UnormSrgb,
+    153 => EacR11Unorm,
+    154 => EacR11Snorm,
+    155 => EacRg11Unorm,
+    156 => EacRg11Snorm,
+    157..=184 => {
+      let blocks = [
+        B4x4, B5x4, B5x5, B6x5, B6x6, B8x5, B8x6, B8x8, B10x5, B10x6, B10x8, B10x10, B12x10, B12x12,
+      ];
+      let index = vk_format - 157;
+      astc(blocks[index as usize / 2], index % 2 == 1)
+    }
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// write the ktx2 file, the levels are the stored data and the uncompressed byte length
+  fn write_ktx2(
+    vk_format: u32,
+    size: (u32, u32),
+    face_count: u32,
+    supercompression: u32,
+    dfd: &[u8],
+    sgd: &[u8],
+    levels: &[(Vec<u8>, usize)],
+  ) -> Vec<u8> {
+    let mut data = KTX2_IDENTIFIER.to_vec();
+    let header = [
+      vk_format,
+      1,
+      size.0,
+      size.1,
+      0,
+      0,
+      face_count,
+      levels.len() as u32,
+      supercompression,
+    ];
+    header.iter().for_each(|v| data.extend(v.to_le_bytes()));
+
+    let dfd_offset = 80 + levels.len() * 24;
+    let sgd_offset = dfd_offset + dfd.len();
+    data.extend((dfd_offset as u32).to_le_bytes());
+    data.extend((dfd.len() as u32).to_le_bytes());
+    // kvd is empty
+    data.extend([0; 8]);
+    data.extend((sgd_offset as u64).to_le_bytes());
+    data.extend((sgd.len() as u64).to_le_bytes());
+
+    let mut offset = sgd_offset + sgd.len();
+    for (level, uncompressed_length) in levels {
+      data.extend((offset as u64).to_le_bytes());
+      data.extend((level.len() as u64).to_le_bytes());
+      data.extend((*uncompressed_length as u64).to_le_bytes());
+      offset += level.len();
+    }
+    data.extend(dfd);
+    data.extend(sgd);
+    levels.iter().for_each(|(level, _)| data.extend(level));
+    data
+  }
+
+  /// write the ktx2 file with the zlib supercompression
+  fn write_test_ktx2(
+    vk_format: u32,
+    size: (u32, u32),
+    face_count: u32,
+    levels: &[Vec<u8>],
+  ) -> Vec<u8> {
+    let levels: Vec<_> = levels
+      .iter()
+      .map(|l| (miniz_oxide::deflate::compress_to_vec_zlib(l, 6), l.len()))
+      .collect();
+    let supercompression = SUPERCOMPRESSION_ZLIB;
+    write_ktx2(
+      vk_format,
+      size,
+      face_count,
+      supercompression,
+      &[],
+      &[],
+      &levels,
+    )
+  }
+
+  /// the data format descriptor only with the color model and the transfer function
+  #[cfg(feature = "basis-universal")]
+  fn write_test_dfd(color_model: u8, transfer: u8) -> Vec<u8> {
+    let mut dfd = vec![0; 44];
+    dfd[0..4].copy_from_slice(&44_u32.to_le_bytes());
+    dfd[10..12].copy_from_slice(&40_u16.to_le_bytes());
+    dfd[12] = color_model;
+    dfd[13] = 1;
+    dfd[14] = transfer;
+    dfd
+  }
+
+  #[test]
+  fn test_ktx2_bc7_mips() {
+    // 8x8 bc7 with 4 levels, every block is filled with the level index
+    let levels: Vec<_> = [4, 1, 1, 1]
+      .iter()
+      .enumerate()
+      .map(|(level, block_count)| vec![level as u8; 16 * block_count])
+      .collect();
+    let data = write_test_ktx2(146, (8, 8), 1, &levels);
+
+    let LoadedTexture::D2(image) = parse_ktx2(&data, &Default::default()).unwrap() else {
+      panic!("expect 2d texture");
+    };
+    assert_eq!(image.format, TextureFormat::Bc7RgbaUnormSrgb);
+    assert_eq!(image.size.into_u32(), (8, 8));
+    assert_eq!(image.mip_level_count(), 4);
+    assert_eq!(image.data, levels[0]);
+    assert_eq!(image.precomputed_mips[2], levels[3]);
+  }
+
+  #[test]
+  fn test_ktx2_cube() {
+    // 4x4 rgba8 cube with 2 levels, each face is filled with the face index
+    let level = |size: usize| {
+      (0..6)
+        .flat_map(|face| vec![face as u8; size * size * 4])
+        .collect()
+    };
+    let levels = vec![level(4), level(2)];
+    let data = write_test_ktx2(37, (4, 4), 6, &levels);
+
+    let LoadedTexture::Cube(faces) = parse_ktx2(&data, &Default::default()).unwrap() else {
+      panic!("expect cube texture");
+    };
+    for (face, image) in faces.iter().enumerate() {
+      assert_eq!(image.mip_level_count(), 2);
+      assert!(image.data.iter().all(|v| *v as usize == face));
+      assert_eq!(image.precomputed_mips[0].len(), 2 * 2 * 4);
+    }
+  }
+
+  #[test]
+  fn test_ktx2_level_count_exceeds_mip_chain() {
+    let levels = vec![vec![0; 64]; 4];
+    let mut data = write_test_ktx2(37, (4, 4), 1, &levels);
+    assert!(matches!(
+      parse_ktx2(&data, &Default::default()),
+      Err(TextureContainerLoadError::UnsupportedDimension)
+    ));
+    data[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+      parse_ktx2(&data, &Default::default()),
+      Err(TextureContainerLoadError::UnsupportedDimension)
+    ));
+  }
+
+  #[test]
+  fn test_ktx2_invalid() {
+    assert!(matches!(
+      parse_ktx2(&[0; 100], &Default::default()),
+      Err(TextureContainerLoadError::InvalidIdentifier)
+    ));
+    let data = write_test_ktx2(1000, (4, 4), 1, &[vec![0; 64]]);
+    assert!(matches!(
+      parse_ktx2(&data, &Default::default()),
+      Err(TextureContainerLoadError::UnsupportedFormat(_))
+    ));
+  }
+
+  #[cfg(feature = "zstd")]
+  #[test]
+  fn test_ktx2_zstd() {
+    let level: Vec<_> = (0..4 * 4 * 4).map(|v| (v % 7) as u8).collect();
+    let compressed = ruzstd::encoding::compress_to_vec(
+      level.as_slice(),
+      ruzstd::encoding::CompressionLevel::Fastest,
+    );
+    let levels = [(compressed, level.len())];
+    let data = write_ktx2(37, (4, 4), 1, SUPERCOMPRESSION_ZSTD, &[], &[], &levels);
+
+    let LoadedTexture::D2(image) = parse_ktx2(&data, &Default::default()).unwrap() else {
+      panic!("expect 2d texture");
+    };
+    assert_eq!(image.data, level);
+
+    // the uncompressed length mismatches, or exceeds the expected level size
+    for uncompressed_length in [level.len() - 1, level.len() + 1, usize::MAX] {
+      let levels = [(levels[0].0.clone(), uncompressed_length)];
+      let data = write_ktx2(37, (4, 4), 1, SUPERCOMPRESSION_ZSTD, &[], &[], &levels);
+      assert!(matches!(
+        parse_ktx2(&data, &Default::default()),
+        Err(TextureContainerLoadError::DecompressFailed(0))
+      ));
+    }
+  }
+
+  #[cfg(feature = "basis-universal")]
+  fn write_test_etc1s_ktx2(alpha: bool) -> Vec<u8> {
+    let (sgd, slices) = crate::basis::tests::write_test_etc1s(alpha);
+    let levels: Vec<_> = slices.into_iter().map(|slice| (slice, 0)).collect();
+    let dfd = write_test_dfd(KHR_DF_MODEL_ETC1S, KHR_DF_TRANSFER_SRGB);
+    let supercompression = SUPERCOMPRESSION_BASIS_LZ;
+    write_ktx2(0, (16, 8), 1, supercompression, &dfd, &sgd, &levels)
+  }
+
+  #[cfg(feature = "basis-universal")]
+  #[test]
+  fn test_ktx2_etc1s() {
+    use crate::basis::tests::*;
+
+    let data = write_test_etc1s_ktx2(true);
+    let LoadedTexture::D2(image) = parse_ktx2(&data, &Default::default()).unwrap() else {
+      panic!("expect 2d texture");
+    };
+    assert_eq!(image.format, TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(image.mip_level_count(), 3);
+    let levels = std::iter::once(&image.data).chain(&image.precomputed_mips);
+    for ((level, (width, _)), blocks) in levels.zip(LEVEL_SIZES).zip(LEVEL_BLOCKS) {
+      for (texel, rgba) in level.chunks(4).enumerate() {
+        let (x, y) = (texel % width, texel / width);
+        let block = blocks[y / 4 * width.div_ceil(4) + x / 4];
+        let [r, g, b] = expected_texel(block, (y % 4) * 4 + x % 4);
+        assert_eq!(rgba, [r, g, b, g]);
+      }
+    }
+
+    // the opaque texture is kept block compressed if the etc2 is supported
+    let data = write_test_etc1s_ktx2(false);
+    let options = Ktx2LoadOptions {
+      basis_universal_etc2_supported: true,
+    };
+    let LoadedTexture::D2(image) = parse_ktx2(&data, &options).unwrap() else {
+      panic!("expect 2d texture");
+    };
+    assert_eq!(image.format, TextureFormat::Etc2Rgb8UnormSrgb);
+    assert_eq!(image.data.len(), LEVEL_BLOCKS[0].len() * 8);
+    assert_eq!(image.precomputed_mips[1].len(), 8);
+  }
+
+  #[cfg(feature = "basis-universal")]
+  #[test]
+  fn test_ktx2_uastc_unsupported() {
+    let dfd = write_test_dfd(166, KHR_DF_TRANSFER_SRGB);
+    let levels = [(vec![0; 16], 16)];
+    let data = write_ktx2(0, (4, 4), 1, SUPERCOMPRESSION_NONE, &dfd, &[], &levels);
+    assert!(matches!(
+      parse_ktx2(&data, &Default::default()),
+      Err(TextureContainerLoadError::UnsupportedFormat(_))
+    ));
+  }
+}
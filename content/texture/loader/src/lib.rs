@@ -8,6 +8,16 @@ use image::*;
 use rendiation_algebra::*;
 use rendiation_texture_core::*;
 
+mod dds;
+mod ktx2;
+pub use dds::*;
+pub use ktx2::*;
+
+#[cfg(feature = "basis-universal")]
+mod basis;
+#[cfg(feature = "basis-universal")]
+use basis::*;
+
 pub struct ImageLibContainerWrap<T>(pub T);
 
 impl<P, C> Texture2D for ImageLibContainerWrap<ImageBuffer<P, C>>
@@ -49,8 +59,133 @@ impl Texture2dInitAble for ImageLibContainerWrap<ImageBuffer<Rgba<u8>, Vec<u8>>>
   }
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum TextureContainerLoadError {
+  #[error("io error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("invalid file identifier")]
+  InvalidIdentifier,
+  #[error("unexpected end of file")]
+  UnexpectedEof,
+  #[error("unsupported format: {0}")]
+  UnsupportedFormat(String),
+  #[error("unsupported supercompression scheme: {0}")]
+  UnsupportedSupercompression(u32),
+  #[error("array and 3d texture, or the level count exceeds the full mip chain are not supported")]
+  UnsupportedDimension,
+  #[error("failed to decompress level {0}")]
+  DecompressFailed(usize),
+  #[error("basis universal texture requires the basis-universal feature")]
+  TranscoderRequired,
+  #[error("failed to transcode basis universal texture")]
+  TranscodeFailed,
+}
+
+/// The texture loaded from the container format like KTX2 and DDS, the precomputed mips are
+/// kept in the image.
+pub enum LoadedTexture {
+  D2(GPUBufferImage),
+  /// in the order of +x, -x, +y, -y, +z, -z
+  Cube(Box<[GPUBufferImage; 6]>),
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, TextureContainerLoadError> {
+  data
+    .get(offset..offset + 4)
+    .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+    .ok_or(TextureContainerLoadError::UnexpectedEof)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, TextureContainerLoadError> {
+  data
+    .get(offset..offset + 8)
+    .map(|v| u64::from_le_bytes(v.try_into().unwrap()))
+    .ok_or(TextureContainerLoadError::UnexpectedEof)
+}
+
+fn level_byte_size(format: TextureFormat, size: Size, level: usize) -> usize {
+  let (width, height) = mip_level_size(size, level).into_usize();
+  let (block_width, block_height) = format.block_dimensions();
+  let block_size = format.block_copy_size(None).unwrap() as usize;
+  width.div_ceil(block_width as usize) * height.div_ceil(block_height as usize) * block_size
+}
+
+/// the level count is untrusted, reject the one exceeds the full mip chain of the size
+fn check_level_count(size: Size, level_count: usize) -> Result<(), TextureContainerLoadError> {
+  let (width, height) = size.into_usize();
+  let max_level_count = width.max(height).ilog2() as usize + 1;
+  if level_count > max_level_count {
+    return Err(TextureContainerLoadError::UnsupportedDimension);
+  }
+  Ok(())
+}
+
+/// split the levels which contain all faces into the per face images
+fn split_level_major_images(
+  format: TextureFormat,
+  size: Size,
+  face_count: usize,
+  levels: Vec<Vec<u8>>,
+) -> Result<LoadedTexture, TextureContainerLoadError> {
+  let mut faces: Vec<Vec<Vec<u8>>> = vec![Vec::with_capacity(levels.len()); face_count];
+  for (level, level_data) in levels.iter().enumerate() {
+    let face_size = level_byte_size(format, size, level);
+    if level_data.len() < face_size * face_count {
+      return Err(TextureContainerLoadError::UnexpectedEof);
+    }
+    for (face, face_levels) in faces.iter_mut().enumerate() {
+      face_levels.push(level_data[face * face_size..(face + 1) * face_size].to_vec());
+    }
+  }
+
+  let mut images = faces.into_iter().map(|levels| {
+    let mut levels = levels.into_iter();
+    GPUBufferImage {
+      data: levels.next().unwrap(),
+      format,
+      size,
+      precomputed_mips: levels.collect(),
+    }
+  });
+
+  if face_count == 6 {
+    let faces: Vec<_> = images.collect();
+    Ok(LoadedTexture::Cube(Box::new(faces.try_into().unwrap())))
+  } else {
+    Ok(LoadedTexture::D2(images.next().unwrap()))
+  }
+}
+
+/// load the KTX2 or DDS file by the extension, the Basis Universal KTX2 is transcoded into
+/// Rgba8, use [parse_ktx2] with the options to keep it block compressed.
+pub fn load_texture_container(
+  path: impl AsRef<Path>,
+) -> Result<LoadedTexture, TextureContainerLoadError> {
+  let path = path.as_ref();
+  let data = std::fs::read(path)?;
+  match path.extension().and_then(|e| e.to_str()) {
+    Some(e) if e.eq_ignore_ascii_case("dds") => parse_dds(&data),
+    _ => parse_ktx2(&data, &Default::default()),
+  }
+}
+
+fn is_texture_container_path(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|e| e.to_str())
+    .is_some_and(|e| e.eq_ignore_ascii_case("ktx2") || e.eq_ignore_ascii_case("dds"))
+}
+
 // todo texture loader should passed in and config ability freely
 pub fn load_tex(path: impl AsRef<Path>) -> GPUBufferImage {
+  let path = path.as_ref();
+  if is_texture_container_path(path) {
+    match load_texture_container(path).unwrap() {
+      LoadedTexture::D2(image) => return image,
+      LoadedTexture::Cube(_) => panic!("expect 2d texture, but cube texture is loaded"),
+    }
+  }
+
   use image::ImageReader;
   let img = ImageReader::open(path).unwrap().decode().unwrap();
   match img {
@@ -59,14 +194,24 @@ pub fn load_tex(path: impl AsRef<Path>) -> GPUBufferImage {
       let size = img.size();
       let format = TextureFormat::Rgba8UnormSrgb;
       let data = img.0.into_raw();
-      GPUBufferImage { data, format, size }
+      GPUBufferImage {
+        data,
+        format,
+        size,
+        precomputed_mips: Vec::new(),
+      }
     }
     image::DynamicImage::ImageRgb8(img) => {
       let img = ImageLibContainerWrap(img);
       let size = img.size();
       let format = TextureFormat::Rgba8UnormSrgb;
       let data = create_padding_buffer(img.0.as_raw(), 3, &[255]);
-      GPUBufferImage { data, format, size }
+      GPUBufferImage {
+        data,
+        format,
+        size,
+        precomputed_mips: Vec::new(),
+      }
     }
     _ => panic!("unsupported texture type"),
   }
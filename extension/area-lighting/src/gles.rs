@@ -1,3 +1,6 @@
+use std::sync::Arc;
+
+use fast_hash_collection::FastHashMap;
 use rendiation_scene_rendering_gpu_gles::*;
 
 use crate::*;
@@ -77,10 +80,65 @@ pub fn create_area_light_uniform(
   compute_light_list(iter_lights)
 }
 
+/// the illuminance under which the area light is considered as no contribution, used to estimate
+/// the range of the area light shadow
+pub const AREA_LIGHT_SHADOW_CUTOFF_ILLUMINANCE: f32 = 0.01;
+/// the shadow frustum is limited to this field of view, as the shadow map resolution is wasted on
+/// the wide angle perspective projection
+pub const AREA_LIGHT_SHADOW_MAX_FOV_DEGREE: f32 = 150.;
+
+/// derive the shadow camera projection of the area light from its world space size and intensity.
+/// the shadow camera is placed at the light center and looks at the lit side, the region outside
+/// of the shadow frustum is not shadowed.
+///
+/// the light is treated as a lambertian emitter with the same power, its illuminance above the
+/// cutoff reaches r(θ) = r0 * sqrt(cos θ) from the light. the far plane is at the end of this
+/// range plus the light extent, and the frustum contains the widest point of the range
+/// (tan²θ = 2) offset by the light half extent, so the larger lights get the wider frustum. the
+/// near plane is relative to the light size, the blockers nearer than that are inside the light
+/// extent and can not be resolved from the light center anyway.
+pub fn area_light_shadow_projection(
+  world_size: Vec2<f32>,
+  intensity: Vec3<f32>,
+) -> PerspectiveProjection<f32> {
+  let half_extent = world_size * 0.5;
+  let half_diagonal = half_extent.length();
+  let area = world_size.x * world_size.y;
+  let range = (intensity.max_channel() * area / AREA_LIGHT_SHADOW_CUTOFF_ILLUMINANCE).sqrt();
+
+  let near = (half_extent.x.min(half_extent.y) * 0.1).max(0.001);
+  let far = (range + half_diagonal).max(near * 2.);
+
+  let cos_widest = 1. / 3_f32.sqrt();
+  let widest_distance = range * cos_widest.sqrt();
+  let lateral = widest_distance * (1. - cos_widest * cos_widest).sqrt() + half_diagonal;
+  let depth = (widest_distance * cos_widest).max(near);
+  let fov = (2. * lateral.atan2(depth).to_degrees()).min(AREA_LIGHT_SHADOW_MAX_FOV_DEGREE);
+
+  PerspectiveProjection {
+    near,
+    far,
+    fov: Deg::by(fov),
+    aspect: 1.,
+  }
+}
+
+pub struct AreaLightShadowGPUData {
+  /// scene entity -> per-scene shadow info, indexed by the light uniform array index
+  pub uniforms: FastHashMap<RawEntityHandle, UniformArray<BasicShadowMapInfo, MAX_SHADOW_COUNT>>,
+  pub shadow_computer: Arc<dyn AbstractShadowComputer>,
+  pub bias_behavior: ShadowBiasBehaviorConfig,
+  pub reversed_depth: bool,
+}
+
 pub struct SceneAreaLightingProvider {
   pub ltc_1: GPU2DTextureView,
   pub ltc_2: GPU2DTextureView,
   pub uniform: LockReadGuardHolder<LightUniformInfo<LTCAreaLightUniform>>,
+  pub shadow: Option<AreaLightShadowGPUData>,
+  /// only shade the lights with shadow, the others are shaded by another light system, for
+  /// example the clustered lighting
+  pub only_shadowed: bool,
 }
 
 impl LightSystemSceneProvider for SceneAreaLightingProvider {
@@ -91,24 +149,42 @@ impl LightSystemSceneProvider for SceneAreaLightingProvider {
   ) -> Option<Box<dyn LightingComputeComponent>> {
     let lights = self.uniform.uniform.get(scene.raw_handle_ref())?.clone();
 
+    let shadow = self.shadow.as_ref().map(|shadow| BasicShadowMapComponent {
+      info: shadow.uniforms.get(scene.raw_handle_ref()).unwrap().clone(),
+      bias_behavior: shadow.bias_behavior,
+      reversed_depth: shadow.reversed_depth,
+      shadow_computer: shadow.shadow_computer.clone(),
+    });
+
     Some(Box::new(LTCLightingComputeComponent {
       ltc_1: self.ltc_1.clone(),
       ltc_2: self.ltc_2.clone(),
       uniforms: lights,
+      shadow,
+      only_shadowed: self.only_shadowed,
     }))
   }
 }
 
 pub const MAX_GLES_AREA_LIGHT_COUNT: usize = 8;
-type UniformArray = UniformArrayWithLengthInfo<LTCAreaLightUniform, MAX_GLES_AREA_LIGHT_COUNT>;
+type LightUniformArray = UniformArrayWithLengthInfo<LTCAreaLightUniform, MAX_GLES_AREA_LIGHT_COUNT>;
 
 pub struct LTCLightingComputeComponent {
   ltc_1: GPU2DTextureView,
   ltc_2: GPU2DTextureView,
-  uniforms: UniformBufferCachedDataView<UniformArray>,
+  uniforms: UniformBufferCachedDataView<LightUniformArray>,
+  shadow: Option<BasicShadowMapComponent>,
+  only_shadowed: bool,
 }
 impl ShaderHashProvider for LTCLightingComputeComponent {
   shader_hash_type_id! {}
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    hasher.hash(self.shadow.is_some());
+    hasher.hash(self.only_shadowed);
+    if let Some(shadow) = &self.shadow {
+      shadow.hash_pipeline(hasher);
+    }
+  }
 }
 
 impl LightingComputeComponent for LTCLightingComputeComponent {
@@ -124,6 +200,8 @@ impl LightingComputeComponent for LTCLightingComputeComponent {
         ltc_2: binding.bind_by(&self.ltc_2),
         sampler: binding.bind_by(&ImmediateGPUSamplerViewBind),
       },
+      shadow: self.shadow.as_ref().map(|s| s.bind_shader(binding)),
+      only_shadowed: self.only_shadowed,
     })
   }
 
@@ -132,12 +210,46 @@ impl LightingComputeComponent for LTCLightingComputeComponent {
     ctx.bind(&self.ltc_1);
     ctx.bind(&self.ltc_2);
     ctx.bind_immediate_sampler(&TextureSampler::default().with_double_linear().into_gpu());
+    if let Some(s) = &self.shadow {
+      s.bind_pass(ctx);
+    }
   }
 }
 
 struct LTCLightingComputeInvocation {
-  uniforms: ShaderReadonlyPtrOf<UniformArray>,
+  uniforms: ShaderReadonlyPtrOf<LightUniformArray>,
   lut: LTCxLUTxInvocation,
+  shadow: Option<BasicShadowMapInvocation>,
+  only_shadowed: bool,
+}
+
+impl LTCLightingComputeInvocation {
+  fn compute_shadow_occlusion(
+    &self,
+    shadow_idx: Node<u32>,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> Node<f32> {
+    let Some(shadow) = &self.shadow else {
+      return val(1.);
+    };
+
+    let camera = geom_ctx.camera_world_position;
+    // the shadow frustum is derived per light, see area_light_shadow_projection
+    let in_shadow_frustum = shadow.is_in_shadow_frustum(geom_ctx.position, shadow_idx, camera);
+
+    in_shadow_frustum.select_branched(
+      || {
+        shadow.query_shadow_occlusion_by_idx(
+          geom_ctx.position,
+          geom_ctx.normal,
+          shadow_idx,
+          geom_ctx.fragment_position.xy(),
+          camera,
+        )
+      },
+      || val(1.),
+    )
+  }
 }
 
 impl LightingComputeInvocation for LTCLightingComputeInvocation {
@@ -150,29 +262,38 @@ impl LightingComputeInvocation for LTCLightingComputeInvocation {
     let diffuse = val(Vec3::<f32>::splat(0.)).make_local_var();
     let lut = self.lut;
 
+    let only_shadowed = self.shadow.as_ref().filter(|_| self.only_shadowed);
+
     self
       .uniforms
       .clone()
       .into_shader_iter()
-      .for_each(|(_, u), _| {
-        let u = u.load().expand();
-        let r = LTCRectLightingCompute {
-          light: ENode::<LTCRectLight> {
-            p1: hpt_uniform_to_hpt(u.p1),
-            p2: hpt_uniform_to_hpt(u.p2),
-            p3: hpt_uniform_to_hpt(u.p3),
-            p4: hpt_uniform_to_hpt(u.p4),
-            intensity: u.intensity,
-            double_side: u.double_side,
-            is_disk: u.is_disk,
+      .for_each(|(shadow_idx, u), _| {
+        let should_shade = match only_shadowed {
+          Some(shadow) => shadow.is_shadow_enabled(shadow_idx),
+          None => val(true),
+        };
+        if_by(should_shade, || {
+          let u = u.load().expand();
+          let occlusion = self.compute_shadow_occlusion(shadow_idx, geom_ctx);
+          let r = LTCRectLightingCompute {
+            light: ENode::<LTCRectLight> {
+              p1: hpt_uniform_to_hpt(u.p1),
+              p2: hpt_uniform_to_hpt(u.p2),
+              p3: hpt_uniform_to_hpt(u.p3),
+              p4: hpt_uniform_to_hpt(u.p4),
+              intensity: u.intensity,
+              double_side: u.double_side,
+              is_disk: u.is_disk,
+            }
+            .construct(),
+            lut,
           }
-          .construct(),
-          lut,
-        }
-        .compute_lights(shading, geom_ctx);
+          .compute_lights(shading, geom_ctx);
 
-        specular.store(specular.load() + r.specular);
-        diffuse.store(diffuse.load() + r.diffuse);
+          specular.store(specular.load() + r.specular * occlusion);
+          diffuse.store(diffuse.load() + r.diffuse * occlusion);
+        });
       });
 
     ENode::<ShaderLightingResult> {
@@ -181,3 +302,38 @@ impl LightingComputeInvocation for LTCLightingComputeInvocation {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_area_light_shadow_projection() {
+    let projection = area_light_shadow_projection(Vec2::new(1., 1.), Vec3::one());
+    // the illuminance range is sqrt(1 * 1 / 0.01)
+    assert!((projection.far - (10. + 0.5_f32.sqrt())).abs() < 1e-4);
+    assert!((projection.near - 0.05).abs() < 1e-6);
+
+    // the range grows with the square root of the light power
+    let brighter = area_light_shadow_projection(Vec2::new(1., 1.), Vec3::new(4., 1., 1.));
+    assert!((brighter.far - (20. + 0.5_f32.sqrt())).abs() < 1e-4);
+
+    // the small light covers the widest point of the lambertian range
+    let small = area_light_shadow_projection(Vec2::splat(0.001), Vec3::splat(1e6));
+    let expect = 2. * 2_f32.sqrt().atan().to_degrees();
+    assert!(
+      (small.fov.value - expect).abs() < 0.1,
+      "{}",
+      small.fov.value
+    );
+
+    // the light extent widens the frustum, up to the max fov
+    assert!(projection.fov.value > small.fov.value);
+    let dim = area_light_shadow_projection(Vec2::new(4., 4.), Vec3::splat(0.0001));
+    assert_eq!(dim.fov.value, AREA_LIGHT_SHADOW_MAX_FOV_DEGREE);
+
+    // the degenerated light still has a valid depth range
+    let empty = area_light_shadow_projection(Vec2::zero(), Vec3::zero());
+    assert!(empty.near > 0. && empty.far > empty.near);
+  }
+}
@@ -2,8 +2,10 @@ use database::*;
 use rendiation_algebra::*;
 use rendiation_lighting_gpu_system::*;
 use rendiation_lighting_ltc::*;
+use rendiation_lighting_shadow_map::*;
 use rendiation_lighting_transport::*;
 use rendiation_scene_core::*;
+use rendiation_scene_rendering_gpu_base::*;
 use rendiation_shader_api::*;
 use rendiation_texture_core::*;
 use rendiation_texture_gpu_base::*;
@@ -12,6 +14,8 @@ use rendiation_webgpu_hook_utils::*;
 
 mod gles;
 pub use gles::*;
+mod storage;
+pub use storage::*;
 
 pub fn register_area_lighting_data_model() {
   global_database()
@@ -0,0 +1,200 @@
+use crate::*;
+
+/// the storage version of the area light data. unlike the uniform version, the vertex is not
+/// precalculated, because the world matrix and the size are updated separately.
+#[repr(C)]
+#[std430_layout]
+#[derive(Copy, Clone, ShaderStruct, Default)]
+pub struct AreaLightStorage {
+  pub position: HighPrecisionTranslationStorage,
+  /// the world space x axis of the light node, the scale is included
+  pub axis_x: Vec3<f32>,
+  /// the world space y axis of the light node, the scale is included
+  pub axis_y: Vec3<f32>,
+  pub half_size: Vec2<f32>,
+  pub intensity: Vec3<f32>,
+  pub double_side: Bool,
+  pub is_disk: Bool,
+}
+
+pub type AreaLightGPUStorage = (
+  AbstractReadonlyStorageBuffer<[AreaLightStorage]>,
+  MultiAccessGPUData,
+);
+
+pub fn use_area_light_storage(cx: &mut QueryGPUHookCx) -> Option<AreaLightGPUStorage> {
+  let (cx, light) = cx.use_storage_buffer("area lights", 128, u32::MAX);
+
+  cx.use_changes::<AreaLightIntensity>().update_storage_array(
+    cx,
+    light,
+    offset_of!(AreaLightStorage, intensity),
+  );
+
+  cx.use_changes::<AreaLightSize>()
+    .map_changes(|size| size / 2.)
+    .update_storage_array(cx, light, offset_of!(AreaLightStorage, half_size));
+
+  cx.use_changes::<AreaLightIsDoubleSide>()
+    .map_changes(Bool::from)
+    .update_storage_array(cx, light, offset_of!(AreaLightStorage, double_side));
+
+  cx.use_changes::<AreaLightIsRound>()
+    .map_changes(Bool::from)
+    .update_storage_array(cx, light, offset_of!(AreaLightStorage, is_disk));
+
+  let (fanout, fanout_) = use_global_node_world_mat(cx)
+    .fanout(cx.use_db_rev_ref_tri_view::<AreaLightRefNode>(), cx)
+    .fork();
+
+  let (fanout_, fanout__) = fanout_.fork();
+
+  fanout
+    .into_delta_change()
+    .map(|change| change.collective_map(|mat| into_hpt(mat.position()).into_storage()))
+    .update_storage_array(cx, light, offset_of!(AreaLightStorage, position));
+
+  fanout_
+    .into_delta_change()
+    .map(|change| change.collective_map(|mat| mat.right().into_f32()))
+    .update_storage_array(cx, light, offset_of!(AreaLightStorage, axis_x));
+
+  fanout__
+    .into_delta_change()
+    .map(|change| change.collective_map(|mat| mat.up().into_f32()))
+    .update_storage_array(cx, light, offset_of!(AreaLightStorage, axis_y));
+
+  light.use_max_item_count_by_db_entity::<AreaLightEntity>(cx);
+  light.use_update(cx);
+
+  let config = MultiAccessGPUDataBuilderInit {
+    max_possible_many_count: u32::MAX,
+    max_possible_one_count: u32::MAX,
+    init_many_count_capacity: 128,
+    init_one_count_capacity: 128,
+  };
+  let updates = cx.use_db_rev_ref_tri_view::<AreaLightRefScene>();
+  let multi_access = use_multi_access_gpu(cx, &config, updates, "area light");
+
+  cx.when_render(|| {
+    let light = light.get_gpu_buffer();
+    (light, multi_access.unwrap())
+  })
+}
+
+/// expand the storage data into the light vertices. the vertex order matches the uniform version.
+pub fn area_light_storage_to_ltc_light(light: ENode<AreaLightStorage>) -> Node<LTCRectLight> {
+  let position = hpt_storage_to_hpt(light.position);
+  let x = light.axis_x * light.half_size.x();
+  let y = light.axis_y * light.half_size.y();
+
+  let vertex = |offset: Node<Vec3<f32>>| {
+    let offset = ENode::<HighPrecisionTranslation> {
+      f1: offset,
+      f2: val(Vec3::zero()),
+    }
+    .construct();
+    hpt_compose_hpt(position, offset)
+  };
+
+  ENode::<LTCRectLight> {
+    p1: vertex(x + y),
+    p2: vertex(-x + y),
+    p3: vertex(-x - y),
+    p4: vertex(x - y),
+    intensity: light.intensity,
+    double_side: light.double_side,
+    is_disk: light.is_disk,
+  }
+  .construct()
+}
+
+/// the storage version of [SceneAreaLightingProvider]. the light count of each scene is not
+/// limited, and the lights are not shadowed.
+pub struct SceneAreaLightingStorageProvider {
+  pub ltc_1: GPU2DTextureView,
+  pub ltc_2: GPU2DTextureView,
+  pub lights: AreaLightGPUStorage,
+}
+
+impl LightSystemSceneProvider for SceneAreaLightingStorageProvider {
+  fn get_scene_lighting(
+    &self,
+    _scene: EntityHandle<SceneEntity>,
+    _camera: EntityHandle<SceneCameraEntity>,
+  ) -> Option<Box<dyn LightingComputeComponent>> {
+    Some(Box::new(LTCStorageLightingComputeComponent {
+      ltc_1: self.ltc_1.clone(),
+      ltc_2: self.ltc_2.clone(),
+      light_data: self.lights.0.clone(),
+      light_accessor: self.lights.1.clone(),
+    }))
+  }
+}
+
+pub struct LTCStorageLightingComputeComponent {
+  ltc_1: GPU2DTextureView,
+  ltc_2: GPU2DTextureView,
+  light_data: AbstractReadonlyStorageBuffer<[AreaLightStorage]>,
+  light_accessor: MultiAccessGPUData,
+}
+
+impl ShaderHashProvider for LTCStorageLightingComputeComponent {
+  shader_hash_type_id! {}
+}
+
+impl LightingComputeComponent for LTCStorageLightingComputeComponent {
+  fn build_light_compute_invocation(
+    &self,
+    binding: &mut ShaderBindGroupBuilder,
+    scene_id: Node<u32>,
+  ) -> Box<dyn LightingComputeInvocation> {
+    Box::new(LTCStorageLightingComputeInvocation {
+      scene_id,
+      light_accessor: self.light_accessor.build(binding),
+      light_data: binding.bind_by(&self.light_data),
+      lut: LTCxLUTxInvocation {
+        ltc_1: binding.bind_by(&self.ltc_1),
+        ltc_2: binding.bind_by(&self.ltc_2),
+        sampler: binding.bind_by(&ImmediateGPUSamplerViewBind),
+      },
+    })
+  }
+
+  fn setup_pass(&self, ctx: &mut BindingBuilder) {
+    self.light_accessor.bind(ctx);
+    ctx.bind(&self.light_data);
+    ctx.bind(&self.ltc_1);
+    ctx.bind(&self.ltc_2);
+    ctx.bind_immediate_sampler(&TextureSampler::default().with_double_linear().into_gpu());
+  }
+}
+
+struct LTCStorageLightingComputeInvocation {
+  scene_id: Node<u32>,
+  light_accessor: MultiAccessGPUInvocation,
+  light_data: ShaderReadonlyPtrOf<[AreaLightStorage]>,
+  lut: LTCxLUTxInvocation,
+}
+
+impl LightingComputeInvocation for LTCStorageLightingComputeInvocation {
+  fn compute_lights(
+    &self,
+    shading: &dyn LightableSurfaceShading,
+    geom_ctx: &ENode<ShaderLightingGeometricCtx>,
+  ) -> ENode<ShaderLightingResult> {
+    let iter = self
+      .light_accessor
+      .iter_refed_many_of(self.scene_id)
+      .map(move |id| {
+        let light = self.light_data.index(id).load().expand();
+        LTCRectLightingCompute {
+          light: area_light_storage_to_ltc_light(light),
+          lut: self.lut,
+        }
+        .compute_lights(shading, geom_ctx)
+      });
+
+    light_iter_sum(iter)
+  }
+}
@@ -339,6 +339,7 @@ impl SlugTextPrepared {
         data: bytemuck::cast_slice(&self.packed.curve_tex_data).to_vec(),
         format: TextureFormat::Rgba32Float,
         size: Size::from_u32_pair_min_one((TEX_WIDTH as u32, self.packed.curve_tex_height as u32)),
+        precomputed_mips: Vec::new(),
       },
     );
 
@@ -348,6 +349,7 @@ impl SlugTextPrepared {
         data: bytemuck::cast_slice(&self.packed.band_tex_data).to_vec(),
         format: TextureFormat::Rgba32Uint,
         size: Size::from_u32_pair_min_one((TEX_WIDTH as u32, self.packed.band_tex_height as u32)),
+        precomputed_mips: Vec::new(),
       },
     )
     .texture
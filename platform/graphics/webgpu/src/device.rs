@@ -32,6 +32,7 @@ impl GPUDevice {
       bindgroup_layout_cache: Default::default(),
       render_pipeline_cache: Default::default(),
       compute_pipeline_cache: Default::default(),
+      persistent_pipeline_cache: Default::default(),
       placeholder_bg: Arc::new(placeholder_bg),
       deferred_explicit_destroy: Default::default(),
       enable_binding_ty_check: {
@@ -98,23 +99,20 @@ impl GPUDevice {
     creator: impl FnOnce(&Self, &str) -> GPURenderPipeline,
   ) -> GPURenderPipeline {
     let mut cache = self.inner.render_pipeline_cache.write();
-
-    #[cfg(feature = "pipeline-label")]
-    {
-      let (key, label) = hasher.finish_with_label();
-      cache
-        .entry(key)
-        .or_insert_with(|| creator(self, &label))
-        .clone()
-    }
-    #[cfg(not(feature = "pipeline-label"))]
-    {
-      let key = hasher.finish();
-      cache
-        .entry(key)
-        .or_insert_with(|| creator(self, ""))
-        .clone()
-    }
+    let (key, label) = hasher.finish_with_label();
+    cache
+      .entry(key)
+      .or_insert_with(|| {
+        self.create_pipeline_with_persistent_cache(key, &label, creator, |record| match record {
+          PipelineRecord::Render(record) => Some(
+            self
+              .create_render_pipeline_by_record(&record, &label)
+              .into(),
+          ),
+          _ => None,
+        })
+      })
+      .clone()
   }
 
   pub fn get_or_cache_create_compute_pipeline(
@@ -123,23 +121,20 @@ impl GPUDevice {
     creator: impl FnOnce(&Self, &str) -> GPUComputePipeline,
   ) -> GPUComputePipeline {
     let mut cache = self.inner.compute_pipeline_cache.write();
-
-    #[cfg(feature = "pipeline-label")]
-    {
-      let (key, label) = hasher.finish_with_label();
-      cache
-        .entry(key)
-        .or_insert_with(|| creator(self, &label))
-        .clone()
-    }
-    #[cfg(not(feature = "pipeline-label"))]
-    {
-      let key = hasher.finish();
-      cache
-        .entry(key)
-        .or_insert_with(|| creator(self, ""))
-        .clone()
-    }
+    let (key, label) = hasher.finish_with_label();
+    cache
+      .entry(key)
+      .or_insert_with(|| {
+        self.create_pipeline_with_persistent_cache(key, &label, creator, |record| match record {
+          PipelineRecord::Compute(record) => Some(
+            self
+              .create_compute_pipeline_by_record(&record, &label)
+              .into(),
+          ),
+          _ => None,
+        })
+      })
+      .clone()
   }
 
   pub fn get_or_cache_create_compute_pipeline_by(
@@ -164,6 +159,13 @@ impl GPUDevice {
       .map(|(i, (ty, vis))| map_shader_value_ty_to_binding_layout_type(ty, i, vis))
       .collect();
 
+    self.create_and_cache_bindgroup_layout_by_entries(raw_layouts)
+  }
+
+  pub fn create_and_cache_bindgroup_layout_by_entries(
+    &self,
+    raw_layouts: Vec<gpu::BindGroupLayoutEntry>,
+  ) -> GPUBindGroupLayout {
     let key = fast_hash_scope(|hasher| raw_layouts.hash(hasher));
 
     self
@@ -221,8 +223,9 @@ pub(crate) struct GPUDeviceImpl {
   sampler_cache: SamplerCache,
   bindgroup_cache: BindGroupCache,
   bindgroup_layout_cache: BindGroupLayoutCache,
-  render_pipeline_cache: RwLock<FastHashMap<u64, GPURenderPipeline>>,
-  compute_pipeline_cache: RwLock<FastHashMap<u64, GPUComputePipeline>>,
+  pub(crate) render_pipeline_cache: RwLock<FastHashMap<u64, GPURenderPipeline>>,
+  pub(crate) compute_pipeline_cache: RwLock<FastHashMap<u64, GPUComputePipeline>>,
+  pub(crate) persistent_pipeline_cache: RwLock<Option<Arc<GPUPipelinePersistentCache>>>,
   pub(crate) deferred_explicit_destroy: DeferExplicitDestroy,
   pub(crate) placeholder_bg: Arc<gpu::BindGroup>,
   pub(crate) enable_binding_ty_check: Arc<RwLock<bool>>,
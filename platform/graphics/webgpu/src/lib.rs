@@ -101,6 +101,8 @@ pub struct GPUCreateConfig<'a> {
   /// if None, then using fxc compiler, which is buggy.
   pub dx_compiler_dll_path: Option<String>,
   pub display: Option<Box<dyn WgpuHasDisplayHandle>>,
+  /// if set, the built pipelines are cached on disk and reused across runs.
+  pub persistent_pipeline_cache: Option<GPUPipelinePersistentCacheConfig>,
 }
 
 impl Default for GPUCreateConfig<'_> {
@@ -116,6 +118,7 @@ impl Default for GPUCreateConfig<'_> {
       enable_debug_info: None,
       dx_compiler_dll_path: None,
       display: None,
+      persistent_pipeline_cache: None,
     }
   }
 }
@@ -231,6 +234,19 @@ impl GPU {
     };
 
     let device = GPUDevice::new(device, info.clone(), config.default_shader_checks);
+
+    if let Some(cache_config) = &config.persistent_pipeline_cache {
+      match GPUPipelinePersistentCache::open(&device, cache_config) {
+        Ok(cache) => {
+          device.set_persistent_pipeline_cache(Some(cache));
+          if cache_config.warm_up {
+            let count = device.warm_up_persistent_pipeline_cache();
+            log::info!("{count} pipelines are prebuilt from persistent cache");
+          }
+        }
+        Err(err) => log::error!("failed to open persistent pipeline cache: {err}"),
+      }
+    }
     let queue = GPUQueue::new(queue);
 
     let surface = init_surface.map(|init_surface| {
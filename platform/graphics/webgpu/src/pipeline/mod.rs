@@ -5,6 +5,9 @@ use rendiation_shader_backend_naga::{NagaModuleBuildResult, ShaderAPINagaImpl};
 mod container;
 pub use container::*;
 
+mod persistent_cache;
+pub use persistent_cache::*;
+
 pub type GPURenderPipeline = GPUPipeline<wgpu::RenderPipeline>;
 pub type GPUComputePipeline = GPUPipeline<wgpu::ComputePipeline>;
 
@@ -28,17 +31,8 @@ impl<T> Clone for GPUPipeline<T> {
   }
 }
 
-impl<T> GPUPipeline<T> {
-  fn new(
-    pipeline: T,
-    raw_bg_layouts: Vec<GPUBindGroupLayout>,
-    bg_layouts: Vec<Vec<ShaderBindingDescriptor>>,
-  ) -> Self {
-    let inner = GPUPipelineImpl {
-      pipeline,
-      bg_layouts,
-      raw_bg_layouts,
-    };
+impl<T> From<GPUPipelineImpl<T>> for GPUPipeline<T> {
+  fn from(inner: GPUPipelineImpl<T>) -> Self {
     Self {
       inner: Arc::new(inner),
     }
@@ -48,7 +42,10 @@ impl<T> GPUPipeline<T> {
 pub struct GPUPipelineImpl<T> {
   pub pipeline: T,
   pub raw_bg_layouts: Vec<GPUBindGroupLayout>,
-  pub bg_layouts: Vec<Vec<ShaderBindingDescriptor>>,
+  /// used in binding check, None if the pipeline is restored from the persistent cache.
+  pub bg_layouts: Option<Vec<Vec<ShaderBindingDescriptor>>>,
+  /// only exist if the device has persistent cache and the pipeline is not stored yet.
+  pub(crate) record: Option<PipelineRecord>,
 }
 
 impl<T> Deref for GPUPipelineImpl<T> {
@@ -192,17 +189,7 @@ impl GPUDevice {
     result: NagaModuleBuildResult,
     checks: ShaderRuntimeChecks,
   ) -> wgpu::ShaderModule {
-    let naga_module = result.module;
-    if result.log_result {
-      // not using log::info here, as sometime we don't set logger(for example in unit test)
-      println!();
-      println!("=== rendiation_shader_api build result ===");
-
-      let shader_str = convert_module_by_wgsl(&naga_module, naga::valid::ValidationFlags::all());
-      println!("{shader_str}",);
-
-      println!("=== result output finished ===");
-    }
+    let naga_module = log_build_result(result);
 
     unsafe {
       self.create_shader_module_trusted(
@@ -234,25 +221,16 @@ impl GPUDevice {
       multisample,
     } = compile_result;
 
-    let compilation_options = wgpu::PipelineCompilationOptions {
-      constants: &[],
-      zero_initialize_workgroup_memory: false,
-    };
-
     let naga_fragment = *frag_shader.downcast::<NagaModuleBuildResult>().unwrap();
-    let fragment = self.create_shader_module_by_shader_api(naga_fragment, checks);
-    let frag_targets = color_states
+    let fragment = ShaderStageRecord {
+      module: log_build_result(naga_fragment),
+      entry: frag_entry,
+    };
+    let targets = color_states
       .iter()
       .map(|s| Some(s.clone()))
       .collect::<Vec<_>>();
 
-    let fragment_stage_desc = gpu::FragmentState {
-      module: &fragment,
-      entry_point: Some(&frag_entry),
-      targets: frag_targets.as_slice(),
-      compilation_options: compilation_options.clone(),
-    };
-
     // avoid wgpu validation error in this case.
     if let Some(depth_stencil) = &mut depth_stencil
       && !primitive_state.topology.is_triangles()
@@ -262,36 +240,50 @@ impl GPUDevice {
       log::warn!("depth bias is ignored for non-triangle topology");
     }
 
-    let (raw_layouts, layouts, pipeline_layout) = create_layouts(self, &bindings);
+    let (bind_group_layouts, layouts) = create_layouts(&bindings);
+    let persistable = bindings.is_persistable() && self.get_persistent_pipeline_cache().is_some();
 
     let pipeline = match shape_shader {
       VertexOrTaskMesh::Vertex((vertex_entry, vertex_shader)) => {
         let naga_vertex = *vertex_shader.downcast::<NagaModuleBuildResult>().unwrap();
-        let vertex = self.create_shader_module_by_shader_api(naga_vertex, checks);
-
-        let vertex_buffers: Vec<_> = vertex_layouts.iter().map(convert_vertex_layout).collect();
 
-        self.create_render_pipeline(&gpu::RenderPipelineDescriptor {
-          label: Some(label),
-          layout: Some(&pipeline_layout),
-          vertex: gpu::VertexState {
-            module: &vertex,
-            entry_point: Some(&vertex_entry),
-            buffers: vertex_buffers.as_slice(),
-            compilation_options,
+        let record = RenderPipelineRecord {
+          checks,
+          bind_group_layouts,
+          vertex: ShaderStageRecord {
+            module: log_build_result(naga_vertex),
+            entry: vertex_entry,
           },
-          fragment: Some(fragment_stage_desc),
+          vertex_buffers: vertex_layouts
+            .into_iter()
+            .map(|layout| VertexBufferLayoutRecord {
+              array_stride: layout.array_stride,
+              step_mode: layout.step_mode,
+              attributes: layout.attributes,
+            })
+            .collect(),
+          fragment,
+          targets,
           primitive: primitive_state,
           depth_stencil,
           multisample,
-          cache: None,
-          multiview_mask: None,
-        })
+        };
+
+        let mut pipeline = self.create_render_pipeline_by_record(&record, label);
+        if persistable {
+          pipeline.record = Some(PipelineRecord::Render(Box::new(record)));
+        }
+        pipeline
       }
       VertexOrTaskMesh::TaskMesh {
         task,
         mesh: (mesh_entry, mesh_shader),
       } => {
+        let compilation_options = wgpu::PipelineCompilationOptions {
+          constants: &[],
+          zero_initialize_workgroup_memory: false,
+        };
+
         let task = task.map(|(task_entry, task_shader)| {
           let naga_task = *task_shader.downcast::<NagaModuleBuildResult>().unwrap();
           let module = self.create_shader_module_by_shader_api(naga_task, checks);
@@ -301,7 +293,12 @@ impl GPUDevice {
         let naga_mesh = *mesh_shader.downcast::<NagaModuleBuildResult>().unwrap();
         let naga_mesh = self.create_shader_module_by_shader_api(naga_mesh, checks);
 
-        self.create_mesh_pipeline(&wgpu::MeshPipelineDescriptor {
+        let fragment_module = self.create_shader_module_by_record(&fragment, checks);
+        let (raw_bg_layouts, pipeline_layout) =
+          self.create_pipeline_layout_by_record(&bind_group_layouts);
+
+        let persistent = self.get_persistent_pipeline_cache();
+        let pipeline = self.create_mesh_pipeline(&wgpu::MeshPipelineDescriptor {
           label: Some(label),
           layout: Some(&pipeline_layout),
           task: task.as_ref().map(|(entry, shader)| wgpu::TaskState {
@@ -312,29 +309,62 @@ impl GPUDevice {
           mesh: wgpu::MeshState {
             module: &naga_mesh,
             entry_point: Some(&mesh_entry),
-            compilation_options,
+            compilation_options: compilation_options.clone(),
           },
           primitive: primitive_state,
           depth_stencil,
           multisample,
-          fragment: Some(fragment_stage_desc),
+          fragment: Some(gpu::FragmentState {
+            module: &fragment_module,
+            entry_point: Some(&fragment.entry),
+            targets: targets.as_slice(),
+            compilation_options,
+          }),
           multiview: None,
-          cache: None,
-        })
+          cache: persistent.as_ref().and_then(|c| c.backend_cache()),
+        });
+
+        // the mesh pipeline is not recorded in persistent cache
+        GPUPipelineImpl {
+          pipeline,
+          raw_bg_layouts,
+          bg_layouts: None,
+          record: None,
+        }
       }
     };
 
-    Ok(GPUPipeline::new(pipeline, raw_layouts, layouts))
+    Ok(
+      GPUPipelineImpl {
+        bg_layouts: Some(layouts),
+        ..pipeline
+      }
+      .into(),
+    )
   }
 }
 
+fn log_build_result(result: NagaModuleBuildResult) -> naga::Module {
+  if result.log_result {
+    // not using log::info here, as sometime we don't set logger(for example in unit test)
+    println!();
+    println!("=== rendiation_shader_api build result ===");
+
+    let shader_str = convert_module_by_wgsl(&result.module, naga::valid::ValidationFlags::all());
+    println!("{shader_str}",);
+
+    println!("=== result output finished ===");
+  }
+  result.module
+}
+
+/// create the raw layout entries and the binding descriptors for binding check
+#[allow(clippy::type_complexity)]
 fn create_layouts(
-  device: &GPUDevice,
   builder: &ShaderBindGroupBuilder,
 ) -> (
-  Vec<GPUBindGroupLayout>,
+  Vec<Vec<gpu::BindGroupLayoutEntry>>,
   Vec<Vec<ShaderBindingDescriptor>>,
-  wgpu::PipelineLayout,
 ) {
   let binding = &builder.bindings;
   let last_empty_count = binding
@@ -342,31 +372,25 @@ fn create_layouts(
     .rev()
     .take_while(|l| l.bindings.is_empty())
     .count();
+  let binding = binding.get(0..binding.len() - last_empty_count).unwrap();
 
   let raw_layouts: Vec<_> = binding
-    .get(0..binding.len() - last_empty_count)
-    .unwrap()
     .iter()
     .map(|b| {
-      device.create_and_cache_bindgroup_layout(b.bindings.iter().map(|e| (&e.desc, e.visibility)))
+      b.bindings
+        .iter()
+        .enumerate()
+        .map(|(i, e)| map_shader_value_ty_to_binding_layout_type(&e.desc, i, e.visibility))
+        .collect()
     })
     .collect();
 
   let layouts: Vec<_> = binding
-    .get(0..binding.len() - last_empty_count)
-    .unwrap()
     .iter()
     .map(|b| b.bindings.iter().map(|e| e.desc.clone()).collect())
     .collect();
 
-  let layouts_ref: Vec<_> = raw_layouts.iter().map(|l| Some(&l.inner)).collect();
-
-  let pipeline_layout = device.create_pipeline_layout(&gpu::PipelineLayoutDescriptor {
-    label: None,
-    bind_group_layouts: layouts_ref.as_slice(),
-    immediate_size: 0,
-  });
-  (raw_layouts, layouts, pipeline_layout)
+  (raw_layouts, layouts)
 }
 
 fn convert_module_by_wgsl(module: &naga::Module, v: naga::valid::ValidationFlags) -> String {
@@ -416,22 +440,23 @@ impl ComputeIntoPipelineExt for ShaderComputePipelineBuilder {
     let (entry, shader) = result.shader;
 
     let naga_compute = shader.downcast::<NagaModuleBuildResult>().unwrap();
-    let module = device.create_shader_module_by_shader_api(*naga_compute, checks);
-
-    let (raw_layouts, layouts, pipeline_layout) = create_layouts(device, &result.bindings);
-
-    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-      label: Some(label),
-      layout: Some(&pipeline_layout),
-      module: &module,
-      entry_point: Some(&entry),
-      compilation_options: wgpu::PipelineCompilationOptions {
-        constants: &[],
-        zero_initialize_workgroup_memory: false,
+    let (bind_group_layouts, layouts) = create_layouts(&result.bindings);
+
+    let record = ComputePipelineRecord {
+      checks,
+      bind_group_layouts,
+      shader: ShaderStageRecord {
+        module: log_build_result(*naga_compute),
+        entry,
       },
-      cache: None,
-    });
+    };
+
+    let mut pipeline = device.create_compute_pipeline_by_record(&record, label);
+    pipeline.bg_layouts = Some(layouts);
+    if result.bindings.is_persistable() && device.get_persistent_pipeline_cache().is_some() {
+      pipeline.record = Some(PipelineRecord::Compute(Box::new(record)));
+    }
 
-    Ok(GPUPipeline::new(pipeline, raw_layouts, layouts))
+    Ok(pipeline.into())
   }
 }
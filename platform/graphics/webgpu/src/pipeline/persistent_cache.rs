@@ -0,0 +1,637 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+/// bump this if the record layout or the pipeline creation logic changed
+const PERSISTENT_PIPELINE_CACHE_FORMAT_VERSION: u32 = 1;
+const RECORD_EXTENSION: &str = "pipeline";
+/// the sub folder of each cache identity is named by this prefix and contains the marker file,
+/// so the stale cache cleanup will never touch the folders not created by us.
+const CACHE_FOLDER_PREFIX: &str = "pipeline-cache-";
+const CACHE_MARKER_FILE: &str = "rendiation-pipeline-cache";
+
+#[derive(Debug, Clone)]
+pub struct GPUPipelinePersistentCacheConfig {
+  /// the root folder of the cache, each device(backend, adapter and driver) and version has
+  /// its own sub folder.
+  pub folder: PathBuf,
+  /// The pipeline key is hashed by the type id, which is only stable within the same binary.
+  /// The executable's size and modify time is mixed into the cache identity by default,
+  /// but the user should still change this if the shader logic changed without rebuild, for
+  /// example the shader is driven by the loaded plugin.
+  pub version: String,
+  /// remove the other cache sub folders in the root folder, they are stale caches created by
+  /// other builds. only the folders created by this cache(named with the cache prefix and
+  /// containing the marker file) are removed, but still disable this if the folder is shared by
+  /// multiple applications or devices.
+  pub remove_stale: bool,
+  /// prebuild all recorded pipelines when the cache is attached to the device.
+  pub warm_up: bool,
+}
+
+impl GPUPipelinePersistentCacheConfig {
+  pub fn new(folder: impl Into<PathBuf>) -> Self {
+    Self {
+      folder: folder.into(),
+      version: String::new(),
+      remove_stale: true,
+      warm_up: true,
+    }
+  }
+}
+
+/// The on-disk cache of the built shader modules and pipeline states, keyed by the pipeline hash.
+///
+/// The device first looks up the memory cache, then this cache, and only builds the shader by
+/// shader api if both missed. Each pipeline is stored as a separate file once it's created, so
+/// the recorded pipelines could be prebuilt by [GPUDevice::warm_up_persistent_pipeline_cache]
+/// at startup. If the device support [Features::PIPELINE_CACHE], the backend's pipeline cache
+/// is also used and persisted by [Self::flush] (or when the cache is dropped).
+///
+/// Note, the pipelines restored from this cache have no shader binding descriptors, so the
+/// debug binding check is skipped for them. The restore also skips all the shader building, so
+/// the pipeline whose building has process local side effects (marked by
+/// [ShaderBindGroupBuilder::mark_not_persistable]) is never stored.
+pub struct GPUPipelinePersistentCache {
+  folder: PathBuf,
+  backend_cache: Option<(gpu::PipelineCache, PathBuf)>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GPUPipelinePersistentCacheError {
+  #[error("io error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("failed to encode the pipeline record: {0}")]
+  Encode(#[from] rmp_serde::encode::Error),
+}
+
+impl GPUPipelinePersistentCache {
+  pub fn open(
+    device: &GPUDevice,
+    config: &GPUPipelinePersistentCacheConfig,
+  ) -> Result<Self, GPUPipelinePersistentCacheError> {
+    let identity = cache_identity(device, &config.version);
+    let folder = config
+      .folder
+      .join(format!("{CACHE_FOLDER_PREFIX}{identity:016x}"));
+
+    if config.remove_stale {
+      remove_stale_cache_folders(&config.folder, &folder)?;
+    }
+    create_cache_folder(&folder)?;
+
+    let backend_cache = if device.features().contains(Features::PIPELINE_CACHE)
+      && let Some(key) = util::pipeline_cache_key(&device.info().adaptor_info)
+    {
+      let path = folder.join(key);
+      let data = std::fs::read(&path).ok();
+      // safety: the data is created by the get_data of the same adapter(checked by the key), and
+      // the fallback is enabled so the invalid data is discarded by the backend.
+      let cache = unsafe {
+        device.create_pipeline_cache(&gpu::PipelineCacheDescriptor {
+          label: "persistent pipeline cache".into(),
+          data: data.as_deref(),
+          fallback: true,
+        })
+      };
+      Some((cache, path))
+    } else {
+      None
+    };
+
+    Ok(Self {
+      folder,
+      backend_cache,
+    })
+  }
+
+  pub fn folder(&self) -> &Path {
+    &self.folder
+  }
+
+  pub(crate) fn backend_cache(&self) -> Option<&gpu::PipelineCache> {
+    self.backend_cache.as_ref().map(|(cache, _)| cache)
+  }
+
+  fn record_path(&self, key: u64) -> PathBuf {
+    self
+      .folder
+      .join(format!("{key:016x}"))
+      .with_extension(RECORD_EXTENSION)
+  }
+
+  /// the keys of all pipelines stored in this cache
+  pub fn recorded_keys(&self) -> Vec<u64> {
+    let Ok(entries) = std::fs::read_dir(&self.folder) else {
+      return Vec::new();
+    };
+    entries
+      .filter_map(|entry| {
+        let path = entry.ok()?.path();
+        if path.extension()? != RECORD_EXTENSION {
+          return None;
+        }
+        u64::from_str_radix(path.file_stem()?.to_str()?, 16).ok()
+      })
+      .collect()
+  }
+
+  pub(crate) fn load(&self, key: u64) -> Option<PipelineRecord> {
+    let path = self.record_path(key);
+    let data = std::fs::read(&path).ok()?;
+    match rmp_serde::from_slice(&data) {
+      Ok(record) => Some(record),
+      Err(err) => {
+        log::warn!("discard broken pipeline cache {}: {err}", path.display());
+        std::fs::remove_file(path).ok();
+        None
+      }
+    }
+  }
+
+  pub(crate) fn store(
+    &self,
+    key: u64,
+    record: &PipelineRecord,
+  ) -> Result<(), GPUPipelinePersistentCacheError> {
+    let data = rmp_serde::to_vec(record)?;
+    write_atomic(&self.record_path(key), &data)
+  }
+
+  /// remove all the recorded pipelines. the backend cache data is only discarded on disk, the
+  /// loaded backend cache is not affected. the files not written by this cache are kept.
+  pub fn clear(&self) -> Result<(), GPUPipelinePersistentCacheError> {
+    let backend_cache_path = self.backend_cache.as_ref().map(|(_, path)| path);
+    for entry in std::fs::read_dir(&self.folder)? {
+      let path = entry?.path();
+      let is_record = path
+        .extension()
+        .is_some_and(|ext| ext == RECORD_EXTENSION || ext == "tmp");
+      if path.is_file() && (is_record || Some(&path) == backend_cache_path) {
+        std::fs::remove_file(path)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// write the backend pipeline cache data to disk, the pipeline records are written when they
+  /// are created so they don't need flush.
+  pub fn flush(&self) -> Result<(), GPUPipelinePersistentCacheError> {
+    if let Some((cache, path)) = &self.backend_cache
+      && let Some(data) = cache.get_data()
+    {
+      write_atomic(path, &data)?;
+    }
+    Ok(())
+  }
+}
+
+impl Drop for GPUPipelinePersistentCache {
+  fn drop(&mut self) {
+    if let Err(err) = self.flush() {
+      log::error!("failed to write backend pipeline cache: {err}");
+    }
+  }
+}
+
+/// write to a temp file and rename, so a crashed write will not leave a broken file.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), GPUPipelinePersistentCacheError> {
+  let temp = path.with_extension("tmp");
+  std::fs::write(&temp, data)?;
+  std::fs::rename(temp, path)?;
+  Ok(())
+}
+
+fn create_cache_folder(folder: &Path) -> Result<(), GPUPipelinePersistentCacheError> {
+  std::fs::create_dir_all(folder)?;
+  let marker = folder.join(CACHE_MARKER_FILE);
+  if !marker.exists() {
+    std::fs::write(marker, [])?;
+  }
+  Ok(())
+}
+
+/// remove the cache folders in the root except the current one, the folders not created by this
+/// cache are kept.
+fn remove_stale_cache_folders(
+  root: &Path,
+  current: &Path,
+) -> Result<(), GPUPipelinePersistentCacheError> {
+  if !root.exists() {
+    return Ok(());
+  }
+  for entry in std::fs::read_dir(root)? {
+    let path = entry?.path();
+    let is_cache_folder = path
+      .file_name()
+      .and_then(|name| name.to_str())
+      .is_some_and(|name| name.starts_with(CACHE_FOLDER_PREFIX))
+      && path.join(CACHE_MARKER_FILE).is_file();
+    if path.is_dir() && is_cache_folder && path != current {
+      log::info!("remove stale pipeline cache: {}", path.display());
+      std::fs::remove_dir_all(path)?;
+    }
+  }
+  Ok(())
+}
+
+fn cache_identity(device: &GPUDevice, version: &str) -> u64 {
+  let executable = std::env::current_exe()
+    .and_then(std::fs::metadata)
+    .ok()
+    .map(|meta| (meta.len(), meta.modified().ok()));
+
+  let info = &device.info().adaptor_info;
+  fast_hash_scope(|hasher| {
+    PERSISTENT_PIPELINE_CACHE_FORMAT_VERSION.hash(hasher);
+    version.hash(hasher);
+    executable.hash(hasher);
+    info.backend.hash(hasher);
+    info.name.hash(hasher);
+    info.vendor.hash(hasher);
+    info.device.hash(hasher);
+    info.driver.hash(hasher);
+    info.driver_info.hash(hasher);
+  })
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ShaderStageRecord {
+  pub module: naga::Module,
+  pub entry: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct VertexBufferLayoutRecord {
+  pub array_stride: BufferAddress,
+  pub step_mode: VertexStepMode,
+  pub attributes: Vec<VertexAttribute>,
+}
+
+/// the built result of a render pipeline, mesh pipeline is not supported yet.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RenderPipelineRecord {
+  pub checks: ShaderRuntimeChecks,
+  pub bind_group_layouts: Vec<Vec<gpu::BindGroupLayoutEntry>>,
+  pub vertex: ShaderStageRecord,
+  pub vertex_buffers: Vec<VertexBufferLayoutRecord>,
+  pub fragment: ShaderStageRecord,
+  pub targets: Vec<Option<ColorTargetState>>,
+  pub primitive: PrimitiveState,
+  pub depth_stencil: Option<DepthStencilState>,
+  pub multisample: MultisampleState,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ComputePipelineRecord {
+  pub checks: ShaderRuntimeChecks,
+  pub bind_group_layouts: Vec<Vec<gpu::BindGroupLayoutEntry>>,
+  pub shader: ShaderStageRecord,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum PipelineRecord {
+  Render(Box<RenderPipelineRecord>),
+  Compute(Box<ComputePipelineRecord>),
+}
+
+const COMPILATION_OPTIONS: wgpu::PipelineCompilationOptions = wgpu::PipelineCompilationOptions {
+  constants: &[],
+  zero_initialize_workgroup_memory: false,
+};
+
+impl GPUDevice {
+  /// attach or detach the persistent pipeline cache, see [GPUPipelinePersistentCache].
+  pub fn set_persistent_pipeline_cache(&self, cache: Option<GPUPipelinePersistentCache>) {
+    *self.inner.persistent_pipeline_cache.write() = cache.map(Arc::new);
+  }
+
+  pub fn get_persistent_pipeline_cache(&self) -> Option<Arc<GPUPipelinePersistentCache>> {
+    self.inner.persistent_pipeline_cache.read().clone()
+  }
+
+  /// prebuild all recorded pipelines into the memory cache, return the count of the created
+  /// pipelines.
+  pub fn warm_up_persistent_pipeline_cache(&self) -> usize {
+    let Some(persistent) = self.get_persistent_pipeline_cache() else {
+      return 0;
+    };
+
+    let mut count = 0;
+    for key in persistent.recorded_keys() {
+      let Some(record) = persistent.load(key) else {
+        continue;
+      };
+      match record {
+        PipelineRecord::Render(record) => {
+          let mut cache = self.inner.render_pipeline_cache.write();
+          if !cache.contains_key(&key) {
+            cache.insert(
+              key,
+              self.create_render_pipeline_by_record(&record, "").into(),
+            );
+            count += 1;
+          }
+        }
+        PipelineRecord::Compute(record) => {
+          let mut cache = self.inner.compute_pipeline_cache.write();
+          if !cache.contains_key(&key) {
+            cache.insert(
+              key,
+              self.create_compute_pipeline_by_record(&record, "").into(),
+            );
+            count += 1;
+          }
+        }
+      }
+    }
+    count
+  }
+
+  /// look up the persistent cache if the creator is missed in memory, and record the newly
+  /// created pipeline.
+  pub(crate) fn create_pipeline_with_persistent_cache<T>(
+    &self,
+    key: u64,
+    label: &str,
+    creator: impl FnOnce(&Self, &str) -> GPUPipeline<T>,
+    restore: impl FnOnce(PipelineRecord) -> Option<GPUPipeline<T>>,
+  ) -> GPUPipeline<T> {
+    let Some(persistent) = self.get_persistent_pipeline_cache() else {
+      return creator(self, label);
+    };
+
+    if let Some(pipeline) = persistent.load(key).and_then(restore) {
+      return pipeline;
+    }
+
+    let mut pipeline = creator(self, label);
+    // the pipeline is just created so it's not shared
+    if let Some(inner) = Arc::get_mut(&mut pipeline.inner)
+      && let Some(record) = inner.record.take()
+      && let Err(err) = persistent.store(key, &record)
+    {
+      log::error!("failed to store pipeline into persistent cache: {err}");
+    }
+    pipeline
+  }
+
+  pub(crate) fn create_shader_module_by_record(
+    &self,
+    record: &ShaderStageRecord,
+    checks: ShaderRuntimeChecks,
+  ) -> wgpu::ShaderModule {
+    unsafe {
+      self.create_shader_module_trusted(
+        gpu::ShaderModuleDescriptor {
+          label: None,
+          source: gpu::ShaderSource::Naga(Cow::Owned(record.module.clone())),
+        },
+        checks,
+      )
+    }
+  }
+
+  pub(crate) fn create_pipeline_layout_by_record(
+    &self,
+    layouts: &[Vec<gpu::BindGroupLayoutEntry>],
+  ) -> (Vec<GPUBindGroupLayout>, wgpu::PipelineLayout) {
+    let raw_layouts: Vec<_> = layouts
+      .iter()
+      .map(|entries| self.create_and_cache_bindgroup_layout_by_entries(entries.clone()))
+      .collect();
+    let layouts_ref: Vec<_> = raw_layouts.iter().map(|l| Some(&l.inner)).collect();
+
+    let pipeline_layout = self.create_pipeline_layout(&gpu::PipelineLayoutDescriptor {
+      label: None,
+      bind_group_layouts: layouts_ref.as_slice(),
+      immediate_size: 0,
+    });
+    (raw_layouts, pipeline_layout)
+  }
+
+  pub(crate) fn create_render_pipeline_by_record(
+    &self,
+    record: &RenderPipelineRecord,
+    label: &str,
+  ) -> GPUPipelineImpl<wgpu::RenderPipeline> {
+    let persistent = self.get_persistent_pipeline_cache();
+    let cache = persistent.as_ref().and_then(|c| c.backend_cache());
+
+    let (raw_layouts, pipeline_layout) =
+      self.create_pipeline_layout_by_record(&record.bind_group_layouts);
+    let vertex = self.create_shader_module_by_record(&record.vertex, record.checks);
+    let fragment = self.create_shader_module_by_record(&record.fragment, record.checks);
+
+    let vertex_buffers: Vec<_> = record
+      .vertex_buffers
+      .iter()
+      .map(|layout| gpu::VertexBufferLayout {
+        array_stride: layout.array_stride,
+        step_mode: layout.step_mode,
+        attributes: layout.attributes.as_slice(),
+      })
+      .collect();
+
+    let pipeline = self.create_render_pipeline(&gpu::RenderPipelineDescriptor {
+      label: Some(label),
+      layout: Some(&pipeline_layout),
+      vertex: gpu::VertexState {
+        module: &vertex,
+        entry_point: Some(&record.vertex.entry),
+        buffers: vertex_buffers.as_slice(),
+        compilation_options: COMPILATION_OPTIONS,
+      },
+      fragment: Some(gpu::FragmentState {
+        module: &fragment,
+        entry_point: Some(&record.fragment.entry),
+        targets: record.targets.as_slice(),
+        compilation_options: COMPILATION_OPTIONS,
+      }),
+      primitive: record.primitive,
+      depth_stencil: record.depth_stencil.clone(),
+      multisample: record.multisample,
+      cache,
+      multiview_mask: None,
+    });
+
+    GPUPipelineImpl {
+      pipeline,
+      raw_bg_layouts: raw_layouts,
+      bg_layouts: None,
+      record: None,
+    }
+  }
+
+  pub(crate) fn create_compute_pipeline_by_record(
+    &self,
+    record: &ComputePipelineRecord,
+    label: &str,
+  ) -> GPUPipelineImpl<wgpu::ComputePipeline> {
+    let persistent = self.get_persistent_pipeline_cache();
+    let cache = persistent.as_ref().and_then(|c| c.backend_cache());
+
+    let (raw_layouts, pipeline_layout) =
+      self.create_pipeline_layout_by_record(&record.bind_group_layouts);
+    let module = self.create_shader_module_by_record(&record.shader, record.checks);
+
+    let pipeline = self.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+      label: Some(label),
+      layout: Some(&pipeline_layout),
+      module: &module,
+      entry_point: Some(&record.shader.entry),
+      compilation_options: COMPILATION_OPTIONS,
+      cache,
+    });
+
+    GPUPipelineImpl {
+      pipeline,
+      raw_bg_layouts: raw_layouts,
+      bg_layouts: None,
+      record: None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn test_folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("rendiation_pipeline_cache_test_{name}"));
+    std::fs::remove_dir_all(&folder).ok();
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+  }
+
+  fn test_cache(folder: &Path) -> GPUPipelinePersistentCache {
+    GPUPipelinePersistentCache {
+      folder: folder.to_path_buf(),
+      backend_cache: None,
+    }
+  }
+
+  fn compute_record() -> PipelineRecord {
+    PipelineRecord::Compute(Box::new(ComputePipelineRecord {
+      checks: ShaderRuntimeChecks::unchecked(),
+      bind_group_layouts: vec![vec![gpu::BindGroupLayoutEntry {
+        binding: 3,
+        visibility: ShaderStages::COMPUTE,
+        ty: gpu::BindingType::Buffer {
+          ty: gpu::BufferBindingType::Storage { read_only: true },
+          has_dynamic_offset: false,
+          min_binding_size: None,
+        },
+        count: None,
+      }]],
+      shader: ShaderStageRecord {
+        module: naga::Module::default(),
+        entry: "main".into(),
+      },
+    }))
+  }
+
+  #[test]
+  fn test_pipeline_record_round_trip() {
+    let record = compute_record();
+    let data = rmp_serde::to_vec(&record).unwrap();
+    let decoded: PipelineRecord = rmp_serde::from_slice(&data).unwrap();
+
+    let PipelineRecord::Compute(decoded) = decoded else {
+      panic!("expect compute record");
+    };
+    assert!(!decoded.checks.bounds_checks);
+    assert_eq!(decoded.shader.entry, "main");
+    assert_eq!(decoded.bind_group_layouts.len(), 1);
+    assert_eq!(decoded.bind_group_layouts[0][0].binding, 3);
+    assert_eq!(
+      decoded.bind_group_layouts[0][0].visibility,
+      ShaderStages::COMPUTE
+    );
+    // the decoded record encodes back to the same bytes
+    let reencoded = rmp_serde::to_vec(&PipelineRecord::Compute(decoded)).unwrap();
+    assert_eq!(reencoded, data);
+
+    // broken data is rejected instead of producing a garbage record
+    assert!(rmp_serde::from_slice::<PipelineRecord>(&data[..data.len() / 2]).is_err());
+  }
+
+  #[test]
+  fn test_recorded_keys_and_clear() {
+    let folder = test_folder("keys");
+    let cache = test_cache(&folder);
+    cache.store(0x1234_abcd, &compute_record()).unwrap();
+    cache.store(u64::MAX, &compute_record()).unwrap();
+    // not a record
+    std::fs::write(folder.join("readme.txt"), b"keep").unwrap();
+    // wrong extension and not a hex stem
+    std::fs::write(folder.join("0000000000000001.bin"), b"").unwrap();
+    std::fs::write(folder.join("not_a_key.pipeline"), b"").unwrap();
+
+    let mut keys = cache.recorded_keys();
+    keys.sort();
+    assert_eq!(keys, vec![0x1234_abcd, u64::MAX]);
+    assert!(cache.load(0x1234_abcd).is_some());
+    assert!(cache.load(7).is_none());
+
+    // the broken record is discarded when loaded
+    std::fs::write(cache.record_path(7), b"broken").unwrap();
+    assert!(cache.load(7).is_none());
+    assert!(!cache.record_path(7).exists());
+
+    cache.clear().unwrap();
+    assert!(cache.recorded_keys().is_empty());
+    assert!(folder.join("readme.txt").exists());
+    assert!(folder.join("0000000000000001.bin").exists());
+
+    std::fs::remove_dir_all(folder).ok();
+  }
+
+  #[test]
+  fn test_remove_stale_cache_folders() {
+    let root = test_folder("stale");
+    let current = root.join(format!("{CACHE_FOLDER_PREFIX}0000000000000001"));
+    let stale = root.join(format!("{CACHE_FOLDER_PREFIX}0000000000000002"));
+    let unmarked = root.join(format!("{CACHE_FOLDER_PREFIX}0000000000000003"));
+    let foreign = root.join("user_data");
+
+    create_cache_folder(&current).unwrap();
+    create_cache_folder(&stale).unwrap();
+    std::fs::create_dir_all(&unmarked).unwrap();
+    std::fs::create_dir_all(&foreign).unwrap();
+    // a marker in a folder without the prefix does not make it a cache folder
+    std::fs::write(foreign.join(CACHE_MARKER_FILE), []).unwrap();
+
+    remove_stale_cache_folders(&root, &current).unwrap();
+    assert!(current.exists());
+    assert!(!stale.exists());
+    assert!(unmarked.exists());
+    assert!(foreign.exists());
+
+    // the missing root is not an error
+    remove_stale_cache_folders(&root.join("missing"), &current).unwrap();
+
+    std::fs::remove_dir_all(root).ok();
+  }
+
+  #[test]
+  fn test_write_atomic() {
+    let folder = test_folder("atomic");
+    let path = folder.join("data.pipeline");
+
+    write_atomic(&path, b"first").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"first");
+    write_atomic(&path, b"second").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+    // the temp file is renamed away
+    assert!(!path.with_extension("tmp").exists());
+
+    // failed write leaves the old file untouched
+    assert!(write_atomic(&folder.join("missing/data.pipeline"), b"third").is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+
+    std::fs::remove_dir_all(folder).ok();
+  }
+}
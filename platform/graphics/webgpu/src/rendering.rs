@@ -56,8 +56,10 @@ pub trait RenderComponent: ShaderHashProvider + GraphicsShaderProvider + ShaderP
     ctx.binding.reset();
     ctx.reset_vertex_binding_index();
 
-    if ctx.enable_bind_check {
-      ctx.binding.setup_checking_layout(&pipeline.bg_layouts);
+    if ctx.enable_bind_check
+      && let Some(layouts) = &pipeline.bg_layouts
+    {
+      ctx.binding.setup_checking_layout(layouts);
     }
 
     if let RenderMethod::MeshPipelineDraw(draw) = draw {
@@ -103,13 +103,15 @@ pub trait WebGPU2DTextureSource: Send + Sync {
     self.format().block_copy_size(None).unwrap() as usize
   }
 
+  /// for the block compressed format, the row is the row of blocks
   fn bytes_per_row_usize(&self) -> usize {
     let width: usize = self.size().width.into();
-    width * self.bytes_per_pixel()
+    let (block_width, _) = self.format().block_dimensions();
+    width.div_ceil(block_width as usize) * self.bytes_per_pixel()
   }
 
   fn bytes_per_row(&self) -> u32 {
-    Into::<usize>::into(self.size().width) as u32 * self.bytes_per_pixel() as u32
+    self.bytes_per_row_usize() as u32
   }
 
   fn gpu_size(&self) -> gpu::Extent3d {
@@ -180,11 +182,7 @@ pub trait WebGPU2DTextureSource: Send + Sync {
       dimension: gpu::TextureDimension::D2,
       format: self.format(),
       view_formats: get_view_format(self.format(), flags),
-      usage: gpu::TextureUsages::TEXTURE_BINDING
-        | gpu::TextureUsages::RENDER_ATTACHMENT
-        // | gpu::TextureUsages::STORAGE_BINDING // used to generate mipmap in compute shader
-        | gpu::TextureUsages::COPY_DST
-        | gpu::TextureUsages::COPY_SRC,
+      usage: source_texture_usages(self.format()),
     }
   }
 
@@ -201,14 +199,24 @@ pub trait WebGPU2DTextureSource: Send + Sync {
       sample_count: 1,
       dimension: gpu::TextureDimension::D2,
       format: self.format(),
-      usage: gpu::TextureUsages::TEXTURE_BINDING
-        | gpu::TextureUsages::RENDER_ATTACHMENT
-        | gpu::TextureUsages::COPY_DST
-        | gpu::TextureUsages::COPY_SRC,
+      usage: source_texture_usages(self.format()),
     }
   }
 }
 
+/// the block compressed format can not be used as the render attachment
+fn source_texture_usages(format: gpu::TextureFormat) -> gpu::TextureUsages {
+  let usages = gpu::TextureUsages::TEXTURE_BINDING
+    // | gpu::TextureUsages::STORAGE_BINDING // used to generate mipmap in compute shader
+    | gpu::TextureUsages::COPY_DST
+    | gpu::TextureUsages::COPY_SRC;
+  if format.is_compressed() {
+    usages
+  } else {
+    usages | gpu::TextureUsages::RENDER_ATTACHMENT
+  }
+}
+
 pub enum MipLevelCount {
   BySize,
   EmptyMipMap,
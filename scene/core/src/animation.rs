@@ -193,7 +193,7 @@ pub enum InterpolationItem {
   Position(Vec3<f32>),
   Scale(Vec3<f32>),
   Quaternion(Quat<f32>),
-  MorphTargetWeights(f32),
+  MorphTargetWeights(MorphTargetWeights),
 }
 
 impl InterpolationItem {
@@ -215,7 +215,7 @@ enum InterpolationCubicItem {
   Position(CubicVertex<Vec3<f32>>),
   Scale(CubicVertex<Vec3<f32>>),
   Quaternion(CubicVertex<Quat<f32>>),
-  MorphTargetWeights(CubicVertex<f32>),
+  MorphTargetWeights(CubicVertex<MorphTargetWeights>),
 }
 
 impl InterpolationCubicItem {
@@ -309,19 +309,47 @@ impl InterpolateInstance<InterpolationItem> {
     let (end_time, end_index) = (sampler.input.get::<f32>(end_index)?, end_index);
     let field_ty = sampler.field;
 
-    if let SceneAnimationField::Rotation | SceneAnimationField::MorphTargetWeights = field_ty {
+    if let SceneAnimationField::Rotation = field_ty {
       // currently we only support float type
       assert_eq!(sampler.output.item_byte_size, 4 * 4);
     }
 
+    // the morph target weights output contains all target's weight for each key frame
+    let morph_target_count = if let SceneAnimationField::MorphTargetWeights = field_ty {
+      // currently we only support float type
+      assert_eq!(sampler.output.item_byte_size, 4);
+      let per_frame = sampler.output.count / sampler.input.count;
+      match sampler.interpolation {
+        InterpolationStyle::Cubic => per_frame / 3,
+        _ => per_frame,
+      }
+    } else {
+      0
+    };
+
+    fn get_morph_target_weights(
+      output: &AttributeAccessor,
+      index: usize,
+      count: usize,
+    ) -> Option<MorphTargetWeights> {
+      let weights = output.visit_slice::<f32>()?;
+      let weights = weights.get(index * count..(index + 1) * count)?;
+      MorphTargetWeights::from_slice(weights).into()
+    }
+
     fn get_output_single(
       output: &AttributeAccessor,
       index: usize,
       field_ty: SceneAnimationField,
+      morph_target_count: usize,
     ) -> Option<InterpolationItem> {
       use SceneAnimationField::*;
       match field_ty {
-        MorphTargetWeights => InterpolationItem::MorphTargetWeights(output.get::<f32>(index)?),
+        MorphTargetWeights => InterpolationItem::MorphTargetWeights(get_morph_target_weights(
+          output,
+          index,
+          morph_target_count,
+        )?),
         Position => InterpolationItem::Position(output.get::<Vec3<f32>>(index)?),
         Rotation => InterpolationItem::Quaternion(output.get::<Quat<f32>>(index)?),
         Scale => InterpolationItem::Scale(output.get::<Vec3<f32>>(index)?),
@@ -333,11 +361,16 @@ impl InterpolateInstance<InterpolationItem> {
       output: &AttributeAccessor,
       index: usize,
       field_ty: SceneAnimationField,
+      morph_target_count: usize,
     ) -> Option<InterpolationCubicItem> {
       use InterpolationCubicItem::*;
       use SceneAnimationField as SF;
       match field_ty {
-        SF::MorphTargetWeights => MorphTargetWeights(output.get::<CubicVertex<f32>>(index)?),
+        SF::MorphTargetWeights => MorphTargetWeights(CubicVertex {
+          enter: get_morph_target_weights(output, index * 3, morph_target_count)?,
+          center: get_morph_target_weights(output, index * 3 + 1, morph_target_count)?,
+          exit: get_morph_target_weights(output, index * 3 + 2, morph_target_count)?,
+        }),
         SF::Position => Position(output.get::<CubicVertex<Vec3<f32>>>(index)?),
         SF::Rotation => Quaternion(output.get::<CubicVertex<Quat<f32>>>(index)?),
         SF::Scale => Scale(output.get::<CubicVertex<Vec3<f32>>>(index)?),
@@ -347,16 +380,18 @@ impl InterpolateInstance<InterpolationItem> {
 
     let curve = match sampler.interpolation {
       InterpolationStyle::Linear => InterpolateInstance::Linear {
-        start: get_output_single(&sampler.output, start_index, field_ty)?,
-        end: get_output_single(&sampler.output, end_index, field_ty)?,
+        start: get_output_single(&sampler.output, start_index, field_ty, morph_target_count)?,
+        end: get_output_single(&sampler.output, end_index, field_ty, morph_target_count)?,
       },
       InterpolationStyle::Step => InterpolateInstance::Step {
-        start: get_output_single(&sampler.output, start_index, field_ty)?,
-        end: get_output_single(&sampler.output, end_index, field_ty)?,
+        start: get_output_single(&sampler.output, start_index, field_ty, morph_target_count)?,
+        end: get_output_single(&sampler.output, end_index, field_ty, morph_target_count)?,
       },
       InterpolationStyle::Cubic => {
-        let cubic_vertex_a = get_output_cubic(&sampler.output, start_index, field_ty)?.transpose();
-        let cubic_vertex_b = get_output_cubic(&sampler.output, end_index, field_ty)?.transpose();
+        let cubic_vertex_a =
+          get_output_cubic(&sampler.output, start_index, field_ty, morph_target_count)?.transpose();
+        let cubic_vertex_b =
+          get_output_cubic(&sampler.output, end_index, field_ty, morph_target_count)?.transpose();
         InterpolateInstance::Cubic {
           start: cubic_vertex_a.center,
           ctrl1: cubic_vertex_a.exit,
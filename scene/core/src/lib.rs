@@ -18,11 +18,14 @@ mod animation;
 mod buffer;
 mod camera;
 mod light;
+mod light_probe;
 mod material;
 mod mesh;
 mod model;
+mod morph;
 mod node;
 mod reader;
+mod reflection_probe;
 mod skin;
 mod texture;
 mod writer;
@@ -31,11 +34,14 @@ pub use animation::*;
 pub use buffer::*;
 pub use camera::*;
 pub use light::*;
+pub use light_probe::*;
 pub use material::*;
 pub use mesh::*;
 pub use model::*;
+pub use morph::*;
 pub use node::*;
 pub use reader::*;
+pub use reflection_probe::*;
 pub use skin::*;
 pub use texture::*;
 pub use writer::*;
@@ -54,10 +60,13 @@ pub fn register_scene_core_data_model() {
   register_directional_light_data_model();
   register_point_light_data_model();
   register_spot_light_data_model();
+  register_light_probe_data_model();
+  register_reflection_probe_data_model();
 
   register_std_model_data_model();
 
   register_attribute_mesh_data_model();
+  register_morph_target_data_model();
 
   register_unlit_material_data_model();
   register_pbr_sg_material_data_model();
@@ -0,0 +1,123 @@
+use crate::*;
+
+pub struct LightProbeVolumeDataView {
+  pub resolution: Vec3<u32>,
+  pub intensity: f32,
+  pub edge_fade: f32,
+  pub node: EntityHandle<SceneNodeEntity>,
+  pub scene: EntityHandle<SceneEntity>,
+}
+
+impl LightProbeVolumeDataView {
+  pub fn write(
+    self,
+    writer: &mut TableWriter<LightProbeVolumeEntity>,
+  ) -> EntityHandle<LightProbeVolumeEntity> {
+    writer.new_entity(|w| {
+      w.write::<LightProbeVolumeResolution>(&self.resolution)
+        .write::<LightProbeVolumeIntensity>(&self.intensity)
+        .write::<LightProbeVolumeEdgeFade>(&self.edge_fade)
+        .write::<LightProbeVolumeRefNode>(&self.node.some_handle())
+        .write::<LightProbeVolumeRefScene>(&self.scene.some_handle())
+    })
+  }
+}
+
+declare_entity!(
+  /// A grid of diffuse irradiance probes. A single placed probe is a volume with resolution of 1.
+  ///
+  /// The probes are evenly distributed in the local space box from (-1, -1, -1) to (1, 1, 1),
+  /// the box is placed by the world matrix of the associated [SceneNodeEntity].
+  LightProbeVolumeEntity);
+declare_foreign_key!(
+  /// Associates this probe volume with a [SceneEntity].
+  LightProbeVolumeRefScene, LightProbeVolumeEntity, SceneEntity);
+declare_foreign_key!(
+  /// Determines the placement of the probe volume by the world space transform of the associated
+  /// [SceneNodeEntity].
+  LightProbeVolumeRefNode, LightProbeVolumeEntity, SceneNodeEntity);
+declare_component!(
+  /// The probe count in each axis, each axis should be at least 1.
+  LightProbeVolumeResolution,
+  LightProbeVolumeEntity,
+  Vec3<u32>,
+  Vec3::one()
+);
+declare_component!(
+  /// The scale applied to the baked irradiance.
+  LightProbeVolumeIntensity, LightProbeVolumeEntity, f32, 1.);
+declare_component!(
+  /// The width of the region near the volume boundary where the probe lighting fades out to the
+  /// environment lighting, relative to the half extent of the volume.
+  LightProbeVolumeEdgeFade, LightProbeVolumeEntity, f32, 0.1);
+declare_foreign_key!(
+  /// The baked radiance of the probes, see [LightProbeVolumeIrradianceLayout] for the layout.
+  ///
+  /// The probe volume contributes nothing if this association does not exist.
+  LightProbeVolumeIrradianceData, LightProbeVolumeEntity, BufferEntity);
+
+pub fn register_light_probe_data_model() {
+  global_database()
+    .declare_entity::<LightProbeVolumeEntity>()
+    .declare_component::<LightProbeVolumeResolution>()
+    .declare_component::<LightProbeVolumeIntensity>()
+    .declare_component::<LightProbeVolumeEdgeFade>()
+    .declare_foreign_key::<LightProbeVolumeRefScene>()
+    .declare_foreign_key::<LightProbeVolumeRefNode>()
+    .declare_foreign_key::<LightProbeVolumeIrradianceData>();
+}
+
+/// The baked data is the radiance projected into 9 rgb sh l2 coefficients per probe, stored as
+/// tightly packed little endian f32. The probes are ordered with x changing fastest, then y, then z.
+pub struct LightProbeVolumeIrradianceLayout {
+  pub resolution: Vec3<u32>,
+}
+
+impl LightProbeVolumeIrradianceLayout {
+  pub const COEFFICIENT_COUNT: usize = 9;
+  pub const PROBE_BYTE_SIZE: usize = Self::COEFFICIENT_COUNT * 3 * std::mem::size_of::<f32>();
+
+  pub fn new(resolution: Vec3<u32>) -> Self {
+    Self {
+      resolution: resolution.map(|v| v.max(1)),
+    }
+  }
+
+  pub fn probe_count(&self) -> usize {
+    (self.resolution.x * self.resolution.y * self.resolution.z) as usize
+  }
+
+  pub fn byte_size(&self) -> usize {
+    self.probe_count() * Self::PROBE_BYTE_SIZE
+  }
+
+  pub fn probe_index(&self, grid: Vec3<u32>) -> usize {
+    (grid.x + grid.y * self.resolution.x + grid.z * self.resolution.x * self.resolution.y) as usize
+  }
+
+  pub fn probe_grid(&self, index: usize) -> Vec3<u32> {
+    let index = index as u32;
+    let layer = self.resolution.x * self.resolution.y;
+    Vec3::new(
+      index % self.resolution.x,
+      (index % layer) / self.resolution.x,
+      index / layer,
+    )
+  }
+
+  /// the probe position in the volume local space
+  pub fn probe_local_position(&self, grid: Vec3<u32>) -> Vec3<f32> {
+    let axis = |i: u32, res: u32| {
+      if res <= 1 {
+        0.
+      } else {
+        i as f32 / (res - 1) as f32 * 2. - 1.
+      }
+    };
+    Vec3::new(
+      axis(grid.x, self.resolution.x),
+      axis(grid.y, self.resolution.y),
+      axis(grid.z, self.resolution.z),
+    )
+  }
+}
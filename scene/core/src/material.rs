@@ -217,6 +217,67 @@ mod mr_material {
     Vec3::zero()
   );
 
+  // the following components are the common gltf pbr material extensions, the default
+  // value is the value when the extension is not used.
+
+  // KHR_materials_emissive_strength
+  declare_component!(
+    PbrMRMaterialEmissiveStrengthComponent,
+    PbrMRMaterialEntity,
+    f32,
+    1.0
+  );
+  // KHR_materials_ior
+  declare_component!(PbrMRMaterialIorComponent, PbrMRMaterialEntity, f32, 1.5);
+  // KHR_materials_specular, the strength of the dielectric specular reflection
+  declare_component!(
+    PbrMRMaterialSpecularFactorComponent,
+    PbrMRMaterialEntity,
+    f32,
+    1.0
+  );
+  // KHR_materials_specular, the dielectric f0 color, in linear space
+  declare_component!(
+    PbrMRMaterialSpecularColorComponent,
+    PbrMRMaterialEntity,
+    Vec3<f32>,
+    Vec3::one()
+  );
+  // KHR_materials_transmission
+  declare_component!(
+    PbrMRMaterialTransmissionComponent,
+    PbrMRMaterialEntity,
+    f32,
+    0.0
+  );
+  // KHR_materials_clearcoat
+  declare_component!(
+    PbrMRMaterialClearcoatComponent,
+    PbrMRMaterialEntity,
+    f32,
+    0.0
+  );
+  // KHR_materials_clearcoat, perceptual roughness
+  declare_component!(
+    PbrMRMaterialClearcoatRoughnessComponent,
+    PbrMRMaterialEntity,
+    f32,
+    0.0
+  );
+  // KHR_materials_sheen, in linear space
+  declare_component!(
+    PbrMRMaterialSheenColorComponent,
+    PbrMRMaterialEntity,
+    Vec3<f32>,
+    Vec3::zero()
+  );
+  // KHR_materials_sheen, perceptual roughness
+  declare_component!(
+    PbrMRMaterialSheenRoughnessComponent,
+    PbrMRMaterialEntity,
+    f32,
+    0.0
+  );
   declare_entity_associated!(PbrMRMaterialAlphaConfig, PbrMRMaterialEntity);
   impl AlphaInfoSemantic for PbrMRMaterialAlphaConfig {}
 
@@ -229,18 +290,61 @@ mod mr_material {
   declare_entity_associated!(PbrMRMaterialNormalInfo, PbrMRMaterialEntity);
   impl NormalInfoSemantic for PbrMRMaterialNormalInfo {}
 
+  declare_entity_associated!(PbrMRMaterialSpecularTex, PbrMRMaterialEntity);
+  impl TextureWithSamplingForeignKeys for PbrMRMaterialSpecularTex {}
+  declare_entity_associated!(PbrMRMaterialSpecularColorTex, PbrMRMaterialEntity);
+  impl TextureWithSamplingForeignKeys for PbrMRMaterialSpecularColorTex {}
+  declare_entity_associated!(PbrMRMaterialTransmissionTex, PbrMRMaterialEntity);
+  impl TextureWithSamplingForeignKeys for PbrMRMaterialTransmissionTex {}
+  declare_entity_associated!(PbrMRMaterialClearcoatTex, PbrMRMaterialEntity);
+  impl TextureWithSamplingForeignKeys for PbrMRMaterialClearcoatTex {}
+  declare_entity_associated!(PbrMRMaterialClearcoatRoughnessTex, PbrMRMaterialEntity);
+  impl TextureWithSamplingForeignKeys for PbrMRMaterialClearcoatRoughnessTex {}
+  declare_entity_associated!(PbrMRMaterialSheenColorTex, PbrMRMaterialEntity);
+  impl TextureWithSamplingForeignKeys for PbrMRMaterialSheenColorTex {}
+  declare_entity_associated!(PbrMRMaterialSheenRoughnessTex, PbrMRMaterialEntity);
+  impl TextureWithSamplingForeignKeys for PbrMRMaterialSheenRoughnessTex {}
+
   pub fn register_pbr_mr_material_data_model() {
     let table = global_database()
       .declare_entity::<PbrMRMaterialEntity>()
       .declare_component::<PbrMRMaterialBaseColorComponent>()
       .declare_component::<PbrMRMaterialRoughnessComponent>()
       .declare_component::<PbrMRMaterialMetallicComponent>()
-      .declare_component::<PbrMRMaterialEmissiveComponent>();
+      .declare_component::<PbrMRMaterialEmissiveComponent>()
+      .declare_component::<PbrMRMaterialEmissiveStrengthComponent>()
+      .declare_component::<PbrMRMaterialIorComponent>()
+      .declare_component::<PbrMRMaterialSpecularFactorComponent>()
+      .declare_component::<PbrMRMaterialSpecularColorComponent>()
+      .declare_component::<PbrMRMaterialTransmissionComponent>()
+      .declare_component::<PbrMRMaterialClearcoatComponent>()
+      .declare_component::<PbrMRMaterialClearcoatRoughnessComponent>()
+      .declare_component::<PbrMRMaterialSheenColorComponent>()
+      .declare_component::<PbrMRMaterialSheenRoughnessComponent>();
 
     let table = register_texture_with_sampling::<PbrMRMaterialBaseColorAlphaTex>(table);
     let table = register_texture_with_sampling::<PbrMRMaterialMetallicRoughnessTex>(table);
     let table = register_texture_with_sampling::<PbrMRMaterialEmissiveTex>(table);
+    let table = register_texture_with_sampling::<PbrMRMaterialSpecularTex>(table);
+    let table = register_texture_with_sampling::<PbrMRMaterialSpecularColorTex>(table);
+    let table = register_texture_with_sampling::<PbrMRMaterialTransmissionTex>(table);
+    let table = register_texture_with_sampling::<PbrMRMaterialClearcoatTex>(table);
+    let table = register_texture_with_sampling::<PbrMRMaterialClearcoatRoughnessTex>(table);
+    let table = register_texture_with_sampling::<PbrMRMaterialSheenColorTex>(table);
+    let table = register_texture_with_sampling::<PbrMRMaterialSheenRoughnessTex>(table);
     let table = register_normal::<PbrMRMaterialNormalInfo>(table);
+
+    let table = register_texture_transform::<PbrMRMaterialBaseColorAlphaTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialMetallicRoughnessTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialEmissiveTex>(table);
+    let table = register_texture_transform::<NormalTexSamplerOf<PbrMRMaterialNormalInfo>>(table);
+    let table = register_texture_transform::<PbrMRMaterialSpecularTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialSpecularColorTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialTransmissionTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialClearcoatTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialClearcoatRoughnessTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialSheenColorTex>(table);
+    let table = register_texture_transform::<PbrMRMaterialSheenRoughnessTex>(table);
     register_alpha_config::<PbrMRMaterialAlphaConfig>(table);
   }
 
@@ -255,6 +359,202 @@ mod mr_material {
     pub metallic_roughness_texture: Option<Texture2DWithSamplingDataView>,
     pub emissive_texture: Option<Texture2DWithSamplingDataView>,
     pub normal_texture: Option<NormalMappingDataView>,
+    pub extension: PhysicalMetallicRoughnessMaterialExtensionDataView,
+  }
+
+  /// The parameters of the common gltf pbr material extensions.
+  #[derive(Clone)]
+  pub struct PhysicalMetallicRoughnessMaterialExtensionDataView {
+    pub emissive_strength: f32,
+    pub ior: f32,
+    pub specular_factor: f32,
+    pub specular_color: Vec3<f32>,
+    pub transmission: f32,
+    pub clearcoat: f32,
+    pub clearcoat_roughness: f32,
+    pub sheen_color: Vec3<f32>,
+    pub sheen_roughness: f32,
+    pub texture_transforms: PhysicalMetallicRoughnessMaterialTextureTransforms,
+    pub specular_texture: Option<Texture2DWithSamplingDataView>,
+    pub specular_color_texture: Option<Texture2DWithSamplingDataView>,
+    pub transmission_texture: Option<Texture2DWithSamplingDataView>,
+    pub clearcoat_texture: Option<Texture2DWithSamplingDataView>,
+    pub clearcoat_roughness_texture: Option<Texture2DWithSamplingDataView>,
+    pub sheen_color_texture: Option<Texture2DWithSamplingDataView>,
+    pub sheen_roughness_texture: Option<Texture2DWithSamplingDataView>,
+  }
+
+  impl Default for PhysicalMetallicRoughnessMaterialExtensionDataView {
+    fn default() -> Self {
+      Self {
+        emissive_strength: 1.0,
+        ior: 1.5,
+        specular_factor: 1.0,
+        specular_color: Vec3::one(),
+        transmission: 0.0,
+        clearcoat: 0.0,
+        clearcoat_roughness: 0.0,
+        sheen_color: Vec3::zero(),
+        sheen_roughness: 0.0,
+        texture_transforms: Default::default(),
+        specular_texture: None,
+        specular_color_texture: None,
+        transmission_texture: None,
+        clearcoat_texture: None,
+        clearcoat_roughness_texture: None,
+        sheen_color_texture: None,
+        sheen_roughness_texture: None,
+      }
+    }
+  }
+
+  impl PhysicalMetallicRoughnessMaterialExtensionDataView {
+    pub fn write(self, w: EntityInitWriteView) -> EntityInitWriteView {
+      let mut w = w
+        .write::<PbrMRMaterialEmissiveStrengthComponent>(&self.emissive_strength)
+        .write::<PbrMRMaterialIorComponent>(&self.ior)
+        .write::<PbrMRMaterialSpecularFactorComponent>(&self.specular_factor)
+        .write::<PbrMRMaterialSpecularColorComponent>(&self.specular_color)
+        .write::<PbrMRMaterialTransmissionComponent>(&self.transmission)
+        .write::<PbrMRMaterialClearcoatComponent>(&self.clearcoat)
+        .write::<PbrMRMaterialClearcoatRoughnessComponent>(&self.clearcoat_roughness)
+        .write::<PbrMRMaterialSheenColorComponent>(&self.sheen_color)
+        .write::<PbrMRMaterialSheenRoughnessComponent>(&self.sheen_roughness);
+
+      w = self.texture_transforms.write(w);
+
+      if let Some(t) = self.specular_texture {
+        w = t.write::<PbrMRMaterialSpecularTex>(w);
+      }
+      if let Some(t) = self.specular_color_texture {
+        w = t.write::<PbrMRMaterialSpecularColorTex>(w);
+      }
+      if let Some(t) = self.transmission_texture {
+        w = t.write::<PbrMRMaterialTransmissionTex>(w);
+      }
+      if let Some(t) = self.clearcoat_texture {
+        w = t.write::<PbrMRMaterialClearcoatTex>(w);
+      }
+      if let Some(t) = self.clearcoat_roughness_texture {
+        w = t.write::<PbrMRMaterialClearcoatRoughnessTex>(w);
+      }
+      if let Some(t) = self.sheen_color_texture {
+        w = t.write::<PbrMRMaterialSheenColorTex>(w);
+      }
+      if let Some(t) = self.sheen_roughness_texture {
+        w = t.write::<PbrMRMaterialSheenRoughnessTex>(w);
+      }
+      w
+    }
+
+    pub fn read(
+      reader: &TableReader<PbrMRMaterialEntity>,
+      id: EntityHandle<PbrMRMaterialEntity>,
+    ) -> Self {
+      Self {
+        emissive_strength: reader.read::<PbrMRMaterialEmissiveStrengthComponent>(id),
+        ior: reader.read::<PbrMRMaterialIorComponent>(id),
+        specular_factor: reader.read::<PbrMRMaterialSpecularFactorComponent>(id),
+        specular_color: reader.read::<PbrMRMaterialSpecularColorComponent>(id),
+        transmission: reader.read::<PbrMRMaterialTransmissionComponent>(id),
+        clearcoat: reader.read::<PbrMRMaterialClearcoatComponent>(id),
+        clearcoat_roughness: reader.read::<PbrMRMaterialClearcoatRoughnessComponent>(id),
+        sheen_color: reader.read::<PbrMRMaterialSheenColorComponent>(id),
+        sheen_roughness: reader.read::<PbrMRMaterialSheenRoughnessComponent>(id),
+        texture_transforms: PhysicalMetallicRoughnessMaterialTextureTransforms::read(reader, id),
+        specular_texture: Texture2DWithSamplingDataView::read::<PbrMRMaterialSpecularTex, _>(
+          reader, id,
+        ),
+        specular_color_texture: Texture2DWithSamplingDataView::read::<
+          PbrMRMaterialSpecularColorTex,
+          _,
+        >(reader, id),
+        transmission_texture: Texture2DWithSamplingDataView::read::<PbrMRMaterialTransmissionTex, _>(
+          reader, id,
+        ),
+        clearcoat_texture: Texture2DWithSamplingDataView::read::<PbrMRMaterialClearcoatTex, _>(
+          reader, id,
+        ),
+        clearcoat_roughness_texture: Texture2DWithSamplingDataView::read::<
+          PbrMRMaterialClearcoatRoughnessTex,
+          _,
+        >(reader, id),
+        sheen_color_texture: Texture2DWithSamplingDataView::read::<PbrMRMaterialSheenColorTex, _>(
+          reader, id,
+        ),
+        sheen_roughness_texture: Texture2DWithSamplingDataView::read::<
+          PbrMRMaterialSheenRoughnessTex,
+          _,
+        >(reader, id),
+      }
+    }
+
+    pub fn iter_textures(&self) -> impl Iterator<Item = &Texture2DWithSamplingDataView> {
+      [
+        &self.specular_texture,
+        &self.specular_color_texture,
+        &self.transmission_texture,
+        &self.clearcoat_texture,
+        &self.clearcoat_roughness_texture,
+        &self.sheen_color_texture,
+        &self.sheen_roughness_texture,
+      ]
+      .into_iter()
+      .flatten()
+    }
+  }
+
+  /// KHR_texture_transform, one transform per texture slot.
+  #[derive(Clone, Copy, Default, PartialEq, Debug)]
+  pub struct PhysicalMetallicRoughnessMaterialTextureTransforms {
+    pub base_color: TextureTransform,
+    pub metallic_roughness: TextureTransform,
+    pub emissive: TextureTransform,
+    pub normal: TextureTransform,
+    pub specular: TextureTransform,
+    pub specular_color: TextureTransform,
+    pub transmission: TextureTransform,
+    pub clearcoat: TextureTransform,
+    pub clearcoat_roughness: TextureTransform,
+    pub sheen_color: TextureTransform,
+    pub sheen_roughness: TextureTransform,
+  }
+
+  impl PhysicalMetallicRoughnessMaterialTextureTransforms {
+    pub fn write(self, w: EntityInitWriteView) -> EntityInitWriteView {
+      w.write::<TextureTransformOf<PbrMRMaterialBaseColorAlphaTex>>(&self.base_color)
+        .write::<TextureTransformOf<PbrMRMaterialMetallicRoughnessTex>>(&self.metallic_roughness)
+        .write::<TextureTransformOf<PbrMRMaterialEmissiveTex>>(&self.emissive)
+        .write::<TextureTransformOf<NormalTexSamplerOf<PbrMRMaterialNormalInfo>>>(&self.normal)
+        .write::<TextureTransformOf<PbrMRMaterialSpecularTex>>(&self.specular)
+        .write::<TextureTransformOf<PbrMRMaterialSpecularColorTex>>(&self.specular_color)
+        .write::<TextureTransformOf<PbrMRMaterialTransmissionTex>>(&self.transmission)
+        .write::<TextureTransformOf<PbrMRMaterialClearcoatTex>>(&self.clearcoat)
+        .write::<TextureTransformOf<PbrMRMaterialClearcoatRoughnessTex>>(&self.clearcoat_roughness)
+        .write::<TextureTransformOf<PbrMRMaterialSheenColorTex>>(&self.sheen_color)
+        .write::<TextureTransformOf<PbrMRMaterialSheenRoughnessTex>>(&self.sheen_roughness)
+    }
+
+    pub fn read(
+      reader: &TableReader<PbrMRMaterialEntity>,
+      id: EntityHandle<PbrMRMaterialEntity>,
+    ) -> Self {
+      Self {
+        base_color: reader.read::<TextureTransformOf<PbrMRMaterialBaseColorAlphaTex>>(id),
+        metallic_roughness: reader
+          .read::<TextureTransformOf<PbrMRMaterialMetallicRoughnessTex>>(id),
+        emissive: reader.read::<TextureTransformOf<PbrMRMaterialEmissiveTex>>(id),
+        normal: reader.read::<TextureTransformOf<NormalTexSamplerOf<PbrMRMaterialNormalInfo>>>(id),
+        specular: reader.read::<TextureTransformOf<PbrMRMaterialSpecularTex>>(id),
+        specular_color: reader.read::<TextureTransformOf<PbrMRMaterialSpecularColorTex>>(id),
+        transmission: reader.read::<TextureTransformOf<PbrMRMaterialTransmissionTex>>(id),
+        clearcoat: reader.read::<TextureTransformOf<PbrMRMaterialClearcoatTex>>(id),
+        clearcoat_roughness: reader
+          .read::<TextureTransformOf<PbrMRMaterialClearcoatRoughnessTex>>(id),
+        sheen_color: reader.read::<TextureTransformOf<PbrMRMaterialSheenColorTex>>(id),
+        sheen_roughness: reader.read::<TextureTransformOf<PbrMRMaterialSheenRoughnessTex>>(id),
+      }
+    }
   }
 
   impl Default for PhysicalMetallicRoughnessMaterialDataView {
@@ -269,6 +569,7 @@ mod mr_material {
         metallic_roughness_texture: None,
         emissive_texture: None,
         normal_texture: None,
+        extension: Default::default(),
       }
     }
   }
@@ -302,12 +603,65 @@ mod mr_material {
         if let Some(normal) = self.normal_texture {
           w = normal.write::<PbrMRMaterialNormalInfo>(w);
         }
-        w
+        self.extension.write(w)
       })
     }
   }
 }
 
+/// The uv transform defined by KHR_texture_transform, the uv is scaled, then rotated, then
+/// translated.
+#[repr(C)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Debug, Facet)]
+pub struct TextureTransform {
+  pub offset: Vec2<f32>,
+  /// in radians, counter-clockwise in uv space
+  pub rotation: f32,
+  pub scale: Vec2<f32>,
+}
+
+impl Default for TextureTransform {
+  fn default() -> Self {
+    Self {
+      offset: Vec2::zero(),
+      rotation: 0.,
+      scale: Vec2::one(),
+    }
+  }
+}
+
+impl TextureTransform {
+  pub fn is_identity(&self) -> bool {
+    *self == Self::default()
+  }
+
+  /// the first two rows of the 3x3 affine matrix, the transformed uv is
+  /// (row_0.xy · uv + row_0.z, row_1.xy · uv + row_1.z), the w is padding.
+  pub fn to_matrix_rows(&self) -> [Vec4<f32>; 2] {
+    let (s, c) = self.rotation.sin_cos();
+    [
+      Vec4::new(c * self.scale.x, s * self.scale.y, self.offset.x, 0.),
+      Vec4::new(-s * self.scale.x, c * self.scale.y, self.offset.y, 0.),
+    ]
+  }
+}
+
+/// the uv transform of the texture slot, the default value is identity.
+pub struct TextureTransformOf<T>(T);
+impl<T: TextureWithSamplingForeignKeys> EntityAssociateSemantic for TextureTransformOf<T> {
+  type Entity = T::Entity;
+}
+impl<T: TextureWithSamplingForeignKeys> ComponentSemantic for TextureTransformOf<T> {
+  type Data = TextureTransform;
+}
+
+pub fn register_texture_transform<T: TextureWithSamplingForeignKeys>(
+  table: TypedArcTable<T::Entity>,
+) -> TypedArcTable<T::Entity> {
+  table.declare_component::<TextureTransformOf<T>>()
+}
+
 pub trait NormalInfoSemantic: EntityAssociateSemantic {}
 pub struct NormalScaleOf<T>(T);
 impl<T: NormalInfoSemantic> EntityAssociateSemantic for NormalScaleOf<T> {
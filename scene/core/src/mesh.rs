@@ -49,6 +49,7 @@ declare_foreign_key!(
 
 pub struct AttributesMeshEntityFromAttributesMeshWriter {
   pub relation: TableWriter<AttributesMeshEntityVertexBufferRelation>,
+  pub morph_target: TableWriter<AttributesMeshMorphTargetEntity>,
   pub mesh: TableWriter<AttributesMeshEntity>,
 }
 
@@ -56,6 +57,7 @@ impl AttributesMeshEntityFromAttributesMeshWriter {
   pub fn from_global() -> Self {
     Self {
       relation: global_entity_of::<AttributesMeshEntityVertexBufferRelation>().entity_writer(),
+      morph_target: global_entity_of::<AttributesMeshMorphTargetEntity>().entity_writer(),
       mesh: global_entity_of::<AttributesMeshEntity>().entity_writer(),
     }
   }
@@ -102,19 +104,26 @@ pub struct AttributesMeshEntities {
 }
 
 impl AttributesMeshEntities {
+  /// The vertex buffer relations and the morph targets are deleted with the mesh by the
+  /// cascade foreign key.
+  ///
   /// this method assume the mesh's buffers are owned by mesh itself and not shared
   pub fn clean_up(
     &self,
     writer: &mut AttributesMeshEntityFromAttributesMeshWriter,
     buffer: &mut TableWriter<BufferEntity>,
   ) {
-    for (r, b) in &self.vertices {
-      writer.relation.delete_entity(*r);
+    writer.mesh.delete_entity_with(
+      self.mesh,
+      &mut [
+        writer.relation.as_untyped_mut(),
+        writer.morph_target.as_untyped_mut(),
+      ],
+    );
+
+    for (_, b) in &self.vertices {
       buffer.delete_entity(*b);
     }
-
-    writer.mesh.delete_entity(self.mesh);
-
     if let Some(index) = self.index {
       buffer.delete_entity(index);
     }
@@ -215,7 +224,10 @@ pub fn register_attribute_mesh_data_model() {
   let table = global_database()
     .declare_entity::<AttributesMeshEntityVertexBufferRelation>()
     .declare_component::<AttributesMeshEntityVertexBufferSemantic>()
-    .declare_foreign_key::<AttributesMeshEntityVertexBufferRelationRefAttributesMeshEntity>();
+    .declare_foreign_key_with_policy::<AttributesMeshEntityVertexBufferRelationRefAttributesMeshEntity>(
+      ForeignKeyDeletePolicy::Cascade,
+    )
+    .declare_hash_index::<AttributesMeshEntityVertexBufferRelationRefAttributesMeshEntity>();
 
   register_scene_buffer_view::<AttributeVertexRef>(table);
 }
@@ -179,13 +179,15 @@ pub fn register_std_model_data_model() {
     .declare_foreign_key::<StandardModelRefUnlitMaterial>()
     .declare_foreign_key::<StandardModelRefPbrSGMaterial>()
     .declare_foreign_key::<StandardModelRefPbrMRMaterial>()
-    .declare_foreign_key::<StandardModelRefSkin>();
+    .declare_foreign_key::<StandardModelRefSkin>()
+    .declare_component::<StandardModelMorphTargetWeights>();
 }
 
 pub struct StandardModelDataView {
   pub material: SceneMaterialDataView,
   pub mesh: EntityHandle<AttributesMeshEntity>,
   pub skin: Option<EntityHandle<SceneSkinEntity>>,
+  pub morph_target_weights: Option<MorphTargetWeights>,
   pub states_override: Option<RasterizationStates>,
 }
 
@@ -195,6 +197,7 @@ impl StandardModelDataView {
       material,
       mesh,
       skin: None,
+      morph_target_weights: None,
       states_override: None,
     }
   }
@@ -204,6 +207,11 @@ impl StandardModelDataView {
     self
   }
 
+  pub fn with_morph_target_weights(mut self, weights: MorphTargetWeights) -> Self {
+    self.morph_target_weights = Some(weights);
+    self
+  }
+
   pub fn with_states_override(mut self, states_override: RasterizationStates) -> Self {
     self.states_override = Some(states_override);
     self
@@ -229,6 +237,7 @@ impl StandardModelDataView {
       .write::<StandardModelRefAttributesMeshEntity>(&self.mesh.some_handle())
       .write::<StandardModelRasterizationOverride>(&self.states_override)
       .write::<StandardModelRefSkin>(&self.skin.map(|v| v.into_raw()))
+      .write::<StandardModelMorphTargetWeights>(&self.morph_target_weights)
     })
   }
 }
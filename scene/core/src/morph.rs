@@ -0,0 +1,126 @@
+use crate::*;
+
+/// The max morph target count that can be applied to one mesh at the same time.
+pub const MAX_MORPH_TARGET_COUNT: usize = 8;
+
+// Each entity is one attribute of one morph target of the mesh. The data is the
+// displacement of the attribute, the item count should be same as the mesh vertex count.
+declare_entity!(AttributesMeshMorphTargetEntity);
+declare_foreign_key!(
+  AttributesMeshMorphTargetRefAttributesMesh,
+  AttributesMeshMorphTargetEntity,
+  AttributesMeshEntity
+);
+// should be less than [MAX_MORPH_TARGET_COUNT]
+declare_component!(
+  AttributesMeshMorphTargetIndex,
+  AttributesMeshMorphTargetEntity,
+  u32
+);
+// only position, normal and tangent is valid
+declare_component!(
+  AttributesMeshMorphTargetSemantic,
+  AttributesMeshMorphTargetEntity,
+  AttributeSemantic
+);
+
+declare_entity_associated!(MorphTargetVertexRef, AttributesMeshMorphTargetEntity);
+impl SceneBufferView for MorphTargetVertexRef {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, Facet)]
+pub struct MorphTargetWeights {
+  pub weights: [f32; MAX_MORPH_TARGET_COUNT],
+}
+
+impl MorphTargetWeights {
+  /// the weights exceed the [MAX_MORPH_TARGET_COUNT] is ignored
+  pub fn from_slice(weights: &[f32]) -> Self {
+    let mut r = Self::default();
+    for (w, input) in r.weights.iter_mut().zip(weights) {
+      *w = *input;
+    }
+    r
+  }
+
+  pub fn lerp(self, other: Self, t: f32) -> Self {
+    let mut r = self;
+    for (w, other) in r.weights.iter_mut().zip(other.weights) {
+      *w = w.lerp(other, t);
+    }
+    r
+  }
+}
+
+// the morph target of the mesh is only applied when the std model has this component
+declare_component!(
+  StandardModelMorphTargetWeights,
+  StandardModelEntity,
+  Option<MorphTargetWeights>
+);
+
+pub fn register_morph_target_data_model() {
+  let table = global_database()
+    .declare_entity::<AttributesMeshMorphTargetEntity>()
+    .declare_foreign_key_with_policy::<AttributesMeshMorphTargetRefAttributesMesh>(
+      ForeignKeyDeletePolicy::Cascade,
+    )
+    .declare_hash_index::<AttributesMeshMorphTargetRefAttributesMesh>()
+    .declare_component::<AttributesMeshMorphTargetIndex>()
+    .declare_component::<AttributesMeshMorphTargetSemantic>();
+  register_scene_buffer_view::<MorphTargetVertexRef>(table);
+}
+
+/// The morph targets of one attribute mesh, the outer index is the target index.
+#[derive(Clone, Default)]
+pub struct AttributesMeshMorphTargets {
+  pub targets: Vec<Vec<(AttributeSemantic, AttributeAccessor)>>,
+}
+
+pub struct AttributesMeshMorphTargetEntities {
+  pub targets: Vec<(
+    EntityHandle<AttributesMeshMorphTargetEntity>,
+    EntityHandle<BufferEntity>,
+  )>,
+}
+
+impl AttributesMeshMorphTargetEntities {
+  /// The target entities are deleted with the mesh by the cascade foreign key, see
+  /// [AttributesMeshEntities::clean_up]. This method only deletes the displacement buffers.
+  pub fn clean_up(&self, buffer: &mut TableWriter<BufferEntity>) {
+    for (_, b) in &self.targets {
+      buffer.delete_entity(*b);
+    }
+  }
+}
+
+impl AttributesMeshMorphTargets {
+  pub fn write(
+    self,
+    mesh: EntityHandle<AttributesMeshEntity>,
+    writer: &mut TableWriter<AttributesMeshMorphTargetEntity>,
+    buffer: &mut TableWriter<BufferEntity>,
+  ) -> AttributesMeshMorphTargetEntities {
+    let mut targets = Vec::new();
+    for (index, target) in self.targets.into_iter().enumerate() {
+      for (semantic, data) in target {
+        let count = data.count as u32;
+        let data = data.write(buffer);
+        let view = SceneBufferViewDataView {
+          data: Some(data),
+          range: None,
+          count,
+        };
+        let target = writer.new_entity(|w| {
+          let w = w
+            .write::<AttributesMeshMorphTargetRefAttributesMesh>(&mesh.some_handle())
+            .write::<AttributesMeshMorphTargetIndex>(&(index as u32))
+            .write::<AttributesMeshMorphTargetSemantic>(&semantic);
+          view.write::<MorphTargetVertexRef>(w)
+        });
+        targets.push((target, data));
+      }
+    }
+    AttributesMeshMorphTargetEntities { targets }
+  }
+}
@@ -114,6 +114,7 @@ impl SceneReader {
       material,
       mesh: m.read_expected_foreign_key::<StandardModelRefAttributesMeshEntity>(id),
       skin: m.read_foreign_key::<StandardModelRefSkin>(id),
+      morph_target_weights: m.read::<StandardModelMorphTargetWeights>(id),
       states_override: m.read::<StandardModelRasterizationOverride>(id),
     }
   }
@@ -172,6 +173,7 @@ impl SceneReader {
       >(m, id),
       emissive_texture: Texture2DWithSamplingDataView::read::<PbrMRMaterialEmissiveTex, _>(m, id),
       normal_texture: NormalMappingDataView::read::<PbrMRMaterialNormalInfo, _>(m, id),
+      extension: PhysicalMetallicRoughnessMaterialExtensionDataView::read(m, id),
     }
   }
 
@@ -0,0 +1,73 @@
+use crate::*;
+
+pub struct ReflectionProbeDataView {
+  pub shape: ReflectionProbeShapeType,
+  pub blend_distance: f32,
+  pub intensity: f32,
+  pub node: EntityHandle<SceneNodeEntity>,
+  pub scene: EntityHandle<SceneEntity>,
+}
+
+impl ReflectionProbeDataView {
+  pub fn write(
+    self,
+    writer: &mut TableWriter<ReflectionProbeEntity>,
+  ) -> EntityHandle<ReflectionProbeEntity> {
+    writer.new_entity(|w| {
+      w.write::<ReflectionProbeShape>(&self.shape)
+        .write::<ReflectionProbeBlendDistance>(&self.blend_distance)
+        .write::<ReflectionProbeIntensity>(&self.intensity)
+        .write::<ReflectionProbeRefNode>(&self.node.some_handle())
+        .write::<ReflectionProbeRefScene>(&self.scene.some_handle())
+    })
+  }
+}
+
+declare_entity!(
+  /// A local specular environment captured at the origin of the associated [SceneNodeEntity].
+  ///
+  /// The influence volume is the local space box from (-1, -1, -1) to (1, 1, 1) or the local
+  /// space unit sphere, placed by the world matrix of the associated [SceneNodeEntity]. The
+  /// influence volume is also used as the proxy geometry for the parallax correction.
+  ReflectionProbeEntity);
+declare_foreign_key!(
+  /// Associates this reflection probe with a [SceneEntity].
+  ReflectionProbeRefScene, ReflectionProbeEntity, SceneEntity);
+declare_foreign_key!(
+  /// Determines the capture position and the influence volume placement by the world space
+  /// transform of the associated [SceneNodeEntity].
+  ReflectionProbeRefNode, ReflectionProbeEntity, SceneNodeEntity);
+declare_component!(
+  /// The shape of the influence volume.
+  ReflectionProbeShape,
+  ReflectionProbeEntity,
+  ReflectionProbeShapeType
+);
+declare_component!(
+  /// The world space distance from the influence volume boundary to the inside, in which the
+  /// probe fades out to the overlapped probes or the environment lighting.
+  ReflectionProbeBlendDistance, ReflectionProbeEntity, f32, 0.5);
+declare_component!(
+  /// The scale applied to the captured radiance.
+  ReflectionProbeIntensity, ReflectionProbeEntity, f32, 1.);
+
+pub fn register_reflection_probe_data_model() {
+  global_database()
+    .declare_entity::<ReflectionProbeEntity>()
+    .declare_component::<ReflectionProbeShape>()
+    .declare_component::<ReflectionProbeBlendDistance>()
+    .declare_component::<ReflectionProbeIntensity>()
+    .declare_foreign_key::<ReflectionProbeRefScene>()
+    .declare_foreign_key::<ReflectionProbeRefNode>();
+}
+
+/// The influence volume shape of a reflection probe.
+#[repr(C)]
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Facet)]
+#[derive(Default)]
+pub enum ReflectionProbeShapeType {
+  #[default]
+  Box,
+  Sphere,
+}
@@ -17,6 +17,8 @@ pub struct SceneWriter {
   pub point_light_writer: TableWriter<PointLightEntity>,
   pub directional_light_writer: TableWriter<DirectionalLightEntity>,
   pub spot_light_writer: TableWriter<SpotLightEntity>,
+  pub light_probe_volume_writer: TableWriter<LightProbeVolumeEntity>,
+  pub reflection_probe_writer: TableWriter<ReflectionProbeEntity>,
   pub animation: TableWriter<SceneAnimationEntity>,
   pub animation_channel: TableWriter<SceneAnimationChannelEntity>,
   pub skin_writer: TableWriter<SceneSkinEntity>,
@@ -41,6 +43,8 @@ impl SceneWriter {
       point_light_writer: global_entity_of().entity_writer(),
       directional_light_writer: global_entity_of().entity_writer(),
       spot_light_writer: global_entity_of().entity_writer(),
+      light_probe_volume_writer: global_entity_of().entity_writer(),
+      reflection_probe_writer: global_entity_of().entity_writer(),
       animation: global_entity_of().entity_writer(),
       animation_channel: global_entity_of().entity_writer(),
       buffer_writer: global_entity_of().entity_writer(),
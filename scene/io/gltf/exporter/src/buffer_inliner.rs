@@ -4,6 +4,32 @@ pub struct BufferResourceInliner<'a> {
   pub binary_data: &'a mut Option<InlineBinary>,
   pub buffers: &'a mut Resource<u64, gltf_json::Buffer>,
   pub buffer_views: &'a mut Resource<u64, gltf_json::buffer::View>,
+  /// encode the mesh buffers by the EXT_meshopt_compression
+  pub meshopt_compression: bool,
+  pub meshopt_fallback: Option<MeshoptFallbackBuffer>,
+}
+
+/// the EXT_meshopt_compression requires the compressed view to reference a fallback buffer, we
+/// not provide the fallback data so the extension is marked as required.
+pub struct MeshoptFallbackBuffer {
+  byte_length: usize,
+  idx: gltf_json::Index<gltf_json::Buffer>,
+}
+
+pub enum MeshoptCompressMode {
+  Attributes,
+  Triangles,
+  Indices,
+}
+
+impl MeshoptCompressMode {
+  fn gltf_name(&self) -> &'static str {
+    match self {
+      Self::Attributes => "ATTRIBUTES",
+      Self::Triangles => "TRIANGLES",
+      Self::Indices => "INDICES",
+    }
+  }
 }
 
 pub struct InlineBinary {
@@ -17,6 +43,77 @@ impl<'a> BufferResourceInliner<'a> {
       self.buffers.collected[binary_data.idx.value()].byte_length =
         gltf_json::validation::USize64(binary_data.binary_data.len() as u64);
     }
+    if let Some(fallback) = &self.meshopt_fallback {
+      self.buffers.collected[fallback.idx.value()].byte_length =
+        gltf_json::validation::USize64(fallback.byte_length as u64);
+    }
+  }
+
+  pub fn meshopt_compression_used(&self) -> bool {
+    self.meshopt_fallback.is_some()
+  }
+
+  /// the encoded data is stored in the binary buffer, and the returned view references the
+  /// range of the decoded data in the fallback buffer.
+  pub fn collect_meshopt_compressed_view(
+    &mut self,
+    encoded: &[u8],
+    mode: MeshoptCompressMode,
+    count: usize,
+    byte_stride: usize,
+  ) -> gltf_json::Index<gltf_json::buffer::View> {
+    // the binary buffer should be created first, as the glb binary chunk must be the first buffer
+    let (buffer, encoded_length, encoded_offset) = self.collect_inline_buffer(encoded);
+
+    let fallback = self.meshopt_fallback.get_or_insert_with(|| {
+      let mut extensions = gltf_json::extensions::buffer::Buffer::default();
+      extensions.others.insert(
+        MESHOPT_COMPRESSION_EXTENSION.to_string(),
+        serde_json::json!({ "fallback": true }),
+      );
+      MeshoptFallbackBuffer {
+        byte_length: 0,
+        idx: self.buffers.append_and_skip_mapping(gltf_json::Buffer {
+          byte_length: gltf_json::validation::USize64(0),
+          name: Default::default(),
+          uri: Default::default(),
+          extensions: Some(extensions),
+          extras: Default::default(),
+        }),
+      }
+    });
+
+    let byte_offset = fallback.byte_length.next_multiple_of(4);
+    let byte_length = count * byte_stride;
+    fallback.byte_length = byte_offset + byte_length;
+
+    let mut extensions = gltf_json::extensions::buffer::View::default();
+    extensions.others.insert(
+      MESHOPT_COMPRESSION_EXTENSION.to_string(),
+      serde_json::json!({
+        "buffer": buffer.value(),
+        "byteOffset": encoded_offset.map(|v| v.0).unwrap_or(0),
+        "byteLength": encoded_length.0,
+        "byteStride": byte_stride,
+        "count": count,
+        "mode": mode.gltf_name(),
+      }),
+    );
+
+    self
+      .buffer_views
+      .append_and_skip_mapping(gltf_json::buffer::View {
+        buffer: fallback.idx,
+        byte_length: gltf_json::validation::USize64(byte_length as u64),
+        byte_offset: gltf_json::validation::USize64(byte_offset as u64).into(),
+        // the index buffer view should not define the byte stride
+        byte_stride: matches!(mode, MeshoptCompressMode::Attributes)
+          .then_some(gltf_json::buffer::Stride(byte_stride)),
+        name: Default::default(),
+        target: Default::default(),
+        extensions: Some(extensions),
+        extras: Default::default(),
+      })
   }
 
   pub fn collect_inline_packed_view_buffer(
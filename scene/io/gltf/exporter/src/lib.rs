@@ -24,6 +24,16 @@ use buffer_inliner::*;
 mod resource_collector;
 use resource_collector::*;
 
+pub const MESHOPT_COMPRESSION_EXTENSION: &str = "EXT_meshopt_compression";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GltfExportConfig {
+  /// encode the index and vertex buffers by the EXT_meshopt_compression, the extension is
+  /// required to load the exported file. The vertex attributes are losslessly encoded, quantize
+  /// them before export to get a better compression ratio.
+  pub meshopt_compression: bool,
+}
+
 #[derive(Debug)]
 pub enum GltfExportErr {
   IO(std::io::Error),
@@ -39,6 +49,16 @@ pub fn build_scene_to_gltf(
   scene: EntityHandle<SceneEntity>,
   folder_path: &Path,
   file_name: &str,
+) -> Result<(), GltfExportErr> {
+  build_scene_to_gltf_with_config(reader, scene, folder_path, file_name, &Default::default())
+}
+
+pub fn build_scene_to_gltf_with_config(
+  reader: &SceneReader,
+  scene: EntityHandle<SceneEntity>,
+  folder_path: &Path,
+  file_name: &str,
+  config: &GltfExportConfig,
 ) -> Result<(), GltfExportErr> {
   fs::create_dir_all(folder_path).map_err(GltfExportErr::IO)?;
 
@@ -96,8 +116,6 @@ pub fn build_scene_to_gltf(
 
   let mut all_material_to_write = FastHashSet::default();
 
-  let mut has_sg_material = false;
-
   for (_, model_info) in reader.std_models(&scene) {
     let std_model = reader.read_std_model(model_info.model);
     all_mesh_to_write.insert(std_model.mesh);
@@ -111,7 +129,6 @@ pub fn build_scene_to_gltf(
         }
       }
       SceneMaterialDataView::PbrSGMaterial(m) => {
-        has_sg_material = true;
         all_material_to_write.insert(std_model.material);
         let m = reader.read_pbr_sg_material(m);
         if let Some(t) = m.albedo_texture {
@@ -142,6 +159,7 @@ pub fn build_scene_to_gltf(
         if let Some(t) = m.emissive_texture {
           all_texture_to_write.insert(t);
         }
+        all_texture_to_write.extend(m.extension.iter_textures().copied());
       }
       _ => log::warn!("unknown material ty"),
     }
@@ -158,6 +176,8 @@ pub fn build_scene_to_gltf(
     binary_data: &mut binary_data,
     buffers: &mut buffers,
     buffer_views: &mut buffer_views,
+    meshopt_compression: config.meshopt_compression,
+    meshopt_fallback: None,
   };
 
   let mut images = Default::default();
@@ -168,6 +188,7 @@ pub fn build_scene_to_gltf(
     data: [255, 255, 255, 255].to_vec(),
     format: rendiation_texture_core::TextureFormat::Rgba8UnormSrgb,
     size: Size::from_u32_pair_min_one((1, 1)),
+    precomputed_mips: Vec::new(),
   };
 
   for tex in all_texture_to_write {
@@ -183,8 +204,9 @@ pub fn build_scene_to_gltf(
   }
 
   let mut materials = Default::default();
+  let mut extensions_used = FastHashSet::default();
   for m in all_material_to_write {
-    build_material(&mut materials, reader, &m, &textures);
+    build_material(&mut materials, &mut extensions_used, reader, &m, &textures);
   }
 
   for (model, model_info) in reader.std_models(&scene) {
@@ -223,6 +245,12 @@ pub fn build_scene_to_gltf(
     );
   }
 
+  let mut extensions_required = Vec::new();
+  if buffer_builder.meshopt_compression_used() {
+    extensions_used.insert(MESHOPT_COMPRESSION_EXTENSION);
+    extensions_required.push(MESHOPT_COMPRESSION_EXTENSION.to_string());
+  }
+
   buffer_builder.finalize();
 
   let scene = gltf_json::Scene {
@@ -241,10 +269,8 @@ pub fn build_scene_to_gltf(
     version: String::from("2.0"),
   };
 
-  let mut extensions_used = Vec::new();
-  if has_sg_material {
-    extensions_used.push(String::from("KHR_materials_pbrSpecularGlossiness"));
-  }
+  let mut extensions_used: Vec<_> = extensions_used.into_iter().map(String::from).collect();
+  extensions_used.sort();
 
   let json = gltf_json::Root {
     accessors: accessors.collected,
@@ -256,7 +282,7 @@ pub fn build_scene_to_gltf(
     extensions: Default::default(),
     extras: Default::default(),
     extensions_used,
-    extensions_required: Default::default(),
+    extensions_required,
     cameras: Default::default(),
     materials: materials.collected,
     meshes: models.collected,
@@ -297,6 +323,53 @@ pub fn build_scene_to_gltf(
 struct ViewKey {
   buffer_id: u64,
   view_range: BufferViewRange,
+  /// the meshopt compressed view is created for each accessor, (byte_offset, count,
+  /// item_byte_size) of the accessor
+  compressed_accessor: Option<(usize, usize, usize)>,
+}
+
+enum InlineAccessorUsage {
+  Vertex,
+  Index { triangle_list: bool },
+}
+
+/// return the encoded data, mode and byte stride if the accessor can be compressed
+fn meshopt_encode_accessor(
+  acc: &AttributeAccessor,
+  usage: &InlineAccessorUsage,
+) -> Option<(Vec<u8>, MeshoptCompressMode, usize)> {
+  let bytes = acc.visit_bytes()?.get(..acc.count * acc.item_byte_size)?;
+  match usage {
+    InlineAccessorUsage::Vertex => {
+      // the gltf vertex stride limit is more strict than the codec
+      let stride = acc.item_byte_size;
+      if !stride.is_multiple_of(4) || !(4..=252).contains(&stride) {
+        return None;
+      }
+      let encoded = rendiation_mesh_compression::encode_vertex_buffer(bytes, stride).ok()?;
+      Some((encoded, MeshoptCompressMode::Attributes, stride))
+    }
+    InlineAccessorUsage::Index { triangle_list } => {
+      let indices: Vec<u32> = match acc.item_byte_size {
+        2 => bytes
+          .chunks_exact(2)
+          .map(|v| u16::from_le_bytes([v[0], v[1]]) as u32)
+          .collect(),
+        4 => bytes
+          .chunks_exact(4)
+          .map(|v| u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+          .collect(),
+        _ => return None,
+      };
+      if *triangle_list && indices.len().is_multiple_of(3) {
+        let encoded = rendiation_mesh_compression::encode_index_buffer(&indices);
+        Some((encoded, MeshoptCompressMode::Triangles, acc.item_byte_size))
+      } else {
+        let encoded = rendiation_mesh_compression::encode_index_sequence(&indices);
+        Some((encoded, MeshoptCompressMode::Indices, acc.item_byte_size))
+      }
+    }
+  }
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -307,6 +380,7 @@ struct AttributeAccessorKey {
   item_byte_size: usize,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_inline_accessor(
   buffer_inliner: &mut BufferResourceInliner,
   buffer_view_map: &mut FastHashMap<ViewKey, gltf_json::Index<gltf_json::buffer::View>>,
@@ -315,24 +389,45 @@ fn build_inline_accessor(
   c_ty: gltf_json::accessor::ComponentType,
   ty: gltf_json::accessor::Type,
   normalized: bool,
+  usage: InlineAccessorUsage,
 ) -> gltf_json::Index<gltf_json::Accessor> {
-  let view = buffer_view_map
-    .entry(ViewKey {
-      buffer_id: acc.view.buffer.as_ptr() as u64,
-      view_range: acc.view.range,
-    })
-    .or_insert_with(|| buffer_inliner.collect_inline_packed_view_buffer(&acc.view.buffer));
+  let compressed = buffer_inliner
+    .meshopt_compression
+    .then(|| meshopt_encode_accessor(acc, &usage))
+    .flatten();
+
+  let (view, byte_offset) = if let Some((encoded, mode, byte_stride)) = compressed {
+    let view = buffer_view_map
+      .entry(ViewKey {
+        buffer_id: acc.view.buffer.as_ptr() as u64,
+        view_range: acc.view.range,
+        compressed_accessor: Some((acc.byte_offset, acc.count, acc.item_byte_size)),
+      })
+      .or_insert_with(|| {
+        buffer_inliner.collect_meshopt_compressed_view(&encoded, mode, acc.count, byte_stride)
+      });
+    (*view, 0)
+  } else {
+    let view = buffer_view_map
+      .entry(ViewKey {
+        buffer_id: acc.view.buffer.as_ptr() as u64,
+        view_range: acc.view.range,
+        compressed_accessor: None,
+      })
+      .or_insert_with(|| buffer_inliner.collect_inline_packed_view_buffer(&acc.view.buffer));
+    (*view, acc.byte_offset)
+  };
 
   let key = AttributeAccessorKey {
     view: view.value(),
-    byte_offset: acc.byte_offset,
+    byte_offset,
     count: acc.count,
     item_byte_size: acc.item_byte_size,
   };
 
   accessors.get_or_insert_with(key, || gltf_json::Accessor {
-    buffer_view: (*view).into(),
-    byte_offset: gltf_json::validation::USize64(acc.byte_offset as u64).into(),
+    buffer_view: view.into(),
+    byte_offset: gltf_json::validation::USize64(byte_offset as u64).into(),
     count: gltf_json::validation::USize64(acc.count as u64),
     component_type: gltf_json::validation::Checked::Valid(
       gltf_json::accessor::GenericComponentType(c_ty),
@@ -404,6 +499,7 @@ fn build_model(
           cty,
           ty,
           false,
+          InlineAccessorUsage::Vertex,
         );
 
         // todo, consider using scene derive data result
@@ -427,6 +523,9 @@ fn build_model(
         attributes.insert(key, acc);
       }
 
+      let index_usage = || InlineAccessorUsage::Index {
+        triangle_list: mesh.mode == MeshPrimitiveTopology::TriangleList,
+      };
       let primitive = gltf_json::mesh::Primitive {
         attributes,
         indices: match &mesh.indices {
@@ -439,6 +538,7 @@ fn build_model(
               gltf_json::accessor::ComponentType::U16,
               gltf_json::accessor::Type::Scalar,
               false,
+              index_usage(),
             ),
             AttributeIndexFormat::Uint32 => build_inline_accessor(
               buffer_inliner,
@@ -448,6 +548,7 @@ fn build_model(
               gltf_json::accessor::ComponentType::U32,
               gltf_json::accessor::Type::Scalar,
               false,
+              index_usage(),
             ),
           }
           .into(),
@@ -470,3 +571,296 @@ fn build_model(
     })
     .into()
 }
+
+/// the tests replace the global database, so they should not run in parallel
+#[cfg(test)]
+static TEST_DATABASE_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+#[cfg(test)]
+fn setup_test_database() -> parking_lot::MutexGuard<'static, ()> {
+  let guard = TEST_DATABASE_LOCK.lock();
+  setup_global_database(Default::default());
+  register_scene_core_data_model();
+  guard
+}
+
+/// each test run writes into its own folder, so the concurrent runs do not overwrite each other
+#[cfg(test)]
+fn test_output_folder(name: &str) -> std::path::PathBuf {
+  let folder = format!(
+    "rendiation_gltf_exporter_test_{}_{name}",
+    std::process::id()
+  );
+  std::env::temp_dir().join(folder)
+}
+
+#[cfg(test)]
+fn reader() -> SceneReader {
+  fn rev_ref<FK: ForeignKeySemantic>()
+  -> BoxedDynMultiQuery<EntityHandle<FK::ForeignEntity>, EntityHandle<FK::Entity>> {
+    let mut map: FastHashMap<_, FastHashSet<_>> = FastHashMap::default();
+    for (k, v) in get_db_view_typed_foreign::<FK>().iter_key_value() {
+      map.entry(v).or_default().insert(k);
+    }
+    map.into_boxed_multi()
+  }
+
+  SceneReader::new_from_global(
+    rev_ref::<AttributesMeshEntityVertexBufferRelationRefAttributesMeshEntity>(),
+    rev_ref::<SceneNodeParentIdx>(),
+    rev_ref::<SceneModelBelongsToScene>(),
+  )
+}
+
+#[test]
+fn test_pbr_mr_material_extension_round_trip() {
+  use rendiation_algebra::{InnerProductSpace, Vec2, Vec4};
+
+  let _guard = setup_test_database();
+
+  let extension = PhysicalMetallicRoughnessMaterialExtensionDataView {
+    emissive_strength: 5.,
+    ior: 1.33,
+    specular_factor: 0.5,
+    specular_color: Vec3::new(1., 0.5, 0.25),
+    transmission: 0.8,
+    clearcoat: 1.,
+    clearcoat_roughness: 0.1,
+    sheen_color: Vec3::new(0.5, 0.25, 1.),
+    sheen_roughness: 0.3,
+    texture_transforms: PhysicalMetallicRoughnessMaterialTextureTransforms {
+      base_color: TextureTransform {
+        offset: Vec2::new(0.5, 0.25),
+        rotation: 0.5,
+        scale: Vec2::new(2., 3.),
+      },
+      clearcoat: TextureTransform {
+        offset: Vec2::new(0.1, 0.2),
+        rotation: 0.,
+        scale: Vec2::new(4., 4.),
+      },
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+
+  let scene = {
+    let mut writer = SceneWriter::from_global();
+    let scene = writer.scene_writer.new_entity(|w| w);
+
+    let mut tex_writer = TexSamplerWriter {
+      tex_writer: &mut writer.tex_writer,
+      sampler_writer: &mut writer.sampler_writer,
+    };
+    let texture = tex_writer.write_direct_tex_with_default_sampler(GPUBufferImage {
+      data: vec![255; 4],
+      format: rendiation_texture_core::TextureFormat::Rgba8UnormSrgb,
+      size: Size::from_u32_pair_min_one((1, 1)),
+      precomputed_mips: Vec::new(),
+    });
+
+    let material = PhysicalMetallicRoughnessMaterialDataView {
+      base_color_texture: Some(texture),
+      extension: PhysicalMetallicRoughnessMaterialExtensionDataView {
+        clearcoat_texture: Some(texture),
+        ..extension.clone()
+      },
+      ..Default::default()
+    }
+    .write(&mut writer.pbr_mr_mat_writer);
+
+    let positions = vec![
+      Vec3::new(0., 0., 0.),
+      Vec3::new(1., 0., 0.),
+      Vec3::new(0., 1., 0.),
+    ];
+    let uvs = vec![Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(0., 1.)];
+    let mesh = AttributesMesh {
+      attributes: vec![
+        (
+          AttributeSemantic::Positions,
+          AttributeAccessor::create_owned(positions, 12),
+        ),
+        (
+          AttributeSemantic::TexCoords(0),
+          AttributeAccessor::create_owned(uvs, 8),
+        ),
+      ]
+      .into_iter()
+      .collect(),
+      indices: None,
+      mode: MeshPrimitiveTopology::TriangleList,
+    };
+    let mesh = writer.write_attribute_mesh(mesh).mesh;
+    let node = writer.create_root_child();
+    writer.create_scene_model(
+      SceneMaterialDataView::PbrMRMaterial(material),
+      mesh,
+      node,
+      scene,
+    );
+    scene
+  };
+
+  let folder = test_output_folder("pbr_mr_extension");
+  build_scene_to_gltf(&reader(), scene, &folder, "pbr_mr_extension").unwrap();
+
+  let loaded = {
+    let mut writer = SceneWriter::from_global();
+    let scene = writer.scene_writer.new_entity(|w| w);
+    let node = writer.create_root_child();
+    let path = folder.join("pbr_mr_extension.glb");
+    rendiation_scene_gltf_loader::load_gltf(path, node, scene, &mut writer, None).unwrap()
+  };
+  assert!(loaded.used_but_not_supported_extensions.is_empty());
+
+  let material = loaded
+    .materials
+    .iter()
+    .find_map(|(_, m)| match m {
+      SceneMaterialDataView::PbrMRMaterial(m) => Some(*m),
+      _ => None,
+    })
+    .unwrap();
+  let loaded = reader().read_pbr_mr_material(material);
+  assert!(loaded.base_color_texture.is_some());
+
+  let loaded = loaded.extension;
+  assert_eq!(loaded.emissive_strength, extension.emissive_strength);
+  assert_eq!(loaded.ior, extension.ior);
+  assert_eq!(loaded.specular_factor, extension.specular_factor);
+  assert_eq!(loaded.specular_color, extension.specular_color);
+  assert_eq!(loaded.transmission, extension.transmission);
+  assert_eq!(loaded.clearcoat, extension.clearcoat);
+  assert_eq!(loaded.clearcoat_roughness, extension.clearcoat_roughness);
+  assert_eq!(loaded.sheen_color, extension.sheen_color);
+  assert_eq!(loaded.sheen_roughness, extension.sheen_roughness);
+  // each texture slot keeps its own transform
+  assert_eq!(loaded.texture_transforms, extension.texture_transforms);
+  assert!(loaded.clearcoat_texture.is_some());
+  assert!(loaded.sheen_color_texture.is_none());
+
+  let rows = loaded.texture_transforms.base_color.to_matrix_rows();
+  let transformed = |uv: Vec2<f32>| {
+    let uv = Vec4::new(uv.x, uv.y, 1., 0.);
+    Vec2::new(rows[0].dot(uv), rows[1].dot(uv))
+  };
+  assert_eq!(
+    transformed(Vec2::new(0., 0.)),
+    extension.texture_transforms.base_color.offset
+  );
+
+  fs::remove_dir_all(&folder).ok();
+}
+
+#[test]
+fn test_meshopt_compression_round_trip() {
+  use rendiation_algebra::Vec2;
+
+  let _guard = setup_test_database();
+
+  let size = 16;
+  let mut positions = Vec::new();
+  let mut uvs = Vec::new();
+  for y in 0..=size {
+    for x in 0..=size {
+      positions.push(Vec3::new(x as f32, y as f32, 0.));
+      uvs.push(Vec2::new(x as f32, y as f32) / size as f32);
+    }
+  }
+  let mut indices = Vec::new();
+  for y in 0..size {
+    for x in 0..size {
+      let a = y * (size + 1) + x;
+      let c = a + size + 1;
+      indices.extend_from_slice(&[a, a + 1, c, a + 1, c + 1, c]);
+    }
+  }
+  let indices: Vec<u16> = indices.into_iter().map(|i| i as u16).collect();
+
+  let scene = {
+    let mut writer = SceneWriter::from_global();
+    let scene = writer.scene_writer.new_entity(|w| w);
+    let material =
+      PhysicalMetallicRoughnessMaterialDataView::default().write(&mut writer.pbr_mr_mat_writer);
+
+    let mesh = AttributesMesh {
+      attributes: vec![
+        (
+          AttributeSemantic::Positions,
+          AttributeAccessor::create_owned(positions.clone(), 12),
+        ),
+        (
+          AttributeSemantic::TexCoords(0),
+          AttributeAccessor::create_owned(uvs.clone(), 8),
+        ),
+      ]
+      .into_iter()
+      .collect(),
+      indices: Some((
+        AttributeIndexFormat::Uint16,
+        AttributeAccessor::create_owned(indices.clone(), 2),
+      )),
+      mode: MeshPrimitiveTopology::TriangleList,
+    };
+    let mesh = writer.write_attribute_mesh(mesh).mesh;
+    let node = writer.create_root_child();
+    writer.create_scene_model(
+      SceneMaterialDataView::PbrMRMaterial(material),
+      mesh,
+      node,
+      scene,
+    );
+    scene
+  };
+
+  let folder = test_output_folder("meshopt");
+  let config = GltfExportConfig {
+    meshopt_compression: true,
+  };
+  build_scene_to_gltf_with_config(&reader(), scene, &folder, "meshopt", &config).unwrap();
+  let path = folder.join("meshopt.glb");
+
+  // the gltf crate treat the unknown required extension as invalid
+  let gltf = gltf::Gltf::from_slice_without_validation(&fs::read(&path).unwrap()).unwrap();
+  assert!(
+    gltf
+      .extensions_required()
+      .any(|e| e == MESHOPT_COMPRESSION_EXTENSION)
+  );
+  // the encoded size is smaller than the raw data
+  let raw_size = positions.len() * 20 + indices.len() * 2;
+  assert!(gltf.blob.as_ref().unwrap().len() < raw_size);
+
+  let loaded = {
+    let mut writer = SceneWriter::from_global();
+    let scene = writer.scene_writer.new_entity(|w| w);
+    let node = writer.create_root_child();
+    rendiation_scene_gltf_loader::load_gltf(path, node, scene, &mut writer, None).unwrap()
+  };
+  assert!(loaded.used_but_not_supported_extensions.is_empty());
+
+  let mesh = reader()
+    .read_attribute_mesh(loaded.meshes[0].mesh)
+    .into_living()
+    .unwrap()
+    .into_attributes_mesh();
+  let (_, loaded_indices) = mesh.indices.as_ref().unwrap();
+  let loaded_indices = loaded_indices.visit_slice::<u16>().unwrap();
+  let loaded_positions = mesh.get_position_slice();
+  let loaded_uvs = mesh
+    .get_attribute(&AttributeSemantic::TexCoords(0))
+    .unwrap()
+    .visit_slice::<Vec2<f32>>()
+    .unwrap();
+
+  assert_eq!(loaded_positions, positions.as_slice());
+  assert_eq!(loaded_uvs, uvs.as_slice());
+  // the vertex order of each triangle may be rotated, but the winding is kept
+  for (a, b) in indices.chunks_exact(3).zip(loaded_indices.chunks_exact(3)) {
+    let rotated = (0..3).any(|r| (0..3).all(|i| a[i] == b[(i + r) % 3]));
+    assert!(rotated, "{a:?} {b:?}");
+  }
+
+  fs::remove_dir_all(&folder).ok();
+}
@@ -2,12 +2,14 @@ use crate::*;
 
 pub fn build_material(
   materials: &mut Resource<SceneMaterialDataView, gltf_json::Material>,
+  extensions_used: &mut FastHashSet<&'static str>,
   reader: &SceneReader,
   m: &SceneMaterialDataView,
   textures: &Resource<(EntityHandle<SceneTexture2dEntity>, TextureSampler), gltf_json::Texture>,
 ) -> Option<gltf_json::Index<gltf_json::Material>> {
   match m {
     SceneMaterialDataView::UnlitMaterial(material) => materials.append(*m, {
+      extensions_used.insert("KHR_materials_unlit");
       let material = reader.read_unlit_material(*material);
       gltf_json::Material {
         alpha_cutoff: gltf_json::material::AlphaCutoff(material.alpha.alpha_cutoff).into(),
@@ -35,6 +37,7 @@ pub fn build_material(
       }
     }),
     SceneMaterialDataView::PbrSGMaterial(material) => materials.append(*m, {
+      extensions_used.insert("KHR_materials_pbrSpecularGlossiness");
       let material = reader.read_pbr_sg_material(*material);
       gltf_json::Material {
         alpha_cutoff: gltf_json::material::AlphaCutoff(material.alpha.alpha_cutoff).into(),
@@ -58,7 +61,7 @@ pub fn build_material(
               .specular_glossiness_texture
               .as_ref()
               .and_then(|t| get_texture2d_info(t, 0, reader, textures)),
-            extras: Default::default(),
+            ..Default::default()
           }),
           ..Default::default()
         }),
@@ -67,6 +70,42 @@ pub fn build_material(
     }),
     SceneMaterialDataView::PbrMRMaterial(material) => materials.append(*m, {
       let material = reader.read_pbr_mr_material(*material);
+      let ext = &material.extension;
+      let transforms = &ext.texture_transforms;
+      let get_texture2d_info = |t: &Texture2DWithSamplingDataView, transform: &TextureTransform| {
+        get_texture2d_info(t, 0, reader, textures)
+          .map(|info| with_texture_transform(info, transform))
+      };
+      let transform_used = [
+        (material.base_color_texture.is_some(), transforms.base_color),
+        (
+          material.metallic_roughness_texture.is_some(),
+          transforms.metallic_roughness,
+        ),
+        (material.emissive_texture.is_some(), transforms.emissive),
+        (material.normal_texture.is_some(), transforms.normal),
+        (ext.specular_texture.is_some(), transforms.specular),
+        (
+          ext.specular_color_texture.is_some(),
+          transforms.specular_color,
+        ),
+        (ext.transmission_texture.is_some(), transforms.transmission),
+        (ext.clearcoat_texture.is_some(), transforms.clearcoat),
+        (
+          ext.clearcoat_roughness_texture.is_some(),
+          transforms.clearcoat_roughness,
+        ),
+        (ext.sheen_color_texture.is_some(), transforms.sheen_color),
+        (
+          ext.sheen_roughness_texture.is_some(),
+          transforms.sheen_roughness,
+        ),
+      ]
+      .iter()
+      .any(|(has_texture, transform)| *has_texture && !transform.is_identity());
+      if transform_used {
+        extensions_used.insert("KHR_texture_transform");
+      }
       gltf_json::Material {
         alpha_cutoff: gltf_json::material::AlphaCutoff(material.alpha.alpha_cutoff).into(),
         alpha_mode: gltf_json::validation::Checked::Valid(map_alpha_mode(
@@ -79,13 +118,13 @@ pub fn build_material(
           base_color_texture: material
             .base_color_texture
             .as_ref()
-            .and_then(|t| get_texture2d_info(t, 0, reader, textures)),
+            .and_then(|t| get_texture2d_info(t, &transforms.base_color)),
           metallic_factor: gltf_json::material::StrengthFactor(material.metallic),
           roughness_factor: gltf_json::material::StrengthFactor(material.roughness),
           metallic_roughness_texture: material
             .metallic_roughness_texture
             .as_ref()
-            .and_then(|t| get_texture2d_info(t, 0, reader, textures)),
+            .and_then(|t| get_texture2d_info(t, &transforms.metallic_roughness)),
           ..Default::default()
         },
         normal_texture: material.normal_texture.as_ref().and_then(|t| {
@@ -96,7 +135,15 @@ pub fn build_material(
             },
             scale: t.scale,
             tex_coord: 0,
-            extensions: Default::default(),
+            extensions: (!transforms.normal.is_identity()).then(|| {
+              let transform = texture_transform_json(&transforms.normal);
+              let transform = serde_json::to_value(transform).unwrap();
+              gltf_json::extensions::material::NormalTexture {
+                others: [("KHR_texture_transform".to_string(), transform)]
+                  .into_iter()
+                  .collect(),
+              }
+            }),
             extras: Default::default(),
           }
           .into()
@@ -104,8 +151,9 @@ pub fn build_material(
         emissive_texture: material
           .emissive_texture
           .as_ref()
-          .and_then(|t| get_texture2d_info(t, 0, reader, textures)),
+          .and_then(|t| get_texture2d_info(t, &transforms.emissive)),
         emissive_factor: gltf_json::material::EmissiveFactor(material.emissive.into()),
+        extensions: build_pbr_mr_extension(ext, extensions_used, get_texture2d_info),
         ..Default::default()
       }
     }),
@@ -114,6 +162,156 @@ pub fn build_material(
   .into()
 }
 
+fn with_texture_transform(
+  mut info: gltf_json::texture::Info,
+  transform: &TextureTransform,
+) -> gltf_json::texture::Info {
+  if !transform.is_identity() {
+    info.extensions = Some(gltf_json::extensions::texture::Info {
+      texture_transform: Some(texture_transform_json(transform)),
+      ..Default::default()
+    });
+  }
+  info
+}
+
+fn texture_transform_json(
+  transform: &TextureTransform,
+) -> gltf_json::extensions::texture::TextureTransform {
+  gltf_json::extensions::texture::TextureTransform {
+    offset: gltf_json::extensions::texture::TextureTransformOffset(transform.offset.into()),
+    rotation: gltf_json::extensions::texture::TextureTransformRotation(transform.rotation),
+    scale: gltf_json::extensions::texture::TextureTransformScale(transform.scale.into()),
+    ..Default::default()
+  }
+}
+
+/// only the extension that is not in default state is written. the gltf-json crate has no
+/// typed definition for clearcoat and sheen, so they are written as raw json.
+fn build_pbr_mr_extension(
+  ext: &PhysicalMetallicRoughnessMaterialExtensionDataView,
+  extensions_used: &mut FastHashSet<&'static str>,
+  get_texture2d_info: impl Fn(
+    &Texture2DWithSamplingDataView,
+    &TextureTransform,
+  ) -> Option<gltf_json::texture::Info>,
+) -> Option<gltf_json::extensions::material::Material> {
+  use gltf_json::extensions::material::*;
+  let default = PhysicalMetallicRoughnessMaterialExtensionDataView::default();
+  let mut r = Material::default();
+  let mut used = |name: &'static str, enabled: bool| {
+    if enabled {
+      extensions_used.insert(name);
+    }
+    enabled
+  };
+
+  let transforms = &ext.texture_transforms;
+  let texture_json = |t: &Option<Texture2DWithSamplingDataView>, transform: &TextureTransform| {
+    t.as_ref()
+      .and_then(|t| get_texture2d_info(t, transform))
+      .map(|info| serde_json::to_value(info).unwrap())
+  };
+
+  if used(
+    "KHR_materials_emissive_strength",
+    ext.emissive_strength != default.emissive_strength,
+  ) {
+    r.emissive_strength = Some(EmissiveStrength {
+      emissive_strength: EmissiveStrengthFactor(ext.emissive_strength),
+    });
+  }
+
+  if used("KHR_materials_ior", ext.ior != default.ior) {
+    r.ior = Some(Ior {
+      ior: IndexOfRefraction(ext.ior),
+      extras: Default::default(),
+    });
+  }
+
+  if used(
+    "KHR_materials_specular",
+    ext.specular_factor != default.specular_factor
+      || ext.specular_color != default.specular_color
+      || ext.specular_texture.is_some()
+      || ext.specular_color_texture.is_some(),
+  ) {
+    r.specular = Some(Specular {
+      specular_factor: SpecularFactor(ext.specular_factor),
+      specular_texture: ext
+        .specular_texture
+        .as_ref()
+        .and_then(|t| get_texture2d_info(t, &transforms.specular)),
+      specular_color_factor: SpecularColorFactor(ext.specular_color.into()),
+      specular_color_texture: ext
+        .specular_color_texture
+        .as_ref()
+        .and_then(|t| get_texture2d_info(t, &transforms.specular_color)),
+      extras: Default::default(),
+    });
+  }
+
+  if used(
+    "KHR_materials_transmission",
+    ext.transmission != default.transmission || ext.transmission_texture.is_some(),
+  ) {
+    r.transmission = Some(Transmission {
+      transmission_factor: TransmissionFactor(ext.transmission),
+      transmission_texture: ext
+        .transmission_texture
+        .as_ref()
+        .and_then(|t| get_texture2d_info(t, &transforms.transmission)),
+      extras: Default::default(),
+    });
+  }
+
+  if used(
+    "KHR_materials_clearcoat",
+    ext.clearcoat != default.clearcoat || ext.clearcoat_texture.is_some(),
+  ) {
+    let mut clearcoat = serde_json::json!({
+      "clearcoatFactor": ext.clearcoat,
+      "clearcoatRoughnessFactor": ext.clearcoat_roughness,
+    });
+    if let Some(t) = texture_json(&ext.clearcoat_texture, &transforms.clearcoat) {
+      clearcoat["clearcoatTexture"] = t;
+    }
+    if let Some(t) = texture_json(
+      &ext.clearcoat_roughness_texture,
+      &transforms.clearcoat_roughness,
+    ) {
+      clearcoat["clearcoatRoughnessTexture"] = t;
+    }
+    r.others
+      .insert("KHR_materials_clearcoat".to_string(), clearcoat);
+  }
+
+  if used(
+    "KHR_materials_sheen",
+    ext.sheen_color != default.sheen_color || ext.sheen_color_texture.is_some(),
+  ) {
+    let sheen_color: [f32; 3] = ext.sheen_color.into();
+    let mut sheen = serde_json::json!({
+      "sheenColorFactor": sheen_color,
+      "sheenRoughnessFactor": ext.sheen_roughness,
+    });
+    if let Some(t) = texture_json(&ext.sheen_color_texture, &transforms.sheen_color) {
+      sheen["sheenColorTexture"] = t;
+    }
+    if let Some(t) = texture_json(&ext.sheen_roughness_texture, &transforms.sheen_roughness) {
+      sheen["sheenRoughnessTexture"] = t;
+    }
+    r.others.insert("KHR_materials_sheen".to_string(), sheen);
+  }
+
+  let is_empty = r.emissive_strength.is_none()
+    && r.ior.is_none()
+    && r.specular.is_none()
+    && r.transmission.is_none()
+    && r.others.is_empty();
+  (!is_empty).then_some(r)
+}
+
 fn get_texture2d_info(
   ts: &Texture2DWithSamplingDataView,
   tex_coord: usize,
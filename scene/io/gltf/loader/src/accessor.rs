@@ -0,0 +1,367 @@
+use gltf::accessor::DataType;
+
+use crate::*;
+
+pub fn component_byte_size(ty: DataType) -> usize {
+  match ty {
+    DataType::I8 => 1,
+    DataType::U8 => 1,
+    DataType::I16 => 2,
+    DataType::U16 => 2,
+    DataType::U32 => 4,
+    DataType::F32 => 4,
+  }
+}
+
+pub fn item_byte_size(accessor: &gltf::Accessor) -> usize {
+  component_byte_size(accessor.data_type()) * accessor.dimensions().multiplicity()
+}
+
+/// the attribute data is not directly referenced by the accessor, and must be decoded
+/// into the packed new buffer:
+/// - the sparse accessor, or the accessor without view(all zero)
+/// - the interleaved layout
+pub fn accessor_require_packing(accessor: &gltf::Accessor) -> bool {
+  let Some(view) = accessor.view() else {
+    return true;
+  };
+  if accessor.sparse().is_some() {
+    return true;
+  }
+  view
+    .stride()
+    .is_some_and(|stride| stride != item_byte_size(accessor))
+}
+
+fn read_index(bytes: &[u8], ty: &gltf::accessor::sparse::IndexType) -> usize {
+  use gltf::accessor::sparse::IndexType;
+  match ty {
+    IndexType::U8 => bytes[0] as usize,
+    IndexType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+    IndexType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+  }
+}
+
+/// the bytes covered by the view, empty if the view exceeds the buffer
+fn view_bytes<'a>(view: &gltf::buffer::View, buffers: &'a [ExternalRefPtr<Vec<u8>>]) -> &'a [u8] {
+  buffers
+    .get(view.buffer().index())
+    .and_then(|b| {
+      b.ptr
+        .get(view.offset()..view.offset().checked_add(view.length())?)
+    })
+    .unwrap_or_default()
+}
+
+/// the byte range of the i-th item, none if the range overflows or exceeds the buffer
+fn item_range(buffer: &[u8], start: usize, i: usize, stride: usize, size: usize) -> Option<&[u8]> {
+  let offset = i.checked_mul(stride)?.checked_add(start)?;
+  buffer.get(offset..offset.checked_add(size)?)
+}
+
+/// read the accessor data into the tightly packed buffer, the byte stride and the sparse
+/// substitution is resolved.
+///
+/// the malformed content(the view shorter than the accessor, or the sparse index out of the
+/// accessor count) is skipped, the skipped items keep zero and a warning is reported.
+pub fn read_accessor_packed(
+  accessor: &gltf::Accessor,
+  buffers: &[ExternalRefPtr<Vec<u8>>],
+  warnings: &mut Vec<String>,
+) -> Vec<u8> {
+  let item_size = item_byte_size(accessor);
+  let count = accessor.count();
+  let mut data = vec![0; item_size * count];
+
+  // https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#sparse-accessors
+  // when the view is not defined, the accessor must be initialized with zeros
+  if let Some(view) = accessor.view() {
+    let buffer = view_bytes(&view, buffers);
+    let stride = view.stride().unwrap_or(item_size);
+    let start = accessor.offset();
+    let mut skipped = 0;
+    for (i, item) in data.chunks_exact_mut(item_size).enumerate() {
+      match item_range(buffer, start, i, stride, item_size) {
+        Some(source) => item.copy_from_slice(source),
+        None => skipped += 1,
+      }
+    }
+    if skipped > 0 {
+      warnings.push(format!(
+        "accessor {} reads {skipped} items out of the buffer view, they are filled with zeros",
+        accessor.index()
+      ));
+    }
+  }
+
+  if let Some(sparse) = accessor.sparse() {
+    let indices = sparse.indices();
+    let index_view = indices.view();
+    let index_type = indices.index_type();
+    let index_size = match index_type {
+      gltf::accessor::sparse::IndexType::U8 => 1,
+      gltf::accessor::sparse::IndexType::U16 => 2,
+      gltf::accessor::sparse::IndexType::U32 => 4,
+    };
+    let index_buffer = view_bytes(&index_view, buffers);
+    let index_start = indices.offset();
+
+    let values = sparse.values();
+    let value_view = values.view();
+    let value_buffer = view_bytes(&value_view, buffers);
+    let value_start = values.offset();
+
+    let mut skipped = 0;
+    for i in 0..sparse.count() {
+      let target = item_range(index_buffer, index_start, i, index_size, index_size)
+        .map(|bytes| read_index(bytes, &index_type))
+        .filter(|target| *target < count);
+      let value = item_range(value_buffer, value_start, i, item_size, item_size);
+      match (target, value) {
+        (Some(target), Some(value)) => {
+          data[target * item_size..(target + 1) * item_size].copy_from_slice(value)
+        }
+        _ => skipped += 1,
+      }
+    }
+    if skipped > 0 {
+      warnings.push(format!(
+        "accessor {} has {skipped} malformed sparse substitutions, they are skipped",
+        accessor.index()
+      ));
+    }
+  }
+
+  data
+}
+
+/// convert the integer component to f32, the normalized integer is mapped to [0, 1] or [-1, 1].
+///
+/// https://github.com/KhronosGroup/glTF/tree/main/extensions/2.0/Khronos/KHR_mesh_quantization
+pub fn dequantize_components(ty: DataType, normalized: bool, packed: &[u8]) -> Vec<f32> {
+  fn map<const N: usize>(packed: &[u8], f: impl Fn([u8; N]) -> f32) -> Vec<f32> {
+    packed
+      .chunks_exact(N)
+      .map(|v| f(v.try_into().unwrap()))
+      .collect()
+  }
+  match (ty, normalized) {
+    (DataType::F32, _) => map(packed, f32::from_le_bytes),
+    (DataType::I8, true) => map(packed, |v| (i8::from_le_bytes(v) as f32 / 127.).max(-1.)),
+    (DataType::I8, false) => map(packed, |v| i8::from_le_bytes(v) as f32),
+    (DataType::U8, true) => map(packed, |v| u8::from_le_bytes(v) as f32 / 255.),
+    (DataType::U8, false) => map(packed, |v| u8::from_le_bytes(v) as f32),
+    (DataType::I16, true) => map(packed, |v| (i16::from_le_bytes(v) as f32 / 32767.).max(-1.)),
+    (DataType::I16, false) => map(packed, |v| i16::from_le_bytes(v) as f32),
+    (DataType::U16, true) => map(packed, |v| u16::from_le_bytes(v) as f32 / 65535.),
+    (DataType::U16, false) => map(packed, |v| u16::from_le_bytes(v) as f32),
+    (DataType::U32, _) => map(packed, |v| u32::from_le_bytes(v) as f32),
+  }
+}
+
+/// build the accessor as float data, the quantized data is dequantized.
+pub fn build_float_accessor(accessor: gltf::Accessor, ctx: &mut Context) -> AttributeAccessor {
+  if accessor.data_type() == DataType::F32 {
+    return build_accessor(accessor, ctx);
+  }
+  let packed = read_accessor_packed(&accessor, &ctx.attributes, &mut ctx.result.warnings);
+  let data = dequantize_components(accessor.data_type(), accessor.normalized(), &packed);
+  let item_byte_size = accessor.dimensions().multiplicity() * 4;
+  AttributeAccessor::create_owned(data, item_byte_size)
+}
+
+pub fn build_accessor(accessor: gltf::Accessor, ctx: &mut Context) -> AttributeAccessor {
+  let item_byte_size = item_byte_size(&accessor);
+
+  if accessor_require_packing(&accessor) {
+    let data = read_accessor_packed(&accessor, &ctx.attributes, &mut ctx.result.warnings);
+    return AttributeAccessor::create_owned(data, item_byte_size);
+  }
+
+  let view = accessor.view().unwrap();
+  let view = build_data_view(view, ctx);
+
+  AttributeAccessor {
+    view,
+    count: accessor.count(),
+    byte_offset: accessor.offset(),
+    item_byte_size,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn accessors(json: &str) -> gltf::Document {
+    gltf::Gltf::from_slice(json.as_bytes()).unwrap().document
+  }
+
+  fn u16_bytes(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+  }
+
+  #[test]
+  fn read_interleaved_and_sparse_accessor() {
+    // two interleaved u16 vec2 attributes, the stride is 8 bytes
+    let interleaved = u16_bytes(&[1, 2, 100, 100, 3, 4, 100, 100, 5, 6, 100, 100]);
+    // sparse indices(u8, padded), then the substituted values
+    let mut sparse = vec![2, 0, 0, 0];
+    sparse.extend(u16_bytes(&[7, 8]));
+
+    let document = accessors(
+      r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "byteLength": 24 }, { "byteLength": 8 }],
+        "bufferViews": [
+          { "buffer": 0, "byteOffset": 0, "byteLength": 24, "byteStride": 8 },
+          { "buffer": 1, "byteOffset": 0, "byteLength": 1 },
+          { "buffer": 1, "byteOffset": 4, "byteLength": 4 }
+        ],
+        "accessors": [
+          { "bufferView": 0, "componentType": 5123, "count": 3, "type": "VEC2" },
+          {
+            "bufferView": 0, "componentType": 5123, "count": 3, "type": "VEC2",
+            "sparse": {
+              "count": 1,
+              "indices": { "bufferView": 1, "componentType": 5121 },
+              "values": { "bufferView": 2 }
+            }
+          },
+          {
+            "componentType": 5123, "count": 3, "type": "VEC2",
+            "sparse": {
+              "count": 1,
+              "indices": { "bufferView": 1, "componentType": 5121 },
+              "values": { "bufferView": 2 }
+            }
+          }
+        ]
+      }"#,
+    );
+    let buffers = [
+      ExternalRefPtr::new(interleaved),
+      ExternalRefPtr::new(sparse),
+    ];
+    let accessors: Vec<_> = document.accessors().collect();
+    let mut warnings = Vec::new();
+
+    assert!(accessor_require_packing(&accessors[0]));
+    assert_eq!(
+      read_accessor_packed(&accessors[0], &buffers, &mut warnings),
+      u16_bytes(&[1, 2, 3, 4, 5, 6])
+    );
+    assert_eq!(
+      read_accessor_packed(&accessors[1], &buffers, &mut warnings),
+      u16_bytes(&[1, 2, 3, 4, 7, 8])
+    );
+    // the sparse accessor without the view is initialized with zeros
+    assert_eq!(
+      read_accessor_packed(&accessors[2], &buffers, &mut warnings),
+      u16_bytes(&[0, 0, 0, 0, 7, 8])
+    );
+    assert!(warnings.is_empty());
+  }
+
+  #[test]
+  fn read_malformed_sparse_accessor() {
+    let data = u16_bytes(&[1, 2, 3, 4, 5, 6]);
+    // sparse indices(u8, padded): the first is out of the accessor count
+    let mut sparse = vec![9, 1, 0, 0];
+    sparse.extend(u16_bytes(&[7, 8, 9, 10]));
+
+    let document = accessors(
+      r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "byteLength": 12 }, { "byteLength": 12 }],
+        "bufferViews": [
+          { "buffer": 0, "byteOffset": 0, "byteLength": 12 },
+          { "buffer": 0, "byteOffset": 0, "byteLength": 8 },
+          { "buffer": 1, "byteOffset": 0, "byteLength": 2 },
+          { "buffer": 1, "byteOffset": 4, "byteLength": 8 }
+        ],
+        "accessors": [
+          {
+            "bufferView": 0, "componentType": 5123, "count": 3, "type": "VEC2",
+            "sparse": {
+              "count": 2,
+              "indices": { "bufferView": 2, "componentType": 5121 },
+              "values": { "bufferView": 3 }
+            }
+          },
+          {
+            "bufferView": 1, "byteOffset": 4, "componentType": 5123, "count": 2, "type": "VEC2"
+          }
+        ]
+      }"#,
+    );
+    let buffers = [ExternalRefPtr::new(data), ExternalRefPtr::new(sparse)];
+    let accessors: Vec<_> = document.accessors().collect();
+
+    let mut warnings = Vec::new();
+    assert_eq!(
+      read_accessor_packed(&accessors[0], &buffers, &mut warnings),
+      u16_bytes(&[1, 2, 9, 10, 5, 6])
+    );
+    assert_eq!(warnings.len(), 1);
+
+    // the view is shorter than the accessor, the exceeded item keeps zero
+    let mut warnings = Vec::new();
+    assert_eq!(
+      read_accessor_packed(&accessors[1], &buffers, &mut warnings),
+      u16_bytes(&[3, 4, 0, 0])
+    );
+    assert_eq!(warnings.len(), 1);
+  }
+
+  #[test]
+  fn dequantize_normalized_and_quantized_components() {
+    let i8_data: Vec<u8> = [-128i8, -127, 0, 127].iter().map(|v| *v as u8).collect();
+    assert_eq!(
+      dequantize_components(DataType::I8, true, &i8_data),
+      vec![-1., -1., 0., 1.]
+    );
+    assert_eq!(
+      dequantize_components(DataType::I8, false, &i8_data),
+      vec![-128., -127., 0., 127.]
+    );
+
+    assert_eq!(
+      dequantize_components(DataType::U8, true, &[0, 51, 255]),
+      vec![0., 0.2, 1.]
+    );
+    assert_eq!(
+      dequantize_components(DataType::U8, false, &[0, 51, 255]),
+      vec![0., 51., 255.]
+    );
+
+    let i16_data: Vec<u8> = [i16::MIN, -32767, 0, 32767]
+      .iter()
+      .flat_map(|v| v.to_le_bytes())
+      .collect();
+    assert_eq!(
+      dequantize_components(DataType::I16, true, &i16_data),
+      vec![-1., -1., 0., 1.]
+    );
+    assert_eq!(
+      dequantize_components(DataType::I16, false, &i16_data),
+      vec![-32768., -32767., 0., 32767.]
+    );
+
+    let u16_data = u16_bytes(&[0, 65535]);
+    assert_eq!(
+      dequantize_components(DataType::U16, true, &u16_data),
+      vec![0., 1.]
+    );
+    assert_eq!(
+      dequantize_components(DataType::U16, false, &u16_data),
+      vec![0., 65535.]
+    );
+
+    let f32_data: Vec<u8> = [1.5f32, -2.].iter().flat_map(|v| v.to_le_bytes()).collect();
+    assert_eq!(
+      dequantize_components(DataType::F32, false, &f32_data),
+      vec![1.5, -2.]
+    );
+  }
+}
@@ -10,22 +10,40 @@ use gltf::Node;
 use rendiation_algebra::*;
 use rendiation_geometry::Box3;
 use rendiation_mesh_core::*;
+use rendiation_mesh_optimization::*;
 use rendiation_scene_core::*;
+mod accessor;
 mod convert_utils;
+mod meshopt;
+use accessor::*;
 use convert_utils::*;
+use meshopt::*;
+use rendiation_mesh_compression::MeshCodecError;
 use rendiation_texture_core::*;
 use storage::IndexKeptVec;
 
-const SUPPORTED_GLTF_EXTENSIONS: [&str; 3] = [
+const SUPPORTED_GLTF_EXTENSIONS: [&str; 12] = [
   "KHR_materials_pbrSpecularGlossiness",
   "KHR_lights_punctual",
   "KHR_materials_unlit",
+  "KHR_mesh_quantization",
+  "KHR_materials_transmission",
+  "KHR_materials_ior",
+  "KHR_materials_specular",
+  "KHR_materials_emissive_strength",
+  "KHR_materials_clearcoat",
+  "KHR_materials_sheen",
+  // the material level texture transform is applied, see [build_pbr_mr_extension]
+  "KHR_texture_transform",
+  // the compressed buffer views are decoded when import, see [decode_meshopt_buffer_views]
+  MESHOPT_COMPRESSION_EXTENSION,
 ];
 
 #[derive(Debug)]
 pub enum GLTFLoaderError {
   GltfFileLoadError(gltf::Error),
   UnsupportedGLTFExtension(String),
+  MeshoptDecodeError(MeshCodecError),
 }
 
 /// the root of the gltf will be loaded under the target node
@@ -50,27 +68,26 @@ pub fn load_gltf(
 /// this call should be spawned to work thread
 pub fn parse_gltf(path: impl AsRef<Path>) -> Result<GltfParseResult, GLTFLoaderError> {
   let path = path.as_ref().to_path_buf();
-  let (document, buffers, images) =
-    gltf::import(&path).map_err(GLTFLoaderError::GltfFileLoadError)?;
-
-  for ext in document.extensions_required() {
-    if !SUPPORTED_GLTF_EXTENSIONS.contains(&ext) {
-      return Err(GLTFLoaderError::UnsupportedGLTFExtension(ext.to_string()));
-    }
-  }
-
-  Ok(GltfParseResult {
-    path: Some(path),
-    document,
-    buffers,
-    images,
-  })
+  let file =
+    std::fs::read(&path).map_err(|e| GLTFLoaderError::GltfFileLoadError(gltf::Error::Io(e)))?;
+  let base = path.parent().unwrap_or_else(|| Path::new("./"));
+  let mut result = import_gltf(&file, Some(base))?;
+  result.path = Some(path);
+  Ok(result)
 }
 
 /// this call should be spawned to work thread
 pub fn parse_gltf_from_buffer(buffer: &[u8]) -> Result<GltfParseResult, GLTFLoaderError> {
-  let (document, buffers, images) =
-    gltf::import_slice(buffer).map_err(GLTFLoaderError::GltfFileLoadError)?;
+  import_gltf(buffer, None)
+}
+
+/// the gltf crate's validation treat the required extension that it not implemented as error,
+/// (for example the KHR_mesh_quantization), so we skip that validation and check the
+/// required extension by ourself.
+fn import_gltf(slice: &[u8], base: Option<&Path>) -> Result<GltfParseResult, GLTFLoaderError> {
+  use gltf::json::validation::{Error, Validate};
+  let gltf::Gltf { document, blob } =
+    gltf::Gltf::from_slice_without_validation(slice).map_err(GLTFLoaderError::GltfFileLoadError)?;
 
   for ext in document.extensions_required() {
     if !SUPPORTED_GLTF_EXTENSIONS.contains(&ext) {
@@ -78,11 +95,32 @@ pub fn parse_gltf_from_buffer(buffer: &[u8]) -> Result<GltfParseResult, GLTFLoad
     }
   }
 
+  let root = document.as_json();
+  let mut errors = Vec::new();
+  root.validate(root, gltf::json::Path::new, &mut |path, error| {
+    let path = path();
+    let is_required_extension = path.as_str().starts_with("extensionsRequired");
+    if !(is_required_extension && matches!(error, Error::Unsupported)) {
+      errors.push((path, error))
+    }
+  });
+  if !errors.is_empty() {
+    return Err(GLTFLoaderError::GltfFileLoadError(gltf::Error::Validation(
+      errors,
+    )));
+  }
+
+  let mut buffers = import_buffers(&document, base, blob)?;
+  decode_meshopt_buffer_views(&document, &mut buffers)?;
+  let images =
+    gltf::import_images(&document, base, &buffers).map_err(GLTFLoaderError::GltfFileLoadError)?;
+
   Ok(GltfParseResult {
     path: None,
     document,
     buffers,
     images,
+    mesh_optimization: None,
   })
 }
 
@@ -91,6 +129,16 @@ pub struct GltfParseResult {
   document: gltf::Document,
   buffers: Vec<gltf::buffer::Data>,
   images: Vec<gltf::image::Data>,
+  mesh_optimization: Option<MeshOptimizationConfig>,
+}
+
+impl GltfParseResult {
+  /// reorder the indexed triangle list meshes for the gpu efficiency when write into scene, the
+  /// vertices of the mesh that has morph targets are not reordered.
+  pub fn with_mesh_optimization(mut self, config: MeshOptimizationConfig) -> Self {
+    self.mesh_optimization = Some(config);
+    self
+  }
 }
 
 pub fn write_gltf_at_node(
@@ -105,10 +153,13 @@ pub fn write_gltf_at_node(
     mut buffers,
     images,
     path,
+    mesh_optimization,
   } = gltf;
 
   let mut ctx = Context {
+    document: &document,
     images,
+    mesh_optimization,
     mesh_buffer_uri_backend,
     attributes: buffers
       .drain(..)
@@ -176,11 +227,13 @@ pub fn write_gltf_at_node(
   ctx.result
 }
 
-struct Context<'a, 'b> {
+struct Context<'a, 'b, 'c> {
+  document: &'c gltf::Document,
   io: &'a mut SceneWriter,
   target_scene: EntityHandle<SceneEntity>,
   mesh_buffer_uri_backend: Option<&'b mut dyn UriDataSourceDyn<Arc<Vec<u8>>>>,
   images: Vec<gltf::image::Data>,
+  mesh_optimization: Option<MeshOptimizationConfig>,
   attributes: Vec<ExternalRefPtr<Vec<u8>>>,
   result: GltfLoadResult,
 }
@@ -198,11 +251,14 @@ pub struct GltfLoadResult {
   pub point_light_map: IndexKeptVec<EntityHandle<PointLightEntity>>,
   pub spot_light_map: IndexKeptVec<EntityHandle<SpotLightEntity>>,
   pub used_but_not_supported_extensions: Vec<String>,
+  /// the content that is loaded with data loss, for example the exceeded morph targets
+  pub warnings: Vec<String>,
   pub scene_models: Vec<EntityHandle<SceneModelEntity>>,
   pub standard_models: Vec<EntityHandle<StandardModelEntity>>,
   pub materials: IndexKeptVec<SceneMaterialDataView>,
   // key: (index of mesh in gltf doc, index of primitive in gltf mesh)
   pub meshes: Vec<AttributesMeshEntities>,
+  pub morph_targets: Vec<AttributesMeshMorphTargetEntities>,
   /// map (image id, srgbness) => created texture
   pub images: FastHashMap<(usize, bool), EntityHandle<SceneTexture2dEntity>>,
   pub samplers: IndexKeptVec<EntityHandle<SceneSamplerEntity>>,
@@ -322,9 +378,9 @@ fn build_model(
     .attributes()
     .map(|(semantic, accessor)| {
       let semantic = map_attribute_semantic(semantic);
-      let mut att = build_accessor(accessor, ctx);
       // expand joint indices from u8/u16 to u32
       if let AttributeSemantic::Joints(_) = &semantic {
+        let mut att = build_accessor(accessor, ctx);
         if att.item_byte_size == 4 {
           let indices = att.visit_slice::<Vec4<u8>>().unwrap();
           let new_indices = indices
@@ -345,20 +401,15 @@ fn build_model(
             att.item_byte_size
           )
         }
+        return (semantic, att);
       }
 
-      if let AttributeSemantic::Weights(_) = &semantic
-        && att.item_byte_size != 16
-      {
-        panic!(
-          "current implementation only supports vec4<f32>, item_byte_size: {}",
-          att.item_byte_size
-        )
-      }
-
+      // the quantized(KHR_mesh_quantization) or normalized integer attribute is
+      // dequantized into float, the weights is also expanded into vec4<f32> here.
+      let att = build_float_accessor(accessor, ctx);
       (semantic, att)
     })
-    .collect();
+    .collect::<Vec<_>>();
 
   let indices = primitive.indices().map(|indices| {
     let format = match indices.data_type() {
@@ -371,12 +422,28 @@ fn build_model(
 
   let mode = map_draw_mode(primitive.mode()).unwrap();
 
-  let mesh = AttributesMesh {
-    attributes,
+  let morph_targets = build_morph_targets(&primitive, ctx);
+
+  let bounding = compute_bounding(&primitive, &attributes, &morph_targets);
+
+  let mut mesh = AttributesMesh {
+    attributes: attributes.into_iter().collect(),
     indices,
     mode,
   };
 
+  if let Some(config) = &ctx.mesh_optimization {
+    // the morph targets are defined per vertex, so the vertices can not be reordered
+    let config = if morph_targets.is_some() {
+      config.triangle_order_only()
+    } else {
+      *config
+    };
+    if let Some(optimized) = optimize_attributes_mesh(&mesh, &config) {
+      mesh = optimized;
+    }
+  }
+
   let mesh = if let Some(mesh_buffer_backend) = &mut ctx.mesh_buffer_uri_backend {
     ctx
       .io
@@ -384,8 +451,6 @@ fn build_model(
   } else {
     ctx.io.write_attribute_mesh(mesh)
   };
-  let bounding = primitive.bounding_box();
-  let bounding = Box3::new3(bounding.min.into(), bounding.max.into());
   ctx
     .io
     .mesh_writer
@@ -395,6 +460,22 @@ fn build_model(
 
   let mut model = StandardModelDataView::new(material, mesh.mesh);
 
+  if let Some(morph_targets) = morph_targets {
+    // the node's weights override the mesh's default weights
+    let weights = gltf_node
+      .weights()
+      .or_else(|| gltf_node.mesh().and_then(|m| m.weights()))
+      .unwrap_or(&[]);
+    model = model.with_morph_target_weights(MorphTargetWeights::from_slice(weights));
+
+    let targets = morph_targets.write(
+      mesh.mesh,
+      &mut ctx.io.mesh_writer.morph_target,
+      &mut ctx.io.buffer_writer,
+    );
+    ctx.result.morph_targets.push(targets);
+  }
+
   ctx.result.meshes.push(mesh);
 
   if let Some(skin) = gltf_node.skin() {
@@ -420,6 +501,83 @@ fn build_model(
   sm
 }
 
+fn build_morph_targets(
+  primitive: &gltf::Primitive,
+  ctx: &mut Context,
+) -> Option<AttributesMeshMorphTargets> {
+  let target_count = primitive.morph_targets().len();
+  if target_count > MAX_MORPH_TARGET_COUNT {
+    ctx.result.warnings.push(format!(
+      "primitive {} has {target_count} morph targets, only the first {MAX_MORPH_TARGET_COUNT} are loaded",
+      primitive.index()
+    ));
+  }
+
+  let targets: Vec<_> = primitive
+    .morph_targets()
+    .take(MAX_MORPH_TARGET_COUNT)
+    .map(|target| {
+      [
+        (AttributeSemantic::Positions, target.positions()),
+        (AttributeSemantic::Normals, target.normals()),
+        (AttributeSemantic::Tangents, target.tangents()),
+      ]
+      .into_iter()
+      .filter_map(|(semantic, accessor)| Some((semantic, build_float_accessor(accessor?, ctx))))
+      .collect::<Vec<_>>()
+    })
+    .collect();
+
+  (!targets.is_empty()).then_some(AttributesMeshMorphTargets { targets })
+}
+
+/// the bounding in gltf is the quantized value if the position is quantized, and it not
+/// considers the morph target displacement(the accessor min max is defined
+/// but hard to use), so we compute it by ourself in this case.
+fn compute_bounding(
+  primitive: &gltf::Primitive,
+  attributes: &[(AttributeSemantic, AttributeAccessor)],
+  morph_targets: &Option<AttributesMeshMorphTargets>,
+) -> Box3 {
+  let position_quantized = primitive
+    .get(&gltf::Semantic::Positions)
+    .is_some_and(|p| p.data_type() != gltf::accessor::DataType::F32);
+
+  if !position_quantized && morph_targets.is_none() {
+    let bounding = primitive.bounding_box();
+    return Box3::new3(bounding.min.into(), bounding.max.into());
+  }
+
+  fn compute(data: &AttributeAccessor) -> Box3 {
+    let mut bounding = Box3::empty();
+    for p in data.visit_slice::<Vec3<f32>>().unwrap_or_default() {
+      bounding.expand_by_point(*p);
+    }
+    bounding
+  }
+
+  let position = attributes
+    .iter()
+    .find(|(s, _)| *s == AttributeSemantic::Positions)
+    .map(|(_, data)| compute(data))
+    .unwrap_or(Box3::empty());
+
+  // assume the weight is in [0, 1], each target expands the bounding by its displacement range
+  let mut min = position.min;
+  let mut max = position.max;
+  for target in morph_targets.iter().flat_map(|t| t.targets.iter()) {
+    for (semantic, data) in target {
+      if *semantic == AttributeSemantic::Positions {
+        let displacement = compute(data);
+        min += displacement.min.min(Vec3::zero());
+        max += displacement.max.max(Vec3::zero());
+      }
+    }
+  }
+
+  Box3::new(min, max)
+}
+
 fn build_animation(animation: gltf::Animation, ctx: &mut Context) {
   let scene = ctx.target_scene;
   let animation_handle = ctx
@@ -438,8 +596,9 @@ fn build_animation(animation: gltf::Animation, ctx: &mut Context) {
     let sampler = AnimationSampler {
       interpolation: map_animation_interpolation(gltf_sampler.interpolation()),
       field,
-      input: build_accessor(gltf_sampler.input(), ctx),
-      output: build_accessor(gltf_sampler.output(), ctx),
+      input: build_float_accessor(gltf_sampler.input(), ctx),
+      // the rotation and weights may be quantized
+      output: build_float_accessor(gltf_sampler.output(), ctx),
     };
 
     let channel = AnimationChannelDataView {
@@ -526,41 +685,6 @@ fn build_data_view(view: gltf::buffer::View, ctx: &mut Context) -> UnTypedBuffer
     .clone()
 }
 
-fn build_accessor(accessor: gltf::Accessor, ctx: &mut Context) -> AttributeAccessor {
-  let view = accessor.view().unwrap(); // not support sparse accessor
-  let view = build_data_view(view, ctx);
-
-  let ty = accessor.data_type();
-  let dimension = accessor.dimensions();
-
-  let byte_offset = accessor.offset();
-  let count = accessor.count();
-
-  let item_byte_size = match ty {
-    gltf::accessor::DataType::I8 => 1,
-    gltf::accessor::DataType::U8 => 1,
-    gltf::accessor::DataType::I16 => 2,
-    gltf::accessor::DataType::U16 => 2,
-    gltf::accessor::DataType::U32 => 4,
-    gltf::accessor::DataType::F32 => 4,
-  } * match dimension {
-    gltf::accessor::Dimensions::Scalar => 1,
-    gltf::accessor::Dimensions::Vec2 => 2,
-    gltf::accessor::Dimensions::Vec3 => 3,
-    gltf::accessor::Dimensions::Vec4 => 4,
-    gltf::accessor::Dimensions::Mat2 => 4,
-    gltf::accessor::Dimensions::Mat3 => 9,
-    gltf::accessor::Dimensions::Mat4 => 16,
-  };
-
-  AttributeAccessor {
-    view,
-    count,
-    byte_offset,
-    item_byte_size,
-  }
-}
-
 fn build_material(material: gltf::Material, ctx: &mut Context) -> SceneMaterialDataView {
   let idx = material.index().unwrap_or(0) + 1; // keep 0 for default material;
   if let Some(re) = ctx.result.materials.try_get(idx).copied() {
@@ -666,7 +790,7 @@ fn build_material_internal(material: gltf::Material, ctx: &mut Context) -> Scene
       metallic_roughness_texture,
       emissive_texture,
       normal_texture,
-      // reflectance: 0.5, // todo from gltf ior extension
+      extension: build_pbr_mr_extension(&material, ctx),
     };
 
     if material.double_sided() {
@@ -678,6 +802,141 @@ fn build_material_internal(material: gltf::Material, ctx: &mut Context) -> Scene
   }
 }
 
+/// The gltf crate has no typed api for clearcoat and sheen, they are parsed from the raw json.
+fn build_pbr_mr_extension(
+  material: &gltf::Material,
+  ctx: &mut Context,
+) -> PhysicalMetallicRoughnessMaterialExtensionDataView {
+  let mut ext = PhysicalMetallicRoughnessMaterialExtensionDataView::default();
+  let transforms = &mut ext.texture_transforms;
+
+  let pbr = material.pbr_metallic_roughness();
+  transforms.base_color = info_texture_transform(pbr.base_color_texture());
+  transforms.metallic_roughness = info_texture_transform(pbr.metallic_roughness_texture());
+  transforms.emissive = info_texture_transform(material.emissive_texture());
+  transforms.normal = material
+    .normal_texture()
+    .and_then(|tex| {
+      tex
+        .extension_value("KHR_texture_transform")
+        .map(json_texture_transform)
+    })
+    .unwrap_or_default();
+
+  if let Some(strength) = material.emissive_strength() {
+    ext.emissive_strength = strength;
+  }
+  if let Some(ior) = material.ior() {
+    ext.ior = ior;
+  }
+  if let Some(specular) = material.specular() {
+    ext.specular_factor = specular.specular_factor();
+    ext.specular_color = Vec3::from(specular.specular_color_factor());
+    ext.specular_texture = specular
+      .specular_texture()
+      .map(|tex| build_texture(tex.texture(), false, ctx));
+    ext.specular_color_texture = specular
+      .specular_color_texture()
+      .map(|tex| build_texture(tex.texture(), true, ctx));
+    ext.texture_transforms.specular = info_texture_transform(specular.specular_texture());
+    ext.texture_transforms.specular_color =
+      info_texture_transform(specular.specular_color_texture());
+  }
+  if let Some(transmission) = material.transmission() {
+    ext.transmission = transmission.transmission_factor();
+    ext.transmission_texture = transmission
+      .transmission_texture()
+      .map(|tex| build_texture(tex.texture(), false, ctx));
+    ext.texture_transforms.transmission =
+      info_texture_transform(transmission.transmission_texture());
+  }
+  if let Some(clearcoat) = material.extension_value("KHR_materials_clearcoat") {
+    ext.clearcoat = json_f32(clearcoat, "clearcoatFactor").unwrap_or(0.);
+    ext.clearcoat_roughness = json_f32(clearcoat, "clearcoatRoughnessFactor").unwrap_or(0.);
+    ext.clearcoat_texture = build_json_texture(clearcoat, "clearcoatTexture", false, ctx);
+    ext.clearcoat_roughness_texture =
+      build_json_texture(clearcoat, "clearcoatRoughnessTexture", false, ctx);
+    ext.texture_transforms.clearcoat = json_info_texture_transform(clearcoat, "clearcoatTexture");
+    ext.texture_transforms.clearcoat_roughness =
+      json_info_texture_transform(clearcoat, "clearcoatRoughnessTexture");
+  }
+  if let Some(sheen) = material.extension_value("KHR_materials_sheen") {
+    if let Some(color) = json_vec3(sheen, "sheenColorFactor") {
+      ext.sheen_color = color;
+    }
+    ext.sheen_roughness = json_f32(sheen, "sheenRoughnessFactor").unwrap_or(0.);
+    ext.sheen_color_texture = build_json_texture(sheen, "sheenColorTexture", true, ctx);
+    ext.sheen_roughness_texture = build_json_texture(sheen, "sheenRoughnessTexture", false, ctx);
+    ext.texture_transforms.sheen_color = json_info_texture_transform(sheen, "sheenColorTexture");
+    ext.texture_transforms.sheen_roughness =
+      json_info_texture_transform(sheen, "sheenRoughnessTexture");
+  }
+
+  ext
+}
+
+/// the KHR_texture_transform of the texture slot, identity if not defined
+fn info_texture_transform(info: Option<gltf::texture::Info>) -> TextureTransform {
+  info
+    .and_then(|info| info.texture_transform())
+    .map(|transform| TextureTransform {
+      offset: Vec2::from(transform.offset()),
+      rotation: transform.rotation(),
+      scale: Vec2::from(transform.scale()),
+    })
+    .unwrap_or_default()
+}
+
+/// the KHR_texture_transform of the texture info json object
+fn json_info_texture_transform(value: &gltf::json::Value, key: &str) -> TextureTransform {
+  value
+    .get(key)
+    .and_then(|info| info.get("extensions")?.get("KHR_texture_transform"))
+    .map(json_texture_transform)
+    .unwrap_or_default()
+}
+
+fn json_texture_transform(transform: &gltf::json::Value) -> TextureTransform {
+  let vec2 = |key: &str| {
+    let v = transform.get(key)?.as_array()?;
+    match v.as_slice() {
+      [x, y] => Some(Vec2::new(x.as_f64()? as f32, y.as_f64()? as f32)),
+      _ => None,
+    }
+  };
+  TextureTransform {
+    offset: vec2("offset").unwrap_or(Vec2::zero()),
+    rotation: json_f32(transform, "rotation").unwrap_or(0.),
+    scale: vec2("scale").unwrap_or(Vec2::one()),
+  }
+}
+
+fn json_f32(value: &gltf::json::Value, key: &str) -> Option<f32> {
+  value.get(key)?.as_f64().map(|v| v as f32)
+}
+
+fn json_vec3(value: &gltf::json::Value, key: &str) -> Option<Vec3<f32>> {
+  let v = value.get(key)?.as_array()?;
+  let v: Vec<f32> = v
+    .iter()
+    .filter_map(|v| v.as_f64())
+    .map(|v| v as f32)
+    .collect();
+  (v.len() == 3).then(|| Vec3::new(v[0], v[1], v[2]))
+}
+
+/// build the texture from the texture info json object
+fn build_json_texture(
+  value: &gltf::json::Value,
+  key: &str,
+  require_srgb: bool,
+  ctx: &mut Context,
+) -> Option<Texture2DWithSamplingDataView> {
+  let index = value.get(key)?.get("index")?.as_u64()? as usize;
+  let texture = ctx.document.textures().nth(index)?;
+  Some(build_texture(texture, require_srgb, ctx))
+}
+
 // i assume all gpu use little endian?
 const F16_BYTES: [u8; 2] = half::f16::from_f32_const(1.0).to_le_bytes();
 const F32_BYTES: [u8; 4] = 1.0_f32.to_le_bytes();
@@ -718,7 +977,12 @@ fn build_image(
   let size =
     rendiation_texture_core::Size::from_u32_pair_min_one((data_input.width, data_input.height));
 
-  let image = std::sync::Arc::new(GPUBufferImage { data, format, size });
+  let image = std::sync::Arc::new(GPUBufferImage {
+    data,
+    format,
+    size,
+    precomputed_mips: Vec::new(),
+  });
   let image = MaybeUriData::Living(image);
   let image = ExternalRefPtr::new(image);
   io.tex_writer
@@ -785,6 +1049,9 @@ impl GltfLoadResult {
     for node in self.new_created_skeleton_root {
       writer.node_writer.delete_entity(node);
     }
+    for targets in self.morph_targets.iter() {
+      targets.clean_up(&mut writer.buffer_writer);
+    }
     for mesh in self.meshes.iter() {
       mesh.clean_up(&mut writer.mesh_writer, &mut writer.buffer_writer);
     }
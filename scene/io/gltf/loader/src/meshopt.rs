@@ -0,0 +1,229 @@
+use gltf::json::Value;
+use rendiation_mesh_compression::*;
+
+use crate::*;
+
+pub const MESHOPT_COMPRESSION_EXTENSION: &str = "EXT_meshopt_compression";
+
+/// the fallback buffer has no data if the extension is required, the content is filled by the
+/// decoded buffer views.
+fn is_meshopt_fallback_buffer(buffer: &gltf::Buffer) -> bool {
+  buffer
+    .extension_value(MESHOPT_COMPRESSION_EXTENSION)
+    .and_then(|ext| ext.get("fallback"))
+    .and_then(Value::as_bool)
+    .unwrap_or(false)
+}
+
+/// same as the [gltf::import_buffers], but the meshopt fallback buffers are zero filled instead of
+/// loaded
+pub fn import_buffers(
+  document: &gltf::Document,
+  base: Option<&Path>,
+  mut blob: Option<Vec<u8>>,
+) -> Result<Vec<gltf::buffer::Data>, GLTFLoaderError> {
+  document
+    .buffers()
+    .map(|buffer| {
+      if is_meshopt_fallback_buffer(&buffer) && matches!(buffer.source(), gltf::buffer::Source::Bin)
+      {
+        return Ok(gltf::buffer::Data(vec![
+          0;
+          buffer.length().next_multiple_of(4)
+        ]));
+      }
+      let data = gltf::buffer::Data::from_source_and_blob(buffer.source(), base, &mut blob)
+        .map_err(GLTFLoaderError::GltfFileLoadError)?;
+      if data.len() < buffer.length() {
+        return Err(GLTFLoaderError::GltfFileLoadError(
+          gltf::Error::BufferLength {
+            buffer: buffer.index(),
+            expected: buffer.length(),
+            actual: data.len(),
+          },
+        ));
+      }
+      Ok(data)
+    })
+    .collect()
+}
+
+struct MeshoptBufferView {
+  buffer: usize,
+  byte_offset: usize,
+  byte_length: usize,
+  byte_stride: usize,
+  count: usize,
+  mode: String,
+  filter: MeshoptFilter,
+}
+
+fn parse_meshopt_buffer_view(ext: &Value) -> Result<MeshoptBufferView, MeshCodecError> {
+  let get_usize = |key: &str| ext.get(key).and_then(Value::as_u64).map(|v| v as usize);
+  let invalid = MeshCodecError::InvalidParameter("invalid EXT_meshopt_compression buffer view");
+
+  let filter = match ext.get("filter").and_then(Value::as_str) {
+    Some(filter) => MeshoptFilter::from_gltf_name(filter).ok_or(invalid)?,
+    None => MeshoptFilter::None,
+  };
+
+  Ok(MeshoptBufferView {
+    buffer: get_usize("buffer").ok_or(invalid)?,
+    byte_offset: get_usize("byteOffset").unwrap_or(0),
+    byte_length: get_usize("byteLength").ok_or(invalid)?,
+    byte_stride: get_usize("byteStride").ok_or(invalid)?,
+    count: get_usize("count").ok_or(invalid)?,
+    mode: ext
+      .get("mode")
+      .and_then(Value::as_str)
+      .ok_or(invalid)?
+      .to_string(),
+    filter,
+  })
+}
+
+fn decode_meshopt_buffer_view(
+  ext: &MeshoptBufferView,
+  source: &[u8],
+) -> Result<Vec<u8>, MeshCodecError> {
+  let write_indices = |indices: Vec<u32>| match ext.byte_stride {
+    2 => Ok(
+      indices
+        .iter()
+        .flat_map(|i| (*i as u16).to_le_bytes())
+        .collect(),
+    ),
+    4 => Ok(bytemuck::cast_slice(&indices).to_vec()),
+    _ => Err(MeshCodecError::InvalidParameter(
+      "the index byte stride should be 2 or 4",
+    )),
+  };
+
+  match ext.mode.as_str() {
+    "ATTRIBUTES" => {
+      let mut data = decode_vertex_buffer(ext.count, ext.byte_stride, source)?;
+      decode_filter(ext.filter, &mut data, ext.byte_stride)?;
+      Ok(data)
+    }
+    "TRIANGLES" => write_indices(decode_index_buffer(ext.count, source)?),
+    "INDICES" => write_indices(decode_index_sequence(ext.count, source)?),
+    _ => Err(MeshCodecError::InvalidParameter(
+      "unknown EXT_meshopt_compression mode",
+    )),
+  }
+}
+
+/// decode the compressed buffer views and write the result into the buffer view's range, after
+/// that the buffer view can be accessed as usual.
+pub fn decode_meshopt_buffer_views(
+  document: &gltf::Document,
+  buffers: &mut [gltf::buffer::Data],
+) -> Result<(), GLTFLoaderError> {
+  for view in document.views() {
+    let Some(ext) = view.extension_value(MESHOPT_COMPRESSION_EXTENSION) else {
+      continue;
+    };
+    let ext = parse_meshopt_buffer_view(ext).map_err(GLTFLoaderError::MeshoptDecodeError)?;
+    // the count is untrusted, reject the decoded size exceeds the view before decoding
+    if ext
+      .count
+      .checked_mul(ext.byte_stride)
+      .is_none_or(|size| size > view.length())
+    {
+      return Err(GLTFLoaderError::MeshoptDecodeError(
+        MeshCodecError::InvalidParameter("the decoded data exceeds the buffer view"),
+      ));
+    }
+
+    let source_end =
+      ext
+        .byte_offset
+        .checked_add(ext.byte_length)
+        .ok_or(GLTFLoaderError::MeshoptDecodeError(
+          MeshCodecError::InvalidParameter("the compressed buffer view range overflows"),
+        ))?;
+    let source = buffers
+      .get(ext.buffer)
+      .and_then(|buffer| buffer.get(ext.byte_offset..source_end))
+      .ok_or(GLTFLoaderError::MeshoptDecodeError(
+        MeshCodecError::UnexpectedEnd,
+      ))?;
+    let decoded =
+      decode_meshopt_buffer_view(&ext, source).map_err(GLTFLoaderError::MeshoptDecodeError)?;
+
+    let target = view
+      .offset()
+      .checked_add(decoded.len())
+      .and_then(|end| buffers[view.buffer().index()].0.get_mut(view.offset()..end))
+      .ok_or(GLTFLoaderError::MeshoptDecodeError(
+        MeshCodecError::InvalidParameter("the decoded data exceeds the buffer view"),
+      ))?;
+    target.copy_from_slice(&decoded);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reject_overflowed_compressed_range() {
+    let document = gltf::Gltf::from_slice(
+      br#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["EXT_meshopt_compression"],
+        "buffers": [{ "byteLength": 16 }],
+        "bufferViews": [{
+          "buffer": 0, "byteLength": 16, "byteStride": 4,
+          "extensions": {
+            "EXT_meshopt_compression": {
+              "buffer": 0, "byteOffset": 18446744073709551615, "byteLength": 2,
+              "byteStride": 4, "count": 4, "mode": "ATTRIBUTES"
+            }
+          }
+        }]
+      }"#,
+    )
+    .unwrap()
+    .document;
+    let mut buffers = vec![gltf::buffer::Data(vec![0; 16])];
+
+    assert!(matches!(
+      decode_meshopt_buffer_views(&document, &mut buffers),
+      Err(GLTFLoaderError::MeshoptDecodeError(
+        MeshCodecError::InvalidParameter("the compressed buffer view range overflows")
+      ))
+    ));
+  }
+
+  #[test]
+  fn reject_count_exceeds_buffer_view() {
+    let document = gltf::Gltf::from_slice(
+      br#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["EXT_meshopt_compression"],
+        "buffers": [{ "byteLength": 16 }],
+        "bufferViews": [{
+          "buffer": 0, "byteLength": 16, "byteStride": 4,
+          "extensions": {
+            "EXT_meshopt_compression": {
+              "buffer": 0, "byteLength": 16,
+              "byteStride": 4, "count": 1000000000, "mode": "ATTRIBUTES"
+            }
+          }
+        }]
+      }"#,
+    )
+    .unwrap()
+    .document;
+    let mut buffers = vec![gltf::buffer::Data(vec![0; 16])];
+
+    assert!(matches!(
+      decode_meshopt_buffer_views(&document, &mut buffers),
+      Err(GLTFLoaderError::MeshoptDecodeError(
+        MeshCodecError::InvalidParameter("the decoded data exceeds the buffer view")
+      ))
+    ));
+  }
+}
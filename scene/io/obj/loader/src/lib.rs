@@ -3,6 +3,7 @@ use std::path::Path;
 use database::*;
 use rendiation_algebra::*;
 use rendiation_mesh_core::*;
+use rendiation_mesh_optimization::*;
 use rendiation_scene_core::*;
 use rendiation_texture_loader::*;
 use smallvec::SmallVec;
@@ -18,9 +19,10 @@ pub fn load_obj(
   node: EntityHandle<SceneNodeEntity>,
   target_scene: EntityHandle<SceneEntity>,
   default_mat: EntityHandle<PbrSGMaterialEntity>,
+  mesh_optimization: Option<&MeshOptimizationConfig>,
   writer: &mut SceneWriter,
 ) -> Result<(), ObjLoadError> {
-  let models = load_obj_content(path, default_mat, writer)?;
+  let models = load_obj_content(path, default_mat, mesh_optimization, writer)?;
 
   for model in models {
     let std_model = model.write(&mut writer.std_model_writer);
@@ -36,9 +38,12 @@ pub fn load_obj(
   Ok(())
 }
 
+/// if the mesh_optimization is provided, the meshes are reordered for the gpu efficiency, see
+/// [optimize_attributes_mesh]
 pub fn load_obj_content(
   path: impl AsRef<Path> + std::fmt::Debug,
   default_mat: EntityHandle<PbrSGMaterialEntity>,
+  mesh_optimization: Option<&MeshOptimizationConfig>,
   writer: &mut SceneWriter,
 ) -> Result<Vec<StandardModelDataView>, ObjLoadError> {
   let (models, materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)?;
@@ -46,7 +51,12 @@ pub fn load_obj_content(
   let models = models
     .iter()
     .map(|m| {
-      let attribute_mesh = create_attribute_mesh_from_obj_mesh(&m.mesh);
+      let mut attribute_mesh = create_attribute_mesh_from_obj_mesh(&m.mesh);
+      if let Some(config) = mesh_optimization
+        && let Some(optimized) = optimize_attributes_mesh(&attribute_mesh, config)
+      {
+        attribute_mesh = optimized;
+      }
       let attribute_mesh = writer.write_attribute_mesh(attribute_mesh).mesh;
 
       let mut material = None;
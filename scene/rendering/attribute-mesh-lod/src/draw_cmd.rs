@@ -20,6 +20,17 @@ pub struct LODCameraInfo {
   /// the screen space error threshold to switch to a coarser level, in pixels,
   /// only the x component is used, the rest is padding
   pub lod_error_threshold: UniformBufferDataView<Vec4<f32>>,
+  /// the host side copy of the view, used by the lod data streaming
+  pub host: LODCameraHostInfo,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LODCameraHostInfo {
+  pub camera: CameraTransform,
+  /// (width, height)
+  pub view_resolution: Vec2<u32>,
+  /// in pixels
+  pub lod_error_threshold: f32,
 }
 
 impl IndexedDrawCommandBuilder for AttributeLODMeshIndirectDrawCreator {
@@ -218,13 +218,26 @@ impl IndirectModelShapeRenderImpl for AttributeLODMeshIndirectRenderer {
 #[derive(Default, Clone)]
 pub struct CurrentLODCameraControl {
   current_view: Arc<Mutex<Option<LODCameraInfo>>>,
+  recent_host_views: Arc<Mutex<Vec<LODCameraHostInfo>>>,
 }
 
 impl CurrentLODCameraControl {
   pub fn set(&self, camera: Option<LODCameraInfo>) {
+    if let Some(camera) = &camera {
+      let mut views = self.recent_host_views.lock();
+      if !views.contains(&camera.host) {
+        views.push(camera.host);
+      }
+    }
     *self.current_view.lock() = camera;
   }
 
+  /// take the views set since the last call. the lod data streaming uses them to decide the
+  /// data required by the next frame, because the views are only known in rendering.
+  pub fn take_recent_host_views(&self) -> Vec<LODCameraHostInfo> {
+    std::mem::take(&mut *self.recent_host_views.lock())
+  }
+
   pub fn get(&self) -> Option<LODCameraInfo> {
     self.current_view.lock().as_ref().cloned()
   }
@@ -34,6 +34,8 @@ use rendiation_shader_api::*;
 use rendiation_texture_core::*;
 use rendiation_texture_gpu_base::*;
 pub use rendiation_texture_gpu_system::TexturePoolSourceInit;
+pub use rendiation_texture_gpu_system::VirtualTextureSystemInit;
+pub use rendiation_texture_gpu_system::VirtualTextureUriLoaderCreator;
 use rendiation_texture_gpu_system::*;
 use rendiation_webgpu::*;
 use rendiation_webgpu_hook_utils::*;
@@ -44,6 +46,8 @@ mod error_model;
 pub use error_model::*;
 mod skin;
 pub use skin::*;
+mod morph;
+pub use morph::*;
 mod world_bounding;
 pub use world_bounding::*;
 mod world_matrix;
@@ -0,0 +1,70 @@
+use crate::*;
+
+pub struct MorphTargetDisplacement {
+  pub position: Node<Vec3<f32>>,
+  pub normal: Node<Vec3<f32>>,
+  pub tangent: Node<Vec3<f32>>,
+}
+
+pub trait MorphTargetAccessInvocation {
+  /// the target index should be less than [MAX_MORPH_TARGET_COUNT]. the weight of the
+  /// not exist target should be zero.
+  fn get_weight(&self, target_index: usize) -> Node<f32>;
+  fn get_displacement(
+    &self,
+    target_index: usize,
+    vertex_index: Node<u32>,
+  ) -> MorphTargetDisplacement;
+}
+
+/// apply the morph target displacement to the geometry, this should be applied after the
+/// geometry attribute is registered, and before the skin transform.
+pub struct MorphTargetVertexTransform;
+
+impl ShaderHashProvider for MorphTargetVertexTransform {
+  shader_hash_type_id! {}
+}
+impl ShaderPassBuilder for MorphTargetVertexTransform {
+  fn setup_pass(&self, _: &mut GPURenderPassCtx) {}
+}
+impl GraphicsShaderProvider for MorphTargetVertexTransform {
+  fn build(&self, builder: &mut ShaderRenderPipelineBuilder) {
+    builder.vertex(|builder, _bind| {
+      let vertex_index = builder.query::<VertexIndex>();
+      let mut position = builder.query::<GeometryPosition>();
+      let mut normal = builder.try_query::<GeometryNormal>();
+      let mut tangent = builder.try_query::<GeometryTangent>();
+
+      let Some(morph_targets) = builder
+        .registry()
+        .any_map
+        .get::<Box<dyn MorphTargetAccessInvocation>>()
+      else {
+        return;
+      };
+
+      for target_index in 0..MAX_MORPH_TARGET_COUNT {
+        let weight = morph_targets.get_weight(target_index);
+        let displacement = morph_targets.get_displacement(target_index, vertex_index);
+
+        position += displacement.position * weight;
+        if let Some(normal) = &mut normal {
+          *normal += displacement.normal * weight;
+        }
+        if let Some(tangent) = &mut tangent {
+          let displaced = tangent.xyz() + displacement.tangent * weight;
+          *tangent = (displaced, tangent.w()).into();
+        }
+      }
+
+      builder.register::<GeometryPosition>(position);
+      if let Some(normal) = normal {
+        builder.register::<GeometryNormal>(normal.normalize());
+      }
+      if let Some(tangent) = tangent {
+        let tangent_dir = tangent.xyz().normalize();
+        builder.register::<GeometryTangent>((tangent_dir, tangent.w()));
+      }
+    })
+  }
+}
@@ -82,8 +82,11 @@ fn use_cube_face_update_impl(
       changed_keys.removed_keys.remove(&k);
 
       if let Some(source) = source.as_living() {
-        let source = GPUBufferImageForeignImpl { inner: source };
-        let mip = if allocate_mipmap {
+        let image = prepare_gpu_buffer_image(cx.gpu, source);
+        let source = GPUBufferImageForeignImpl { inner: &image };
+        let mip = if !image.precomputed_mips.is_empty() {
+          MipLevelCount::Fixed(image.mip_level_count())
+        } else if allocate_mipmap {
           MipLevelCount::BySize
         } else {
           MipLevelCount::EmptyMipMap
@@ -116,7 +119,16 @@ fn use_cube_face_update_impl(
         let gpu_texture = target.entry(k).or_insert_with(create);
         let gpu_texture: GPUCubeTexture = gpu_texture.resource.clone().try_into().unwrap();
 
-        let _ = gpu_texture.upload(&cx.gpu.queue, &source, face, 0);
+        let level_count = gpu_texture.desc.mip_level_count as usize;
+        for level in 0..image.mip_level_count().min(level_count) {
+          let source = GPUBufferImageLevelForeignImpl {
+            inner: &image,
+            level,
+          };
+          let _ = gpu_texture
+            .clone()
+            .upload(&cx.gpu.queue, &source, face, level);
+        }
       } else {
         log::warn!("cube texture requires alive source");
         target.remove(&k);
@@ -16,6 +16,8 @@ pub fn use_texture_system<
   cx: &mut QueryGPUHookCx,
   ty: GPUTextureBindingSystemType,
   pool_init_config: &TexturePoolSourceInit,
+  virtual_texture_init_config: &VirtualTextureSystemInit,
+  virtual_texture_uri_loader: &VirtualTextureUriLoaderCreator,
   source_creator: impl FnOnce(&mut QueryGPUHookCx<'_>) -> UseResult<R>,
 ) -> Option<GPUTextureBindingSystem> {
   cx.next_scope_index();
@@ -34,6 +36,10 @@ pub fn use_texture_system<
       let source = source_creator(cx);
       use_pool_texture_system(cx, pool_init_config, source)
     }),
+    // the virtual texture loads the uri source by itself, only the required pages are loaded
+    GPUTextureBindingSystemType::VirtualTexture => cx.scope(|cx| {
+      use_virtual_texture_system(cx, virtual_texture_init_config, virtual_texture_uri_loader)
+    }),
   }
 }
 
@@ -228,10 +234,57 @@ pub fn use_pool_texture_system(
   })
 }
 
+pub fn use_virtual_texture_system(
+  cx: &mut QueryGPUHookCx,
+  init: &VirtualTextureSystemInit,
+  uri_loader: &VirtualTextureUriLoaderCreator,
+) -> Option<GPUTextureBindingSystem> {
+  let (cx, samplers) =
+    cx.use_storage_buffer("sampler info", init.init_sampler_count_capacity, u32::MAX);
+  cx.use_changes::<SceneSamplerInfo>()
+    .map_changes(TextureSamplerShaderInfo::from)
+    .update_storage_array(cx, samplers, 0);
+
+  samplers.use_max_item_count_by_db_entity::<SceneSamplerEntity>(cx);
+  samplers.use_update(cx);
+
+  let (cx, virtual_texture) =
+    cx.use_plain_state(|| StreamingVirtualTexture::new(cx.gpu, *init, uri_loader()));
+
+  let source_changes = cx
+    .use_changes::<SceneTexture2dEntityDirectContent>()
+    .map_spawn_stage_in_thread_data_changes(cx, |changes| {
+      let mut result = Vec::new();
+      result.extend(changes.iter_removed().map(|k| (k, None)));
+      result.extend(
+        changes
+          .iter_update_or_insert()
+          .map(|(k, v)| (k, v.map(|v| v.ptr.as_ref().clone()))),
+      );
+      Arc::new(result)
+    })
+    .use_assure_result(cx);
+
+  if let GPUQueryHookStage::CreateRender { encoder, .. } = &mut cx.stage {
+    if let Some(changes) = source_changes.into_resolve_stage() {
+      for (texture, source) in changes.iter() {
+        virtual_texture.set_texture(*texture, source.clone());
+      }
+    }
+    virtual_texture.update(cx.gpu, encoder);
+  }
+
+  cx.when_render(|| {
+    Box::new(virtual_texture.create_system(samplers.get_gpu_buffer())) as GPUTextureBindingSystem
+  })
+}
+
 pub enum GPUTextureBindingSystemType {
   GlesSingleBinding,
   Bindless,
   TexturePool,
+  /// sparse page table texture system, only the pages required by the rendering are resident
+  VirtualTexture,
 }
 
 const BINDLESS_EFFECTIVE_COUNT: u32 = 8192;
@@ -282,3 +335,13 @@ impl GraphicsShaderProvider for GPUTextureSystemAsRenderComponent<'_> {
     self.0.post_build(builder)
   }
 }
+
+/// apply the uv transform in the form of [TextureTransform::to_matrix_rows]
+pub fn apply_texture_transform(
+  uv: Node<Vec2<f32>>,
+  row_0: Node<Vec4<f32>>,
+  row_1: Node<Vec4<f32>>,
+) -> Node<Vec2<f32>> {
+  let uv: Node<Vec3<f32>> = (uv, val(1.)).into();
+  (row_0.xyz().dot(uv), row_1.xyz().dot(uv)).into()
+}
@@ -16,6 +16,8 @@ mod node;
 pub use node::*;
 mod skin;
 pub use skin::*;
+mod morph;
+pub use morph::*;
 mod light;
 pub use light::*;
 mod scene;
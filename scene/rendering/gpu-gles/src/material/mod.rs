@@ -15,6 +15,21 @@ pub struct TextureSamplerHandlePair {
   pub sampler_handle: u32,
 }
 
+/// the first two rows of the uv transform matrix, see [TextureTransform::to_matrix_rows]
+#[repr(C)]
+#[std140_layout]
+#[derive(Clone, Copy, ShaderStruct, Default, Debug, PartialEq)]
+pub struct TextureTransformRows {
+  pub row_0: Vec4<f32>,
+  pub row_1: Vec4<f32>,
+}
+
+impl TextureTransformRowsShaderAPIInstance {
+  pub fn apply(&self, uv: Node<Vec2<f32>>) -> Node<Vec2<f32>> {
+    apply_texture_transform(uv, self.row_0, self.row_1)
+  }
+}
+
 pub fn setup_tex(
   ctx: &mut GPURenderPassCtx,
   binding_sys: &GPUTextureBindingSystem,
@@ -72,6 +87,20 @@ pub fn use_tex_watcher<T, TexUniform>(
     .update_uniforms(uniform, offset + sam_offset, cx.gpu);
 }
 
+pub fn use_tex_transform_watcher<T, TexUniform>(
+  cx: &mut QueryGPUHookCx,
+  offset: usize,
+  uniform: &UniformBufferCollection<u32, TexUniform>,
+) where
+  TexUniform: Std140 + Default,
+  T: TextureWithSamplingForeignKeys,
+{
+  cx.use_changes::<TextureTransformOf<T>>()
+    .map(|changes| changes.collective_map(|t| t.to_matrix_rows()))
+    // the two rows are continuous in the uniform
+    .update_uniforms(uniform, offset, cx.gpu);
+}
+
 pub trait GLESModelMaterialRenderImpl {
   fn make_component<'a>(
     &'a self,
@@ -1,4 +1,4 @@
-use rendiation_lighting_transport::{MetallicChannel, RoughnessChannel};
+use rendiation_lighting_transport::*;
 use rendiation_shader_library::normal_mapping::apply_normal_mapping_conditional;
 
 use crate::*;
@@ -24,6 +24,33 @@ pub fn use_pbr_mr_material_uniforms(cx: &mut QueryGPUHookCx) -> Option<PbrMRMate
   cx.use_changes::<AlphaOf<PbrMRMaterialAlphaConfig>>()
     .update_uniforms(&uniforms, offset_of!(Uniform, alpha), cx.gpu);
 
+  cx.use_changes::<PbrMRMaterialEmissiveStrengthComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, emissive_strength), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialIorComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, ior), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialSpecularFactorComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, specular_factor), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialSpecularColorComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, specular_color), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialTransmissionComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, transmission), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialClearcoatComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, clearcoat), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialClearcoatRoughnessComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, clearcoat_roughness), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialSheenColorComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, sheen_color), cx.gpu);
+
+  cx.use_changes::<PbrMRMaterialSheenRoughnessComponent>()
+    .update_uniforms(&uniforms, offset_of!(Uniform, sheen_roughness), cx.gpu);
+
   cx.use_changes::<AlphaCutoffOf<PbrMRMaterialAlphaConfig>>()
     .update_uniforms(&uniforms, offset_of!(Uniform, alpha_cutoff), cx.gpu);
 
@@ -33,21 +60,70 @@ pub fn use_pbr_mr_material_uniforms(cx: &mut QueryGPUHookCx) -> Option<PbrMRMate
   let emissive = offset_of!(TexUniform, emissive_texture);
   let metallic_roughness = offset_of!(TexUniform, metallic_roughness_texture);
   let normal = offset_of!(TexUniform, normal_texture);
+  let specular = offset_of!(TexUniform, specular_texture);
+  let specular_color = offset_of!(TexUniform, specular_color_texture);
+  let transmission = offset_of!(TexUniform, transmission_texture);
+  let clearcoat = offset_of!(TexUniform, clearcoat_texture);
+  let clearcoat_roughness = offset_of!(TexUniform, clearcoat_roughness_texture);
+  let sheen_color = offset_of!(TexUniform, sheen_color_texture);
+  let sheen_roughness = offset_of!(TexUniform, sheen_roughness_texture);
 
   use_tex_watcher::<PbrMRMaterialBaseColorAlphaTex, _>(cx, base_color_alpha, &tex_uniforms);
   use_tex_watcher::<PbrMRMaterialEmissiveTex, _>(cx, emissive, &tex_uniforms);
   use_tex_watcher::<PbrMRMaterialMetallicRoughnessTex, _>(cx, metallic_roughness, &tex_uniforms);
   use_tex_watcher::<NormalTexSamplerOf<PbrMRMaterialNormalInfo>, _>(cx, normal, &tex_uniforms);
+  use_tex_watcher::<PbrMRMaterialSpecularTex, _>(cx, specular, &tex_uniforms);
+  use_tex_watcher::<PbrMRMaterialSpecularColorTex, _>(cx, specular_color, &tex_uniforms);
+  use_tex_watcher::<PbrMRMaterialTransmissionTex, _>(cx, transmission, &tex_uniforms);
+  use_tex_watcher::<PbrMRMaterialClearcoatTex, _>(cx, clearcoat, &tex_uniforms);
+  use_tex_watcher::<PbrMRMaterialClearcoatRoughnessTex, _>(cx, clearcoat_roughness, &tex_uniforms);
+  use_tex_watcher::<PbrMRMaterialSheenColorTex, _>(cx, sheen_color, &tex_uniforms);
+  use_tex_watcher::<PbrMRMaterialSheenRoughnessTex, _>(cx, sheen_roughness, &tex_uniforms);
+
+  let tex_transform_uniforms = cx.use_uniform_buffers("pbr mr tex transform uniform");
+
+  let base_color_alpha = offset_of!(TexTransformUniform, base_color_alpha_transform);
+  let emissive = offset_of!(TexTransformUniform, emissive_transform);
+  let metallic_roughness = offset_of!(TexTransformUniform, metallic_roughness_transform);
+  let normal = offset_of!(TexTransformUniform, normal_transform);
+  let specular = offset_of!(TexTransformUniform, specular_transform);
+  let specular_color = offset_of!(TexTransformUniform, specular_color_transform);
+  let transmission = offset_of!(TexTransformUniform, transmission_transform);
+  let clearcoat = offset_of!(TexTransformUniform, clearcoat_transform);
+  let clearcoat_roughness = offset_of!(TexTransformUniform, clearcoat_roughness_transform);
+  let sheen_color = offset_of!(TexTransformUniform, sheen_color_transform);
+  let sheen_roughness = offset_of!(TexTransformUniform, sheen_roughness_transform);
+
+  let tex = &tex_transform_uniforms;
+  use_tex_transform_watcher::<PbrMRMaterialBaseColorAlphaTex, _>(cx, base_color_alpha, tex);
+  use_tex_transform_watcher::<PbrMRMaterialEmissiveTex, _>(cx, emissive, tex);
+  use_tex_transform_watcher::<PbrMRMaterialMetallicRoughnessTex, _>(cx, metallic_roughness, tex);
+  use_tex_transform_watcher::<NormalTexSamplerOf<PbrMRMaterialNormalInfo>, _>(cx, normal, tex);
+  use_tex_transform_watcher::<PbrMRMaterialSpecularTex, _>(cx, specular, tex);
+  use_tex_transform_watcher::<PbrMRMaterialSpecularColorTex, _>(cx, specular_color, tex);
+  use_tex_transform_watcher::<PbrMRMaterialTransmissionTex, _>(cx, transmission, tex);
+  use_tex_transform_watcher::<PbrMRMaterialClearcoatTex, _>(cx, clearcoat, tex);
+  use_tex_transform_watcher::<PbrMRMaterialClearcoatRoughnessTex, _>(cx, clearcoat_roughness, tex);
+  use_tex_transform_watcher::<PbrMRMaterialSheenColorTex, _>(cx, sheen_color, tex);
+  use_tex_transform_watcher::<PbrMRMaterialSheenRoughnessTex, _>(cx, sheen_roughness, tex);
 
   cx.when_render(|| PbrMRMaterialGlesRenderer {
     material_access: read_global_db_foreign_key(),
     uniforms: uniforms.make_read_holder(),
     tex_uniforms: tex_uniforms.make_read_holder(),
+    tex_transform_uniforms: tex_transform_uniforms.make_read_holder(),
     alpha_mode: read_global_db_component(),
     base_color_tex_sampler: TextureSamplerIdView::read_from_global(),
     mr_tex_sampler: TextureSamplerIdView::read_from_global(),
     emissive_tex_sampler: TextureSamplerIdView::read_from_global(),
     normal_tex_sampler: TextureSamplerIdView::read_from_global(),
+    specular_tex_sampler: TextureSamplerIdView::read_from_global(),
+    specular_color_tex_sampler: TextureSamplerIdView::read_from_global(),
+    transmission_tex_sampler: TextureSamplerIdView::read_from_global(),
+    clearcoat_tex_sampler: TextureSamplerIdView::read_from_global(),
+    clearcoat_roughness_tex_sampler: TextureSamplerIdView::read_from_global(),
+    sheen_color_tex_sampler: TextureSamplerIdView::read_from_global(),
+    sheen_roughness_tex_sampler: TextureSamplerIdView::read_from_global(),
   })
 }
 
@@ -55,11 +131,19 @@ pub struct PbrMRMaterialGlesRenderer {
   material_access: ForeignKeyReadView<StandardModelRefPbrMRMaterial>,
   uniforms: LockReadGuardHolder<PbrMRMaterialUniforms>,
   tex_uniforms: LockReadGuardHolder<PbrMRMaterialTexUniforms>,
+  tex_transform_uniforms: LockReadGuardHolder<PbrMRMaterialTexTransformUniforms>,
   alpha_mode: ComponentReadView<AlphaModeOf<PbrMRMaterialAlphaConfig>>,
   base_color_tex_sampler: TextureSamplerIdView<PbrMRMaterialBaseColorAlphaTex>,
   mr_tex_sampler: TextureSamplerIdView<PbrMRMaterialMetallicRoughnessTex>,
   emissive_tex_sampler: TextureSamplerIdView<PbrMRMaterialEmissiveTex>,
   normal_tex_sampler: TextureSamplerIdView<NormalTexSamplerOf<PbrMRMaterialNormalInfo>>,
+  specular_tex_sampler: TextureSamplerIdView<PbrMRMaterialSpecularTex>,
+  specular_color_tex_sampler: TextureSamplerIdView<PbrMRMaterialSpecularColorTex>,
+  transmission_tex_sampler: TextureSamplerIdView<PbrMRMaterialTransmissionTex>,
+  clearcoat_tex_sampler: TextureSamplerIdView<PbrMRMaterialClearcoatTex>,
+  clearcoat_roughness_tex_sampler: TextureSamplerIdView<PbrMRMaterialClearcoatRoughnessTex>,
+  sheen_color_tex_sampler: TextureSamplerIdView<PbrMRMaterialSheenColorTex>,
+  sheen_roughness_tex_sampler: TextureSamplerIdView<PbrMRMaterialSheenRoughnessTex>,
 }
 
 impl GLESModelMaterialRenderImpl for PbrMRMaterialGlesRenderer {
@@ -69,6 +153,19 @@ impl GLESModelMaterialRenderImpl for PbrMRMaterialGlesRenderer {
     cx: &'a GPUTextureBindingSystem,
   ) -> Option<Box<dyn RenderComponent + 'a>> {
     let idx = self.material_access.get(idx)?;
+    let extension_tex_samplers = [
+      self.specular_tex_sampler.get_pair(idx),
+      self.specular_color_tex_sampler.get_pair(idx),
+      self.transmission_tex_sampler.get_pair(idx),
+      self.clearcoat_tex_sampler.get_pair(idx),
+      self.clearcoat_roughness_tex_sampler.get_pair(idx),
+      self.sheen_color_tex_sampler.get_pair(idx),
+      self.sheen_roughness_tex_sampler.get_pair(idx),
+    ];
+    let extension_tex_samplers = extension_tex_samplers
+      .iter()
+      .any(|pair| pair.is_some())
+      .then(|| extension_tex_samplers.map(|pair| pair.unwrap_or(EMPTY_H)));
     let r = PhysicalMetallicRoughnessMaterialGPU {
       uniform: self.uniforms.get(&idx.alloc_index())?,
       alpha_mode: self.alpha_mode.get_value(idx)?,
@@ -76,7 +173,9 @@ impl GLESModelMaterialRenderImpl for PbrMRMaterialGlesRenderer {
       mr_tex_sampler: self.mr_tex_sampler.get_pair(idx).unwrap_or(EMPTY_H),
       emissive_tex_sampler: self.emissive_tex_sampler.get_pair(idx).unwrap_or(EMPTY_H),
       normal_tex_sampler: self.normal_tex_sampler.get_pair(idx).unwrap_or(EMPTY_H),
+      extension_tex_samplers,
       texture_uniforms: self.tex_uniforms.get(&idx.alloc_index())?,
+      texture_transform_uniforms: self.tex_transform_uniforms.get(&idx.alloc_index())?,
       binding_sys: cx,
     };
     let r = Box::new(r) as Box<dyn RenderComponent + '_>;
@@ -95,6 +194,15 @@ struct PhysicalMetallicRoughnessMaterialUniform {
   pub normal_mapping_scale: f32,
   pub alpha_cutoff: f32,
   pub alpha: f32,
+  pub emissive_strength: f32,
+  pub ior: f32,
+  pub specular_factor: f32,
+  pub specular_color: Vec3<f32>,
+  pub transmission: f32,
+  pub clearcoat: f32,
+  pub clearcoat_roughness: f32,
+  pub sheen_color: Vec3<f32>,
+  pub sheen_roughness: f32,
 }
 
 type Uniform = PhysicalMetallicRoughnessMaterialUniform;
@@ -108,11 +216,39 @@ struct PhysicalMetallicRoughnessMaterialTextureHandlesUniform {
   pub emissive_texture: TextureSamplerHandlePair,
   pub metallic_roughness_texture: TextureSamplerHandlePair,
   pub normal_texture: TextureSamplerHandlePair,
+  pub specular_texture: TextureSamplerHandlePair,
+  pub specular_color_texture: TextureSamplerHandlePair,
+  pub transmission_texture: TextureSamplerHandlePair,
+  pub clearcoat_texture: TextureSamplerHandlePair,
+  pub clearcoat_roughness_texture: TextureSamplerHandlePair,
+  pub sheen_color_texture: TextureSamplerHandlePair,
+  pub sheen_roughness_texture: TextureSamplerHandlePair,
 }
 
 type TexUniform = PhysicalMetallicRoughnessMaterialTextureHandlesUniform;
 type PbrMRMaterialTexUniforms = UniformBufferCollectionRaw<u32, TexUniform>;
 
+/// the uv transform of each texture slot
+#[repr(C)]
+#[std140_layout]
+#[derive(Clone, Copy, ShaderStruct, Debug, PartialEq, Default)]
+struct PhysicalMetallicRoughnessMaterialTextureTransformsUniform {
+  pub base_color_alpha_transform: TextureTransformRows,
+  pub emissive_transform: TextureTransformRows,
+  pub metallic_roughness_transform: TextureTransformRows,
+  pub normal_transform: TextureTransformRows,
+  pub specular_transform: TextureTransformRows,
+  pub specular_color_transform: TextureTransformRows,
+  pub transmission_transform: TextureTransformRows,
+  pub clearcoat_transform: TextureTransformRows,
+  pub clearcoat_roughness_transform: TextureTransformRows,
+  pub sheen_color_transform: TextureTransformRows,
+  pub sheen_roughness_transform: TextureTransformRows,
+}
+
+type TexTransformUniform = PhysicalMetallicRoughnessMaterialTextureTransformsUniform;
+type PbrMRMaterialTexTransformUniforms = UniformBufferCollectionRaw<u32, TexTransformUniform>;
+
 struct PhysicalMetallicRoughnessMaterialGPU<'a> {
   uniform: &'a UniformBufferDataView<PhysicalMetallicRoughnessMaterialUniform>,
   alpha_mode: AlphaMode,
@@ -121,16 +257,25 @@ struct PhysicalMetallicRoughnessMaterialGPU<'a> {
   mr_tex_sampler: (u32, u32),
   emissive_tex_sampler: (u32, u32),
   normal_tex_sampler: (u32, u32),
+  /// the extension textures are only sampled if any of them exists, in the order of specular,
+  /// specular color, transmission, clearcoat, clearcoat roughness, sheen color, sheen roughness.
+  ///
+  /// this avoids exceeding the per stage texture binding limit in the per object binding mode
+  /// for the most materials.
+  extension_tex_samplers: Option<[(u32, u32); 7]>,
   // no matter if we are using indirect texture binding, this uniform is required for checking the
   // texture if it is existing in shader
   texture_uniforms:
     &'a UniformBufferDataView<PhysicalMetallicRoughnessMaterialTextureHandlesUniform>,
+  texture_transform_uniforms:
+    &'a UniformBufferDataView<PhysicalMetallicRoughnessMaterialTextureTransformsUniform>,
   binding_sys: &'a GPUTextureBindingSystem,
 }
 
 impl ShaderHashProvider for PhysicalMetallicRoughnessMaterialGPU<'_> {
   fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
     hasher.hash(self.alpha_mode);
+    hasher.hash(self.extension_tex_samplers.is_some());
   }
   shader_hash_type_id! {PhysicalMetallicRoughnessMaterialGPU<'static>}
 }
@@ -139,10 +284,14 @@ impl ShaderPassBuilder for PhysicalMetallicRoughnessMaterialGPU<'_> {
   fn setup_pass(&self, ctx: &mut GPURenderPassCtx) {
     ctx.binding.bind(self.uniform);
     ctx.binding.bind(self.texture_uniforms);
+    ctx.binding.bind(self.texture_transform_uniforms);
     setup_tex(ctx, self.binding_sys, self.base_color_alpha_tex_sampler);
     setup_tex(ctx, self.binding_sys, self.mr_tex_sampler);
     setup_tex(ctx, self.binding_sys, self.emissive_tex_sampler);
     setup_tex(ctx, self.binding_sys, self.normal_tex_sampler);
+    for pair in self.extension_tex_samplers.iter().flatten() {
+      setup_tex(ctx, self.binding_sys, *pair);
+    }
   }
 }
 
@@ -151,6 +300,10 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialGPU<'_> {
     builder.fragment(|builder, binding| {
       let uniform = binding.bind_by(&self.uniform).load().expand();
       let tex_uniform = binding.bind_by(&self.texture_uniforms).load().expand();
+      let tex_transform = binding
+        .bind_by(&self.texture_transform_uniforms)
+        .load()
+        .expand();
 
       let uv = builder.get_or_compute_fragment_uv();
 
@@ -163,7 +316,7 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialGPU<'_> {
         builder.registry(),
         self.base_color_alpha_tex_sampler,
         tex_uniform.base_color_alpha_texture,
-        uv,
+        tex_transform.base_color_alpha_transform.expand().apply(uv),
         val(Vec4::one()),
       );
       alpha *= base_color_alpha_tex.w();
@@ -178,7 +331,10 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialGPU<'_> {
         builder.registry(),
         self.mr_tex_sampler,
         tex_uniform.metallic_roughness_texture,
-        uv,
+        tex_transform
+          .metallic_roughness_transform
+          .expand()
+          .apply(uv),
         val(Vec4::one()),
       );
 
@@ -192,24 +348,25 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialGPU<'_> {
         builder.registry(),
         self.emissive_tex_sampler,
         tex_uniform.emissive_texture,
-        uv,
+        tex_transform.emissive_transform.expand().apply(uv),
         val(Vec4::one()),
       )
       .xyz();
 
+      let normal_uv = tex_transform.normal_transform.expand().apply(uv);
       let (normal_sample, enabled) = bind_and_sample_enabled(
         self.binding_sys,
         binding,
         builder.registry(),
         self.normal_tex_sampler,
         tex_uniform.normal_texture,
-        uv,
+        normal_uv,
       );
 
       apply_normal_mapping_conditional(
         builder,
         normal_sample.xyz(),
-        uv,
+        normal_uv,
         uniform.normal_mapping_scale,
         enabled,
       );
@@ -223,10 +380,95 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialGPU<'_> {
       }
       .apply(builder);
 
+      let mut specular_factor = uniform.specular_factor;
+      let mut specular_color = uniform.specular_color;
+      let mut transmission = uniform.transmission;
+      let mut clearcoat = uniform.clearcoat;
+      let mut clearcoat_roughness = uniform.clearcoat_roughness;
+      let mut sheen_color = uniform.sheen_color;
+      let mut sheen_roughness = uniform.sheen_roughness;
+
+      if let Some(
+        [
+          specular_tex,
+          specular_color_tex,
+          transmission_tex,
+          clearcoat_tex,
+          clearcoat_roughness_tex,
+          sheen_color_tex,
+          sheen_roughness_tex,
+        ],
+      ) = self.extension_tex_samplers
+      {
+        let mut sample = |host_pair, handles, transform: Node<TextureTransformRows>| {
+          bind_and_sample(
+            self.binding_sys,
+            binding,
+            builder.registry(),
+            host_pair,
+            handles,
+            transform.expand().apply(uv),
+            val(Vec4::one()),
+          )
+        };
+
+        // the channel usage is defined by the gltf extensions
+        specular_factor *= sample(
+          specular_tex,
+          tex_uniform.specular_texture,
+          tex_transform.specular_transform,
+        )
+        .w();
+        specular_color *= sample(
+          specular_color_tex,
+          tex_uniform.specular_color_texture,
+          tex_transform.specular_color_transform,
+        )
+        .xyz();
+        transmission *= sample(
+          transmission_tex,
+          tex_uniform.transmission_texture,
+          tex_transform.transmission_transform,
+        )
+        .x();
+        clearcoat *= sample(
+          clearcoat_tex,
+          tex_uniform.clearcoat_texture,
+          tex_transform.clearcoat_transform,
+        )
+        .x();
+        clearcoat_roughness *= sample(
+          clearcoat_roughness_tex,
+          tex_uniform.clearcoat_roughness_texture,
+          tex_transform.clearcoat_roughness_transform,
+        )
+        .y();
+        sheen_color *= sample(
+          sheen_color_tex,
+          tex_uniform.sheen_color_texture,
+          tex_transform.sheen_color_transform,
+        )
+        .xyz();
+        sheen_roughness *= sample(
+          sheen_roughness_tex,
+          tex_uniform.sheen_roughness_texture,
+          tex_transform.sheen_roughness_transform,
+        )
+        .w();
+      }
+
       builder.register::<ColorChannel>(base_color);
-      builder.register::<EmissiveChannel>(emissive);
+      builder.register::<EmissiveChannel>(emissive * uniform.emissive_strength);
       builder.register::<MetallicChannel>(metallic);
       builder.register::<RoughnessChannel>(roughness);
+      builder.register::<IorChannel>(uniform.ior);
+      builder.register::<SpecularFactorChannel>(specular_factor);
+      builder.register::<SpecularColorChannel>(specular_color);
+      builder.register::<TransmissionChannel>(transmission);
+      builder.register::<ClearcoatChannel>(clearcoat);
+      builder.register::<ClearcoatRoughnessChannel>(clearcoat_roughness);
+      builder.register::<SheenColorChannel>(sheen_color);
+      builder.register::<SheenRoughnessChannel>(sheen_roughness);
 
       builder.register::<DefaultDisplay>((base_color, val(1.)));
       builder.insert_type_tag::<PbrMRMaterialTag>();
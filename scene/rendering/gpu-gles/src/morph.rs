@@ -0,0 +1,301 @@
+use fast_hash_collection::{FastHashMap, FastHashSet};
+use rendiation_mesh_core::AttributeSemantic;
+use rendiation_texture_core::{GPUBufferImage, Size};
+use rendiation_texture_gpu_base::GPUBufferImageForeignImpl;
+
+use crate::*;
+
+/// each target vertex occupies the position, normal and tangent displacement texel
+const MORPH_TARGET_TEXEL_PER_VERTEX: usize = 3;
+const MORPH_TARGET_TEXTURE_WIDTH: usize = 2048;
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Clone, Copy, Default, PartialEq, ShaderStruct, Debug)]
+pub struct MorphTargetMeshUniform {
+  pub vertex_count: u32,
+  pub target_count: u32,
+}
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Clone, Copy, Default, PartialEq, ShaderStruct, Debug)]
+pub struct MorphTargetWeightsUniform {
+  pub weights_0_3: Vec4<f32>,
+  pub weights_4_7: Vec4<f32>,
+}
+
+pub struct MorphTargetProvider<'a> {
+  data: &'a GPU2DTextureView,
+  mesh: &'a UniformBufferDataView<MorphTargetMeshUniform>,
+  weights: &'a UniformBufferDataView<MorphTargetWeightsUniform>,
+}
+
+pub struct MorphTargetInvocationProvider {
+  data: BindingNode<ShaderTexture2D>,
+  mesh: ENode<MorphTargetMeshUniform>,
+  weights: ENode<MorphTargetWeightsUniform>,
+}
+
+impl MorphTargetAccessInvocation for MorphTargetInvocationProvider {
+  fn get_weight(&self, target_index: usize) -> Node<f32> {
+    let weights = if target_index < 4 {
+      self.weights.weights_0_3
+    } else {
+      self.weights.weights_4_7
+    };
+    match target_index % 4 {
+      0 => weights.x(),
+      1 => weights.y(),
+      2 => weights.z(),
+      _ => weights.w(),
+    }
+  }
+
+  fn get_displacement(
+    &self,
+    target_index: usize,
+    vertex_index: Node<u32>,
+  ) -> MorphTargetDisplacement {
+    // the not exist target's weight is zero, clamp the index to avoid the out of bound access
+    let target_index = val(target_index as u32).min(self.mesh.target_count - val(1));
+    let vertex = target_index * self.mesh.vertex_count + vertex_index;
+    let texel = vertex * val(MORPH_TARGET_TEXEL_PER_VERTEX as u32);
+
+    let load = |texel: Node<u32>| {
+      let width = val(MORPH_TARGET_TEXTURE_WIDTH as u32);
+      let uv = vec2_node((texel % width, texel / width));
+      self.data.load_texel(uv, 0).xyz()
+    };
+
+    MorphTargetDisplacement {
+      position: load(texel),
+      normal: load(texel + val(1)),
+      tangent: load(texel + val(2)),
+    }
+  }
+}
+
+impl GraphicsShaderProvider for MorphTargetProvider<'_> {
+  fn build(&self, builder: &mut ShaderRenderPipelineBuilder) {
+    builder.vertex(|builder, bind| {
+      let morph_impl = MorphTargetInvocationProvider {
+        data: bind.bind_by(self.data),
+        mesh: bind.bind_by(self.mesh).load().expand(),
+        weights: bind.bind_by(self.weights).load().expand(),
+      };
+      let morph_impl = Box::new(morph_impl) as Box<dyn MorphTargetAccessInvocation>;
+      builder.registry().any_map.register(morph_impl);
+    })
+  }
+}
+impl ShaderPassBuilder for MorphTargetProvider<'_> {
+  fn setup_pass(&self, ctx: &mut GPURenderPassCtx) {
+    ctx.binding.bind(self.data);
+    ctx.binding.bind(self.mesh);
+    ctx.binding.bind(self.weights);
+  }
+}
+impl ShaderHashProvider for MorphTargetProvider<'_> {
+  shader_hash_type_id! {MorphTargetProvider<'static>}
+}
+
+pub fn use_morph_target(cx: &mut QueryGPUHookCx) -> Option<MorphTargetGPURenderer> {
+  let target_changes = cx
+    .use_changes::<AttributesMeshMorphTargetRefAttributesMesh>()
+    .use_assure_result(cx);
+
+  let mesh_targets = cx
+    .use_db_rev_ref_typed::<AttributesMeshMorphTargetRefAttributesMesh>()
+    .use_assure_result(cx);
+
+  let weights = cx.use_uniform_buffers("morph target weights uniform");
+  cx.use_changes::<StandardModelMorphTargetWeights>()
+    .map(|changes| changes.collective_map(|w| w.unwrap_or_default().weights))
+    // the weights array exactly fill the two vec4
+    .update_uniforms(
+      &weights,
+      offset_of!(MorphTargetWeightsUniform, weights_0_3),
+      cx.gpu,
+    );
+
+  let (cx, morph_gpu) = cx.use_plain_state_default::<SharedMorphTargetsGPU>();
+
+  cx.when_render(|| {
+    let target_changes = target_changes.expect_resolve_stage();
+    let mesh_targets = mesh_targets.expect_resolve_stage();
+    morph_gpu
+      .write()
+      .update(target_changes, &mesh_targets, cx.gpu);
+
+    MorphTargetGPURenderer {
+      mesh_data: morph_gpu.make_read_holder(),
+      weights: weights.make_read_holder(),
+      model_weights: read_global_db_component(),
+      mesh: read_global_db_foreign_key(),
+    }
+  })
+}
+
+pub struct MorphTargetGPURenderer {
+  mesh_data: LockReadGuardHolder<MorphTargetsGPU>,
+  weights: LockReadGuardHolder<UniformBufferCollectionRaw<u32, MorphTargetWeightsUniform>>,
+  model_weights: ComponentReadView<StandardModelMorphTargetWeights>,
+  mesh: ForeignKeyReadView<StandardModelRefAttributesMeshEntity>,
+}
+
+impl MorphTargetGPURenderer {
+  /// return None if the model has no morph target to apply
+  pub fn get_morph_target_provider(
+    &self,
+    model: EntityHandle<StandardModelEntity>,
+  ) -> Option<MorphTargetProvider<'_>> {
+    self.model_weights.get_value(model)??;
+    let mesh = self.mesh.get(model)?;
+    let (data, mesh) = self.mesh_data.meshes.get(&mesh.into_raw())?;
+    MorphTargetProvider {
+      data,
+      mesh,
+      weights: self.weights.get(&model.alloc_index())?,
+    }
+    .into()
+  }
+}
+
+pub type SharedMorphTargetsGPU = Arc<RwLock<MorphTargetsGPU>>;
+
+/// in gles mode we have to use texture to store the morph target displacement.
+///
+/// the morph target data is assumed to be immutable, only the target create and remove
+/// is tracked.
+#[derive(Default)]
+pub struct MorphTargetsGPU {
+  /// target => mesh
+  target_mesh: FastHashMap<u32, RawEntityHandle>,
+  meshes: FastHashMap<
+    RawEntityHandle,
+    (
+      GPU2DTextureView,
+      UniformBufferDataView<MorphTargetMeshUniform>,
+    ),
+  >,
+}
+
+impl MorphTargetsGPU {
+  pub fn update(
+    &mut self,
+    target_changes: impl DataChanges<Key = u32, Value = Option<RawEntityHandle>>,
+    mesh_targets: &RevRefForeignKeyReadTyped<AttributesMeshMorphTargetRefAttributesMesh>,
+    gpu: &GPU,
+  ) {
+    let mut changed_meshes = FastHashSet::default();
+    for target in target_changes.iter_removed() {
+      if let Some(mesh) = self.target_mesh.remove(&target) {
+        changed_meshes.insert(mesh);
+      }
+    }
+    for (target, mesh) in target_changes.iter_update_or_insert() {
+      if let Some(previous) = self.target_mesh.remove(&target) {
+        changed_meshes.insert(previous);
+      }
+      if let Some(mesh) = mesh {
+        self.target_mesh.insert(target, mesh);
+        changed_meshes.insert(mesh);
+      }
+    }
+
+    if changed_meshes.is_empty() {
+      return;
+    }
+
+    let index = get_db_view::<AttributesMeshMorphTargetIndex>();
+    let semantic = get_db_view::<AttributesMeshMorphTargetSemantic>();
+    let buffer_reader = read_global_db_component::<BufferEntityData>();
+    let view_read = SceneBufferViewReadView::<MorphTargetVertexRef>::new_from_global();
+
+    for mesh in changed_meshes {
+      self.meshes.remove(&mesh);
+      let mesh_handle = unsafe { EntityHandle::from_raw(mesh) };
+      let Some(targets) = mesh_targets.access_multi(&mesh_handle) else {
+        continue;
+      };
+
+      let mut target_count = 0;
+      let mut vertex_count = 0;
+      let mut displacements = Vec::new();
+      for target in targets {
+        let slot = match semantic.access(target.raw_handle_ref()).unwrap() {
+          AttributeSemantic::Positions => 0,
+          AttributeSemantic::Normals => 1,
+          AttributeSemantic::Tangents => 2,
+          _ => continue,
+        };
+        let target_index = index.access(target.raw_handle_ref()).unwrap() as usize;
+        let Some(data) = view_read
+          .read_view(target)
+          .and_then(|view| scene_buffer_view_into_attribute(view, &buffer_reader))
+        else {
+          continue;
+        };
+        target_count = target_count.max(target_index + 1);
+        vertex_count = vertex_count.max(data.count);
+        displacements.push((target_index, slot, data));
+      }
+
+      if target_count == 0 || vertex_count == 0 {
+        continue;
+      }
+
+      let texel_count = target_count * vertex_count * MORPH_TARGET_TEXEL_PER_VERTEX;
+      let height = texel_count.div_ceil(MORPH_TARGET_TEXTURE_WIDTH);
+      let max_height = gpu.info().supported_limits.max_texture_dimension_2d as usize;
+      if height > max_height {
+        log::warn!(
+          "morph target of mesh {mesh:?} requires {height} texture rows, exceeds the device limit {max_height}, the morph target is not applied"
+        );
+        continue;
+      }
+
+      let mut texels = vec![Vec4::<f32>::zero(); texel_count];
+      for (target_index, slot, data) in displacements {
+        let Some(data) = data.visit_slice::<Vec3<f32>>() else {
+          continue;
+        };
+        for (vertex, d) in data.iter().enumerate() {
+          let texel = (target_index * vertex_count + vertex) * MORPH_TARGET_TEXEL_PER_VERTEX + slot;
+          texels[texel] = Vec4::new(d.x, d.y, d.z, 0.);
+        }
+      }
+
+      let texture = create_morph_target_texture(gpu, texels);
+      let uniform = MorphTargetMeshUniform {
+        vertex_count: vertex_count as u32,
+        target_count: target_count as u32,
+        ..Zeroable::zeroed()
+      };
+      let uniform =
+        UniformBufferDataView::create(&gpu.device, uniform, "morph target mesh uniform");
+      self.meshes.insert(mesh, (texture, uniform));
+    }
+  }
+}
+
+fn create_morph_target_texture(cx: &GPU, mut texels: Vec<Vec4<f32>>) -> GPU2DTextureView {
+  let width = texels.len().min(MORPH_TARGET_TEXTURE_WIDTH);
+  let height = texels.len().div_ceil(MORPH_TARGET_TEXTURE_WIDTH);
+  texels.resize(width * height, Vec4::zero());
+
+  let image = GPUBufferImage {
+    data: cast_slice(&texels).to_vec(),
+    format: TextureFormat::Rgba32Float,
+    size: Size::from_usize_pair_min_one((width, height)),
+    precomputed_mips: Vec::new(),
+  };
+  let texture = GPUBufferImageForeignImpl { inner: &image };
+
+  let desc = texture.create_tex2d_desc(MipLevelCount::EmptyMipMap, cx.info().downgrade_info.flags);
+  let gpu_texture = GPUTexture::create(desc, &cx.device);
+  let gpu_texture: GPU2DTexture = gpu_texture.try_into().unwrap();
+  let gpu_texture = gpu_texture.upload_into(&cx.queue, &texture, 0);
+  gpu_texture.create_default_view().try_into().unwrap()
+}
@@ -133,6 +133,7 @@ fn create_data_texture(cx: &GPU, bind_matrixes: &[Mat4<f32>]) -> GPU2DTextureVie
     data: cast_slice(bind_matrixes).to_vec(),
     format: TextureFormat::Rgba32Float,
     size: Size::from_usize_pair_min_one((pixel_count_required, 1)),
+    precomputed_mips: Vec::new(),
   };
   let texture = GPUBufferImageForeignImpl { inner: &image };
 
@@ -48,6 +48,7 @@ pub fn std_model_renderer(
   revere_z: bool,
 ) -> Option<SceneStdModelRenderer> {
   let skin_gpu = use_skin(cx);
+  let morph_gpu = use_morph_target(cx);
 
   let state_override = use_state_overrides(cx, revere_z);
 
@@ -56,6 +57,7 @@ pub fn std_model_renderer(
     materials: materials.unwrap(),
     shapes: shapes.unwrap(),
     skin_gpu: skin_gpu.unwrap(),
+    morph_gpu: morph_gpu.unwrap(),
     states: state_override.unwrap(),
     skin: read_global_db_foreign_key(),
   })
@@ -66,6 +68,7 @@ pub struct SceneStdModelRenderer {
   materials: Box<dyn GLESModelMaterialRenderImpl>,
   shapes: Box<dyn GLESModelShapeRenderImpl>,
   skin_gpu: LockReadGuardHolder<SkinBoneMatrixesGPU>,
+  morph_gpu: MorphTargetGPURenderer,
   skin: ForeignKeyReadView<StandardModelRefSkin>,
   states: StateOverrides,
 }
@@ -81,17 +84,25 @@ impl GLESModelRenderImpl for SceneStdModelRenderer {
     let (base_shape, cmd) = self.shapes.make_component(model)?;
     let state = self.states.get_gpu(model)?;
 
-    let shape = if let Some(skin) = self.skin.get(model) {
-      let bones = self.skin_gpu.get_bone_provider(skin).unwrap();
-      let bones = Box::new(bones) as Box<dyn RenderComponent>;
-      let render = RenderArray([
-        bones,
-        base_shape,
-        Box::new(state),
-        Box::new(SkinVertexTransform),
-      ]);
-
-      Box::new(render)
+    let skin = self.skin.get(model);
+    let morph = self.morph_gpu.get_morph_target_provider(model);
+
+    let shape = if skin.is_some() || morph.is_some() {
+      let mut render = RenderVec::default();
+      let bones = skin.map(|skin| self.skin_gpu.get_bone_provider(skin).unwrap());
+      render.push(OptionRender(bones)).push(base_shape);
+
+      // the morph target is applied in the local space before skinning
+      if let Some(morph) = morph {
+        render.push(morph).push(MorphTargetVertexTransform);
+      }
+
+      render.push(state);
+      if skin.is_some() {
+        render.push(SkinVertexTransform);
+      }
+
+      Box::new(render) as Box<dyn RenderComponent>
     } else {
       base_shape
     };
@@ -8,6 +8,7 @@ pub struct PointLightStorage {
   pub luminance_intensity: Vec3<f32>,
   pub position: HighPrecisionTranslationStorage,
   pub cutoff_distance: f32,
+  pub enabled: Bool,
 }
 
 pub fn use_point_light_storage(
@@ -22,6 +23,10 @@ pub fn use_point_light_storage(
   cx.use_changes::<PointLightCutOffDistance>()
     .update_storage_array(cx, light, offset_of!(PointLightStorage, cutoff_distance));
 
+  cx.use_changes::<PointLightEnabled>()
+    .map_changes(Bool::from)
+    .update_storage_array(cx, light, offset_of!(PointLightStorage, enabled));
+
   use_global_node_world_mat(cx)
     .fanout(cx.use_db_rev_ref_tri_view::<PointLightRefNode>(), cx)
     .into_delta_change()
@@ -10,6 +10,7 @@ pub struct SpotLightStorage {
   pub cutoff_distance: f32,
   pub half_cone_cos: f32,
   pub half_penumbra_cos: f32,
+  pub enabled: Bool,
 }
 
 pub fn use_spot_light_storage(
@@ -32,6 +33,10 @@ pub fn use_spot_light_storage(
     .map_changes(|rad| rad.cos())
     .update_storage_array(cx, light, offset_of!(SpotLightStorage, half_penumbra_cos));
 
+  cx.use_changes::<SpotLightEnabled>()
+    .map_changes(Bool::from)
+    .update_storage_array(cx, light, offset_of!(SpotLightStorage, enabled));
+
   let (fanout, fanout_) = use_global_node_world_mat(cx)
     .fanout(cx.use_db_rev_ref_tri_view::<SpotLightRefNode>(), cx)
     .fork();
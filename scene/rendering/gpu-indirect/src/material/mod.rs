@@ -19,6 +19,21 @@ pub struct TextureSamplerHandlePair {
   pub sampler_handle: u32,
 }
 
+/// the first two rows of the uv transform matrix, see [TextureTransform::to_matrix_rows]
+#[repr(C)]
+#[std430_layout]
+#[derive(Clone, Copy, ShaderStruct, Default, Debug, PartialEq)]
+pub struct TextureTransformRows {
+  pub row_0: Vec4<f32>,
+  pub row_1: Vec4<f32>,
+}
+
+impl TextureTransformRowsShaderAPIInstance {
+  pub fn apply(&self, uv: Node<Vec2<f32>>) -> Node<Vec2<f32>> {
+    apply_texture_transform(uv, self.row_0, self.row_1)
+  }
+}
+
 pub fn indirect_sample(
   system: &GPUTextureBindingSystem,
   reg: &SemanticRegistry,
@@ -75,6 +90,20 @@ pub fn use_tex_watcher<T, TexStorage>(
     .update_storage_array(cx, storage, offset + sam_offset);
 }
 
+pub fn use_tex_transform_watcher<T, TexStorage>(
+  cx: &mut QueryGPUHookCx,
+  storage: &mut SparseUpdateStorageBuffer<TexStorage>,
+  offset: usize,
+) where
+  TexStorage: Std430 + ShaderSizedValueNodeType + Default,
+  T: TextureWithSamplingForeignKeys,
+{
+  cx.use_changes::<TextureTransformOf<T>>()
+    .map(|changes| changes.collective_map(|t| t.to_matrix_rows()))
+    // the two rows are continuous in the storage
+    .update_storage_array(cx, storage, offset);
+}
+
 pub fn use_tex_watcher_with_host<T, TexStorage>(
   cx: &mut QueryGPUHookCx,
   storage: &mut SparseUpdateStorageWithHostBuffer<TexStorage>,
@@ -1,4 +1,4 @@
-use rendiation_lighting_transport::{MetallicChannel, RoughnessChannel};
+use rendiation_lighting_transport::*;
 use rendiation_shader_library::normal_mapping::apply_normal_mapping_conditional_uniform_cfg;
 
 use crate::*;
@@ -26,6 +26,33 @@ pub fn use_pbr_mr_material_storage(
   cx.use_changes::<AlphaOf<PbrMRMaterialAlphaConfig>>()
     .update_storage_array(cx, storages, offset_of!(Storage, alpha));
 
+  cx.use_changes::<PbrMRMaterialEmissiveStrengthComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, emissive_strength));
+
+  cx.use_changes::<PbrMRMaterialIorComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, ior));
+
+  cx.use_changes::<PbrMRMaterialSpecularFactorComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, specular_factor));
+
+  cx.use_changes::<PbrMRMaterialSpecularColorComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, specular_color));
+
+  cx.use_changes::<PbrMRMaterialTransmissionComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, transmission));
+
+  cx.use_changes::<PbrMRMaterialClearcoatComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, clearcoat));
+
+  cx.use_changes::<PbrMRMaterialClearcoatRoughnessComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, clearcoat_roughness));
+
+  cx.use_changes::<PbrMRMaterialSheenColorComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, sheen_color));
+
+  cx.use_changes::<PbrMRMaterialSheenRoughnessComponent>()
+    .update_storage_array(cx, storages, offset_of!(Storage, sheen_roughness));
+
   cx.use_changes::<AlphaCutoffOf<PbrMRMaterialAlphaConfig>>()
     .update_storage_array(cx, storages, offset_of!(Storage, alpha_cutoff));
 
@@ -38,19 +65,65 @@ pub fn use_pbr_mr_material_storage(
   let emissive = offset_of!(TexStorage, emissive_texture);
   let metallic_roughness = offset_of!(TexStorage, metallic_roughness_texture);
   let normal = offset_of!(TexStorage, normal_texture);
+  let specular = offset_of!(TexStorage, specular_texture);
+  let specular_color = offset_of!(TexStorage, specular_color_texture);
+  let transmission = offset_of!(TexStorage, transmission_texture);
+  let clearcoat = offset_of!(TexStorage, clearcoat_texture);
+  let clearcoat_roughness = offset_of!(TexStorage, clearcoat_roughness_texture);
+  let sheen_color = offset_of!(TexStorage, sheen_color_texture);
+  let sheen_roughness = offset_of!(TexStorage, sheen_roughness_texture);
 
   use_tex_watcher::<PbrMRMaterialBaseColorAlphaTex, _>(cx, tex_storages, base_color_alpha);
   use_tex_watcher::<PbrMRMaterialEmissiveTex, _>(cx, tex_storages, emissive);
   use_tex_watcher::<PbrMRMaterialMetallicRoughnessTex, _>(cx, tex_storages, metallic_roughness);
   use_tex_watcher::<NormalTexSamplerOf<PbrMRMaterialNormalInfo>, _>(cx, tex_storages, normal);
+  use_tex_watcher::<PbrMRMaterialSpecularTex, _>(cx, tex_storages, specular);
+  use_tex_watcher::<PbrMRMaterialSpecularColorTex, _>(cx, tex_storages, specular_color);
+  use_tex_watcher::<PbrMRMaterialTransmissionTex, _>(cx, tex_storages, transmission);
+  use_tex_watcher::<PbrMRMaterialClearcoatTex, _>(cx, tex_storages, clearcoat);
+  use_tex_watcher::<PbrMRMaterialClearcoatRoughnessTex, _>(cx, tex_storages, clearcoat_roughness);
+  use_tex_watcher::<PbrMRMaterialSheenColorTex, _>(cx, tex_storages, sheen_color);
+  use_tex_watcher::<PbrMRMaterialSheenRoughnessTex, _>(cx, tex_storages, sheen_roughness);
 
   tex_storages.use_max_item_count_by_db_entity::<PbrMRMaterialEntity>(cx);
   tex_storages.use_update(cx);
 
+  let (cx, tex_transform_storages) =
+    cx.use_storage_buffer("pbr mr materials texture transform data", 128, u32::MAX);
+
+  let base_color_alpha = offset_of!(TexTransformStorage, base_color_alpha_transform);
+  let emissive = offset_of!(TexTransformStorage, emissive_transform);
+  let metallic_roughness = offset_of!(TexTransformStorage, metallic_roughness_transform);
+  let normal = offset_of!(TexTransformStorage, normal_transform);
+  let specular = offset_of!(TexTransformStorage, specular_transform);
+  let specular_color = offset_of!(TexTransformStorage, specular_color_transform);
+  let transmission = offset_of!(TexTransformStorage, transmission_transform);
+  let clearcoat = offset_of!(TexTransformStorage, clearcoat_transform);
+  let clearcoat_roughness = offset_of!(TexTransformStorage, clearcoat_roughness_transform);
+  let sheen_color = offset_of!(TexTransformStorage, sheen_color_transform);
+  let sheen_roughness = offset_of!(TexTransformStorage, sheen_roughness_transform);
+
+  let tex = &mut *tex_transform_storages;
+  use_tex_transform_watcher::<PbrMRMaterialBaseColorAlphaTex, _>(cx, tex, base_color_alpha);
+  use_tex_transform_watcher::<PbrMRMaterialEmissiveTex, _>(cx, tex, emissive);
+  use_tex_transform_watcher::<PbrMRMaterialMetallicRoughnessTex, _>(cx, tex, metallic_roughness);
+  use_tex_transform_watcher::<NormalTexSamplerOf<PbrMRMaterialNormalInfo>, _>(cx, tex, normal);
+  use_tex_transform_watcher::<PbrMRMaterialSpecularTex, _>(cx, tex, specular);
+  use_tex_transform_watcher::<PbrMRMaterialSpecularColorTex, _>(cx, tex, specular_color);
+  use_tex_transform_watcher::<PbrMRMaterialTransmissionTex, _>(cx, tex, transmission);
+  use_tex_transform_watcher::<PbrMRMaterialClearcoatTex, _>(cx, tex, clearcoat);
+  use_tex_transform_watcher::<PbrMRMaterialClearcoatRoughnessTex, _>(cx, tex, clearcoat_roughness);
+  use_tex_transform_watcher::<PbrMRMaterialSheenColorTex, _>(cx, tex, sheen_color);
+  use_tex_transform_watcher::<PbrMRMaterialSheenRoughnessTex, _>(cx, tex, sheen_roughness);
+
+  tex_transform_storages.use_max_item_count_by_db_entity::<PbrMRMaterialEntity>(cx);
+  tex_transform_storages.use_update(cx);
+
   cx.when_render(|| PbrMRMaterialIndirectRenderer {
     material_access: read_global_db_foreign_key(),
     storages: storages.get_gpu_buffer(),
     tex_storages: tex_storages.get_gpu_buffer(),
+    tex_transform_storages: tex_transform_storages.get_gpu_buffer(),
     alpha_mode: read_global_db_component(),
   })
 }
@@ -61,6 +134,8 @@ pub struct PbrMRMaterialIndirectRenderer {
   pub storages: AbstractReadonlyStorageBuffer<[PhysicalMetallicRoughnessMaterialStorage]>,
   pub tex_storages:
     AbstractReadonlyStorageBuffer<[PhysicalMetallicRoughnessMaterialTextureHandlesStorage]>,
+  pub tex_transform_storages:
+    AbstractReadonlyStorageBuffer<[PhysicalMetallicRoughnessMaterialTextureTransformsStorage]>,
   alpha_mode: ComponentReadView<AlphaModeOf<PbrMRMaterialAlphaConfig>>,
 }
 
@@ -75,6 +150,7 @@ impl IndirectModelMaterialRenderImpl for PbrMRMaterialIndirectRenderer {
       storage: &self.storages,
       alpha_mode: self.alpha_mode.get_value(idx)?,
       texture_storages: &self.tex_storages,
+      texture_transform_storages: &self.tex_transform_storages,
       binding_sys: cx,
     };
     let r = Box::new(r) as Box<dyn RenderComponent + '_>;
@@ -105,6 +181,15 @@ pub struct PhysicalMetallicRoughnessMaterialStorage {
   pub normal_mapping_scale: f32,
   pub alpha_cutoff: f32,
   pub alpha: f32,
+  pub emissive_strength: f32,
+  pub ior: f32,
+  pub specular_factor: f32,
+  pub specular_color: Vec3<f32>,
+  pub transmission: f32,
+  pub clearcoat: f32,
+  pub clearcoat_roughness: f32,
+  pub sheen_color: Vec3<f32>,
+  pub sheen_roughness: f32,
 }
 
 type Storage = PhysicalMetallicRoughnessMaterialStorage;
@@ -117,15 +202,44 @@ pub struct PhysicalMetallicRoughnessMaterialTextureHandlesStorage {
   pub emissive_texture: TextureSamplerHandlePair,
   pub metallic_roughness_texture: TextureSamplerHandlePair,
   pub normal_texture: TextureSamplerHandlePair,
+  pub specular_texture: TextureSamplerHandlePair,
+  pub specular_color_texture: TextureSamplerHandlePair,
+  pub transmission_texture: TextureSamplerHandlePair,
+  pub clearcoat_texture: TextureSamplerHandlePair,
+  pub clearcoat_roughness_texture: TextureSamplerHandlePair,
+  pub sheen_color_texture: TextureSamplerHandlePair,
+  pub sheen_roughness_texture: TextureSamplerHandlePair,
 }
 
 type TexStorage = PhysicalMetallicRoughnessMaterialTextureHandlesStorage;
 
+/// the uv transform of each texture slot
+#[repr(C)]
+#[std430_layout]
+#[derive(Clone, Copy, ShaderStruct, Debug, PartialEq, Default)]
+pub struct PhysicalMetallicRoughnessMaterialTextureTransformsStorage {
+  pub base_color_alpha_transform: TextureTransformRows,
+  pub emissive_transform: TextureTransformRows,
+  pub metallic_roughness_transform: TextureTransformRows,
+  pub normal_transform: TextureTransformRows,
+  pub specular_transform: TextureTransformRows,
+  pub specular_color_transform: TextureTransformRows,
+  pub transmission_transform: TextureTransformRows,
+  pub clearcoat_transform: TextureTransformRows,
+  pub clearcoat_roughness_transform: TextureTransformRows,
+  pub sheen_color_transform: TextureTransformRows,
+  pub sheen_roughness_transform: TextureTransformRows,
+}
+
+type TexTransformStorage = PhysicalMetallicRoughnessMaterialTextureTransformsStorage;
+
 pub struct PhysicalMetallicRoughnessMaterialIndirectGPU<'a> {
   storage: &'a AbstractReadonlyStorageBuffer<[PhysicalMetallicRoughnessMaterialStorage]>,
   alpha_mode: AlphaMode,
   texture_storages:
     &'a AbstractReadonlyStorageBuffer<[PhysicalMetallicRoughnessMaterialTextureHandlesStorage]>,
+  texture_transform_storages:
+    &'a AbstractReadonlyStorageBuffer<[PhysicalMetallicRoughnessMaterialTextureTransformsStorage]>,
   binding_sys: &'a GPUTextureBindingSystem,
 }
 
@@ -140,6 +254,7 @@ impl ShaderPassBuilder for PhysicalMetallicRoughnessMaterialIndirectGPU<'_> {
   fn setup_pass(&self, ctx: &mut GPURenderPassCtx) {
     ctx.binding.bind(self.storage);
     ctx.binding.bind(self.texture_storages);
+    ctx.binding.bind(self.texture_transform_storages);
   }
 }
 
@@ -153,6 +268,11 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialIndirectGPU<'_>
         .index(id)
         .load()
         .expand();
+      let tex_transform = binding
+        .bind_by(self.texture_transform_storages)
+        .index(id)
+        .load()
+        .expand();
 
       let uv = builder.get_or_compute_fragment_uv();
 
@@ -163,7 +283,7 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialIndirectGPU<'_>
         self.binding_sys,
         builder.registry(),
         tex_storage.base_color_alpha_texture,
-        uv,
+        tex_transform.base_color_alpha_transform.expand().apply(uv),
         val(Vec4::one()),
       );
       alpha *= base_color_alpha_tex.w();
@@ -176,7 +296,10 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialIndirectGPU<'_>
         self.binding_sys,
         builder.registry(),
         tex_storage.metallic_roughness_texture,
-        uv,
+        tex_transform
+          .metallic_roughness_transform
+          .expand()
+          .apply(uv),
         val(Vec4::one()),
       );
 
@@ -188,22 +311,23 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialIndirectGPU<'_>
         self.binding_sys,
         builder.registry(),
         tex_storage.emissive_texture,
-        uv,
+        tex_transform.emissive_transform.expand().apply(uv),
         val(Vec4::one()),
       )
       .xyz();
 
+      let normal_uv = tex_transform.normal_transform.expand().apply(uv);
       let (normal_sample, enabled) = indirect_sample_enabled(
         self.binding_sys,
         builder.registry(),
         tex_storage.normal_texture,
-        uv,
+        normal_uv,
       );
 
       apply_normal_mapping_conditional_uniform_cfg(
         builder,
         normal_sample.xyz(),
-        uv,
+        normal_uv,
         storage.normal_mapping_scale,
         enabled,
       );
@@ -217,10 +341,72 @@ impl GraphicsShaderProvider for PhysicalMetallicRoughnessMaterialIndirectGPU<'_>
       }
       .apply(builder);
 
+      let sample = |handles, transform: Node<TextureTransformRows>| {
+        indirect_sample(
+          self.binding_sys,
+          builder.registry(),
+          handles,
+          transform.expand().apply(uv),
+          val(Vec4::one()),
+        )
+      };
+
+      // the channel usage is defined by the gltf extensions
+      let specular_factor = storage.specular_factor
+        * sample(
+          tex_storage.specular_texture,
+          tex_transform.specular_transform,
+        )
+        .w();
+      let specular_color = storage.specular_color
+        * sample(
+          tex_storage.specular_color_texture,
+          tex_transform.specular_color_transform,
+        )
+        .xyz();
+      let transmission = storage.transmission
+        * sample(
+          tex_storage.transmission_texture,
+          tex_transform.transmission_transform,
+        )
+        .x();
+      let clearcoat = storage.clearcoat
+        * sample(
+          tex_storage.clearcoat_texture,
+          tex_transform.clearcoat_transform,
+        )
+        .x();
+      let clearcoat_roughness = storage.clearcoat_roughness
+        * sample(
+          tex_storage.clearcoat_roughness_texture,
+          tex_transform.clearcoat_roughness_transform,
+        )
+        .y();
+      let sheen_color = storage.sheen_color
+        * sample(
+          tex_storage.sheen_color_texture,
+          tex_transform.sheen_color_transform,
+        )
+        .xyz();
+      let sheen_roughness = storage.sheen_roughness
+        * sample(
+          tex_storage.sheen_roughness_texture,
+          tex_transform.sheen_roughness_transform,
+        )
+        .w();
+
       builder.register::<ColorChannel>(base_color);
-      builder.register::<EmissiveChannel>(emissive);
+      builder.register::<EmissiveChannel>(emissive * storage.emissive_strength);
       builder.register::<MetallicChannel>(metallic);
       builder.register::<RoughnessChannel>(roughness);
+      builder.register::<IorChannel>(storage.ior);
+      builder.register::<SpecularFactorChannel>(specular_factor);
+      builder.register::<SpecularColorChannel>(specular_color);
+      builder.register::<TransmissionChannel>(transmission);
+      builder.register::<ClearcoatChannel>(clearcoat);
+      builder.register::<ClearcoatRoughnessChannel>(clearcoat_roughness);
+      builder.register::<SheenColorChannel>(sheen_color);
+      builder.register::<SheenRoughnessChannel>(sheen_roughness);
 
       builder.register::<DefaultDisplay>((base_color, val(1.)));
       builder.insert_type_tag::<PbrMRMaterialTag>();
@@ -320,6 +320,12 @@ pub fn use_std_model_renderer(
     .map(|mesh| mesh.map_u32_index_or_u32_max())
     .update_storage_array(cx, std_model, offset_of!(SceneStdModelStorage, skin));
 
+  // the morph target is not supported in indirect mode yet, the model is rendered without
+  // the displacement.
+  let morph_weights = cx
+    .use_changes::<StandardModelMorphTargetWeights>()
+    .use_assure_result(cx);
+
   let state_override = use_state_overrides(cx, revere_z);
 
   material_key.update_storage_array(cx, std_model, offset_of!(SceneStdModelStorage, material));
@@ -327,6 +333,23 @@ pub fn use_std_model_renderer(
   std_model.use_update(cx);
   std_model.use_max_item_count_by_db_entity::<StandardModelEntity>(cx);
 
+  let (cx, morph_warned) = cx.use_plain_state_default::<bool>();
+
+  cx.when_render(|| {
+    if !*morph_warned {
+      let morph_weights = morph_weights.expect_resolve_stage();
+      if morph_weights
+        .iter_update_or_insert()
+        .any(|(_, weights)| weights.is_some())
+      {
+        log::warn!(
+          "morph target is not supported in indirect rendering, the displacement is ignored"
+        );
+        *morph_warned = true;
+      }
+    }
+  });
+
   cx.when_render(|| SceneStdModelIndirectRenderer {
     model: read_global_db_foreign_key(),
     materials: materials.unwrap(),
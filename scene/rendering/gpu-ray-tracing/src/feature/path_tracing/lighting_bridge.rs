@@ -1,3 +1,4 @@
+use rendiation_area_lighting::AreaLightStorageShaderAPIInstance;
 use rendiation_lighting_punctual::punctual_light_intensity_to_illuminance_factor_fn;
 
 use crate::*;
@@ -256,3 +257,234 @@ impl ShaderLightSource for DirectionalLightStorageShaderAPIInstance {
     }
   }
 }
+
+/// the light surface is uniformly sampled by area, and the pdf is converted into the solid angle
+/// measure. unlike the punctual lights, the area light is not a dirac distribution, so the
+/// [ShaderLightSource] is not implemented, because the pdf can not be decided by the direction
+/// only.
+impl DevicePathTracingLightingInvocation for AreaLightStorageShaderAPIInstance {
+  fn importance_sampling_light(
+    &self,
+    world_position: Node<Vec3<f32>>,
+    sampler: &dyn DeviceSampler,
+  ) -> (RTLightSampling, Node<bool>) {
+    let x = self.axis_x * self.half_size.x();
+    let y = self.axis_y * self.half_size.y();
+    let is_disk = self.is_disk.into_bool();
+
+    let uv = sampler.next_2d();
+    let offset = is_disk.select_branched(
+      || {
+        // the sqrt makes the samples uniformly distributed on the disk area
+        let r = uv.x().sqrt();
+        let theta = uv.y() * val(2. * f32::PI());
+        x * (r * theta.cos()) + y * (r * theta.sin())
+      },
+      || {
+        let uv = uv * val(2.) - val(Vec2::one());
+        x * uv.x() + y * uv.y()
+      },
+    );
+
+    let normal = x.cross(y);
+    let area = normal.length() * is_disk.select(val(f32::PI()), val(4.));
+    let normal = normal.normalize();
+
+    let position_to_light = self.position.expand().f1 + offset - world_position;
+    let distance = position_to_light.length();
+    let sampling_dir = position_to_light / distance.splat();
+
+    // the light emits to the -z of the light node, see area_light_storage_to_ltc_light
+    let cos = sampling_dir.dot(normal);
+    let cos = self.double_side.into_bool().select(cos.abs(), cos);
+    let valid = cos.greater_than(val(0.));
+
+    let pdf = distance * distance / (area * cos.max(val(f32::EPSILON)));
+
+    let sampling = RTLightSampling {
+      sampling_dir,
+      distance,
+      pdf,
+      radiance: self.intensity,
+    };
+    (sampling, valid)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use rendiation_area_lighting::AreaLightStorage;
+  use rendiation_shader_backend_cpu::*;
+
+  use super::*;
+
+  struct FixedSampler(Node<Vec2<f32>>);
+
+  impl DeviceSampler for FixedSampler {
+    fn reset(&self, _: Node<u32>) {}
+    fn next(&self) -> Node<f32> {
+      self.0.x()
+    }
+    fn next_2d(&self) -> Node<Vec2<f32>> {
+      self.0
+    }
+  }
+
+  /// the light is at the origin and emits to -z, the size is 1 x 1
+  fn area_light(double_side: bool, is_disk: bool) -> AreaLightStorage {
+    let mut light = AreaLightStorage::default();
+    light.position = into_hpt(Vec3::zero()).into_storage();
+    light.axis_x = Vec3::new(1., 0., 0.);
+    light.axis_y = Vec3::new(0., 1., 0.);
+    light.half_size = Vec2::splat(0.5);
+    light.intensity = Vec3::new(1., 2., 3.);
+    light.double_side = double_side.into();
+    light.is_disk = is_disk.into();
+    light
+  }
+
+  struct SamplingResult {
+    direction: Vec3<f32>,
+    pdf: f32,
+    distance: f32,
+    radiance: Vec3<f32>,
+    valid: bool,
+  }
+
+  /// sample the light of the given index by the (uv, receiver position) pairs
+  fn sample_area_lights(
+    lights: &[AreaLightStorage],
+    light_index: u32,
+    samples: &[(Vec2<f32>, Vec3<f32>)],
+  ) -> Vec<SamplingResult> {
+    let input: Vec<Vec4<f32>> = samples
+      .iter()
+      .flat_map(|(uv, receiver)| [Vec4::new(uv.x, uv.y, 0., 0.), receiver.expand_with(0.)])
+      .collect();
+    let lights = CpuStorageBufferReadonlyDataView::<[AreaLightStorage]>::new(lights);
+    let input = CpuStorageBufferReadonlyDataView::<[Vec4<f32>]>::new(input.as_slice());
+    let output_len = samples.len() * 3;
+    let output =
+      CpuStorageBufferDataView::<[Vec4<f32>]>::new(vec![Vec4::zero(); output_len].as_slice());
+
+    let mut cx = cpu_compute_shader_builder().with_config_work_group_size(samples.len() as u32);
+    let lights_node = cx.bind_by(&lights);
+    let input_node = cx.bind_by(&input);
+    let output_node = cx.bind_by(&output);
+    let id = cx.global_invocation_id().x();
+    let light = lights_node.index(val(light_index)).load().expand();
+    let uv = input_node.index(id * val(2)).load().xy();
+    let receiver = input_node.index(id * val(2) + val(1)).load().xyz();
+
+    let (sampling, valid) = light.importance_sampling_light(receiver, &FixedSampler(uv));
+    let valid = valid.select(val(1.), val(0.));
+    output_node
+      .index(id * val(3))
+      .store(vec4_node((sampling.sampling_dir, sampling.pdf)));
+    output_node
+      .index(id * val(3) + val(1))
+      .store(vec4_node((sampling.radiance, sampling.distance)));
+    output_node
+      .index(id * val(3) + val(2))
+      .store(vec4_node((valid.splat::<Vec3<f32>>(), val(0.))));
+
+    let module = cx.create_cpu_module().unwrap();
+    let bindings = CpuShaderBindingBuilder::default()
+      .with_bind(&lights)
+      .with_bind(&input)
+      .with_bind(&output);
+    module.dispatch(&bindings, (1, 1, 1)).unwrap();
+
+    output
+      .read()
+      .into_vec()
+      .chunks(3)
+      .map(|r| SamplingResult {
+        direction: r[0].xyz(),
+        pdf: r[0].w,
+        radiance: r[1].xyz(),
+        distance: r[1].w,
+        valid: r[2].x > 0.5,
+      })
+      .collect()
+  }
+
+  fn assert_near(a: f32, b: f32) {
+    assert!((a - b).abs() <= b.abs().max(1.) * 1e-4, "{a} != {b}");
+  }
+
+  #[test]
+  fn test_area_light_importance_sampling() {
+    let lights = [
+      area_light(false, false),
+      area_light(true, false),
+      area_light(false, true),
+    ];
+    let front = Vec3::new(0., 0., -2.);
+    let back = Vec3::new(0., 0., 2.);
+    let samples = [
+      (Vec2::splat(0.5), front),
+      (Vec2::splat(1.), front),
+      (Vec2::splat(0.5), back),
+    ];
+
+    let rect = sample_area_lights(&lights, 0, &samples);
+    // the light center, the pdf is distance² / (area * cos)
+    assert!(rect[0].valid);
+    assert_eq!(rect[0].direction, Vec3::new(0., 0., 1.));
+    assert_near(rect[0].distance, 2.);
+    assert_near(rect[0].pdf, 4.);
+    assert_eq!(rect[0].radiance, Vec3::new(1., 2., 3.));
+    // the light corner
+    let distance = 4.5_f32.sqrt();
+    assert!(rect[1].valid);
+    assert_near(rect[1].distance, distance);
+    assert_near(rect[1].pdf, 4.5 / (2. / distance));
+    // the receiver behind the single side light is not lit
+    assert!(!rect[2].valid);
+
+    let double_side = sample_area_lights(&lights, 1, &samples);
+    assert!(double_side[2].valid);
+    assert_eq!(double_side[2].direction, Vec3::new(0., 0., -1.));
+    assert_near(double_side[2].pdf, 4.);
+
+    // the disk edge, the disk area is π / 4
+    let disk = sample_area_lights(&lights, 2, &[(Vec2::new(1., 0.), front)]);
+    let distance = 4.25_f32.sqrt();
+    assert!(disk[0].valid);
+    assert_near(disk[0].distance, distance);
+    assert_near(disk[0].pdf, 4.25 / (f32::PI() * 0.25 * (2. / distance)));
+  }
+
+  #[test]
+  fn test_area_light_sampling_pdf_is_solid_angle_measure() {
+    // the stratified estimation of the solid angle by the expectation of 1 / pdf
+    let grid = 32;
+    let receiver = Vec3::new(0.3, -0.2, -2.);
+    let samples: Vec<_> = (0..grid * grid)
+      .map(|i| {
+        let uv = Vec2::new((i % grid) as f32 + 0.5, (i / grid) as f32 + 0.5) / grid as f32;
+        (uv, receiver)
+      })
+      .collect();
+    let result = sample_area_lights(&[area_light(false, false)], 0, &samples);
+    assert!(result.iter().all(|r| r.valid));
+    let estimated = result.iter().map(|r| 1. / r.pdf).sum::<f32>() / samples.len() as f32;
+
+    // the solid angle of the rect by the sum of the signed rect corner terms
+    let corner_solid_angle = |x: f32, y: f32| {
+      let d = 2_f32;
+      (x * y / (d * (x * x + y * y + d * d).sqrt())).atan()
+    };
+    let (x0, x1) = (-0.5 - 0.3, 0.5 - 0.3);
+    let (y0, y1) = (-0.5 + 0.2, 0.5 + 0.2);
+    let expected =
+      corner_solid_angle(x1, y1) - corner_solid_angle(x0, y1) - corner_solid_angle(x1, y0)
+        + corner_solid_angle(x0, y0);
+
+    assert!(
+      (estimated - expected).abs() < expected * 1e-3,
+      "{estimated} != {expected}"
+    );
+  }
+}
@@ -1,14 +1,18 @@
+use rendiation_area_lighting::{AreaLightStorage, use_area_light_storage};
+
 use crate::*;
 
 pub fn use_scene_pt_light_source(cx: &mut QueryGPUHookCx) -> Option<ScenePTLightingSceneDataGroup> {
   let directional_lights = use_directional_light_storage(cx);
   let spot_lights = use_spot_light_storage(cx);
   let point_lights = use_point_light_storage(cx);
+  let area_lights = use_area_light_storage(cx);
 
   cx.when_render(|| ScenePTLightingSceneDataGroup {
     spot_lights: spot_lights.unwrap().into(),
     point_lights: point_lights.unwrap().into(),
     directional_lights: directional_lights.unwrap().into(),
+    area_lights: area_lights.unwrap().into(),
   })
 }
 
@@ -26,6 +30,7 @@ pub struct ScenePTLightingSceneDataGroup {
   pub spot_lights: ScenePTLightingSceneData<SpotLightStorage>,
   pub point_lights: ScenePTLightingSceneData<PointLightStorage>,
   pub directional_lights: ScenePTLightingSceneData<DirectionalLightStorage>,
+  pub area_lights: ScenePTLightingSceneData<AreaLightStorage>,
 }
 
 #[derive(Clone)]
@@ -74,20 +79,39 @@ impl DevicePathTracingLighting for ScenePTLighting {
 
     let directional_lights = cx.bind_by(&self.scene_data.directional_lights.lights);
     let accessor = self.scene_data.directional_lights.lights_accessor.build(cx);
-    let light_count = accessor.meta.index(scene_id).len().load();
+    let directional_count = accessor.meta.index(scene_id).len().load();
     let directional = ScenePTLightingInvocation {
       lights: LightingGroup {
-        strategy: Arc::new(UniformLightSamplingStrategy { light_count }),
+        strategy: Arc::new(UniformLightSamplingStrategy {
+          light_count: directional_count,
+        }),
         lights: directional_lights,
         light_access: accessor,
         scene_id,
       },
     };
 
+    let area_lights = cx.bind_by(&self.scene_data.area_lights.lights);
+    let accessor = self.scene_data.area_lights.lights_accessor.build(cx);
+    let area_count = accessor.meta.index(scene_id).len().load();
+    let area = ScenePTLightingInvocation {
+      lights: LightingGroup {
+        strategy: Arc::new(UniformLightSamplingStrategy {
+          light_count: area_count,
+        }),
+        lights: area_lights,
+        light_access: accessor,
+        scene_id,
+      },
+    };
+
     let group = ScenePTLightingInvocationGroup {
       // points,
       // spot,
       directional,
+      directional_count,
+      area,
+      area_count,
     };
 
     Box::new(group)
@@ -104,6 +128,9 @@ impl DevicePathTracingLighting for ScenePTLighting {
 
     cx.bind(&self.scene_data.directional_lights.lights);
     self.scene_data.directional_lights.lights_accessor.bind(cx);
+
+    cx.bind(&self.scene_data.area_lights.lights);
+    self.scene_data.area_lights.lights_accessor.bind(cx);
   }
 }
 
@@ -112,6 +139,9 @@ struct ScenePTLightingInvocationGroup {
   // points: ScenePTLightingInvocation<PointLightStorage>,
   // spot: ScenePTLightingInvocation<SpotLightStorage>,
   directional: ScenePTLightingInvocation<DirectionalLightStorage>,
+  directional_count: Node<u32>,
+  area: ScenePTLightingInvocation<AreaLightStorage>,
+  area_count: Node<u32>,
 }
 
 impl DevicePathTracingLightingInvocation for ScenePTLightingInvocationGroup {
@@ -120,9 +150,46 @@ impl DevicePathTracingLightingInvocation for ScenePTLightingInvocationGroup {
     world_position: Node<Vec3<f32>>,
     sampler: &dyn DeviceSampler,
   ) -> (RTLightSampling, Node<bool>) {
-    self
-      .directional
-      .importance_sampling_light(world_position, sampler)
+    // the light type is selected by the light count, so each light in the scene has the same
+    // probability to be selected
+    let light_count = self.directional_count + self.area_count;
+    let directional_pmf = light_count.equals(0).select(
+      val(0.),
+      self.directional_count.into_f32() / light_count.into_f32(),
+    );
+
+    let sampling_dir = zeroed_val::<Vec3<f32>>().make_local_var();
+    let distance = val(0.).make_local_var();
+    let pdf = val(0.).make_local_var();
+    let radiance = zeroed_val::<Vec3<f32>>().make_local_var();
+    let valid = val(false).make_local_var();
+
+    let store = |(sampling, is_valid): (RTLightSampling, Node<bool>), pmf: Node<f32>| {
+      sampling_dir.store(sampling.sampling_dir);
+      distance.store(sampling.distance);
+      pdf.store(sampling.pdf * pmf);
+      radiance.store(sampling.radiance);
+      valid.store(is_valid);
+    };
+
+    if_by(sampler.next().less_than(directional_pmf), || {
+      let sampling = self
+        .directional
+        .importance_sampling_light(world_position, sampler);
+      store(sampling, directional_pmf);
+    })
+    .else_by(|| {
+      let sampling = self.area.importance_sampling_light(world_position, sampler);
+      store(sampling, val(1.) - directional_pmf);
+    });
+
+    let sampling = RTLightSampling {
+      sampling_dir: sampling_dir.load(),
+      distance: distance.load(),
+      pdf: pdf.load(),
+      radiance: radiance.load(),
+    };
+    (sampling, valid.load())
   }
 }
 
@@ -26,6 +26,9 @@ use ray_miss::*;
 mod frame_state;
 use frame_state::*;
 
+mod probe_bake;
+pub use probe_bake::*;
+
 pub fn use_rtx_pt_sbt(cx: &mut QueryGPUHookCx, rtx: &RtxSystemCore) -> Option<(GPUSbt, bool)> {
   cx.next_scope_index();
   let (cx, sbt) = cx.use_plain_state(|| {
@@ -101,7 +104,6 @@ impl Default for PathTracingShaderHandles {
 
 pub struct DeviceReferencePathTracingRenderer {
   executor: GPURaytracingPipelineExecutor,
-  shader_handles: PathTracingShaderHandles,
   frame_state: Arc<RwLock<Option<PTRenderState>>>,
   max_ray_depth: u32,
   gpu: GPU,
@@ -111,7 +113,6 @@ impl DeviceReferencePathTracingRenderer {
   pub fn new(rtx: &RtxSystemCore, gpu: &GPU) -> Self {
     Self {
       executor: rtx.rtx_device.create_raytracing_pipeline_executor(),
-      shader_handles: Default::default(),
       frame_state: Default::default(),
       max_ray_depth: MAX_RAY_DEPTH,
       gpu: gpu.clone(),
@@ -176,52 +177,17 @@ impl DeviceReferencePathTracingRenderer {
       MAX_RAY_DEPTH as usize,
     );
 
-    let lighting = ScenePTLighting {
-      scene_data: base.lighting.clone(),
-      scene_id: base.scene_ids.get(&scene.into_raw()).unwrap().clone(),
-    };
-
-    let closest = build_ray_hit_shader(
+    let source = build_pt_pipeline_source(
       &trace_base_builder,
-      PTRayClosestCtx {
-        bindless_mesh: base.mesh.make_dispatcher(),
-        surface: Box::new(base.material.clone()),
-        config: state.config.clone(),
-        lighting: Box::new(lighting),
-      },
+      ray_gen,
+      base,
+      scene,
+      &state.config,
+      background,
+      frame.gpu,
+      self.max_ray_depth,
     );
 
-    let miss_ctx = PTRayMissCtx::new(background, scene, frame.gpu);
-    let miss = build_ray_miss_shader(&trace_base_builder, miss_ctx);
-
-    let shadow_test_closest = trace_base_builder
-      .create_closest_hit_shader_base::<ShaderTestPayload>()
-      .map(|_, cx| {
-        cx.expect_payload::<ShaderTestPayload>()
-          .radiance()
-          .store(Vec3::zero());
-      });
-
-    let shadow_miss = trace_base_builder
-      .create_miss_hit_shader_base::<ShaderTestPayload>()
-      .map(|_, _| {
-        // do nothing
-      });
-
-    let mut source = GPURaytracingPipelineAndBindingSource::default();
-    let handles = PathTracingShaderHandles {
-      ray_gen: source.register_ray_gen(ray_gen),
-      closest_hit: source.register_ray_closest_hit::<CorePathPayload>(closest, 1),
-      shadow_test_hit: source.register_ray_closest_hit::<ShaderTestPayload>(shadow_test_closest, 1),
-      miss: source.register_ray_miss::<CorePathPayload>(miss, 1),
-      shadow_test_miss: source.register_ray_miss::<ShaderTestPayload>(shadow_miss, 1),
-    };
-    assert_eq!(handles, self.shader_handles);
-
-    source.set_execution_round_hint(self.max_ray_depth * 5);
-    // this is 2 because when previous ray is reading back, there is no empty space for allocate new ray
-    source.max_in_flight_trace_ray = 2;
-
     let sbt = sbt.inner.read();
     rtx_encoder.trace_ray(
       &source,
@@ -235,6 +201,67 @@ impl DeviceReferencePathTracingRenderer {
   }
 }
 
+/// register the ray gen together with the path tracing hit and miss shaders, the shader handles
+/// match the sbt created in [use_rtx_pt_sbt].
+#[allow(clippy::too_many_arguments)]
+fn build_pt_pipeline_source(
+  trace_base_builder: &TraceFutureBaseBuilder,
+  ray_gen: impl TraceOperator<()> + 'static,
+  base: &SceneRayTracingRendererBase,
+  scene: EntityHandle<SceneEntity>,
+  config: &UniformBufferDataView<PTConfig>,
+  background: &SceneBackgroundRenderer,
+  gpu: &GPU,
+  max_ray_depth: u32,
+) -> GPURaytracingPipelineAndBindingSource {
+  let lighting = ScenePTLighting {
+    scene_data: base.lighting.clone(),
+    scene_id: base.scene_ids.get(&scene.into_raw()).unwrap().clone(),
+  };
+
+  let closest = build_ray_hit_shader(
+    trace_base_builder,
+    PTRayClosestCtx {
+      bindless_mesh: base.mesh.make_dispatcher(),
+      surface: Box::new(base.material.clone()),
+      config: config.clone(),
+      lighting: Box::new(lighting),
+    },
+  );
+
+  let miss_ctx = PTRayMissCtx::new(background, scene, gpu);
+  let miss = build_ray_miss_shader(trace_base_builder, miss_ctx);
+
+  let shadow_test_closest = trace_base_builder
+    .create_closest_hit_shader_base::<ShaderTestPayload>()
+    .map(|_, cx| {
+      cx.expect_payload::<ShaderTestPayload>()
+        .radiance()
+        .store(Vec3::zero());
+    });
+
+  let shadow_miss = trace_base_builder
+    .create_miss_hit_shader_base::<ShaderTestPayload>()
+    .map(|_, _| {
+      // do nothing
+    });
+
+  let mut source = GPURaytracingPipelineAndBindingSource::default();
+  let handles = PathTracingShaderHandles {
+    ray_gen: source.register_ray_gen(ray_gen),
+    closest_hit: source.register_ray_closest_hit::<CorePathPayload>(closest, 1),
+    shadow_test_hit: source.register_ray_closest_hit::<ShaderTestPayload>(shadow_test_closest, 1),
+    miss: source.register_ray_miss::<CorePathPayload>(miss, 1),
+    shadow_test_miss: source.register_ray_miss::<ShaderTestPayload>(shadow_miss, 1),
+  };
+  assert_eq!(handles, PathTracingShaderHandles::default());
+
+  source.set_execution_round_hint(max_ray_depth * 5);
+  // this is 2 because when previous ray is reading back, there is no empty space for allocate new ray
+  source.max_in_flight_trace_ray = 2;
+  source
+}
+
 #[derive(Clone, Copy, ShaderStruct, Default)]
 struct CorePathPayload {
   pub sampled_radiance: Vec3<f32>,
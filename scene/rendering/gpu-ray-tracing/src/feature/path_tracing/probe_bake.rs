@@ -0,0 +1,224 @@
+use std::future::Future;
+
+use rendiation_shader_library::{sampling::sample_sphere_uniform_fn, sh::*};
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightProbeBakeConfig {
+  /// the path count traced for each probe
+  pub sample_count: u32,
+  pub max_path_depth: u32,
+}
+
+impl Default for LightProbeBakeConfig {
+  fn default() -> Self {
+    Self {
+      sample_count: 1024,
+      max_path_depth: MAX_RAY_DEPTH,
+    }
+  }
+}
+
+/// the path count traced for each probe in one trace call, the rest samples are traced in the
+/// following rounds with different random sequence.
+const PROBE_SAMPLE_PER_ROUND: u32 = 64;
+
+/// Bakes the incoming radiance of light probes into sh l2 using the reference path tracer.
+///
+/// The baking reuses the hit and miss shaders of the path tracer, so the path tracing sbt
+/// created by [use_rtx_pt_sbt] should be used.
+pub struct DeviceLightProbeBaker {
+  executor: GPURaytracingPipelineExecutor,
+  gpu: GPU,
+}
+
+impl DeviceLightProbeBaker {
+  pub fn new(rtx: &RtxSystemCore, gpu: &GPU) -> Self {
+    Self {
+      executor: rtx.rtx_device.create_raytracing_pipeline_executor(),
+      gpu: gpu.clone(),
+    }
+  }
+
+  /// the probe positions are in world space. the returned future resolves the radiance sh of
+  /// each probe in the same order as the input, or None if the result read back failed.
+  #[allow(clippy::too_many_arguments)]
+  pub fn bake(
+    &self,
+    rtx_system: &dyn GPURaytracingSystem,
+    base: &SceneRayTracingRendererBase,
+    scene: EntityHandle<SceneEntity>,
+    background: &SceneBackgroundRenderer,
+    sbt: &GPUSbt,
+    probes: &[Vec3<f32>],
+    config: LightProbeBakeConfig,
+  ) -> impl Future<Output = Option<Vec<ShL2Rgb>>> + use<> {
+    let probe_count = probes.len() as u32;
+    let round_count = config.sample_count.div_ceil(PROBE_SAMPLE_PER_ROUND).max(1);
+
+    let mut round_results = Vec::new();
+    if probe_count != 0 {
+      let scene_tlas = base.scene_tlas.access(&scene.into_raw()).unwrap().clone();
+      // bind tlas, see ShaderRayTraceCall::tlas_idx.
+      rtx_system
+        .create_acceleration_structure_system()
+        .bind_tlas(&[scene_tlas.tlas_handle]);
+
+      let positions = probes
+        .iter()
+        .map(|p| Vec4::new(p.x, p.y, p.z, 1.))
+        .collect::<Vec<_>>();
+      let positions = create_gpu_readonly_storage(
+        positions.as_slice(),
+        &self.gpu,
+        "light probe bake positions",
+      );
+      let samples = create_gpu_read_write_storage::<[LightProbeBakeSample]>(
+        ZeroedArrayByArrayLength((probe_count * PROBE_SAMPLE_PER_ROUND) as usize),
+        &self.gpu,
+        "light probe bake samples",
+      );
+
+      let sbt = sbt.inner.read();
+      for round in 0..round_count {
+        let mut pt_config = PTConfig::new(config.max_path_depth);
+        pt_config.current_sample_count = round;
+        let pt_config = create_uniform(pt_config, &self.gpu, "light probe bake PTConfig");
+
+        let trace_base_builder = rtx_system.create_tracer_base_builder();
+        let ray_gen = build_ray_gen_shader(
+          &trace_base_builder,
+          LightProbeRayGenCtx {
+            positions: positions.clone(),
+            samples: samples.clone(),
+            config: pt_config.clone(),
+          },
+          config.max_path_depth as usize,
+        );
+
+        let source = build_pt_pipeline_source(
+          &trace_base_builder,
+          ray_gen,
+          base,
+          scene,
+          &pt_config,
+          background,
+          &self.gpu,
+          config.max_path_depth,
+        );
+
+        let mut rtx_encoder = rtx_system.create_raytracing_encoder();
+        rtx_encoder.trace_ray(
+          &source,
+          &self.executor,
+          (PROBE_SAMPLE_PER_ROUND, probe_count, 1),
+          (*sbt).as_ref(),
+        );
+
+        let mut encoder = self.gpu.create_encoder();
+        round_results.push(encoder.read_storage_array(&self.gpu.device, &samples));
+        self.gpu.submit_encoder(encoder);
+      }
+    }
+
+    async move {
+      let mut result = vec![[Vec3::zero(); SH_L2_COEFFICIENT_COUNT]; probe_count as usize];
+      let mut sample_counts = vec![0_u32; probe_count as usize];
+
+      for round in round_results {
+        let samples = round.await.ok()?;
+        for (i, sample) in samples.iter().enumerate() {
+          let probe = i / PROBE_SAMPLE_PER_ROUND as usize;
+          // the nan is mainly caused by 0 pdf, skip these samples
+          if sample.radiance.x.is_nan() || sample.radiance.y.is_nan() || sample.radiance.z.is_nan()
+          {
+            continue;
+          }
+          sh_l2_accumulate_radiance(&mut result[probe], sample.direction, sample.radiance);
+          sample_counts[probe] += 1;
+        }
+      }
+
+      for (sh, count) in result.iter_mut().zip(sample_counts) {
+        let scale = 4. * std::f32::consts::PI / count.max(1) as f32;
+        sh.iter_mut().for_each(|c| *c *= scale);
+      }
+
+      Some(result)
+    }
+  }
+}
+
+#[repr(C)]
+#[std430_layout]
+#[derive(Clone, Copy, ShaderStruct)]
+struct LightProbeBakeSample {
+  pub radiance: Vec3<f32>,
+  pub direction: Vec3<f32>,
+}
+
+#[derive(Clone)]
+struct LightProbeRayGenCtx {
+  positions: StorageBufferReadonlyDataView<[Vec4<f32>]>,
+  samples: StorageBufferDataView<[LightProbeBakeSample]>,
+  config: UniformBufferDataView<PTConfig>,
+}
+
+impl ShaderHashProvider for LightProbeRayGenCtx {
+  shader_hash_type_id! {}
+}
+
+impl RayTracingCustomCtxProvider for LightProbeRayGenCtx {
+  type Invocation = LightProbeRayGenCtxInvocation;
+
+  fn build_invocation(&self, cx: &mut ShaderBindGroupBuilder) -> Self::Invocation {
+    LightProbeRayGenCtxInvocation {
+      positions: cx.bind_by(&self.positions),
+      samples: cx.bind_by(&self.samples),
+      config: cx.bind_by(&self.config),
+    }
+  }
+
+  fn bind(&self, builder: &mut BindingBuilder) {
+    builder.bind(&self.positions);
+    builder.bind(&self.samples);
+    builder.bind(&self.config);
+  }
+}
+
+/// the launch x is the sample index of the probe, the launch y is the probe index.
+#[derive(Clone)]
+struct LightProbeRayGenCtxInvocation {
+  positions: ShaderReadonlyPtrOf<[Vec4<f32>]>,
+  samples: ShaderPtrOf<[LightProbeBakeSample]>,
+  config: ShaderReadonlyPtrOf<PTConfig>,
+}
+
+impl LightProbeRayGenCtxInvocation {
+  fn sample(&self, launch: &PTLaunchInfo) -> ShaderPtrOf<LightProbeBakeSample> {
+    let index = launch.launch_id.y() * launch.launch_size.x() + launch.launch_id.x();
+    self.samples.index(index)
+  }
+}
+
+impl PTRayGenSourceInvocation for LightProbeRayGenCtxInvocation {
+  fn sample_index(&self) -> Node<u32> {
+    self.config.current_sample_count().load()
+  }
+
+  fn max_path_depth(&self) -> Node<u32> {
+    self.config.max_path_depth().load()
+  }
+
+  fn generate_primary_ray(&self, launch: &PTLaunchInfo, sampler: &dyn DeviceSampler) -> ShaderRay {
+    let origin = self.positions.index(launch.launch_id.y()).load().xyz();
+    let direction = sample_sphere_uniform_fn(sampler.next_2d());
+    self.sample(launch).direction().store(direction);
+    ShaderRay { origin, direction }
+  }
+
+  fn write_result(&self, launch: &PTLaunchInfo, radiance: Node<Vec3<f32>>) {
+    self.sample(launch).radiance().store(radiance);
+  }
+}
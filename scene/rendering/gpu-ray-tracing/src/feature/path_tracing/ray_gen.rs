@@ -1,30 +1,62 @@
+use std::marker::PhantomData;
+
 use anymap::AnyMap;
 use rendiation_texture_gpu_process::ToneMapInvocation;
 
 use super::*;
 
-pub fn build_ray_gen_shader(
+/// The camera view rendering and the light probe baking share the same path integrator, they only
+/// differ in how the primary ray is generated and how the path radiance is consumed.
+pub trait PTRayGenSourceInvocation: Clone + 'static {
+  fn sample_index(&self) -> Node<u32>;
+  fn max_path_depth(&self) -> Node<u32>;
+  fn generate_primary_ray(&self, launch: &PTLaunchInfo, sampler: &dyn DeviceSampler) -> ShaderRay;
+  /// called when the path is terminated
+  fn write_result(&self, launch: &PTLaunchInfo, radiance: Node<Vec3<f32>>);
+}
+
+pub struct PTLaunchInfo {
+  pub launch_id: Node<Vec3<u32>>,
+  pub launch_size: Node<Vec3<u32>>,
+}
+
+pub fn build_ray_gen_shader<C>(
   base: &TraceFutureBaseBuilder,
-  ctx: PTRayGenCtx,
+  ctx: C,
   max_trace_depth: usize,
-) -> impl TraceOperator<()> + 'static {
-  PTRayGen {
+) -> impl TraceOperator<()> + 'static
+where
+  C: RayTracingCustomCtxProvider,
+  C::Invocation: PTRayGenSourceInvocation,
+{
+  PTRayGen::<C::Invocation> {
     internal: Box::new(base.create_ray_gen_shader_base().inject_ctx(ctx)),
     max_trace_depth,
+    source: PhantomData,
   }
 }
 
-#[derive(Clone)]
-struct PTRayGen {
+struct PTRayGen<S> {
   internal: Box<dyn TraceOperator<()>>,
   max_trace_depth: usize,
+  source: PhantomData<fn() -> S>,
 }
 
-impl ShaderHashProvider for PTRayGen {
+impl<S> Clone for PTRayGen<S> {
+  fn clone(&self) -> Self {
+    Self {
+      internal: self.internal.clone(),
+      max_trace_depth: self.max_trace_depth,
+      source: PhantomData,
+    }
+  }
+}
+
+impl<S: 'static> ShaderHashProvider for PTRayGen<S> {
   shader_hash_type_id! {}
 }
 
-impl NativeRayTracingShaderBuilder for PTRayGen {
+impl<S: PTRayGenSourceInvocation> NativeRayTracingShaderBuilder for PTRayGen<S> {
   type Output = ();
   fn build(&self, _: &mut dyn NativeRayTracingShaderCtx) -> Self::Output {
     unimplemented!()
@@ -34,26 +66,28 @@ impl NativeRayTracingShaderBuilder for PTRayGen {
   }
 }
 
-impl ShaderFutureProvider for PTRayGen {
+impl<S: PTRayGenSourceInvocation> ShaderFutureProvider for PTRayGen<S> {
   type Output = ();
 
   fn build_device_future(&self, ctx: &mut AnyMap) -> DynShaderFuture<Self::Output> {
-    PTRayGenShaderFuture {
+    PTRayGenShaderFuture::<S> {
       internal: self.internal.build_device_future(ctx),
       max_trace_depth: self.max_trace_depth,
+      source: PhantomData,
     }
     .into_dyn()
   }
 }
 
-struct PTRayGenShaderFuture {
+struct PTRayGenShaderFuture<S> {
   internal: DynShaderFuture<()>,
   max_trace_depth: usize,
+  source: PhantomData<fn() -> S>,
 }
-impl ShaderFuture for PTRayGenShaderFuture {
+impl<S: PTRayGenSourceInvocation> ShaderFuture for PTRayGenShaderFuture<S> {
   type Output = ();
 
-  type Invocation = PTRayGenShaderFutureInvocation;
+  type Invocation = PTRayGenShaderFutureInvocation<S>;
 
   fn required_poll_count(&self) -> usize {
     self.internal.required_poll_count() + self.max_trace_depth
@@ -68,6 +102,7 @@ impl ShaderFuture for PTRayGenShaderFuture {
         .state_builder
         .create_or_reconstruct_inline_state_with_default(Vec3::one()),
       radiance: ctx.make_state::<Node<Vec3<f32>>>(),
+      source: PhantomData,
     }
   }
 
@@ -76,15 +111,16 @@ impl ShaderFuture for PTRayGenShaderFuture {
   }
 }
 
-struct PTRayGenShaderFutureInvocation {
+struct PTRayGenShaderFutureInvocation<S> {
   upstream: Box<dyn ShaderFutureInvocation<Output = ()>>,
   current_flying_ray: TracingFutureInvocation<CorePathPayload>,
   current_depth: BoxedShaderLoadStore<Node<u32>>,
   current_throughput: BoxedShaderLoadStore<Node<Vec3<f32>>>,
   radiance: BoxedShaderLoadStore<Node<Vec3<f32>>>,
+  source: PhantomData<fn() -> S>,
 }
 
-impl ShaderFutureInvocation for PTRayGenShaderFutureInvocation {
+impl<S: PTRayGenSourceInvocation> ShaderFutureInvocation for PTRayGenShaderFutureInvocation<S> {
   type Output = ();
   fn device_poll(&self, ctx: &mut DeviceTaskSystemPollCtx) -> ShaderPoll<Self::Output> {
     let ray_origin = zeroed_val::<Vec3<f32>>().make_local_var();
@@ -93,23 +129,23 @@ impl ShaderFutureInvocation for PTRayGenShaderFutureInvocation {
 
     let rt_ctx = ctx.invocation_registry.get::<TracingCtx>().unwrap();
     let rg_cx = rt_ctx.expect_ray_gen_ctx();
-    let image_position = rg_cx.launch_id().xy();
-    let image_size = rg_cx.launch_size().xy();
-    let cx = rt_ctx.expect_custom_cx::<PTRayGenCtxInvocation>();
-    let result_buffer = cx.result_buffer;
-    let tonemap = cx.tonemap.clone();
-    let sample_count = cx.config.current_sample_count().load();
+    let launch = PTLaunchInfo {
+      launch_id: rg_cx.launch_id(),
+      launch_size: rg_cx.launch_size(),
+    };
+    let cx = rt_ctx.expect_custom_cx::<S>().clone();
+    let sample_count = cx.sample_index();
 
     if_by(r.is_resolved(), || {
       // generate primary ray
       let sampler = &PCGRandomSampler::from_ray_ctx_and_sample_index(rg_cx, sample_count);
 
-      let ray = cx.camera.generate_ray(image_position, image_size, sampler);
+      let ray = cx.generate_primary_ray(&launch, sampler);
       ray_origin.store(ray.origin);
       ray_dir.store(ray.direction);
     });
 
-    let max_depth = cx.config.max_path_depth().load();
+    let max_depth = cx.max_path_depth();
     let current_depth = self.current_depth.abstract_load().make_local_var();
     let radiance = self.radiance.abstract_load().make_local_var();
     let fly_ray = self.current_flying_ray.device_poll(ctx);
@@ -188,24 +224,7 @@ impl ShaderFutureInvocation for PTRayGenShaderFutureInvocation {
 
     let final_resolved = require_more_tracing.not();
     if_by(final_resolved, || {
-      let averaged_result = result_buffer.load_texel(image_position).xyz();
-
-      let sample_radiance = radiance.load();
-      let ldr_result = tonemap.compute_ldr(sample_radiance);
-
-      // we not enable this is to see if anything cause nan besides for 0 pdf
-      // let is_nan = sample_result
-      //   .x()
-      //   .is_nan()
-      //   .or(sample_result.y().is_nan())
-      //   .or(sample_result.z().is_nan());
-      // let sample_result = is_nan.select(averaged_result, ldr_result);
-
-      let sample_count = sample_count.into_f32();
-      let updated_average =
-        (averaged_result * sample_count + ldr_result) / (sample_count + val(1.)).splat();
-
-      result_buffer.write_texel(image_position, (updated_average, val(1.)).into());
+      cx.write_result(&launch, radiance.load());
     });
 
     r.resolved.store(final_resolved);
@@ -254,3 +273,44 @@ pub struct PTRayGenCtxInvocation {
   config: ShaderReadonlyPtrOf<PTConfig>,
   tonemap: ToneMapInvocation,
 }
+
+impl PTRayGenSourceInvocation for PTRayGenCtxInvocation {
+  fn sample_index(&self) -> Node<u32> {
+    self.config.current_sample_count().load()
+  }
+
+  fn max_path_depth(&self) -> Node<u32> {
+    self.config.max_path_depth().load()
+  }
+
+  fn generate_primary_ray(&self, launch: &PTLaunchInfo, sampler: &dyn DeviceSampler) -> ShaderRay {
+    let image_position = launch.launch_id.xy();
+    let image_size = launch.launch_size.xy();
+    self
+      .camera
+      .generate_ray(image_position, image_size, sampler)
+  }
+
+  fn write_result(&self, launch: &PTLaunchInfo, radiance: Node<Vec3<f32>>) {
+    let image_position = launch.launch_id.xy();
+    let averaged_result = self.result_buffer.load_texel(image_position).xyz();
+
+    let ldr_result = self.tonemap.compute_ldr(radiance);
+
+    // we not enable this is to see if anything cause nan besides for 0 pdf
+    // let is_nan = sample_result
+    //   .x()
+    //   .is_nan()
+    //   .or(sample_result.y().is_nan())
+    //   .or(sample_result.z().is_nan());
+    // let sample_result = is_nan.select(averaged_result, ldr_result);
+
+    let sample_count = self.sample_index().into_f32();
+    let updated_average =
+      (averaged_result * sample_count + ldr_result) / (sample_count + val(1.)).splat();
+
+    self
+      .result_buffer
+      .write_texel(image_position, (updated_average, val(1.)).into());
+  }
+}
@@ -10,12 +10,14 @@ impl SceneMaterialSurfaceSupport for PbrMRMaterialIndirectRenderer {
     Box::new(PbrMRMaterialRtxInvocation {
       storage: cx.bind_by(&self.storages),
       texture_storages: cx.bind_by(&self.tex_storages),
+      texture_transform_storages: cx.bind_by(&self.tex_transform_storages),
     })
   }
 
   fn bind(&self, cx: &mut BindingBuilder) {
     cx.bind(&self.storages);
     cx.bind(&self.tex_storages);
+    cx.bind(&self.tex_transform_storages);
   }
 }
 
@@ -23,6 +25,8 @@ struct PbrMRMaterialRtxInvocation {
   pub storage: ShaderReadonlyPtrOf<[PhysicalMetallicRoughnessMaterialStorage]>,
   pub texture_storages:
     ShaderReadonlyPtrOf<[PhysicalMetallicRoughnessMaterialTextureHandlesStorage]>,
+  pub texture_transform_storages:
+    ShaderReadonlyPtrOf<[PhysicalMetallicRoughnessMaterialTextureTransformsStorage]>,
 }
 
 impl SceneMaterialSurfaceSupportInvocation for PbrMRMaterialRtxInvocation {
@@ -35,6 +39,7 @@ impl SceneMaterialSurfaceSupportInvocation for PbrMRMaterialRtxInvocation {
   ) {
     let storage = self.storage.index(id).load().expand();
     let tex_storage = self.texture_storages.index(id).load().expand();
+    let tex_transform = self.texture_transform_storages.index(id).load().expand();
 
     let mut alpha = storage.alpha;
     let mut base_color = storage.base_color;
@@ -43,7 +48,7 @@ impl SceneMaterialSurfaceSupportInvocation for PbrMRMaterialRtxInvocation {
       textures,
       reg,
       tex_storage.base_color_alpha_texture,
-      uv,
+      tex_transform.base_color_alpha_transform.expand().apply(uv),
       val(Vec4::one()),
     );
     alpha *= base_color_alpha_tex.w();
@@ -56,7 +61,10 @@ impl SceneMaterialSurfaceSupportInvocation for PbrMRMaterialRtxInvocation {
       textures,
       reg,
       tex_storage.metallic_roughness_texture,
-      uv,
+      tex_transform
+        .metallic_roughness_transform
+        .expand()
+        .apply(uv),
       val(Vec4::one()),
     );
 
@@ -68,14 +76,47 @@ impl SceneMaterialSurfaceSupportInvocation for PbrMRMaterialRtxInvocation {
       textures,
       reg,
       tex_storage.emissive_texture,
-      uv,
+      tex_transform.emissive_transform.expand().apply(uv),
       val(Vec4::one()),
     )
     .xyz();
 
     reg.register_fragment_stage::<ColorChannel>(base_color);
-    reg.register_fragment_stage::<EmissiveChannel>(emissive);
+    reg.register_fragment_stage::<EmissiveChannel>(emissive * storage.emissive_strength);
     reg.register_fragment_stage::<MetallicChannel>(metallic);
     reg.register_fragment_stage::<RoughnessChannel>(roughness);
+    // the clearcoat and sheen layer is not supported in path tracing yet
+    reg.register_fragment_stage::<IorChannel>(storage.ior);
+    let sample = |handles, transform: Node<TextureTransformRows>| {
+      indirect_sample(
+        textures,
+        reg,
+        handles,
+        transform.expand().apply(uv),
+        val(Vec4::one()),
+      )
+    };
+    let specular_factor = storage.specular_factor
+      * sample(
+        tex_storage.specular_texture,
+        tex_transform.specular_transform,
+      )
+      .w();
+    let specular_color = storage.specular_color
+      * sample(
+        tex_storage.specular_color_texture,
+        tex_transform.specular_color_transform,
+      )
+      .xyz();
+    let transmission = storage.transmission
+      * sample(
+        tex_storage.transmission_texture,
+        tex_transform.transmission_transform,
+      )
+      .x();
+
+    reg.register_fragment_stage::<SpecularFactorChannel>(specular_factor);
+    reg.register_fragment_stage::<SpecularColorChannel>(specular_color);
+    reg.register_fragment_stage::<TransmissionChannel>(transmission);
   }
 }
@@ -0,0 +1,467 @@
+use std::{path::PathBuf, sync::Arc};
+
+use rayon::prelude::*;
+use rendiation_geometry::Sphere;
+use rendiation_mesh_core::{
+  AttributeSemantic, CommonMeshBuffer, CommonVertex, MeshPrimitiveTopology,
+};
+use rendiation_mesh_lod_graph::*;
+use rendiation_uri_streaming::LoaderFunction;
+
+use crate::*;
+
+#[repr(C)]
+#[std430_layout]
+#[derive(Debug, Clone, PartialEq, Copy, ShaderStruct, Default)]
+pub struct MeshLODGraphVertex {
+  /// xyz is the position, w is the u of the uv
+  pub position_u: Vec4<f32>,
+  /// xyz is the normal, w is the v of the uv
+  pub normal_v: Vec4<f32>,
+}
+
+#[repr(C)]
+#[std430_layout]
+#[derive(Debug, Clone, PartialEq, Copy, ShaderStruct, Default)]
+pub struct MeshLODGraphMeshlet {
+  /// Relative to the mesh itself's all level's indices, not the global indices pool.
+  pub index_offset: u32,
+  pub index_count: u32,
+  /// the simplification error of the meshlet itself in mesh's local space, zero for the finest level
+  pub self_error: f32,
+  /// the simplification error of the coarser meshlets simplified from the meshlet's group,
+  /// [f32::MAX] if the meshlet is in the coarsest level
+  pub parent_error: f32,
+  /// the bounding sphere of the meshlet for culling, xyz is the center, w is the radius
+  pub bounding: Vec4<f32>,
+  /// the sphere to project the self error, same layout as bounding
+  pub self_lod_sphere: Vec4<f32>,
+  /// the sphere to project the parent error, same layout as bounding
+  pub parent_lod_sphere: Vec4<f32>,
+}
+
+/// The resident pages of the lod graph are flattened into one mesh.
+///
+/// The finest complete level's indices are placed at the start, so it can be drawn directly
+/// without any lod selection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshLODGraphGPUData {
+  pub meshlets: ExternalRefPtr<Vec<MeshLODGraphMeshlet>>,
+  pub vertices: ExternalRefPtr<Vec<MeshLODGraphVertex>>,
+  /// the index is relative to the mesh's vertex range
+  pub indices: ExternalRefPtr<Vec<u32>>,
+  pub complete_level_index_count: u32,
+}
+
+pub type MeshLODGraphGPUDataChanges = Arc<LinearBatchChanges<RawEntityHandle, MeshLODGraphGPUData>>;
+
+/// check if the mesh can be rendered by the mesh lod graph renderer. the not applicable mesh should
+/// be rendered by other renderer.
+pub fn is_mesh_lod_graph_applicable(
+  mesh: &AttributesMeshWithVertexRelationInfo,
+  config: &MeshLODGraphRendererConfig,
+) -> bool {
+  // the meshlet segmentation relies on metis, which is not available in wasm
+  if cfg!(target_family = "wasm") {
+    return false;
+  }
+  if mesh.mode != MeshPrimitiveTopology::TriangleList {
+    return false;
+  }
+  let Some(indices) = &mesh.indices else {
+    return false;
+  };
+  if indices.count == 0 || indices.count / 3 < config.min_triangle_count {
+    return false;
+  }
+  let byte_per_item = indices.byte_view().len() / indices.count;
+  if byte_per_item != 2 && byte_per_item != 4 {
+    return false;
+  }
+  mesh
+    .vertices
+    .iter()
+    .any(|v| v.semantic == AttributeSemantic::Positions)
+}
+
+declare_component!(
+  AttributesMeshPrebuiltLODGraphAsset,
+  AttributesMeshEntity,
+  Option<String>
+);
+
+/// The lod graph packed in the page asset, the pages are streamed to the device by
+/// [use_mesh_lod_graph_page_streaming].
+#[derive(Clone)]
+pub struct MeshLODGraphStreamingAsset {
+  pub info: Arc<MeshLODGraphAssetInfo>,
+  pub source: MeshLODGraphStreamingAssetSource,
+}
+
+#[derive(Clone)]
+pub enum MeshLODGraphStreamingAssetSource {
+  /// the asset built at runtime, the whole asset lives in memory
+  InMemory(Arc<Vec<u8>>),
+  /// the prebuilt asset file, only the info is loaded and the pages are read on demand
+  File(Arc<PathBuf>),
+}
+
+impl MeshLODGraphStreamingAsset {
+  pub fn new(graph: &MeshLODGraph) -> Result<Self, MeshLODGraphAssetError> {
+    let config = MeshLODGraphAssetBuildConfig {
+      // the asset is never stored, prefer the fast compression
+      compression_level: 1,
+      ..Default::default()
+    };
+    let data = write_mesh_lod_graph_asset(graph, &config);
+    let info = MeshLODGraphAssetInfo::parse(&data)?;
+    Ok(Self {
+      info: Arc::new(info),
+      source: MeshLODGraphStreamingAssetSource::InMemory(Arc::new(data)),
+    })
+  }
+
+  pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, MeshLODGraphAssetError> {
+    let path = path.into();
+    let info = MeshLODGraphAssetInfo::read_from_file(&path)?;
+    Ok(Self {
+      info: Arc::new(info),
+      source: MeshLODGraphStreamingAssetSource::File(Arc::new(path)),
+    })
+  }
+
+  pub fn create_page_loader(
+    &self,
+  ) -> Box<LoaderFunction<MeshLODGraphPageRequest, MeshLODGraphPage>> {
+    match &self.source {
+      MeshLODGraphStreamingAssetSource::InMemory(data) => {
+        create_mesh_lod_graph_in_memory_page_loader(self.info.clone(), data.clone())
+      }
+      MeshLODGraphStreamingAssetSource::File(path) => {
+        create_mesh_lod_graph_file_page_loader(self.info.clone(), path.clone())
+      }
+    }
+  }
+}
+
+pub type MeshLODGraphStreamingAssetChanges =
+  Arc<LinearBatchChanges<RawEntityHandle, MeshLODGraphStreamingAsset>>;
+
+/// convert the applicable meshes into lod graph, the not applicable or not loaded meshes are
+/// emitted as removed.
+///
+/// If the mesh references a prebuilt asset by [AttributesMeshPrebuiltLODGraphAsset], the pages
+/// are streamed from the asset file, the lod graph is only built at runtime if the mesh has no
+/// prebuilt asset or the asset can not be read. The reference is read when the mesh data
+/// changes, so it should be written together with the mesh data.
+pub fn use_mesh_lod_graph_conversion(
+  cx: &mut impl DBHookCxLike,
+  mesh_changes: UseResult<AttributesMeshDataChangeInput>,
+  config: &MeshLODGraphRendererConfig,
+) -> UseResult<MeshLODGraphStreamingAssetChanges> {
+  let spawner = cx.spawner();
+  let config = config.clone();
+  let prebuilt = cx.use_dual_query::<AttributesMeshPrebuiltLODGraphAsset>();
+  mesh_changes.join(prebuilt).map_spawn_stage_in_thread(
+    cx,
+    |(meshes_changes, _)| meshes_changes.has_change(),
+    move |(meshes_changes, prebuilt)| {
+      let spawner = spawner.unwrap();
+      let prebuilt = prebuilt.view();
+
+      let mut removed = meshes_changes.removed.clone();
+      let mut items = Vec::new();
+      for (id, mesh) in meshes_changes.iter_update_or_insert() {
+        match mesh.if_loaded_ref() {
+          Some(mesh) if is_mesh_lod_graph_applicable(mesh, &config) => {
+            let prebuilt = prebuilt.access(&id).flatten();
+            items.push((id, mesh.clone(), prebuilt))
+          }
+          _ => removed.push(id),
+        }
+      }
+
+      // the graph building of each mesh is independent and expensive, run them in parallel
+      // in the project's own rayon pool instead of the global one
+      let update_or_insert = spawner.install(|| {
+        items
+          .into_par_iter()
+          .filter_map(|(id, mesh, prebuilt)| {
+            if let Some(path) = prebuilt {
+              match MeshLODGraphStreamingAsset::from_file(&path) {
+                Ok(asset) => return Some((id, asset)),
+                Err(e) => log::warn!(
+                  "failed to read prebuilt mesh lod graph asset {path}: {e}, fallback to runtime build"
+                ),
+              }
+            }
+
+            let mesh = read_common_mesh(&mesh)?;
+            let graph = build_lod_graph_or_fallback(mesh);
+            match MeshLODGraphStreamingAsset::new(&graph) {
+              Ok(asset) => Some((id, asset)),
+              Err(e) => {
+                log::error!("failed to create mesh lod graph streaming asset: {e}");
+                None
+              }
+            }
+          })
+          .collect()
+      });
+
+      Arc::new(LinearBatchChanges {
+        removed,
+        update_or_insert,
+      })
+    },
+  )
+}
+
+fn read_common_mesh(mesh: &AttributesMeshWithVertexRelationInfo) -> Option<CommonMeshBuffer> {
+  let indices = mesh.indices.as_ref()?;
+  let indices_bytes = indices.byte_view();
+  let indices: Vec<u32> = if indices_bytes.len() / indices.count == 2 {
+    let indices = bytemuck::try_cast_slice::<u8, u16>(indices_bytes).ok()?;
+    indices.iter().map(|v| *v as u32).collect()
+  } else {
+    bytemuck::try_cast_slice::<u8, u32>(indices_bytes)
+      .ok()?
+      .to_vec()
+  };
+
+  let read = |semantic: AttributeSemantic| {
+    mesh
+      .vertices
+      .iter()
+      .find(|v| v.semantic == semantic)
+      .map(|v| v.data.byte_view())
+  };
+
+  let positions =
+    bytemuck::try_cast_slice::<u8, Vec3<f32>>(read(AttributeSemantic::Positions)?).ok()?;
+  // the optional attributes are treated as missing if the layout is not expected
+  let normals = read(AttributeSemantic::Normals)
+    .and_then(|v| bytemuck::try_cast_slice::<u8, Vec3<f32>>(v).ok())
+    .filter(|v| v.len() == positions.len());
+  let uvs = read(AttributeSemantic::TexCoords(0))
+    .and_then(|v| bytemuck::try_cast_slice::<u8, Vec2<f32>>(v).ok())
+    .filter(|v| v.len() == positions.len());
+
+  if indices.iter().any(|i| *i as usize >= positions.len()) {
+    return None;
+  }
+
+  let vertices = positions
+    .iter()
+    .enumerate()
+    .map(|(i, position)| CommonVertex {
+      position: *position,
+      normal: normals.map(|n| n[i]).unwrap_or(Vec3::zero()),
+      uv: uvs.map(|uv| uv[i]).unwrap_or(Vec2::zero()),
+    })
+    .collect();
+
+  Some(CommonMeshBuffer { indices, vertices })
+}
+
+fn build_lod_graph_or_fallback(mesh: CommonMeshBuffer) -> MeshLODGraph {
+  match (DefaultMeshLODBuilder {}).build_from_mesh(mesh.clone()) {
+    Ok(graph) if !graph.levels.is_empty() => graph,
+    Ok(_) => {
+      log::warn!("mesh lod graph has no level, fallback to single meshlet");
+      single_meshlet_lod_graph(mesh)
+    }
+    Err(e) => {
+      log::warn!("failed to build mesh lod graph: {e}, fallback to single meshlet");
+      single_meshlet_lod_graph(mesh)
+    }
+  }
+}
+
+/// the whole mesh as one meshlet, the meshlet is always selected
+pub(crate) fn single_meshlet_lod_graph(mesh: CommonMeshBuffer) -> MeshLODGraph {
+  let bounding = Sphere::from_points(mesh.vertices.iter().map(|v| v.position));
+  let group = MeshletGroup {
+    meshlets: OffsetSize { offset: 0, size: 1 },
+    lod_error_simplify_to_next_level: 0.,
+    max_meshlet_simplification_error_among_meshlet_in_their_parent_group: 0.,
+    union_meshlet_bounding_among_meshlet_in_their_parent_group: bounding,
+  };
+  let meshlet = Meshlet {
+    group_index: 0,
+    group_index_in_previous_level: u32::MAX,
+    index_range: OffsetSize {
+      offset: 0,
+      size: mesh.indices.len() as u32,
+    },
+    bounding_in_local: bounding,
+  };
+  MeshLODGraph {
+    levels: vec![MeshLODGraphLevel {
+      groups: vec![group],
+      meshlets: vec![meshlet],
+      mesh,
+    }],
+  }
+}
+
+/// The meshlet selection on the resident mesh is same as the full lod graph, see the page
+/// meshlet in [MeshLODGraphPageMeshlet].
+pub(crate) fn flatten_resident_mesh(resident: &MeshLODGraphResidentMesh) -> MeshLODGraphGPUData {
+  let mesh = &resident.mesh;
+  let meshlets = mesh
+    .meshlets
+    .iter()
+    .map(|m| MeshLODGraphMeshlet {
+      index_offset: m.index_offset,
+      index_count: m.index_count,
+      self_error: m.self_error,
+      parent_error: m.parent_error,
+      bounding: m.bounding.into(),
+      self_lod_sphere: m.self_lod_sphere.into(),
+      parent_lod_sphere: m.parent_lod_sphere.into(),
+      ..Default::default()
+    })
+    .collect();
+  let vertices = mesh
+    .vertices
+    .iter()
+    .map(|v| MeshLODGraphVertex {
+      position_u: Vec4::new(v.position.x, v.position.y, v.position.z, v.uv.x),
+      normal_v: Vec4::new(v.normal.x, v.normal.y, v.normal.z, v.uv.y),
+      ..Default::default()
+    })
+    .collect();
+
+  MeshLODGraphGPUData {
+    meshlets: ExternalRefPtr::new(meshlets),
+    vertices: ExternalRefPtr::new(vertices),
+    indices: ExternalRefPtr::new(mesh.indices.clone()),
+    complete_level_index_count: resident.complete_level_index_count,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn build_test_sphere() -> CommonMeshBuffer {
+    let segments = 32;
+    let rings = 16;
+    let mut vertices = Vec::new();
+    for r in 0..=rings {
+      let phi = std::f32::consts::PI * r as f32 / rings as f32;
+      for s in 0..=segments {
+        let theta = std::f32::consts::TAU * s as f32 / segments as f32;
+        let normal = Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin());
+        vertices.push(CommonVertex {
+          position: normal,
+          normal,
+          uv: Vec2::new(s as f32 / segments as f32, r as f32 / rings as f32),
+        });
+      }
+    }
+    let mut indices = Vec::new();
+    let row = segments + 1;
+    for r in 0..rings {
+      for s in 0..segments {
+        let a = r * row + s;
+        let b = a + row;
+        indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+      }
+    }
+    CommonMeshBuffer { indices, vertices }.deduplicate_indices_and_remove_unused_vertices()
+  }
+
+  /// stream in all pages, the finest level should be complete
+  fn stream_all(graph: &MeshLODGraph) -> MeshLODGraphGPUData {
+    stream_all_from(MeshLODGraphStreamingAsset::new(graph).unwrap())
+  }
+
+  fn stream_all_from(asset: MeshLODGraphStreamingAsset) -> MeshLODGraphGPUData {
+    let mut loader = asset.create_page_loader();
+    let mut streaming = MeshLODGraphPageStreaming::new(asset.info, u64::MAX);
+    let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+    streaming.require_all_pages();
+    while streaming.is_loading() {
+      streaming.poll_loading(&mut cx, &mut loader);
+    }
+    let resident = streaming.build_resident_mesh();
+    assert_eq!(resident.complete_level, Some(0));
+    flatten_resident_mesh(&resident)
+  }
+
+  /// select on host with the error itself as the projected error
+  fn select(data: &MeshLODGraphGPUData, threshold: f32) -> Vec<MeshLODGraphMeshlet> {
+    data
+      .meshlets
+      .iter()
+      .filter(|m| m.self_error <= threshold && m.parent_error > threshold)
+      .copied()
+      .collect()
+  }
+
+  #[test]
+  fn test_flatten_lod_graph() {
+    let mesh = build_test_sphere();
+    let origin_index_count = mesh.indices.len() as u32;
+    let data = stream_all(&build_lod_graph_or_fallback(mesh));
+
+    assert_eq!(data.complete_level_index_count, origin_index_count);
+    for m in data.meshlets.iter() {
+      assert!(m.self_error <= m.parent_error);
+      assert!(m.index_offset + m.index_count <= data.indices.len() as u32);
+    }
+    assert!(
+      data
+        .indices
+        .iter()
+        .all(|i| (*i as usize) < data.vertices.len())
+    );
+
+    // zero threshold selects the finest level only
+    let finest = select(&data, 0.);
+    let finest_count: u32 = finest.iter().map(|m| m.index_count).sum();
+    assert!(finest_count <= origin_index_count);
+
+    // the infinite threshold selects the coarsest level only
+    let coarsest = select(&data, f32::MAX / 2.);
+    assert!(!coarsest.is_empty());
+    assert!(coarsest.iter().all(|m| m.parent_error == f32::MAX));
+  }
+
+  #[test]
+  fn test_single_meshlet_fallback() {
+    let mesh = build_test_sphere();
+    let origin_index_count = mesh.indices.len() as u32;
+    let data = stream_all(&single_meshlet_lod_graph(mesh));
+
+    assert_eq!(data.meshlets.len(), 1);
+    assert_eq!(data.complete_level_index_count, origin_index_count);
+    assert_eq!(select(&data, 0.).len(), 1);
+    assert_eq!(select(&data, f32::MAX / 2.).len(), 1);
+  }
+
+  #[test]
+  fn test_prebuilt_asset_file_streaming() {
+    let graph = build_lod_graph_or_fallback(build_test_sphere());
+    let runtime = stream_all(&graph);
+
+    let path = std::env::temp_dir().join("scene_mesh_lod_graph_prebuilt_asset_test.lodgraph");
+    let data = write_mesh_lod_graph_asset(&graph, &Default::default());
+    std::fs::write(&path, data).unwrap();
+
+    let asset = MeshLODGraphStreamingAsset::from_file(&path).unwrap();
+    assert!(matches!(
+      asset.source,
+      MeshLODGraphStreamingAssetSource::File(_)
+    ));
+    let prebuilt = stream_all_from(asset);
+    assert_eq!(*prebuilt.meshlets, *runtime.meshlets);
+    assert_eq!(*prebuilt.vertices, *runtime.vertices);
+    assert_eq!(*prebuilt.indices, *runtime.indices);
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(MeshLODGraphStreamingAsset::from_file(&path).is_err());
+  }
+}
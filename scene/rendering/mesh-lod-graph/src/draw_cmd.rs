@@ -0,0 +1,94 @@
+use crate::*;
+
+/// The draw command creator without the meshlet selection, the finest complete level of the
+/// resident pages is drawn as a whole. This is used by the host driven draw and other place that requires
+/// the per scene model draw command.
+#[derive(Clone)]
+pub(crate) struct MeshLODGraphDrawCommandCreator {
+  pub(crate) meta: AbstractReadonlyStorageBuffer<[MeshLODGraphMeshMeta]>,
+  pub(crate) meta_host: LockReadGuardHolder<SparseStorageBufferWithHostRaw<MeshLODGraphMeshMeta>>,
+  pub(crate) sm_to_mesh: BoxedDynQuery<RawEntityHandle, RawEntityHandle>,
+  pub(crate) sm_to_mesh_device: AbstractReadonlyStorageBuffer<[u32]>,
+}
+
+impl IndexedDrawCommandBuilder for MeshLODGraphDrawCommandCreator {
+  fn draw_command_host_access(&self, id: EntityHandle<SceneModelEntity>) -> Option<DrawCommand> {
+    let mesh_id = self.sm_to_mesh.access(&id.into_raw())?;
+    let meta = self.meta_host.get(mesh_id.alloc_index())?;
+
+    if meta.indices.x == DEVICE_RANGE_ALLOCATE_FAIL_MARKER
+      || meta.vertices.x == DEVICE_RANGE_ALLOCATE_FAIL_MARKER
+    {
+      return None;
+    }
+
+    let start = meta.indices.x;
+    let end = start + meta.complete_level_index_count;
+    DrawCommand::Indexed {
+      base_vertex: meta.vertices.x as i32,
+      indices: start..end,
+      instances: 0..1,
+    }
+    .into()
+  }
+
+  fn build_invocation(
+    &self,
+    cx: &mut ShaderComputePipelineBuilder,
+  ) -> Box<dyn IndexedDrawCommandBuilderInvocation> {
+    Box::new(MeshLODGraphDrawCommandCreatorInvocation {
+      meta: cx.bind_by(&self.meta),
+      sm_to_mesh_device: cx.bind_by(&self.sm_to_mesh_device),
+    })
+  }
+
+  fn bind(&self, builder: &mut BindingBuilder) {
+    builder.bind(&self.meta);
+    builder.bind(&self.sm_to_mesh_device);
+  }
+}
+
+impl ShaderHashProvider for MeshLODGraphDrawCommandCreator {
+  shader_hash_type_id! {}
+}
+
+struct MeshLODGraphDrawCommandCreatorInvocation {
+  meta: ShaderReadonlyPtrOf<[MeshLODGraphMeshMeta]>,
+  sm_to_mesh_device: ShaderReadonlyPtrOf<[u32]>,
+}
+
+impl IndexedDrawCommandBuilderInvocation for MeshLODGraphDrawCommandCreatorInvocation {
+  fn generate_draw_command(
+    &self,
+    draw_id: Node<u32>, // aka sm id
+  ) -> Node<DrawIndexedIndirectArgsStorage> {
+    let mesh_handle: Node<u32> = self.sm_to_mesh_device.index(draw_id).load();
+    let meta = self.meta.index(mesh_handle).load().expand();
+
+    let is_allocated = is_mesh_lod_graph_allocated(meta.indices, meta.vertices);
+    let vertex_count = is_allocated.select(meta.complete_level_index_count, val(0));
+
+    ENode::<DrawIndexedIndirectArgsStorage> {
+      vertex_count,
+      instance_count: val(1),
+      base_index: meta.indices.x(),
+      vertex_offset: meta.vertices.x().bitcast::<i32>(),
+      base_instance: draw_id,
+    }
+    .construct()
+  }
+}
+
+pub(crate) fn is_mesh_lod_graph_allocated(
+  indices: Node<Vec2<u32>>,
+  vertices: Node<Vec2<u32>>,
+) -> Node<bool> {
+  indices
+    .x()
+    .not_equals(val(DEVICE_RANGE_ALLOCATE_FAIL_MARKER))
+    .and(
+      vertices
+        .x()
+        .not_equals(val(DEVICE_RANGE_ALLOCATE_FAIL_MARKER)),
+    )
+}
@@ -0,0 +1,480 @@
+use std::{any::Any, hash::Hash, mem::offset_of, sync::Arc};
+
+use database::*;
+use fast_hash_collection::*;
+use rendiation_algebra::*;
+use rendiation_device_parallel_compute::*;
+use rendiation_scene_batch_extractor::*;
+use rendiation_scene_core::*;
+use rendiation_scene_indirect_attribute_mesh_lod::*;
+use rendiation_scene_rendering_gpu_indirect::*;
+use rendiation_shader_api::*;
+use rendiation_webgpu::*;
+use rendiation_webgpu_hook_utils::*;
+use serde::*;
+
+mod convert;
+pub use convert::*;
+
+mod draw_cmd;
+use draw_cmd::*;
+
+mod selection;
+use selection::*;
+
+mod shape;
+use shape::*;
+
+mod streaming;
+pub use streaming::*;
+
+/// The mesh lod graph renderer converts the attribute meshes into the mesh lod graph, and
+/// streams the graph's pages to the device by the lod camera views(the meshlets of all resident
+/// pages are flattened in one mesh). The meshlets of each mesh are selected on device by the
+/// projected error of the current lod camera. Only the selected meshlets are drawn by the
+/// multi draw indirect count.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeshLODGraphRendererConfig {
+  /// meshes with less triangles than this are not converted, the benefit is too low
+  pub min_triangle_count: usize,
+  /// the max selected meshlet count of each draw batch, the exceeded meshlets are not drawn.
+  /// rounded up to multiple of 64 to meet the storage buffer offset alignment.
+  pub max_meshlet_draw_count_per_batch: u32,
+  /// cull the selected meshlets by the lod camera frustum
+  pub enable_meshlet_frustum_culling: bool,
+  /// the max compressed page bytes in loading at the same time of each mesh
+  pub page_streaming_bandwidth_limitation: u64,
+  pub init_meshlet_count: u32,
+  pub max_meshlet_count: u32,
+  pub init_vertex_count: u32,
+  pub max_vertex_count: u32,
+  pub init_index_count: u32,
+  pub max_index_count: u32,
+}
+
+impl Default for MeshLODGraphRendererConfig {
+  fn default() -> Self {
+    Self {
+      min_triangle_count: 2048,
+      max_meshlet_draw_count_per_batch: 32768,
+      enable_meshlet_frustum_culling: true,
+      page_streaming_bandwidth_limitation: 4 * 1024 * 1024,
+      init_meshlet_count: 8192,
+      max_meshlet_count: 8192 * 100,
+      init_vertex_count: 100_000,
+      max_vertex_count: 4_000_000,
+      init_index_count: 200_000,
+      max_index_count: 200_000 * 100,
+    }
+  }
+}
+
+impl MeshLODGraphRendererConfig {
+  pub(crate) fn meshlet_draw_capacity_per_batch(&self) -> u32 {
+    // the command size is 20 bytes, 64 commands is 1280 bytes which is multiple of 256,
+    // the max storage buffer offset alignment
+    self
+      .max_meshlet_draw_count_per_batch
+      .max(1)
+      .next_multiple_of(64)
+  }
+}
+
+#[repr(C)]
+#[std430_layout]
+#[derive(Debug, Clone, PartialEq, Copy, ShaderStruct, Default)]
+pub struct MeshLODGraphMeshMeta {
+  /// (offset, count) in the meshlet pool
+  pub meshlets: Vec2<u32>,
+  /// (offset, count) in the vertex pool
+  pub vertices: Vec2<u32>,
+  /// (offset, count) in the index pool
+  pub indices: Vec2<u32>,
+  /// the index count of the finest complete level of the resident pages, used to draw the
+  /// mesh without lod selection
+  pub complete_level_index_count: u32,
+}
+
+pub fn register_mesh_lod_graph_data_model() {
+  global_entity_of::<AttributesMeshEntity>()
+    .declare_component::<AttributesMeshPrebuiltLODGraphAsset>();
+}
+
+/// The meshlet draw relies on the native multi draw indirect count, and the index pool must be
+/// a real buffer to be bound as the index buffer.
+pub fn is_mesh_lod_graph_renderer_supported(
+  info: &GPUInfo,
+  using_texture_as_storage_buffer: bool,
+) -> bool {
+  !rendiation_webgpu_midc_downgrade::require_midc_downgrade(info, using_texture_as_storage_buffer)
+}
+
+pub fn use_mesh_lod_graph_indirect_renderer(
+  cx: &mut QueryGPUHookCx,
+  config: &MeshLODGraphRendererConfig,
+  mesh_input: UseResult<AttributesMeshDataChangeInput>,
+  node_info: Option<Box<dyn IndirectNodeRenderImpl>>,
+  current_lod_camera: CurrentLODCameraControl,
+) -> Option<MeshLODGraphIndirectRenderer> {
+  let relation = cx.use_db_rev_ref_tri_view::<SceneModelStdModelRenderPayload>();
+  let (fanout, fanout_) = cx
+    .use_dual_query::<StandardModelRefAttributesMeshEntity>()
+    .fanout(relation, cx)
+    .fork();
+  let (sm_to_mesh, sm_to_mesh_) = fanout_
+    .map(|v| v.view().filter_map(|v| v).into_boxed())
+    .fork();
+
+  let assets = use_mesh_lod_graph_conversion(cx, mesh_input, config);
+  let converted =
+    use_mesh_lod_graph_page_streaming(cx, assets, sm_to_mesh_, &current_lod_camera, config);
+  let (converted, converted_) = converted.fork();
+  let (converted_, converted__) = converted_.fork();
+  let (converted__, converted___) = converted__.fork();
+  let (converted___, converted____) = converted___.fork();
+
+  let (meshlets, meshlets_allocation) = use_range_allocated_device_buffers::<MeshLODGraphMeshlet>(
+    cx,
+    "mesh lod graph meshlets",
+    config.init_meshlet_count,
+    config.max_meshlet_count,
+    converted.map_changes(|v| v.meshlets),
+  );
+
+  let (vertices, vertices_allocation) = use_range_allocated_device_buffers::<MeshLODGraphVertex>(
+    cx,
+    "mesh lod graph vertices",
+    config.init_vertex_count,
+    config.max_vertex_count,
+    converted_.map_changes(|v| v.vertices),
+  );
+
+  let (indices_allocation, indices) = use_mesh_lod_graph_indices(
+    cx,
+    config.init_index_count,
+    config.max_index_count,
+    converted__.map_changes(|v| v.indices),
+  );
+
+  let (cx, meta) = cx.use_storage_buffer_with_host_backup::<MeshLODGraphMeshMeta>(
+    "mesh lod graph mesh meta",
+    128,
+    u32::MAX,
+  );
+
+  meshlets_allocation
+    .map(|v| v.allocation_changes.clone())
+    .update_storage_array_with_host(cx, meta, offset_of!(MeshLODGraphMeshMeta, meshlets));
+  vertices_allocation
+    .map(|v| v.allocation_changes.clone())
+    .update_storage_array_with_host(cx, meta, offset_of!(MeshLODGraphMeshMeta, vertices));
+  indices_allocation.update_storage_array_with_host(
+    cx,
+    meta,
+    offset_of!(MeshLODGraphMeshMeta, indices),
+  );
+  converted___
+    .map_changes(|v| v.complete_level_index_count)
+    .update_storage_array_with_host(
+      cx,
+      meta,
+      offset_of!(MeshLODGraphMeshMeta, complete_level_index_count),
+    );
+
+  meta.use_max_item_count_by_db_entity::<AttributesMeshEntity>(cx);
+  meta.use_update(cx);
+
+  // the converted mesh set decides which std model is rendered by this renderer
+  let converted_meshes = converted____
+    .map_changes(|_| ())
+    .use_change_to_dual_query_in_spawn_stage(cx)
+    .dual_query_boxed()
+    .use_assure_result(cx);
+
+  let (cx, sm_to_mesh_device) =
+    cx.use_storage_buffer::<u32>("mesh lod graph scene_model to mesh mapping", 128, u32::MAX);
+
+  fanout
+    .map_raw_handle_or_u32_max_changes()
+    .update_storage_array(cx, sm_to_mesh_device, 0);
+
+  sm_to_mesh_device.use_max_item_count_by_db_entity::<SceneModelEntity>(cx);
+  sm_to_mesh_device.use_update(cx);
+
+  let sm_to_mesh = sm_to_mesh.use_assure_result(cx);
+
+  cx.when_render(|| MeshLODGraphIndirectRenderer {
+    meshlets,
+    vertices,
+    indices,
+    meta: meta.get_gpu_buffer(),
+    meta_host: meta.buffer.make_read_holder(),
+    sm_to_mesh_device: sm_to_mesh_device.get_gpu_buffer(),
+    sm_to_mesh: sm_to_mesh.expect_resolve_stage(),
+    std_to_mesh: read_global_db_foreign_key(),
+    converted_meshes: converted_meshes.expect_resolve_stage().view,
+    sm_node_info: node_info.unwrap(),
+    current_lod_camera,
+    config: config.clone(),
+  })
+}
+
+/// the index pool is bound as the index buffer directly, so it's allocated with the index usage
+fn use_mesh_lod_graph_indices(
+  cx: &mut QueryGPUHookCx,
+  init_item_count: u32,
+  max_item_count: u32,
+  index_source: UseResult<
+    impl DataChanges<Key = RawEntityHandle, Value = ExternalRefPtr<Vec<u32>>> + 'static,
+  >,
+) -> (
+  UseResult<impl DataChanges<Key = RawEntityHandle, Value = [u32; 2]> + 'static>,
+  AbstractReadonlyStorageBuffer<[u32]>,
+) {
+  let label = "mesh lod graph indices";
+  let (cx, gpu_buffer) = cx.use_gpu_init(|gpu, _| {
+    let indices: AbstractReadonlyStorageBuffer<[u32]> =
+      StorageBufferReadonlyDataView::<[u32]>::create_by_with_extra_usage(
+        &gpu.device,
+        ZeroedArrayByArrayLength(init_item_count as usize).into(),
+        BufferUsages::INDEX,
+        label,
+      )
+      .into();
+
+    let indices = indices.with_direct_resize(gpu);
+
+    Arc::new(parking_lot::RwLock::new(indices))
+  });
+
+  cx.if_inspect(|inspector| {
+    let buffer_size = gpu_buffer.read().gpu().byte_size();
+    inspector.label_device_memory_usage(label, buffer_size);
+  });
+
+  let allocator = cx.use_sharable_plain_state(|| {
+    GrowableRangeAllocator::new(label, max_item_count, init_item_count, 1)
+  });
+
+  let gpu_buffer_ = gpu_buffer.clone();
+
+  let allocation_info = index_source.map_spawn_stage_in_thread_data_changes(cx, move |change| {
+    let removed_and_changed_keys = change
+      .iter_removed()
+      .chain(change.iter_update_or_insert().map(|(k, _)| k));
+
+    let mut buffers_to_write = RangeAllocateBufferCollector::default();
+    let mut sizes = Vec::new();
+
+    for (k, buffer) in change.iter_update_or_insert() {
+      let buffer = buffer.ptr.clone();
+      buffers_to_write.collect_direct(k, bytemuck::cast_slice(buffer.as_slice()));
+      sizes.push((k, buffer.len() as u32));
+    }
+
+    let changes = allocator.write().update(removed_and_changed_keys, sizes);
+
+    let buffers_to_write = buffers_to_write.prepare(&changes, 4);
+
+    let allocation_changes = BatchAllocateResultShared::new(changes, 1);
+    allocation_changes.apply_resize(&mut *gpu_buffer_.write());
+
+    Arc::new(RangeAllocateBufferUpdates {
+      buffers_to_write,
+      allocation_changes,
+    })
+  });
+
+  let (allocation_info, allocation_info_) = allocation_info.fork();
+
+  let allocation_info_ = allocation_info_.use_assure_result(cx);
+
+  if let GPUQueryHookStage::CreateRender { encoder, .. } = &mut cx.stage {
+    let mut gpu_buffer = gpu_buffer.write();
+    let gpu_buffer = gpu_buffer.abstract_gpu();
+    allocation_info_
+      .expect_resolve_stage()
+      .write(cx.gpu, encoder, gpu_buffer);
+  }
+
+  let changes = allocation_info.map(|v| v.allocation_changes.clone());
+  let buffer = gpu_buffer.read().gpu().clone();
+  (changes, buffer)
+}
+
+/// Split the std model mesh group key: the models whose mesh is rendered by the mesh lod graph
+/// renderer are grouped by the returned foreign key, the others keep the attribute mesh key.
+///
+/// return (attribute mesh key, foreign mesh key), both are std model id -> key
+pub fn use_mesh_lod_graph_group_key(
+  cx: &mut QueryGPUHookCx,
+  config: &MeshLODGraphRendererConfig,
+  mesh_input: UseResult<AttributesMeshDataChangeInput>,
+  att_mesh_key: UseResult<BoxedDynDualQuery<RawEntityHandle, AttributeMeshRenderHashKey>>,
+) -> (
+  UseResult<BoxedDynDualQuery<RawEntityHandle, AttributeMeshRenderHashKey>>,
+  UseResult<BoxedDynDualQuery<RawEntityHandle, MeshGroupKey>>,
+) {
+  let config = config.clone();
+  let (applicable, applicable_) = mesh_input
+    .map_changes(move |v| {
+      v.if_loaded_ref()
+        .map(|v| is_mesh_lod_graph_applicable(v, &config))
+        .unwrap_or(false)
+    })
+    .use_change_to_dual_query_in_spawn_stage(cx)
+    .fanout(
+      cx.use_db_rev_ref_tri_view::<StandardModelRefAttributesMeshEntity>(),
+      cx,
+    )
+    .dual_query_boxed()
+    .fork();
+
+  let not_applicable = applicable.dual_query_filter_map(|v| (!v).then_some(()));
+  let att_mesh_key = att_mesh_key
+    .dual_query_filter_by_set(not_applicable)
+    .dual_query_boxed();
+
+  let lod_graph_key = applicable_
+    .dual_query_filter_map(|v| {
+      v.then(|| {
+        let hash =
+          fast_hash_scope(|hasher| std::any::TypeId::of::<MeshLODGraphMeshMeta>().hash(hasher));
+        MeshGroupKey::ForeignHash(hash)
+      })
+    })
+    .dual_query_boxed();
+
+  (att_mesh_key, lod_graph_key)
+}
+
+#[derive(Clone)]
+pub struct MeshLODGraphIndirectRenderer {
+  meshlets: AbstractReadonlyStorageBuffer<[MeshLODGraphMeshlet]>,
+  vertices: AbstractReadonlyStorageBuffer<[MeshLODGraphVertex]>,
+  indices: AbstractReadonlyStorageBuffer<[u32]>,
+  meta: AbstractReadonlyStorageBuffer<[MeshLODGraphMeshMeta]>,
+  meta_host: LockReadGuardHolder<SparseStorageBufferWithHostRaw<MeshLODGraphMeshMeta>>,
+  sm_to_mesh_device: AbstractReadonlyStorageBuffer<[u32]>,
+  sm_to_mesh: BoxedDynQuery<RawEntityHandle, RawEntityHandle>,
+  std_to_mesh: ForeignKeyReadView<StandardModelRefAttributesMeshEntity>,
+  converted_meshes: BoxedDynQuery<RawEntityHandle, ()>,
+  sm_node_info: Box<dyn IndirectNodeRenderImpl>,
+  current_lod_camera: CurrentLODCameraControl,
+  config: MeshLODGraphRendererConfig,
+}
+
+impl MeshLODGraphIndirectRenderer {
+  fn is_std_model_owned(&self, id: EntityHandle<StandardModelEntity>) -> bool {
+    self.std_to_mesh.get(id).is_some_and(|mesh| {
+      self
+        .converted_meshes
+        .access(mesh.raw_handle_ref())
+        .is_some()
+    })
+  }
+
+  fn make_draw_command_creator(&self) -> MeshLODGraphDrawCommandCreator {
+    MeshLODGraphDrawCommandCreator {
+      meta: self.meta.clone(),
+      meta_host: self.meta_host.clone(),
+      sm_to_mesh: self.sm_to_mesh.clone(),
+      sm_to_mesh_device: self.sm_to_mesh_device.clone(),
+    }
+  }
+}
+
+impl DrawCommandBuilderCreator for MeshLODGraphIndirectRenderer {
+  fn make_draw_command_builder(&self, id: RawEntityHandle) -> Option<DrawCommandBuilder> {
+    let id = unsafe { EntityHandle::from_raw(id) };
+    if !self.is_std_model_owned(id) {
+      return None;
+    }
+    DrawCommandBuilder::Indexed(Box::new(self.make_draw_command_creator())).into()
+  }
+}
+
+impl IndirectDrawProviderCreator for MeshLODGraphIndirectRenderer {
+  fn get_impl_distinguish_key_by_impl_select_id(&self, id: RawEntityHandle) -> Option<u64> {
+    let id = unsafe { EntityHandle::from_raw(id) };
+    if !self.is_std_model_owned(id) {
+      return None;
+    }
+    fast_hash_scope(|hasher| self.type_id().hash(hasher)).into()
+  }
+
+  fn use_create_or_update_indirect_draw_providers(
+    &self,
+    cx: &mut DeviceParallelComputeCtx,
+    list: &DeviceDrawList,
+    _dispatch_info_device_offset_compacted: &MultiRangeDispatchInfo,
+    id: RawEntityHandle,
+  ) -> Option<Vec<Box<dyn IndirectDrawProvider>>> {
+    let std_id = unsafe { EntityHandle::from_raw(id) };
+    if !self.is_std_model_owned(std_id) {
+      return None;
+    }
+
+    let selector = MeshLODGraphMeshletSelector {
+      meshlets: self.meshlets.clone(),
+      meta: self.meta.clone(),
+      sm_to_mesh_device: self.sm_to_mesh_device.clone(),
+      sm_node_info: self.sm_node_info.make_component_indirect().unwrap(),
+      lod_camera_info: self
+        .current_lod_camera
+        .get()
+        .expect("active_lod_camera not set"),
+      enable_frustum_culling: self.config.enable_meshlet_frustum_culling,
+    };
+
+    use_mesh_lod_graph_meshlet_selection(
+      cx,
+      list,
+      &selector,
+      self.config.meshlet_draw_capacity_per_batch(),
+    )
+    .into()
+  }
+}
+
+impl IndirectModelShapeRenderImpl for MeshLODGraphIndirectRenderer {
+  fn make_component_indirect(
+    &self,
+    any_idx: EntityHandle<StandardModelEntity>,
+  ) -> Option<Box<dyn RenderComponent + '_>> {
+    if !self.is_std_model_owned(any_idx) {
+      return None;
+    }
+    Some(Box::new(MeshLODGraphRasterDispatcher {
+      vertices: self.vertices.clone(),
+      indices: self.indices.clone(),
+    }))
+  }
+
+  fn get_index_storage_buffer(
+    &self,
+    any_idx: EntityHandle<StandardModelEntity>,
+  ) -> Option<Option<IndicesBufferInfo>> {
+    if !self.is_std_model_owned(any_idx) {
+      return None;
+    }
+    Some(Some(IndicesBufferInfo {
+      buffer: self.indices.clone(),
+      should_access_as_u16: false,
+    }))
+  }
+
+  fn hash_shader_group_key(
+    &self,
+    any_id: EntityHandle<StandardModelEntity>,
+    _: &mut PipelineHasher,
+  ) -> Option<()> {
+    // the mesh is always indexed triangle list with u32 indices
+    self.is_std_model_owned(any_id).then_some(())
+  }
+
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+}
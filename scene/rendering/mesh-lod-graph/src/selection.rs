@@ -0,0 +1,434 @@
+use crate::*;
+
+const MESHLET_SELECTION_WORKGROUP_SIZE: u32 = 64;
+
+/// the dispatch is not indirect, the workgroups iterate the draw list with stride, so the
+/// workgroup count is limited to the max dispatch size
+const MAX_MESHLET_SELECTION_WORKGROUP_COUNT: u32 = 65535;
+
+#[derive(Clone)]
+pub(crate) struct MeshLODGraphMeshletSelector {
+  pub(crate) meshlets: AbstractReadonlyStorageBuffer<[MeshLODGraphMeshlet]>,
+  pub(crate) meta: AbstractReadonlyStorageBuffer<[MeshLODGraphMeshMeta]>,
+  pub(crate) sm_to_mesh_device: AbstractReadonlyStorageBuffer<[u32]>,
+  pub(crate) sm_node_info: Box<dyn IndirectNodeInfoSceneModelAccess>,
+  pub(crate) lod_camera_info: LODCameraInfo,
+  pub(crate) enable_frustum_culling: bool,
+}
+
+impl MeshLODGraphMeshletSelector {
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    self.sm_node_info.hash_pipeline_with_type_info(hasher);
+    hasher.hash(self.enable_frustum_culling);
+  }
+
+  fn build(&self, cx: &mut ShaderComputePipelineBuilder) -> MeshLODGraphMeshletSelectorInvocation {
+    MeshLODGraphMeshletSelectorInvocation {
+      meshlets: cx.bind_by(&self.meshlets),
+      meta: cx.bind_by(&self.meta),
+      sm_to_mesh_device: cx.bind_by(&self.sm_to_mesh_device),
+      camera: cx.bind_by(&self.lod_camera_info.camera),
+      view_resolution: cx.bind_by(&self.lod_camera_info.view_resolution),
+      lod_error_threshold: cx.bind_by(&self.lod_camera_info.lod_error_threshold),
+      sm_node_info: self.sm_node_info.build(&mut cx.bindgroups),
+      enable_frustum_culling: self.enable_frustum_culling,
+    }
+  }
+
+  fn bind(&self, builder: &mut BindingBuilder) {
+    builder.bind(&self.meshlets);
+    builder.bind(&self.meta);
+    builder.bind(&self.sm_to_mesh_device);
+    builder.bind(&self.lod_camera_info.camera);
+    builder.bind(&self.lod_camera_info.view_resolution);
+    builder.bind(&self.lod_camera_info.lod_error_threshold);
+    self.sm_node_info.bind(builder);
+  }
+}
+
+struct MeshLODGraphMeshletSelectorInvocation {
+  meshlets: ShaderReadonlyPtrOf<[MeshLODGraphMeshlet]>,
+  meta: ShaderReadonlyPtrOf<[MeshLODGraphMeshMeta]>,
+  sm_to_mesh_device: ShaderReadonlyPtrOf<[u32]>,
+  camera: ShaderReadonlyPtrOf<CameraGPUTransform>,
+  view_resolution: ShaderReadonlyPtrOf<Vec4<u32>>,
+  lod_error_threshold: ShaderReadonlyPtrOf<Vec4<f32>>,
+  sm_node_info: Box<dyn IndirectNodeInfoSceneModelAccessInvocation>,
+  enable_frustum_culling: bool,
+}
+
+impl MeshLODGraphMeshletSelectorInvocation {
+  /// the meshlets of the scene model's mesh are iterated by the invocations in workgroup,
+  /// each selected meshlet emits one draw command
+  fn select_meshlets(
+    &self,
+    sm: Node<u32>,
+    local_id: Node<u32>,
+    on_selected: impl Fn(Node<DrawIndexedIndirectArgsStorage>),
+  ) {
+    let mesh_handle = self.sm_to_mesh_device.index(sm).load();
+    if_by(mesh_handle.not_equals(val(u32::MAX)), || {
+      let meta = self.meta.index(mesh_handle).load().expand();
+      if_by(
+        is_mesh_lod_graph_allocated(meta.indices, meta.vertices),
+        || {
+          let camera = self.camera.load().expand();
+          let node = self.sm_node_info.get_node_info_value(sm).expand();
+
+          // all positions are computed in camera relative space to keep the precision
+          let camera_position = hpt_uniform_to_hpt(camera.world_position);
+          let model_position = hpt_storage_to_hpt(node.world_position_hp);
+          let translation = hpt_sub_hpt(model_position, camera_position);
+          let mat = node.world_matrix_none_translation;
+
+          // scale the local space error to world space, use the max axis scale to stay conservative
+          let world_scale = mat
+            .x()
+            .xyz()
+            .length()
+            .max(mat.y().xyz().length())
+            .max(mat.z().xyz().length());
+
+          // see the projected error computation in the attribute mesh lod renderer
+          let projection = camera.projection;
+          let viewport_height = self.view_resolution.load().y().into_f32();
+          let pixel_scale = viewport_height * projection.y().y() / val(2.);
+          let is_perspective = projection.z().w().not_equals(val(0.));
+          let error_threshold = self.lod_error_threshold.load().x();
+
+          let to_camera_space = |sphere: Node<Vec4<f32>>| {
+            let center = (mat * (sphere.xyz(), val(1.)).into()).xyz() + translation;
+            (center, sphere.w() * world_scale)
+          };
+
+          let project_error = |sphere: Node<Vec4<f32>>, error: Node<f32>| {
+            let (center, radius) = to_camera_space(sphere);
+            let distance_scale = is_perspective.select_branched(
+              || {
+                // the closest distance to the sphere, so the error is never underestimated
+                let distance = (center.length() - radius).max(val(1e-6));
+                val(1.) / distance
+              },
+              || val(1.),
+            );
+            error * world_scale * pixel_scale * distance_scale
+          };
+
+          let planes = self
+            .enable_frustum_culling
+            .then(|| frustum_clip_planes(camera.view_projection_without_translation));
+
+          let meshlet_index = local_id.make_local_var();
+          loop_by(|cx| {
+            let index = meshlet_index.load();
+            if_by(index.greater_equal_than(meta.meshlets.y()), || {
+              cx.do_break()
+            });
+            meshlet_index.store(index + val(MESHLET_SELECTION_WORKGROUP_SIZE));
+
+            let meshlet = self
+              .meshlets
+              .index(meta.meshlets.x() + index)
+              .load()
+              .expand();
+
+            let self_error = project_error(meshlet.self_lod_sphere, meshlet.self_error);
+            let self_acceptable = self_error.less_equal_than(error_threshold);
+
+            // the parent error is not finite in the coarsest level, skip the projection
+            let is_coarsest = meshlet.parent_error.equals(val(f32::MAX));
+            let parent_not_acceptable = is_coarsest.select_branched(
+              || val(true),
+              || {
+                project_error(meshlet.parent_lod_sphere, meshlet.parent_error)
+                  .greater_than(error_threshold)
+              },
+            );
+
+            let mut selected = self_acceptable.and(parent_not_acceptable);
+
+            if let Some(planes) = &planes {
+              let (center, radius) = to_camera_space(meshlet.bounding);
+              selected = selected.and(sphere_in_frustum(planes, center, radius));
+            }
+
+            if_by(selected, || {
+              let cmd = ENode::<DrawIndexedIndirectArgsStorage> {
+                vertex_count: meshlet.index_count,
+                instance_count: val(1),
+                base_index: meta.indices.x() + meshlet.index_offset,
+                vertex_offset: meta.vertices.x().bitcast::<i32>(),
+                base_instance: sm,
+              }
+              .construct();
+              on_selected(cmd);
+            });
+          });
+        },
+      );
+    });
+  }
+}
+
+/// The planes are extracted from the rows of the view projection matrix. The webgpu clip
+/// space depth is always in 0..w, the reversed depth only swaps the near and far plane, and the
+/// infinite far plane degenerates into a plane that never culls.
+fn frustum_clip_planes(view_projection: Node<Mat4<f32>>) -> [Node<Vec4<f32>>; 6] {
+  let vp = view_projection.transpose();
+  let (r0, r1, r2, r3) = (vp.x(), vp.y(), vp.z(), vp.w());
+  [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2]
+}
+
+fn sphere_in_frustum(
+  planes: &[Node<Vec4<f32>>; 6],
+  center: Node<Vec3<f32>>,
+  radius: Node<f32>,
+) -> Node<bool> {
+  planes.iter().fold(val(true), |visible, plane| {
+    let distance = plane.xyz().dot(center) + plane.w();
+    visible.and(distance.greater_equal_than(-radius * plane.xyz().length()))
+  })
+}
+
+/// Select the meshlets of all scene models in the draw list and write the draw commands.
+/// Each sub list has its own fixed capacity of draw commands.
+pub(crate) fn use_mesh_lod_graph_meshlet_selection(
+  cx: &mut DeviceParallelComputeCtx,
+  list: &DeviceDrawList,
+  selector: &MeshLODGraphMeshletSelector,
+  capacity_per_list: u32,
+) -> Vec<Box<dyn IndirectDrawProvider>> {
+  let sub_list_count = list.dispatch_info.host_capacity_ranges.len();
+
+  let draw_counts = cx.use_rw_storage_buffer_array_impl::<u32>(
+    sub_list_count,
+    "mesh lod graph meshlet draw count",
+    BufferUsages::INDIRECT,
+  );
+  let draw_commands = cx.use_rw_storage_buffer_array_impl::<DrawIndexedIndirectArgsStorage>(
+    sub_list_count * capacity_per_list as usize,
+    "mesh lod graph meshlet draw command buffer",
+    BufferUsages::INDIRECT,
+  );
+
+  let draw_counts_atomic = draw_counts.clone().into_device_atomic_array();
+
+  cx.record_pass(|pass, device| {
+    let hasher = shader_hasher_from_marker_ty!(MeshLODGraphMeshletDrawCountReset);
+    let pipeline = device.get_or_cache_create_compute_pipeline_by(hasher, |mut builder| {
+      builder.config_work_group_size(MESHLET_SELECTION_WORKGROUP_SIZE);
+      let draw_counts = builder.bind_by(&draw_counts_atomic);
+      let id = builder.global_invocation_id().x();
+      if_by(id.less_than(draw_counts.array_length()), || {
+        draw_counts.index(id).atomic_store(val(0));
+      });
+      builder
+    });
+
+    BindingBuilder::default()
+      .with_bind(&draw_counts_atomic)
+      .setup_compute_pass(pass, device, &pipeline);
+
+    let workgroup_count = (draw_counts.item_count()).div_ceil(MESHLET_SELECTION_WORKGROUP_SIZE);
+    pass.dispatch_workgroups(workgroup_count, 1, 1);
+  });
+
+  cx.record_pass(|pass, device| {
+    let mut hasher =
+      shader_hasher_from_marker_ty!(MeshLODGraphMeshletSelection).with_hash(capacity_per_list);
+    list.hash_pipeline_with_type_info(&mut hasher);
+    selector.hash_pipeline(&mut hasher);
+
+    let pipeline = device.get_or_cache_create_compute_pipeline_by(hasher, |mut builder| {
+      builder.config_work_group_size(MESHLET_SELECTION_WORKGROUP_SIZE);
+      let scene_models = list.build_shader(&mut builder);
+      let selector = selector.build(&mut builder);
+      let draw_counts = builder.bind_by(&draw_counts_atomic);
+      let draw_commands = builder.bind_by(&draw_commands);
+
+      let local_id = builder.local_invocation_index();
+      let stride = builder.workgroup_count().x();
+      let total = scene_models.invocation_size().x();
+
+      // each workgroup processes one scene model at a time
+      let entry = builder.workgroup_id().x().make_local_var();
+      loop_by(|cx| {
+        let current = entry.load();
+        if_by(current.greater_equal_than(total), || cx.do_break());
+        entry.store(current + stride);
+
+        let (sm_and_list_index, _) =
+          scene_models.invocation_logic((current, val(0), val(0)).into());
+        let sm = sm_and_list_index.x();
+        let list_index = sm_and_list_index.y();
+
+        selector.select_meshlets(sm, local_id, |cmd| {
+          let slot = draw_counts.index(list_index).atomic_add(val(1));
+          // the exceeded meshlets are dropped, the draw count is clamped by the max count in draw
+          if_by(slot.less_than(val(capacity_per_list)), || {
+            let write_index = list_index * val(capacity_per_list) + slot;
+            draw_commands.index(write_index).store(cmd);
+          });
+        });
+      });
+
+      builder
+    });
+
+    BindingBuilder::default()
+      .with_fn(|b| list.bind_input(b))
+      .with_fn(|b| selector.bind(b))
+      .with_bind(&draw_counts_atomic)
+      .with_bind(&draw_commands)
+      .setup_compute_pass(pass, device, &pipeline);
+
+    let workgroup_count = list
+      .dispatch_info
+      .total_capacity
+      .clamp(1, MAX_MESHLET_SELECTION_WORKGROUP_COUNT);
+    pass.dispatch_workgroups(workgroup_count, 1, 1);
+  });
+
+  let command_byte_size = std::mem::size_of::<DrawIndexedIndirectArgsStorage>() as u64;
+  let list_byte_size = capacity_per_list as u64 * command_byte_size;
+  (0..sub_list_count as u64)
+    .map(|i| {
+      let draw_commands = draw_commands.gpu.resource.create_view(GPUBufferViewRange {
+        offset: i * list_byte_size,
+        size: std::num::NonZeroU64::new(list_byte_size),
+      });
+      let draw_count = draw_counts.gpu.resource.create_view(GPUBufferViewRange {
+        offset: i * 4,
+        size: std::num::NonZeroU64::new(4),
+      });
+      Box::new(MeshLODGraphMeshletDrawBatch {
+        draw_commands,
+        draw_count,
+        max_count: capacity_per_list,
+      }) as Box<dyn IndirectDrawProvider>
+    })
+    .collect()
+}
+
+struct MeshLODGraphMeshletDrawBatch {
+  draw_commands: GPUBufferResourceView,
+  draw_count: GPUBufferResourceView,
+  max_count: u32,
+}
+
+impl IndirectDrawProvider for MeshLODGraphMeshletDrawBatch {
+  fn create_indirect_invocation_source(
+    &self,
+    _: &mut ShaderBindGroupBuilder,
+  ) -> Box<dyn IndirectBatchInvocationSource> {
+    struct MeshLODGraphMeshletDrawBatchInvocation;
+
+    impl IndirectBatchInvocationSource for MeshLODGraphMeshletDrawBatchInvocation {
+      fn current_invocation_scene_model_id(&self, builder: &mut ShaderVertexBuilder) -> Node<u32> {
+        // the base instance of each meshlet draw command is the scene model id
+        builder.query::<VertexInstanceIndex>()
+      }
+    }
+
+    Box::new(MeshLODGraphMeshletDrawBatchInvocation)
+  }
+
+  fn draw_command(&self) -> DrawCommand {
+    DrawCommand::MultiIndirectCount {
+      indexed: true,
+      indirect_buffer: self.draw_commands.clone(),
+      indirect_count: self.draw_count.clone(),
+      max_count: self.max_count,
+    }
+  }
+}
+
+impl ShaderPassBuilder for MeshLODGraphMeshletDrawBatch {}
+impl ShaderHashProvider for MeshLODGraphMeshletDrawBatch {
+  shader_hash_type_id! {}
+}
+
+#[cfg(test)]
+mod tests {
+  use rendiation_shader_backend_cpu::*;
+
+  use super::*;
+
+  /// the camera looks at -z, the spheres are in camera space
+  fn cull_on_cpu(view_projection: Mat4<f32>, spheres: &[Vec4<f32>]) -> Vec<bool> {
+    let input = CpuStorageBufferReadonlyDataView::<[Vec4<f32>]>::new(spheres);
+    let output = CpuStorageBufferDataView::<[u32]>::new(vec![0; spheres.len()].as_slice());
+
+    let mut cx = cpu_compute_shader_builder().with_config_work_group_size(1);
+    let input_node = cx.bind_by(&input);
+    let output_node = cx.bind_by(&output);
+    let id = cx.global_invocation_id().x();
+    let sphere = input_node.index(id).load();
+    let planes = frustum_clip_planes(val(view_projection));
+    let visible = sphere_in_frustum(&planes, sphere.xyz(), sphere.w());
+    output_node.index(id).store(visible.select(val(1), val(0)));
+    let module = cx.create_cpu_module().unwrap();
+
+    let bindings = CpuShaderBindingBuilder::default()
+      .with_bind(&input)
+      .with_bind(&output);
+    module
+      .dispatch(&bindings, (spheres.len() as u32, 1, 1))
+      .unwrap();
+
+    output.read().iter().map(|v| *v == 1).collect()
+  }
+
+  fn perspective(near: f32, far: f32) -> Mat4<f32> {
+    PerspectiveProjection {
+      near,
+      far,
+      fov: Deg::by(90.),
+      aspect: 1.,
+    }
+    .compute_projection_mat(&WebGPUxNDC)
+  }
+
+  #[test]
+  fn test_frustum_culling_near_far_plane() {
+    let spheres = [
+      Vec4::new(0., 0., -10., 0.5),   // inside
+      Vec4::new(0., 0., -0.5, 0.1),   // in front of the near plane
+      Vec4::new(0., 0., -0.95, 0.1),  // intersect the near plane
+      Vec4::new(0., 0., -110., 5.),   // behind the far plane
+      Vec4::new(0., 0., -102., 5.),   // intersect the far plane
+      Vec4::new(100., 0., -10., 0.5), // outside of the side plane
+    ];
+    let expect = [true, false, true, false, true, false];
+
+    assert_eq!(cull_on_cpu(perspective(1., 100.), &spheres), expect);
+
+    // the reversed depth only swaps the near and far plane
+    let reverse_z = Mat4::new(
+      1., 0., 0., 0., //
+      0., 1., 0., 0., //
+      0., 0., -1., 0., //
+      0., 0., 1., 1., //
+    );
+    assert_eq!(
+      cull_on_cpu(reverse_z * perspective(1., 100.), &spheres),
+      expect
+    );
+  }
+
+  #[test]
+  fn test_frustum_culling_infinite_far_plane() {
+    // the reversed depth infinite perspective projection with 90 degree fov and near 1.
+    let infinite_reverse_z = Mat4::new(
+      1., 0., 0., 0., //
+      0., 1., 0., 0., //
+      0., 0., 0., -1., //
+      0., 0., 1., 0., //
+    );
+    let spheres = [
+      Vec4::new(0., 0., -1e6, 0.5), // far away but still inside
+      Vec4::new(0., 0., -0.5, 0.1), // in front of the near plane
+    ];
+    assert_eq!(cull_on_cpu(infinite_reverse_z, &spheres), [true, false]);
+  }
+}
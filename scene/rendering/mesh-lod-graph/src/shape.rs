@@ -0,0 +1,42 @@
+use crate::*;
+
+pub(crate) struct MeshLODGraphRasterDispatcher {
+  pub(crate) vertices: AbstractReadonlyStorageBuffer<[MeshLODGraphVertex]>,
+  pub(crate) indices: AbstractReadonlyStorageBuffer<[u32]>,
+}
+
+impl ShaderHashProvider for MeshLODGraphRasterDispatcher {
+  shader_hash_type_id! {}
+}
+
+impl ShaderPassBuilder for MeshLODGraphRasterDispatcher {
+  fn setup_pass(&self, ctx: &mut GPURenderPassCtx) {
+    // may be failed if we are using texture as storage
+    if let Some(index) = self.indices.get_gpu_buffer_view() {
+      ctx
+        .pass
+        .set_index_buffer_by_buffer_resource_view(&index, IndexFormat::Uint32);
+    }
+    ctx.binding.bind(&self.vertices);
+  }
+}
+
+impl GraphicsShaderProvider for MeshLODGraphRasterDispatcher {
+  fn build(&self, builder: &mut ShaderRenderPipelineBuilder) {
+    builder.vertex(|vertex, binding| {
+      let vertices = binding.bind_by(&self.vertices);
+
+      // the vertex offset of the draw command is the mesh's vertex offset in pool,
+      // so the vertex index can be used to access the pool directly
+      let vertex_id = vertex.query::<VertexIndex>();
+      let v = vertices.index(vertex_id).load().expand();
+
+      vertex.register::<GeometryPosition>(v.position_u.xyz());
+      vertex.register::<GeometryNormal>(v.normal_v.xyz());
+      let uv: Node<Vec2<f32>> = (v.position_u.w(), v.normal_v.w()).into();
+      vertex.register::<GeometryUV>(uv);
+
+      vertex.primitive_state().topology = PrimitiveTopology::TriangleList;
+    })
+  }
+}
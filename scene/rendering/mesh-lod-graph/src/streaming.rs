@@ -0,0 +1,141 @@
+use std::task::Context;
+
+use rendiation_mesh_lod_graph::*;
+use rendiation_uri_streaming::LoaderFunction;
+
+use crate::*;
+
+struct MeshLODGraphMeshStreaming {
+  streaming: MeshLODGraphPageStreaming,
+  loader: Box<LoaderFunction<MeshLODGraphPageRequest, MeshLODGraphPage>>,
+}
+
+#[derive(Default)]
+struct MeshLODGraphStreamingStates {
+  meshes: FastHashMap<RawEntityHandle, MeshLODGraphMeshStreaming>,
+  /// the views are only recorded in rendering, the last ones are kept if nothing is rendered
+  views: Vec<LODCameraHostInfo>,
+}
+
+/// The view in the mesh local space, the model's world scale is applied on the pixel scale.
+///
+/// Return None for the orthographic view, its projected error does not decrease by the
+/// distance, so it can not be expressed by the streaming view.
+fn mesh_local_streaming_view(
+  view: &LODCameraHostInfo,
+  model_world: Mat4<f64>,
+) -> Option<MeshLODGraphStreamingView> {
+  // see the projected error computation in the meshlet selection
+  let projection = view.camera.projection;
+  let is_perspective = projection.c4 != 0.;
+  if !is_perspective {
+    return None;
+  }
+
+  let camera_position = model_world.inverse_or_identity() * view.camera.world.position();
+  let pixel_scale =
+    view.view_resolution.y as f32 * projection.b2 / 2. * model_world.max_scale() as f32;
+
+  MeshLODGraphStreamingView {
+    camera_position: camera_position.into_f32(),
+    pixel_scale,
+    error_threshold: view.lod_error_threshold,
+  }
+  .into()
+}
+
+/// Stream the pages of the converted meshes by the recently rendered lod camera views. The
+/// coarsest level is loaded first, and the finer pages are streamed in by the screen space
+/// error of all the scene models that use the mesh. The resident pages of the changed mesh are
+/// flattened and emitted as the mesh's new device data.
+pub fn use_mesh_lod_graph_page_streaming(
+  cx: &mut QueryGPUHookCx,
+  assets: UseResult<MeshLODGraphStreamingAssetChanges>,
+  sm_to_mesh: UseResult<BoxedDynQuery<RawEntityHandle, RawEntityHandle>>,
+  current_lod_camera: &CurrentLODCameraControl,
+  config: &MeshLODGraphRendererConfig,
+) -> UseResult<MeshLODGraphGPUDataChanges> {
+  let sm_world = cx.use_shared_dual_query_view(GlobalSceneModelWorldMatrix);
+  let (cx, states) =
+    cx.use_plain_state_default_cloned::<Arc<parking_lot::RwLock<MeshLODGraphStreamingStates>>>();
+
+  let waker = cx.waker().clone();
+  let current_lod_camera = current_lod_camera.clone();
+  let bandwidth_limitation = config.page_streaming_bandwidth_limitation;
+
+  assets
+    .join(sm_to_mesh)
+    .join(sm_world)
+    .map_spawn_stage_in_thread(
+      cx,
+      |_| true,
+      move |((assets, sm_to_mesh), sm_world)| {
+        let mut states = states.write();
+        let states = &mut *states;
+
+        for removed in &assets.removed {
+          states.meshes.remove(removed);
+        }
+        for (id, asset) in &assets.update_or_insert {
+          let streaming = MeshLODGraphMeshStreaming {
+            streaming: MeshLODGraphPageStreaming::new(asset.info.clone(), bandwidth_limitation),
+            loader: asset.create_page_loader(),
+          };
+          states.meshes.insert(*id, streaming);
+        }
+
+        let recent_views = current_lod_camera.take_recent_host_views();
+        if !recent_views.is_empty() {
+          states.views = recent_views;
+        }
+
+        let mut mesh_instances = FastHashMap::<RawEntityHandle, Vec<Mat4<f64>>>::default();
+        for (sm, mesh) in sm_to_mesh.iter_key_value() {
+          if states.meshes.contains_key(&mesh)
+            && let Some(world) = sm_world.access(&sm)
+          {
+            mesh_instances.entry(mesh).or_default().push(world);
+          }
+        }
+
+        let mut cx = Context::from_waker(&waker);
+        let mut update_or_insert = Vec::new();
+        let mut is_loading = false;
+        for (id, mesh) in states.meshes.iter_mut() {
+          if let Some(instances) = mesh_instances.get(id) {
+            let views = instances
+              .iter()
+              .flat_map(|world| {
+                states
+                  .views
+                  .iter()
+                  .map(|view| mesh_local_streaming_view(view, *world))
+              })
+              .collect::<Option<Vec<_>>>();
+            match views {
+              None => mesh.streaming.require_all_pages(),
+              Some(views) if !views.is_empty() => mesh.streaming.update_views(&views),
+              // keep the current pages if the mesh is not visible in any view
+              _ => {}
+            }
+          }
+
+          if mesh.streaming.poll_loading(&mut cx, &mut mesh.loader) {
+            let resident = mesh.streaming.build_resident_mesh();
+            update_or_insert.push((*id, flatten_resident_mesh(&resident)));
+          }
+          is_loading |= mesh.streaming.is_loading();
+        }
+
+        // the dispatched page load is polled in the next frame
+        if is_loading {
+          waker.wake_by_ref();
+        }
+
+        Arc::new(LinearBatchChanges {
+          removed: assets.removed.clone(),
+          update_or_insert,
+        })
+      },
+    )
+}
@@ -6,6 +6,8 @@ pub struct ShaderBindGroupBuilder {
   pub custom_states: FastHashMap<u64, Arc<dyn Any>>,
   binding_re_enter: BindingReEnter,
   stage_layout: ShaderStageGroup<()>,
+  /// see [Self::mark_not_persistable]
+  not_persistable: bool,
 }
 
 enum BindingReEnter {
@@ -77,6 +79,24 @@ impl<T: AbstractShaderBindingSource> BindingPreparer<'_, T> {
     set_current_building(None);
     GraphicsPairInputNodeAccessor { shape, fragment }
   }
+
+  /// bind and register only in the fragment stage, this is required for the binding that not
+  /// allowed to be visible in the vertex stage, for example the read write storage buffer.
+  pub fn using_fragment(
+    mut self,
+    builder: &mut ShaderRenderPipelineBuilder,
+    register: impl FnOnce(&mut SemanticRegistry, &T::ShaderBindResult),
+  ) -> T::ShaderBindResult {
+    assert!(
+      get_current_stage().is_none(),
+      "using_fragment must be called outside any graphics sub shader stage"
+    );
+    set_current_building(ShaderStage::Fragment.into());
+    let fragment = self.using(builder);
+    register(&mut builder.fragment.registry, &fragment);
+    set_current_building(None);
+    fragment
+  }
 }
 
 pub struct GraphicsPairInputNodeAccessor<T: AbstractShaderBindingSource> {
@@ -117,9 +137,21 @@ impl ShaderBindGroupBuilder {
       custom_states: Default::default(),
       binding_re_enter: BindingReEnter::None,
       stage_layout,
+      not_persistable: false,
     }
   }
 
+  /// Mark the built shader depends on the process local state created in the building, for
+  /// example an id allocated from a global registry. The built result is only valid in the
+  /// current process, so the pipeline will not be stored into the persistent pipeline cache.
+  pub fn mark_not_persistable(&mut self) {
+    self.not_persistable = true;
+  }
+
+  pub fn is_persistable(&self) -> bool {
+    !self.not_persistable
+  }
+
   pub fn set_binding_slot(&mut self, new: usize) -> usize {
     std::mem::replace(&mut self.current_index, new)
   }
@@ -0,0 +1,554 @@
+use crate::*;
+
+/// The [ShaderAPI] implementation that records the shader graph into a tree of statements,
+/// the result module could be executed on host by [CpuShaderModule::dispatch].
+pub struct ShaderAPICpuImpl {
+  stage: ShaderStage,
+  handle_id: usize,
+  workgroup_size: (u32, u32, u32),
+  block: Vec<(Vec<CpuShaderStatement>, BlockBuildingState)>,
+  control_structure: Vec<CpuShaderStatement>,
+  building_fn: Vec<CpuShaderFunctionBuilding>,
+  functions: Vec<CpuShaderFunction>,
+  fn_mapping: FastHashMap<String, usize>,
+  entry: Option<CpuShaderFunction>,
+  globals: Vec<CpuShaderGlobal>,
+  inputs: FastHashMap<ShaderNodeRawHandle, CpuShaderModuleInput>,
+}
+
+struct CpuShaderFunctionBuilding {
+  name: String,
+  parameters: Vec<ShaderNodeRawHandle>,
+  locals: Vec<CpuShaderValue>,
+}
+
+enum BlockBuildingState {
+  Common,
+  SwitchCase(CpuShaderSwitchCase),
+  Loop,
+  IfAccept,
+  Else,
+  Function,
+}
+
+const ENTRY_POINT_NAME: &str = "main";
+
+impl ShaderAPICpuImpl {
+  pub fn new(stage: ShaderStage) -> Self {
+    Self {
+      stage,
+      handle_id: 0,
+      workgroup_size: (1, 1, 1),
+      block: vec![(Default::default(), BlockBuildingState::Function)],
+      control_structure: Default::default(),
+      building_fn: vec![CpuShaderFunctionBuilding {
+        name: ENTRY_POINT_NAME.to_owned(),
+        parameters: Default::default(),
+        locals: Default::default(),
+      }],
+      functions: Default::default(),
+      fn_mapping: Default::default(),
+      entry: None,
+      globals: Default::default(),
+      inputs: Default::default(),
+    }
+  }
+
+  fn make_new_handle(&mut self) -> ShaderNodeRawHandle {
+    self.handle_id += 1;
+    ShaderNodeRawHandle {
+      handle: self.handle_id,
+    }
+  }
+
+  fn push_top_statement(&mut self, st: CpuShaderStatement) {
+    self.block.last_mut().unwrap().0.push(st);
+  }
+
+  fn eval(&mut self, expr: CpuShaderExpr) -> ShaderNodeRawHandle {
+    let handle = self.make_new_handle();
+    self.push_top_statement(CpuShaderStatement::Eval { handle, expr });
+    handle
+  }
+
+  fn define_global(&mut self, kind: CpuShaderGlobalKind) -> ShaderNodeRawHandle {
+    let index = self.globals.len();
+    self.globals.push(CpuShaderGlobal { kind });
+    let handle = self.make_new_handle();
+    self
+      .inputs
+      .insert(handle, CpuShaderModuleInput::Global(index));
+    handle
+  }
+
+  fn define_binding(
+    &mut self,
+    desc: ShaderBindingDescriptor,
+    slot: (usize, usize),
+  ) -> ShaderNodeRawHandle {
+    let kind = match &desc.ty {
+      ShaderValueType::Single(ty) => match ty {
+        ShaderValueSingleType::Sized(_) | ShaderValueSingleType::Unsized(_) => {
+          CpuShaderGlobalKind::Buffer {
+            slot,
+            ty: ty.clone(),
+            layout: desc
+              .get_buffer_layout()
+              .unwrap_or(StructLayoutTarget::Std430),
+            writeable: desc.should_as_storage_buffer_if_is_buffer_like && desc.writeable_if_storage,
+          }
+        }
+        ShaderValueSingleType::Sampler(_) => CpuShaderGlobalKind::Sampler { slot },
+        ShaderValueSingleType::Texture {
+          dimension,
+          sample_type,
+          ..
+        } => CpuShaderGlobalKind::Texture {
+          slot,
+          dimension: *dimension,
+          sample_type: *sample_type,
+        },
+        ShaderValueSingleType::StorageTexture { dimension, .. } => {
+          CpuShaderGlobalKind::StorageTexture {
+            slot,
+            dimension: *dimension,
+          }
+        }
+        ShaderValueSingleType::AccelerationStructure | ShaderValueSingleType::RayQuery => {
+          CpuShaderGlobalKind::Unsupported("acceleration structure binding")
+        }
+      },
+      ShaderValueType::BindingArray { .. } => CpuShaderGlobalKind::Unsupported("binding array"),
+      ShaderValueType::Never => CpuShaderGlobalKind::Unsupported("never type binding"),
+    };
+    self.define_global(kind)
+  }
+
+  fn make_expression_impl(&mut self, expr: ShaderNodeExpr) -> ShaderNodeRawHandle {
+    let expr = match expr {
+      ShaderNodeExpr::Fake => return ShaderNodeRawHandle { handle: 0 },
+      ShaderNodeExpr::Zeroed { target } => {
+        CpuShaderExpr::Constant(CpuShaderValue::zero_sized(&target))
+      }
+      ShaderNodeExpr::Convert {
+        source,
+        convert_to,
+        convert,
+      } => CpuShaderExpr::Convert {
+        source,
+        convert_to: match convert_to {
+          ValueKind::Uint => ScalarType::U32,
+          ValueKind::Int => ScalarType::I32,
+          ValueKind::Float => ScalarType::F32,
+          ValueKind::Bool => ScalarType::Bool,
+        },
+        bitcast: convert.is_none(),
+      },
+      ShaderNodeExpr::AtomicCall {
+        pointer,
+        function,
+        value,
+        ..
+      } => CpuShaderExpr::Atomic {
+        pointer,
+        function,
+        value,
+      },
+      ShaderNodeExpr::FunctionCall { meta, parameters } => match meta {
+        ShaderFunctionType::Custom(meta) => {
+          let Some(function) = self.fn_mapping.get(&meta.name).copied() else {
+            return self.eval(CpuShaderExpr::Unsupported("call undefined function"));
+          };
+          let result = self.make_new_handle();
+          self.push_top_statement(CpuShaderStatement::Call {
+            result,
+            function,
+            arguments: parameters,
+          });
+          return result;
+        }
+        ShaderFunctionType::BuiltIn { ty, .. } => CpuShaderExpr::BuiltIn {
+          function: ty,
+          parameters,
+        },
+      },
+      ShaderNodeExpr::TextureSampling(s) => CpuShaderExpr::TextureSampling(s),
+      ShaderNodeExpr::TextureLoad(l) => CpuShaderExpr::TextureLoad(l),
+      ShaderNodeExpr::TextureQuery(t, q) => CpuShaderExpr::TextureQuery(t, q),
+      ShaderNodeExpr::Swizzle { ty, source } => {
+        let components: Vec<_> = ty
+          .chars()
+          .map(|c| match c {
+            'x' | 'r' => 0,
+            'y' | 'g' => 1,
+            'z' | 'b' => 2,
+            'w' | 'a' => 3,
+            _ => panic!("invalid swizzle"),
+          })
+          .collect();
+        // single component swizzle is also valid on pointer
+        if components.len() == 1 {
+          CpuShaderExpr::IndexStatic {
+            base: source,
+            index: components[0],
+          }
+        } else {
+          CpuShaderExpr::Swizzle { source, components }
+        }
+      }
+      ShaderNodeExpr::Derivative { .. } => CpuShaderExpr::Unsupported("derivative"),
+      ShaderNodeExpr::Compose { target, parameters } => {
+        CpuShaderExpr::Compose { target, parameters }
+      }
+      ShaderNodeExpr::Operator(op) => match op {
+        OperatorNode::Unary { one, operator } => CpuShaderExpr::Unary { operator, one },
+        OperatorNode::Binary {
+          left,
+          right,
+          operator,
+        } => CpuShaderExpr::Binary {
+          operator,
+          left,
+          right,
+        },
+        OperatorNode::Index { array, entry } => CpuShaderExpr::Index {
+          base: array,
+          index: entry,
+        },
+      },
+      ShaderNodeExpr::IndexStatic {
+        field_index,
+        target,
+      } => CpuShaderExpr::IndexStatic {
+        base: target,
+        index: field_index,
+      },
+      ShaderNodeExpr::RayQueryProceed { .. }
+      | ShaderNodeExpr::RayQueryGetCandidateIntersection { .. }
+      | ShaderNodeExpr::RayQueryGetCommittedIntersection { .. } => {
+        CpuShaderExpr::Unsupported("ray query")
+      }
+      ShaderNodeExpr::WorkGroupUniformLoad { pointer, .. } => {
+        // the uniform load has the barrier semantic before and after the load
+        self.push_top_statement(CpuShaderStatement::Barrier);
+        let r = self.eval(CpuShaderExpr::Load(pointer));
+        self.push_top_statement(CpuShaderStatement::Barrier);
+        return r;
+      }
+      ShaderNodeExpr::SubgroupBallot { .. }
+      | ShaderNodeExpr::SubgroupGather { .. }
+      | ShaderNodeExpr::SubgroupCollectiveOperation { .. } => {
+        CpuShaderExpr::Unsupported("subgroup operation")
+      }
+    };
+    self.eval(expr)
+  }
+}
+
+impl ShaderAPI for ShaderAPICpuImpl {
+  fn set_workgroup_size(&mut self, size: (u32, u32, u32)) {
+    self.workgroup_size = size;
+  }
+
+  fn barrier(&mut self, _scope: BarrierScope) {
+    self.push_top_statement(CpuShaderStatement::Barrier);
+  }
+
+  fn define_mesh_info(&mut self, _mesh_info: MeshStageInfo) {}
+  fn define_task_payload_io(&mut self, _payload: ShaderNodeRawHandle) {}
+  fn set_output_mesh_task_size(&mut self, _size: ShaderNodeRawHandle) {}
+
+  fn define_module_input(&mut self, input: ShaderInputNode) -> ShaderNodeRawHandle {
+    match input {
+      ShaderInputNode::BuiltIn(ty) => {
+        let handle = self.make_new_handle();
+        self
+          .inputs
+          .insert(handle, CpuShaderModuleInput::BuiltIn(ty));
+        handle
+      }
+      ShaderInputNode::Binding {
+        desc,
+        bindgroup_index,
+        entry_index,
+      } => self.define_binding(desc, (bindgroup_index, entry_index)),
+      ShaderInputNode::UserDefinedIn { .. } => {
+        let handle = self.make_new_handle();
+        self.inputs.insert(handle, CpuShaderModuleInput::StageIO);
+        handle
+      }
+      ShaderInputNode::WorkGroupShared { ty } => self.define_global(
+        CpuShaderGlobalKind::WorkGroup(CpuShaderValue::zero_sized(&ty)),
+      ),
+      ShaderInputNode::Private { ty } | ShaderInputNode::TaskPayload { ty } => self.define_global(
+        CpuShaderGlobalKind::Private(CpuShaderValue::zero_sized(&ty)),
+      ),
+    }
+  }
+
+  fn define_next_frag_out(&mut self, ty: ShaderSizedValueType) -> ShaderNodeRawHandle {
+    self.make_local_var(ShaderValueType::Single(ShaderValueSingleType::Sized(ty)))
+  }
+
+  fn define_next_vertex_output(
+    &mut self,
+    ty: PrimitiveShaderValueType,
+    _interpolation: Option<ShaderInterpolation>,
+  ) -> ShaderNodeRawHandle {
+    self.make_local_var(ShaderValueType::Single(ShaderValueSingleType::Sized(
+      ShaderSizedValueType::Primitive(ty),
+    )))
+  }
+
+  fn define_vertex_position_output(&mut self) -> ShaderNodeRawHandle {
+    self.define_next_vertex_output(PrimitiveShaderValueType::vec4::<f32>(), None)
+  }
+
+  fn define_frag_depth_output(&mut self) -> ShaderNodeRawHandle {
+    self.define_next_vertex_output(PrimitiveShaderValueType::f32(), None)
+  }
+
+  fn define_const(
+    &mut self,
+    init_value: ShaderStructFieldInitValue,
+    _ty: ShaderSizedValueType,
+    _inlined: bool,
+  ) -> ShaderNodeRawHandle {
+    self.eval(CpuShaderExpr::Constant(CpuShaderValue::from_init_value(
+      &init_value,
+    )))
+  }
+
+  fn make_expression(&mut self, expr: ShaderNodeExpr) -> ShaderNodeRawHandle {
+    self.make_expression_impl(expr)
+  }
+
+  fn make_local_var(&mut self, ty: ShaderValueType) -> ShaderNodeRawHandle {
+    let locals = &mut self.building_fn.last_mut().unwrap().locals;
+    let index = locals.len();
+    locals.push(CpuShaderValue::zero(&ty));
+    self.eval(CpuShaderExpr::LocalVariable(index))
+  }
+
+  fn make_zero_val(&mut self, ty: ShaderValueType) -> ShaderNodeRawHandle {
+    self.eval(CpuShaderExpr::Constant(CpuShaderValue::zero(&ty)))
+  }
+
+  fn mark_handle_debug_name(&mut self, _handle: ShaderNodeRawHandle, _name: String) {}
+
+  fn store(&mut self, source: ShaderNodeRawHandle, target: ShaderNodeRawHandle) {
+    self.push_top_statement(CpuShaderStatement::Store {
+      pointer: target,
+      value: source,
+    });
+  }
+
+  fn load(&mut self, source: ShaderNodeRawHandle) -> ShaderNodeRawHandle {
+    self.eval(CpuShaderExpr::Load(source))
+  }
+
+  fn texture_store(&mut self, store: ShaderTextureStore) {
+    self.push_top_statement(CpuShaderStatement::TextureStore(store));
+  }
+
+  fn ray_query_initialize(
+    &mut self,
+    _query: ShaderNodeRawHandle,
+    _tlas: BindingNode<ShaderAccelerationStructure>,
+    _ray_desc: ShaderRayDesc,
+  ) {
+    self.push_top_statement(CpuShaderStatement::Unsupported("ray query"));
+  }
+
+  fn ray_query_terminate(&mut self, _query: ShaderNodeRawHandle) {
+    self.push_top_statement(CpuShaderStatement::Unsupported("ray query"));
+  }
+
+  fn push_scope(&mut self) {
+    self
+      .block
+      .push((Default::default(), BlockBuildingState::Common));
+  }
+
+  fn pop_scope(&mut self) {
+    let (b, ty) = self.block.pop().unwrap();
+    let b: CpuShaderBlock = b.into();
+    match ty {
+      BlockBuildingState::Common => self.push_top_statement(CpuShaderStatement::Block(b)),
+      BlockBuildingState::SwitchCase(case) => {
+        if let Some(CpuShaderStatement::Switch { cases, .. }) = self.control_structure.last_mut() {
+          cases.push((case, b));
+        } else {
+          panic!("expect switch")
+        }
+      }
+      BlockBuildingState::Loop => {
+        let loop_s = self.control_structure.pop();
+        assert!(matches!(loop_s, Some(CpuShaderStatement::Loop(_))));
+        self.push_top_statement(CpuShaderStatement::Loop(b));
+      }
+      BlockBuildingState::IfAccept => {
+        let mut if_s = self.control_structure.pop().unwrap();
+        if let CpuShaderStatement::If { accept, .. } = &mut if_s {
+          *accept = b;
+        } else {
+          panic!("expect if")
+        }
+        self.push_top_statement(if_s);
+      }
+      BlockBuildingState::Else => {
+        let mut if_s = self.control_structure.pop().unwrap();
+        if let CpuShaderStatement::If { reject, .. } = &mut if_s {
+          *reject = b;
+        } else {
+          panic!("expect if")
+        }
+        self.push_top_statement(if_s);
+      }
+      BlockBuildingState::Function => {
+        let f = self.building_fn.pop().unwrap();
+        let f = CpuShaderFunction {
+          name: f.name,
+          parameters: f.parameters,
+          locals: f.locals,
+          body: b,
+        };
+        if self.building_fn.is_empty() {
+          self.entry = Some(f);
+        } else {
+          self.fn_mapping.insert(f.name.clone(), self.functions.len());
+          self.functions.push(f);
+        }
+      }
+    }
+  }
+
+  fn push_if_scope(&mut self, condition: ShaderNodeRawHandle) {
+    self
+      .block
+      .push((Default::default(), BlockBuildingState::IfAccept));
+    self.control_structure.push(CpuShaderStatement::If {
+      condition,
+      accept: Arc::new([]),
+      reject: Arc::new([]),
+    });
+  }
+
+  fn push_else_scope(&mut self) {
+    // find last if block in the top level statements
+    let top_statements = &mut self.block.last_mut().unwrap().0;
+    let index = top_statements
+      .iter()
+      .rposition(|s| matches!(s, CpuShaderStatement::If { .. }))
+      .expect("expect if clause");
+    let if_s = top_statements.remove(index);
+
+    self.control_structure.push(if_s);
+    self
+      .block
+      .push((Default::default(), BlockBuildingState::Else));
+  }
+
+  fn push_loop_scope(&mut self) {
+    self
+      .block
+      .push((Default::default(), BlockBuildingState::Loop));
+    self
+      .control_structure
+      .push(CpuShaderStatement::Loop(Arc::new([])));
+  }
+
+  fn do_continue(&mut self) {
+    self.push_top_statement(CpuShaderStatement::Continue);
+  }
+
+  fn do_break(&mut self) {
+    self.push_top_statement(CpuShaderStatement::Break);
+  }
+
+  fn begin_switch(&mut self, switch_target: ShaderNodeRawHandle) {
+    self.control_structure.push(CpuShaderStatement::Switch {
+      selector: switch_target,
+      cases: Default::default(),
+    });
+  }
+
+  fn push_switch_case_scope(&mut self, case: SwitchCaseCondition) {
+    self.block.push((
+      Default::default(),
+      BlockBuildingState::SwitchCase(case.into()),
+    ));
+  }
+
+  fn end_switch(&mut self) {
+    let switch = self.control_structure.pop().unwrap();
+    assert!(matches!(switch, CpuShaderStatement::Switch { .. }));
+    self.push_top_statement(switch);
+  }
+
+  fn discard(&mut self) {
+    self.push_top_statement(CpuShaderStatement::Discard);
+  }
+
+  fn get_fn(&mut self, name: String) -> Option<ShaderUserDefinedFunction> {
+    self
+      .fn_mapping
+      .contains_key(&name)
+      .then_some(ShaderUserDefinedFunction { name })
+  }
+
+  fn begin_define_fn(&mut self, name: String, _return_ty: Option<ShaderValueType>) {
+    if self.building_fn.iter().any(|f| f.name == name) {
+      panic!("recursive fn definition is not allowed")
+    }
+    assert!(
+      !self.fn_mapping.contains_key(&name),
+      "function redefinition"
+    );
+
+    self.building_fn.push(CpuShaderFunctionBuilding {
+      name,
+      parameters: Default::default(),
+      locals: Default::default(),
+    });
+    self
+      .block
+      .push((Default::default(), BlockBuildingState::Function));
+  }
+
+  fn push_fn_parameter(&mut self, _ty: ShaderValueType) -> ShaderNodeRawHandle {
+    let handle = self.make_new_handle();
+    self.building_fn.last_mut().unwrap().parameters.push(handle);
+    handle
+  }
+
+  fn do_return(&mut self, v: Option<ShaderNodeRawHandle>) {
+    self.push_top_statement(CpuShaderStatement::Return(v));
+  }
+
+  fn end_fn_define(&mut self) -> ShaderUserDefinedFunction {
+    let (_, s) = self.block.last().unwrap();
+    assert!(matches!(s, BlockBuildingState::Function));
+    let name = self.building_fn.last().unwrap().name.clone();
+    self.pop_scope();
+    ShaderUserDefinedFunction { name }
+  }
+
+  fn log_build_result(&mut self) {}
+
+  fn build(&mut self) -> (String, Box<dyn Any>) {
+    self.pop_scope();
+
+    let module = CpuShaderModule {
+      stage: self.stage,
+      workgroup_size: self.workgroup_size,
+      entry: self.entry.take().unwrap(),
+      functions: std::mem::take(&mut self.functions),
+      globals: std::mem::take(&mut self.globals),
+      inputs: std::mem::take(&mut self.inputs),
+      max_steps_per_invocation: DEFAULT_MAX_STEPS_PER_INVOCATION,
+    };
+
+    (ENTRY_POINT_NAME.to_owned(), Box::new(module))
+  }
+}
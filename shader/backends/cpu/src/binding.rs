@@ -0,0 +1,303 @@
+use crate::*;
+
+/// The host buffer, the content is the gpu layout bytes. The writeable binding will write
+/// back the buffer when the dispatch finished.
+#[derive(Clone, Default)]
+pub struct CpuShaderBuffer {
+  inner: Arc<RwLock<Vec<u8>>>,
+}
+
+impl CpuShaderBuffer {
+  pub fn new(bytes: Vec<u8>) -> Self {
+    Self {
+      inner: Arc::new(RwLock::new(bytes)),
+    }
+  }
+
+  pub fn zeroed(byte_size: usize) -> Self {
+    Self::new(vec![0; byte_size])
+  }
+
+  pub fn byte_size(&self) -> usize {
+    self.inner.read().len()
+  }
+
+  pub fn read_bytes(&self) -> Vec<u8> {
+    self.inner.read().clone()
+  }
+
+  pub fn write_bytes(&self, bytes: &[u8]) {
+    *self.inner.write() = bytes.to_vec();
+  }
+
+  pub(crate) fn read(&self) -> parking_lot::RwLockReadGuard<'_, Vec<u8>> {
+    self.inner.read()
+  }
+
+  pub(crate) fn write(&self) -> parking_lot::RwLockWriteGuard<'_, Vec<u8>> {
+    self.inner.write()
+  }
+}
+
+#[derive(Clone)]
+pub enum CpuShaderBindingResource {
+  Buffer(CpuShaderBuffer),
+  Texture(CpuShaderTexture),
+  Sampler(CpuShaderSampler),
+}
+
+pub trait CpuShaderBindingSource {
+  fn binding_resource(&self) -> CpuShaderBindingResource;
+}
+
+/// Collect the binding resources in the same order as the shader building side's binding
+/// calls, the usage is same as the gpu side's BindingBuilder.
+#[derive(Default)]
+pub struct CpuShaderBindingBuilder {
+  groups: [Vec<CpuShaderBindingResource>; 5],
+  current_index: usize,
+}
+
+impl CpuShaderBindingBuilder {
+  pub fn set_binding_slot(&mut self, new: usize) -> usize {
+    std::mem::replace(&mut self.current_index, new)
+  }
+
+  pub fn with_bind(mut self, item: &impl CpuShaderBindingSource) -> Self {
+    self.bind(item);
+    self
+  }
+
+  pub fn bind(&mut self, item: &impl CpuShaderBindingSource) -> &mut Self {
+    self.groups[self.current_index].push(item.binding_resource());
+    self
+  }
+
+  pub(crate) fn get(&self, (group, entry): (usize, usize)) -> Option<&CpuShaderBindingResource> {
+    self.groups.get(group)?.get(entry)
+  }
+}
+
+macro_rules! impl_buffer_view {
+  ($name: tt, $doc: literal) => {
+    #[doc = $doc]
+    pub struct $name<T: ?Sized> {
+      buffer: CpuShaderBuffer,
+      phantom: PhantomData<T>,
+    }
+
+    impl<T: ?Sized> Clone for $name<T> {
+      fn clone(&self) -> Self {
+        Self {
+          buffer: self.buffer.clone(),
+          phantom: PhantomData,
+        }
+      }
+    }
+
+    impl<T: ?Sized> $name<T> {
+      pub fn new_from_buffer(buffer: CpuShaderBuffer) -> Self {
+        Self {
+          buffer,
+          phantom: PhantomData,
+        }
+      }
+
+      pub fn buffer(&self) -> &CpuShaderBuffer {
+        &self.buffer
+      }
+    }
+
+    impl<T: ?Sized> CpuShaderBindingSource for $name<T> {
+      fn binding_resource(&self) -> CpuShaderBindingResource {
+        CpuShaderBindingResource::Buffer(self.buffer.clone())
+      }
+    }
+  };
+}
+
+impl_buffer_view!(
+  CpuStorageBufferDataView,
+  "The read write storage buffer binding"
+);
+impl_buffer_view!(
+  CpuStorageBufferReadonlyDataView,
+  "The readonly storage buffer binding"
+);
+impl_buffer_view!(CpuUniformBufferDataView, "The uniform buffer binding");
+
+impl<T: Std430MaybeUnsized + ?Sized> CpuStorageBufferDataView<T> {
+  pub fn new(data: &T) -> Self {
+    Self::new_from_buffer(CpuShaderBuffer::new(data.bytes().to_vec()))
+  }
+
+  pub fn read(&self) -> Box<T> {
+    T::from_bytes_into_boxed(&self.buffer.read())
+  }
+}
+
+impl<T: Std430MaybeUnsized + ?Sized> CpuStorageBufferReadonlyDataView<T> {
+  pub fn new(data: &T) -> Self {
+    Self::new_from_buffer(CpuShaderBuffer::new(data.bytes().to_vec()))
+  }
+}
+
+impl<T: Std140> CpuUniformBufferDataView<T> {
+  pub fn new(data: &T) -> Self {
+    Self::new_from_buffer(CpuShaderBuffer::new(bytes_of(data).to_vec()))
+  }
+}
+
+impl<T> ShaderBindingProvider for CpuUniformBufferDataView<T>
+where
+  T: ShaderSizedValueNodeType + Std140 + SizedShaderAbstractPtrAccess,
+{
+  type Node = ShaderBinding<T>;
+  type ShaderInstance = ShaderReadonlyPtrOf<T>;
+  fn create_instance(&self, node: Node<Self::Node>) -> Self::ShaderInstance {
+    T::create_readonly_view_from_raw_ptr(Box::new(node.handle()))
+  }
+}
+
+impl<T> ShaderBindingProvider for CpuStorageBufferReadonlyDataView<T>
+where
+  T: ShaderMaybeUnsizedValueNodeType + Std430MaybeUnsized + ShaderAbstractPtrAccess + ?Sized,
+{
+  type Node = ShaderBinding<T>;
+  type ShaderInstance = ShaderReadonlyPtrOf<T>;
+  fn create_instance(&self, node: Node<Self::Node>) -> Self::ShaderInstance {
+    T::create_readonly_view_from_raw_ptr(Box::new(node.handle()))
+  }
+
+  fn binding_desc(&self) -> ShaderBindingDescriptor {
+    ShaderBindingDescriptor {
+      should_as_storage_buffer_if_is_buffer_like: true,
+      writeable_if_storage: false,
+      ty: Self::Node::ty(),
+    }
+  }
+}
+
+impl<T> ShaderBindingProvider for CpuStorageBufferDataView<T>
+where
+  T: ShaderMaybeUnsizedValueNodeType + Std430MaybeUnsized + ShaderAbstractPtrAccess + ?Sized,
+{
+  type Node = ShaderBinding<T>;
+  type ShaderInstance = ShaderPtrOf<T>;
+  fn create_instance(&self, node: Node<Self::Node>) -> Self::ShaderInstance {
+    T::create_view_from_raw_ptr(Box::new(node.handle()))
+  }
+
+  fn binding_desc(&self) -> ShaderBindingDescriptor {
+    ShaderBindingDescriptor {
+      should_as_storage_buffer_if_is_buffer_like: true,
+      writeable_if_storage: true,
+      ty: Self::Node::ty(),
+    }
+  }
+}
+
+pub struct CpuTextureView<D, F> {
+  pub texture: CpuShaderTexture,
+  phantom: PhantomData<(D, F)>,
+}
+
+impl<D, F> CpuTextureView<D, F> {
+  pub fn new(texture: CpuShaderTexture) -> Self {
+    Self {
+      texture,
+      phantom: PhantomData,
+    }
+  }
+}
+
+impl<D, F> CpuShaderBindingSource for CpuTextureView<D, F> {
+  fn binding_resource(&self) -> CpuShaderBindingResource {
+    CpuShaderBindingResource::Texture(self.texture.clone())
+  }
+}
+
+impl<D, F> ShaderBindingProvider for CpuTextureView<D, F>
+where
+  D: ShaderTextureDimension,
+  F: ShaderTextureKind,
+{
+  type Node = ShaderBinding<ShaderTexture<D, F>>;
+  fn create_instance(&self, node: Node<Self::Node>) -> Self::ShaderInstance {
+    node
+  }
+}
+
+pub struct CpuStorageTextureView<A, D, F> {
+  pub texture: CpuShaderTexture,
+  pub format: StorageFormat,
+  phantom: PhantomData<(A, D, F)>,
+}
+
+impl<A, D, F> CpuStorageTextureView<A, D, F> {
+  pub fn new(texture: CpuShaderTexture, format: StorageFormat) -> Self {
+    Self {
+      texture,
+      format,
+      phantom: PhantomData,
+    }
+  }
+}
+
+impl<A, D, F> CpuShaderBindingSource for CpuStorageTextureView<A, D, F> {
+  fn binding_resource(&self) -> CpuShaderBindingResource {
+    CpuShaderBindingResource::Texture(self.texture.clone())
+  }
+}
+
+impl<A, D, F> ShaderBindingProvider for CpuStorageTextureView<A, D, F>
+where
+  A: StorageTextureAccessMarker,
+  D: ShaderTextureDimension,
+  F: ShaderTextureKind,
+{
+  type Node = ShaderBinding<ShaderStorageTexture<A, D, F>>;
+  fn create_instance(&self, node: Node<Self::Node>) -> Self::ShaderInstance {
+    node
+  }
+
+  fn binding_desc(&self) -> ShaderBindingDescriptor {
+    let mut ty = Self::Node::ty();
+
+    if let ShaderValueType::Single(ShaderValueSingleType::StorageTexture { format, .. }) = &mut ty {
+      *format = self.format;
+    }
+
+    ShaderBindingDescriptor {
+      should_as_storage_buffer_if_is_buffer_like: false,
+      writeable_if_storage: false,
+      ty,
+    }
+  }
+}
+
+impl CpuShaderBindingSource for CpuShaderSampler {
+  fn binding_resource(&self) -> CpuShaderBindingResource {
+    CpuShaderBindingResource::Sampler(*self)
+  }
+}
+
+impl CpuShaderBindingSource for CpuShaderComparisonSampler {
+  fn binding_resource(&self) -> CpuShaderBindingResource {
+    CpuShaderBindingResource::Sampler(self.0)
+  }
+}
+
+impl ShaderBindingProvider for CpuShaderSampler {
+  type Node = ShaderBinding<ShaderSampler>;
+  fn create_instance(&self, node: Node<Self::Node>) -> Self::ShaderInstance {
+    node
+  }
+}
+
+impl ShaderBindingProvider for CpuShaderComparisonSampler {
+  type Node = ShaderBinding<ShaderCompareSampler>;
+  fn create_instance(&self, node: Node<Self::Node>) -> Self::ShaderInstance {
+    node
+  }
+}
@@ -0,0 +1,789 @@
+use crate::*;
+
+type EvalResult = Result<CpuShaderValue, CpuShaderExecutionError>;
+
+fn as_f32(v: ScalarValue) -> Result<f32, CpuShaderExecutionError> {
+  match v {
+    ScalarValue::F32(v) => Ok(v),
+    v => Err(CpuShaderExecutionError::type_mismatch(
+      "f32",
+      &CpuShaderValue::Scalar(v),
+    )),
+  }
+}
+
+fn as_u32(v: ScalarValue) -> Result<u32, CpuShaderExecutionError> {
+  match v {
+    ScalarValue::U32(v) => Ok(v),
+    ScalarValue::I32(v) => Ok(v as u32),
+    v => Err(CpuShaderExecutionError::type_mismatch(
+      "integer",
+      &CpuShaderValue::Scalar(v),
+    )),
+  }
+}
+
+fn f32_components(v: &CpuShaderValue) -> Result<Vec<f32>, CpuShaderExecutionError> {
+  v.components()?.iter().map(|v| as_f32(*v)).collect()
+}
+
+fn from_f32_components(v: Vec<f32>) -> CpuShaderValue {
+  CpuShaderValue::from_components(v.into_iter().map(ScalarValue::F32).collect())
+}
+
+fn matrix_f32(v: &CpuShaderValue) -> Result<Vec<Vec<f32>>, CpuShaderExecutionError> {
+  match v {
+    CpuShaderValue::Matrix(columns) => columns
+      .iter()
+      .map(|c| c.iter().map(|v| as_f32(*v)).collect())
+      .collect(),
+    _ => Err(CpuShaderExecutionError::type_mismatch("matrix", v)),
+  }
+}
+
+fn from_matrix_f32(columns: Vec<Vec<f32>>) -> CpuShaderValue {
+  CpuShaderValue::Matrix(
+    columns
+      .into_iter()
+      .map(|c| c.into_iter().map(ScalarValue::F32).collect())
+      .collect(),
+  )
+}
+
+fn map_components(
+  v: &CpuShaderValue,
+  f: impl Fn(ScalarValue) -> Result<ScalarValue, CpuShaderExecutionError>,
+) -> EvalResult {
+  match v {
+    CpuShaderValue::Matrix(columns) => Ok(CpuShaderValue::Matrix(
+      columns
+        .iter()
+        .map(|c| c.iter().map(|v| f(*v)).collect())
+        .collect::<Result<_, _>>()?,
+    )),
+    v => Ok(CpuShaderValue::from_components(
+      v.components()?
+        .iter()
+        .map(|v| f(*v))
+        .collect::<Result<_, _>>()?,
+    )),
+  }
+}
+
+fn map_f32(v: &CpuShaderValue, f: impl Fn(f32) -> f32) -> EvalResult {
+  map_components(v, |v| Ok(ScalarValue::F32(f(as_f32(v)?))))
+}
+
+/// apply the function component wise, the scalar operand is broadcast to the vector
+fn zip_components(
+  values: &[&CpuShaderValue],
+  f: impl Fn(&[ScalarValue]) -> Result<ScalarValue, CpuShaderExecutionError>,
+) -> EvalResult {
+  let components = values
+    .iter()
+    .map(|v| v.components())
+    .collect::<Result<Vec<_>, _>>()?;
+  let len = components.iter().map(|c| c.len()).max().unwrap_or(1);
+  let mut args = Vec::with_capacity(values.len());
+  let result = (0..len)
+    .map(|i| {
+      args.clear();
+      for c in &components {
+        if c.len() == 1 {
+          args.push(c[0]);
+        } else {
+          args.push(*c.get(i).ok_or(CpuShaderExecutionError::InvalidOperation(
+            "component count mismatch",
+          ))?);
+        }
+      }
+      f(&args)
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  Ok(CpuShaderValue::from_components(result))
+}
+
+fn zip_f32(values: &[&CpuShaderValue], f: impl Fn(&[f32]) -> f32) -> EvalResult {
+  zip_components(values, |args| {
+    let args = args
+      .iter()
+      .map(|v| as_f32(*v))
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(ScalarValue::F32(f(&args)))
+  })
+}
+
+pub(crate) fn eval_unary(operator: &UnaryOperator, one: &CpuShaderValue) -> EvalResult {
+  map_components(one, |v| {
+    Ok(match (operator, v) {
+      (UnaryOperator::LogicalNot, ScalarValue::Bool(v)) => ScalarValue::Bool(!v),
+      (UnaryOperator::BitwiseNot, ScalarValue::U32(v)) => ScalarValue::U32(!v),
+      (UnaryOperator::BitwiseNot, ScalarValue::I32(v)) => ScalarValue::I32(!v),
+      (UnaryOperator::Neg, ScalarValue::F32(v)) => ScalarValue::F32(-v),
+      (UnaryOperator::Neg, ScalarValue::I32(v)) => ScalarValue::I32(v.wrapping_neg()),
+      _ => return Err(CpuShaderExecutionError::InvalidOperation("unary operand")),
+    })
+  })
+}
+
+fn compare<T: PartialOrd>(operator: &BinaryOperator, a: T, b: T) -> Option<bool> {
+  Some(match operator {
+    BinaryOperator::Eq => a == b,
+    BinaryOperator::NotEq => a != b,
+    BinaryOperator::GreaterThan => a > b,
+    BinaryOperator::LessThan => a < b,
+    BinaryOperator::GreaterEqualThan => a >= b,
+    BinaryOperator::LessEqualThan => a <= b,
+    _ => return None,
+  })
+}
+
+/// the integer arithmetic is wrapping, and the division by zero returns the left operand
+/// (the remainder returns zero) as the wgsl spec required.
+fn eval_scalar_binary(
+  operator: &BinaryOperator,
+  left: ScalarValue,
+  right: ScalarValue,
+) -> Result<ScalarValue, CpuShaderExecutionError> {
+  use BinaryOperator as Op;
+  use ScalarValue as S;
+  let invalid = || CpuShaderExecutionError::InvalidOperation("binary operand");
+  Ok(match (left, right) {
+    (S::F32(a), S::F32(b)) => match operator {
+      Op::Add => S::F32(a + b),
+      Op::Sub => S::F32(a - b),
+      Op::Mul => S::F32(a * b),
+      Op::Div => S::F32(a / b),
+      Op::Rem => S::F32(a % b),
+      op => S::Bool(compare(op, a, b).ok_or_else(invalid)?),
+    },
+    (S::U32(a), S::U32(b)) => match operator {
+      Op::Add => S::U32(a.wrapping_add(b)),
+      Op::Sub => S::U32(a.wrapping_sub(b)),
+      Op::Mul => S::U32(a.wrapping_mul(b)),
+      Op::Div => S::U32(a.checked_div(b).unwrap_or(a)),
+      Op::Rem => S::U32(a.checked_rem(b).unwrap_or(0)),
+      Op::BitAnd => S::U32(a & b),
+      Op::BitOr => S::U32(a | b),
+      Op::BitXor => S::U32(a ^ b),
+      Op::ShiftLeft => S::U32(a.wrapping_shl(b)),
+      Op::ShiftRight => S::U32(a.wrapping_shr(b)),
+      op => S::Bool(compare(op, a, b).ok_or_else(invalid)?),
+    },
+    (S::I32(a), S::I32(b)) => match operator {
+      Op::Add => S::I32(a.wrapping_add(b)),
+      Op::Sub => S::I32(a.wrapping_sub(b)),
+      Op::Mul => S::I32(a.wrapping_mul(b)),
+      Op::Div => S::I32(if b == 0 { a } else { a.wrapping_div(b) }),
+      Op::Rem => S::I32(if b == 0 { 0 } else { a.wrapping_rem(b) }),
+      Op::BitAnd => S::I32(a & b),
+      Op::BitOr => S::I32(a | b),
+      Op::BitXor => S::I32(a ^ b),
+      op => S::Bool(compare(op, a, b).ok_or_else(invalid)?),
+    },
+    (S::I32(a), S::U32(b)) => match operator {
+      Op::ShiftLeft => S::I32(a.wrapping_shl(b)),
+      Op::ShiftRight => S::I32(a.wrapping_shr(b)),
+      _ => return Err(invalid()),
+    },
+    (S::Bool(a), S::Bool(b)) => match operator {
+      Op::LogicalAnd | Op::BitAnd => S::Bool(a && b),
+      Op::LogicalOr | Op::BitOr => S::Bool(a || b),
+      Op::BitXor => S::Bool(a ^ b),
+      op => S::Bool(compare(op, a, b).ok_or_else(invalid)?),
+    },
+    _ => return Err(invalid()),
+  })
+}
+
+fn mat_mul_vec(m: &[Vec<f32>], v: &[f32]) -> Vec<f32> {
+  let rows = m.first().map(|c| c.len()).unwrap_or(0);
+  (0..rows)
+    .map(|r| m.iter().zip(v).map(|(c, v)| c[r] * v).sum())
+    .collect()
+}
+
+fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+  a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn transpose(m: &[Vec<f32>]) -> Vec<Vec<f32>> {
+  let rows = m.first().map(|c| c.len()).unwrap_or(0);
+  (0..rows)
+    .map(|r| m.iter().map(|c| c[r]).collect())
+    .collect()
+}
+
+pub(crate) fn eval_binary(
+  operator: &BinaryOperator,
+  left: &CpuShaderValue,
+  right: &CpuShaderValue,
+) -> EvalResult {
+  use CpuShaderValue as V;
+  match (left, right) {
+    (V::Matrix(_), V::Matrix(_)) => {
+      let a = matrix_f32(left)?;
+      let b = matrix_f32(right)?;
+      match operator {
+        BinaryOperator::Mul => Ok(from_matrix_f32(
+          b.iter().map(|c| mat_mul_vec(&a, c)).collect(),
+        )),
+        BinaryOperator::Add | BinaryOperator::Sub => {
+          let sign = if let BinaryOperator::Add = operator {
+            1.
+          } else {
+            -1.
+          };
+          Ok(from_matrix_f32(
+            a.iter()
+              .zip(&b)
+              .map(|(a, b)| a.iter().zip(b).map(|(a, b)| a + sign * b).collect())
+              .collect(),
+          ))
+        }
+        _ => Err(CpuShaderExecutionError::InvalidOperation("matrix operand")),
+      }
+    }
+    (V::Matrix(_), V::Vector(_)) => Ok(from_f32_components(mat_mul_vec(
+      &matrix_f32(left)?,
+      &f32_components(right)?,
+    ))),
+    (V::Vector(_), V::Matrix(_)) => {
+      let v = f32_components(left)?;
+      Ok(from_f32_components(
+        matrix_f32(right)?.iter().map(|c| dot_f32(c, &v)).collect(),
+      ))
+    }
+    (V::Matrix(_), V::Scalar(s)) | (V::Scalar(s), V::Matrix(_)) => {
+      let s = as_f32(*s)?;
+      let m = if let V::Matrix(_) = left { left } else { right };
+      map_f32(m, |v| v * s)
+    }
+    _ => zip_components(&[left, right], |args| {
+      eval_scalar_binary(operator, args[0], args[1])
+    }),
+  }
+}
+
+pub(crate) fn eval_convert(
+  source: &CpuShaderValue,
+  convert_to: ScalarType,
+  bitcast: bool,
+) -> EvalResult {
+  map_components(source, |v| {
+    if bitcast {
+      return Ok(decode_scalar(convert_to, scalar_bits(v)));
+    }
+    use ScalarValue as S;
+    Ok(match (v, convert_to) {
+      (v, ty) if v.ty() == ty => v,
+      (S::F32(v), ScalarType::U32) => S::U32(v as u32),
+      (S::F32(v), ScalarType::I32) => S::I32(v as i32),
+      (S::U32(v), ScalarType::F32) => S::F32(v as f32),
+      (S::U32(v), ScalarType::I32) => S::I32(v as i32),
+      (S::I32(v), ScalarType::F32) => S::F32(v as f32),
+      (S::I32(v), ScalarType::U32) => S::U32(v as u32),
+      (S::Bool(v), ScalarType::F32) => S::F32(v as u32 as f32),
+      (S::Bool(v), ScalarType::U32) => S::U32(v as u32),
+      (S::Bool(v), ScalarType::I32) => S::I32(v as i32),
+      (v, ScalarType::Bool) => S::Bool(scalar_bits(v) != 0 && v != S::F32(-0.)),
+      _ => unreachable!(),
+    })
+  })
+}
+
+pub(crate) fn eval_compose(
+  target: &ShaderSizedValueType,
+  parameters: Vec<CpuShaderValue>,
+) -> EvalResult {
+  match target {
+    ShaderSizedValueType::Primitive(PrimitiveShaderValueType::Vector { size, .. }) => {
+      let size = *size as usize;
+      let mut components = Vec::with_capacity(size);
+      for p in &parameters {
+        components.extend_from_slice(p.components()?);
+      }
+      if components.len() == 1 {
+        components = vec![components[0]; size];
+      }
+      if components.len() != size {
+        return Err(CpuShaderExecutionError::InvalidOperation(
+          "vector compose component count mismatch",
+        ));
+      }
+      Ok(CpuShaderValue::Vector(components))
+    }
+    ShaderSizedValueType::Primitive(PrimitiveShaderValueType::Matrix { columns, rows, .. }) => {
+      let (columns, rows) = (*columns as usize, *rows as usize);
+      if parameters.len() == columns {
+        parameters
+          .iter()
+          .map(|c| c.components().map(|c| c.to_vec()))
+          .collect::<Result<_, _>>()
+          .map(CpuShaderValue::Matrix)
+      } else {
+        let mut components = Vec::with_capacity(columns * rows);
+        for p in &parameters {
+          components.extend_from_slice(p.components()?);
+        }
+        if components.len() != columns * rows {
+          return Err(CpuShaderExecutionError::InvalidOperation(
+            "matrix compose component count mismatch",
+          ));
+        }
+        Ok(CpuShaderValue::Matrix(
+          components.chunks(rows).map(|c| c.to_vec()).collect(),
+        ))
+      }
+    }
+    ShaderSizedValueType::Primitive(PrimitiveShaderValueType::Scalar(_))
+    | ShaderSizedValueType::Atomic(_) => parameters
+      .into_iter()
+      .next()
+      .ok_or(CpuShaderExecutionError::InvalidOperation("empty compose")),
+    ShaderSizedValueType::Struct(_) | ShaderSizedValueType::FixedSizeArray(..) => {
+      Ok(CpuShaderValue::Composite(parameters))
+    }
+  }
+}
+
+pub(crate) fn eval_swizzle(source: &CpuShaderValue, components: &[usize]) -> EvalResult {
+  let source = source.components()?;
+  components
+    .iter()
+    .map(|i| {
+      source
+        .get(*i)
+        .copied()
+        .ok_or(CpuShaderExecutionError::IndexOutOfBounds(*i))
+    })
+    .collect::<Result<_, _>>()
+    .map(CpuShaderValue::from_components)
+}
+
+/// compute the atomic result, returns the value to be stored and the expression result
+pub(crate) fn eval_atomic(
+  function: &AtomicFunction,
+  old: &CpuShaderValue,
+  value: &CpuShaderValue,
+  compare: Option<&CpuShaderValue>,
+) -> Result<(CpuShaderValue, CpuShaderValue), CpuShaderExecutionError> {
+  let op = match function {
+    AtomicFunction::Add => BinaryOperator::Add,
+    AtomicFunction::Subtract => BinaryOperator::Sub,
+    AtomicFunction::And => BinaryOperator::BitAnd,
+    AtomicFunction::ExclusiveOr => BinaryOperator::BitXor,
+    AtomicFunction::InclusiveOr => BinaryOperator::BitOr,
+    AtomicFunction::Min | AtomicFunction::Max => {
+      let f = if let AtomicFunction::Min = function {
+        ShaderBuiltInFunction::Min
+      } else {
+        ShaderBuiltInFunction::Max
+      };
+      let new = eval_builtin(f, &[old.clone(), value.clone()])?;
+      return Ok((new, old.clone()));
+    }
+    AtomicFunction::Exchange { .. } => {
+      return Ok(match compare {
+        Some(compare) => {
+          let exchanged = old == compare;
+          let new = if exchanged { value } else { old };
+          let result = CpuShaderValue::Composite(vec![
+            old.clone(),
+            CpuShaderValue::Scalar(ScalarValue::Bool(exchanged)),
+          ]);
+          (new.clone(), result)
+        }
+        None => (value.clone(), old.clone()),
+      });
+    }
+  };
+  Ok((eval_binary(&op, old, value)?, old.clone()))
+}
+
+fn length(v: &[f32]) -> f32 {
+  dot_f32(v, v).sqrt()
+}
+
+fn frexp(x: f32) -> (f32, i32) {
+  if x == 0. || !x.is_finite() {
+    return (x, 0);
+  }
+  let (x, bias) = if x.is_subnormal() {
+    (x * (1u64 << 32) as f32, -32)
+  } else {
+    (x, 0)
+  };
+  let bits = x.to_bits();
+  let exp = ((bits >> 23) & 0xff) as i32 - 126;
+  let fract = f32::from_bits((bits & 0x807f_ffff) | (126 << 23));
+  (fract, exp + bias)
+}
+
+fn determinant(m: &[Vec<f32>]) -> f32 {
+  let n = m.len();
+  let mut a = m.to_vec();
+  let mut det = 1.;
+  for i in 0..n {
+    let pivot = (i..n)
+      .max_by(|x, y| a[*x][i].abs().total_cmp(&a[*y][i].abs()))
+      .unwrap();
+    if a[pivot][i] == 0. {
+      return 0.;
+    }
+    if pivot != i {
+      a.swap(pivot, i);
+      det = -det;
+    }
+    det *= a[i][i];
+    let (top, bottom) = a.split_at_mut(i + 1);
+    let row = &top[i];
+    for other in bottom {
+      let factor = other[i] / row[i];
+      for (v, r) in other[i..].iter_mut().zip(&row[i..]) {
+        *v -= factor * r;
+      }
+    }
+  }
+  det
+}
+
+/// gauss jordan elimination, the matrix is stored in column major but the inverse of the
+/// transpose is the transpose of the inverse so the layout does not matter here.
+fn inverse(m: &[Vec<f32>]) -> Vec<Vec<f32>> {
+  let n = m.len();
+  let mut a = m.to_vec();
+  let mut inv: Vec<Vec<f32>> = (0..n)
+    .map(|i| (0..n).map(|j| if i == j { 1. } else { 0. }).collect())
+    .collect();
+  for i in 0..n {
+    let pivot = (i..n)
+      .max_by(|x, y| a[*x][i].abs().total_cmp(&a[*y][i].abs()))
+      .unwrap();
+    a.swap(pivot, i);
+    inv.swap(pivot, i);
+    let p = a[i][i];
+    for k in 0..n {
+      a[i][k] /= p;
+      inv[i][k] /= p;
+    }
+    for j in 0..n {
+      if j != i {
+        let factor = a[j][i];
+        for k in 0..n {
+          a[j][k] -= factor * a[i][k];
+          inv[j][k] -= factor * inv[i][k];
+        }
+      }
+    }
+  }
+  inv
+}
+
+fn extract_bits(e: ScalarValue, offset: u32, count: u32) -> ScalarValue {
+  let o = offset.min(32);
+  let c = count.min(32 - o);
+  match e {
+    ScalarValue::I32(e) if c > 0 => ScalarValue::I32((e << (32 - o - c)) >> (32 - c)),
+    ScalarValue::I32(_) => ScalarValue::I32(0),
+    e => {
+      let e = scalar_bits(e);
+      let mask = if c == 32 { u32::MAX } else { (1 << c) - 1 };
+      ScalarValue::U32(e.checked_shr(o).unwrap_or(0) & mask)
+    }
+  }
+}
+
+fn insert_bits(e: ScalarValue, new: ScalarValue, offset: u32, count: u32) -> ScalarValue {
+  let o = offset.min(32);
+  let c = count.min(32 - o);
+  let mask = if c == 32 {
+    u32::MAX
+  } else {
+    ((1u32 << c) - 1).checked_shl(o).unwrap_or(0)
+  };
+  let r = (scalar_bits(e) & !mask) | (scalar_bits(new).checked_shl(o).unwrap_or(0) & mask);
+  decode_scalar(e.ty(), r)
+}
+
+fn pack(v: &[f32], bits: u32, f: impl Fn(f32) -> u32) -> u32 {
+  v.iter()
+    .enumerate()
+    .fold(0, |r, (i, v)| r | (f(*v) << (bits * i as u32)))
+}
+
+fn unpack(v: u32, count: u32, f: impl Fn(u32) -> f32) -> CpuShaderValue {
+  let bits = 32 / count;
+  let mask = (1u32 << bits) - 1;
+  from_f32_components((0..count).map(|i| f((v >> (bits * i)) & mask)).collect())
+}
+
+pub(crate) fn eval_builtin(
+  function: ShaderBuiltInFunction,
+  params: &[CpuShaderValue],
+) -> EvalResult {
+  use ScalarValue as S;
+  use ShaderBuiltInFunction as F;
+  let p = |i: usize| {
+    params
+      .get(i)
+      .ok_or(CpuShaderExecutionError::InvalidOperation(
+        "builtin function parameter count mismatch",
+      ))
+  };
+  let f32_of = |i: usize| p(i).and_then(f32_components);
+  let scalar_f32 = |v: f32| CpuShaderValue::Scalar(S::F32(v));
+
+  match function {
+    F::Select => {
+      let (reject, accept, condition) = (p(0)?, p(1)?, p(2)?);
+      match condition {
+        CpuShaderValue::Scalar(c) => Ok(if *c == S::Bool(true) {
+          accept.clone()
+        } else {
+          reject.clone()
+        }),
+        _ => zip_components(&[reject, accept, condition], |args| {
+          Ok(if args[2] == S::Bool(true) {
+            args[1]
+          } else {
+            args[0]
+          })
+        }),
+      }
+    }
+    F::All | F::Any => {
+      let components = p(0)?.components()?;
+      let test = |v: &ScalarValue| *v == S::Bool(true);
+      let r = if let F::All = function {
+        components.iter().all(test)
+      } else {
+        components.iter().any(test)
+      };
+      Ok(CpuShaderValue::Scalar(S::Bool(r)))
+    }
+    F::IsNan => map_components(p(0)?, |v| Ok(S::Bool(as_f32(v)?.is_nan()))),
+    F::IsInf => map_components(p(0)?, |v| Ok(S::Bool(as_f32(v)?.is_infinite()))),
+    F::Abs => map_components(p(0)?, |v| {
+      Ok(match v {
+        S::F32(v) => S::F32(v.abs()),
+        S::I32(v) => S::I32(v.wrapping_abs()),
+        v => v,
+      })
+    }),
+    F::Min | F::Max => {
+      let is_min = matches!(function, F::Min);
+      zip_components(&[p(0)?, p(1)?], |args| {
+        Ok(match (args[0], args[1]) {
+          (S::F32(a), S::F32(b)) => S::F32(if is_min { a.min(b) } else { a.max(b) }),
+          (S::U32(a), S::U32(b)) => S::U32(if is_min { a.min(b) } else { a.max(b) }),
+          (S::I32(a), S::I32(b)) => S::I32(if is_min { a.min(b) } else { a.max(b) }),
+          _ => return Err(CpuShaderExecutionError::InvalidOperation("min max operand")),
+        })
+      })
+    }
+    F::Clamp => {
+      let low = eval_builtin(F::Max, &[p(0)?.clone(), p(1)?.clone()])?;
+      eval_builtin(F::Min, &[low, p(2)?.clone()])
+    }
+    F::Saturate => map_f32(p(0)?, |v| v.clamp(0., 1.)),
+    F::Cos => map_f32(p(0)?, f32::cos),
+    F::Cosh => map_f32(p(0)?, f32::cosh),
+    F::Sin => map_f32(p(0)?, f32::sin),
+    F::Sinh => map_f32(p(0)?, f32::sinh),
+    F::Tan => map_f32(p(0)?, f32::tan),
+    F::Tanh => map_f32(p(0)?, f32::tanh),
+    F::Acos => map_f32(p(0)?, f32::acos),
+    F::Asin => map_f32(p(0)?, f32::asin),
+    F::Atan => map_f32(p(0)?, f32::atan),
+    F::Atan2 => zip_f32(&[p(0)?, p(1)?], |a| a[0].atan2(a[1])),
+    F::Asinh => map_f32(p(0)?, f32::asinh),
+    F::Acosh => map_f32(p(0)?, f32::acosh),
+    F::Atanh => map_f32(p(0)?, f32::atanh),
+    F::Radians => map_f32(p(0)?, f32::to_radians),
+    F::Degrees => map_f32(p(0)?, f32::to_degrees),
+    F::Ceil => map_f32(p(0)?, f32::ceil),
+    F::Floor => map_f32(p(0)?, f32::floor),
+    F::Round => map_f32(p(0)?, f32::round_ties_even),
+    F::Fract => map_f32(p(0)?, |v| v - v.floor()),
+    F::Trunc => map_f32(p(0)?, f32::trunc),
+    F::Modf => Ok(CpuShaderValue::Composite(vec![
+      map_f32(p(0)?, f32::fract)?,
+      map_f32(p(0)?, f32::trunc)?,
+    ])),
+    F::Frexp => {
+      let (fract, exp): (Vec<_>, Vec<_>) = f32_of(0)?.into_iter().map(frexp).unzip();
+      Ok(CpuShaderValue::Composite(vec![
+        from_f32_components(fract),
+        CpuShaderValue::from_components(exp.into_iter().map(S::I32).collect()),
+      ]))
+    }
+    F::Ldexp => zip_components(&[p(0)?, p(1)?], |args| match (args[0], args[1]) {
+      (S::F32(e1), S::I32(e2)) => Ok(S::F32(e1 * 2f32.powi(e2))),
+      _ => Err(CpuShaderExecutionError::InvalidOperation("ldexp operand")),
+    }),
+    F::Exp => map_f32(p(0)?, f32::exp),
+    F::Exp2 => map_f32(p(0)?, f32::exp2),
+    F::Log => map_f32(p(0)?, f32::ln),
+    F::Log2 => map_f32(p(0)?, f32::log2),
+    F::Pow => zip_f32(&[p(0)?, p(1)?], |a| a[0].powf(a[1])),
+    F::Dot => {
+      let a = p(0)?.components()?;
+      let b = p(1)?.components()?;
+      a.iter()
+        .zip(b)
+        .try_fold(None, |sum: Option<ScalarValue>, (a, b)| {
+          let v = eval_scalar_binary(&BinaryOperator::Mul, *a, *b)?;
+          match sum {
+            Some(sum) => eval_scalar_binary(&BinaryOperator::Add, sum, v).map(Some),
+            None => Ok(Some(v)),
+          }
+        })?
+        .map(CpuShaderValue::Scalar)
+        .ok_or(CpuShaderExecutionError::InvalidOperation("dot operand"))
+    }
+    F::Outer => {
+      let a = f32_of(0)?;
+      let b = f32_of(1)?;
+      Ok(from_matrix_f32(
+        b.iter()
+          .map(|b| a.iter().map(|a| a * b).collect())
+          .collect(),
+      ))
+    }
+    F::Cross => {
+      let a = f32_of(0)?;
+      let b = f32_of(1)?;
+      if a.len() != 3 || b.len() != 3 {
+        return Err(CpuShaderExecutionError::InvalidOperation("cross operand"));
+      }
+      Ok(from_f32_components(vec![
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+      ]))
+    }
+    F::Distance => {
+      let a = f32_of(0)?;
+      let b = f32_of(1)?;
+      let d: Vec<_> = a.iter().zip(&b).map(|(a, b)| a - b).collect();
+      Ok(scalar_f32(length(&d)))
+    }
+    F::Length => Ok(scalar_f32(length(&f32_of(0)?))),
+    F::Normalize => {
+      let v = f32_of(0)?;
+      let l = length(&v);
+      Ok(from_f32_components(v.iter().map(|v| v / l).collect()))
+    }
+    F::FaceForward => {
+      let e1 = f32_of(0)?;
+      let d = dot_f32(&f32_of(1)?, &f32_of(2)?);
+      let sign = if d < 0. { 1. } else { -1. };
+      Ok(from_f32_components(e1.iter().map(|v| v * sign).collect()))
+    }
+    F::Reflect => {
+      let e1 = f32_of(0)?;
+      let e2 = f32_of(1)?;
+      let d = dot_f32(&e2, &e1);
+      Ok(from_f32_components(
+        e1.iter().zip(&e2).map(|(a, b)| a - 2. * d * b).collect(),
+      ))
+    }
+    F::Refract => {
+      let e1 = f32_of(0)?;
+      let e2 = f32_of(1)?;
+      let eta = f32_of(2)?[0];
+      let d = dot_f32(&e2, &e1);
+      let k = 1. - eta * eta * (1. - d * d);
+      if k < 0. {
+        return Ok(from_f32_components(vec![0.; e1.len()]));
+      }
+      Ok(from_f32_components(
+        e1.iter()
+          .zip(&e2)
+          .map(|(a, b)| eta * a - (eta * d + k.sqrt()) * b)
+          .collect(),
+      ))
+    }
+    F::Sign => map_components(p(0)?, |v| {
+      Ok(match v {
+        S::F32(v) => S::F32(if v > 0. {
+          1.
+        } else if v < 0. {
+          -1.
+        } else {
+          0.
+        }),
+        S::I32(v) => S::I32(v.signum()),
+        v => v,
+      })
+    }),
+    F::Fma => zip_f32(&[p(0)?, p(1)?, p(2)?], |a| a[0].mul_add(a[1], a[2])),
+    F::Mix => zip_f32(&[p(0)?, p(1)?, p(2)?], |a| a[0] * (1. - a[2]) + a[1] * a[2]),
+    F::Step => zip_f32(&[p(0)?, p(1)?], |a| if a[1] >= a[0] { 1. } else { 0. }),
+    F::SmoothStep => zip_f32(&[p(0)?, p(1)?, p(2)?], |a| {
+      let t = ((a[2] - a[0]) / (a[1] - a[0])).clamp(0., 1.);
+      t * t * (3. - 2. * t)
+    }),
+    F::Sqrt => map_f32(p(0)?, f32::sqrt),
+    F::InverseSqrt => map_f32(p(0)?, |v| 1. / v.sqrt()),
+    F::Inverse => Ok(from_matrix_f32(inverse(&matrix_f32(p(0)?)?))),
+    F::Transpose => Ok(from_matrix_f32(transpose(&matrix_f32(p(0)?)?))),
+    F::Determinant => Ok(scalar_f32(determinant(&matrix_f32(p(0)?)?))),
+    F::CountTrailingZeros => map_components(p(0)?, |v| {
+      Ok(decode_scalar(v.ty(), scalar_bits(v).trailing_zeros()))
+    }),
+    F::CountLeadingZeros => map_components(p(0)?, |v| {
+      Ok(decode_scalar(v.ty(), scalar_bits(v).leading_zeros()))
+    }),
+    F::CountOneBits => map_components(p(0)?, |v| {
+      Ok(decode_scalar(v.ty(), scalar_bits(v).count_ones()))
+    }),
+    F::ReverseBits => map_components(p(0)?, |v| {
+      Ok(decode_scalar(v.ty(), scalar_bits(v).reverse_bits()))
+    }),
+    F::ExtractBits => {
+      let offset = as_u32(p(1)?.as_scalar()?)?;
+      let count = as_u32(p(2)?.as_scalar()?)?;
+      map_components(p(0)?, |v| Ok(extract_bits(v, offset, count)))
+    }
+    F::InsertBits => {
+      let offset = as_u32(p(2)?.as_scalar()?)?;
+      let count = as_u32(p(3)?.as_scalar()?)?;
+      zip_components(&[p(0)?, p(1)?], |args| {
+        Ok(insert_bits(args[0], args[1], offset, count))
+      })
+    }
+    F::Pack4x8snorm => Ok(CpuShaderValue::Scalar(S::U32(pack(&f32_of(0)?, 8, |v| {
+      ((v.clamp(-1., 1.) * 127.).round() as i8) as u8 as u32
+    })))),
+    F::Pack4x8unorm => Ok(CpuShaderValue::Scalar(S::U32(pack(&f32_of(0)?, 8, |v| {
+      (v.clamp(0., 1.) * 255.).round() as u32
+    })))),
+    F::Pack2x16snorm => Ok(CpuShaderValue::Scalar(S::U32(pack(&f32_of(0)?, 16, |v| {
+      ((v.clamp(-1., 1.) * 32767.).round() as i16) as u16 as u32
+    })))),
+    F::Pack2x16unorm => Ok(CpuShaderValue::Scalar(S::U32(pack(&f32_of(0)?, 16, |v| {
+      (v.clamp(0., 1.) * 65535.).round() as u32
+    })))),
+    F::Pack2x16float => Ok(CpuShaderValue::Scalar(S::U32(pack(&f32_of(0)?, 16, |v| {
+      half::f16::from_f32(v).to_bits() as u32
+    })))),
+    F::Unpack4x8snorm => Ok(unpack(as_u32(p(0)?.as_scalar()?)?, 4, |v| {
+      (v as u8 as i8 as f32 / 127.).max(-1.)
+    })),
+    F::Unpack4x8unorm => Ok(unpack(as_u32(p(0)?.as_scalar()?)?, 4, |v| v as f32 / 255.)),
+    F::Unpack2x16snorm => Ok(unpack(as_u32(p(0)?.as_scalar()?)?, 2, |v| {
+      (v as u16 as i16 as f32 / 32767.).max(-1.)
+    })),
+    F::Unpack2x16unorm => Ok(unpack(as_u32(p(0)?.as_scalar()?)?, 2, |v| {
+      v as f32 / 65535.
+    })),
+    F::Unpack2x16float => Ok(unpack(as_u32(p(0)?.as_scalar()?)?, 2, |v| {
+      half::f16::from_bits(v as u16).to_f32()
+    })),
+    F::ArrayLength => Err(CpuShaderExecutionError::InvalidOperation(
+      "array length should be evaluated with pointer",
+    )),
+  }
+}
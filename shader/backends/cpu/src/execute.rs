@@ -0,0 +1,757 @@
+use crate::*;
+
+pub(crate) const DEFAULT_MAX_STEPS_PER_INVOCATION: usize = 1 << 24;
+
+/// The recorded shader module that could be executed on host
+pub struct CpuShaderModule {
+  pub(crate) stage: ShaderStage,
+  pub(crate) workgroup_size: (u32, u32, u32),
+  pub(crate) entry: CpuShaderFunction,
+  pub(crate) functions: Vec<CpuShaderFunction>,
+  pub(crate) globals: Vec<CpuShaderGlobal>,
+  pub(crate) inputs: FastHashMap<ShaderNodeRawHandle, CpuShaderModuleInput>,
+  pub(crate) max_steps_per_invocation: usize,
+}
+
+impl CpuShaderModule {
+  pub fn workgroup_size(&self) -> (u32, u32, u32) {
+    self.workgroup_size
+  }
+
+  /// The invocation will be aborted with error if the executed statements count exceeds
+  /// the limit, this is useful to catch the infinite loop in test.
+  pub fn with_max_steps_per_invocation(mut self, steps: usize) -> Self {
+    self.max_steps_per_invocation = steps;
+    self
+  }
+
+  /// Execute the compute shader. The invocations in each workgroup are executed one by one,
+  /// and switched when the invocation reaches the barrier, so the workgroup shared memory
+  /// communication works as expected.
+  pub fn dispatch(
+    &self,
+    bindings: &CpuShaderBindingBuilder,
+    workgroup_count: (u32, u32, u32),
+  ) -> Result<(), CpuShaderExecutionError> {
+    if self.stage != ShaderStage::Compute {
+      return Err(CpuShaderExecutionError::Unsupported(
+        "non compute stage dispatch",
+      ));
+    }
+
+    let mut ctx = ExecutionContext::new(self, bindings, workgroup_count)?;
+
+    let (wx, wy, wz) = self.workgroup_size;
+    for group_z in 0..workgroup_count.2 {
+      for group_y in 0..workgroup_count.1 {
+        for group_x in 0..workgroup_count.0 {
+          ctx.reset_workgroup_memory();
+          let workgroup_id = [group_x, group_y, group_z];
+
+          let mut invocations = Vec::with_capacity((wx * wy * wz) as usize);
+          for z in 0..wz {
+            for y in 0..wy {
+              for x in 0..wx {
+                invocations.push(Invocation::new(self, workgroup_id, [x, y, z]));
+              }
+            }
+          }
+
+          while invocations.iter().any(|inv| !inv.finished) {
+            for inv in invocations.iter_mut().filter(|inv| !inv.finished) {
+              ctx.run_until_barrier(inv)?;
+            }
+          }
+        }
+      }
+    }
+
+    ctx.write_back()
+  }
+}
+
+struct Invocation {
+  local_id: [u32; 3],
+  global_id: [u32; 3],
+  workgroup_id: [u32; 3],
+  local_index: u32,
+  frames: Vec<Frame>,
+  privates: Vec<Option<CpuShaderValue>>,
+  steps: usize,
+  finished: bool,
+}
+
+struct Frame {
+  values: FastHashMap<ShaderNodeRawHandle, CpuShaderValue>,
+  locals: Vec<CpuShaderValue>,
+  cursors: Vec<Cursor>,
+  /// where to write the return value in the caller frame
+  result: Option<ShaderNodeRawHandle>,
+}
+
+impl Frame {
+  fn new(function: &CpuShaderFunction, result: Option<ShaderNodeRawHandle>) -> Self {
+    Self {
+      values: Default::default(),
+      locals: function.locals.clone(),
+      cursors: vec![Cursor {
+        block: function.body.clone(),
+        pc: 0,
+        kind: CursorKind::Plain,
+      }],
+      result,
+    }
+  }
+}
+
+struct Cursor {
+  block: CpuShaderBlock,
+  pc: usize,
+  kind: CursorKind,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CursorKind {
+  Plain,
+  Loop,
+  Switch,
+}
+
+impl Invocation {
+  fn new(module: &CpuShaderModule, workgroup_id: [u32; 3], local_id: [u32; 3]) -> Self {
+    let (wx, wy, wz) = module.workgroup_size;
+    let size = [wx, wy, wz];
+    Self {
+      local_id,
+      global_id: std::array::from_fn(|i| workgroup_id[i] * size[i] + local_id[i]),
+      workgroup_id,
+      local_index: local_id[0] + local_id[1] * wx + local_id[2] * wx * wy,
+      frames: vec![Frame::new(&module.entry, None)],
+      privates: module
+        .globals
+        .iter()
+        .map(|g| match &g.kind {
+          CpuShaderGlobalKind::Private(v) => Some(v.clone()),
+          _ => None,
+        })
+        .collect(),
+      steps: 0,
+      finished: false,
+    }
+  }
+
+  fn frame(&mut self) -> &mut Frame {
+    self.frames.last_mut().unwrap()
+  }
+}
+
+struct ExecutionContext<'a> {
+  module: &'a CpuShaderModule,
+  workgroup_count: (u32, u32, u32),
+  /// the buffer content and the workgroup memory, indexed by global
+  memory: Vec<Option<CpuShaderValue>>,
+  resources: Vec<Option<CpuShaderBindingResource>>,
+}
+
+fn u32_vec(v: [u32; 3]) -> CpuShaderValue {
+  CpuShaderValue::Vector(v.map(ScalarValue::U32).to_vec())
+}
+
+impl<'a> ExecutionContext<'a> {
+  fn new(
+    module: &'a CpuShaderModule,
+    bindings: &CpuShaderBindingBuilder,
+    workgroup_count: (u32, u32, u32),
+  ) -> Result<Self, CpuShaderExecutionError> {
+    let mut memory = Vec::with_capacity(module.globals.len());
+    let mut resources = Vec::with_capacity(module.globals.len());
+    for global in &module.globals {
+      let slot = match &global.kind {
+        CpuShaderGlobalKind::Buffer { slot, .. }
+        | CpuShaderGlobalKind::Texture { slot, .. }
+        | CpuShaderGlobalKind::StorageTexture { slot, .. }
+        | CpuShaderGlobalKind::Sampler { slot } => Some(*slot),
+        _ => None,
+      };
+      let resource = slot
+        .map(|slot| {
+          bindings
+            .get(slot)
+            .cloned()
+            .ok_or(CpuShaderExecutionError::BindingMismatch(slot.0, slot.1))
+        })
+        .transpose()?;
+
+      let value = match (&global.kind, &resource) {
+        (
+          CpuShaderGlobalKind::Buffer { ty, layout, .. },
+          Some(CpuShaderBindingResource::Buffer(buffer)),
+        ) => Some(decode_buffer(ty, *layout, &buffer.read())?),
+        (CpuShaderGlobalKind::Buffer { slot, .. }, _)
+        | (
+          CpuShaderGlobalKind::Texture { slot, .. }
+          | CpuShaderGlobalKind::StorageTexture { slot, .. },
+          Some(CpuShaderBindingResource::Buffer(_) | CpuShaderBindingResource::Sampler(_)),
+        )
+        | (
+          CpuShaderGlobalKind::Sampler { slot },
+          Some(CpuShaderBindingResource::Buffer(_) | CpuShaderBindingResource::Texture(_)),
+        ) => return Err(CpuShaderExecutionError::BindingMismatch(slot.0, slot.1)),
+        (CpuShaderGlobalKind::WorkGroup(v), _) => Some(v.clone()),
+        _ => None,
+      };
+
+      memory.push(value);
+      resources.push(resource);
+    }
+
+    Ok(Self {
+      module,
+      workgroup_count,
+      memory,
+      resources,
+    })
+  }
+
+  fn reset_workgroup_memory(&mut self) {
+    for (global, memory) in self.module.globals.iter().zip(&mut self.memory) {
+      if let CpuShaderGlobalKind::WorkGroup(v) = &global.kind {
+        *memory = Some(v.clone());
+      }
+    }
+  }
+
+  fn write_back(&self) -> Result<(), CpuShaderExecutionError> {
+    for ((global, memory), resource) in self
+      .module
+      .globals
+      .iter()
+      .zip(&self.memory)
+      .zip(&self.resources)
+    {
+      if let CpuShaderGlobalKind::Buffer {
+        ty,
+        layout,
+        writeable: true,
+        ..
+      } = &global.kind
+        && let Some(CpuShaderBindingResource::Buffer(buffer)) = resource
+        && let Some(value) = memory
+      {
+        encode_buffer(ty, *layout, value, &mut buffer.write())?;
+      }
+    }
+    Ok(())
+  }
+
+  fn get(
+    &self,
+    inv: &Invocation,
+    handle: ShaderNodeRawHandle,
+  ) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+    if let Some(v) = inv.frames.last().unwrap().values.get(&handle) {
+      return Ok(v.clone());
+    }
+    let input =
+      self
+        .module
+        .inputs
+        .get(&handle)
+        .ok_or(CpuShaderExecutionError::InvalidOperation(
+          "access the value that not evaluated",
+        ))?;
+    Ok(match input {
+      CpuShaderModuleInput::BuiltIn(builtin) => match builtin {
+        ShaderBuiltInDecorator::CompLocalInvocationId => u32_vec(inv.local_id),
+        ShaderBuiltInDecorator::CompGlobalInvocationId => u32_vec(inv.global_id),
+        ShaderBuiltInDecorator::CompLocalInvocationIndex => {
+          CpuShaderValue::Scalar(ScalarValue::U32(inv.local_index))
+        }
+        ShaderBuiltInDecorator::CompWorkgroupId => u32_vec(inv.workgroup_id),
+        ShaderBuiltInDecorator::CompNumWorkgroup => {
+          let (x, y, z) = self.workgroup_count;
+          u32_vec([x, y, z])
+        }
+        // every invocation is a single subgroup
+        ShaderBuiltInDecorator::CompSubgroupSize => CpuShaderValue::Scalar(ScalarValue::U32(1)),
+        ShaderBuiltInDecorator::CompSubgroupId => {
+          CpuShaderValue::Scalar(ScalarValue::U32(inv.local_index))
+        }
+        ShaderBuiltInDecorator::CompSubgroupInvocationId => {
+          CpuShaderValue::Scalar(ScalarValue::U32(0))
+        }
+        _ => {
+          return Err(CpuShaderExecutionError::Unsupported(
+            "non compute builtin input",
+          ));
+        }
+      },
+      CpuShaderModuleInput::Global(index) => match &self.module.globals[*index].kind {
+        CpuShaderGlobalKind::Unsupported(s) => {
+          return Err(CpuShaderExecutionError::Unsupported(s));
+        }
+        kind if kind.is_resource_handle() => CpuShaderValue::Resource(*index),
+        _ => CpuShaderValue::Pointer(CpuShaderPointer {
+          root: CpuShaderPointerRoot::Global(*index),
+          path: Vec::new(),
+        }),
+      },
+      CpuShaderModuleInput::StageIO => {
+        return Err(CpuShaderExecutionError::Unsupported("stage io in compute"));
+      }
+    })
+  }
+
+  fn pointee<'b>(
+    &'b mut self,
+    inv: &'b mut Invocation,
+    pointer: &CpuShaderPointer,
+  ) -> Result<&'b mut CpuShaderValue, CpuShaderExecutionError> {
+    let root = match pointer.root {
+      CpuShaderPointerRoot::Local { frame, index } => inv.frames[frame].locals.get_mut(index),
+      CpuShaderPointerRoot::Global(index) => match &self.module.globals[index].kind {
+        CpuShaderGlobalKind::Private(_) => inv.privates[index].as_mut(),
+        _ => self.memory[index].as_mut(),
+      },
+    };
+    root.ok_or(CpuShaderExecutionError::InvalidOperation("invalid pointer"))
+  }
+
+  fn load(
+    &mut self,
+    inv: &mut Invocation,
+    pointer: ShaderNodeRawHandle,
+  ) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+    let pointer = self.get(inv, pointer)?;
+    let pointer = pointer.as_pointer()?;
+    self.pointee(inv, pointer)?.load_path(&pointer.path)
+  }
+
+  fn store(
+    &mut self,
+    inv: &mut Invocation,
+    pointer: ShaderNodeRawHandle,
+    value: CpuShaderValue,
+  ) -> Result<(), CpuShaderExecutionError> {
+    let pointer = self.get(inv, pointer)?;
+    let pointer = pointer.as_pointer()?;
+    self.pointee(inv, pointer)?.store_path(&pointer.path, value)
+  }
+
+  fn texture(
+    &self,
+    inv: &Invocation,
+    handle: ShaderNodeRawHandle,
+  ) -> Result<(&CpuShaderTexture, &CpuShaderGlobalKind), CpuShaderExecutionError> {
+    let CpuShaderValue::Resource(index) = self.get(inv, handle)? else {
+      return Err(CpuShaderExecutionError::InvalidOperation("expect texture"));
+    };
+    match &self.resources[index] {
+      Some(CpuShaderBindingResource::Texture(t)) => Ok((t, &self.module.globals[index].kind)),
+      _ => Err(CpuShaderExecutionError::InvalidOperation("expect texture")),
+    }
+  }
+
+  fn sampler(
+    &self,
+    inv: &Invocation,
+    handle: ShaderNodeRawHandle,
+  ) -> Result<&CpuShaderSampler, CpuShaderExecutionError> {
+    let CpuShaderValue::Resource(index) = self.get(inv, handle)? else {
+      return Err(CpuShaderExecutionError::InvalidOperation("expect sampler"));
+    };
+    match &self.resources[index] {
+      Some(CpuShaderBindingResource::Sampler(s)) => Ok(s),
+      _ => Err(CpuShaderExecutionError::InvalidOperation("expect sampler")),
+    }
+  }
+
+  /// resolve the texel coordinate and the layer, the z component is treated as layer
+  fn texel_position(
+    &self,
+    inv: &Invocation,
+    position: ShaderNodeRawHandle,
+    array_index: Option<ShaderNodeRawHandle>,
+  ) -> Result<([u32; 2], u32), CpuShaderExecutionError> {
+    let position = self.get(inv, position)?;
+    let mut coord = [0; 3];
+    for (c, v) in coord.iter_mut().zip(position.components()?) {
+      *c = CpuShaderValue::Scalar(*v)
+        .as_index()?
+        .try_into()
+        .unwrap_or(u32::MAX);
+    }
+    let layer = match array_index {
+      Some(layer) => self
+        .get(inv, layer)?
+        .as_index()?
+        .try_into()
+        .unwrap_or(u32::MAX),
+      None => coord[2],
+    };
+    Ok(([coord[0], coord[1]], layer))
+  }
+
+  fn f32_scalar(
+    &self,
+    inv: &Invocation,
+    handle: ShaderNodeRawHandle,
+  ) -> Result<f32, CpuShaderExecutionError> {
+    match self.get(inv, handle)?.as_scalar()? {
+      ScalarValue::F32(v) => Ok(v),
+      v => Err(CpuShaderExecutionError::type_mismatch(
+        "f32",
+        &CpuShaderValue::Scalar(v),
+      )),
+    }
+  }
+
+  fn f32_vec2(
+    &self,
+    inv: &Invocation,
+    handle: ShaderNodeRawHandle,
+  ) -> Result<[f32; 2], CpuShaderExecutionError> {
+    let v = self.get(inv, handle)?;
+    let mut r = [0.; 2];
+    for (r, v) in r.iter_mut().zip(v.components()?) {
+      if let ScalarValue::F32(v) = v {
+        *r = *v;
+      }
+    }
+    Ok(r)
+  }
+
+  fn sample(
+    &self,
+    inv: &Invocation,
+    s: &ShaderTextureSampling,
+  ) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+    let (texture, kind) = self.texture(inv, s.texture)?;
+    let CpuShaderGlobalKind::Texture {
+      dimension,
+      sample_type,
+      ..
+    } = kind
+    else {
+      return Err(CpuShaderExecutionError::InvalidOperation(
+        "sample storage texture",
+      ));
+    };
+    if !matches!(
+      dimension,
+      TextureViewDimension::D1 | TextureViewDimension::D2 | TextureViewDimension::D2Array
+    ) {
+      return Err(CpuShaderExecutionError::Unsupported(
+        "cube or 3d texture sampling",
+      ));
+    }
+    let sampler = self.sampler(inv, s.sampler)?;
+
+    let lod = match s.level {
+      SampleLevel::Auto | SampleLevel::Zero => CpuTextureSampleLod::Level(0.),
+      // the implicit derivative is zero in compute
+      SampleLevel::Exact(l) | SampleLevel::Bias(l) => {
+        CpuTextureSampleLod::Level(self.f32_scalar(inv, l)?)
+      }
+      SampleLevel::Gradient { x, y } => {
+        CpuTextureSampleLod::Gradient(self.f32_vec2(inv, x)?, self.f32_vec2(inv, y)?)
+      }
+    };
+
+    let request = CpuTextureSampleRequest {
+      uv: self.f32_vec2(inv, s.position)?,
+      layer: match s.array_index {
+        Some(layer) => self.get(inv, layer)?.as_index()? as u32,
+        None => 0,
+      },
+      lod,
+      offset: s.offset.map(|o| [o.x, o.y]).unwrap_or_default(),
+      reference: s.reference.map(|r| self.f32_scalar(inv, r)).transpose()?,
+      gather: s.gather_channel.map(|c| match c {
+        GatherChannel::X => 0,
+        GatherChannel::Y => 1,
+        GatherChannel::Z => 2,
+        GatherChannel::W => 3,
+      }),
+      is_depth: matches!(sample_type, TextureSampleType::Depth),
+    };
+
+    Ok(texture.sample(sampler, request))
+  }
+
+  fn eval(
+    &mut self,
+    inv: &mut Invocation,
+    expr: &CpuShaderExpr,
+  ) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+    Ok(match expr {
+      CpuShaderExpr::Constant(v) => v.clone(),
+      CpuShaderExpr::LocalVariable(index) => CpuShaderValue::Pointer(CpuShaderPointer {
+        root: CpuShaderPointerRoot::Local {
+          frame: inv.frames.len() - 1,
+          index: *index,
+        },
+        path: Vec::new(),
+      }),
+      CpuShaderExpr::Load(pointer) => self.load(inv, *pointer)?,
+      CpuShaderExpr::Convert {
+        source,
+        convert_to,
+        bitcast,
+      } => eval_convert(&self.get(inv, *source)?, *convert_to, *bitcast)?,
+      CpuShaderExpr::Atomic {
+        pointer,
+        function,
+        value,
+      } => {
+        let old = self.load(inv, *pointer)?;
+        let value = self.get(inv, *value)?;
+        let compare = match function {
+          AtomicFunction::Exchange {
+            compare: Some(c), ..
+          } => Some(self.get(inv, *c)?),
+          _ => None,
+        };
+        let (new, result) = eval_atomic(function, &old, &value, compare.as_ref())?;
+        self.store(inv, *pointer, new)?;
+        result
+      }
+      CpuShaderExpr::BuiltIn {
+        function,
+        parameters,
+      } => {
+        if let ShaderBuiltInFunction::ArrayLength = function {
+          let pointer = self.get(inv, parameters[0])?;
+          let pointer = pointer.as_pointer()?;
+          let len = self
+            .pointee(inv, pointer)?
+            .composite_path_ref(&pointer.path)?
+            .len()?;
+          return Ok(CpuShaderValue::Scalar(ScalarValue::U32(len as u32)));
+        }
+        let parameters = parameters
+          .iter()
+          .map(|p| self.get(inv, *p))
+          .collect::<Result<Vec<_>, _>>()?;
+        eval_builtin(*function, &parameters)?
+      }
+      CpuShaderExpr::TextureSampling(s) => self.sample(inv, s)?,
+      CpuShaderExpr::TextureLoad(l) => {
+        let (texture, kind) = self.texture(inv, l.texture)?;
+        let is_depth = matches!(
+          kind,
+          CpuShaderGlobalKind::Texture {
+            sample_type: TextureSampleType::Depth,
+            ..
+          }
+        );
+        let (position, layer) = self.texel_position(inv, l.position, l.array_index)?;
+        let level = match l.level {
+          Some(level) => self.get(inv, level)?.as_index()? as u32,
+          None => 0,
+        };
+        texture.load(position, layer, level, is_depth)?
+      }
+      CpuShaderExpr::TextureQuery(t, query) => {
+        let (texture, kind) = self.texture(inv, *t)?;
+        let dimension = match kind {
+          CpuShaderGlobalKind::Texture { dimension, .. }
+          | CpuShaderGlobalKind::StorageTexture { dimension, .. } => *dimension,
+          _ => unreachable!(),
+        };
+        let level = match query {
+          TextureQuery::Size { level: Some(level) } => self.get(inv, *level)?.as_index()? as u32,
+          _ => 0,
+        };
+        texture.query(*query, level, dimension)
+      }
+      CpuShaderExpr::Swizzle { source, components } => {
+        let mut source = self.get(inv, *source)?;
+        if let CpuShaderValue::Pointer(p) = &source {
+          let p = p.clone();
+          source = self.pointee(inv, &p)?.load_path(&p.path)?;
+        }
+        eval_swizzle(&source, components)?
+      }
+      CpuShaderExpr::Compose { target, parameters } => {
+        let parameters = parameters
+          .iter()
+          .map(|p| self.get(inv, *p))
+          .collect::<Result<Vec<_>, _>>()?;
+        eval_compose(target, parameters)?
+      }
+      CpuShaderExpr::Unary { operator, one } => eval_unary(operator, &self.get(inv, *one)?)?,
+      CpuShaderExpr::Binary {
+        operator,
+        left,
+        right,
+      } => eval_binary(operator, &self.get(inv, *left)?, &self.get(inv, *right)?)?,
+      CpuShaderExpr::Index { base, index } => {
+        let index = self.get(inv, *index)?.as_index()?;
+        self.access(inv, *base, index)?
+      }
+      CpuShaderExpr::IndexStatic { base, index } => self.access(inv, *base, *index)?,
+      CpuShaderExpr::Unsupported(s) => return Err(CpuShaderExecutionError::Unsupported(s)),
+    })
+  }
+
+  fn access(
+    &self,
+    inv: &Invocation,
+    base: ShaderNodeRawHandle,
+    index: usize,
+  ) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+    match self.get(inv, base)? {
+      CpuShaderValue::Pointer(p) => Ok(CpuShaderValue::Pointer(p.access(index))),
+      v => v.get(index),
+    }
+  }
+
+  fn run_until_barrier(&mut self, inv: &mut Invocation) -> Result<(), CpuShaderExecutionError> {
+    loop {
+      inv.steps += 1;
+      if inv.steps > self.module.max_steps_per_invocation {
+        return Err(CpuShaderExecutionError::StepLimitExceeded);
+      }
+
+      let frame = inv.frame();
+      let Some(cursor) = frame.cursors.last_mut() else {
+        // reach the end of the function body
+        if self.do_return(inv, None)? {
+          return Ok(());
+        }
+        continue;
+      };
+
+      if cursor.pc >= cursor.block.len() {
+        if cursor.kind == CursorKind::Loop {
+          cursor.pc = 0;
+        } else {
+          frame.cursors.pop();
+        }
+        continue;
+      }
+
+      let block = cursor.block.clone();
+      cursor.pc += 1;
+      let statement = &block[cursor.pc - 1];
+
+      match statement {
+        CpuShaderStatement::Eval { handle, expr } => {
+          let value = self.eval(inv, expr)?;
+          inv.frame().values.insert(*handle, value);
+        }
+        CpuShaderStatement::Store { pointer, value } => {
+          let value = self.get(inv, *value)?;
+          self.store(inv, *pointer, value)?;
+        }
+        CpuShaderStatement::TextureStore(s) => {
+          let (texture, _) = self.texture(inv, s.image)?;
+          let (position, layer) = self.texel_position(inv, s.position, s.array_index)?;
+          texture.store(position, layer, &self.get(inv, s.value)?)?;
+        }
+        CpuShaderStatement::Call {
+          result,
+          function,
+          arguments,
+        } => {
+          let function = &self.module.functions[*function];
+          let mut frame = Frame::new(function, Some(*result));
+          for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            frame.values.insert(*parameter, self.get(inv, *argument)?);
+          }
+          inv.frames.push(frame);
+        }
+        CpuShaderStatement::Block(block) => frame_push(inv, block, CursorKind::Plain),
+        CpuShaderStatement::If {
+          condition,
+          accept,
+          reject,
+        } => {
+          let block = if self.get(inv, *condition)?.as_bool()? {
+            accept
+          } else {
+            reject
+          };
+          frame_push(inv, block, CursorKind::Plain);
+        }
+        CpuShaderStatement::Loop(block) => frame_push(inv, block, CursorKind::Loop),
+        CpuShaderStatement::Switch { selector, cases } => {
+          let selector = self.get(inv, *selector)?.as_scalar()?;
+          let case = cases
+            .iter()
+            .find(|(case, _)| match (case, selector) {
+              (CpuShaderSwitchCase::U32(c), ScalarValue::U32(s)) => *c == s,
+              (CpuShaderSwitchCase::I32(c), ScalarValue::I32(s)) => *c == s,
+              _ => false,
+            })
+            .or_else(|| {
+              cases
+                .iter()
+                .find(|(case, _)| matches!(case, CpuShaderSwitchCase::Default))
+            });
+          if let Some((_, block)) = case {
+            frame_push(inv, block, CursorKind::Switch);
+          }
+        }
+        CpuShaderStatement::Break => {
+          let cursors = &mut inv.frame().cursors;
+          while let Some(cursor) = cursors.pop() {
+            if cursor.kind != CursorKind::Plain {
+              break;
+            }
+          }
+        }
+        CpuShaderStatement::Continue => {
+          let cursors = &mut inv.frame().cursors;
+          while let Some(cursor) = cursors.last_mut() {
+            if cursor.kind == CursorKind::Loop {
+              cursor.pc = 0;
+              break;
+            }
+            cursors.pop();
+          }
+        }
+        CpuShaderStatement::Return(value) => {
+          let value = value.map(|v| self.get(inv, v)).transpose()?;
+          if self.do_return(inv, value)? {
+            return Ok(());
+          }
+        }
+        CpuShaderStatement::Discard => {
+          inv.finished = true;
+          return Ok(());
+        }
+        CpuShaderStatement::Barrier => return Ok(()),
+        CpuShaderStatement::Unsupported(s) => {
+          return Err(CpuShaderExecutionError::Unsupported(s));
+        }
+      }
+    }
+  }
+
+  /// return true if the invocation is finished
+  fn do_return(
+    &mut self,
+    inv: &mut Invocation,
+    value: Option<CpuShaderValue>,
+  ) -> Result<bool, CpuShaderExecutionError> {
+    let frame = inv.frames.pop().unwrap();
+    if inv.frames.is_empty() {
+      inv.finished = true;
+      return Ok(true);
+    }
+    if let (Some(result), Some(value)) = (frame.result, value) {
+      inv.frame().values.insert(result, value);
+    }
+    Ok(false)
+  }
+}
+
+fn frame_push(inv: &mut Invocation, block: &CpuShaderBlock, kind: CursorKind) {
+  inv.frame().cursors.push(Cursor {
+    block: block.clone(),
+    pc: 0,
+    kind,
+  });
+}
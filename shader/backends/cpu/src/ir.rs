@@ -0,0 +1,158 @@
+use crate::*;
+
+pub(crate) type CpuShaderBlock = Arc<[CpuShaderStatement]>;
+
+/// The recorded shader statement, the expression is evaluated by the [CpuShaderStatement::Eval]
+/// in the recording order, so the evaluation order is exactly same as the shader building code.
+pub(crate) enum CpuShaderStatement {
+  Eval {
+    handle: ShaderNodeRawHandle,
+    expr: CpuShaderExpr,
+  },
+  Store {
+    pointer: ShaderNodeRawHandle,
+    value: ShaderNodeRawHandle,
+  },
+  TextureStore(ShaderTextureStore),
+  Call {
+    result: ShaderNodeRawHandle,
+    function: usize,
+    arguments: Vec<ShaderNodeRawHandle>,
+  },
+  Block(CpuShaderBlock),
+  If {
+    condition: ShaderNodeRawHandle,
+    accept: CpuShaderBlock,
+    reject: CpuShaderBlock,
+  },
+  Loop(CpuShaderBlock),
+  Switch {
+    selector: ShaderNodeRawHandle,
+    cases: Vec<(CpuShaderSwitchCase, CpuShaderBlock)>,
+  },
+  Break,
+  Continue,
+  Return(Option<ShaderNodeRawHandle>),
+  Discard,
+  Barrier,
+  Unsupported(&'static str),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum CpuShaderSwitchCase {
+  U32(u32),
+  I32(i32),
+  Default,
+}
+
+impl From<SwitchCaseCondition> for CpuShaderSwitchCase {
+  fn from(value: SwitchCaseCondition) -> Self {
+    match value {
+      SwitchCaseCondition::U32(v) => Self::U32(v),
+      SwitchCaseCondition::I32(v) => Self::I32(v),
+      SwitchCaseCondition::Default => Self::Default,
+    }
+  }
+}
+
+pub(crate) enum CpuShaderExpr {
+  Constant(CpuShaderValue),
+  LocalVariable(usize),
+  Load(ShaderNodeRawHandle),
+  Convert {
+    source: ShaderNodeRawHandle,
+    convert_to: ScalarType,
+    bitcast: bool,
+  },
+  Atomic {
+    pointer: ShaderNodeRawHandle,
+    function: AtomicFunction,
+    value: ShaderNodeRawHandle,
+  },
+  BuiltIn {
+    function: ShaderBuiltInFunction,
+    parameters: Vec<ShaderNodeRawHandle>,
+  },
+  TextureSampling(ShaderTextureSampling),
+  TextureLoad(ShaderTextureLoad),
+  TextureQuery(ShaderNodeRawHandle, TextureQuery),
+  Swizzle {
+    source: ShaderNodeRawHandle,
+    components: Vec<usize>,
+  },
+  Compose {
+    target: ShaderSizedValueType,
+    parameters: Vec<ShaderNodeRawHandle>,
+  },
+  Unary {
+    operator: UnaryOperator,
+    one: ShaderNodeRawHandle,
+  },
+  Binary {
+    operator: BinaryOperator,
+    left: ShaderNodeRawHandle,
+    right: ShaderNodeRawHandle,
+  },
+  Index {
+    base: ShaderNodeRawHandle,
+    index: ShaderNodeRawHandle,
+  },
+  IndexStatic {
+    base: ShaderNodeRawHandle,
+    index: usize,
+  },
+  Unsupported(&'static str),
+}
+
+pub(crate) struct CpuShaderFunction {
+  pub name: String,
+  pub parameters: Vec<ShaderNodeRawHandle>,
+  /// the zero initialized value of each local variable
+  pub locals: Vec<CpuShaderValue>,
+  pub body: CpuShaderBlock,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum CpuShaderModuleInput {
+  BuiltIn(ShaderBuiltInDecorator),
+  Global(usize),
+  /// the vertex or fragment stage io, not available in the compute execution
+  StageIO,
+}
+
+pub(crate) struct CpuShaderGlobal {
+  pub kind: CpuShaderGlobalKind,
+}
+
+pub(crate) enum CpuShaderGlobalKind {
+  Buffer {
+    slot: (usize, usize),
+    ty: ShaderValueSingleType,
+    layout: StructLayoutTarget,
+    writeable: bool,
+  },
+  Texture {
+    slot: (usize, usize),
+    dimension: TextureViewDimension,
+    sample_type: TextureSampleType,
+  },
+  StorageTexture {
+    slot: (usize, usize),
+    dimension: TextureViewDimension,
+  },
+  Sampler {
+    slot: (usize, usize),
+  },
+  WorkGroup(CpuShaderValue),
+  Private(CpuShaderValue),
+  Unsupported(&'static str),
+}
+
+impl CpuShaderGlobalKind {
+  pub fn is_resource_handle(&self) -> bool {
+    matches!(
+      self,
+      Self::Texture { .. } | Self::StorageTexture { .. } | Self::Sampler { .. }
+    )
+  }
+}
@@ -0,0 +1,82 @@
+//! The shader backend that interprets the shader graph on the host.
+//!
+//! This backend is mainly used for unit testing the compute kernels and shader functions without
+//! a gpu device, and cross check the result with the gpu implementation. The performance is not
+//! the concern, the invocations in one workgroup are executed serially and switched at each
+//! workgroup barrier.
+
+use std::any::Any;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use fast_hash_collection::*;
+use parking_lot::RwLock;
+use rendiation_shader_api::*;
+
+mod api;
+mod binding;
+mod eval;
+mod execute;
+mod ir;
+mod texture;
+mod value;
+
+pub use api::*;
+pub use binding::*;
+use eval::*;
+pub use execute::*;
+use ir::*;
+pub use texture::*;
+use value::*;
+
+#[cfg(test)]
+mod tests;
+
+pub fn cpu_compute_shader_builder() -> ShaderComputePipelineBuilder {
+  ShaderComputePipelineBuilder::new(
+    &|stage| Box::new(ShaderAPICpuImpl::new(stage)),
+    ShaderRuntimeChecks::checked(),
+  )
+}
+
+pub trait ComputeIntoCpuModuleExt {
+  fn create_cpu_module(self) -> Result<CpuShaderModule, ShaderBuildError>;
+}
+
+impl ComputeIntoCpuModuleExt for ShaderComputePipelineBuilder {
+  fn create_cpu_module(self) -> Result<CpuShaderModule, ShaderBuildError> {
+    let result = self.build()?;
+    let (_, module) = result.shader;
+    Ok(*module.downcast::<CpuShaderModule>().unwrap())
+  }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CpuShaderExecutionError {
+  #[error("Type mismatch, expect {expected}, but the value is {actual}")]
+  TypeMismatch {
+    expected: &'static str,
+    actual: String,
+  },
+  #[error("Index {0} out of bounds")]
+  IndexOutOfBounds(usize),
+  #[error("The bound buffer is too small for the binding type")]
+  BufferTooSmall,
+  #[error("The binding at group {0} entry {1} is missing or has mismatched resource type")]
+  BindingMismatch(usize, usize),
+  #[error("The invocation exceeds the max execution steps, the shader may never terminate")]
+  StepLimitExceeded,
+  #[error("The {0} is not supported by the cpu backend")]
+  Unsupported(&'static str),
+  #[error("Invalid operation: {0}")]
+  InvalidOperation(&'static str),
+}
+
+impl CpuShaderExecutionError {
+  pub(crate) fn type_mismatch(expected: &'static str, value: &CpuShaderValue) -> Self {
+    Self::TypeMismatch {
+      expected,
+      actual: format!("{value:?}"),
+    }
+  }
+}
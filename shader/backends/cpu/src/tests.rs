@@ -0,0 +1,311 @@
+use rendiation_webgpu::*;
+use wgpu_types::FilterMode;
+
+use crate::*;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+fn workgroup_prefix_sum(
+  cx: &ShaderComputePipelineBuilder,
+  input: ShaderReadonlyPtrOf<[u32]>,
+  output: ShaderPtrOf<[u32]>,
+) {
+  let shared = cx.define_workgroup_shared_var_host_size_array::<u32>(WORKGROUP_SIZE);
+
+  let global_id = cx.global_invocation_id().x();
+  let local_id = cx.local_invocation_id().x();
+
+  let value = input.index(global_id).load().make_local_var();
+  shared.index(local_id).store(value.load());
+
+  WORKGROUP_SIZE.ilog2().into_shader_iter().for_each(|i, _| {
+    workgroup_barrier();
+
+    if_by(local_id.greater_equal_than(val(1) << i), || {
+      value.store(value.load() + shared.index(local_id - (val(1) << i)).load())
+    });
+
+    workgroup_barrier();
+    shared.index(local_id).store(value.load())
+  });
+
+  output.index(global_id).store(value.load());
+}
+
+fn expect_prefix_sum(input: &[u32]) -> Vec<u32> {
+  input
+    .chunks(WORKGROUP_SIZE as usize)
+    .flat_map(|chunk| {
+      chunk.iter().scan(0, |sum, v| {
+        *sum += v;
+        Some(*sum)
+      })
+    })
+    .collect()
+}
+
+fn prefix_sum_input() -> Vec<u32> {
+  (0..WORKGROUP_SIZE * 2).map(|i| i % 7).collect()
+}
+
+fn run_cpu_prefix_sum(input_data: &[u32]) -> Vec<u32> {
+  let input = CpuStorageBufferReadonlyDataView::<[u32]>::new(input_data);
+  let output = CpuStorageBufferDataView::<[u32]>::new(vec![0; input_data.len()].as_slice());
+
+  let mut cx = cpu_compute_shader_builder().with_config_work_group_size(WORKGROUP_SIZE);
+  let input_node = cx.bind_by(&input);
+  let output_node = cx.bind_by(&output);
+  workgroup_prefix_sum(&cx, input_node, output_node);
+  let module = cx.create_cpu_module().unwrap();
+
+  let bindings = CpuShaderBindingBuilder::default()
+    .with_bind(&input)
+    .with_bind(&output);
+  let workgroup_count = input_data.len() as u32 / WORKGROUP_SIZE;
+  module.dispatch(&bindings, (workgroup_count, 1, 1)).unwrap();
+
+  output.read().into_vec()
+}
+
+#[test]
+fn test_workgroup_barrier() {
+  let input = prefix_sum_input();
+  assert_eq!(run_cpu_prefix_sum(&input), expect_prefix_sum(&input));
+}
+
+#[pollster::test]
+async fn test_compare_with_gpu() {
+  // skip if the test environment has no gpu available
+  let Ok((gpu, _)) = GPU::new(Default::default()).await else {
+    return;
+  };
+
+  let input_data = prefix_sum_input();
+  let input = create_gpu_readonly_storage(input_data.as_slice(), &gpu, "input");
+  let init = ZeroedArrayByArrayLength(input_data.len());
+  let output = create_gpu_read_write_storage::<[u32]>(init, &gpu, "output");
+
+  let pipeline = {
+    let mut cx = compute_shader_builder(&gpu).with_config_work_group_size(WORKGROUP_SIZE);
+    let input = cx.bind_by(&input);
+    let output = cx.bind_by(&output);
+    workgroup_prefix_sum(&cx, input, output);
+    cx.create_compute_pipeline(&gpu, "prefix sum").unwrap()
+  };
+
+  let mut encoder = gpu.create_encoder().with_compute_pass_scoped(|mut pass| {
+    BindingBuilder::default()
+      .with_bind(&input)
+      .with_bind(&output)
+      .setup_compute_pass(&mut pass, &gpu.device, &pipeline);
+    pass.dispatch_workgroups(input_data.len() as u32 / WORKGROUP_SIZE, 1, 1);
+  });
+  let result = encoder.read_buffer(&gpu.device, &output);
+  gpu.submit_encoder(encoder);
+
+  let result = result.await.unwrap();
+  let gpu_result = <[u32]>::from_bytes_into_boxed(&result.read_raw()).into_vec();
+
+  assert_eq!(gpu_result, run_cpu_prefix_sum(&input_data));
+}
+
+#[shader_fn]
+fn collatz_steps(value: Node<u32>) -> Node<u32> {
+  let v = value.make_local_var();
+  let steps = val(0_u32).make_local_var();
+  loop_by(|cx| {
+    if_by(v.load().less_equal_than(1), || cx.do_break());
+    if_by((v.load() % val(2)).equals(0), || v.store(v.load() / val(2)))
+      .else_by(|| v.store(v.load() * val(3) + val(1)));
+    steps.store(steps.load() + val(1));
+  });
+  steps.load()
+}
+
+#[test]
+fn test_function_and_control_flow() {
+  let count = 32_u32;
+  let output = CpuStorageBufferDataView::<[u32]>::new(vec![0; count as usize].as_slice());
+
+  let mut cx = cpu_compute_shader_builder().with_config_work_group_size(8);
+  let output_node = cx.bind_by(&output);
+  let id = cx.global_invocation_id().x();
+
+  let result = val(0_u32).make_local_var();
+  switch_by(id % val(3))
+    .case(0, || result.store(collatz_steps_fn(id)))
+    .case(1, || result.store(id * val(2)))
+    .end_with_default(|| result.store(val(u32::MAX) + id)); // wrapping add
+  output_node.index(id).store(result.load());
+
+  let module = cx.create_cpu_module().unwrap();
+  let bindings = CpuShaderBindingBuilder::default().with_bind(&output);
+  module.dispatch(&bindings, (count / 8, 1, 1)).unwrap();
+
+  let collatz = |mut v: u32| {
+    let mut steps = 0;
+    while v > 1 {
+      v = if v.is_multiple_of(2) {
+        v / 2
+      } else {
+        v * 3 + 1
+      };
+      steps += 1;
+    }
+    steps
+  };
+  let expect: Vec<u32> = (0..count)
+    .map(|i| match i % 3 {
+      0 => collatz(i),
+      1 => i * 2,
+      _ => u32::MAX.wrapping_add(i),
+    })
+    .collect();
+  assert_eq!(output.read().into_vec(), expect);
+}
+
+#[test]
+fn test_atomic() {
+  let input_data: Vec<u32> = (0..100).collect();
+  let input = CpuStorageBufferReadonlyDataView::<[u32]>::new(input_data.as_slice());
+  let counter = CpuStorageBufferDataView::<DeviceAtomic<u32>>::new(&DeviceAtomic(0));
+
+  let mut cx = cpu_compute_shader_builder().with_config_work_group_size(32);
+  let input_node = cx.bind_by(&input);
+  let counter_node = cx.bind_by(&counter);
+  let id = cx.global_invocation_id().x();
+  if_by(id.less_than(input_node.array_length()), || {
+    if_by(input_node.index(id).load().greater_than(41), || {
+      counter_node.atomic_add(val(1));
+    });
+  });
+
+  let module = cx.create_cpu_module().unwrap();
+  let bindings = CpuShaderBindingBuilder::default()
+    .with_bind(&input)
+    .with_bind(&counter);
+  module.dispatch(&bindings, (4, 1, 1)).unwrap();
+
+  assert_eq!(counter.read().0, 58);
+}
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Clone, Copy, ShaderStruct, Default)]
+struct TestTransformUniform {
+  pub transform: Mat4<f32>,
+  pub scale: f32,
+  pub offset: f32,
+}
+
+#[test]
+fn test_uniform_struct() {
+  let uniform = CpuUniformBufferDataView::new(&TestTransformUniform {
+    transform: Mat4::translate((1., 2., 3.)),
+    scale: 2.,
+    offset: 0.5,
+    ..Zeroable::zeroed()
+  });
+  let input_data = [Vec4::new(1., 1., 1., 1.), Vec4::new(-1., 0., 2., 1.)];
+  let input = CpuStorageBufferReadonlyDataView::<[Vec4<f32>]>::new(input_data.as_slice());
+  let output = CpuStorageBufferDataView::<[Vec4<f32>]>::new([Vec4::default(); 2].as_slice());
+
+  let mut cx = cpu_compute_shader_builder().with_config_work_group_size(2);
+  let uniform_node = cx.bind_by(&uniform).load().expand();
+  let input_node = cx.bind_by(&input);
+  let output_node = cx.bind_by(&output);
+  let id = cx.global_invocation_id().x();
+  let position = uniform_node.transform * input_node.index(id).load();
+  let r =
+    position * uniform_node.scale.splat::<Vec4<f32>>() + uniform_node.offset.splat::<Vec4<f32>>();
+  output_node.index(id).store(r);
+
+  let module = cx.create_cpu_module().unwrap();
+  let bindings = CpuShaderBindingBuilder::default()
+    .with_bind(&uniform)
+    .with_bind(&input)
+    .with_bind(&output);
+  module.dispatch(&bindings, (1, 1, 1)).unwrap();
+
+  let result = output.read().into_vec();
+  assert_eq!(result[0], Vec4::new(4.5, 6.5, 8.5, 2.5));
+  assert_eq!(result[1], Vec4::new(0.5, 4.5, 10.5, 2.5));
+}
+
+#[test]
+fn test_texture_sampling() {
+  let texture = CpuShaderTexture::from_texels(
+    ScalarType::F32,
+    2,
+    2,
+    &[
+      [0_f32, 0., 0., 1.],
+      [1., 0., 0., 1.],
+      [0., 1., 0., 1.],
+      [1., 1., 0., 1.],
+    ],
+  );
+  let texture = CpuTextureView::<TextureDimension2, f32>::new(texture);
+  let sampler = CpuShaderSampler {
+    mag_filter: FilterMode::Linear,
+    min_filter: FilterMode::Linear,
+    ..Default::default()
+  };
+  let output = CpuStorageBufferDataView::<[Vec4<f32>]>::new([Vec4::default(); 3].as_slice());
+
+  let mut cx = cpu_compute_shader_builder().with_config_work_group_size(1);
+  let texture_node = cx.bind_by(&texture);
+  let sampler_node = cx.bind_by(&sampler);
+  let output_node = cx.bind_by(&output);
+  let center = texture_node.sample_zero_level(sampler_node, val(Vec2::new(0.5, 0.5)));
+  output_node.index(0).store(center);
+  let corner = texture_node.sample_zero_level(sampler_node, val(Vec2::new(0.25, 0.75)));
+  output_node.index(1).store(corner);
+  let texel = texture_node.load_texel(val(Vec2::new(1, 0)), val(0));
+  output_node.index(2).store(texel);
+
+  let module = cx.create_cpu_module().unwrap();
+  let bindings = CpuShaderBindingBuilder::default()
+    .with_bind(&texture)
+    .with_bind(&sampler)
+    .with_bind(&output);
+  module.dispatch(&bindings, (1, 1, 1)).unwrap();
+
+  let result = output.read().into_vec();
+  assert_eq!(result[0], Vec4::new(0.5, 0.5, 0., 1.));
+  assert_eq!(result[1], Vec4::new(0., 1., 0., 1.));
+  assert_eq!(result[2], Vec4::new(1., 0., 0., 1.));
+}
+
+#[test]
+fn test_errors() {
+  let output = CpuStorageBufferDataView::<[u32]>::new([0_u32; 4].as_slice());
+
+  let mut cx = cpu_compute_shader_builder().with_config_work_group_size(1);
+  let output_node = cx.bind_by(&output);
+  let id = cx.global_invocation_id().x();
+  output_node.index(id).store(val(1));
+  let module = cx.create_cpu_module().unwrap();
+
+  let bindings = CpuShaderBindingBuilder::default().with_bind(&output);
+  let r = module.dispatch(&bindings, (5, 1, 1));
+  assert!(matches!(
+    r,
+    Err(CpuShaderExecutionError::IndexOutOfBounds(4))
+  ));
+
+  let r = module.dispatch(&CpuShaderBindingBuilder::default(), (1, 1, 1));
+  assert!(matches!(
+    r,
+    Err(CpuShaderExecutionError::BindingMismatch(0, 0))
+  ));
+
+  let cx = cpu_compute_shader_builder().with_config_work_group_size(1);
+  loop_by(|_| {});
+  let module = cx
+    .create_cpu_module()
+    .unwrap()
+    .with_max_steps_per_invocation(1000);
+  let r = module.dispatch(&CpuShaderBindingBuilder::default(), (1, 1, 1));
+  assert!(matches!(r, Err(CpuShaderExecutionError::StepLimitExceeded)));
+}
@@ -0,0 +1,413 @@
+use wgpu_types::{AddressMode, CompareFunction, FilterMode, MipmapFilterMode};
+
+use crate::*;
+
+/// The host texture used by the cpu backend, the texel is stored as four 32bit channels
+/// regardless of the gpu format, and the channel is interpreted by the `texel_kind`.
+#[derive(Clone)]
+pub struct CpuShaderTexture {
+  inner: Arc<RwLock<CpuShaderTextureData>>,
+}
+
+struct CpuShaderTextureData {
+  texel_kind: ScalarType,
+  width: u32,
+  height: u32,
+  layers: u32,
+  levels: Vec<Vec<[u32; 4]>>,
+}
+
+fn level_extent(size: u32, level: usize) -> u32 {
+  (size >> level).max(1)
+}
+
+impl CpuShaderTextureData {
+  fn level_size(&self, level: usize) -> (u32, u32) {
+    (
+      level_extent(self.width, level),
+      level_extent(self.height, level),
+    )
+  }
+
+  fn texel_index(&self, level: usize, layer: u32, x: u32, y: u32) -> Option<usize> {
+    let (w, h) = self.level_size(level);
+    (level < self.levels.len() && layer < self.layers && x < w && y < h)
+      .then(|| ((layer * h + y) * w + x) as usize)
+  }
+
+  fn texel(&self, level: usize, layer: u32, x: u32, y: u32) -> Option<[u32; 4]> {
+    let index = self.texel_index(level, layer, x, y)?;
+    Some(self.levels[level][index])
+  }
+
+  fn texel_f32(&self, level: usize, layer: u32, x: u32, y: u32) -> [f32; 4] {
+    self
+      .texel(level, layer, x, y)
+      .map(|t| t.map(f32::from_bits))
+      .unwrap_or_default()
+  }
+}
+
+impl CpuShaderTexture {
+  pub fn new(
+    texel_kind: ScalarType,
+    width: u32,
+    height: u32,
+    layers: u32,
+    mip_levels: u32,
+  ) -> Self {
+    let levels = (0..mip_levels.max(1) as usize)
+      .map(|level| {
+        let size = level_extent(width, level) * level_extent(height, level) * layers;
+        vec![[0; 4]; size as usize]
+      })
+      .collect();
+    Self {
+      inner: Arc::new(RwLock::new(CpuShaderTextureData {
+        texel_kind,
+        width,
+        height,
+        layers,
+        levels,
+      })),
+    }
+  }
+
+  /// create single level single layer texture, the texels are in row major order
+  pub fn from_texels<T: Pod>(
+    texel_kind: ScalarType,
+    width: u32,
+    height: u32,
+    texels: &[[T; 4]],
+  ) -> Self {
+    let texture = Self::new(texel_kind, width, height, 1, 1);
+    assert_eq!(texels.len(), (width * height) as usize);
+    texture.inner.write().levels[0] = texels.iter().map(|t| t.map(cast)).collect();
+    texture
+  }
+
+  pub fn size(&self) -> (u32, u32) {
+    let data = self.inner.read();
+    (data.width, data.height)
+  }
+
+  pub fn layer_count(&self) -> u32 {
+    self.inner.read().layers
+  }
+
+  pub fn mip_level_count(&self) -> u32 {
+    self.inner.read().levels.len() as u32
+  }
+
+  pub fn write_texel<T: Pod>(&self, level: u32, layer: u32, x: u32, y: u32, texel: [T; 4]) {
+    let mut data = self.inner.write();
+    let index = data
+      .texel_index(level as usize, layer, x, y)
+      .expect("texel out of bounds");
+    data.levels[level as usize][index] = texel.map(cast);
+  }
+
+  pub fn read_texel<T: Pod>(&self, level: u32, layer: u32, x: u32, y: u32) -> [T; 4] {
+    let data = self.inner.read();
+    data
+      .texel(level as usize, layer, x, y)
+      .expect("texel out of bounds")
+      .map(cast)
+  }
+
+  pub(crate) fn load(
+    &self,
+    position: [u32; 2],
+    layer: u32,
+    level: u32,
+    is_depth: bool,
+  ) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+    let data = self.inner.read();
+    let texel = data
+      .texel(level as usize, layer, position[0], position[1])
+      .ok_or(CpuShaderExecutionError::IndexOutOfBounds(
+        position[0].max(position[1]) as usize,
+      ))?;
+    if is_depth {
+      return Ok(CpuShaderValue::Scalar(ScalarValue::F32(f32::from_bits(
+        texel[0],
+      ))));
+    }
+    Ok(CpuShaderValue::Vector(
+      texel
+        .iter()
+        .map(|bits| decode_scalar(data.texel_kind, *bits))
+        .collect(),
+    ))
+  }
+
+  pub(crate) fn store(
+    &self,
+    position: [u32; 2],
+    layer: u32,
+    value: &CpuShaderValue,
+  ) -> Result<(), CpuShaderExecutionError> {
+    let mut data = self.inner.write();
+    let index = data.texel_index(0, layer, position[0], position[1]).ok_or(
+      CpuShaderExecutionError::IndexOutOfBounds(position[0].max(position[1]) as usize),
+    )?;
+    let mut texel = [0; 4];
+    for (t, v) in texel.iter_mut().zip(value.components()?) {
+      *t = scalar_bits(*v);
+    }
+    data.levels[0][index] = texel;
+    Ok(())
+  }
+
+  pub(crate) fn query(
+    &self,
+    query: TextureQuery,
+    level: u32,
+    dimension: TextureViewDimension,
+  ) -> CpuShaderValue {
+    let data = self.inner.read();
+    let u = |v: u32| ScalarValue::U32(v);
+    match query {
+      TextureQuery::Size { .. } => {
+        let (w, h) = data.level_size(level as usize);
+        match dimension {
+          TextureViewDimension::D1 => CpuShaderValue::Scalar(u(w)),
+          TextureViewDimension::D3 => CpuShaderValue::Vector(vec![u(w), u(h), u(data.layers)]),
+          _ => CpuShaderValue::Vector(vec![u(w), u(h)]),
+        }
+      }
+      TextureQuery::NumLevels => CpuShaderValue::Scalar(u(data.levels.len() as u32)),
+      TextureQuery::NumLayers => CpuShaderValue::Scalar(u(data.layers)),
+      TextureQuery::NumSamples => CpuShaderValue::Scalar(u(1)),
+    }
+  }
+
+  pub(crate) fn sample(
+    &self,
+    sampler: &CpuShaderSampler,
+    request: CpuTextureSampleRequest,
+  ) -> CpuShaderValue {
+    let data = self.inner.read();
+    let max_level = (data.levels.len() - 1) as f32;
+
+    let lod = match request.lod {
+      CpuTextureSampleLod::Level(lod) => lod,
+      CpuTextureSampleLod::Gradient(x, y) => {
+        let (w, h) = (data.width as f32, data.height as f32);
+        let len = |d: [f32; 2]| ((d[0] * w).powi(2) + (d[1] * h).powi(2)).sqrt();
+        len(x).max(len(y)).log2()
+      }
+    };
+
+    if let Some(channel) = request.gather {
+      let texels = data.footprint(sampler, request.uv, request.offset, 0);
+      let value = |texel: [f32; 4], bits: [u32; 4]| match request.reference {
+        Some(r) => ScalarValue::F32(sampler.compare_value(r, texel[0])),
+        None => decode_scalar(data.texel_kind, bits[channel]),
+      };
+      // the gather order is (u0,v1), (u1,v1), (u1,v0), (u0,v0)
+      let fetch = |(x, y): (Option<u32>, Option<u32>)| {
+        let bits = x
+          .zip(y)
+          .and_then(|(x, y)| data.texel(0, request.layer, x, y))
+          .unwrap_or_default();
+        value(bits.map(f32::from_bits), bits)
+      };
+      let [x0, x1] = texels.x;
+      let [y0, y1] = texels.y;
+      return CpuShaderValue::Vector(vec![
+        fetch((x0, y1)),
+        fetch((x1, y1)),
+        fetch((x1, y0)),
+        fetch((x0, y0)),
+      ]);
+    }
+
+    let filter = if lod <= 0. {
+      sampler.mag_filter
+    } else {
+      sampler.min_filter
+    };
+    let lod = lod.clamp(0., max_level);
+    let levels = match sampler.mipmap_filter {
+      MipmapFilterMode::Nearest => vec![((lod + 0.5).floor() as usize, 1.)],
+      MipmapFilterMode::Linear => {
+        let base = lod.floor();
+        let weight = lod - base;
+        vec![
+          (base as usize, 1. - weight),
+          ((base as usize + 1).min(max_level as usize), weight),
+        ]
+      }
+    };
+
+    let mut result = [0.; 4];
+    for (level, level_weight) in levels {
+      let texel = data.filter(sampler, filter, &request, level);
+      for (r, t) in result.iter_mut().zip(texel) {
+        *r += t * level_weight;
+      }
+    }
+
+    if request.is_depth {
+      CpuShaderValue::Scalar(ScalarValue::F32(result[0]))
+    } else {
+      CpuShaderValue::Vector(result.map(ScalarValue::F32).to_vec())
+    }
+  }
+}
+
+struct TexelFootprint {
+  x: [Option<u32>; 2],
+  y: [Option<u32>; 2],
+  weight: [f32; 2],
+}
+
+fn address(mode: AddressMode, coord: i64, size: u32) -> Option<u32> {
+  let size = size as i64;
+  let r = match mode {
+    AddressMode::ClampToEdge => coord.clamp(0, size - 1),
+    AddressMode::Repeat => coord.rem_euclid(size),
+    AddressMode::MirrorRepeat => {
+      let period = coord.rem_euclid(size * 2);
+      if period < size {
+        period
+      } else {
+        size * 2 - 1 - period
+      }
+    }
+    AddressMode::ClampToBorder => {
+      if coord < 0 || coord >= size {
+        return None;
+      }
+      coord
+    }
+  };
+  Some(r as u32)
+}
+
+impl CpuShaderTextureData {
+  fn footprint(
+    &self,
+    sampler: &CpuShaderSampler,
+    uv: [f32; 2],
+    offset: [i32; 2],
+    level: usize,
+  ) -> TexelFootprint {
+    let (w, h) = self.level_size(level);
+    let axis = |coord: f32, size: u32, offset: i32, mode: AddressMode| {
+      let texel = coord * size as f32 - 0.5;
+      let base = texel.floor();
+      let weight = texel - base;
+      let base = base as i64 + offset as i64;
+      (
+        [address(mode, base, size), address(mode, base + 1, size)],
+        weight,
+      )
+    };
+    let (x, wx) = axis(uv[0], w, offset[0], sampler.address_mode_u);
+    let (y, wy) = axis(uv[1], h, offset[1], sampler.address_mode_v);
+    TexelFootprint {
+      x,
+      y,
+      weight: [wx, wy],
+    }
+  }
+
+  fn filter(
+    &self,
+    sampler: &CpuShaderSampler,
+    filter: FilterMode,
+    request: &CpuTextureSampleRequest,
+    level: usize,
+  ) -> [f32; 4] {
+    let fetch = |x: Option<u32>, y: Option<u32>| {
+      let texel = match x.zip(y) {
+        Some((x, y)) => self.texel_f32(level, request.layer, x, y),
+        None => [0.; 4],
+      };
+      match request.reference {
+        Some(r) => [sampler.compare_value(r, texel[0]), 0., 0., 0.],
+        None => texel,
+      }
+    };
+
+    match filter {
+      FilterMode::Nearest => {
+        let (w, h) = self.level_size(level);
+        let axis = |coord: f32, size: u32, offset: i32, mode: AddressMode| {
+          address(
+            mode,
+            (coord * size as f32).floor() as i64 + offset as i64,
+            size,
+          )
+        };
+        fetch(
+          axis(request.uv[0], w, request.offset[0], sampler.address_mode_u),
+          axis(request.uv[1], h, request.offset[1], sampler.address_mode_v),
+        )
+      }
+      FilterMode::Linear => {
+        let f = self.footprint(sampler, request.uv, request.offset, level);
+        let [wx, wy] = f.weight;
+        let mut result = [0.; 4];
+        for (x, wx) in f.x.into_iter().zip([1. - wx, wx]) {
+          for (y, wy) in f.y.into_iter().zip([1. - wy, wy]) {
+            let texel = fetch(x, y);
+            for (r, t) in result.iter_mut().zip(texel) {
+              *r += t * wx * wy;
+            }
+          }
+        }
+        result
+      }
+    }
+  }
+}
+
+pub(crate) enum CpuTextureSampleLod {
+  Level(f32),
+  Gradient([f32; 2], [f32; 2]),
+}
+
+pub(crate) struct CpuTextureSampleRequest {
+  pub uv: [f32; 2],
+  pub layer: u32,
+  pub lod: CpuTextureSampleLod,
+  pub offset: [i32; 2],
+  pub reference: Option<f32>,
+  pub gather: Option<usize>,
+  pub is_depth: bool,
+}
+
+/// The sampler state, the field meaning is same as the wgpu's sampler descriptor
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CpuShaderSampler {
+  pub address_mode_u: AddressMode,
+  pub address_mode_v: AddressMode,
+  pub address_mode_w: AddressMode,
+  pub mag_filter: FilterMode,
+  pub min_filter: FilterMode,
+  pub mipmap_filter: MipmapFilterMode,
+  pub compare: Option<CompareFunction>,
+}
+
+impl CpuShaderSampler {
+  fn compare_value(&self, reference: f32, depth: f32) -> f32 {
+    let pass = match self.compare.unwrap_or(CompareFunction::Always) {
+      CompareFunction::Never => false,
+      CompareFunction::Less => reference < depth,
+      CompareFunction::Equal => reference == depth,
+      CompareFunction::LessEqual => reference <= depth,
+      CompareFunction::Greater => reference > depth,
+      CompareFunction::NotEqual => reference != depth,
+      CompareFunction::GreaterEqual => reference >= depth,
+      CompareFunction::Always => true,
+    };
+    if pass { 1. } else { 0. }
+  }
+}
+
+/// The comparison sampler, the compare function should be set in the inner sampler
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CpuShaderComparisonSampler(pub CpuShaderSampler);
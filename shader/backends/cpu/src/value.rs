@@ -0,0 +1,528 @@
+use crate::*;
+
+/// The runtime value of the interpreter. The value is dynamically typed, the shader graph has
+/// already been type checked by the upper layer's rust type system.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum CpuShaderValue {
+  Scalar(ScalarValue),
+  Vector(Vec<ScalarValue>),
+  /// column major
+  Matrix(Vec<Vec<ScalarValue>>),
+  /// struct or array
+  Composite(Vec<CpuShaderValue>),
+  Pointer(CpuShaderPointer),
+  /// texture or sampler, the value is the module global index
+  Resource(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CpuShaderPointer {
+  pub root: CpuShaderPointerRoot,
+  pub path: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CpuShaderPointerRoot {
+  Local { frame: usize, index: usize },
+  Global(usize),
+}
+
+impl CpuShaderPointer {
+  pub fn access(&self, index: usize) -> Self {
+    let mut path = self.path.clone();
+    path.push(index);
+    Self {
+      root: self.root,
+      path,
+    }
+  }
+}
+
+pub(crate) fn scalar_zero(ty: ScalarType) -> ScalarValue {
+  match ty {
+    ScalarType::F32 => ScalarValue::F32(0.),
+    ScalarType::U32 => ScalarValue::U32(0),
+    ScalarType::I32 => ScalarValue::I32(0),
+    ScalarType::Bool => ScalarValue::Bool(false),
+  }
+}
+
+impl CpuShaderValue {
+  pub fn zero_primitive(ty: PrimitiveShaderValueType) -> Self {
+    match ty {
+      PrimitiveShaderValueType::Scalar(s) => Self::Scalar(scalar_zero(s)),
+      PrimitiveShaderValueType::Vector { size, scalar } => {
+        Self::Vector(vec![scalar_zero(scalar); size as usize])
+      }
+      PrimitiveShaderValueType::Matrix {
+        columns,
+        rows,
+        scalar,
+      } => Self::Matrix(vec![
+        vec![scalar_zero(scalar); rows as usize];
+        columns as usize
+      ]),
+    }
+  }
+
+  pub fn zero_sized(ty: &ShaderSizedValueType) -> Self {
+    match ty {
+      ShaderSizedValueType::Atomic(ShaderAtomicValueType::U32) => Self::Scalar(ScalarValue::U32(0)),
+      ShaderSizedValueType::Atomic(ShaderAtomicValueType::I32) => Self::Scalar(ScalarValue::I32(0)),
+      ShaderSizedValueType::Primitive(p) => Self::zero_primitive(*p),
+      ShaderSizedValueType::Struct(meta) => Self::Composite(
+        meta
+          .fields
+          .iter()
+          .map(|f| Self::zero_sized(&f.ty))
+          .collect(),
+      ),
+      ShaderSizedValueType::FixedSizeArray(ty, size) => {
+        Self::Composite(vec![Self::zero_sized(ty); *size])
+      }
+    }
+  }
+
+  /// the unsized array is zero length
+  pub fn zero(ty: &ShaderValueType) -> Self {
+    match ty {
+      ShaderValueType::Single(ShaderValueSingleType::Sized(ty)) => Self::zero_sized(ty),
+      ShaderValueType::Single(ShaderValueSingleType::Unsized(
+        ShaderUnSizedValueType::UnsizedStruct(meta),
+      )) => {
+        let mut fields: Vec<_> = meta
+          .sized_fields
+          .iter()
+          .map(|f| Self::zero_sized(&f.ty))
+          .collect();
+        fields.push(Self::Composite(Vec::new()));
+        Self::Composite(fields)
+      }
+      _ => Self::Composite(Vec::new()),
+    }
+  }
+
+  pub fn from_primitive(value: PrimitiveShaderValue) -> Self {
+    match value {
+      PrimitiveShaderValue::Scalar(v) => Self::Scalar(v),
+      PrimitiveShaderValue::Vector { data, .. } => Self::Vector(data.as_slice().to_vec()),
+      PrimitiveShaderValue::Matrix { data, .. } => {
+        Self::Matrix(data.iter().map(|c| c.as_slice().to_vec()).collect())
+      }
+    }
+  }
+
+  pub fn from_init_value(value: &ShaderStructFieldInitValue) -> Self {
+    match value {
+      ShaderStructFieldInitValue::Primitive(v) => Self::from_primitive(*v),
+      ShaderStructFieldInitValue::Struct(v) | ShaderStructFieldInitValue::Array(v) => {
+        Self::Composite(v.iter().map(Self::from_init_value).collect())
+      }
+    }
+  }
+
+  pub fn as_scalar(&self) -> Result<ScalarValue, CpuShaderExecutionError> {
+    match self {
+      Self::Scalar(v) => Ok(*v),
+      _ => Err(CpuShaderExecutionError::type_mismatch("scalar", self)),
+    }
+  }
+
+  pub fn as_pointer(&self) -> Result<&CpuShaderPointer, CpuShaderExecutionError> {
+    match self {
+      Self::Pointer(v) => Ok(v),
+      _ => Err(CpuShaderExecutionError::type_mismatch("pointer", self)),
+    }
+  }
+
+  pub fn as_bool(&self) -> Result<bool, CpuShaderExecutionError> {
+    match self.as_scalar()? {
+      ScalarValue::Bool(v) => Ok(v),
+      _ => Err(CpuShaderExecutionError::type_mismatch("bool", self)),
+    }
+  }
+
+  /// read the u32 or i32 scalar as index, the negative value is mapped to an out of bound index
+  pub fn as_index(&self) -> Result<usize, CpuShaderExecutionError> {
+    match self.as_scalar()? {
+      ScalarValue::U32(v) => Ok(v as usize),
+      ScalarValue::I32(v) => Ok(usize::try_from(v).unwrap_or(usize::MAX)),
+      _ => Err(CpuShaderExecutionError::type_mismatch("integer", self)),
+    }
+  }
+
+  /// the scalar components of the scalar or vector
+  pub fn components(&self) -> Result<&[ScalarValue], CpuShaderExecutionError> {
+    match self {
+      Self::Scalar(v) => Ok(std::slice::from_ref(v)),
+      Self::Vector(v) => Ok(v),
+      _ => Err(CpuShaderExecutionError::type_mismatch(
+        "scalar or vector",
+        self,
+      )),
+    }
+  }
+
+  /// create scalar or vector by the component count
+  pub fn from_components(components: Vec<ScalarValue>) -> Self {
+    if components.len() == 1 {
+      Self::Scalar(components[0])
+    } else {
+      Self::Vector(components)
+    }
+  }
+
+  /// extract the sub value, used for both value access and pointer access
+  pub fn get(&self, index: usize) -> Result<Self, CpuShaderExecutionError> {
+    let r = match self {
+      Self::Vector(v) => v.get(index).map(|v| Self::Scalar(*v)),
+      Self::Matrix(v) => v.get(index).map(|v| Self::Vector(v.clone())),
+      Self::Composite(v) => v.get(index).cloned(),
+      _ => return Err(CpuShaderExecutionError::type_mismatch("indexable", self)),
+    };
+    r.ok_or(CpuShaderExecutionError::IndexOutOfBounds(index))
+  }
+
+  pub fn load_path(&self, path: &[usize]) -> Result<Self, CpuShaderExecutionError> {
+    match path.split_first() {
+      None => Ok(self.clone()),
+      Some((index, rest)) => match self {
+        Self::Composite(v) => v
+          .get(*index)
+          .ok_or(CpuShaderExecutionError::IndexOutOfBounds(*index))?
+          .load_path(rest),
+        _ => self.get(*index)?.load_path(rest),
+      },
+    }
+  }
+
+  /// access the struct or array member without clone, used for the array length query
+  pub fn composite_path_ref(&self, path: &[usize]) -> Result<&Self, CpuShaderExecutionError> {
+    path.iter().try_fold(self, |v, index| match v {
+      Self::Composite(v) => v
+        .get(*index)
+        .ok_or(CpuShaderExecutionError::IndexOutOfBounds(*index)),
+      _ => Err(CpuShaderExecutionError::type_mismatch("struct or array", v)),
+    })
+  }
+
+  pub fn store_path(&mut self, path: &[usize], value: Self) -> Result<(), CpuShaderExecutionError> {
+    let Some((index, rest)) = path.split_first() else {
+      *self = value;
+      return Ok(());
+    };
+    let index = *index;
+    match self {
+      Self::Composite(v) => v
+        .get_mut(index)
+        .ok_or(CpuShaderExecutionError::IndexOutOfBounds(index))?
+        .store_path(rest, value),
+      Self::Vector(v) => {
+        let target = v
+          .get_mut(index)
+          .ok_or(CpuShaderExecutionError::IndexOutOfBounds(index))?;
+        let mut sub = Self::Scalar(*target);
+        sub.store_path(rest, value)?;
+        *target = sub.as_scalar()?;
+        Ok(())
+      }
+      Self::Matrix(v) => {
+        let target = v
+          .get_mut(index)
+          .ok_or(CpuShaderExecutionError::IndexOutOfBounds(index))?;
+        let mut sub = Self::Vector(std::mem::take(target));
+        let r = sub.store_path(rest, value);
+        if let Self::Vector(sub) = sub {
+          *target = sub;
+        }
+        r
+      }
+      _ => Err(CpuShaderExecutionError::type_mismatch("indexable", self)),
+    }
+  }
+
+  pub fn len(&self) -> Result<usize, CpuShaderExecutionError> {
+    match self {
+      Self::Vector(v) => Ok(v.len()),
+      Self::Matrix(v) => Ok(v.len()),
+      Self::Composite(v) => Ok(v.len()),
+      _ => Err(CpuShaderExecutionError::type_mismatch("array", self)),
+    }
+  }
+}
+
+fn matrix_column_stride(rows: VectorSize, layout: StructLayoutTarget) -> usize {
+  match (layout, rows) {
+    (StructLayoutTarget::Packed, rows) => rows as usize * 4,
+    (_, VectorSize::Bi) => 8,
+    _ => 16,
+  }
+}
+
+fn array_stride(ty: &ShaderSizedValueType, layout: StructLayoutTarget) -> usize {
+  let array = ShaderSizedValueType::FixedSizeArray(Box::new(ty.clone()), 1);
+  round_up(array.align_of_self(layout), ty.size_of_self(layout))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, CpuShaderExecutionError> {
+  bytes
+    .get(offset..offset + 4)
+    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    .ok_or(CpuShaderExecutionError::BufferTooSmall)
+}
+
+fn write_u32(bytes: &mut [u8], offset: usize, v: u32) -> Result<(), CpuShaderExecutionError> {
+  bytes
+    .get_mut(offset..offset + 4)
+    .ok_or(CpuShaderExecutionError::BufferTooSmall)?
+    .copy_from_slice(&v.to_le_bytes());
+  Ok(())
+}
+
+pub(crate) fn decode_scalar(ty: ScalarType, bits: u32) -> ScalarValue {
+  match ty {
+    ScalarType::F32 => ScalarValue::F32(f32::from_bits(bits)),
+    ScalarType::U32 => ScalarValue::U32(bits),
+    ScalarType::I32 => ScalarValue::I32(bits as i32),
+    ScalarType::Bool => ScalarValue::Bool(bits != 0),
+  }
+}
+
+pub(crate) fn scalar_bits(v: ScalarValue) -> u32 {
+  match v {
+    ScalarValue::F32(v) => v.to_bits(),
+    ScalarValue::U32(v) => v,
+    ScalarValue::I32(v) => v as u32,
+    ScalarValue::Bool(v) => v as u32,
+  }
+}
+
+/// Decode the host shareable value from the buffer bytes by the given layout
+pub(crate) fn decode_sized(
+  ty: &ShaderSizedValueType,
+  layout: StructLayoutTarget,
+  bytes: &[u8],
+  offset: usize,
+) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+  Ok(match ty {
+    ShaderSizedValueType::Atomic(ShaderAtomicValueType::U32) => {
+      CpuShaderValue::Scalar(ScalarValue::U32(read_u32(bytes, offset)?))
+    }
+    ShaderSizedValueType::Atomic(ShaderAtomicValueType::I32) => {
+      CpuShaderValue::Scalar(ScalarValue::I32(read_u32(bytes, offset)? as i32))
+    }
+    ShaderSizedValueType::Primitive(p) => match *p {
+      PrimitiveShaderValueType::Scalar(s) => {
+        CpuShaderValue::Scalar(decode_scalar(s, read_u32(bytes, offset)?))
+      }
+      PrimitiveShaderValueType::Vector { size, scalar } => CpuShaderValue::Vector(
+        (0..size as usize)
+          .map(|i| Ok(decode_scalar(scalar, read_u32(bytes, offset + i * 4)?)))
+          .collect::<Result<_, _>>()?,
+      ),
+      PrimitiveShaderValueType::Matrix {
+        columns,
+        rows,
+        scalar,
+      } => {
+        let stride = matrix_column_stride(rows, layout);
+        CpuShaderValue::Matrix(
+          (0..columns as usize)
+            .map(|c| {
+              (0..rows as usize)
+                .map(|r| {
+                  Ok(decode_scalar(
+                    scalar,
+                    read_u32(bytes, offset + c * stride + r * 4)?,
+                  ))
+                })
+                .collect::<Result<_, _>>()
+            })
+            .collect::<Result<_, _>>()?,
+        )
+      }
+    },
+    ShaderSizedValueType::Struct(meta) => {
+      let mut fields = Vec::with_capacity(meta.fields.len());
+      let mut error = None;
+      iter_field_start_offset_in_bytes(&meta.fields, layout, &mut |field_offset, field| {
+        match decode_sized(&field.ty, layout, bytes, offset + field_offset) {
+          Ok(v) => fields.push(v),
+          Err(e) => error = Some(e),
+        }
+      });
+      if let Some(e) = error {
+        return Err(e);
+      }
+      CpuShaderValue::Composite(fields)
+    }
+    ShaderSizedValueType::FixedSizeArray(ty, size) => {
+      let stride = array_stride(ty, layout);
+      CpuShaderValue::Composite(
+        (0..*size)
+          .map(|i| decode_sized(ty, layout, bytes, offset + i * stride))
+          .collect::<Result<_, _>>()?,
+      )
+    }
+  })
+}
+
+pub(crate) fn encode_sized(
+  ty: &ShaderSizedValueType,
+  layout: StructLayoutTarget,
+  value: &CpuShaderValue,
+  bytes: &mut [u8],
+  offset: usize,
+) -> Result<(), CpuShaderExecutionError> {
+  match (ty, value) {
+    (ShaderSizedValueType::Atomic(_), CpuShaderValue::Scalar(v)) => {
+      write_u32(bytes, offset, scalar_bits(*v))
+    }
+    (ShaderSizedValueType::Primitive(_), CpuShaderValue::Scalar(v)) => {
+      write_u32(bytes, offset, scalar_bits(*v))
+    }
+    (ShaderSizedValueType::Primitive(_), CpuShaderValue::Vector(v)) => {
+      for (i, v) in v.iter().enumerate() {
+        write_u32(bytes, offset + i * 4, scalar_bits(*v))?;
+      }
+      Ok(())
+    }
+    (
+      ShaderSizedValueType::Primitive(PrimitiveShaderValueType::Matrix { rows, .. }),
+      CpuShaderValue::Matrix(v),
+    ) => {
+      let stride = matrix_column_stride(*rows, layout);
+      for (c, column) in v.iter().enumerate() {
+        for (r, v) in column.iter().enumerate() {
+          write_u32(bytes, offset + c * stride + r * 4, scalar_bits(*v))?;
+        }
+      }
+      Ok(())
+    }
+    (ShaderSizedValueType::Struct(meta), CpuShaderValue::Composite(v)) => {
+      let mut result = Ok(());
+      let mut index = 0;
+      iter_field_start_offset_in_bytes(&meta.fields, layout, &mut |field_offset, field| {
+        if let Some(v) = v.get(index) {
+          let r = encode_sized(&field.ty, layout, v, bytes, offset + field_offset);
+          if r.is_err() {
+            result = r;
+          }
+        }
+        index += 1;
+      });
+      result
+    }
+    (ShaderSizedValueType::FixedSizeArray(ty, _), CpuShaderValue::Composite(v)) => {
+      let stride = array_stride(ty, layout);
+      for (i, v) in v.iter().enumerate() {
+        encode_sized(ty, layout, v, bytes, offset + i * stride)?;
+      }
+      Ok(())
+    }
+    _ => Err(CpuShaderExecutionError::type_mismatch(
+      "host shareable",
+      value,
+    )),
+  }
+}
+
+fn unsized_array_layout(
+  ty: &ShaderUnSizedValueType,
+  layout: StructLayoutTarget,
+) -> (Vec<ShaderStructFieldMetaInfo>, ShaderSizedValueType, usize) {
+  match ty {
+    ShaderUnSizedValueType::UnsizedArray(ty) => (Vec::new(), *ty.clone(), 0),
+    ShaderUnSizedValueType::UnsizedStruct(meta) => {
+      let fields: Vec<_> = meta.sized_fields.to_vec();
+      let offset = size_of_struct_sized_fields(&fields, layout);
+      (fields, *meta.last_dynamic_array_field.1.clone(), offset)
+    }
+  }
+}
+
+/// Decode the binding buffer, the unsized array's length is decided by the buffer size
+pub(crate) fn decode_buffer(
+  ty: &ShaderValueSingleType,
+  layout: StructLayoutTarget,
+  bytes: &[u8],
+) -> Result<CpuShaderValue, CpuShaderExecutionError> {
+  match ty {
+    ShaderValueSingleType::Sized(ty) => decode_sized(ty, layout, bytes, 0),
+    ShaderValueSingleType::Unsized(ty) => {
+      let (fields, array_ty, array_offset) = unsized_array_layout(ty, layout);
+      let stride = array_stride(&array_ty, layout);
+      let count = bytes.len().saturating_sub(array_offset) / stride;
+      let array = CpuShaderValue::Composite(
+        (0..count)
+          .map(|i| decode_sized(&array_ty, layout, bytes, array_offset + i * stride))
+          .collect::<Result<_, _>>()?,
+      );
+      if let ShaderUnSizedValueType::UnsizedArray(_) = ty {
+        return Ok(array);
+      }
+      let meta = ShaderStructMetaInfo {
+        name: String::new(),
+        fields,
+      };
+      let CpuShaderValue::Composite(mut fields) =
+        decode_sized(&ShaderSizedValueType::Struct(meta), layout, bytes, 0)?
+      else {
+        unreachable!()
+      };
+      fields.push(array);
+      Ok(CpuShaderValue::Composite(fields))
+    }
+    _ => Err(CpuShaderExecutionError::Unsupported(
+      "non buffer binding decode",
+    )),
+  }
+}
+
+pub(crate) fn encode_buffer(
+  ty: &ShaderValueSingleType,
+  layout: StructLayoutTarget,
+  value: &CpuShaderValue,
+  bytes: &mut [u8],
+) -> Result<(), CpuShaderExecutionError> {
+  match ty {
+    ShaderValueSingleType::Sized(ty) => encode_sized(ty, layout, value, bytes, 0),
+    ShaderValueSingleType::Unsized(ty) => {
+      let (fields, array_ty, array_offset) = unsized_array_layout(ty, layout);
+      let stride = array_stride(&array_ty, layout);
+      let CpuShaderValue::Composite(values) = value else {
+        return Err(CpuShaderExecutionError::type_mismatch("array", value));
+      };
+      let array = if let ShaderUnSizedValueType::UnsizedArray(_) = ty {
+        values
+      } else {
+        let (array, sized) = values
+          .split_last()
+          .ok_or(CpuShaderExecutionError::type_mismatch("struct", value))?;
+        let meta = ShaderStructMetaInfo {
+          name: String::new(),
+          fields,
+        };
+        let sized = CpuShaderValue::Composite(sized.to_vec());
+        encode_sized(
+          &ShaderSizedValueType::Struct(meta),
+          layout,
+          &sized,
+          bytes,
+          0,
+        )?;
+        let CpuShaderValue::Composite(array) = array else {
+          return Err(CpuShaderExecutionError::type_mismatch("array", array));
+        };
+        array
+      };
+      for (i, v) in array.iter().enumerate() {
+        encode_sized(&array_ty, layout, v, bytes, array_offset + i * stride)?;
+      }
+      Ok(())
+    }
+    _ => Err(CpuShaderExecutionError::Unsupported(
+      "non buffer binding encode",
+    )),
+  }
+}
@@ -0,0 +1,136 @@
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShaderDebugPrintFilter {
+  /// all print is skipped
+  #[default]
+  Disabled,
+  /// all invocations are printed, this may produce huge amount of records
+  All,
+  /// match the x and y of the invocation id, for fragment shader it's the pixel position.
+  Pixel(Vec2<u32>),
+  /// match the full invocation id, for compute shader it's the global invocation id.
+  Invocation(Vec3<u32>),
+}
+
+impl ShaderDebugPrintFilter {
+  fn into_uniform(self) -> ShaderDebugPrintFilterUniform {
+    let (mode, target) = match self {
+      Self::Disabled => (0, Vec3::zero()),
+      Self::All => (1, Vec3::zero()),
+      Self::Pixel(p) => (2, Vec3::new(p.x, p.y, 0)),
+      Self::Invocation(id) => (3, id),
+    };
+    ShaderDebugPrintFilterUniform {
+      target,
+      mode,
+      ..Zeroable::zeroed()
+    }
+  }
+}
+
+#[repr(C)]
+#[std140_layout]
+#[derive(Clone, Copy, ShaderStruct, Default)]
+struct ShaderDebugPrintFilterUniform {
+  pub target: Vec3<u32>,
+  pub mode: u32,
+}
+
+/// The device side debug print buffer.
+///
+/// The buffer should be reset before the frame or dispatch that contains print, and read back
+/// after the work submitted. The buffer layout is a u32 write cursor followed by the records.
+#[derive(Clone)]
+pub struct ShaderDebugPrint {
+  buffer: StorageBufferDataView<[DeviceAtomic<u32>]>,
+  filter: UniformBufferDataView<ShaderDebugPrintFilterUniform>,
+}
+
+impl ShaderDebugPrint {
+  pub fn new(device: &GPUDevice, capacity_in_words: u32, filter: ShaderDebugPrintFilter) -> Self {
+    let init = ZeroedArrayByArrayLength(capacity_in_words as usize + 1);
+    Self {
+      buffer: create_gpu_read_write_storage(init, device, "shader debug print buffer"),
+      filter: create_uniform(filter.into_uniform(), device, "shader debug print filter"),
+    }
+  }
+
+  pub fn set_filter(&self, filter: ShaderDebugPrintFilter, queue: &GPUQueue) {
+    self.filter.write_at(queue, &filter.into_uniform(), 0);
+  }
+
+  /// clear the cursor and all records.
+  ///
+  /// the records are cleared too, because the decoder relies on the zeroed header to find
+  /// the dropped record when the buffer overflows.
+  pub fn reset(&self, encoder: &mut GPUCommandEncoder) {
+    let view = &self.buffer.gpu;
+    encoder.clear_buffer(
+      view.buffer.gpu(),
+      view.range.offset,
+      view.range.size.map(|v| v.get()),
+    );
+  }
+
+  pub fn read_back(
+    &self,
+    encoder: &mut GPUCommandEncoder,
+    device: &GPUDevice,
+  ) -> impl Future<Output = Result<ShaderDebugPrintResult, BufferAsyncError>> + use<> {
+    encoder
+      .read_atomic_storage_array(device, &self.buffer)
+      .map(|words| words.map(|words| decode_shader_debug_print(&words)))
+  }
+
+  /// the invocation id is used to match the filter.
+  pub fn build(
+    &self,
+    builder: &mut ShaderBindGroupBuilder,
+    invocation_id: Node<Vec3<u32>>,
+  ) -> ShaderDebugPrinter {
+    // the format id baked into the shader is allocated from the process local registry
+    builder.mark_not_persistable();
+    let buffer = builder.bind_by(&self.buffer);
+    let filter = builder.bind_by(&self.filter).load().expand();
+
+    let xy_match = invocation_id.xy().equals(filter.target.xy()).all();
+    let z_match = invocation_id.z().equals(filter.target.z());
+    let enabled = filter
+      .mode
+      .equals(1)
+      .or(filter.mode.equals(2).and(xy_match))
+      .or(filter.mode.equals(3).and(xy_match).and(z_match));
+
+    ShaderDebugPrinter {
+      capacity: buffer.array_length() - val(1),
+      buffer,
+      invocation_id,
+      enabled,
+    }
+  }
+
+  /// filter by the global invocation id
+  pub fn build_for_compute(
+    &self,
+    builder: &mut ShaderComputePipelineBuilder,
+  ) -> ShaderDebugPrinter {
+    let id = builder.global_invocation_id();
+    self.build(&mut builder.bindgroups, id)
+  }
+
+  /// filter by the pixel position, the z of the invocation id is zero.
+  pub fn build_for_fragment(
+    &self,
+    builder: &mut ShaderFragmentBuilderView,
+    binding: &mut ShaderBindGroupBuilder,
+  ) -> ShaderDebugPrinter {
+    let pixel = builder.query::<FragmentPosition>().xy().floor().into_u32();
+    self.build(binding, (pixel, val(0)).into())
+  }
+
+  pub fn bind(&self, builder: &mut BindingBuilder) {
+    builder.bind(&self.buffer);
+    builder.bind(&self.filter);
+  }
+}
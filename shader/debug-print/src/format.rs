@@ -0,0 +1,169 @@
+use crate::*;
+
+/// The format string and argument types are only known at shader build time, the device side
+/// record only stores the format id. The registry is global so the id is stable even if the
+/// pipeline is cached and reused by another [ShaderDebugPrint] instance. The id is only valid
+/// in the current process, so the pipeline using the print is not persistently cached.
+static FORMATS: RwLock<Vec<ShaderDebugPrintFormat>> = RwLock::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderDebugPrintFormat {
+  pub format: String,
+  pub args: Vec<PrimitiveShaderValueType>,
+}
+
+impl ShaderDebugPrintFormat {
+  /// the u32 word count of the record payload, not including the header
+  pub fn payload_word_count(&self) -> u32 {
+    self.args.iter().map(|ty| component_count(*ty)).sum()
+  }
+}
+
+pub(crate) fn component_count(ty: PrimitiveShaderValueType) -> u32 {
+  match ty {
+    PrimitiveShaderValueType::Scalar(_) => 1,
+    PrimitiveShaderValueType::Vector { size, .. } => size as u32,
+    PrimitiveShaderValueType::Matrix { columns, rows, .. } => columns as u32 * rows as u32,
+  }
+}
+
+/// return the format id, the same format will be deduplicated.
+pub(crate) fn register_format(format: ShaderDebugPrintFormat) -> u32 {
+  let placeholder_count = split_format(&format.format).len() - 1;
+  assert_eq!(
+    placeholder_count,
+    format.args.len(),
+    "shader debug print format `{}` expect {} arguments",
+    format.format,
+    placeholder_count,
+  );
+
+  let mut formats = FORMATS.write();
+  if let Some(id) = formats.iter().position(|f| *f == format) {
+    return id as u32;
+  }
+  formats.push(format);
+  formats.len() as u32 - 1
+}
+
+pub fn get_shader_debug_print_format(id: u32) -> Option<ShaderDebugPrintFormat> {
+  FORMATS.read().get(id as usize).cloned()
+}
+
+/// split the format string by the `{}` placeholder, the `{{` and `}}` are escaped.
+fn split_format(format: &str) -> Vec<String> {
+  let mut segments = vec![String::new()];
+  let mut chars = format.chars().peekable();
+  while let Some(c) = chars.next() {
+    match (c, chars.peek()) {
+      ('{', Some('{')) | ('}', Some('}')) => {
+        chars.next();
+        segments.last_mut().unwrap().push(c);
+      }
+      ('{', Some('}')) => {
+        chars.next();
+        segments.push(String::new());
+      }
+      _ => segments.last_mut().unwrap().push(c),
+    }
+  }
+  segments
+}
+
+fn format_scalar(ty: ScalarType, word: u32) -> String {
+  match ty {
+    ScalarType::F32 => format!("{:?}", f32::from_bits(word)),
+    ScalarType::U32 => word.to_string(),
+    ScalarType::I32 => (word as i32).to_string(),
+    ScalarType::Bool => (word != 0).to_string(),
+  }
+}
+
+fn format_value(ty: PrimitiveShaderValueType, words: &[u32]) -> String {
+  let join = |scalar, words: &[u32]| {
+    let components: Vec<_> = words.iter().map(|w| format_scalar(scalar, *w)).collect();
+    format!("({})", components.join(", "))
+  };
+  match ty {
+    PrimitiveShaderValueType::Scalar(scalar) => format_scalar(scalar, words[0]),
+    PrimitiveShaderValueType::Vector { scalar, .. } => join(scalar, words),
+    PrimitiveShaderValueType::Matrix { rows, scalar, .. } => {
+      let columns: Vec<_> = words
+        .chunks(rows as usize)
+        .map(|column| join(scalar, column))
+        .collect();
+      format!("[{}]", columns.join(", "))
+    }
+  }
+}
+
+impl ShaderDebugPrintFormat {
+  pub fn format_payload(&self, payload: &[u32]) -> String {
+    let segments = split_format(&self.format);
+    let mut result = segments[0].clone();
+    let mut offset = 0;
+    for (ty, segment) in self.args.iter().zip(&segments[1..]) {
+      let count = component_count(*ty) as usize;
+      result.push_str(&format_value(*ty, &payload[offset..offset + count]));
+      result.push_str(segment);
+      offset += count;
+    }
+    result
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderDebugPrintRecord {
+  /// the invocation id that used to match the filter, for fragment shader it's the pixel position.
+  pub invocation_id: Vec3<u32>,
+  pub message: String,
+}
+
+impl std::fmt::Display for ShaderDebugPrintRecord {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let id = self.invocation_id;
+    write!(f, "[{}, {}, {}] {}", id.x, id.y, id.z, self.message)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderDebugPrintResult {
+  pub records: Vec<ShaderDebugPrintRecord>,
+  /// if the buffer is full, the later records are dropped
+  pub truncated: bool,
+}
+
+/// decode the raw content of the debug print buffer, the first word is the write cursor
+pub fn decode_shader_debug_print(words: &[u32]) -> ShaderDebugPrintResult {
+  let Some((cursor, data)) = words.split_first() else {
+    return Default::default();
+  };
+  let capacity = data.len();
+  let data = &data[..capacity.min(*cursor as usize)];
+
+  let mut records = Vec::new();
+  let mut offset = 0;
+  // a zero header means the record is not written because of buffer overflow.
+  while let Some(header) = data.get(offset..offset + RECORD_HEADER_WORD_COUNT as usize)
+    && header[0] != 0
+  {
+    let Some(format) = get_shader_debug_print_format(header[0] - 1) else {
+      break;
+    };
+    let payload_start = offset + RECORD_HEADER_WORD_COUNT as usize;
+    let payload_end = payload_start + format.payload_word_count() as usize;
+    let Some(payload) = data.get(payload_start..payload_end) else {
+      break;
+    };
+    records.push(ShaderDebugPrintRecord {
+      invocation_id: Vec3::new(header[1], header[2], header[3]),
+      message: format.format_payload(payload),
+    });
+    offset = payload_end;
+  }
+
+  ShaderDebugPrintResult {
+    records,
+    truncated: *cursor as usize > capacity,
+  }
+}
@@ -0,0 +1,22 @@
+//! Shader side printf. The print records are appended to a storage buffer by an atomic cursor
+//! and decoded on host after read back. The format string is registered at shader build time,
+//! so only the format id and the argument values are written by the device.
+
+use std::future::Future;
+
+use futures::FutureExt;
+use parking_lot::RwLock;
+use rendiation_shader_api::*;
+use rendiation_webgpu::*;
+
+mod device;
+pub use device::*;
+
+mod format;
+pub use format::*;
+
+mod shader;
+pub use shader::*;
+
+#[cfg(test)]
+mod test;
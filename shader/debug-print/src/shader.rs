@@ -0,0 +1,175 @@
+use crate::*;
+
+/// the record header contains the format id(plus one, zero means not written) and the invocation id
+pub(crate) const RECORD_HEADER_WORD_COUNT: u32 = 4;
+
+/// The value that could be printed, the value is encoded as u32 words in the record.
+pub trait ShaderDebugPrintValue: PrimitiveShaderNodeType {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>>;
+}
+
+impl ShaderDebugPrintValue for u32 {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+    vec![node]
+  }
+}
+
+impl ShaderDebugPrintValue for i32 {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+    vec![node.bitcast::<u32>()]
+  }
+}
+
+impl ShaderDebugPrintValue for f32 {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+    vec![node.bitcast::<u32>()]
+  }
+}
+
+impl ShaderDebugPrintValue for bool {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+    vec![node.select(val(1_u32), val(0_u32))]
+  }
+}
+
+macro_rules! impl_vector_print {
+  ($scalar: ty) => {
+    impl ShaderDebugPrintValue for Vec2<$scalar> {
+      fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+        [node.x(), node.y()]
+          .into_iter()
+          .flat_map(<$scalar>::debug_print_words)
+          .collect()
+      }
+    }
+    impl ShaderDebugPrintValue for Vec3<$scalar> {
+      fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+        [node.x(), node.y(), node.z()]
+          .into_iter()
+          .flat_map(<$scalar>::debug_print_words)
+          .collect()
+      }
+    }
+    impl ShaderDebugPrintValue for Vec4<$scalar> {
+      fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+        [node.x(), node.y(), node.z(), node.w()]
+          .into_iter()
+          .flat_map(<$scalar>::debug_print_words)
+          .collect()
+      }
+    }
+  };
+}
+
+impl_vector_print!(u32);
+impl_vector_print!(i32);
+impl_vector_print!(f32);
+
+impl ShaderDebugPrintValue for Mat2<f32> {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+    [node.x(), node.y()]
+      .into_iter()
+      .flat_map(Vec2::debug_print_words)
+      .collect()
+  }
+}
+
+impl ShaderDebugPrintValue for Mat3<f32> {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+    [node.x(), node.y(), node.z()]
+      .into_iter()
+      .flat_map(Vec3::debug_print_words)
+      .collect()
+  }
+}
+
+impl ShaderDebugPrintValue for Mat4<f32> {
+  fn debug_print_words(node: Node<Self>) -> Vec<Node<u32>> {
+    [node.x(), node.y(), node.z(), node.w()]
+      .into_iter()
+      .flat_map(Vec4::debug_print_words)
+      .collect()
+  }
+}
+
+/// The type erased print argument, created by [shader_debug_print] macro.
+pub struct ShaderDebugPrintArg {
+  pub ty: PrimitiveShaderValueType,
+  pub words: Vec<Node<u32>>,
+}
+
+impl ShaderDebugPrintArg {
+  pub fn new<T: ShaderDebugPrintValue>(node: Node<T>) -> Self {
+    Self {
+      ty: T::primitive_ty(),
+      words: T::debug_print_words(node),
+    }
+  }
+}
+
+/// The shader side print access, created by [ShaderDebugPrint::build].
+///
+/// The nodes are created in the current building function, so the printer should be passed
+/// into the user function as parameters or only used in the entry function.
+#[derive(Clone)]
+pub struct ShaderDebugPrinter {
+  pub(crate) buffer: ShaderPtrOf<[DeviceAtomic<u32>]>,
+  /// the data capacity in words, not including the cursor
+  pub(crate) capacity: Node<u32>,
+  pub(crate) invocation_id: Node<Vec3<u32>>,
+  pub(crate) enabled: Node<bool>,
+}
+
+impl ShaderDebugPrinter {
+  /// check if the current invocation pass the filter, the print is noop if not enabled.
+  pub fn enabled(&self) -> Node<bool> {
+    self.enabled
+  }
+
+  /// the format string use `{}` as placeholder, and `{{` `}}` to escape the brace.
+  ///
+  /// prefer using the [shader_debug_print] macro.
+  pub fn print(&self, format: &str, args: Vec<ShaderDebugPrintArg>) {
+    let format_id = register_format(ShaderDebugPrintFormat {
+      format: format.to_string(),
+      args: args.iter().map(|arg| arg.ty).collect(),
+    });
+
+    let id = self.invocation_id;
+    let words: Vec<_> = [val(format_id + 1), id.x(), id.y(), id.z()]
+      .into_iter()
+      .chain(args.into_iter().flat_map(|arg| arg.words))
+      .collect();
+    let word_count = words.len() as u32;
+
+    if_by(self.enabled, || {
+      let offset = self.buffer.index(0).atomic_add(val(word_count));
+      // the cursor keeps increasing after overflow, the host could know if record is dropped.
+      if_by(
+        (offset + val(word_count)).less_equal_than(self.capacity),
+        || {
+          // skip the cursor word
+          let start = offset + val(1);
+          for (i, word) in words.iter().enumerate() {
+            self.buffer.index(start + val(i as u32)).atomic_store(*word);
+          }
+        },
+      );
+    });
+  }
+}
+
+/// Print the shader runtime values into the debug print buffer.
+///
+/// ```ignore
+/// shader_debug_print!(printer, "uv: {}, depth: {}", uv, depth);
+/// ```
+#[macro_export]
+macro_rules! shader_debug_print {
+  ($printer: expr, $format: literal $(, $arg: expr)* $(,)?) => {
+    $printer.print(
+      $format,
+      vec![$($crate::ShaderDebugPrintArg::new($arg)),*],
+    )
+  };
+}
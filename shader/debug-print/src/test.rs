@@ -0,0 +1,147 @@
+use crate::*;
+
+#[test]
+fn test_decode() {
+  let format = ShaderDebugPrintFormat {
+    format: "a = {}, b = {} {{}}".to_string(),
+    args: vec![
+      PrimitiveShaderValueType::i32(),
+      PrimitiveShaderValueType::vec2::<f32>(),
+    ],
+  };
+  let id = register_format(format.clone());
+  assert_eq!(id, register_format(format));
+
+  let record = [id + 1, 1, 2, 0, (-3_i32) as u32, 1.5_f32.to_bits(), 0];
+  let mut words = vec![record.len() as u32 * 2];
+  words.extend_from_slice(&record);
+  // the second record is dropped because of overflow, the header is zero
+  words.extend_from_slice(&[0; 5]);
+
+  let result = decode_shader_debug_print(&words);
+  assert!(result.truncated);
+  assert_eq!(result.records.len(), 1);
+  assert_eq!(
+    result.records[0].to_string(),
+    "[1, 2, 0] a = -3, b = (1.5, 0.0) {}"
+  );
+}
+
+#[pollster::test]
+async fn test_compute_print() {
+  let (gpu, _) = GPU::new(Default::default()).await.unwrap();
+
+  let filter = ShaderDebugPrintFilter::Invocation(Vec3::new(3, 0, 0));
+  let debug_print = ShaderDebugPrint::new(&gpu.device, 1024, filter);
+
+  let pipeline = {
+    let mut cx = compute_shader_builder(&gpu).with_config_work_group_size(8);
+    let printer = debug_print.build_for_compute(&mut cx);
+    let id = cx.global_invocation_id().x();
+    shader_debug_print!(
+      printer,
+      "id: {}, half: {}, even: {}",
+      id,
+      id.into_f32() * val(0.5),
+      (id % val(2)).equals(0)
+    );
+    cx.create_compute_pipeline(&gpu, "debug print test")
+      .unwrap()
+  };
+
+  let mut encoder = gpu.create_encoder();
+  debug_print.reset(&mut encoder);
+  encoder.compute_pass_scoped(|mut pass| {
+    let mut binding = BindingBuilder::default();
+    debug_print.bind(&mut binding);
+    binding.setup_compute_pass(&mut pass, &gpu.device, &pipeline);
+    pass.dispatch_workgroups(1, 1, 1);
+  });
+  let result = debug_print.read_back(&mut encoder, &gpu.device);
+  gpu.submit_encoder(encoder);
+
+  let result = result.await.unwrap();
+  assert!(!result.truncated);
+  assert_eq!(result.records.len(), 1);
+  assert_eq!(
+    result.records[0].to_string(),
+    "[3, 0, 0] id: 3, half: 1.5, even: false"
+  );
+}
+
+#[pollster::test]
+async fn test_debug_print_pipeline_not_persistently_cached() {
+  let folder = std::env::temp_dir().join("rendiation_debug_print_pipeline_cache_test");
+  std::fs::remove_dir_all(&folder).ok();
+  let config = GPUPipelinePersistentCacheConfig {
+    remove_stale: false,
+    warm_up: false,
+    ..GPUPipelinePersistentCacheConfig::new(&folder)
+  };
+
+  let filter = ShaderDebugPrintFilter::Invocation(Vec3::new(5, 0, 0));
+  let create_pipelines = |gpu: &GPU, debug_print: &ShaderDebugPrint| {
+    let hasher = PipelineHasher::default().with_hash("persistent cache debug print");
+    let print = gpu
+      .device
+      .get_or_cache_create_compute_pipeline_by(hasher, |mut cx| {
+        cx.config_work_group_size(8);
+        let printer = debug_print.build_for_compute(&mut cx);
+        shader_debug_print!(printer, "id: {}", cx.global_invocation_id().x());
+        cx
+      });
+    let hasher = PipelineHasher::default().with_hash("persistent cache plain");
+    gpu
+      .device
+      .get_or_cache_create_compute_pipeline_by(hasher, |cx| cx.with_config_work_group_size(8));
+    print
+  };
+
+  {
+    let (gpu, _) = GPU::new(Default::default()).await.unwrap();
+    let cache = GPUPipelinePersistentCache::open(&gpu.device, &config).unwrap();
+    gpu.device.set_persistent_pipeline_cache(Some(cache));
+    let debug_print = ShaderDebugPrint::new(&gpu.device, 1024, filter);
+    create_pipelines(&gpu, &debug_print);
+
+    let cache = gpu.device.get_persistent_pipeline_cache().unwrap();
+    assert_eq!(cache.recorded_keys().len(), 1);
+  }
+
+  // only the plain pipeline is restored, the debug print pipeline is built again by the
+  // shader api so the format is registered in this build.
+  let (gpu, _) = GPU::new(Default::default()).await.unwrap();
+  let cache = GPUPipelinePersistentCache::open(&gpu.device, &config).unwrap();
+  gpu.device.set_persistent_pipeline_cache(Some(cache));
+  assert_eq!(gpu.device.warm_up_persistent_pipeline_cache(), 1);
+
+  let debug_print = ShaderDebugPrint::new(&gpu.device, 1024, filter);
+  let pipeline = create_pipelines(&gpu, &debug_print);
+  assert_eq!(
+    gpu
+      .device
+      .get_persistent_pipeline_cache()
+      .unwrap()
+      .recorded_keys()
+      .len(),
+    1
+  );
+
+  let mut encoder = gpu.create_encoder();
+  debug_print.reset(&mut encoder);
+  encoder.compute_pass_scoped(|mut pass| {
+    let mut binding = BindingBuilder::default();
+    debug_print.bind(&mut binding);
+    binding.setup_compute_pass(&mut pass, &gpu.device, &pipeline);
+    pass.dispatch_workgroups(1, 1, 1);
+  });
+  let result = debug_print.read_back(&mut encoder, &gpu.device);
+  gpu.submit_encoder(encoder);
+
+  let result = result.await.unwrap();
+  assert_eq!(result.records.len(), 1);
+  assert_eq!(result.records[0].to_string(), "[5, 0, 0] id: 5");
+
+  gpu.device.set_persistent_pipeline_cache(None);
+  std::fs::remove_dir_all(&folder).ok();
+}
@@ -6,6 +6,7 @@ pub mod normal_mapping;
 pub mod octahedral;
 pub mod plane;
 pub mod sampling;
+pub mod sh;
 pub mod sky;
 pub mod z_order;
 
@@ -117,3 +117,12 @@ pub fn concentric_sample_disk_device(sample: Node<Vec2<f32>>) -> Node<Vec2<f32>>
   });
   re.load()
 }
+
+/// map the distribution from the unit square to the whole unit sphere uniformly
+#[shader_fn]
+pub fn sample_sphere_uniform(uv: Node<Vec2<f32>>) -> Node<Vec3<f32>> {
+  let phi = val(2.0 * std::f32::consts::PI) * uv.y();
+  let cos_theta = val(1.0) - val(2.0) * uv.x();
+  let sin_theta = (val(1.0) - cos_theta * cos_theta).max(0.).sqrt();
+  (phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta).into()
+}
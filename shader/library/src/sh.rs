@@ -0,0 +1,134 @@
+//! Real spherical harmonics up to band 2 (9 coefficients).
+//!
+//! https://cseweb.ucsd.edu/~ravir/papers/envmap/envmap.pdf
+
+use crate::*;
+
+pub const SH_L2_COEFFICIENT_COUNT: usize = 9;
+
+/// radiance or irradiance projected into sh l2, one rgb coefficient per basis.
+pub type ShL2Rgb = [Vec3<f32>; SH_L2_COEFFICIENT_COUNT];
+
+const SH_Y0: f32 = 0.282_094_8;
+const SH_Y1: f32 = 0.488_602_5;
+const SH_Y2: f32 = 1.092_548_4;
+const SH_Y20: f32 = 0.315_391_6;
+const SH_Y22: f32 = 0.546_274_2;
+
+/// the cosine lobe convolution factor of each band, used to convert radiance sh to irradiance
+const SH_COSINE_A0: f32 = std::f32::consts::PI;
+const SH_COSINE_A1: f32 = std::f32::consts::PI * 2. / 3.;
+const SH_COSINE_A2: f32 = std::f32::consts::PI / 4.;
+
+/// evaluate the 9 basis functions, the dir must be normalized.
+pub fn sh_l2_basis(dir: Vec3<f32>) -> [f32; SH_L2_COEFFICIENT_COUNT] {
+  let Vec3 { x, y, z } = dir;
+  [
+    SH_Y0,
+    SH_Y1 * y,
+    SH_Y1 * z,
+    SH_Y1 * x,
+    SH_Y2 * x * y,
+    SH_Y2 * y * z,
+    SH_Y20 * (3. * z * z - 1.),
+    SH_Y2 * x * z,
+    SH_Y22 * (x * x - y * y),
+  ]
+}
+
+/// add a radiance sample into the sh projection. the result should be scaled by
+/// 4π / sample count if the sample direction is uniformly distributed on the sphere.
+pub fn sh_l2_accumulate_radiance(sh: &mut ShL2Rgb, dir: Vec3<f32>, radiance: Vec3<f32>) {
+  for (c, basis) in sh.iter_mut().zip(sh_l2_basis(dir)) {
+    *c += radiance * basis;
+  }
+}
+
+/// compute the irradiance of the surface facing the normal direction from the radiance sh.
+pub fn sh_l2_irradiance(sh: &ShL2Rgb, normal: Vec3<f32>) -> Vec3<f32> {
+  let basis = sh_l2_basis(normal);
+  let band_factor = |i: usize| match i {
+    0 => SH_COSINE_A0,
+    1..=3 => SH_COSINE_A1,
+    _ => SH_COSINE_A2,
+  };
+  sh.iter()
+    .zip(basis)
+    .enumerate()
+    .fold(Vec3::zero(), |acc, (i, (c, basis))| {
+      acc + *c * (basis * band_factor(i))
+    })
+}
+
+/// the device version of [sh_l2_irradiance]
+pub fn sh_l2_irradiance_device(
+  sh: [Node<Vec3<f32>>; SH_L2_COEFFICIENT_COUNT],
+  normal: Node<Vec3<f32>>,
+) -> Node<Vec3<f32>> {
+  let x = normal.x();
+  let y = normal.y();
+  let z = normal.z();
+
+  let band0 = sh[0] * val(SH_Y0 * SH_COSINE_A0);
+
+  let band1 = sh[1] * y + sh[2] * z + sh[3] * x;
+  let band1 = band1 * val(SH_Y1 * SH_COSINE_A1);
+
+  let band2 = (sh[4] * (x * y) + sh[5] * (y * z) + sh[7] * (x * z)) * val(SH_Y2)
+    + sh[6] * ((val(3.) * z * z - val(1.)) * val(SH_Y20))
+    + sh[8] * ((x * x - y * y) * val(SH_Y22));
+  let band2 = band2 * val(SH_COSINE_A2);
+
+  band0 + band1 + band2
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn project_uniform_sphere(radiance: impl Fn(Vec3<f32>) -> Vec3<f32>) -> ShL2Rgb {
+    let mut sh = [Vec3::zero(); SH_L2_COEFFICIENT_COUNT];
+    let theta_steps = 256;
+    let phi_steps = 512;
+    let mut weight_sum = 0.;
+    for i in 0..theta_steps {
+      let theta = (i as f32 + 0.5) / theta_steps as f32 * std::f32::consts::PI;
+      for j in 0..phi_steps {
+        let phi = (j as f32 + 0.5) / phi_steps as f32 * std::f32::consts::TAU;
+        let dir = Vec3::new(
+          theta.sin() * phi.cos(),
+          theta.sin() * phi.sin(),
+          theta.cos(),
+        );
+        let weight = theta.sin();
+        weight_sum += weight;
+        sh_l2_accumulate_radiance(&mut sh, dir, radiance(dir) * weight);
+      }
+    }
+    let scale = 4. * std::f32::consts::PI / weight_sum;
+    sh.map(|c| c * scale)
+  }
+
+  #[test]
+  fn constant_radiance_irradiance() {
+    let sh = project_uniform_sphere(|_| Vec3::one());
+    for normal in [
+      Vec3::new(1., 0., 0.),
+      Vec3::new(0., -1., 0.),
+      Vec3::new(0., 0., 1.),
+    ] {
+      let e = sh_l2_irradiance(&sh, normal);
+      assert!((e.x - std::f32::consts::PI).abs() < 0.01, "{e:?}");
+    }
+  }
+
+  #[test]
+  fn hemisphere_radiance_irradiance() {
+    // only the upper hemisphere emits, a surface facing up receives π, facing down receives 0.
+    let sh = project_uniform_sphere(|d| if d.z > 0. { Vec3::one() } else { Vec3::zero() });
+    let up = sh_l2_irradiance(&sh, Vec3::new(0., 0., 1.));
+    let down = sh_l2_irradiance(&sh, Vec3::new(0., 0., -1.));
+    assert!((up.x - std::f32::consts::PI).abs() < 0.1, "{up:?}");
+    assert!(down.x.abs() < 0.1, "{down:?}");
+  }
+}
@@ -21,6 +21,10 @@ mod io;
 pub use io::*;
 mod radix_sort;
 pub use radix_sort::*;
+mod radix_sort_onesweep;
+pub use radix_sort_onesweep::*;
+mod run_length;
+pub use run_length::*;
 mod stream_compaction;
 pub use stream_compaction::*;
 mod shuffle_move;
@@ -452,6 +456,80 @@ where
       cx,
     )
   }
+
+  /// see [use_device_radix_sort_onesweep]
+  fn device_radix_sort<S>(
+    self,
+    workgroup_size: u32,
+    cx: &mut DeviceParallelComputeCtx,
+  ) -> DeviceMaterializeResult<T>
+  where
+    S: DeviceRadixSortKeyLogic<Data = T> + 'static,
+  {
+    use_device_radix_sort_onesweep::<T, S, u32>(Box::new(self), None, workgroup_size, cx).0
+  }
+
+  /// see [use_device_radix_sort_onesweep]
+  fn device_radix_sort_key_value<S, V>(
+    self,
+    values: impl ComputeComponentIO<V> + 'static,
+    workgroup_size: u32,
+    cx: &mut DeviceParallelComputeCtx,
+  ) -> (DeviceMaterializeResult<T>, DeviceMaterializeResult<V>)
+  where
+    S: DeviceRadixSortKeyLogic<Data = T> + 'static,
+    V: ShaderSizedValueNodeType + Std430 + Debug,
+  {
+    let (keys, values) = use_device_radix_sort_onesweep::<T, S, V>(
+      Box::new(self),
+      Some(Box::new(values)),
+      workgroup_size,
+      cx,
+    );
+    (keys, values.unwrap())
+  }
+
+  /// see [use_device_segmented_radix_sort_onesweep]
+  fn device_segmented_radix_sort_key_value<S, V>(
+    self,
+    values: impl ComputeComponentIO<V> + 'static,
+    segment_ids: impl ComputeComponentIO<u32> + 'static,
+    segment_count: u32,
+    workgroup_size: u32,
+    cx: &mut DeviceParallelComputeCtx,
+  ) -> (DeviceMaterializeResult<T>, DeviceMaterializeResult<V>)
+  where
+    S: DeviceRadixSortKeyLogic<Data = T> + 'static,
+    V: ShaderSizedValueNodeType + Std430 + Debug,
+  {
+    use_device_segmented_radix_sort_onesweep::<T, S, V>(
+      Box::new(self),
+      Box::new(values),
+      Box::new(segment_ids),
+      segment_count,
+      workgroup_size,
+      cx,
+    )
+  }
+
+  /// remove the adjacent duplicated values, the result has device size
+  fn use_unique(self, cx: &mut DeviceParallelComputeCtx) -> DeviceMaterializeResult<T>
+  where
+    T: DeviceValueEquality,
+  {
+    use_unique(Box::new(self), cx)
+  }
+
+  /// see [use_run_length_encode]
+  fn use_run_length_encode(
+    self,
+    cx: &mut DeviceParallelComputeCtx,
+  ) -> (DeviceMaterializeResult<T>, DeviceMaterializeResult<u32>)
+  where
+    T: DeviceValueEquality,
+  {
+    use_run_length_encode(Box::new(self), cx)
+  }
 }
 
 impl<X, T> DeviceParallelComputeIOExt<T> for X
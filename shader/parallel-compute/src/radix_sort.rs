@@ -3,7 +3,12 @@ use crate::*;
 pub trait DeviceRadixSortKeyLogic {
   const MAX_BITS: u32;
   type Data: ShaderSizedValueNodeType;
-  fn is_one(value: Node<Self::Data>, bit_position: Node<u32>) -> Node<bool>;
+  /// map the key to the unsigned bits, the unsigned order of the bits must be same as the key
+  /// order. only the lowest MAX_BITS are sorted.
+  fn radix_bits(value: Node<Self::Data>) -> Node<u32>;
+  fn is_one(value: Node<Self::Data>, bit_position: Node<u32>) -> Node<bool> {
+    (Self::radix_bits(value) & (val(1) << bit_position)).not_equals(val(0))
+  }
 }
 
 pub struct IntBitOrderRadixSortLogic<T>(PhantomData<T>);
@@ -11,8 +16,32 @@ pub struct IntBitOrderRadixSortLogic<T>(PhantomData<T>);
 impl DeviceRadixSortKeyLogic for IntBitOrderRadixSortLogic<u32> {
   const MAX_BITS: u32 = u32::BITS;
   type Data = u32;
-  fn is_one(value: Node<Self::Data>, bit_position: Node<u32>) -> Node<bool> {
-    (value & (val(1) << bit_position)).not_equals(val(0))
+  fn radix_bits(value: Node<Self::Data>) -> Node<u32> {
+    value
+  }
+}
+
+/// flip the sign bit so the negative values are ordered before the positive values
+impl DeviceRadixSortKeyLogic for IntBitOrderRadixSortLogic<i32> {
+  const MAX_BITS: u32 = i32::BITS;
+  type Data = i32;
+  fn radix_bits(value: Node<Self::Data>) -> Node<u32> {
+    value.bitcast::<u32>() ^ val(0x8000_0000)
+  }
+}
+
+/// the ascending order of the float keys, the negative zero is ordered before the positive zero
+/// and the NaN is ordered by its sign and payload bits.
+pub struct FloatBitOrderRadixSortLogic;
+
+impl DeviceRadixSortKeyLogic for FloatBitOrderRadixSortLogic {
+  const MAX_BITS: u32 = u32::BITS;
+  type Data = f32;
+  fn radix_bits(value: Node<Self::Data>) -> Node<u32> {
+    // flip all bits for negative values, flip the sign bit for positive values
+    let bits = value.bitcast::<u32>();
+    let is_negative = (bits & val(0x8000_0000)).not_equals(val(0));
+    bits ^ is_negative.select(val(0xFFFF_FFFF), val(0x8000_0000))
   }
 }
 
@@ -25,7 +54,7 @@ impl ShaderHashProvider for IterIndexHasher {
   shader_hash_type_id! {}
 }
 
-// todo, impl memory coalesced version for better performance
+/// the one bit per pass split sort, prefer [use_device_radix_sort_onesweep] for large input.
 pub fn use_device_radix_sort_naive<T, S>(
   input: impl ComputeComponentIO<T> + 'static,
   per_pass_first_stage_workgroup_size: u32,
@@ -135,8 +164,8 @@ impl DeviceRadixSortKeyLogic for U32RadixSort {
 
   type Data = u32;
 
-  fn is_one(value: Node<Self::Data>, bit_position: Node<u32>) -> Node<bool> {
-    (value & (val(1) << bit_position)).not_equals(val(0))
+  fn radix_bits(value: Node<Self::Data>) -> Node<u32> {
+    value
   }
 }
 
@@ -0,0 +1,593 @@
+use crate::*;
+
+const RADIX_BITS: u32 = 4;
+const RADIX: u32 = 1 << RADIX_BITS;
+/// the local digit counts are packed as 16 bits, two digit per word
+const PACKED_WORDS: u32 = RADIX / 2;
+
+const STATUS_NOT_READY: u32 = 0;
+const STATUS_AGGREGATE: u32 = 1;
+const STATUS_PREFIX: u32 = 2;
+const STATUS_VALUE_MASK: u32 = (1 << 30) - 1;
+
+/// Onesweep style LSD radix sort, see "Onesweep: A Faster Least Significant Digit Radix Sort for
+/// GPUs".
+///
+/// The digit histograms of all passes are computed in one upfront pass, then each digit pass
+/// scatters the elements in one dispatch: every workgroup gets its tile by an atomic counter,
+/// ranks the keys locally and resolves the tile's global digit offsets by chained decoupled
+/// look back. The sort is stable, so the values keep the input order for the equal keys.
+///
+/// The workgroup size is the tile size, it must be power of two and not larger than 1024. The
+/// look back relies on the forward progress of the earlier started workgroups, which holds on
+/// all known hardware but is not guaranteed by the spec.
+pub fn use_device_radix_sort_onesweep<K, S, V>(
+  keys: Box<dyn ComputeComponentIO<K>>,
+  values: Option<Box<dyn ComputeComponentIO<V>>>,
+  workgroup_size: u32,
+  cx: &mut DeviceParallelComputeCtx,
+) -> (
+  DeviceMaterializeResult<K>,
+  Option<DeviceMaterializeResult<V>>,
+)
+where
+  K: ShaderSizedValueNodeType + Std430 + Debug,
+  V: ShaderSizedValueNodeType + Std430 + Debug,
+  S: DeviceRadixSortKeyLogic<Data = K> + 'static,
+{
+  use_radix_sort_onesweep_bits::<K, S, V>(keys, values, S::MAX_BITS, workgroup_size, cx)
+}
+
+/// only the lowest `key_bits` of the radix bits are sorted
+pub(crate) fn use_radix_sort_onesweep_bits<K, S, V>(
+  keys: Box<dyn ComputeComponentIO<K>>,
+  values: Option<Box<dyn ComputeComponentIO<V>>>,
+  key_bits: u32,
+  workgroup_size: u32,
+  cx: &mut DeviceParallelComputeCtx,
+) -> (
+  DeviceMaterializeResult<K>,
+  Option<DeviceMaterializeResult<V>>,
+)
+where
+  K: ShaderSizedValueNodeType + Std430 + Debug,
+  V: ShaderSizedValueNodeType + Std430 + Debug,
+  S: DeviceRadixSortKeyLogic<Data = K> + 'static,
+{
+  assert!(workgroup_size.is_power_of_two() && (RADIX..=1024).contains(&workgroup_size));
+  assert!(key_bits <= S::MAX_BITS);
+  let pass_count = key_bits.div_ceil(RADIX_BITS);
+
+  cx.next_scope_index();
+  cx.scope(|cx| {
+    let keys = keys.use_materialize_storage_buffer(cx);
+    let values = values.map(|values| values.use_materialize_storage_buffer(cx));
+    let capacity = keys.buffer.item_count();
+    if let Some(values) = &values {
+      assert!(values.buffer.item_count() >= capacity);
+    }
+
+    if pass_count == 0 {
+      return (keys, values);
+    }
+
+    let tile_count = capacity.div_ceil(workgroup_size).max(1);
+    let buffers = OneSweepBuffers {
+      histogram: cx.use_rw_storage_buffer_array_impl::<DeviceAtomic<u32>>(
+        (pass_count * RADIX) as usize,
+        "radix sort histogram",
+        BufferUsages::empty(),
+      ),
+      tile_counter: cx.use_rw_storage_buffer_array_impl::<DeviceAtomic<u32>>(
+        pass_count as usize,
+        "radix sort tile counter",
+        BufferUsages::empty(),
+      ),
+      status: cx.use_rw_storage_buffer_array_impl::<DeviceAtomic<u32>>(
+        (tile_count * pass_count * RADIX) as usize,
+        "radix sort tile status",
+        BufferUsages::empty(),
+      ),
+    };
+    buffers.reset(cx);
+
+    let ping_pong_keys = [
+      cx.use_rw_storage_buffer_array::<K>(capacity as usize, "radix sort keys ping"),
+      cx.use_rw_storage_buffer_array::<K>(capacity as usize, "radix sort keys pong"),
+    ];
+    let ping_pong_values = values.as_ref().map(|_| {
+      [
+        cx.use_rw_storage_buffer_array::<V>(capacity as usize, "radix sort values ping"),
+        cx.use_rw_storage_buffer_array::<V>(capacity as usize, "radix sort values pong"),
+      ]
+    });
+
+    let histogram = OneSweepHistogram::<S> {
+      keys: keys.clone(),
+      histogram: buffers.histogram.clone(),
+      pass_count,
+      workgroup_size,
+      logic: PhantomData,
+    };
+    histogram.dispatch(tile_count, cx);
+
+    let mut current_keys = keys.buffer.clone();
+    let mut current_values = values.as_ref().map(|v| v.buffer.clone());
+    for pass in 0..pass_count {
+      let target = (pass % 2) as usize;
+      let scatter = OneSweepScatter::<S, V> {
+        src_keys: current_keys,
+        src_values: current_values,
+        dst_keys: ping_pong_keys[target].clone(),
+        dst_values: ping_pong_values.as_ref().map(|v| v[target].clone()),
+        size: keys.size.clone(),
+        buffers: buffers.clone(),
+        pass,
+        pass_count,
+        workgroup_size,
+        logic: PhantomData,
+      };
+      scatter.dispatch(tile_count, cx);
+
+      current_keys = scatter.dst_keys.into_readonly_view();
+      current_values = scatter.dst_values.map(|v| v.into_readonly_view());
+    }
+
+    let sorted_keys = DeviceMaterializeResult {
+      buffer: current_keys,
+      size: keys.size.clone(),
+    };
+    let sorted_values = current_values.map(|buffer| DeviceMaterializeResult {
+      buffer,
+      size: keys.size.clone(),
+    });
+    (sorted_keys, sorted_values)
+  })
+}
+
+#[derive(Clone)]
+struct OneSweepBuffers {
+  /// the digit histogram of all passes, indexed by pass * RADIX + digit
+  histogram: StorageBufferDataView<[DeviceAtomic<u32>]>,
+  /// the tile allocation counter of each pass
+  tile_counter: StorageBufferDataView<[DeviceAtomic<u32>]>,
+  /// the packed look back status, indexed by (tile * pass_count + pass) * RADIX + digit
+  status: StorageBufferDataView<[DeviceAtomic<u32>]>,
+}
+
+impl OneSweepBuffers {
+  fn reset(&self, cx: &mut DeviceParallelComputeCtx) {
+    cx.flush_pass();
+    for buffer in [&self.histogram, &self.tile_counter, &self.status] {
+      let view = &buffer.gpu;
+      cx.encoder.clear_buffer(
+        view.resource.gpu(),
+        view.range.offset,
+        view.range.size.map(|v| v.get()),
+      );
+    }
+  }
+}
+
+fn bind_keys_len<K: ShaderSizedValueNodeType + Std430>(
+  builder: &mut ShaderComputePipelineBuilder,
+  keys: &StorageBufferReadonlyDataView<[K]>,
+  size: Option<&StorageBufferReadonlyDataView<Vec4<u32>>>,
+) -> (DynLengthArrayReadonlyView<K>, Node<u32>) {
+  let keys = builder.bind_by(keys);
+  let len = if let Some(size) = size {
+    builder.bind_by(size).load().x().min(keys.array_length())
+  } else {
+    keys.array_length()
+  };
+  (keys, len)
+}
+
+struct OneSweepHistogram<S: DeviceRadixSortKeyLogic>
+where
+  S::Data: Std430,
+{
+  keys: DeviceMaterializeResult<S::Data>,
+  histogram: StorageBufferDataView<[DeviceAtomic<u32>]>,
+  pass_count: u32,
+  workgroup_size: u32,
+  logic: PhantomData<S>,
+}
+
+impl<S: DeviceRadixSortKeyLogic + 'static> ShaderHashProvider for OneSweepHistogram<S>
+where
+  S::Data: Std430,
+{
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    hasher.hash(self.pass_count);
+    hasher.hash(self.workgroup_size);
+    hasher.hash(self.keys.size.is_some());
+  }
+  shader_hash_type_id! {}
+}
+
+impl<S> OneSweepHistogram<S>
+where
+  S: DeviceRadixSortKeyLogic + 'static,
+  S::Data: Std430,
+{
+  fn dispatch(&self, tile_count: u32, cx: &mut DeviceParallelComputeCtx) {
+    let total_bins = self.pass_count * RADIX;
+    let pipeline = cx.get_or_create_compute_pipeline(self, |builder| {
+      builder.config_work_group_size(self.workgroup_size);
+      let local_id = builder.local_invocation_id().x();
+      let global_id = builder.global_invocation_id().x();
+      let shared =
+        builder.define_workgroup_shared_var_host_size_array::<DeviceAtomic<u32>>(total_bins);
+
+      let (keys, len) = bind_keys_len(builder, &self.keys.buffer, self.keys.size.as_ref());
+      let histogram = builder.bind_by(&self.histogram);
+
+      // the workgroup memory is not zero initialized
+      for base in (0..total_bins).step_by(self.workgroup_size as usize) {
+        let bin = local_id + val(base);
+        if_by(bin.less_than(total_bins), || {
+          shared.index(bin).atomic_store(val(0));
+        });
+      }
+      workgroup_barrier();
+
+      if_by(global_id.less_than(len), || {
+        let bits = S::radix_bits(keys.index(global_id).load());
+        for pass in 0..self.pass_count {
+          let digit = (bits >> val(pass * RADIX_BITS)) & val(RADIX - 1);
+          shared.index(digit + val(pass * RADIX)).atomic_add(val(1));
+        }
+      });
+      workgroup_barrier();
+
+      for base in (0..total_bins).step_by(self.workgroup_size as usize) {
+        let bin = local_id + val(base);
+        if_by(bin.less_than(total_bins), || {
+          let count = shared.index(bin).atomic_load();
+          if_by(count.not_equals(0), || {
+            histogram.index(bin).atomic_add(count);
+          });
+        });
+      }
+    });
+
+    cx.record_pass(|pass, device| {
+      BindingBuilder::default()
+        .with_bind(&self.keys.buffer)
+        .with_fn(|bb| {
+          if let Some(size) = &self.keys.size {
+            bb.bind(size);
+          }
+        })
+        .with_bind(&self.histogram)
+        .setup_compute_pass(pass, device, &pipeline);
+      pass.dispatch_workgroups(tile_count, 1, 1);
+    });
+  }
+}
+
+struct OneSweepScatter<S: DeviceRadixSortKeyLogic, V: Std430>
+where
+  S::Data: Std430,
+{
+  src_keys: StorageBufferReadonlyDataView<[S::Data]>,
+  src_values: Option<StorageBufferReadonlyDataView<[V]>>,
+  dst_keys: StorageBufferDataView<[S::Data]>,
+  dst_values: Option<StorageBufferDataView<[V]>>,
+  size: Option<StorageBufferReadonlyDataView<Vec4<u32>>>,
+  buffers: OneSweepBuffers,
+  pass: u32,
+  pass_count: u32,
+  workgroup_size: u32,
+  logic: PhantomData<S>,
+}
+
+impl<S: DeviceRadixSortKeyLogic + 'static, V: Std430> ShaderHashProvider for OneSweepScatter<S, V>
+where
+  S::Data: Std430,
+{
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    hasher.hash(self.pass);
+    hasher.hash(self.pass_count);
+    hasher.hash(self.workgroup_size);
+    hasher.hash(self.size.is_some());
+    hasher.hash(self.src_values.is_some());
+  }
+  shader_hash_type_id! {}
+}
+
+impl<S, V> OneSweepScatter<S, V>
+where
+  S: DeviceRadixSortKeyLogic + 'static,
+  S::Data: Std430,
+  V: ShaderSizedValueNodeType + Std430,
+{
+  fn dispatch(&self, tile_count: u32, cx: &mut DeviceParallelComputeCtx) {
+    let workgroup_size = self.workgroup_size;
+    let pipeline = cx.get_or_create_compute_pipeline(self, |builder| {
+      builder.config_work_group_size(workgroup_size);
+      let local_id = builder.local_invocation_id().x();
+      let shared_tile = builder.define_workgroup_shared_var::<u32>();
+      let shared_scan =
+        builder.define_workgroup_shared_var_host_size_array::<u32>(workgroup_size * PACKED_WORDS);
+      let shared_offset = builder.define_workgroup_shared_var_host_size_array::<u32>(RADIX);
+
+      let (src_keys, len) = bind_keys_len(builder, &self.src_keys, self.size.as_ref());
+      let src_values = self.src_values.as_ref().map(|v| builder.bind_by(v));
+      let dst_keys = builder.bind_by(&self.dst_keys);
+      let dst_values = self.dst_values.as_ref().map(|v| builder.bind_by(v));
+      let histogram = builder.bind_by(&self.buffers.histogram);
+      let tile_counter = builder.bind_by(&self.buffers.tile_counter);
+      let status = builder.bind_by(&self.buffers.status);
+
+      // allocate the tile in the workgroup start order, so the look back only waits for the
+      // workgroups that have already started.
+      if_by(local_id.equals(0), || {
+        let tile = tile_counter.index(val(self.pass)).atomic_add(val(1));
+        shared_tile.store(tile);
+      });
+      let tile = workgroup_uniform_load(shared_tile);
+
+      let index = tile * val(workgroup_size) + local_id;
+      let valid = index.less_than(len);
+      let key = valid.select_branched(|| src_keys.index(index).load(), zeroed_val);
+      let digit = (S::radix_bits(key) >> val(self.pass * RADIX_BITS)) & val(RADIX - 1);
+      let word = digit >> val(1);
+      let word_shift = (digit & val(1)) * val(16);
+
+      // the stable local rank is computed by the packed inclusive scan of the digit flags
+      let flag = valid.select(val(1) << word_shift, val(0));
+      let scanned: Vec<_> = (0..PACKED_WORDS)
+        .map(|w| {
+          let v = word.equals(w).select(flag, val(0)).make_local_var();
+          shared_scan
+            .index(local_id * val(PACKED_WORDS) + val(w))
+            .store(v.load());
+          v
+        })
+        .collect();
+
+      for i in 0..workgroup_size.ilog2() {
+        let stride = 1 << i;
+        workgroup_barrier();
+        if_by(local_id.greater_equal_than(stride), || {
+          for (w, v) in scanned.iter().enumerate() {
+            let other = shared_scan
+              .index((local_id - val(stride)) * val(PACKED_WORDS) + val(w as u32))
+              .load();
+            v.store(v.load() + other);
+          }
+        });
+        workgroup_barrier();
+        for (w, v) in scanned.iter().enumerate() {
+          shared_scan
+            .index(local_id * val(PACKED_WORDS) + val(w as u32))
+            .store(v.load());
+        }
+      }
+      workgroup_barrier();
+
+      let inclusive = shared_scan
+        .index(local_id * val(PACKED_WORDS) + word)
+        .load();
+      let local_rank = ((inclusive >> word_shift) & val(0xFFFF)) - val(1);
+
+      if_by(local_id.less_than(RADIX), || {
+        let digit = local_id;
+        let tile_total = shared_scan
+          .index(val((workgroup_size - 1) * PACKED_WORDS) + (digit >> val(1)))
+          .load();
+        let count = (tile_total >> ((digit & val(1)) * val(16))) & val(0xFFFF);
+
+        let pass = val(self.pass);
+        let pass_count = val(self.pass_count);
+        let status_at =
+          |tile: Node<u32>| status.index((tile * pass_count + pass) * val(RADIX) + digit);
+
+        let prefix = val(0_u32).make_local_var();
+        if_by(tile.equals(0), || {
+          status_at(tile).atomic_store(val(STATUS_PREFIX << 30) | count);
+        })
+        .else_by(|| {
+          status_at(tile).atomic_store(val(STATUS_AGGREGATE << 30) | count);
+
+          let look_back = (tile - val(1)).make_local_var();
+          loop_by(|cx| {
+            let s = status_at(look_back.load()).atomic_load();
+            let state = s >> val(30);
+            if_by(state.equals(STATUS_NOT_READY), || {
+              cx.do_continue();
+            });
+            prefix.store(prefix.load() + (s & val(STATUS_VALUE_MASK)));
+            if_by(state.equals(STATUS_PREFIX), || {
+              cx.do_break();
+            });
+            look_back.store(look_back.load() - val(1));
+          });
+
+          status_at(tile).atomic_store(val(STATUS_PREFIX << 30) | (prefix.load() + count));
+        });
+
+        let digit_offset = val(0_u32).make_local_var();
+        for d in 0..RADIX {
+          if_by(digit.greater_than(d), || {
+            let count = histogram.index(val(self.pass * RADIX + d)).atomic_load();
+            digit_offset.store(digit_offset.load() + count);
+          });
+        }
+
+        shared_offset
+          .index(digit)
+          .store(digit_offset.load() + prefix.load());
+      });
+      workgroup_barrier();
+
+      if_by(valid, || {
+        let target = shared_offset.index(digit).load() + local_rank;
+        dst_keys.index(target).store(key);
+        if let (Some(src_values), Some(dst_values)) = (&src_values, &dst_values) {
+          dst_values
+            .index(target)
+            .store(src_values.index(index).load());
+        }
+      });
+    });
+
+    cx.record_pass(|pass, device| {
+      BindingBuilder::default()
+        .with_bind(&self.src_keys)
+        .with_fn(|bb| {
+          if let Some(size) = &self.size {
+            bb.bind(size);
+          }
+          if let Some(src_values) = &self.src_values {
+            bb.bind(src_values);
+          }
+        })
+        .with_bind(&self.dst_keys)
+        .with_fn(|bb| {
+          if let Some(dst_values) = &self.dst_values {
+            bb.bind(dst_values);
+          }
+        })
+        .with_bind(&self.buffers.histogram)
+        .with_bind(&self.buffers.tile_counter)
+        .with_bind(&self.buffers.status)
+        .setup_compute_pass(pass, device, &pipeline);
+      pass.dispatch_workgroups(tile_count, 1, 1);
+    });
+  }
+}
+
+/// Stable sort the keys inside each segment, the segment ids should be smaller than
+/// segment_count. The output is grouped by the segment id in ascending order.
+///
+/// The keys are sorted first with the index as payload, then the segment ids are gathered by
+/// the sorted index and sorted by their used bits. Because the sort is stable, the key order
+/// is kept inside each segment.
+pub fn use_device_segmented_radix_sort_onesweep<K, S, V>(
+  keys: Box<dyn ComputeComponentIO<K>>,
+  values: Box<dyn ComputeComponentIO<V>>,
+  segment_ids: Box<dyn ComputeComponentIO<u32>>,
+  segment_count: u32,
+  workgroup_size: u32,
+  cx: &mut DeviceParallelComputeCtx,
+) -> (DeviceMaterializeResult<K>, DeviceMaterializeResult<V>)
+where
+  K: ShaderSizedValueNodeType + Std430 + Debug,
+  V: ShaderSizedValueNodeType + Std430 + Debug,
+  S: DeviceRadixSortKeyLogic<Data = K> + 'static,
+{
+  cx.next_scope_index();
+  cx.scope(|cx| {
+    let keys = keys.use_materialize_storage_buffer(cx);
+    let values = values.use_materialize_storage_buffer(cx);
+    let segment_ids = segment_ids.use_materialize_storage_buffer(cx);
+
+    let index = DeviceInvocationIndexCompute {
+      upstream: keys.clone_boxed(),
+    };
+    let (_, by_key) = use_radix_sort_onesweep_bits::<K, S, u32>(
+      Box::new(keys.clone()),
+      Some(Box::new(index)),
+      S::MAX_BITS,
+      workgroup_size,
+      cx,
+    );
+    let by_key = by_key.unwrap();
+
+    let segment_by_key = ShuffleAccess {
+      source: segment_ids.buffer,
+      shuffle_idx: Box::new(by_key.clone()),
+    };
+    let segment_bits = u32::BITS - segment_count.saturating_sub(1).leading_zeros();
+    let (_, permutation) = use_radix_sort_onesweep_bits::<u32, IntBitOrderRadixSortLogic<u32>, u32>(
+      Box::new(segment_by_key),
+      Some(Box::new(by_key)),
+      segment_bits,
+      workgroup_size,
+      cx,
+    );
+    let permutation = permutation.unwrap();
+
+    let keys = ShuffleAccess {
+      source: keys.buffer,
+      shuffle_idx: Box::new(permutation.clone()),
+    }
+    .use_materialize_storage_buffer(cx);
+    let values = ShuffleAccess {
+      source: values.buffer,
+      shuffle_idx: Box::new(permutation),
+    }
+    .use_materialize_storage_buffer(cx);
+    (keys, values)
+  })
+}
+
+#[pollster::test]
+async fn test_key_value() {
+  gpu_cx!(cx);
+  let count = 1000;
+  let input: Vec<u32> = (0..count).map(|i| (i * 7919 + 13) % 97).collect();
+  let values: Vec<u32> = (0..count).collect();
+
+  let mut expect: Vec<(u32, u32)> = input.iter().copied().zip(values.iter().copied()).collect();
+  expect.sort_by_key(|(k, _)| *k);
+  let expect_keys: Vec<_> = expect.iter().map(|(k, _)| *k).collect();
+  let expect_values: Vec<_> = expect.iter().map(|(_, v)| *v).collect();
+
+  let (keys, values) = slice_into_compute(&input, cx)
+    .device_radix_sort_key_value::<U32RadixSort, _>(slice_into_compute(&values, cx), 64, cx);
+
+  keys.run_test(cx, &expect_keys).await;
+  values.run_test(cx, &expect_values).await;
+}
+
+#[pollster::test]
+async fn test_signed_and_float() {
+  gpu_cx!(cx);
+  let input: Vec<i32> = (0..300).map(|i| (i * 37 % 101) - 50).collect();
+  let mut expect = input.clone();
+  expect.sort();
+
+  slice_into_compute(&input, cx)
+    .device_radix_sort::<IntBitOrderRadixSortLogic<i32>>(256, cx)
+    .run_test(cx, &expect)
+    .await;
+
+  let input: Vec<f32> = (0..300)
+    .map(|i| ((i * 37 % 101) as f32 - 50.) * 0.25)
+    .chain([f32::INFINITY, f32::NEG_INFINITY, -0.5, 0.])
+    .collect();
+  let mut expect = input.clone();
+  expect.sort_by(|a, b| a.total_cmp(b));
+
+  slice_into_compute(&input, cx)
+    .device_radix_sort::<FloatBitOrderRadixSortLogic>(256, cx)
+    .run_test(cx, &expect)
+    .await;
+}
+
+#[pollster::test]
+async fn test_segmented() {
+  gpu_cx!(cx);
+  let keys = [5, 3, 9, 1, 3, 8, 2, 7, 2, 0].to_vec();
+  let values = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9].to_vec();
+  let segments = [2, 0, 2, 1, 0, 1, 2, 0, 1, 2].to_vec();
+
+  let expect_keys = [3, 3, 7, 1, 2, 8, 0, 2, 5, 9].to_vec();
+  let expect_values = [1, 4, 7, 3, 8, 5, 9, 6, 0, 2].to_vec();
+
+  let (keys, values) = slice_into_compute(&keys, cx)
+    .device_segmented_radix_sort_key_value::<U32RadixSort, _>(
+      slice_into_compute(&values, cx),
+      slice_into_compute(&segments, cx),
+      3,
+      64,
+      cx,
+    );
+
+  keys.run_test(cx, &expect_keys).await;
+  values.run_test(cx, &expect_values).await;
+}
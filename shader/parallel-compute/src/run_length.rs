@@ -0,0 +1,277 @@
+use crate::*;
+
+/// The value equality used to find the runs in unique and run length encode.
+pub trait DeviceValueEquality: ShaderSizedValueNodeType {
+  fn device_equals(a: Node<Self>, b: Node<Self>) -> Node<bool>;
+}
+
+macro_rules! impl_scalar_equality {
+  ($ty: ty) => {
+    impl DeviceValueEquality for $ty {
+      fn device_equals(a: Node<Self>, b: Node<Self>) -> Node<bool> {
+        a.equals(b)
+      }
+    }
+  };
+}
+impl_scalar_equality!(u32);
+impl_scalar_equality!(i32);
+impl_scalar_equality!(f32);
+impl_scalar_equality!(bool);
+
+macro_rules! impl_vector_equality {
+  ($ty: ty) => {
+    impl DeviceValueEquality for $ty {
+      fn device_equals(a: Node<Self>, b: Node<Self>) -> Node<bool> {
+        a.equals(b).all()
+      }
+    }
+  };
+}
+impl_vector_equality!(Vec2<u32>);
+impl_vector_equality!(Vec3<u32>);
+impl_vector_equality!(Vec4<u32>);
+impl_vector_equality!(Vec2<i32>);
+impl_vector_equality!(Vec3<i32>);
+impl_vector_equality!(Vec4<i32>);
+impl_vector_equality!(Vec2<f32>);
+impl_vector_equality!(Vec3<f32>);
+impl_vector_equality!(Vec4<f32>);
+
+/// The invocation index of the upstream, the upstream value is not accessed.
+#[derive_where(Clone)]
+pub struct DeviceInvocationIndexCompute<T> {
+  pub upstream: Box<dyn ComputeComponent<Node<T>>>,
+}
+
+impl<T: 'static> ShaderHashProvider for DeviceInvocationIndexCompute<T> {
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    self.upstream.hash_pipeline_with_type_info(hasher)
+  }
+  shader_hash_type_id! {}
+}
+
+impl<T: 'static> ComputeComponent<Node<u32>> for DeviceInvocationIndexCompute<T> {
+  fn result_size(&self) -> u32 {
+    self.upstream.result_size()
+  }
+
+  fn build_shader(
+    &self,
+    builder: &mut ShaderComputePipelineBuilder,
+  ) -> Box<dyn DeviceInvocation<Node<u32>>> {
+    self
+      .upstream
+      .build_shader(builder)
+      .adhoc_invoke_with_self_size(|upstream, id| {
+        let valid = id.x().less_than(upstream.invocation_size().x());
+        (id.x(), valid)
+      })
+      .into_boxed()
+  }
+
+  fn bind_input(&self, builder: &mut BindingBuilder) {
+    self.upstream.bind_input(builder);
+  }
+
+  fn requested_workgroup_size(&self) -> Option<u32> {
+    self.upstream.requested_workgroup_size()
+  }
+
+  fn work_size(&self) -> Option<u32> {
+    self.upstream.work_size()
+  }
+
+  fn clone_boxed(&self) -> Box<dyn ComputeComponent<Node<u32>>> {
+    Box::new(self.clone())
+  }
+}
+
+impl<T: 'static> ComputeComponentIO<u32> for DeviceInvocationIndexCompute<T> {}
+
+/// Mark the first element of each run of the equal adjacent values.
+#[derive_where(Clone)]
+pub struct DeviceRunHeadCompute<T> {
+  pub upstream: Box<dyn ComputeComponent<Node<T>>>,
+}
+
+impl<T: 'static> ShaderHashProvider for DeviceRunHeadCompute<T> {
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    self.upstream.hash_pipeline_with_type_info(hasher)
+  }
+  shader_hash_type_id! {}
+}
+
+impl<T: DeviceValueEquality> ComputeComponent<Node<bool>> for DeviceRunHeadCompute<T> {
+  fn result_size(&self) -> u32 {
+    self.upstream.result_size()
+  }
+
+  fn build_shader(
+    &self,
+    builder: &mut ShaderComputePipelineBuilder,
+  ) -> Box<dyn DeviceInvocation<Node<bool>>> {
+    self
+      .upstream
+      .build_shader(builder)
+      .adhoc_invoke_with_self_size(|upstream, id| {
+        let (current, valid) = upstream.invocation_logic(id);
+        let is_first = id.x().equals(0);
+        let previous_id: Node<Vec3<u32>> = (id.x().max(1) - val(1), id.y(), id.z()).into();
+        let (previous, _) = upstream.invocation_logic(previous_id);
+        let head = is_first.or(T::device_equals(current, previous).not());
+        (head, valid)
+      })
+      .into_boxed()
+  }
+
+  fn bind_input(&self, builder: &mut BindingBuilder) {
+    self.upstream.bind_input(builder);
+  }
+
+  fn requested_workgroup_size(&self) -> Option<u32> {
+    self.upstream.requested_workgroup_size()
+  }
+
+  fn work_size(&self) -> Option<u32> {
+    self.upstream.work_size()
+  }
+
+  fn clone_boxed(&self) -> Box<dyn ComputeComponent<Node<bool>>> {
+    Box::new(self.clone())
+  }
+}
+
+impl<T: DeviceValueEquality> ComputeComponentIO<bool> for DeviceRunHeadCompute<T> {}
+
+/// Compute the run length by the compacted run start index of each run.
+#[derive_where(Clone)]
+struct RunLengthFromStarts<T> {
+  starts: DeviceMaterializeResult<u32>,
+  /// only used to get the total length
+  source: Box<dyn ComputeComponent<Node<T>>>,
+}
+
+impl<T: 'static> ShaderHashProvider for RunLengthFromStarts<T> {
+  fn hash_pipeline(&self, hasher: &mut PipelineHasher) {
+    self.starts.hash_pipeline_with_type_info(hasher);
+    self.source.hash_pipeline_with_type_info(hasher);
+  }
+  shader_hash_type_id! {}
+}
+
+impl<T: 'static> ComputeComponent<Node<u32>> for RunLengthFromStarts<T> {
+  fn result_size(&self) -> u32 {
+    self.starts.result_size()
+  }
+
+  fn build_shader(
+    &self,
+    builder: &mut ShaderComputePipelineBuilder,
+  ) -> Box<dyn DeviceInvocation<Node<u32>>> {
+    let source = self.source.build_shader(builder);
+    self
+      .starts
+      .build_shader(builder)
+      .adhoc_invoke_with_self_size(move |starts, id| {
+        let (start, valid) = starts.invocation_logic(id);
+        let run_count = starts.invocation_size().x();
+        let next_id = id.x() + val(1);
+        let has_next = next_id.less_than(run_count);
+        let next_id: Node<Vec3<u32>> =
+          (next_id.min(run_count.max(1) - val(1)), val(0), val(0)).into();
+        let next_start = has_next.select(
+          starts.invocation_logic(next_id).0,
+          source.invocation_size().x(),
+        );
+        (next_start - start, valid)
+      })
+      .into_boxed()
+  }
+
+  fn bind_input(&self, builder: &mut BindingBuilder) {
+    self.source.bind_input(builder);
+    self.starts.bind_input(builder);
+  }
+
+  fn requested_workgroup_size(&self) -> Option<u32> {
+    self.starts.requested_workgroup_size()
+  }
+
+  fn work_size(&self) -> Option<u32> {
+    self.starts.work_size()
+  }
+
+  fn clone_boxed(&self) -> Box<dyn ComputeComponent<Node<u32>>> {
+    Box::new(self.clone())
+  }
+}
+
+impl<T: 'static> ComputeComponentIO<u32> for RunLengthFromStarts<T> {}
+
+pub fn use_unique<T>(
+  source: Box<dyn ComputeComponentIO<T>>,
+  cx: &mut DeviceParallelComputeCtx,
+) -> DeviceMaterializeResult<T>
+where
+  T: DeviceValueEquality + Std430 + Debug,
+{
+  let head = DeviceRunHeadCompute {
+    upstream: source.clone_boxed(),
+  };
+  use_stream_compaction(source, Box::new(head), cx)
+}
+
+/// return the value and the length of each run of the equal adjacent values.
+pub fn use_run_length_encode<T>(
+  source: Box<dyn ComputeComponentIO<T>>,
+  cx: &mut DeviceParallelComputeCtx,
+) -> (DeviceMaterializeResult<T>, DeviceMaterializeResult<u32>)
+where
+  T: DeviceValueEquality + Std430 + Debug,
+{
+  let head = DeviceRunHeadCompute {
+    upstream: source.clone_boxed(),
+  };
+  let index = DeviceInvocationIndexCompute {
+    upstream: source.clone_boxed(),
+  };
+
+  let values = use_stream_compaction(source.clone(), Box::new(head.clone()), cx);
+  let starts = use_stream_compaction(Box::new(index), Box::new(head), cx);
+
+  let lengths = RunLengthFromStarts {
+    starts,
+    source: source.clone_boxed(),
+  }
+  .use_materialize_storage_buffer(cx);
+
+  (values, lengths)
+}
+
+#[pollster::test]
+async fn test_unique() {
+  gpu_cx!(cx);
+  let input = vec![1, 1, 2, 3, 3, 3, 1, 5];
+  let expect = vec![1, 2, 3, 1, 5, 0, 0, 0];
+
+  slice_into_compute(&input, cx)
+    .use_unique(cx)
+    .run_test_with_size_test(cx, &expect, Some(Vec3::new(5, 0, 0)))
+    .await;
+}
+
+#[pollster::test]
+async fn test_run_length_encode() {
+  gpu_cx!(cx);
+  let input = vec![7, 7, 7, 2, 4, 4, 7];
+
+  let (values, lengths) = slice_into_compute(&input, cx).use_run_length_encode(cx);
+
+  values
+    .run_test_with_size_test(cx, &[7, 2, 4, 7, 0, 0, 0], Some(Vec3::new(4, 0, 0)))
+    .await;
+  lengths
+    .run_test_with_size_test(cx, &[3, 1, 2, 1, 0, 0, 0], Some(Vec3::new(4, 0, 0)))
+    .await;
+}
@@ -103,8 +103,8 @@ where
   }
 
   fn bind_input(&self, builder: &mut BindingBuilder) {
-    self.shuffle_idx.bind_input(builder);
     builder.bind(&self.source);
+    self.shuffle_idx.bind_input(builder);
   }
 
   fn work_size(&self) -> Option<u32> {
@@ -116,6 +116,8 @@ where
   }
 }
 
+impl<T> ComputeComponentIO<T> for ShuffleAccess<T> where T: Std430 + ShaderSizedValueNodeType {}
+
 #[pollster::test]
 async fn test() {
   gpu_cx!(cx);
@@ -1,23 +1,31 @@
 mod message;
+mod readable;
 mod replay;
+mod schema;
+mod snapshot;
 mod writer;
 
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use database::*;
 use fast_hash_collection::*;
 pub use message::*;
+use parking_lot::Mutex;
 pub use replay::*;
+pub use schema::*;
+pub use snapshot::*;
 pub use writer::*;
 
 /// Build a name table from all currently registered entity types and components
 /// in the database. Entity names are assigned IDs first, then component names.
+/// The data type schemas of the components are also recorded.
 pub fn build_name_table(database: &Database) -> NameTable {
   let tables = database.tables.read();
 
   let mut names = Vec::new();
   let mut entity_name_to_id = FastHashMap::default();
   let mut component_name_to_id = FastHashMap::default();
+  let mut component_schemas = FastHashMap::default();
 
   for (e_id, table) in tables.iter() {
     let e_name_id = names.len() as u32;
@@ -28,6 +36,7 @@ pub fn build_name_table(database: &Database) -> NameTable {
       let c_name_id = names.len() as u32;
       names.push(component.name.clone());
       component_name_to_id.insert(component.component_type_id, c_name_id);
+      component_schemas.insert(c_name_id, ComponentTypeSchema::from_component(component));
     });
   }
 
@@ -35,6 +44,7 @@ pub fn build_name_table(database: &Database) -> NameTable {
     names,
     entity_name_to_id,
     component_name_to_id,
+    component_schemas,
   }
 }
 
@@ -44,6 +54,14 @@ pub fn build_name_table(database: &Database) -> NameTable {
 /// protocol header via `writer.write_header()`, then subscribes to all database
 /// change events. Each mutation is forwarded as a `TracingMessage` to the writer.
 ///
+/// If the database is not empty, the current state is emitted as the initial records:
+/// all living entities are created first, then all their component values are set. The
+/// database should not be mutated by other threads while the tracing is starting.
+///
+/// The entity types and components declared after the tracing started are supported,
+/// a `NameDefined` record is emitted to extend the name table before any records use it, and
+/// a `ComponentSchemaDefined` record follows for the component.
+///
 /// The writer determines how the header and records are transported
 /// (`FileTraceWriter` writes to a file, custom implementations could
 /// send over the network).
@@ -56,76 +74,155 @@ pub fn start_tracing<T: TraceIO + TraceReplayTarget + Send + Sync + 'static>(
 
   let tables = database.tables.read();
 
+  emit_initial_state(&tables, &name_table, &writer);
+
+  let name_table = Arc::new(Mutex::new(name_table));
+
   let writer_ = writer.clone();
-  database.entity_meta_watcher.on(|_| {
-    unreachable!("tracing not support post entity define yet");
+  let name_table_ = name_table.clone();
+  database.entity_meta_watcher.on(move |table| {
+    let e_name_id = name_table_
+      .lock()
+      .insert_entity(table.entity_id(), table.name().to_string());
+    let msg = DatabaseTracingMessage::NameDefined(e_name_id, table.name().to_string());
+    writer_.write_message(TracingMessage::DatabaseMutation(msg));
+
+    trace_table(table, e_name_id, &name_table_, &writer_);
+    false
   });
 
   for (e_id, table) in tables.iter() {
-    assert_eq!(table.living_entity_count(), 0);
-
-    let e_id = *e_id;
-    let e_name_id = name_table.entity_name_to_id[&e_id];
-    let writer__ = writer_.clone();
-
-    table.entity_watchers().on(move |change| {
-      match change {
-        ScopedMessage::Start => {}
-        ScopedMessage::End => {}
-        ScopedMessage::ReserveSpace(_size) => {}
-        ScopedMessage::Message(change) => match change {
-          EntityChange::NewEntityStartCreate(handle) => {
-            let msg = DatabaseTracingMessage::EntityCreated(e_name_id, *handle);
-            writer__.write_message(TracingMessage::DatabaseMutation(msg));
-          }
-          EntityChange::NewEntityCreated(_) => {}
-          EntityChange::DeleteEntity(handle) => {
-            let msg = DatabaseTracingMessage::EntityDeleted(e_name_id, *handle);
-            writer__.write_message(TracingMessage::DatabaseMutation(msg));
-          }
-        },
-      }
-      false
-    });
+    let e_name_id = name_table.lock().entity_name_to_id[e_id];
+    trace_table(table, e_name_id, &name_table, &writer);
+  }
 
-    let writer_ = writer.clone();
+  writer
+}
 
-    table.component_define_watchers().on(|_| {
-      unreachable!("tracing not support post component define yet");
-    });
+fn emit_initial_state<T: Send + Sync + 'static>(
+  tables: &FastHashMap<EntityId, ArcTable>,
+  name_table: &NameTable,
+  writer: &impl TraceWriter<TracingMessage<T>>,
+) {
+  // create all entities first, so that the replay is able to remap the foreign keys
+  for (e_id, table) in tables.iter() {
+    let e_name_id = name_table.entity_name_to_id[e_id];
+    for handle in table.iter_entity_idx() {
+      let msg = DatabaseTracingMessage::EntityCreated(e_name_id, handle);
+      writer.write_message(TracingMessage::DatabaseMutation(msg));
+    }
+  }
 
+  for table in tables.values() {
+    if table.living_entity_count() == 0 {
+      continue;
+    }
     table.visit_components(|component| {
-      let writer__ = writer_.clone();
       let c_name_id = name_table.component_name_to_id[&component.component_type_id];
       let c_is_fk = component.as_foreign_key.is_some();
-      component.data_watchers.on(move |change| unsafe {
-        match change {
-          ScopedMessage::Start => {}
-          ScopedMessage::End => {}
-          ScopedMessage::ReserveSpace(_size) => {}
-          ScopedMessage::Message(change) => match change.change {
-            ValueChange::Delta((data_ptr, dyn_ptr), _) => {
-              let field_data = if c_is_fk {
-                let fk = (data_ptr as *const Option<RawEntityHandle>).read();
-                EntityFieldData::ForeignKey(fk)
-              } else {
-                let new = &*dyn_ptr as &dyn DynDataBaseDataType;
-                // todo move the serialize into writer thread
-                let buffer = new.serialize_into_buffer();
-                EntityFieldData::Pod(buffer.to_vec())
-              };
-              let msg = DatabaseTracingMessage::EntityFieldSet(c_name_id, change.idx, field_data);
-              writer__.write_message(TracingMessage::DatabaseMutation(msg));
-            }
-            ValueChange::Remove(_) => {}
-          },
-        };
-        false
-      });
+      let reader = component.read_untyped();
+      for handle in table.iter_entity_idx() {
+        let (data_ptr, data) = reader
+          .get_without_generation_check_dyn_data_type(handle.alloc_index())
+          .unwrap();
+        let field_data = create_field_data(c_is_fk, data_ptr, data);
+        let msg = DatabaseTracingMessage::EntityFieldSet(c_name_id, handle, field_data);
+        writer.write_message(TracingMessage::DatabaseMutation(msg));
+      }
     });
   }
+}
 
-  writer
+fn create_field_data(
+  c_is_fk: bool,
+  data_ptr: *const (),
+  data: &dyn DynDataBaseDataType,
+) -> EntityFieldData {
+  if c_is_fk {
+    let fk = unsafe { (data_ptr as *const Option<RawEntityHandle>).read() };
+    EntityFieldData::ForeignKey(fk)
+  } else {
+    // todo move the serialize into writer thread
+    let buffer = data.serialize_into_buffer();
+    EntityFieldData::Pod(buffer.to_vec())
+  }
+}
+
+fn trace_table<T: Send + Sync + 'static>(
+  table: &ArcTable,
+  e_name_id: u32,
+  name_table: &Arc<Mutex<NameTable>>,
+  writer: &impl TraceWriter<TracingMessage<T>>,
+) {
+  let writer_ = writer.clone();
+  table.entity_watchers().on(move |change| {
+    match change {
+      ScopedMessage::Start => {}
+      ScopedMessage::End => {}
+      ScopedMessage::ReserveSpace(_size) => {}
+      ScopedMessage::Message(change) => match change {
+        EntityChange::NewEntityStartCreate(handle) => {
+          let msg = DatabaseTracingMessage::EntityCreated(e_name_id, *handle);
+          writer_.write_message(TracingMessage::DatabaseMutation(msg));
+        }
+        EntityChange::NewEntityCreated(_) => {}
+        EntityChange::DeleteEntity(handle) => {
+          let msg = DatabaseTracingMessage::EntityDeleted(e_name_id, *handle);
+          writer_.write_message(TracingMessage::DatabaseMutation(msg));
+        }
+      },
+    }
+    false
+  });
+
+  let writer_ = writer.clone();
+  let name_table_ = name_table.clone();
+  table.component_define_watchers().on(move |component| {
+    let schema = ComponentTypeSchema::from_component(component);
+    let c_name_id = name_table_.lock().insert_component(
+      component.component_type_id,
+      component.name.clone(),
+      schema.clone(),
+    );
+    let msg = DatabaseTracingMessage::NameDefined(c_name_id, component.name.clone());
+    writer_.write_message(TracingMessage::DatabaseMutation(msg));
+    let msg = DatabaseTracingMessage::ComponentSchemaDefined(c_name_id, schema);
+    writer_.write_message(TracingMessage::DatabaseMutation(msg));
+
+    trace_component(component, c_name_id, &writer_);
+    false
+  });
+
+  table.visit_components(|component| {
+    let c_name_id = name_table.lock().component_name_to_id[&component.component_type_id];
+    trace_component(component, c_name_id, writer);
+  });
+}
+
+fn trace_component<T: Send + Sync + 'static>(
+  component: &ComponentUntyped,
+  c_name_id: u32,
+  writer: &impl TraceWriter<TracingMessage<T>>,
+) {
+  let writer = writer.clone();
+  let c_is_fk = component.as_foreign_key.is_some();
+  component.data_watchers.on(move |change| {
+    match change {
+      ScopedMessage::Start => {}
+      ScopedMessage::End => {}
+      ScopedMessage::ReserveSpace(_size) => {}
+      ScopedMessage::Message(change) => match change.change {
+        ValueChange::Delta((data_ptr, dyn_ptr), _) => {
+          let data = unsafe { &*dyn_ptr as &dyn DynDataBaseDataType };
+          let field_data = create_field_data(c_is_fk, data_ptr, data);
+          let msg = DatabaseTracingMessage::EntityFieldSet(c_name_id, change.idx, field_data);
+          writer.write_message(TracingMessage::DatabaseMutation(msg));
+        }
+        ValueChange::Remove(_) => {}
+      },
+    };
+    false
+  });
 }
 
 /// Convert a trace binary file to human-readable text.
@@ -147,8 +244,8 @@ pub fn trace_to_text<T: TraceIO>(
   let mut file = std::fs::File::open(input_path)?;
   let (name_table, _discriminant) = read_trace_file_header(&mut file)?;
 
-  let ctx = FormatCtx {
-    names: &name_table.names,
+  let mut ctx = FormatCtx {
+    name_table,
     db,
     max_data_debug_len,
   };
@@ -156,6 +253,7 @@ pub fn trace_to_text<T: TraceIO>(
   loop {
     match TracingMessage::<T>::read(&mut file) {
       Ok(msg) => {
+        ctx.observe(&msg);
         let line = format_message(&msg, &ctx);
         writeln!(output, "{}", line)?;
       }
@@ -168,9 +266,18 @@ pub fn trace_to_text<T: TraceIO>(
 }
 
 pub(crate) struct FormatCtx<'a> {
-  names: &'a [String],
-  db: Option<&'a Database>,
-  max_data_debug_len: usize,
+  pub(crate) name_table: NameTable,
+  pub(crate) db: Option<&'a Database>,
+  pub(crate) max_data_debug_len: usize,
+}
+
+impl FormatCtx<'_> {
+  /// Extend the name table if the message defines new names.
+  pub(crate) fn observe<T>(&mut self, msg: &TracingMessage<T>) {
+    if let TracingMessage::DatabaseMutation(msg) = msg {
+      self.name_table.apply_definition(msg);
+    }
+  }
 }
 
 pub(crate) fn format_message<T: Debug>(msg: &TracingMessage<T>, ctx: &FormatCtx) -> String {
@@ -189,7 +296,7 @@ fn format_db_msg(msg: &DatabaseTracingMessage, ctx: &FormatCtx) -> String {
     DatabaseTracingMessage::EntityCreated(name_id, handle) => {
       format!(
         "[EntityCreated] entity=\"{}\" handle=({}, g:{})",
-        lookup(ctx.names, *name_id),
+        lookup(&ctx.name_table.names, *name_id),
         handle.alloc_index(),
         handle.generation()
       )
@@ -197,7 +304,7 @@ fn format_db_msg(msg: &DatabaseTracingMessage, ctx: &FormatCtx) -> String {
     DatabaseTracingMessage::EntityDeleted(name_id, handle) => {
       format!(
         "[EntityDeleted] entity=\"{}\" handle=({}, g:{})",
-        lookup(ctx.names, *name_id),
+        lookup(&ctx.name_table.names, *name_id),
         handle.alloc_index(),
         handle.generation()
       )
@@ -209,12 +316,24 @@ fn format_db_msg(msg: &DatabaseTracingMessage, ctx: &FormatCtx) -> String {
       };
       format!(
         "[EntityFieldSet] component=\"{}\" handle=({}, g:{}) {}",
-        lookup(ctx.names, *name_id),
+        lookup(&ctx.name_table.names, *name_id),
         handle.alloc_index(),
         handle.generation(),
         value_str
       )
     }
+    DatabaseTracingMessage::NameDefined(name_id, name) => {
+      format!("[NameDefined] name_id={} name=\"{}\"", name_id, name)
+    }
+    DatabaseTracingMessage::ComponentSchemaDefined(name_id, schema) => {
+      format!(
+        "[ComponentSchemaDefined] component=\"{}\" type=\"{}\" size={} fk={}",
+        lookup(&ctx.name_table.names, *name_id),
+        schema.type_name,
+        schema.layout_size,
+        schema.is_foreign_key
+      )
+    }
   }
 }
 
@@ -228,7 +347,7 @@ fn format_component_value(ctx: &FormatCtx, name_id: u32, data: &[u8]) -> String
     return format!("data_len={}", data.len());
   };
 
-  let component_name = match ctx.names.get(name_id as usize) {
+  let component_name = match ctx.name_table.names.get(name_id as usize) {
     Some(n) => n.as_str(),
     None => return format!("data_len={}", data.len()),
   };
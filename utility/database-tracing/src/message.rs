@@ -6,6 +6,8 @@ use std::{
 use database::*;
 use fast_hash_collection::*;
 
+use crate::schema::*;
+
 /// Maps entity type names and component type names to compact u32 IDs.
 ///
 /// Entity names and component names share the same ID space (the `names` Vec).
@@ -14,6 +16,55 @@ pub struct NameTable {
   pub names: Vec<String>,
   pub entity_name_to_id: FastHashMap<EntityId, u32>,
   pub component_name_to_id: FastHashMap<ComponentId, u32>,
+  /// The data type schema of the components, keyed by the component name id. The trace
+  /// written by the old version has no schema.
+  pub component_schemas: FastHashMap<u32, ComponentTypeSchema>,
+}
+
+impl NameTable {
+  /// Insert a name at the given id, used when applying the [DatabaseTracingMessage::NameDefined].
+  pub fn define_name(&mut self, name_id: u32, name: String) {
+    let name_id = name_id as usize;
+    if self.names.len() <= name_id {
+      self.names.resize(name_id + 1, String::new());
+    }
+    self.names[name_id] = name;
+  }
+
+  /// Extend the name table by the definition message, used when reading the trace.
+  pub fn apply_definition(&mut self, msg: &DatabaseTracingMessage) {
+    match msg {
+      DatabaseTracingMessage::NameDefined(name_id, name) => {
+        self.define_name(*name_id, name.clone())
+      }
+      DatabaseTracingMessage::ComponentSchemaDefined(name_id, schema) => {
+        self.component_schemas.insert(*name_id, schema.clone());
+      }
+      _ => {}
+    }
+  }
+
+  /// Assign a new name id for the entity declared after the name table is built.
+  pub fn insert_entity(&mut self, e_id: EntityId, name: String) -> u32 {
+    let name_id = self.names.len() as u32;
+    self.names.push(name);
+    self.entity_name_to_id.insert(e_id, name_id);
+    name_id
+  }
+
+  /// Assign a new name id for the component declared after the name table is built.
+  pub fn insert_component(
+    &mut self,
+    c_id: ComponentId,
+    name: String,
+    schema: ComponentTypeSchema,
+  ) -> u32 {
+    let name_id = self.names.len() as u32;
+    self.names.push(name);
+    self.component_name_to_id.insert(c_id, name_id);
+    self.component_schemas.insert(name_id, schema);
+    name_id
+  }
 }
 
 // message type tags in binary format
@@ -21,10 +72,14 @@ pub(crate) const TAG_EVENT: u8 = 0x00;
 pub(crate) const TAG_ENTITY_CREATED: u8 = 0x01;
 pub(crate) const TAG_ENTITY_DELETED: u8 = 0x02;
 pub(crate) const TAG_ENTITY_FIELD_SET: u8 = 0x03;
+pub(crate) const TAG_NAME_DEFINED: u8 = 0x04;
+pub(crate) const TAG_COMPONENT_SCHEMA_DEFINED: u8 = 0x05;
 
 // file header constants
 pub(crate) const MAGIC: &[u8; 4] = b"RTRC";
-pub(crate) const VERSION: u32 = 1;
+pub(crate) const VERSION: u32 = 2;
+/// The version before the component schemas are stored in the header.
+pub(crate) const VERSION_WITHOUT_SCHEMA: u32 = 1;
 pub(crate) const HEADER_SIZE: u32 = 20;
 
 pub enum TracingMessage<T> {
@@ -54,10 +109,15 @@ pub enum EntityFieldData {
 /// name_id is an index into the file's name table.
 /// For EntityCreated/EntityDeleted, name_id refers to an entity type name.
 /// For EntityFieldSet, name_id refers to a component type name.
+/// NameDefined extends the name table for the entity or component declared after
+/// the tracing started, the name_id is the newly assigned id.
+/// ComponentSchemaDefined follows the NameDefined of the late declared component.
 pub enum DatabaseTracingMessage {
   EntityCreated(u32, RawEntityHandle),
   EntityDeleted(u32, RawEntityHandle),
   EntityFieldSet(u32, RawEntityHandle, EntityFieldData),
+  NameDefined(u32, String),
+  ComponentSchemaDefined(u32, ComponentTypeSchema),
 }
 
 impl Debug for DatabaseTracingMessage {
@@ -87,6 +147,16 @@ impl Debug for DatabaseTracingMessage {
           )
         }
       },
+      DatabaseTracingMessage::NameDefined(name_id, name) => {
+        write!(f, "NameDefined(name_id={}, name={})", name_id, name)
+      }
+      DatabaseTracingMessage::ComponentSchemaDefined(name_id, schema) => {
+        write!(
+          f,
+          "ComponentSchemaDefined(name_id={}, schema={:?})",
+          name_id, schema
+        )
+      }
     }
   }
 }
@@ -97,6 +167,8 @@ impl DatabaseTracingMessage {
       DatabaseTracingMessage::EntityCreated(_, _) => TAG_ENTITY_CREATED,
       DatabaseTracingMessage::EntityDeleted(_, _) => TAG_ENTITY_DELETED,
       DatabaseTracingMessage::EntityFieldSet(_, _, _) => TAG_ENTITY_FIELD_SET,
+      DatabaseTracingMessage::NameDefined(_, _) => TAG_NAME_DEFINED,
+      DatabaseTracingMessage::ComponentSchemaDefined(_, _) => TAG_COMPONENT_SCHEMA_DEFINED,
     }
   }
 
@@ -111,6 +183,10 @@ impl DatabaseTracingMessage {
           EntityFieldData::ForeignKey(_) => 1 + 12,     // has_fk(1) + fk handle(12)
         }
       }
+      // name_id(4) + name_len(2) + name
+      DatabaseTracingMessage::NameDefined(_, name) => 4 + 2 + name.len(),
+      // name_id(4) + schema
+      DatabaseTracingMessage::ComponentSchemaDefined(_, schema) => 4 + schema.binary_len(),
     }
   }
 
@@ -147,6 +223,14 @@ impl DatabaseTracingMessage {
           }
         }
       }
+      DatabaseTracingMessage::NameDefined(name_id, name) => {
+        write_u32_le(w, *name_id)?;
+        write_name_table_entry(w, name)?;
+      }
+      DatabaseTracingMessage::ComponentSchemaDefined(name_id, schema) => {
+        write_u32_le(w, *name_id)?;
+        schema.write_binary(w)?;
+      }
     }
     Ok(())
   }
@@ -237,6 +321,18 @@ impl TraceIO for DatabaseTracingMessage {
           name_id, handle, field_data,
         ))
       }
+      TAG_NAME_DEFINED => {
+        let name_id = read_u32_le(source)?;
+        let name = read_name_table_entry(source)?;
+        Ok(DatabaseTracingMessage::NameDefined(name_id, name))
+      }
+      TAG_COMPONENT_SCHEMA_DEFINED => {
+        let name_id = read_u32_le(source)?;
+        let schema = ComponentTypeSchema::read_binary(source)?;
+        Ok(DatabaseTracingMessage::ComponentSchemaDefined(
+          name_id, schema,
+        ))
+      }
       _ => Err(std::io::Error::new(
         std::io::ErrorKind::InvalidData,
         format!("unknown database message tag: {}", tag[0]),
@@ -341,7 +437,7 @@ pub(crate) fn read_u64_le(source: &mut (impl Read + ?Sized)) -> std::io::Result<
 
 // header I/O
 
-/// Write the trace file header (magic, version, name table) to a writer.
+/// Write the trace file header (magic, version, name table, component schemas) to a writer.
 pub fn write_trace_file_header(
   w: &mut impl Write,
   name_table: &NameTable,
@@ -357,6 +453,15 @@ pub fn write_trace_file_header(
     write_name_table_entry(w, name)?;
   }
 
+  // sort to make the header content stable
+  let mut schemas: Vec<_> = name_table.component_schemas.iter().collect();
+  schemas.sort_by_key(|(name_id, _)| **name_id);
+  write_u32_le(w, schemas.len() as u32)?;
+  for (name_id, schema) in schemas {
+    write_u32_le(w, *name_id)?;
+    schema.write_binary(w)?;
+  }
+
   Ok(())
 }
 
@@ -366,9 +471,18 @@ pub(crate) fn write_name_table_entry(w: &mut impl Write, name: &str) -> std::io:
   w.write_all(bytes)
 }
 
+pub(crate) fn read_name_table_entry(source: &mut (impl Read + ?Sized)) -> std::io::Result<String> {
+  let name_len = read_u16_le(source)? as usize;
+  let mut name_buf = vec![0u8; name_len];
+  source.read_exact(&mut name_buf)?;
+  String::from_utf8(name_buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
 /// Read and validate the trace file header. Returns the name table
 /// (with empty debuggers since function pointers cannot be serialized)
 /// and the stored type discriminant.
+///
+/// The header of the old version without component schemas is also accepted.
 pub fn read_trace_file_header(source: &mut impl Read) -> std::io::Result<(NameTable, u32)> {
   let mut magic_buf = [0u8; 4];
   source.read_exact(&mut magic_buf)?;
@@ -384,7 +498,7 @@ pub fn read_trace_file_header(source: &mut impl Read) -> std::io::Result<(NameTa
   }
 
   let version = read_u32_le(source)?;
-  if version != VERSION {
+  if version != VERSION && version != VERSION_WITHOUT_SCHEMA {
     return Err(std::io::Error::new(
       std::io::ErrorKind::InvalidData,
       format!("unsupported version: {}", version),
@@ -397,13 +511,16 @@ pub fn read_trace_file_header(source: &mut impl Read) -> std::io::Result<(NameTa
 
   let mut names = Vec::with_capacity(name_count);
   for _ in 0..name_count {
-    let name_len = read_u16_le(source)? as usize;
-    let mut name_buf = vec![0u8; name_len];
-    source.read_exact(&mut name_buf)?;
-    names.push(
-      String::from_utf8(name_buf)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
-    );
+    names.push(read_name_table_entry(source)?);
+  }
+
+  let mut component_schemas = FastHashMap::default();
+  if version != VERSION_WITHOUT_SCHEMA {
+    let schema_count = read_u32_le(source)?;
+    for _ in 0..schema_count {
+      let name_id = read_u32_le(source)?;
+      component_schemas.insert(name_id, ComponentTypeSchema::read_binary(source)?);
+    }
   }
 
   Ok((
@@ -411,6 +528,7 @@ pub fn read_trace_file_header(source: &mut impl Read) -> std::io::Result<(NameTa
       names,
       entity_name_to_id: FastHashMap::default(),
       component_name_to_id: FastHashMap::default(),
+      component_schemas,
     },
     type_discriminant,
   ))
@@ -568,6 +686,20 @@ mod tests {
     assert_eq!(format!("{:?}", original), format!("{:?}", read_back));
   }
 
+  #[test]
+  fn test_round_trip_name_defined() {
+    let original = DatabaseTracingMessage::NameDefined(5, "LateComponent".to_string());
+    assert_eq!(original.write_len(), 1 + 4 + 2 + 13);
+
+    let mut buf = Vec::new();
+    let written = original.write(&mut buf).unwrap();
+    assert_eq!(written, original.write_len());
+
+    let mut cursor = Cursor::new(buf);
+    let read_back = DatabaseTracingMessage::read(&mut cursor).unwrap();
+    assert_eq!(format!("{:?}", original), format!("{:?}", read_back));
+  }
+
   #[test]
   fn test_round_trip_tracing_message_event() {
     let original = TracingMessage::<()>::Event(());
@@ -596,9 +728,33 @@ mod tests {
 
   // -- header I/O tests --
 
+  #[test]
+  fn test_round_trip_component_schema_defined() {
+    let schema = ComponentTypeSchema {
+      type_name: "f32".to_string(),
+      layout_size: 4,
+      is_foreign_key: false,
+    };
+    let original = DatabaseTracingMessage::ComponentSchemaDefined(3, schema);
+
+    let mut buf = Vec::new();
+    let written = original.write(&mut buf).unwrap();
+    assert_eq!(written, original.write_len());
+    assert_eq!(buf.len(), original.write_len());
+
+    let mut cursor = Cursor::new(buf);
+    let read_back = DatabaseTracingMessage::read(&mut cursor).unwrap();
+    assert_eq!(format!("{:?}", original), format!("{:?}", read_back));
+  }
+
   #[test]
   fn test_header_round_trip() {
     let name_table = vec!["TestEntity".to_string(), "TestComponent".to_string()];
+    let schema = ComponentTypeSchema {
+      type_name: "u32".to_string(),
+      layout_size: 4,
+      is_foreign_key: false,
+    };
 
     let mut buf = Vec::new();
     write_trace_file_header(
@@ -607,6 +763,7 @@ mod tests {
         names: name_table.clone(),
         entity_name_to_id: FastHashMap::default(),
         component_name_to_id: FastHashMap::default(),
+        component_schemas: [(1, schema.clone())].into_iter().collect(),
       },
       0,
     )
@@ -615,5 +772,23 @@ mod tests {
     let mut cursor = Cursor::new(buf);
     let (read_back, _disc) = read_trace_file_header(&mut cursor).unwrap();
     assert_eq!(name_table, read_back.names);
+    assert_eq!(read_back.component_schemas.get(&1), Some(&schema));
+  }
+
+  #[test]
+  fn test_header_without_schema() {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(MAGIC);
+    write_u32_le(&mut buf, VERSION_WITHOUT_SCHEMA).unwrap();
+    write_u32_le(&mut buf, HEADER_SIZE).unwrap();
+    write_u32_le(&mut buf, 7).unwrap();
+    write_u32_le(&mut buf, 1).unwrap();
+    write_name_table_entry(&mut buf, "TestEntity").unwrap();
+
+    let mut cursor = Cursor::new(buf);
+    let (read_back, disc) = read_trace_file_header(&mut cursor).unwrap();
+    assert_eq!(disc, 7);
+    assert_eq!(read_back.names, vec!["TestEntity".to_string()]);
+    assert!(read_back.component_schemas.is_empty());
   }
 }
@@ -0,0 +1,259 @@
+//! Lossless transcoding between the msgpack component data and json.
+//!
+//! The msgpack values that have a natural json form are written directly, so the readable
+//! snapshot is easy to inspect and edit by hand:
+//!
+//! - nil, bool, integer, utf8 string and array are mapped to their json counterpart.
+//! - f32 is written as json float, the shortest representation is used.
+//! - map with utf8 string keys is written as json object if the keys are in strictly ascending
+//!   order (json object keys are sorted, so the order can be preserved).
+//!
+//! The others are written as a single key object, the key starts with `$`:
+//!
+//! - `{"$f64": 1.0}`, f64 value.
+//! - `{"$f32": "NaN"}`, `{"$f32": "inf"}` etc, the non-finite float.
+//! - `{"$bin": [..]}`, binary.
+//! - `{"$str": [..]}`, string that is not valid utf8.
+//! - `{"$map": [[k, v], ..]}`, map that can not be written as json object.
+//! - `{"$ext": [type, [..]]}`, msgpack extension.
+
+use rmpv::{Integer, Utf8String, Value};
+use serde_json::{Map, Number, Value as Json, json};
+
+fn invalid_data(msg: impl Into<String>) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidData, msg.into())
+}
+
+pub(crate) fn msgpack_to_readable(mut data: &[u8]) -> std::io::Result<Json> {
+  let value = rmpv::decode::read_value(&mut data).map_err(|e| invalid_data(e.to_string()))?;
+  if !data.is_empty() {
+    return Err(invalid_data("trailing bytes after the msgpack value"));
+  }
+  Ok(to_json(&value))
+}
+
+pub(crate) fn readable_to_msgpack(value: &Json) -> std::io::Result<Vec<u8>> {
+  let value = from_json(value)?;
+  let mut data = Vec::new();
+  rmpv::encode::write_value(&mut data, &value).map_err(|e| invalid_data(e.to_string()))?;
+  Ok(data)
+}
+
+fn non_finite_name(v: f64) -> &'static str {
+  if v.is_nan() {
+    "NaN"
+  } else if v > 0. {
+    "inf"
+  } else {
+    "-inf"
+  }
+}
+
+fn non_finite_from_name(name: &str) -> Option<f64> {
+  match name {
+    "NaN" => Some(f64::NAN),
+    "inf" => Some(f64::INFINITY),
+    "-inf" => Some(f64::NEG_INFINITY),
+    _ => None,
+  }
+}
+
+fn bytes_to_json(bytes: &[u8]) -> Json {
+  Json::Array(bytes.iter().map(|b| json!(b)).collect())
+}
+
+fn to_json(value: &Value) -> Json {
+  match value {
+    Value::Nil => Json::Null,
+    Value::Boolean(v) => Json::Bool(*v),
+    Value::Integer(v) => {
+      if let Some(v) = v.as_u64() {
+        json!(v)
+      } else {
+        json!(v.as_i64().unwrap())
+      }
+    }
+    Value::F32(v) => {
+      if v.is_finite() {
+        // the shortest representation of f32 is parsed back to the same f32 through f64
+        Json::Number(Number::from_f64(v.to_string().parse().unwrap()).unwrap())
+      } else {
+        json!({ "$f32": non_finite_name(*v as f64) })
+      }
+    }
+    Value::F64(v) => {
+      if v.is_finite() {
+        json!({ "$f64": v })
+      } else {
+        json!({ "$f64": non_finite_name(*v) })
+      }
+    }
+    Value::String(v) => match v.as_str() {
+      Some(s) => Json::String(s.to_string()),
+      None => json!({ "$str": bytes_to_json(v.as_bytes()) }),
+    },
+    Value::Binary(v) => json!({ "$bin": bytes_to_json(v) }),
+    Value::Array(v) => Json::Array(v.iter().map(to_json).collect()),
+    Value::Map(entries) => {
+      let keys: Option<Vec<&str>> = entries.iter().map(|(k, _)| k.as_str()).collect();
+      let as_object = keys.filter(|keys| {
+        keys.windows(2).all(|w| w[0] < w[1]) && !keys.iter().any(|k| k.starts_with('$'))
+      });
+      if let Some(keys) = as_object {
+        let map = keys
+          .into_iter()
+          .zip(entries)
+          .map(|(k, (_, v))| (k.to_string(), to_json(v)))
+          .collect::<Map<_, _>>();
+        Json::Object(map)
+      } else {
+        let entries = entries
+          .iter()
+          .map(|(k, v)| Json::Array(vec![to_json(k), to_json(v)]))
+          .collect();
+        json!({ "$map": Json::Array(entries) })
+      }
+    }
+    Value::Ext(ty, data) => json!({ "$ext": [ty, bytes_to_json(data)] }),
+  }
+}
+
+fn json_to_bytes(value: &Json) -> std::io::Result<Vec<u8>> {
+  let Json::Array(bytes) = value else {
+    return Err(invalid_data("expect byte array"));
+  };
+  bytes
+    .iter()
+    .map(|b| {
+      b.as_u64()
+        .and_then(|b| u8::try_from(b).ok())
+        .ok_or_else(|| invalid_data(format!("invalid byte {b}")))
+    })
+    .collect()
+}
+
+fn json_to_float(value: &Json) -> std::io::Result<f64> {
+  match value {
+    Json::Number(v) => v
+      .as_f64()
+      .ok_or_else(|| invalid_data(format!("invalid float {v}"))),
+    Json::String(name) => {
+      non_finite_from_name(name).ok_or_else(|| invalid_data(format!("invalid float {name}")))
+    }
+    _ => Err(invalid_data(format!("invalid float {value}"))),
+  }
+}
+
+/// The [Utf8String] with invalid utf8 can only be created by decoding.
+fn non_utf8_string(bytes: Vec<u8>) -> std::io::Result<Value> {
+  let mut data = vec![0xdb];
+  let len = u32::try_from(bytes.len()).map_err(|_| invalid_data("string too long"))?;
+  data.extend_from_slice(&len.to_be_bytes());
+  data.extend(bytes);
+  rmpv::decode::read_value(&mut data.as_slice()).map_err(|e| invalid_data(e.to_string()))
+}
+
+fn from_json(value: &Json) -> std::io::Result<Value> {
+  Ok(match value {
+    Json::Null => Value::Nil,
+    Json::Bool(v) => Value::Boolean(*v),
+    Json::Number(v) => {
+      if let Some(v) = v.as_u64() {
+        Value::Integer(Integer::from(v))
+      } else if let Some(v) = v.as_i64() {
+        Value::Integer(Integer::from(v))
+      } else {
+        Value::F32(v.as_f64().unwrap() as f32)
+      }
+    }
+    Json::String(v) => Value::String(Utf8String::from(v.as_str())),
+    Json::Array(v) => Value::Array(v.iter().map(from_json).collect::<Result<_, _>>()?),
+    Json::Object(map) => {
+      let mut tagged = map.iter().filter(|(k, _)| k.starts_with('$'));
+      if let Some((tag, v)) = tagged.next() {
+        if map.len() != 1 {
+          return Err(invalid_data(format!("unexpected fields beside {tag}")));
+        }
+        match tag.as_str() {
+          "$f32" => Value::F32(json_to_float(v)? as f32),
+          "$f64" => Value::F64(json_to_float(v)?),
+          "$bin" => Value::Binary(json_to_bytes(v)?),
+          "$str" => non_utf8_string(json_to_bytes(v)?)?,
+          "$map" => {
+            let Json::Array(entries) = v else {
+              return Err(invalid_data("expect map entry array"));
+            };
+            let entries = entries
+              .iter()
+              .map(|entry| match entry.as_array().map(|e| e.as_slice()) {
+                Some([k, v]) => Ok((from_json(k)?, from_json(v)?)),
+                _ => Err(invalid_data("expect [key, value] map entry")),
+              })
+              .collect::<Result<_, _>>()?;
+            Value::Map(entries)
+          }
+          "$ext" => match v.as_array().map(|e| e.as_slice()) {
+            Some([ty, data]) => {
+              let ty = ty
+                .as_i64()
+                .and_then(|ty| i8::try_from(ty).ok())
+                .ok_or_else(|| invalid_data(format!("invalid ext type {ty}")))?;
+              Value::Ext(ty, json_to_bytes(data)?)
+            }
+            _ => return Err(invalid_data("expect [type, data] ext")),
+          },
+          _ => return Err(invalid_data(format!("unknown tag {tag}"))),
+        }
+      } else {
+        let entries = map
+          .iter()
+          .map(|(k, v)| Ok((Value::String(Utf8String::from(k.as_str())), from_json(v)?)))
+          .collect::<std::io::Result<_>>()?;
+        Value::Map(entries)
+      }
+    }
+  })
+}
+
+#[test]
+fn test_readable_transcoding_is_lossless() {
+  fn check(value: Value) {
+    let mut data = Vec::new();
+    rmpv::encode::write_value(&mut data, &value).unwrap();
+    let readable = msgpack_to_readable(&data).unwrap();
+    let text = serde_json::to_string(&readable).unwrap();
+    let readable: Json = serde_json::from_str(&text).unwrap();
+    assert_eq!(readable_to_msgpack(&readable).unwrap(), data, "{text}");
+  }
+
+  check(Value::Nil);
+  check(Value::Boolean(true));
+  check(Value::from(u64::MAX));
+  check(Value::from(i64::MIN));
+  check(Value::F32(0.1));
+  check(Value::F32(1.0));
+  check(Value::F32(-3.4e38));
+  check(Value::F32(f32::NAN));
+  check(Value::F32(f32::NEG_INFINITY));
+  check(Value::F64(0.1));
+  check(Value::F64(1.0));
+  check(Value::F64(f64::INFINITY));
+  check(Value::from("text"));
+  check(non_utf8_string(vec![0xff, 0xfe]).unwrap());
+  check(Value::Binary(vec![0, 1, 255]));
+  check(Value::Ext(3, vec![1, 2]));
+  check(Value::Array(vec![Value::from(1), Value::F32(2.5)]));
+  check(Value::Map(vec![
+    (Value::from("a"), Value::from(1)),
+    (Value::from("b"), Value::Nil),
+  ]));
+  // not sorted, so it can not be represented by the json object
+  check(Value::Map(vec![
+    (Value::from("b"), Value::from(1)),
+    (Value::from("a"), Value::Nil),
+  ]));
+  check(Value::Map(vec![
+    (Value::from(1), Value::from("x")),
+    (Value::from("$f64"), Value::F64(2.)),
+  ]));
+}
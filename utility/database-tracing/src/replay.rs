@@ -4,7 +4,7 @@ use database::*;
 use fast_hash_collection::*;
 use smallvec::SmallVec;
 
-use crate::message::*;
+use crate::{message::*, schema::*};
 
 /// Parsed view of a single trace record, ready for replay.
 pub struct ParsedRecord {
@@ -23,6 +23,11 @@ pub enum RecordKind {
     handle: RawEntityHandle,
     field_data: EntityFieldData,
   },
+  /// The name table extension of late declared entity or component. The name is already
+  /// merged into [ReplayState::names] when loading, so nothing to apply.
+  NameDefined(u32),
+  /// The schema of late declared component, also merged into [ReplayState::schemas] when loading.
+  ComponentSchemaDefined(u32),
   Event,
 }
 
@@ -31,10 +36,38 @@ pub struct ReplayState {
   pub records: Vec<ParsedRecord>,
   pub position: usize,
   pub names: Vec<String>,
+  /// The recorded component data type schemas keyed by the name id, empty if the trace
+  /// is written by the old version.
+  pub schemas: FastHashMap<u32, ComponentTypeSchema>,
+  /// How the recorded names and data map to the live database, see [DatabaseMigration].
+  pub migration: DatabaseMigration,
   /// Per-entity-type handle map: EntityId → (original handle → live handle).
   /// Each entity type has its own independent handle allocator, so the same
   /// RawEntityHandle value can appear in different entity types.
   pub handle_map: FastHashMap<EntityId, FastHashMap<RawEntityHandle, RawEntityHandle>>,
+  /// The default fill values of each live table, computed when the first entity of the
+  /// table is created, see [DatabaseMigration::table_default_fills].
+  default_fills: FastHashMap<EntityId, Vec<(ComponentId, Vec<u8>)>>,
+}
+
+impl ReplayState {
+  pub fn new(records: Vec<ParsedRecord>, name_table: NameTable) -> Self {
+    Self {
+      records,
+      position: 0,
+      names: name_table.names,
+      schemas: name_table.component_schemas,
+      migration: DatabaseMigration::default(),
+      handle_map: FastHashMap::default(),
+      default_fills: FastHashMap::default(),
+    }
+  }
+
+  pub fn with_migration(mut self, migration: DatabaseMigration) -> Self {
+    self.migration = migration;
+    self.default_fills.clear();
+    self
+  }
 }
 
 pub trait TraceReplayTarget {
@@ -45,12 +78,13 @@ pub trait TraceReplayTarget {
   fn is_replay_target(&self) -> bool;
 }
 
-/// A type-erased function pointer that reads trace records from a reader.
-type RecordLoader = fn(&mut dyn Read, &NameTable) -> std::io::Result<Vec<ParsedRecord>>;
+/// A type-erased function pointer that reads trace records from a reader. The name table
+/// is extended by the name definitions in the records.
+type RecordLoader = fn(&mut dyn Read, &mut NameTable) -> std::io::Result<Vec<ParsedRecord>>;
 
 /// A type-erased function pointer that converts trace records to human-readable text.
 type TextConverter =
-  fn(&mut dyn Read, &[String], Option<&Database>, usize, &mut dyn Write) -> std::io::Result<()>;
+  fn(&mut dyn Read, NameTable, Option<&Database>, usize, &mut dyn Write) -> std::io::Result<()>;
 
 #[derive(Clone)]
 struct ReplayTypeEntry {
@@ -98,7 +132,7 @@ impl ReplayTypeRegistry {
   /// * A record fails to deserialize.
   pub fn load(&self, input_path: impl AsRef<std::path::Path>) -> std::io::Result<LoadedReplay> {
     let mut file = std::fs::File::open(input_path.as_ref())?;
-    let (name_table, disc) = read_trace_file_header(&mut file)?;
+    let (mut name_table, disc) = read_trace_file_header(&mut file)?;
     let entry = self.entries.get(&disc).ok_or_else(|| {
       std::io::Error::new(
         std::io::ErrorKind::InvalidData,
@@ -109,14 +143,9 @@ impl ReplayTypeRegistry {
         ),
       )
     })?;
-    let records = (entry.loader)(&mut file, &name_table)?;
+    let records = (entry.loader)(&mut file, &mut name_table)?;
     Ok(LoadedReplay {
-      state: ReplayState {
-        records,
-        position: 0,
-        names: name_table.names,
-        handle_map: FastHashMap::default(),
-      },
+      state: ReplayState::new(records, name_table),
       type_discriminant: disc,
       type_name: entry.type_name,
     })
@@ -145,7 +174,7 @@ impl ReplayTypeRegistry {
         ),
       )
     })?;
-    (entry.text_converter)(&mut file, &name_table.names, db, max_data_debug_len, output)
+    (entry.text_converter)(&mut file, name_table, db, max_data_debug_len, output)
   }
 }
 
@@ -169,15 +198,17 @@ pub struct LoadedReplay {
 /// Returns a `RecordLoader` function pointer when monomorphized for a concrete `T`.
 fn read_records_for<T: TraceIO + TraceReplayTarget>(
   reader: &mut dyn Read,
-  name_table: &NameTable,
+  name_table: &mut NameTable,
 ) -> std::io::Result<Vec<ParsedRecord>> {
-  let names = &name_table.names;
   let mut records = Vec::new();
   loop {
     match TracingMessage::<T>::read(reader) {
       Ok(msg) => {
+        if let TracingMessage::DatabaseMutation(msg) = &msg {
+          name_table.apply_definition(msg);
+        }
         let (kind, is_replay_target) = extract_kind(&msg);
-        let mut summary = format_replay_summary(&msg, names);
+        let mut summary = format_replay_summary(&msg, &name_table.names);
         if is_replay_target {
           summary.push_str(" ◀ target");
         }
@@ -199,19 +230,20 @@ fn read_records_for<T: TraceIO + TraceReplayTarget>(
 /// Returns a `TextConverter` function pointer when monomorphized for a concrete `T`.
 fn convert_to_text_for<T: TraceIO + std::fmt::Debug>(
   reader: &mut dyn Read,
-  names: &[String],
+  name_table: NameTable,
   db: Option<&Database>,
   max_data_debug_len: usize,
   output: &mut dyn Write,
 ) -> std::io::Result<()> {
-  let ctx = crate::FormatCtx {
-    names,
+  let mut ctx = crate::FormatCtx {
+    name_table,
     db,
     max_data_debug_len,
   };
   loop {
     match TracingMessage::<T>::read(reader) {
       Ok(msg) => {
+        ctx.observe(&msg);
         let line = crate::format_message(&msg, &ctx);
         writeln!(output, "{}", line)?;
       }
@@ -229,7 +261,7 @@ pub fn load_replay<T: TraceIO + TraceReplayTarget>(
   input_path: impl AsRef<std::path::Path>,
 ) -> std::io::Result<ReplayState> {
   let mut file = std::fs::File::open(input_path)?;
-  let (name_table, stored_disc) = read_trace_file_header(&mut file)?;
+  let (mut name_table, stored_disc) = read_trace_file_header(&mut file)?;
   let expected_disc = T::type_discriminant();
   if stored_disc != expected_disc {
     return Err(std::io::Error::new(
@@ -242,14 +274,9 @@ pub fn load_replay<T: TraceIO + TraceReplayTarget>(
     ));
   }
 
-  let records = read_records_for::<T>(&mut file, &name_table)?;
+  let records = read_records_for::<T>(&mut file, &mut name_table)?;
 
-  Ok(ReplayState {
-    records,
-    position: 0,
-    names: name_table.names,
-    handle_map: FastHashMap::default(),
-  })
+  Ok(ReplayState::new(records, name_table))
 }
 
 fn extract_kind<T: TraceReplayTarget>(msg: &TracingMessage<T>) -> (RecordKind, bool) {
@@ -270,6 +297,10 @@ fn extract_kind<T: TraceReplayTarget>(msg: &TracingMessage<T>) -> (RecordKind, b
         },
         false,
       ),
+      DatabaseTracingMessage::NameDefined(name_id, _) => (RecordKind::NameDefined(*name_id), false),
+      DatabaseTracingMessage::ComponentSchemaDefined(name_id, _) => {
+        (RecordKind::ComponentSchemaDefined(*name_id), false)
+      }
     },
   }
 }
@@ -297,27 +328,60 @@ pub fn step_forward_single(state: &mut ReplayState, db: &Database) {
     return;
   }
   let record = &state.records[state.position];
-  apply_single(db, &state.names, &mut state.handle_map, &record.kind);
+  let ctx = ApplyCtx {
+    names: &state.names,
+    schemas: &state.schemas,
+    migration: &state.migration,
+  };
+  apply_single(
+    db,
+    &ctx,
+    &mut state.handle_map,
+    &mut state.default_fills,
+    &record.kind,
+  );
   state.position += 1;
 }
 
+struct ApplyCtx<'a> {
+  names: &'a [String],
+  schemas: &'a FastHashMap<u32, ComponentTypeSchema>,
+  migration: &'a DatabaseMigration,
+}
+
 /// Apply a single record kind.
 fn apply_single(
   db: &Database,
-  names: &[String],
+  ctx: &ApplyCtx,
   handle_map: &mut FastHashMap<EntityId, FastHashMap<RawEntityHandle, RawEntityHandle>>,
+  default_fills: &mut FastHashMap<EntityId, Vec<(ComponentId, Vec<u8>)>>,
   kind: &RecordKind,
 ) {
   match kind {
     RecordKind::EntityCreated(name_id, orig) => {
-      let e_name = lookup_name(names, *name_id);
-      let e_id = resolve_entity_id(db, e_name);
-      let live = db.entity_writer_untyped_dyn(e_id).new_entity(|w| w);
+      let e_name = lookup_name(ctx.names, *name_id);
+      let Some(e_id) = resolve_entity_id(db, ctx.migration.map_entity_name(e_name)) else {
+        return;
+      };
+      let mut writer = db.entity_writer_untyped_dyn(e_id);
+      let live = writer.new_entity(|w| w);
+      let fills = default_fills.entry(e_id).or_insert_with(|| {
+        let fills = ctx
+          .migration
+          .default_fills(ctx.names.iter().map(|n| n.as_str()));
+        let names = db.name_mapping.read();
+        ctx
+          .migration
+          .table_default_fills(&mut writer, &names, &fills)
+      });
+      write_default_fills(&mut writer, live, fills);
       handle_map.entry(e_id).or_default().insert(*orig, live);
     }
     RecordKind::EntityDeleted(name_id, orig) => {
-      let e_name = lookup_name(names, *name_id);
-      let e_id = resolve_entity_id(db, e_name);
+      let e_name = lookup_name(ctx.names, *name_id);
+      let Some(e_id) = resolve_entity_id(db, ctx.migration.map_entity_name(e_name)) else {
+        return;
+      };
       if let Some(live) = handle_map.get(&e_id).and_then(|m| m.get(orig)).copied() {
         db.entity_writer_untyped_dyn(e_id).delete_entity(live);
         handle_map.get_mut(&e_id).unwrap().remove(orig);
@@ -328,8 +392,10 @@ fn apply_single(
       handle,
       field_data,
     } => {
-      apply_field_set(db, names, *name_id, field_data, *handle, handle_map);
+      apply_field_set(db, ctx, *name_id, field_data, *handle, handle_map);
     }
+    RecordKind::NameDefined(_) => {}
+    RecordKind::ComponentSchemaDefined(_) => {}
     RecordKind::Event => {}
   }
 }
@@ -339,6 +405,7 @@ pub fn restart_and_run_to(state: &mut ReplayState, db: &Database, target: usize)
   let target = target.min(state.records.len());
   state.position = 0;
   state.handle_map.clear();
+  state.default_fills.clear();
   while state.position < target {
     step_forward_single(state, db);
   }
@@ -348,29 +415,38 @@ fn lookup_name(names: &[String], id: u32) -> &str {
   names.get(id as usize).map(|s| s.as_str()).unwrap_or("?")
 }
 
-fn resolve_entity_id(db: &Database, name: &str) -> EntityId {
+/// The entity that not exist in the live database is treated as removed.
+fn resolve_entity_id(db: &Database, name: &str) -> Option<EntityId> {
   let mapping = db.name_mapping.read();
-  *mapping
-    .entities_inv
-    .get(name)
-    .unwrap_or_else(|| panic!("entity \"{}\" not found in live database", name))
+  let e_id = mapping.entities_inv.get(name).copied();
+  if e_id.is_none() {
+    log::warn!("entity \"{}\" not found in live database, skipped", name);
+  }
+  e_id
 }
 
 fn apply_field_set(
   db: &Database,
-  names: &[String],
+  ctx: &ApplyCtx,
   name_id: u32,
   field_data: &EntityFieldData,
   original_handle: RawEntityHandle,
   handle_map: &FastHashMap<EntityId, FastHashMap<RawEntityHandle, RawEntityHandle>>,
 ) {
-  let component_name = lookup_name(names, name_id);
+  let component_name = ctx
+    .migration
+    .map_component_name(lookup_name(ctx.names, name_id));
+  let recorded_schema = ctx.schemas.get(&name_id);
 
   let name_mapping = db.name_mapping.read();
-  let c_id = *name_mapping
-    .components_inv
-    .get(component_name)
-    .unwrap_or_else(|| panic!("component \"{}\" not found in live db", component_name));
+  // the component that not exist in the live database is treated as removed.
+  let Some(c_id) = name_mapping.components_inv.get(component_name).copied() else {
+    log::warn!(
+      "component \"{}\" not found in live db, skipped",
+      component_name
+    );
+    return;
+  };
   let e_id = *name_mapping
     .component_to_entity
     .get(&c_id)
@@ -390,35 +466,46 @@ fn apply_field_set(
 
   db.access_table_dyn(e_id, |table| {
     table.access_component(c_id, |component| {
-      let mut writer = component.write_untyped();
       let value = match field_data {
         EntityFieldData::Pod(data) => {
-          let buffer = SmallVec::from_slice(data);
+          let current_schema = ComponentTypeSchema::from_component(component);
+          let Some(data) =
+            ctx
+              .migration
+              .migrate_pod_data(component_name, recorded_schema, &current_schema, data)
+          else {
+            return;
+          };
+          let buffer = SmallVec::from_slice(&data);
           DatabaseSerializedFieldBufferOrForeignKey::Pod(buffer)
         }
-        EntityFieldData::ForeignKey(fk) => match fk {
-          Some(h) => {
-            let target_e_id = component.as_foreign_key.unwrap_or_else(|| {
-              panic!(
-                "FK component \"{}\" missing foreign key target",
-                component_name
-              )
-            });
-            let remapped = handle_map
-              .get(&target_e_id)
-              .and_then(|m| m.get(h))
-              .copied()
-              .unwrap_or_else(|| panic!("FK target {:?} not created yet — invalid trace", h));
-            DatabaseSerializedFieldBufferOrForeignKey::ForeignKey(remapped)
+        EntityFieldData::ForeignKey(fk) => {
+          let Some(target_e_id) = component.as_foreign_key else {
+            log::warn!(
+              "component \"{}\" is not foreign key in live db, skipped",
+              component_name
+            );
+            return;
+          };
+          match fk {
+            Some(h) => {
+              let remapped = handle_map
+                .get(&target_e_id)
+                .and_then(|m| m.get(h))
+                .copied()
+                .unwrap_or_else(|| panic!("FK target {:?} not created yet — invalid trace", h));
+              DatabaseSerializedFieldBufferOrForeignKey::ForeignKey(remapped)
+            }
+            None => {
+              let mut buf = Vec::new();
+              let none_val: Option<RawEntityHandle> = None;
+              let _ = none_val.serialize_to_writer(&mut buf);
+              DatabaseSerializedFieldBufferOrForeignKey::Pod(SmallVec::from_slice(&buf))
+            }
           }
-          None => {
-            let mut buf = Vec::new();
-            let none_val: Option<RawEntityHandle> = None;
-            let _ = none_val.serialize_to_writer(&mut buf);
-            DatabaseSerializedFieldBufferOrForeignKey::Pod(SmallVec::from_slice(&buf))
-          }
-        },
+        }
       };
+      let mut writer = component.write_untyped();
       unsafe {
         writer.write_by_small_serialize_data(live_handle, value);
       }
@@ -458,6 +545,269 @@ fn format_replay_summary<T>(msg: &TracingMessage<T>, names: &[String]) -> String
           value
         )
       }
+      DatabaseTracingMessage::NameDefined(name_id, name) => {
+        format!("Defined name \"{}\" as {}", name, name_id)
+      }
+      DatabaseTracingMessage::ComponentSchemaDefined(name_id, schema) => format!(
+        "Defined schema of \"{}\" as {}",
+        lookup(names, *name_id),
+        schema.type_name
+      ),
     },
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use parking_lot::Mutex;
+
+  use super::*;
+  use crate::*;
+
+  #[derive(Debug)]
+  struct TestEvent;
+
+  impl TraceIO for TestEvent {
+    fn write_len(&self) -> usize {
+      0
+    }
+    fn write(&self, _w: &mut impl Write) -> std::io::Result<usize> {
+      Ok(0)
+    }
+    fn read(_source: &mut dyn Read) -> std::io::Result<Self> {
+      Ok(TestEvent)
+    }
+  }
+
+  impl TraceReplayTarget for TestEvent {
+    fn type_discriminant() -> u32 {
+      0
+    }
+    fn is_replay_target(&self) -> bool {
+      true
+    }
+  }
+
+  #[derive(Clone, Default)]
+  struct MemoryTraceWriter {
+    buffer: Arc<Mutex<Vec<u8>>>,
+  }
+
+  impl TraceWriter<TracingMessage<TestEvent>> for MemoryTraceWriter {
+    fn write_header(&self, name_table: &NameTable, type_discriminant: u32) {
+      write_trace_file_header(&mut *self.buffer.lock(), name_table, type_discriminant).unwrap();
+    }
+
+    fn write_message(&self, message: TracingMessage<TestEvent>) {
+      message.write(&mut *self.buffer.lock()).unwrap();
+    }
+  }
+
+  declare_entity!(ReplayTestNode);
+  declare_component!(ReplayTestNodeValue, ReplayTestNode, u32);
+  declare_entity!(ReplayTestLateModel);
+  declare_component!(ReplayTestLateModelValue, ReplayTestLateModel, f32);
+  declare_foreign_key!(
+    ReplayTestLateModelRefNode,
+    ReplayTestLateModel,
+    ReplayTestNode
+  );
+
+  fn collect_living<E: EntitySemantic>(db: &Database) -> Vec<EntityHandle<E>> {
+    db.access_table_dyn(E::entity_id(), |t| {
+      t.iter_entity_idx()
+        .map(|h| unsafe { EntityHandle::from_raw(h) })
+        .collect()
+    })
+  }
+
+  #[test]
+  fn test_tracing_non_empty_database_and_late_declaration() {
+    let db = Database::default();
+    db.declare_entity::<ReplayTestNode>();
+
+    let mut nodes = db.entity_writer::<ReplayTestNode>();
+    let removed = nodes.new_entity(|w| w);
+    let _node_a = nodes.new_entity(|w| w);
+    nodes.delete_entity(removed);
+    drop(nodes);
+
+    let writer = MemoryTraceWriter::default();
+    start_tracing(&db, writer.clone());
+
+    // declare component and entity after the tracing started
+    db.access_table::<ReplayTestNode, _>(|t| {
+      t.clone().declare_component::<ReplayTestNodeValue>();
+    });
+    db.declare_entity::<ReplayTestLateModel>()
+      .declare_component::<ReplayTestLateModelValue>()
+      .declare_foreign_key::<ReplayTestLateModelRefNode>();
+
+    let node_b = db
+      .entity_writer::<ReplayTestNode>()
+      .new_entity(|w| w.write::<ReplayTestNodeValue>(&42));
+    db.entity_writer::<ReplayTestLateModel>().new_entity(|w| {
+      w.write::<ReplayTestLateModelValue>(&1.5)
+        .write::<ReplayTestLateModelRefNode>(&node_b.some_handle())
+    });
+
+    let buffer = writer.buffer.lock().clone();
+    let mut reader = buffer.as_slice();
+    let (mut name_table, _) = read_trace_file_header(&mut reader).unwrap();
+    let records = read_records_for::<TestEvent>(&mut reader, &mut name_table).unwrap();
+
+    let target = Database::default();
+    target
+      .declare_entity::<ReplayTestNode>()
+      .declare_component::<ReplayTestNodeValue>();
+    target
+      .declare_entity::<ReplayTestLateModel>()
+      .declare_component::<ReplayTestLateModelValue>()
+      .declare_foreign_key::<ReplayTestLateModelRefNode>();
+
+    let mut state = ReplayState::new(records, name_table);
+    restart_and_run_to(&mut state, &target, usize::MAX);
+
+    assert_eq!(collect_living::<ReplayTestNode>(&target).len(), 2);
+    let models = collect_living::<ReplayTestLateModel>(&target);
+    assert_eq!(models.len(), 1);
+    assert_eq!(
+      target
+        .read::<ReplayTestLateModelValue>()
+        .get_value(models[0]),
+      Some(1.5)
+    );
+    let node = target
+      .read_foreign_key::<ReplayTestLateModelRefNode>()
+      .get(models[0])
+      .unwrap();
+    assert_eq!(
+      target.read::<ReplayTestNodeValue>().get_value(node),
+      Some(42)
+    );
+  }
+
+  declare_entity!(ReplayTestMigrationNode);
+  declare_component!(ReplayTestMigrationOldValue, ReplayTestMigrationNode, u32);
+  declare_component!(ReplayTestMigrationValue, ReplayTestMigrationNode, f64);
+
+  #[test]
+  fn test_replay_with_migration() {
+    let db = Database::default();
+    db.declare_entity::<ReplayTestMigrationNode>();
+
+    let writer = MemoryTraceWriter::default();
+    start_tracing(&db, writer.clone());
+
+    // the schema of late declared component is recorded by message
+    db.access_table::<ReplayTestMigrationNode, _>(|t| {
+      t.clone().declare_component::<ReplayTestMigrationOldValue>();
+    });
+    db.entity_writer::<ReplayTestMigrationNode>()
+      .new_entity(|w| w.write::<ReplayTestMigrationOldValue>(&3));
+
+    let buffer = writer.buffer.lock().clone();
+    let mut reader = buffer.as_slice();
+    let (mut name_table, _) = read_trace_file_header(&mut reader).unwrap();
+    let records = read_records_for::<TestEvent>(&mut reader, &mut name_table).unwrap();
+    let old_name_id = name_table
+      .names
+      .iter()
+      .position(|n| n == ReplayTestMigrationOldValue::unique_name())
+      .unwrap() as u32;
+    assert_eq!(name_table.component_schemas[&old_name_id].type_name, "u32");
+
+    let target = Database::default();
+    target
+      .declare_entity::<ReplayTestMigrationNode>()
+      .declare_component::<ReplayTestMigrationValue>();
+
+    let migration = DatabaseMigration::default()
+      .rename_component(
+        ReplayTestMigrationOldValue::unique_name(),
+        ReplayTestMigrationValue::unique_name(),
+      )
+      .convert::<ReplayTestMigrationValue, u32>(|v| v as f64 + 0.5);
+    let mut state = ReplayState::new(records, name_table).with_migration(migration);
+    restart_and_run_to(&mut state, &target, usize::MAX);
+
+    let nodes = collect_living::<ReplayTestMigrationNode>(&target);
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(
+      target
+        .read::<ReplayTestMigrationValue>()
+        .get_value(nodes[0]),
+      Some(3.5)
+    );
+  }
+
+  #[test]
+  fn test_replay_mixed_layout_traces_with_migration() {
+    let record = |declare: fn(&Database), write: fn(&Database)| {
+      let db = Database::default();
+      db.declare_entity::<ReplayTestMigrationNode>();
+      declare(&db);
+      let writer = MemoryTraceWriter::default();
+      start_tracing(&db, writer.clone());
+      write(&db);
+
+      let buffer = writer.buffer.lock().clone();
+      let mut reader = buffer.as_slice();
+      let (mut name_table, _) = read_trace_file_header(&mut reader).unwrap();
+      let records = read_records_for::<TestEvent>(&mut reader, &mut name_table).unwrap();
+      (records, name_table)
+    };
+
+    let old_trace = record(
+      |db| {
+        db.access_table::<ReplayTestMigrationNode, _>(|t| {
+          t.clone().declare_component::<ReplayTestMigrationOldValue>();
+        });
+      },
+      |db| {
+        db.entity_writer::<ReplayTestMigrationNode>()
+          .new_entity(|w| w.write::<ReplayTestMigrationOldValue>(&3));
+      },
+    );
+    // written by the new build, the value is already in the new layout
+    let new_trace = record(
+      |db| {
+        db.access_table::<ReplayTestMigrationNode, _>(|t| {
+          t.clone().declare_component::<ReplayTestMigrationValue>();
+        });
+      },
+      |db| {
+        db.entity_writer::<ReplayTestMigrationNode>()
+          .new_entity(|w| w.write::<ReplayTestMigrationValue>(&2.25));
+      },
+    );
+
+    let target = Database::default();
+    target
+      .declare_entity::<ReplayTestMigrationNode>()
+      .declare_component::<ReplayTestMigrationValue>();
+
+    let migration = DatabaseMigration::default()
+      .rename_component(
+        ReplayTestMigrationOldValue::unique_name(),
+        ReplayTestMigrationValue::unique_name(),
+      )
+      .convert::<ReplayTestMigrationValue, u32>(|v| v as f64 + 0.5);
+
+    for (records, name_table) in [old_trace, new_trace] {
+      let mut state = ReplayState::new(records, name_table).with_migration(migration.clone());
+      restart_and_run_to(&mut state, &target, usize::MAX);
+    }
+
+    let values = target.read::<ReplayTestMigrationValue>();
+    let mut values: Vec<_> = collect_living::<ReplayTestMigrationNode>(&target)
+      .into_iter()
+      .map(|node| values.get_value(node).unwrap())
+      .collect();
+    values.sort_by(f64::total_cmp);
+    // the converter only applies to the old layout data
+    assert_eq!(values, [2.25, 3.5]);
+  }
+}
@@ -0,0 +1,363 @@
+use std::{
+  borrow::Cow,
+  io::{Read, Write},
+  sync::Arc,
+};
+
+use database::*;
+use fast_hash_collection::*;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use crate::message::*;
+
+/// The persistent form of a component's [DataTypeMetaInfo]. The meta info itself holds
+/// function pointers and static reflection data that can not be stored, so only the parts
+/// that could identify the data layout are kept. These are used to detect layout change
+/// when loading the trace or snapshot written by an older build.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ComponentTypeSchema {
+  /// the full type name of the component data, including the generic parameters
+  pub type_name: String,
+  /// the in memory size of the component data, zero if not available
+  pub layout_size: u32,
+  pub is_foreign_key: bool,
+}
+
+impl ComponentTypeSchema {
+  pub fn from_meta(meta: &DataTypeMetaInfo, is_foreign_key: bool) -> Self {
+    Self {
+      type_name: meta.shape.to_string(),
+      layout_size: meta
+        .shape
+        .layout
+        .sized_layout()
+        .map(|layout| layout.size() as u32)
+        .unwrap_or(0),
+      is_foreign_key,
+    }
+  }
+
+  pub fn from_component(component: &ComponentUntyped) -> Self {
+    Self::from_meta(&component.data_meta, component.as_foreign_key.is_some())
+  }
+
+  /// Whether the data written with `self` can be directly read as the `other`.
+  pub fn is_compatible_with(&self, other: &Self) -> bool {
+    self == other
+  }
+
+  pub(crate) fn binary_len(&self) -> usize {
+    2 + self.type_name.len() + 4 + 1
+  }
+
+  pub(crate) fn write_binary(&self, w: &mut impl Write) -> std::io::Result<()> {
+    write_name_table_entry(w, &self.type_name)?;
+    write_u32_le(w, self.layout_size)?;
+    w.write_all(&[self.is_foreign_key as u8])
+  }
+
+  pub(crate) fn read_binary(source: &mut (impl Read + ?Sized)) -> std::io::Result<Self> {
+    let type_name = read_name_table_entry(source)?;
+    let layout_size = read_u32_le(source)?;
+    let mut fk = [0u8; 1];
+    source.read_exact(&mut fk)?;
+    Ok(Self {
+      type_name,
+      layout_size,
+      is_foreign_key: fk[0] != 0,
+    })
+  }
+}
+
+/// Convert the recorded component data into the current layout. The input is the recorded
+/// schema and the serialized data, return None if the data can not be converted, in this
+/// case the value is skipped. It's only called when the recorded schema is unknown or not
+/// compatible with the current one.
+pub type ComponentDataConverter =
+  Arc<dyn Fn(Option<&ComponentTypeSchema>, &[u8]) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Describes how the data recorded by an older build maps to the current database
+/// declaration. Used when replaying traces and restoring snapshots.
+///
+/// - The renamed entity or component is mapped by the old name to the new name.
+/// - The removed entity or component (not exist in the live database) is skipped.
+/// - The component that not exist in the record is filled with the registered default value,
+///   or the component's own default if no value is registered.
+/// - The component whose data layout is changed is converted by the registered converter. If
+///   no converter is registered, the recorded value is skipped with a warning.
+#[derive(Default, Clone)]
+pub struct DatabaseMigration {
+  entity_renames: FastHashMap<String, String>,
+  component_renames: FastHashMap<String, String>,
+  converters: FastHashMap<String, ComponentDataConverter>,
+  default_values: FastHashMap<String, Vec<u8>>,
+}
+
+impl DatabaseMigration {
+  pub fn rename_entity(mut self, old_name: impl Into<String>, new_name: impl Into<String>) -> Self {
+    self.entity_renames.insert(old_name.into(), new_name.into());
+    self
+  }
+
+  pub fn rename_component(
+    mut self,
+    old_name: impl Into<String>,
+    new_name: impl Into<String>,
+  ) -> Self {
+    self
+      .component_renames
+      .insert(old_name.into(), new_name.into());
+    self
+  }
+
+  /// Fill the value for the component `C` if it is not exist in the record.
+  pub fn fill_default<C: ComponentSemantic>(mut self, value: C::Data) -> Self {
+    let mut buffer = Vec::new();
+    let _ = value.serialize_to_writer(&mut buffer);
+    self
+      .default_values
+      .insert(C::unique_name().to_string(), buffer);
+    self
+  }
+
+  /// Register the converter for the component (by the current name) from the recorded
+  /// serialized data.
+  pub fn convert_with(
+    mut self,
+    component_name: impl Into<String>,
+    converter: impl Fn(Option<&ComponentTypeSchema>, &[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+  ) -> Self {
+    self
+      .converters
+      .insert(component_name.into(), Arc::new(converter));
+    self
+  }
+
+  /// Typed version of [Self::convert_with], the recorded data is deserialized as `Old`. The
+  /// data recorded with another layout than `Old` is skipped, the record without schema is
+  /// assumed to be `Old`.
+  pub fn convert<C: ComponentSemantic, Old: DataBaseDataType>(
+    self,
+    converter: impl Fn(Old) -> C::Data + Send + Sync + 'static,
+  ) -> Self {
+    let old_schema = ComponentTypeSchema::from_meta(&DataTypeMetaInfo::from_type::<Old>(), false);
+    self.convert_with(C::unique_name(), move |recorded, data| {
+      if let Some(recorded) = recorded
+        && (recorded.type_name != old_schema.type_name
+          || recorded.layout_size != old_schema.layout_size)
+      {
+        return None;
+      }
+      let mut old = Old::default();
+      old.deserialize_from_reader(&mut &*data)?;
+      let mut buffer = Vec::new();
+      converter(old).serialize_to_writer(&mut buffer)?;
+      Some(buffer)
+    })
+  }
+
+  pub fn map_entity_name<'a>(&'a self, name: &'a str) -> &'a str {
+    self
+      .entity_renames
+      .get(name)
+      .map(|s| s.as_str())
+      .unwrap_or(name)
+  }
+
+  pub fn map_component_name<'a>(&'a self, name: &'a str) -> &'a str {
+    self
+      .component_renames
+      .get(name)
+      .map(|s| s.as_str())
+      .unwrap_or(name)
+  }
+
+  /// Migrate the recorded pod data of the component (by the current name) into the
+  /// current layout. Return None if the data should be skipped.
+  pub fn migrate_pod_data<'a>(
+    &self,
+    component_name: &str,
+    recorded: Option<&ComponentTypeSchema>,
+    current: &ComponentTypeSchema,
+    data: &'a [u8],
+  ) -> Option<Cow<'a, [u8]>> {
+    // the data already in the current layout is never converted
+    if let Some(recorded) = recorded
+      && recorded.is_compatible_with(current)
+    {
+      return Some(Cow::Borrowed(data));
+    }
+
+    if let Some(converter) = self.converters.get(component_name) {
+      let converted = converter(recorded, data);
+      if converted.is_none() {
+        log::warn!("failed to convert the data of component \"{component_name}\", skipped");
+      }
+      return converted.map(Cow::Owned);
+    }
+
+    // the record without schema is written by an old build, we can only assume it's unchanged
+    let Some(recorded) = recorded else {
+      return Some(Cow::Borrowed(data));
+    };
+
+    log::warn!(
+      "the data layout of component \"{}\" is changed from {:?} to {:?} and no converter \
+       is registered, skipped",
+      component_name,
+      recorded,
+      current
+    );
+    None
+  }
+
+  /// The registered default values of the components that not exist in the record,
+  /// the `recorded_names` are the names(before renaming) exist in the record.
+  pub fn default_fills<'a>(
+    &'a self,
+    recorded_names: impl IntoIterator<Item = &'a str>,
+  ) -> FastHashMap<&'a str, &'a [u8]> {
+    if self.default_values.is_empty() {
+      return FastHashMap::default();
+    }
+    let mut fills: FastHashMap<_, _> = self
+      .default_values
+      .iter()
+      .map(|(name, value)| (name.as_str(), value.as_slice()))
+      .collect();
+    for name in recorded_names {
+      fills.remove(self.map_component_name(name));
+    }
+    fills
+  }
+
+  /// Select the default fill values of the components that belong to the table, the name
+  /// lookup is done here so the fill values can be reused for all entities of the table.
+  pub fn table_default_fills(
+    &self,
+    writer: &mut TableWriterUntyped,
+    names: &DBNameMapping,
+    fills: &FastHashMap<&str, &[u8]>,
+  ) -> Vec<(ComponentId, Vec<u8>)> {
+    fills
+      .iter()
+      .filter_map(|(name, value)| {
+        let c_id = *names.components_inv.get(*name)?;
+        writer.get_component_by_id_mut(c_id)?;
+        Some((c_id, value.to_vec()))
+      })
+      .collect()
+  }
+}
+
+/// Write the default fill values(selected by [DatabaseMigration::table_default_fills]) for
+/// the newly created entity.
+pub fn write_default_fills(
+  writer: &mut TableWriterUntyped,
+  handle: RawEntityHandle,
+  fills: &[(ComponentId, Vec<u8>)],
+) {
+  for (c_id, value) in fills {
+    if let Some(component) = writer.get_component_by_id_mut(*c_id) {
+      let value = DatabaseSerializedFieldBufferOrForeignKey::Pod(SmallVec::from_slice(value));
+      unsafe {
+        component.write_component_by_small_serialize_data(handle, value);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::*;
+
+  mod old {
+    use database::*;
+    declare_entity!(MigrationTestOldNode);
+    declare_component!(MigrationTestOldNodeWeight, MigrationTestOldNode, u32);
+    declare_component!(MigrationTestRemoved, MigrationTestOldNode, bool);
+    declare_component!(MigrationTestNodeScale, MigrationTestOldNode, u32);
+  }
+
+  declare_entity!(MigrationTestNode);
+  declare_component!(MigrationTestNodeWeight, MigrationTestNode, u32);
+  declare_component!(MigrationTestNodeScale, MigrationTestNode, f32);
+  declare_component!(MigrationTestNodeAdded, MigrationTestNode, u64);
+
+  fn single_living<E: EntitySemantic>(db: &Database) -> EntityHandle<E> {
+    db.access_table_dyn(E::entity_id(), |t| {
+      let mut iter = t.iter_entity_idx();
+      let handle = iter.next().unwrap();
+      assert!(iter.next().is_none());
+      unsafe { EntityHandle::from_raw(handle) }
+    })
+  }
+
+  #[test]
+  fn test_snapshot_migration() {
+    let db = Database::default();
+    db.declare_entity::<old::MigrationTestOldNode>()
+      .declare_component::<old::MigrationTestOldNodeWeight>()
+      .declare_component::<old::MigrationTestRemoved>()
+      .declare_component::<old::MigrationTestNodeScale>();
+    db.entity_writer::<old::MigrationTestOldNode>()
+      .new_entity(|w| {
+        w.write::<old::MigrationTestOldNodeWeight>(&7)
+          .write::<old::MigrationTestRemoved>(&true)
+          .write::<old::MigrationTestNodeScale>(&3)
+      });
+    let snapshot = create_database_snapshot(&db);
+
+    let target = || {
+      let db = Database::default();
+      db.declare_entity::<MigrationTestNode>()
+        .declare_component::<MigrationTestNodeWeight>()
+        .declare_component::<MigrationTestNodeScale>()
+        .declare_component::<MigrationTestNodeAdded>();
+      db
+    };
+
+    let migration = DatabaseMigration::default()
+      .rename_entity(
+        old::MigrationTestOldNode::unique_name(),
+        MigrationTestNode::unique_name(),
+      )
+      .rename_component(
+        old::MigrationTestOldNodeWeight::unique_name(),
+        MigrationTestNodeWeight::unique_name(),
+      )
+      .rename_component(
+        old::MigrationTestNodeScale::unique_name(),
+        MigrationTestNodeScale::unique_name(),
+      )
+      .fill_default::<MigrationTestNodeAdded>(42);
+
+    // the layout changed component is skipped without converter
+    let db = target();
+    restore_database_snapshot_with_migration(&db, &snapshot, &migration).unwrap();
+    let node = single_living::<MigrationTestNode>(&db);
+    assert_eq!(
+      db.read::<MigrationTestNodeWeight>().get_value(node),
+      Some(7)
+    );
+    assert_eq!(
+      db.read::<MigrationTestNodeScale>().get_value(node),
+      Some(0.)
+    );
+    assert_eq!(
+      db.read::<MigrationTestNodeAdded>().get_value(node),
+      Some(42)
+    );
+
+    let migration = migration.convert::<MigrationTestNodeScale, u32>(|v| v as f32 * 0.5);
+    let db = target();
+    restore_database_snapshot_with_migration(&db, &snapshot, &migration).unwrap();
+    let node = single_living::<MigrationTestNode>(&db);
+    assert_eq!(
+      db.read::<MigrationTestNodeScale>().get_value(node),
+      Some(1.5)
+    );
+  }
+}
@@ -0,0 +1,610 @@
+use std::io::{Read, Write};
+
+use database::*;
+use fast_hash_collection::*;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use crate::{message::*, readable::*, schema::*};
+
+pub(crate) const SNAPSHOT_MAGIC: &[u8; 4] = b"RSNP";
+pub const SNAPSHOT_VERSION: u32 = 2;
+/// The version before the component schemas are stored.
+pub(crate) const SNAPSHOT_VERSION_WITHOUT_SCHEMA: u32 = 1;
+
+/// The full state of a database at some point, all the tables and components are
+/// identified by their unique name, so the snapshot can be loaded into another
+/// database(for example in another process) that has same entity and component declared.
+///
+/// The `V` is the component data representation: the binary form uses the msgpack bytes
+/// produced by [DataBaseDataType::serialize_to_writer], the human-readable form uses the
+/// json value losslessly transcoded from the msgpack bytes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DatabaseSnapshot<V = Vec<u8>> {
+  pub version: u32,
+  pub tables: Vec<TableSnapshot<V>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TableSnapshot<V> {
+  /// the unique name of the entity
+  pub name: String,
+  /// the original (index, generation) of all living entities, the foreign keys
+  /// are referencing these handles.
+  pub entities: Vec<(u32, u64)>,
+  pub components: Vec<ComponentSnapshot<V>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ComponentSnapshot<V> {
+  /// the unique name of the component
+  pub name: String,
+  /// the values are in the same order as [TableSnapshot::entities]
+  pub values: ComponentSnapshotValues<V>,
+  /// the data type schema when the snapshot is created, None if created by the old version.
+  #[serde(default)]
+  pub schema: Option<ComponentTypeSchema>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ComponentSnapshotValues<V> {
+  Pod(Vec<V>),
+  ForeignKey {
+    /// the unique name of the referenced entity
+    foreign_entity: String,
+    values: Vec<Option<(u32, u64)>>,
+  },
+}
+
+/// Per-entity-type handle map: EntityId → (snapshot handle → live handle).
+pub type SnapshotHandleMapping =
+  FastHashMap<EntityId, FastHashMap<RawEntityHandle, RawEntityHandle>>;
+
+fn handle_to_pair(handle: RawEntityHandle) -> (u32, u64) {
+  (handle.alloc_index(), handle.generation())
+}
+
+fn pair_to_handle((index, generation): (u32, u64)) -> RawEntityHandle {
+  RawEntityHandle::create_only_for_testing_with_gen(index as usize, generation)
+}
+
+fn invalid_data(msg: impl Into<String>) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Capture all tables, components and foreign keys of the database.
+pub fn create_database_snapshot(db: &Database) -> DatabaseSnapshot {
+  let tables = db.tables.read();
+  let names = db.name_mapping.read();
+
+  let mut table_snapshots: Vec<_> = tables
+    .values()
+    .map(|table| {
+      let entities: Vec<_> = table.iter_entity_idx().collect();
+
+      let mut components = Vec::new();
+      table.visit_components(|component| {
+        let reader = component.read_untyped();
+        let values = if let Some(foreign_entity) = component.as_foreign_key {
+          let values = entities
+            .iter()
+            .map(|handle| {
+              let ptr = reader.get(*handle).unwrap();
+              let fk = unsafe { (ptr as *const ForeignKeyComponentData).read() };
+              fk.map(handle_to_pair)
+            })
+            .collect();
+          ComponentSnapshotValues::ForeignKey {
+            foreign_entity: names.entities[&foreign_entity].clone(),
+            values,
+          }
+        } else {
+          let values = entities
+            .iter()
+            .map(|handle| {
+              let (_, data) = reader
+                .get_without_generation_check_dyn_data_type(handle.alloc_index())
+                .unwrap();
+              data.serialize_into_buffer().to_vec()
+            })
+            .collect();
+          ComponentSnapshotValues::Pod(values)
+        };
+
+        components.push(ComponentSnapshot {
+          name: component.name.clone(),
+          values,
+          schema: Some(ComponentTypeSchema::from_component(component)),
+        });
+      });
+      // make the snapshot content stable
+      components.sort_by(|a, b| a.name.cmp(&b.name));
+
+      TableSnapshot {
+        name: table.name().to_string(),
+        entities: entities.into_iter().map(handle_to_pair).collect(),
+        components,
+      }
+    })
+    .collect();
+  table_snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+
+  DatabaseSnapshot {
+    version: SNAPSHOT_VERSION,
+    tables: table_snapshots,
+  }
+}
+
+/// Create all the entities in the snapshot into the database, and write their component data.
+///
+/// The database must have all entities and components in the snapshot declared, the unknown
+/// entity or component in the snapshot will be skipped with a warning. The existing entities
+/// in the database are not affected.
+///
+/// The snapshot is validated before any modification, if an error is returned, the database
+/// is not changed.
+///
+/// Returns the handle mapping from the snapshot to the live database.
+pub fn restore_database_snapshot(
+  db: &Database,
+  snapshot: &DatabaseSnapshot,
+) -> std::io::Result<SnapshotHandleMapping> {
+  restore_database_snapshot_with_migration(db, snapshot, &DatabaseMigration::default())
+}
+
+/// Same as [restore_database_snapshot], but the snapshot created by an older build is
+/// mapped into the live database by the migration, see [DatabaseMigration].
+pub fn restore_database_snapshot_with_migration(
+  db: &Database,
+  snapshot: &DatabaseSnapshot,
+  migration: &DatabaseMigration,
+) -> std::io::Result<SnapshotHandleMapping> {
+  check_snapshot_version(snapshot.version)?;
+  validate_snapshot(db, snapshot, migration)?;
+
+  let mut handle_map = SnapshotHandleMapping::default();
+
+  let fills = migration.default_fills(
+    snapshot
+      .tables
+      .iter()
+      .flat_map(|t| t.components.iter().map(|c| c.name.as_str())),
+  );
+
+  // create all entities first, so that the foreign keys can be remapped
+  for table in &snapshot.tables {
+    let entity_name = migration.map_entity_name(&table.name);
+    let names = db.name_mapping.read();
+    let Some(e_id) = names.entities_inv.get(entity_name).copied() else {
+      log::warn!(
+        "entity \"{}\" not found in live database, skipped",
+        entity_name
+      );
+      continue;
+    };
+    let mut writer = db.entity_writer_untyped_dyn(e_id);
+    writer.notify_reserve_changes(table.entities.len());
+    let table_fills = migration.table_default_fills(&mut writer, &names, &fills);
+    let mapping = handle_map.entry(e_id).or_default();
+    for handle in &table.entities {
+      let live = writer.new_entity(|w| w);
+      write_default_fills(&mut writer, live, &table_fills);
+      mapping.insert(pair_to_handle(*handle), live);
+    }
+  }
+
+  for table in &snapshot.tables {
+    let names = db.name_mapping.read();
+    let Some(e_id) = names
+      .entities_inv
+      .get(migration.map_entity_name(&table.name))
+      .copied()
+    else {
+      continue;
+    };
+    let mapping = &handle_map[&e_id];
+
+    let mut writer = db.entity_writer_untyped_dyn(e_id);
+    for component in &table.components {
+      let component_name = migration.map_component_name(&component.name);
+      let Some(c_id) = names.components_inv.get(component_name).copied() else {
+        log::warn!(
+          "component \"{}\" not found in live database, skipped",
+          component_name
+        );
+        continue;
+      };
+      let component_writer = writer
+        .get_component_by_id_mut(c_id)
+        .expect("checked by validate_snapshot");
+      let current_schema = {
+        let tables = db.tables.read();
+        tables[&e_id]
+          .access_component(c_id, ComponentTypeSchema::from_component)
+          .unwrap()
+      };
+
+      match &component.values {
+        ComponentSnapshotValues::Pod(values) => {
+          for (handle, value) in table.entities.iter().zip(values) {
+            let live = mapping[&pair_to_handle(*handle)];
+            let Some(value) = migration.migrate_pod_data(
+              component_name,
+              component.schema.as_ref(),
+              &current_schema,
+              value,
+            ) else {
+              continue;
+            };
+            let value =
+              DatabaseSerializedFieldBufferOrForeignKey::Pod(SmallVec::from_slice(&value));
+            unsafe {
+              component_writer.write_component_by_small_serialize_data(live, value);
+            }
+          }
+        }
+        ComponentSnapshotValues::ForeignKey {
+          foreign_entity,
+          values,
+        } => {
+          if !current_schema.is_foreign_key {
+            log::warn!(
+              "component \"{}\" is not foreign key in live database, skipped",
+              component_name
+            );
+            continue;
+          }
+          let foreign_entity = migration.map_entity_name(foreign_entity);
+          let foreign_mapping = handle_map.get(&names.entities_inv[foreign_entity]);
+
+          for (handle, value) in table.entities.iter().zip(values) {
+            let live = mapping[&pair_to_handle(*handle)];
+            let value: ForeignKeyComponentData =
+              value.map(|target| foreign_mapping.unwrap()[&pair_to_handle(target)]);
+            unsafe {
+              component_writer
+                .write_component(live, &value as *const ForeignKeyComponentData as *const ());
+            }
+          }
+        }
+      }
+    }
+  }
+
+  Ok(handle_map)
+}
+
+/// Check all the errors that [restore_database_snapshot_with_migration] may meet.
+fn validate_snapshot(
+  db: &Database,
+  snapshot: &DatabaseSnapshot,
+  migration: &DatabaseMigration,
+) -> std::io::Result<()> {
+  let names = db.name_mapping.read();
+  let tables = db.tables.read();
+
+  // the snapshot handles that will be created in each live table
+  let mut created: FastHashMap<EntityId, FastHashSet<RawEntityHandle>> = Default::default();
+  for table in &snapshot.tables {
+    if let Some(e_id) = names
+      .entities_inv
+      .get(migration.map_entity_name(&table.name))
+    {
+      let handles = table.entities.iter().copied().map(pair_to_handle);
+      created.entry(*e_id).or_default().extend(handles);
+    }
+  }
+
+  for table in &snapshot.tables {
+    let Some(e_id) = names
+      .entities_inv
+      .get(migration.map_entity_name(&table.name))
+    else {
+      continue;
+    };
+    for component in &table.components {
+      let component_name = migration.map_component_name(&component.name);
+      let Some(c_id) = names.components_inv.get(component_name).copied() else {
+        continue;
+      };
+      let Some(is_foreign_key) =
+        tables[e_id].access_component(c_id, |c| c.as_foreign_key.is_some())
+      else {
+        return Err(invalid_data(format!(
+          "component \"{}\" is not belong to entity \"{}\"",
+          component_name, table.name
+        )));
+      };
+
+      match &component.values {
+        ComponentSnapshotValues::Pod(values) => {
+          check_value_count(table, component, values.len())?;
+        }
+        ComponentSnapshotValues::ForeignKey {
+          foreign_entity,
+          values,
+        } => {
+          check_value_count(table, component, values.len())?;
+          if !is_foreign_key {
+            continue;
+          }
+          let foreign_entity = migration.map_entity_name(foreign_entity);
+          let foreign_e_id = names
+            .entities_inv
+            .get(foreign_entity)
+            .ok_or_else(|| invalid_data(format!("entity \"{foreign_entity}\" not found")))?;
+          let targets = created.get(foreign_e_id);
+          for target in values.iter().flatten() {
+            let target = pair_to_handle(*target);
+            if !targets.is_some_and(|t| t.contains(&target)) {
+              return Err(invalid_data(format!(
+                "foreign key \"{}\" references a not exist entity {:?}",
+                component.name, target
+              )));
+            }
+          }
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+fn check_snapshot_version(version: u32) -> std::io::Result<()> {
+  if version != SNAPSHOT_VERSION && version != SNAPSHOT_VERSION_WITHOUT_SCHEMA {
+    return Err(invalid_data(format!(
+      "unsupported snapshot version: {}",
+      version
+    )));
+  }
+  Ok(())
+}
+
+fn check_value_count<V>(
+  table: &TableSnapshot<V>,
+  component: &ComponentSnapshot<V>,
+  count: usize,
+) -> std::io::Result<()> {
+  if count != table.entities.len() {
+    return Err(invalid_data(format!(
+      "component \"{}\" value count {} mismatch with entity count {}",
+      component.name,
+      count,
+      table.entities.len()
+    )));
+  }
+  Ok(())
+}
+
+impl<V> DatabaseSnapshot<V> {
+  pub fn map_pod<R>(
+    self,
+    mut f: impl FnMut(V) -> std::io::Result<R>,
+  ) -> std::io::Result<DatabaseSnapshot<R>> {
+    let tables = self
+      .tables
+      .into_iter()
+      .map(|table| {
+        let components = table
+          .components
+          .into_iter()
+          .map(|component| {
+            let values = match component.values {
+              ComponentSnapshotValues::Pod(values) => ComponentSnapshotValues::Pod(
+                values.into_iter().map(&mut f).collect::<Result<_, _>>()?,
+              ),
+              ComponentSnapshotValues::ForeignKey {
+                foreign_entity,
+                values,
+              } => ComponentSnapshotValues::ForeignKey {
+                foreign_entity,
+                values,
+              },
+            };
+            Ok(ComponentSnapshot {
+              name: component.name,
+              values,
+              schema: component.schema,
+            })
+          })
+          .collect::<std::io::Result<_>>()?;
+        Ok(TableSnapshot {
+          name: table.name,
+          entities: table.entities,
+          components,
+        })
+      })
+      .collect::<std::io::Result<_>>()?;
+
+    Ok(DatabaseSnapshot {
+      version: self.version,
+      tables,
+    })
+  }
+}
+
+impl DatabaseSnapshot {
+  /// Write the snapshot in binary form: magic, version, then the msgpack encoded body.
+  pub fn write_binary(&self, w: &mut impl Write) -> std::io::Result<()> {
+    w.write_all(SNAPSHOT_MAGIC)?;
+    write_u32_le(w, self.version)?;
+    rmp_serde::encode::write(w, self).map_err(|e| invalid_data(e.to_string()))
+  }
+
+  pub fn read_binary(source: &mut impl Read) -> std::io::Result<Self> {
+    let mut magic_buf = [0u8; 4];
+    source.read_exact(&mut magic_buf)?;
+    if &magic_buf != SNAPSHOT_MAGIC {
+      return Err(invalid_data("invalid snapshot magic"));
+    }
+    check_snapshot_version(read_u32_le(source)?)?;
+    rmp_serde::from_read(source).map_err(|e| invalid_data(e.to_string()))
+  }
+
+  /// Write the snapshot in human-readable json form, the component data is transcoded
+  /// from msgpack to json.
+  pub fn write_readable(&self, w: &mut impl Write) -> std::io::Result<()> {
+    let readable = self.clone().map_pod(|data| msgpack_to_readable(&data))?;
+    serde_json::to_writer_pretty(w, &readable).map_err(std::io::Error::other)
+  }
+
+  pub fn read_readable(source: &mut impl Read) -> std::io::Result<Self> {
+    let readable: DatabaseSnapshot<serde_json::Value> =
+      serde_json::from_reader(source).map_err(|e| invalid_data(e.to_string()))?;
+    check_snapshot_version(readable.version)?;
+    readable.map_pod(|value| readable_to_msgpack(&value))
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotFormat {
+  Binary,
+  Readable,
+}
+
+/// Save the whole database into a file.
+pub fn save_database_snapshot(
+  db: &Database,
+  path: impl AsRef<std::path::Path>,
+  format: SnapshotFormat,
+) -> std::io::Result<()> {
+  let snapshot = create_database_snapshot(db);
+  let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+  match format {
+    SnapshotFormat::Binary => snapshot.write_binary(&mut file)?,
+    SnapshotFormat::Readable => snapshot.write_readable(&mut file)?,
+  }
+  file.flush()
+}
+
+/// Load a snapshot file(the format is detected by the file content) into the database.
+///
+/// See [restore_database_snapshot] for details.
+pub fn load_database_snapshot(
+  db: &Database,
+  path: impl AsRef<std::path::Path>,
+) -> std::io::Result<SnapshotHandleMapping> {
+  let content = std::fs::read(path)?;
+  let snapshot = if content.starts_with(SNAPSHOT_MAGIC) {
+    DatabaseSnapshot::read_binary(&mut content.as_slice())?
+  } else {
+    DatabaseSnapshot::read_readable(&mut content.as_slice())?
+  };
+  restore_database_snapshot(db, &snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  declare_entity!(SnapshotTestNode);
+  declare_component!(SnapshotTestNodeValue, SnapshotTestNode, (f32, u32));
+  declare_component!(SnapshotTestNodeName, SnapshotTestNode, String);
+  declare_entity!(SnapshotTestModel);
+  declare_foreign_key!(
+    SnapshotTestModelRefNode,
+    SnapshotTestModel,
+    SnapshotTestNode
+  );
+
+  fn create_db() -> Database {
+    let db = Database::default();
+    db.declare_entity::<SnapshotTestNode>()
+      .declare_component::<SnapshotTestNodeValue>()
+      .declare_component::<SnapshotTestNodeName>();
+    db.declare_entity::<SnapshotTestModel>()
+      .declare_foreign_key::<SnapshotTestModelRefNode>();
+    db
+  }
+
+  fn check_restored(db: &Database, mapping: &SnapshotHandleMapping, models: &[RawEntityHandle]) {
+    let node_value = db.read::<SnapshotTestNodeValue>();
+    let node_name = db.read::<SnapshotTestNodeName>();
+    let model_ref = db.read_foreign_key::<SnapshotTestModelRefNode>();
+
+    let model_mapping = &mapping[&SnapshotTestModel::entity_id()];
+    assert_eq!(model_mapping.len(), 2);
+
+    let model = unsafe { EntityHandle::<SnapshotTestModel>::from_raw(model_mapping[&models[0]]) };
+    let node = model_ref.get(model).unwrap();
+    assert_eq!(node_value.get_value(node), Some((1.5, 2)));
+    assert_eq!(node_name.get_value(node).as_deref(), Some("node b"));
+
+    let model = unsafe { EntityHandle::<SnapshotTestModel>::from_raw(model_mapping[&models[1]]) };
+    assert_eq!(model_ref.get(model), None);
+  }
+
+  #[test]
+  fn test_snapshot_round_trip() {
+    let db = create_db();
+
+    let (model_a, model_b) = {
+      let mut nodes = db.entity_writer::<SnapshotTestNode>();
+      let a = nodes.new_entity(|w| w.write::<SnapshotTestNodeName>(&"node a".into()));
+      let b = nodes.new_entity(|w| {
+        w.write::<SnapshotTestNodeValue>(&(1.5, 2))
+          .write::<SnapshotTestNodeName>(&"node b".into())
+      });
+      // make the handle not trivial
+      nodes.delete_entity(a);
+      drop(nodes);
+
+      let mut models = db.entity_writer::<SnapshotTestModel>();
+      let model_a = models.new_entity(|w| w.write::<SnapshotTestModelRefNode>(&b.some_handle()));
+      let model_b = models.new_entity(|w| w);
+      (model_a.into_raw(), model_b.into_raw())
+    };
+    let models = [model_a, model_b];
+
+    let snapshot = create_database_snapshot(&db);
+
+    let mut binary = Vec::new();
+    snapshot.write_binary(&mut binary).unwrap();
+    let from_binary = DatabaseSnapshot::read_binary(&mut binary.as_slice()).unwrap();
+    assert_eq!(from_binary, snapshot);
+
+    let target = create_db();
+    let mapping = restore_database_snapshot(&target, &from_binary).unwrap();
+    check_restored(&target, &mapping, &models);
+
+    let mut readable = Vec::new();
+    snapshot.write_readable(&mut readable).unwrap();
+    let from_readable = DatabaseSnapshot::read_readable(&mut readable.as_slice()).unwrap();
+    // the msgpack data is transcoded without loss
+    assert_eq!(from_readable, snapshot);
+
+    let target = create_db();
+    let mapping = restore_database_snapshot(&target, &from_readable).unwrap();
+    check_restored(&target, &mapping, &models);
+  }
+
+  #[test]
+  fn test_snapshot_restore_is_atomic() {
+    let db = create_db();
+    let node = db
+      .entity_writer::<SnapshotTestNode>()
+      .new_entity(|w| w.write::<SnapshotTestNodeValue>(&(1., 1)));
+    db.entity_writer::<SnapshotTestModel>()
+      .new_entity(|w| w.write::<SnapshotTestModelRefNode>(&node.some_handle()));
+
+    let mut snapshot = create_database_snapshot(&db);
+    // break the foreign key of the last table
+    let model_table = snapshot
+      .tables
+      .iter_mut()
+      .find(|t| {
+        t.name == db.access_table_dyn(SnapshotTestModel::entity_id(), |t| t.name().to_string())
+      })
+      .unwrap();
+    let ComponentSnapshotValues::ForeignKey { values, .. } = &mut model_table.components[0].values
+    else {
+      unreachable!()
+    };
+    values[0] = Some((100, 0));
+
+    let target = create_db();
+    assert!(restore_database_snapshot(&target, &snapshot).is_err());
+    let restored = create_database_snapshot(&target);
+    assert!(restored.tables.iter().all(|t| t.entities.is_empty()));
+  }
+}
@@ -96,6 +96,7 @@ mod tests {
       names: vec!["TestEntity".into()],
       entity_name_to_id: FastHashMap::default(),
       component_name_to_id: FastHashMap::default(),
+      component_schemas: FastHashMap::default(),
     };
     let writer = FileTraceWriter::<TracingMessage<()>>::new(&tmp);
     writer.write_header(&name_table, 0);
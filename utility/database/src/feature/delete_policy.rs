@@ -0,0 +1,531 @@
+use std::sync::Weak;
+
+use crate::*;
+
+/// Describes what happens to the referencing entities when the referenced entity is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForeignKeyDeletePolicy {
+  /// Do nothing, the referential integrity should be guaranteed by the upper level
+  /// implementations. This is the default behavior.
+  #[default]
+  NoAction,
+  /// The deletion fails if the entity is still referenced.
+  Restrict,
+  /// The referencing entities are deleted as well, recursively.
+  Cascade,
+  /// The referencing foreign keys are set to None.
+  SetNull,
+}
+
+#[derive(Clone)]
+pub(crate) struct ForeignKeyReference {
+  /// the referencing table
+  table: Weak<Table>,
+  entity: EntityId,
+  component: ComponentId,
+  policy: ForeignKeyDeletePolicy,
+}
+
+/// The reverse relation of the foreign keys, referenced entity => referencing foreign keys.
+#[derive(Default)]
+pub struct DBForeignKeyReferences {
+  referenced_by: FastHashMap<EntityId, Vec<ForeignKeyReference>>,
+}
+
+impl DBForeignKeyReferences {
+  pub(crate) fn register(
+    &mut self,
+    referenced: EntityId,
+    table: &ArcTable,
+    component: ComponentId,
+    policy: ForeignKeyDeletePolicy,
+  ) {
+    if policy == ForeignKeyDeletePolicy::NoAction {
+      return;
+    }
+    self
+      .referenced_by
+      .entry(referenced)
+      .or_default()
+      .push(ForeignKeyReference {
+        table: Arc::downgrade(&table.internal),
+        entity: table.internal.type_id,
+        component,
+        policy,
+      });
+  }
+
+  fn get(&self, referenced: EntityId) -> &[ForeignKeyReference] {
+    self
+      .referenced_by
+      .get(&referenced)
+      .map(|v| v.as_slice())
+      .unwrap_or(&[])
+  }
+}
+
+/// A foreign key of an entity: (entity type, foreign key component, entity handle).
+pub type ForeignKeyLocation = (EntityId, ComponentId, RawEntityHandle);
+
+/// The effect of deleting an entity with the foreign key delete policies applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ForeignKeyDeletePlan {
+  /// The entities will be deleted, including the requested one. Sorted in the deletion
+  /// order: the referencing entities come before the referenced.
+  pub deletions: Vec<(EntityId, RawEntityHandle)>,
+  /// The foreign keys will be set to None.
+  pub set_nulls: Vec<ForeignKeyLocation>,
+  /// The foreign keys that restrict the deletion, the deletion is not allowed if not empty.
+  pub restricted_by: Vec<ForeignKeyLocation>,
+}
+
+impl ForeignKeyDeletePlan {
+  pub fn is_allowed(&self) -> bool {
+    self.restricted_by.is_empty()
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct ForeignKeyDeleteError {
+  pub entity: EntityId,
+  pub handle: RawEntityHandle,
+  pub restricted_by: Vec<ForeignKeyLocation>,
+}
+
+impl std::fmt::Display for ForeignKeyDeleteError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "failed to delete entity {}, it's still referenced by restrict foreign keys: {:?}",
+      self.handle, self.restricted_by
+    )
+  }
+}
+
+impl std::error::Error for ForeignKeyDeleteError {}
+
+impl TableWriterUntyped {
+  /// Report what will happen if the entity is deleted, without any modification. The
+  /// [ForeignKeyDeletePlan::deletions] is empty if the handle is not valid.
+  ///
+  /// The components of other tables are read to find the referencing entities, so the
+  /// writer of the referencing table should not be held by the caller, or it will deadlock.
+  /// Use [Self::plan_delete_entity_with] if the writers are held. If the foreign key has a
+  /// hash index, the index is used instead of the full scan.
+  pub fn plan_delete_entity(&self, handle: RawEntityHandle) -> ForeignKeyDeletePlan {
+    self.plan_delete_entity_with(handle, &[])
+  }
+
+  /// Same as [Self::plan_delete_entity], the referencing tables of the held writers are read
+  /// from the writers directly.
+  pub fn plan_delete_entity_with(
+    &self,
+    handle: RawEntityHandle,
+    held: &[&TableWriterUntyped],
+  ) -> ForeignKeyDeletePlan {
+    self.plan_delete_entity_impl(handle, held).0
+  }
+
+  /// Delete the entity with the foreign key delete policies enforced, return what has
+  /// been removed. Nothing is modified if the deletion is restricted.
+  ///
+  /// Same as [Self::plan_delete_entity], the writer of the referencing table(except self)
+  /// should not be held by the caller, use [Self::try_delete_entity_with] if they are held.
+  pub fn try_delete_entity(
+    &mut self,
+    handle: RawEntityHandle,
+  ) -> Result<ForeignKeyDeletePlan, ForeignKeyDeleteError> {
+    self.try_delete_entity_with(handle, &mut [])
+  }
+
+  /// Same as [Self::try_delete_entity], the held writers of the referencing tables are used
+  /// to find and modify the referencing entities instead of acquiring new ones.
+  pub fn try_delete_entity_with(
+    &mut self,
+    handle: RawEntityHandle,
+    held: &mut [&mut TableWriterUntyped],
+  ) -> Result<ForeignKeyDeletePlan, ForeignKeyDeleteError> {
+    // fast path, nothing to enforce
+    if self
+      .foreign_key_references
+      .read()
+      .get(self.type_id)
+      .is_empty()
+    {
+      self.delete_entity_unchecked(handle);
+      return Ok(ForeignKeyDeletePlan {
+        deletions: vec![(self.type_id, handle)],
+        ..Default::default()
+      });
+    }
+
+    let held_ref: Vec<&TableWriterUntyped> = held.iter().map(|w| &**w).collect();
+    let (plan, tables) = self.plan_delete_entity_impl(handle, &held_ref);
+    assert!(!plan.deletions.is_empty(), "bad handle");
+    if !plan.is_allowed() {
+      return Err(ForeignKeyDeleteError {
+        entity: self.type_id,
+        handle,
+        restricted_by: plan.restricted_by,
+      });
+    }
+
+    // acquire the writers that are not held by self or the caller
+    let mut other_writers = FastHashMap::<EntityId, TableWriterUntyped>::default();
+    let touched = plan
+      .set_nulls
+      .iter()
+      .map(|(e_id, _, _)| *e_id)
+      .chain(plan.deletions.iter().map(|(e_id, _)| *e_id));
+    for e_id in touched {
+      if e_id != self.type_id && !held.iter().any(|w| w.type_id == e_id) {
+        other_writers.entry(e_id).or_insert_with(|| {
+          ArcTable {
+            internal: tables[&e_id].upgrade().unwrap(),
+          }
+          .entity_writer_dyn()
+        });
+      }
+    }
+
+    for (e_id, c_id, h) in &plan.set_nulls {
+      let writer = select_writer(self, held, &mut other_writers, *e_id);
+      let value: ForeignKeyComponentData = None;
+      let component = writer.get_component_by_id_mut(*c_id).unwrap();
+      unsafe {
+        component.write_component(*h, &value as *const ForeignKeyComponentData as DataPtr);
+      }
+    }
+
+    for (e_id, h) in &plan.deletions {
+      select_writer(self, held, &mut other_writers, *e_id).delete_entity_unchecked(*h);
+    }
+
+    Ok(plan)
+  }
+
+  fn plan_delete_entity_impl(
+    &self,
+    handle: RawEntityHandle,
+    held: &[&TableWriterUntyped],
+  ) -> (ForeignKeyDeletePlan, FastHashMap<EntityId, Weak<Table>>) {
+    let mut plan = ForeignKeyDeletePlan::default();
+    let mut tables = FastHashMap::default();
+    if self.allocator.get(handle.0).is_none() {
+      return (plan, tables);
+    }
+
+    let references = self.foreign_key_references.read();
+
+    let mut to_delete = FastHashSet::default();
+    to_delete.insert((self.type_id, handle));
+    let mut queue = vec![(self.type_id, handle)];
+    let mut set_nulls = Vec::new();
+    let mut restricted_by = Vec::new();
+
+    while let Some((e_id, target)) = queue.pop() {
+      plan.deletions.push((e_id, target));
+      for reference in references.get(e_id) {
+        tables.insert(reference.entity, reference.table.clone());
+        for referencing in self.find_referencing_entities(reference, target, held) {
+          let location = (reference.entity, reference.component, referencing);
+          match reference.policy {
+            ForeignKeyDeletePolicy::NoAction => {}
+            ForeignKeyDeletePolicy::Restrict => restricted_by.push(location),
+            ForeignKeyDeletePolicy::SetNull => set_nulls.push(location),
+            ForeignKeyDeletePolicy::Cascade => {
+              if to_delete.insert((reference.entity, referencing)) {
+                queue.push((reference.entity, referencing));
+              }
+            }
+          }
+        }
+      }
+    }
+
+    // the referencing entities that deleted in the same plan do not need the extra handling.
+    let is_kept = |(e_id, _, h): &ForeignKeyLocation| !to_delete.contains(&(*e_id, *h));
+    plan.set_nulls = set_nulls.into_iter().filter(is_kept).collect();
+    plan.restricted_by = restricted_by.into_iter().filter(is_kept).collect();
+    plan.deletions.reverse();
+
+    (plan, tables)
+  }
+
+  fn find_referencing_entities(
+    &self,
+    reference: &ForeignKeyReference,
+    target: RawEntityHandle,
+    held: &[&TableWriterUntyped],
+  ) -> Vec<RawEntityHandle> {
+    let key: ForeignKeyComponentData = Some(target);
+
+    let Some(table) = reference.table.upgrade() else {
+      return Vec::new();
+    };
+    let table = ArcTable { internal: table };
+
+    // the index is maintained by the change watcher, so it's also usable when the table is
+    // locked by the writer.
+    if let Some(index) = table
+      .access_index::<ForeignKeyComponentData, ComponentHashIndex<ForeignKeyComponentData>>(
+        reference.component,
+      )
+    {
+      return index.read().get(&key).collect();
+    }
+
+    // the table locked by this writer or the held writers, so read the data from the writer
+    let writer = if reference.entity == self.type_id {
+      Some(self)
+    } else {
+      held.iter().find(|w| w.type_id == reference.entity).copied()
+    };
+    if let Some(writer) = writer {
+      let component = writer.get_component_by_id(reference.component).unwrap();
+      return writer
+        .allocator
+        .iter()
+        .map(|(h, _)| RawEntityHandle(h))
+        .filter(|h| {
+          let value = component.get(*h, &writer.allocator).unwrap();
+          unsafe { *(value as *const ForeignKeyComponentData) == key }
+        })
+        .collect();
+    }
+
+    table
+      .access_component(reference.component, |component| {
+        let reader = component.read_untyped();
+        reader
+          .allocator
+          .iter()
+          .map(|(h, _)| RawEntityHandle(h))
+          .filter(|h| {
+            let value = reader.get(*h).unwrap();
+            unsafe { *(value as *const ForeignKeyComponentData) == key }
+          })
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+}
+
+/// Select the writer of the entity type from self, the caller held and the newly acquired ones.
+fn select_writer<'a>(
+  this: &'a mut TableWriterUntyped,
+  held: &'a mut [&mut TableWriterUntyped],
+  others: &'a mut FastHashMap<EntityId, TableWriterUntyped>,
+  e_id: EntityId,
+) -> &'a mut TableWriterUntyped {
+  if e_id == this.type_id {
+    return this;
+  }
+  if let Some(writer) = held.iter_mut().find(|w| w.type_id == e_id) {
+    return writer;
+  }
+  others.get_mut(&e_id).unwrap()
+}
+
+impl<E: EntitySemantic> TableWriter<E> {
+  /// See [TableWriterUntyped::plan_delete_entity]
+  pub fn plan_delete_entity(&self, handle: EntityHandle<E>) -> ForeignKeyDeletePlan {
+    self.inner.plan_delete_entity(handle.handle)
+  }
+
+  /// See [TableWriterUntyped::try_delete_entity]
+  pub fn try_delete_entity(
+    &mut self,
+    handle: EntityHandle<E>,
+  ) -> Result<ForeignKeyDeletePlan, ForeignKeyDeleteError> {
+    self.inner.try_delete_entity(handle.handle)
+  }
+
+  /// See [TableWriterUntyped::try_delete_entity_with]
+  pub fn try_delete_entity_with(
+    &mut self,
+    handle: EntityHandle<E>,
+    held: &mut [&mut TableWriterUntyped],
+  ) -> Result<ForeignKeyDeletePlan, ForeignKeyDeleteError> {
+    self.inner.try_delete_entity_with(handle.handle, held)
+  }
+
+  /// See [TableWriterUntyped::delete_entity_with]
+  pub fn delete_entity_with(
+    &mut self,
+    handle: EntityHandle<E>,
+    held: &mut [&mut TableWriterUntyped],
+  ) {
+    self.inner.delete_entity_with(handle.handle, held)
+  }
+}
+
+#[test]
+fn test_foreign_key_delete_policy() {
+  declare_entity!(DeletePolicyTestScene);
+  declare_entity!(DeletePolicyTestNode);
+  declare_foreign_key!(
+    DeletePolicyTestNodeBelongsToScene,
+    DeletePolicyTestNode,
+    DeletePolicyTestScene
+  );
+  declare_foreign_key!(
+    DeletePolicyTestNodeParent,
+    DeletePolicyTestNode,
+    DeletePolicyTestNode
+  );
+  declare_entity!(DeletePolicyTestModel);
+  declare_foreign_key!(
+    DeletePolicyTestModelRefNode,
+    DeletePolicyTestModel,
+    DeletePolicyTestNode
+  );
+  declare_entity!(DeletePolicyTestCamera);
+  declare_foreign_key!(
+    DeletePolicyTestCameraRefNode,
+    DeletePolicyTestCamera,
+    DeletePolicyTestNode
+  );
+
+  let db = Database::default();
+  db.declare_entity::<DeletePolicyTestScene>();
+  db.declare_entity::<DeletePolicyTestNode>()
+    .declare_foreign_key_with_policy::<DeletePolicyTestNodeBelongsToScene>(
+      ForeignKeyDeletePolicy::Cascade,
+    )
+    .declare_foreign_key_with_policy::<DeletePolicyTestNodeParent>(ForeignKeyDeletePolicy::Cascade);
+  db.declare_entity::<DeletePolicyTestModel>()
+    .declare_foreign_key_with_policy::<DeletePolicyTestModelRefNode>(
+      ForeignKeyDeletePolicy::SetNull,
+    )
+    .declare_hash_index::<DeletePolicyTestModelRefNode>();
+  db.declare_entity::<DeletePolicyTestCamera>()
+    .declare_foreign_key_with_policy::<DeletePolicyTestCameraRefNode>(
+      ForeignKeyDeletePolicy::Restrict,
+    );
+
+  let scene = db
+    .entity_writer::<DeletePolicyTestScene>()
+    .new_entity(|w| w);
+  let (root, child, other) = {
+    let mut nodes = db.entity_writer::<DeletePolicyTestNode>();
+    let root =
+      nodes.new_entity(|w| w.write::<DeletePolicyTestNodeBelongsToScene>(&scene.some_handle()));
+    let child = nodes.new_entity(|w| w.write::<DeletePolicyTestNodeParent>(&root.some_handle()));
+    let other = nodes.new_entity(|w| w);
+    (root, child, other)
+  };
+  let model = db
+    .entity_writer::<DeletePolicyTestModel>()
+    .new_entity(|w| w.write::<DeletePolicyTestModelRefNode>(&child.some_handle()));
+  let camera = db
+    .entity_writer::<DeletePolicyTestCamera>()
+    .new_entity(|w| w.write::<DeletePolicyTestCameraRefNode>(&other.some_handle()));
+
+  let node_count = || db.access_table::<DeletePolicyTestNode, _>(|t| t.inner.living_entity_count());
+
+  // restrict
+  let mut nodes = db.entity_writer::<DeletePolicyTestNode>();
+  let plan = nodes.plan_delete_entity(other);
+  assert!(!plan.is_allowed());
+  assert!(nodes.try_delete_entity(other).is_err());
+  drop(nodes);
+  assert_eq!(node_count(), 3);
+
+  db.entity_writer::<DeletePolicyTestCamera>()
+    .delete_entity(camera);
+  db.entity_writer::<DeletePolicyTestNode>()
+    .delete_entity(other);
+  assert_eq!(node_count(), 2);
+
+  // dry run reports all the cascade effects
+  let scenes = db.entity_writer::<DeletePolicyTestScene>();
+  let plan = scenes.plan_delete_entity(scene);
+  assert!(plan.is_allowed());
+  assert_eq!(
+    plan.deletions,
+    vec![
+      (DeletePolicyTestNode::entity_id(), child.into_raw()),
+      (DeletePolicyTestNode::entity_id(), root.into_raw()),
+      (DeletePolicyTestScene::entity_id(), scene.into_raw()),
+    ]
+  );
+  assert_eq!(
+    plan.set_nulls,
+    vec![(
+      DeletePolicyTestModel::entity_id(),
+      DeletePolicyTestModelRefNode::component_id(),
+      model.into_raw()
+    )]
+  );
+  drop(scenes);
+  assert_eq!(node_count(), 2);
+
+  let removed = db
+    .entity_writer::<DeletePolicyTestScene>()
+    .try_delete_entity(scene)
+    .unwrap();
+  assert_eq!(removed, plan);
+  assert_eq!(node_count(), 0);
+  assert_eq!(
+    db.read_foreign_key::<DeletePolicyTestModelRefNode>()
+      .get(model),
+    None
+  );
+}
+
+#[test]
+fn test_foreign_key_delete_policy_with_held_writers() {
+  declare_entity!(DeletePolicyHeldTestMesh);
+  declare_entity!(DeletePolicyHeldTestVertex);
+  declare_foreign_key!(
+    DeletePolicyHeldTestVertexRefMesh,
+    DeletePolicyHeldTestVertex,
+    DeletePolicyHeldTestMesh
+  );
+  declare_entity!(DeletePolicyHeldTestUser);
+  declare_foreign_key!(
+    DeletePolicyHeldTestUserRefMesh,
+    DeletePolicyHeldTestUser,
+    DeletePolicyHeldTestMesh
+  );
+
+  let db = Database::default();
+  db.declare_entity::<DeletePolicyHeldTestMesh>();
+  db.declare_entity::<DeletePolicyHeldTestVertex>()
+    .declare_foreign_key_with_policy::<DeletePolicyHeldTestVertexRefMesh>(
+      ForeignKeyDeletePolicy::Cascade,
+    );
+  db.declare_entity::<DeletePolicyHeldTestUser>()
+    .declare_foreign_key_with_policy::<DeletePolicyHeldTestUserRefMesh>(
+      ForeignKeyDeletePolicy::Restrict,
+    )
+    .declare_hash_index::<DeletePolicyHeldTestUserRefMesh>();
+
+  let mut meshes = db.entity_writer::<DeletePolicyHeldTestMesh>();
+  let mut vertices = db.entity_writer::<DeletePolicyHeldTestVertex>();
+  let mut users = db.entity_writer::<DeletePolicyHeldTestUser>();
+
+  let a = meshes.new_entity(|w| w);
+  let b = meshes.new_entity(|w| w);
+  for mesh in [a, a, b] {
+    vertices.new_entity(|w| w.write::<DeletePolicyHeldTestVertexRefMesh>(&mesh.some_handle()));
+  }
+  let user = users.new_entity(|w| w.write::<DeletePolicyHeldTestUserRefMesh>(&b.some_handle()));
+
+  let removed = meshes
+    .try_delete_entity_with(a, &mut [vertices.as_untyped_mut(), users.as_untyped_mut()])
+    .unwrap();
+  assert_eq!(removed.deletions.len(), 3);
+  assert_eq!(vertices.inner.allocator.len(), 1);
+
+  // the restricted deletion is skipped instead of panic
+  meshes.delete_entity_with(b, &mut [vertices.as_untyped_mut(), users.as_untyped_mut()]);
+  assert_eq!(meshes.inner.allocator.len(), 1);
+  assert_eq!(vertices.inner.allocator.len(), 1);
+
+  users.delete_entity(user);
+  meshes.delete_entity_with(b, &mut [vertices.as_untyped_mut(), users.as_untyped_mut()]);
+  assert_eq!(meshes.inner.allocator.len(), 0);
+  assert_eq!(vertices.inner.allocator.len(), 0);
+}
@@ -0,0 +1,407 @@
+#[allow(clippy::disallowed_types)] // the range lookup requires the ordered map
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+
+use crate::*;
+
+/// The storage of a secondary index, maps the component value to the entities that
+/// hold it. The index is updated synchronously in the component's change callback.
+pub trait ComponentIndexStorage<K>: Default + Send + Sync + 'static {
+  fn insert(&mut self, key: &K, handle: RawEntityHandle);
+  fn remove(&mut self, key: &K, handle: RawEntityHandle);
+  /// the heap memory of the value itself(for example the String) is not included.
+  fn memory_usage_in_bytes(&self) -> usize;
+}
+
+type IndexEntities = FastHashSet<RawEntityHandle>;
+
+fn entities_memory_usage(entities: &IndexEntities) -> usize {
+  entities.capacity() * std::mem::size_of::<RawEntityHandle>()
+}
+
+/// The hash index supports the point lookup.
+pub struct ComponentHashIndex<K> {
+  map: FastHashMap<K, IndexEntities>,
+}
+
+impl<K> Default for ComponentHashIndex<K> {
+  fn default() -> Self {
+    Self {
+      map: Default::default(),
+    }
+  }
+}
+
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> ComponentIndexStorage<K>
+  for ComponentHashIndex<K>
+{
+  fn insert(&mut self, key: &K, handle: RawEntityHandle) {
+    if let Some(entities) = self.map.get_mut(key) {
+      entities.insert(handle);
+    } else {
+      self.map.entry(key.clone()).or_default().insert(handle);
+    }
+  }
+
+  fn remove(&mut self, key: &K, handle: RawEntityHandle) {
+    if let Some(entities) = self.map.get_mut(key) {
+      entities.remove(&handle);
+      if entities.is_empty() {
+        self.map.remove(key);
+      }
+    }
+  }
+
+  fn memory_usage_in_bytes(&self) -> usize {
+    let entry_size = std::mem::size_of::<K>() + std::mem::size_of::<IndexEntities>();
+    self.map.capacity() * entry_size + self.map.values().map(entities_memory_usage).sum::<usize>()
+  }
+}
+
+impl<K: Hash + Eq> ComponentHashIndex<K> {
+  pub(crate) fn get(&self, key: &K) -> impl Iterator<Item = RawEntityHandle> + '_ {
+    self.map.get(key).into_iter().flatten().copied()
+  }
+}
+
+/// The ordered index supports the point lookup and the range lookup.
+pub struct ComponentOrderedIndex<K> {
+  #[allow(clippy::disallowed_types)]
+  map: BTreeMap<K, IndexEntities>,
+}
+
+impl<K> Default for ComponentOrderedIndex<K> {
+  fn default() -> Self {
+    Self {
+      map: Default::default(),
+    }
+  }
+}
+
+impl<K: Ord + Clone + Send + Sync + 'static> ComponentIndexStorage<K> for ComponentOrderedIndex<K> {
+  fn insert(&mut self, key: &K, handle: RawEntityHandle) {
+    if let Some(entities) = self.map.get_mut(key) {
+      entities.insert(handle);
+    } else {
+      self.map.entry(key.clone()).or_default().insert(handle);
+    }
+  }
+
+  fn remove(&mut self, key: &K, handle: RawEntityHandle) {
+    if let Some(entities) = self.map.get_mut(key) {
+      entities.remove(&handle);
+      if entities.is_empty() {
+        self.map.remove(key);
+      }
+    }
+  }
+
+  fn memory_usage_in_bytes(&self) -> usize {
+    let entry_size = std::mem::size_of::<K>() + std::mem::size_of::<IndexEntities>();
+    self.map.len() * entry_size + self.map.values().map(entities_memory_usage).sum::<usize>()
+  }
+}
+
+/// Type erased index, stored in the table for memory reporting and typed access.
+pub(crate) trait ComponentIndexUntyped: Send + Sync {
+  fn memory_usage_in_bytes(&self) -> usize;
+  fn as_any(&self) -> &dyn Any;
+}
+
+struct ComponentIndexHolder<K, I> {
+  phantom: PhantomData<fn() -> K>,
+  index: Arc<RwLock<I>>,
+}
+
+impl<K: 'static, I: ComponentIndexStorage<K>> ComponentIndexUntyped for ComponentIndexHolder<K, I> {
+  fn memory_usage_in_bytes(&self) -> usize {
+    self.index.read().memory_usage_in_bytes()
+  }
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+}
+
+impl ArcTable {
+  /// Create the index for the component and keep it updated on every write. The index
+  /// is built from the existing data if the table is not empty.
+  ///
+  /// # Safety
+  ///
+  /// K must match the component's data type
+  unsafe fn declare_index_dyn<K, I>(&self, c_id: ComponentId)
+  where
+    K: 'static,
+    I: ComponentIndexStorage<K>,
+  {
+    let index = Arc::new(RwLock::new(I::default()));
+
+    let registered = self.access_component(c_id, |component| {
+      // hold the read view to block the writers until the watcher is registered
+      let reader = component.read_untyped();
+      {
+        let mut index = index.write();
+        for (handle, _) in reader.allocator.iter() {
+          let handle = RawEntityHandle(handle);
+          let value = reader.get(handle).unwrap();
+          index.insert(unsafe { &*(value as *const K) }, handle);
+        }
+      }
+
+      let index = index.clone();
+      component.data_watchers.on(move |change| {
+        if let ScopedMessage::Message(change) = change {
+          let mut index = index.write();
+          match &change.change {
+            ValueChange::Delta((new, _), old) => unsafe {
+              if let Some((old, _)) = old {
+                index.remove(&*(*old as *const K), change.idx);
+              }
+              index.insert(&*(*new as *const K), change.idx);
+            },
+            ValueChange::Remove((old, _)) => unsafe {
+              index.remove(&*(*old as *const K), change.idx);
+            },
+          }
+        }
+        false
+      });
+    });
+    assert!(
+      registered.is_some(),
+      "declare index for not exist component, make sure the component declared before the index"
+    );
+
+    let previous = self.internal.indexes.write().insert(
+      (c_id, TypeId::of::<I>()),
+      Box::new(ComponentIndexHolder {
+        phantom: PhantomData::<fn() -> K>,
+        index,
+      }),
+    );
+    assert!(previous.is_none(), "index already declared");
+  }
+
+  pub(crate) fn access_index<K: 'static, I: 'static>(
+    &self,
+    c_id: ComponentId,
+  ) -> Option<Arc<RwLock<I>>> {
+    let indexes = self.internal.indexes.read();
+    let index = indexes.get(&(c_id, TypeId::of::<I>()))?;
+    let holder = index
+      .as_any()
+      .downcast_ref::<ComponentIndexHolder<K, I>>()
+      .unwrap();
+    Some(holder.index.clone())
+  }
+
+  pub fn indexes_memory_usage_in_bytes(&self) -> usize {
+    let indexes = self.internal.indexes.read();
+    indexes.values().map(|i| i.memory_usage_in_bytes()).sum()
+  }
+}
+
+impl<E: EntitySemantic> TypedArcTable<E> {
+  /// Declare a hash index on the component for the point lookup by value.
+  pub fn declare_hash_index<S>(self) -> Self
+  where
+    S: ComponentSemantic<Entity = E>,
+    S::Data: Hash + Eq,
+  {
+    unsafe {
+      self
+        .inner
+        .declare_index_dyn::<S::Data, ComponentHashIndex<S::Data>>(S::component_id());
+    }
+    self
+  }
+
+  /// Declare an ordered index on the component for the point and range lookup by value.
+  pub fn declare_ordered_index<S>(self) -> Self
+  where
+    S: ComponentSemantic<Entity = E>,
+    S::Data: Ord,
+  {
+    unsafe {
+      self
+        .inner
+        .declare_index_dyn::<S::Data, ComponentOrderedIndex<S::Data>>(S::component_id());
+    }
+    self
+  }
+}
+
+pub struct ComponentIndexReadView<C, I: 'static> {
+  phantom: PhantomData<C>,
+  index: LockReadGuardHolder<I>,
+}
+
+pub type ComponentHashIndexReadView<C> =
+  ComponentIndexReadView<C, ComponentHashIndex<<C as ComponentSemantic>::Data>>;
+pub type ComponentOrderedIndexReadView<C> =
+  ComponentIndexReadView<C, ComponentOrderedIndex<<C as ComponentSemantic>::Data>>;
+
+fn typed_entities<E: EntitySemantic>(
+  entities: Option<&IndexEntities>,
+) -> impl Iterator<Item = EntityHandle<E>> + '_ {
+  entities
+    .into_iter()
+    .flatten()
+    .map(|h| unsafe { EntityHandle::from_raw(*h) })
+}
+
+impl<C> ComponentHashIndexReadView<C>
+where
+  C: ComponentSemantic,
+  C::Data: Hash + Eq,
+{
+  /// Get all entities whose component value equals to the `value`.
+  pub fn query(&self, value: &C::Data) -> impl Iterator<Item = EntityHandle<C::Entity>> + '_ {
+    typed_entities(self.index.map.get(value))
+  }
+
+  pub fn count(&self, value: &C::Data) -> usize {
+    self.index.map.get(value).map(|e| e.len()).unwrap_or(0)
+  }
+
+  /// Iterate all distinct values in the index, in arbitrary order.
+  pub fn iter_values(&self) -> impl Iterator<Item = &C::Data> + '_ {
+    self.index.map.keys()
+  }
+}
+
+impl<C> ComponentOrderedIndexReadView<C>
+where
+  C: ComponentSemantic,
+  C::Data: Ord,
+{
+  /// Get all entities whose component value equals to the `value`.
+  pub fn query(&self, value: &C::Data) -> impl Iterator<Item = EntityHandle<C::Entity>> + '_ {
+    typed_entities(self.index.map.get(value))
+  }
+
+  pub fn count(&self, value: &C::Data) -> usize {
+    self.index.map.get(value).map(|e| e.len()).unwrap_or(0)
+  }
+
+  /// Get all entities whose component value is in the `range`, ordered by the value.
+  pub fn range(
+    &self,
+    range: impl RangeBounds<C::Data>,
+  ) -> impl Iterator<Item = (&C::Data, EntityHandle<C::Entity>)> + '_ {
+    self
+      .index
+      .map
+      .range(range)
+      .flat_map(|(value, entities)| typed_entities(Some(entities)).map(move |e| (value, e)))
+  }
+
+  /// Iterate all distinct values in the index, in ascending order.
+  pub fn iter_values(&self) -> impl Iterator<Item = &C::Data> + '_ {
+    self.index.map.keys()
+  }
+}
+
+impl Database {
+  fn read_index<C: ComponentSemantic, I: 'static>(&self) -> ComponentIndexReadView<C, I> {
+    let index = self.access_table_dyn(C::Entity::entity_id(), |t| {
+      t.access_index::<C::Data, I>(C::component_id())
+    });
+    let index = index.unwrap_or_else(|| {
+      panic!(
+        "access not exist index of {}, make sure declared before use",
+        std::any::type_name::<C>()
+      )
+    });
+    ComponentIndexReadView {
+      phantom: PhantomData,
+      index: index.make_read_holder(),
+    }
+  }
+
+  /// Note, the read view holds the index's read lock, the write to the component will
+  /// be blocked until the view is dropped.
+  pub fn read_hash_index<C>(&self) -> ComponentHashIndexReadView<C>
+  where
+    C: ComponentSemantic,
+    C::Data: Hash + Eq,
+  {
+    self.read_index::<C, ComponentHashIndex<C::Data>>()
+  }
+
+  /// Note, the read view holds the index's read lock, the write to the component will
+  /// be blocked until the view is dropped.
+  pub fn read_ordered_index<C>(&self) -> ComponentOrderedIndexReadView<C>
+  where
+    C: ComponentSemantic,
+    C::Data: Ord,
+  {
+    self.read_index::<C, ComponentOrderedIndex<C::Data>>()
+  }
+}
+
+#[test]
+fn test_component_index() {
+  declare_entity!(IndexTestNode);
+  declare_component!(IndexTestNodeLayer, IndexTestNode, u32);
+  declare_component!(IndexTestNodeName, IndexTestNode, String);
+  declare_entity!(IndexTestModel);
+  declare_foreign_key!(IndexTestModelRefNode, IndexTestModel, IndexTestNode);
+
+  let db = Database::default();
+  db.declare_entity::<IndexTestNode>()
+    .declare_component::<IndexTestNodeLayer>()
+    .declare_component::<IndexTestNodeName>()
+    .declare_ordered_index::<IndexTestNodeLayer>();
+
+  let mut writer = db.entity_writer::<IndexTestNode>();
+  let a = writer.new_entity(|w| w.write::<IndexTestNodeLayer>(&1));
+  let b = writer.new_entity(|w| {
+    w.write::<IndexTestNodeLayer>(&3)
+      .write::<IndexTestNodeName>(&"b".into())
+  });
+  let c = writer.new_entity(|w| w.write::<IndexTestNodeLayer>(&3));
+  drop(writer);
+
+  // declared on the table that has data
+  db.access_table::<IndexTestNode, _>(|t| {
+    t.clone().declare_hash_index::<IndexTestNodeName>();
+  });
+  db.declare_entity::<IndexTestModel>()
+    .declare_foreign_key::<IndexTestModelRefNode>()
+    .declare_hash_index::<IndexTestModelRefNode>();
+
+  {
+    let names = db.read_hash_index::<IndexTestNodeName>();
+    assert_eq!(names.query(&"b".into()).collect::<Vec<_>>(), vec![b]);
+    assert_eq!(names.count(&String::new()), 2);
+
+    let layers = db.read_ordered_index::<IndexTestNodeLayer>();
+    assert_eq!(layers.count(&3), 2);
+    let in_range: Vec<_> = layers.range(0..2).collect();
+    assert_eq!(in_range, vec![(&1, a)]);
+  }
+
+  let mut models = db.entity_writer::<IndexTestModel>();
+  let m1 = models.new_entity(|w| w.write::<IndexTestModelRefNode>(&b.some_handle()));
+  let m2 = models.new_entity(|w| w.write::<IndexTestModelRefNode>(&b.some_handle()));
+  drop(models);
+
+  db.write::<IndexTestNodeLayer>().write(c, 5);
+  db.entity_writer::<IndexTestNode>().delete_entity(a);
+  db.entity_writer::<IndexTestModel>().delete_entity(m1);
+
+  {
+    let layers = db.read_ordered_index::<IndexTestNodeLayer>();
+    assert_eq!(layers.count(&1), 0);
+    let values: Vec<_> = layers.range(..).map(|(v, e)| (*v, e)).collect();
+    assert_eq!(values, vec![(3, b), (5, c)]);
+
+    let refs = db.read_hash_index::<IndexTestModelRefNode>();
+    assert_eq!(refs.query(&b.some_handle()).collect::<Vec<_>>(), vec![m2]);
+  }
+
+  let table = db.access_table::<IndexTestNode, _>(|t| t.clone().into_untyped());
+  assert!(table.indexes_memory_usage_in_bytes() > 0);
+  assert!(table.memory_usage_in_bytes() > table.indexes_memory_usage_in_bytes());
+}
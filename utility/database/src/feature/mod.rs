@@ -1,2 +1,8 @@
+mod delete_policy;
+pub use delete_policy::*;
+mod index;
+pub use index::*;
 mod label;
 pub use label::*;
+mod undo;
+pub use undo::*;
@@ -0,0 +1,977 @@
+use std::collections::VecDeque;
+use std::sync::Weak;
+use std::thread::ThreadId;
+
+use parking_lot::Mutex;
+
+use crate::*;
+
+/// The recorded component value of a change.
+pub enum DBRecordedValue {
+  /// Foreign key is stored directly as handle so that it can be remapped if the referenced
+  /// entity is recreated by undo/redo.
+  ForeignKey(Option<RawEntityHandle>),
+  /// Normal component data, cloned from the database. The clone keeps the [ExternalRefPtr]
+  /// data shared, so the undo will restore exactly the same ptr.
+  Data(Box<dyn DynDataBaseDataType + Send + Sync>),
+}
+
+impl DBRecordedValue {
+  fn memory_usage_in_bytes(&self) -> usize {
+    match self {
+      DBRecordedValue::ForeignKey(_) => 0,
+      // note, the heap memory of the data is not tracked
+      DBRecordedValue::Data(data) => std::mem::size_of_val(data.as_ref()),
+    }
+  }
+}
+
+impl std::fmt::Debug for DBRecordedValue {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DBRecordedValue::ForeignKey(fk) => write!(f, "FK({:?})", fk),
+      DBRecordedValue::Data(data) => write!(f, "{}", data.debug_value()),
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum DBChangeRecord {
+  EntityCreated {
+    entity: EntityId,
+    handle: RawEntityHandle,
+  },
+  EntityDeleted {
+    entity: EntityId,
+    handle: RawEntityHandle,
+  },
+  /// old is None means the value is initialized by entity creation,
+  /// new is None means the value is removed by entity deletion.
+  ComponentChanged {
+    entity: EntityId,
+    component: ComponentId,
+    handle: RawEntityHandle,
+    old: Option<DBRecordedValue>,
+    new: Option<DBRecordedValue>,
+  },
+}
+
+impl DBChangeRecord {
+  fn memory_usage_in_bytes(&self) -> usize {
+    let mut byte_count = std::mem::size_of::<Self>();
+    if let DBChangeRecord::ComponentChanged { old, new, .. } = self {
+      byte_count += old.as_ref().map(|v| v.memory_usage_in_bytes()).unwrap_or(0);
+      byte_count += new.as_ref().map(|v| v.memory_usage_in_bytes()).unwrap_or(0);
+    }
+    byte_count
+  }
+}
+
+/// A named group of database changes that can be reverted or replayed as a whole.
+pub struct DBTransaction {
+  pub name: String,
+  /// the records are stored in the order they emitted by the database.
+  pub records: Vec<DBChangeRecord>,
+  /// how many scoped writes(the Start/End pair of the table or component writer)
+  /// have touched this transaction.
+  pub scoped_write_count: usize,
+  byte_count: usize,
+}
+
+impl DBTransaction {
+  fn new(name: String) -> Self {
+    Self {
+      name,
+      records: Default::default(),
+      scoped_write_count: 0,
+      byte_count: std::mem::size_of::<Self>(),
+    }
+  }
+
+  fn push(&mut self, record: DBChangeRecord) {
+    self.byte_count += record.memory_usage_in_bytes();
+    self.records.push(record);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.records.is_empty()
+  }
+
+  /// The heap memory of the component data is not included, the [ExternalRefPtr]'s
+  /// data is shared with the database so it's not included either.
+  pub fn memory_usage_in_bytes(&self) -> usize {
+    self.byte_count + self.name.capacity()
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DBUndoHistoryConfig {
+  /// the max undo-able transaction count, the oldest one will be dropped if exceeded.
+  pub max_transaction_count: usize,
+  /// the max memory used by the undo and redo stack, the oldest undo transaction will
+  /// be dropped if exceeded. If the undo stack is empty, the farthest redo transaction
+  /// is dropped instead.
+  pub max_memory_usage_in_bytes: usize,
+}
+
+impl Default for DBUndoHistoryConfig {
+  fn default() -> Self {
+    Self {
+      max_transaction_count: 128,
+      max_memory_usage_in_bytes: 64 * 1024 * 1024,
+    }
+  }
+}
+
+type HandleRemapping = FastHashMap<(EntityId, RawEntityHandle), RawEntityHandle>;
+
+struct UndoHistoryState {
+  config: DBUndoHistoryConfig,
+  recording: Option<DBTransaction>,
+  recording_depth: u32,
+  undo: VecDeque<DBTransaction>,
+  redo: Vec<DBTransaction>,
+  /// When the undo/redo recreate an entity, the handle will be different from the recorded
+  /// one. This maps the previous handle to the recreated handle. Because the generation of
+  /// handle never repeats, the mapping can be chained.
+  handle_remapping: HandleRemapping,
+}
+
+impl UndoHistoryState {
+  fn record(&mut self, record: DBChangeRecord) {
+    if let Some(recording) = &mut self.recording {
+      recording.push(record);
+    }
+  }
+
+  fn memory_usage_in_bytes(&self) -> usize {
+    let mut byte_count = std::mem::size_of::<Self>();
+    byte_count += self
+      .recording
+      .as_ref()
+      .map(|t| t.memory_usage_in_bytes())
+      .unwrap_or(0);
+    byte_count += self
+      .undo
+      .iter()
+      .map(|t| t.memory_usage_in_bytes())
+      .sum::<usize>();
+    byte_count += self
+      .redo
+      .iter()
+      .map(|t| t.memory_usage_in_bytes())
+      .sum::<usize>();
+    byte_count += self.handle_remapping.capacity()
+      * std::mem::size_of::<((EntityId, RawEntityHandle), RawEntityHandle)>();
+    byte_count
+  }
+
+  fn evict_if_exceed_budget(&mut self) {
+    let mut evicted_any = false;
+    while self.undo.len() > self.config.max_transaction_count {
+      let evicted = self.undo.pop_front().unwrap();
+      log::info!("undo transaction {} evicted from history", evicted.name);
+      evicted_any = true;
+    }
+    if evicted_any {
+      self.prune_handle_remapping();
+    }
+    // the memory budget covers both stacks, so both of them should be evictable, otherwise a
+    // large redo stack will keep the budget exceeded forever.
+    while self.memory_usage_in_bytes() > self.config.max_memory_usage_in_bytes {
+      if let Some(evicted) = self.undo.pop_front() {
+        log::info!("undo transaction {} evicted from history", evicted.name);
+      } else if !self.redo.is_empty() {
+        // the first one is the last to redo
+        let evicted = self.redo.remove(0);
+        log::info!("redo transaction {} evicted from history", evicted.name);
+      } else {
+        break;
+      }
+      self.prune_handle_remapping();
+    }
+  }
+
+  /// Remove the remapping that no remaining transaction could resolve. The remapping is
+  /// chained, so the mapping reachable from a referenced handle is kept as well.
+  fn prune_handle_remapping(&mut self) {
+    if self.handle_remapping.is_empty() {
+      return;
+    }
+
+    let mut referenced = FastHashSet::default();
+    // the referenced foreign entity type is not recorded, the handle is matched in any type
+    let mut referenced_fk = FastHashSet::default();
+    let transactions = self.undo.iter().chain(self.redo.iter());
+    for transaction in transactions.chain(self.recording.iter()) {
+      for record in &transaction.records {
+        match record {
+          DBChangeRecord::EntityCreated { entity, handle }
+          | DBChangeRecord::EntityDeleted { entity, handle } => {
+            referenced.insert((*entity, *handle));
+          }
+          DBChangeRecord::ComponentChanged {
+            entity,
+            handle,
+            old,
+            new,
+            ..
+          } => {
+            referenced.insert((*entity, *handle));
+            for value in old.iter().chain(new.iter()) {
+              if let DBRecordedValue::ForeignKey(Some(target)) = value {
+                referenced_fk.insert(*target);
+              }
+            }
+          }
+        }
+      }
+    }
+
+    let mut keep = FastHashSet::default();
+    for (entity, handle) in self.handle_remapping.keys() {
+      if !referenced.contains(&(*entity, *handle)) && !referenced_fk.contains(handle) {
+        continue;
+      }
+      let mut key = (*entity, *handle);
+      while keep.insert(key) {
+        match self.handle_remapping.get(&key) {
+          Some(mapped) => key = (*entity, *mapped),
+          None => break,
+        }
+      }
+    }
+
+    self.handle_remapping.retain(|key, _| keep.contains(key));
+    self.handle_remapping.shrink_to_fit();
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DBUndoHistoryError {
+  /// undo or redo is not allowed when a transaction is recording, the recorded changes
+  /// would be interleaved with the applied ones.
+  TransactionRecording,
+}
+
+impl std::fmt::Display for DBUndoHistoryError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DBUndoHistoryError::TransactionRecording => {
+        write!(f, "can not undo or redo when a transaction is recording")
+      }
+    }
+  }
+}
+
+impl std::error::Error for DBUndoHistoryError {}
+
+/// The thread that is applying the undo or redo, the changes made by it are not recorded.
+type ApplyingThread = Arc<RwLock<Option<ThreadId>>>;
+
+fn is_applying_thread(applying: &ApplyingThread) -> bool {
+  *applying.read() == Some(std::thread::current().id())
+}
+
+/// Record the database changes into named transactions, and support undo and redo them.
+///
+/// Only the changes happens between [DBUndoHistory::begin_transaction] and
+/// [DBUndoHistory::commit_transaction] are recorded, other changes are ignored. The user
+/// should make sure the changes outside of the transaction not conflict with the recorded
+/// ones, the conflicted(for example writing to a deleted entity) record will be skipped when
+/// undo/redo.
+///
+/// When the undo or redo is applying, the changes made by the applying thread are not recorded,
+/// the changes made by other threads are still recorded as usual.
+#[derive(Clone)]
+pub struct DBUndoHistory {
+  db: Database,
+  state: Arc<Mutex<UndoHistoryState>>,
+  applying: ApplyingThread,
+}
+
+impl DBUndoHistory {
+  pub fn new(db: &Database, config: DBUndoHistoryConfig) -> Self {
+    let history = Self {
+      db: db.clone(),
+      state: Arc::new(Mutex::new(UndoHistoryState {
+        config,
+        recording: None,
+        recording_depth: 0,
+        undo: Default::default(),
+        redo: Default::default(),
+        handle_remapping: Default::default(),
+      })),
+      applying: Default::default(),
+    };
+
+    let weak_state = Arc::downgrade(&history.state);
+    let applying = history.applying.clone();
+    db.entity_meta_watcher.on(move |table| {
+      if weak_state.strong_count() == 0 {
+        return true;
+      }
+      watch_table(table, weak_state.clone(), applying.clone());
+      false
+    });
+
+    for table in db.tables.read().values() {
+      watch_table(
+        table,
+        Arc::downgrade(&history.state),
+        history.applying.clone(),
+      );
+    }
+
+    history
+  }
+
+  /// Start to record a transaction. The nested call will be merged into the outer most
+  /// transaction, and the outer most name is used.
+  pub fn begin_transaction(&self, name: impl Into<String>) {
+    let mut state = self.state.lock();
+    if state.recording_depth == 0 {
+      state.recording = Some(DBTransaction::new(name.into()));
+    }
+    state.recording_depth += 1;
+  }
+
+  /// Finish the current recording transaction, return if the transaction has any change and
+  /// is pushed into the undo stack. The redo stack is cleared if so.
+  pub fn commit_transaction(&self) -> bool {
+    let mut state = self.state.lock();
+    assert!(state.recording_depth > 0, "no transaction to commit");
+    state.recording_depth -= 1;
+    if state.recording_depth > 0 {
+      return false;
+    }
+    // canceled by the nested level
+    let Some(transaction) = state.recording.take() else {
+      return false;
+    };
+    if transaction.is_empty() {
+      return false;
+    }
+    state.redo.clear();
+    state.undo.push_back(transaction);
+    state.evict_if_exceed_budget();
+    true
+  }
+
+  /// Drop the current recording transaction and revert all the changes it recorded.
+  /// For nested transaction, the whole outer most transaction is canceled, the enclosing
+  /// levels still need to be ended by commit or cancel, but nothing is recorded after the
+  /// cancel and the commit of them is no-op.
+  pub fn cancel_transaction(&self) {
+    let mut state = self.state.lock();
+    assert!(state.recording_depth > 0, "no transaction to cancel");
+    state.recording_depth -= 1;
+    // already canceled by the nested level
+    let Some(transaction) = state.recording.take() else {
+      return;
+    };
+    drop(state);
+    self.apply(&transaction, true);
+  }
+
+  /// Record all changes made in f as a transaction.
+  pub fn transaction<R>(&self, name: impl Into<String>, f: impl FnOnce() -> R) -> R {
+    self.begin_transaction(name);
+    let r = f();
+    self.commit_transaction();
+    r
+  }
+
+  pub fn is_recording(&self) -> bool {
+    self.state.lock().recording_depth > 0
+  }
+
+  /// Revert the last transaction, return its name if any.
+  pub fn undo(&self) -> Result<Option<String>, DBUndoHistoryError> {
+    let mut state = self.state.lock();
+    if state.recording_depth > 0 {
+      return Err(DBUndoHistoryError::TransactionRecording);
+    }
+    let Some(transaction) = state.undo.pop_back() else {
+      return Ok(None);
+    };
+    drop(state);
+
+    self.apply(&transaction, true);
+
+    let name = transaction.name.clone();
+    let mut state = self.state.lock();
+    state.redo.push(transaction);
+    state.evict_if_exceed_budget();
+    Ok(Some(name))
+  }
+
+  /// Replay the last undone transaction, return its name if any.
+  pub fn redo(&self) -> Result<Option<String>, DBUndoHistoryError> {
+    let mut state = self.state.lock();
+    if state.recording_depth > 0 {
+      return Err(DBUndoHistoryError::TransactionRecording);
+    }
+    let Some(transaction) = state.redo.pop() else {
+      return Ok(None);
+    };
+    drop(state);
+
+    self.apply(&transaction, false);
+
+    let name = transaction.name.clone();
+    let mut state = self.state.lock();
+    state.undo.push_back(transaction);
+    state.evict_if_exceed_budget();
+    Ok(Some(name))
+  }
+
+  pub fn can_undo(&self) -> bool {
+    !self.state.lock().undo.is_empty()
+  }
+
+  pub fn can_redo(&self) -> bool {
+    !self.state.lock().redo.is_empty()
+  }
+
+  /// The undo-able transaction names, the last one is the next to undo.
+  pub fn undo_transaction_names(&self) -> Vec<String> {
+    let state = self.state.lock();
+    state.undo.iter().map(|t| t.name.clone()).collect()
+  }
+
+  /// The redo-able transaction names, the last one is the next to redo.
+  pub fn redo_transaction_names(&self) -> Vec<String> {
+    let state = self.state.lock();
+    state.redo.iter().map(|t| t.name.clone()).collect()
+  }
+
+  /// Drop all recorded transactions, the current recording transaction is not affected.
+  pub fn clear(&self) {
+    let mut state = self.state.lock();
+    state.undo.clear();
+    state.redo.clear();
+    if state.recording.is_none() {
+      state.handle_remapping.clear();
+    }
+  }
+
+  pub fn memory_usage_in_bytes(&self) -> usize {
+    self.state.lock().memory_usage_in_bytes()
+  }
+
+  fn apply(&self, transaction: &DBTransaction, revert: bool) {
+    *self.applying.write() = Some(std::thread::current().id());
+
+    let mut remapping = std::mem::take(&mut self.state.lock().handle_remapping);
+    let mut applier = TransactionApplier {
+      db: &self.db,
+      writer: None,
+      remapping: &mut remapping,
+    };
+    if revert {
+      for record in transaction.records.iter().rev() {
+        applier.revert(record);
+      }
+    } else {
+      for record in transaction.records.iter() {
+        applier.replay(record);
+      }
+    }
+    drop(applier);
+    self.state.lock().handle_remapping = remapping;
+
+    *self.applying.write() = None;
+  }
+}
+
+fn watch_table(table: &ArcTable, state: Weak<Mutex<UndoHistoryState>>, applying: ApplyingThread) {
+  let entity = table.internal.type_id;
+
+  let state_ = state.clone();
+  let applying_ = applying.clone();
+  table.entity_watchers().on(move |change| {
+    let Some(state) = state_.upgrade() else {
+      return true;
+    };
+    if is_applying_thread(&applying_) {
+      return false;
+    }
+    let mut state = state.lock();
+    match change {
+      ScopedMessage::Start => {
+        if let Some(recording) = &mut state.recording {
+          recording.scoped_write_count += 1;
+        }
+      }
+      ScopedMessage::End => {}
+      ScopedMessage::ReserveSpace(_) => {}
+      ScopedMessage::Message(change) => match change {
+        EntityChange::NewEntityStartCreate(handle) => {
+          state.record(DBChangeRecord::EntityCreated {
+            entity,
+            handle: *handle,
+          });
+        }
+        EntityChange::NewEntityCreated(_) => {}
+        EntityChange::DeleteEntity(handle) => {
+          state.record(DBChangeRecord::EntityDeleted {
+            entity,
+            handle: *handle,
+          });
+        }
+      },
+    }
+    false
+  });
+
+  let state_ = state.clone();
+  let applying_ = applying.clone();
+  table.component_define_watchers().on(move |component| {
+    if state_.strong_count() == 0 {
+      return true;
+    }
+    watch_component(component, state_.clone(), applying_.clone());
+    false
+  });
+
+  table.visit_components(|component| watch_component(component, state.clone(), applying.clone()));
+}
+
+fn watch_component(
+  component: &ComponentUntyped,
+  state: Weak<Mutex<UndoHistoryState>>,
+  applying: ApplyingThread,
+) {
+  let entity = component.entity_type_id;
+  let c_id = component.component_type_id;
+  let is_fk = component.as_foreign_key.is_some();
+
+  component.data_watchers.on(move |change| {
+    let Some(state) = state.upgrade() else {
+      return true;
+    };
+    if is_applying_thread(&applying) {
+      return false;
+    }
+    if let ScopedMessage::Message(change) = change {
+      let mut state = state.lock();
+      if state.recording.is_none() {
+        return false;
+      }
+
+      let record_value = |(data_ptr, dyn_ptr): &(DataPtr, *const dyn DynDataBaseDataType)| unsafe {
+        if is_fk {
+          DBRecordedValue::ForeignKey((*data_ptr as *const ForeignKeyComponentData).read())
+        } else {
+          DBRecordedValue::Data((**dyn_ptr).clone_boxed())
+        }
+      };
+
+      let (old, new) = match &change.change {
+        ValueChange::Delta(new, old) => (old.as_ref().map(record_value), Some(record_value(new))),
+        ValueChange::Remove(old) => (Some(record_value(old)), None),
+      };
+
+      state.record(DBChangeRecord::ComponentChanged {
+        entity,
+        component: c_id,
+        handle: change.idx,
+        old,
+        new,
+      });
+    }
+    false
+  });
+}
+
+struct TransactionApplier<'a> {
+  db: &'a Database,
+  /// the last used table writer, reused if the next record is the same entity type.
+  writer: Option<TableWriterUntyped>,
+  remapping: &'a mut HandleRemapping,
+}
+
+impl TransactionApplier<'_> {
+  fn revert(&mut self, record: &DBChangeRecord) {
+    match record {
+      DBChangeRecord::EntityCreated { entity, handle } => self.delete(*entity, *handle),
+      DBChangeRecord::EntityDeleted { entity, handle } => self.create(*entity, *handle),
+      DBChangeRecord::ComponentChanged {
+        entity,
+        component,
+        handle,
+        old,
+        ..
+      } => {
+        if let Some(old) = old {
+          self.write(*entity, *component, *handle, old);
+        }
+      }
+    }
+  }
+
+  fn replay(&mut self, record: &DBChangeRecord) {
+    match record {
+      DBChangeRecord::EntityCreated { entity, handle } => self.create(*entity, *handle),
+      DBChangeRecord::EntityDeleted { entity, handle } => self.delete(*entity, *handle),
+      DBChangeRecord::ComponentChanged {
+        entity,
+        component,
+        handle,
+        new,
+        ..
+      } => {
+        if let Some(new) = new {
+          self.write(*entity, *component, *handle, new);
+        }
+      }
+    }
+  }
+
+  fn resolve(&self, entity: EntityId, mut handle: RawEntityHandle) -> RawEntityHandle {
+    while let Some(mapped) = self.remapping.get(&(entity, handle)) {
+      handle = *mapped;
+    }
+    handle
+  }
+
+  fn writer(&mut self, entity: EntityId) -> &mut TableWriterUntyped {
+    if self.writer.as_ref().map(|w| w.type_id) != Some(entity) {
+      // drop the previous writer first to avoid holding multiple table locks
+      self.writer = None;
+      self.writer = Some(self.db.entity_writer_untyped_dyn(entity));
+    }
+    self.writer.as_mut().unwrap()
+  }
+
+  fn create(&mut self, entity: EntityId, handle: RawEntityHandle) {
+    let previous = self.resolve(entity, handle);
+    let created = self.writer(entity).new_entity(|w| w);
+    self.remapping.insert((entity, previous), created);
+  }
+
+  fn delete(&mut self, entity: EntityId, handle: RawEntityHandle) {
+    let handle = self.resolve(entity, handle);
+    let writer = self.writer(entity);
+    if writer.allocator.contains(handle.0) {
+      writer.delete_entity(handle);
+    } else {
+      log::warn!("undo history skipped deleting a not exist entity {handle}");
+    }
+  }
+
+  fn write(
+    &mut self,
+    entity: EntityId,
+    component: ComponentId,
+    handle: RawEntityHandle,
+    value: &DBRecordedValue,
+  ) {
+    let handle = self.resolve(entity, handle);
+    let fk_target = match value {
+      DBRecordedValue::ForeignKey(Some(target)) => {
+        let foreign_entity = self
+          .db
+          .access_table_dyn(entity, |t| {
+            t.internal.foreign_keys.read().get(&component).copied()
+          })
+          .expect("foreign key declaration not found");
+        Some(self.resolve(foreign_entity, *target))
+      }
+      _ => None,
+    };
+
+    let writer = self.writer(entity);
+    if !writer.allocator.contains(handle.0) {
+      log::warn!("undo history skipped writing a not exist entity {handle}");
+      return;
+    }
+    let Some(component) = writer.get_component_by_id_mut(component) else {
+      log::warn!("undo history skipped writing a not exist component");
+      return;
+    };
+
+    unsafe {
+      // safety, the handle is checked alive above, the data type matches the component
+      match value {
+        DBRecordedValue::ForeignKey(_) => {
+          let fk: ForeignKeyComponentData = fk_target;
+          component.write_component(handle, &fk as *const ForeignKeyComponentData as DataPtr);
+        }
+        DBRecordedValue::Data(data) => component.write_component(handle, data.as_data_ptr()),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+fn living_count<E: EntitySemantic>(db: &Database) -> usize {
+  db.access_table_dyn(E::entity_id(), |t| t.living_entity_count())
+}
+
+#[cfg(test)]
+fn first_living<E: EntitySemantic>(db: &Database) -> EntityHandle<E> {
+  let handle = db.access_table_dyn(E::entity_id(), |t| t.iter_entity_idx().next().unwrap());
+  unsafe { EntityHandle::from_raw(handle) }
+}
+
+#[test]
+fn test_undo_redo() {
+  declare_entity!(UndoTestNode);
+  declare_component!(UndoTestNodeValue, UndoTestNode, u32);
+  declare_component!(UndoTestNodeBuffer, UndoTestNode, ExternalRefPtr<Vec<u8>>);
+  declare_entity!(UndoTestModel);
+  declare_foreign_key!(UndoTestModelRefNode, UndoTestModel, UndoTestNode);
+
+  let db = Database::default();
+  db.declare_entity::<UndoTestNode>()
+    .declare_component::<UndoTestNodeValue>()
+    .declare_component::<UndoTestNodeBuffer>();
+  db.declare_entity::<UndoTestModel>()
+    .declare_foreign_key::<UndoTestModelRefNode>();
+
+  let history = DBUndoHistory::new(&db, Default::default());
+
+  let buffer = ExternalRefPtr::new(vec![1, 2, 3]);
+  let (node, model) = history.transaction("create", || {
+    let node = db.entity_writer::<UndoTestNode>().new_entity(|w| {
+      w.write::<UndoTestNodeValue>(&1)
+        .write::<UndoTestNodeBuffer>(&buffer)
+    });
+    let model = db
+      .entity_writer::<UndoTestModel>()
+      .new_entity(|w| w.write::<UndoTestModelRefNode>(&node.some_handle()));
+    (node, model)
+  });
+
+  history.transaction("modify", || {
+    db.write::<UndoTestNodeValue>().write(node, 2);
+  });
+
+  history.transaction("delete", || {
+    db.entity_writer::<UndoTestModel>().delete_entity(model);
+    db.entity_writer::<UndoTestNode>().delete_entity(node);
+  });
+
+  assert_eq!(living_count::<UndoTestNode>(&db), 0);
+  assert_eq!(
+    history.undo_transaction_names(),
+    ["create", "modify", "delete"]
+  );
+
+  assert_eq!(history.undo().unwrap().as_deref(), Some("delete"));
+  let node = first_living::<UndoTestNode>(&db);
+  let model = first_living::<UndoTestModel>(&db);
+  assert_eq!(db.read::<UndoTestNodeValue>().get_value(node), Some(2));
+  assert_eq!(
+    db.read::<UndoTestNodeBuffer>().get_value(node),
+    Some(buffer.clone())
+  );
+  assert_eq!(
+    db.read_foreign_key::<UndoTestModelRefNode>().get(model),
+    Some(node)
+  );
+
+  assert_eq!(history.undo().unwrap().as_deref(), Some("modify"));
+  assert_eq!(db.read::<UndoTestNodeValue>().get_value(node), Some(1));
+
+  assert_eq!(history.undo().unwrap().as_deref(), Some("create"));
+  assert_eq!(living_count::<UndoTestNode>(&db), 0);
+  assert_eq!(living_count::<UndoTestModel>(&db), 0);
+  assert!(!history.can_undo());
+
+  assert_eq!(history.redo().unwrap().as_deref(), Some("create"));
+  assert_eq!(history.redo().unwrap().as_deref(), Some("modify"));
+  let node = first_living::<UndoTestNode>(&db);
+  let model = first_living::<UndoTestModel>(&db);
+  assert_eq!(db.read::<UndoTestNodeValue>().get_value(node), Some(2));
+  assert_eq!(
+    db.read_foreign_key::<UndoTestModelRefNode>().get(model),
+    Some(node)
+  );
+
+  assert_eq!(history.redo().unwrap().as_deref(), Some("delete"));
+  assert_eq!(living_count::<UndoTestNode>(&db), 0);
+  assert!(!history.can_redo());
+}
+
+#[test]
+fn test_undo_history_budget_and_cancel() {
+  declare_entity!(UndoBudgetTestEntity);
+  declare_component!(UndoBudgetTestValue, UndoBudgetTestEntity, u32);
+
+  let db = Database::default();
+  db.declare_entity::<UndoBudgetTestEntity>()
+    .declare_component::<UndoBudgetTestValue>();
+
+  let history = DBUndoHistory::new(
+    &db,
+    DBUndoHistoryConfig {
+      max_transaction_count: 2,
+      ..Default::default()
+    },
+  );
+
+  let e = db.entity_writer::<UndoBudgetTestEntity>().new_entity(|w| w);
+
+  for i in 1..5 {
+    history.transaction(format!("set {i}"), || {
+      db.write::<UndoBudgetTestValue>().write(e, i);
+    });
+  }
+  assert_eq!(history.undo_transaction_names(), ["set 3", "set 4"]);
+  assert!(history.memory_usage_in_bytes() > 0);
+
+  history.begin_transaction("canceled");
+  db.write::<UndoBudgetTestValue>().write(e, 100);
+  history.cancel_transaction();
+  assert_eq!(db.read::<UndoBudgetTestValue>().get_value(e), Some(4));
+
+  // changes outside of transaction are not recorded
+  db.write::<UndoBudgetTestValue>().write(e, 5);
+  assert_eq!(history.undo_transaction_names(), ["set 3", "set 4"]);
+}
+
+#[test]
+fn test_undo_history_recording_and_redo_budget() {
+  declare_entity!(UndoRedoBudgetTestEntity);
+  declare_component!(UndoRedoBudgetTestValue, UndoRedoBudgetTestEntity, u32);
+
+  let db = Database::default();
+  db.declare_entity::<UndoRedoBudgetTestEntity>()
+    .declare_component::<UndoRedoBudgetTestValue>();
+
+  let delete_in_transaction = |history: &DBUndoHistory| {
+    let e = db
+      .entity_writer::<UndoRedoBudgetTestEntity>()
+      .new_entity(|w| w);
+    history.transaction("delete", || {
+      db.entity_writer::<UndoRedoBudgetTestEntity>()
+        .delete_entity(e);
+    });
+  };
+
+  // measure the memory usage before and after the undo, the undo recreates the entity so
+  // the handle remapping will grow.
+  let probe = DBUndoHistory::new(&db, Default::default());
+  delete_in_transaction(&probe);
+  let usage_after_commit = probe.memory_usage_in_bytes();
+  probe.undo().unwrap();
+  assert!(probe.memory_usage_in_bytes() > usage_after_commit);
+  drop(probe);
+
+  let history = DBUndoHistory::new(
+    &db,
+    DBUndoHistoryConfig {
+      max_memory_usage_in_bytes: usage_after_commit,
+      ..Default::default()
+    },
+  );
+  delete_in_transaction(&history);
+  assert!(history.can_undo());
+
+  history.begin_transaction("recording");
+  assert_eq!(
+    history.undo(),
+    Err(DBUndoHistoryError::TransactionRecording)
+  );
+  assert_eq!(
+    history.redo(),
+    Err(DBUndoHistoryError::TransactionRecording)
+  );
+  history.commit_transaction();
+
+  // the undone transaction exceeds the budget in the redo stack, and it should be evicted
+  assert_eq!(history.undo().unwrap().as_deref(), Some("delete"));
+  assert!(!history.can_redo());
+  assert!(history.memory_usage_in_bytes() <= usage_after_commit);
+}
+
+#[test]
+fn test_undo_history_nested_cancel() {
+  declare_entity!(UndoNestedCancelTestEntity);
+  declare_component!(UndoNestedCancelTestValue, UndoNestedCancelTestEntity, u32);
+
+  let db = Database::default();
+  db.declare_entity::<UndoNestedCancelTestEntity>()
+    .declare_component::<UndoNestedCancelTestValue>();
+  let history = DBUndoHistory::new(&db, Default::default());
+
+  let e = db
+    .entity_writer::<UndoNestedCancelTestEntity>()
+    .new_entity(|w| w);
+
+  history.transaction("outer", || {
+    db.write::<UndoNestedCancelTestValue>().write(e, 1);
+    history.begin_transaction("inner");
+    db.write::<UndoNestedCancelTestValue>().write(e, 2);
+    history.cancel_transaction();
+    // the whole outer transaction is reverted at once
+    assert_eq!(db.read::<UndoNestedCancelTestValue>().get_value(e), Some(0));
+    assert!(history.is_recording());
+
+    // nothing is recorded after the cancel
+    db.write::<UndoNestedCancelTestValue>().write(e, 3);
+  });
+  assert!(!history.is_recording());
+  assert!(!history.can_undo());
+  assert_eq!(db.read::<UndoNestedCancelTestValue>().get_value(e), Some(3));
+
+  // the canceled outer level can also be ended by cancel
+  history.begin_transaction("outer");
+  history.begin_transaction("inner");
+  db.write::<UndoNestedCancelTestValue>().write(e, 4);
+  history.cancel_transaction();
+  history.cancel_transaction();
+  assert!(!history.is_recording());
+  assert_eq!(db.read::<UndoNestedCancelTestValue>().get_value(e), Some(3));
+}
+
+#[test]
+fn test_undo_history_evict_handle_remapping() {
+  declare_entity!(UndoRemappingTestEntity);
+  declare_component!(UndoRemappingTestValue, UndoRemappingTestEntity, u32);
+
+  let db = Database::default();
+  db.declare_entity::<UndoRemappingTestEntity>()
+    .declare_component::<UndoRemappingTestValue>();
+  let history = DBUndoHistory::new(
+    &db,
+    DBUndoHistoryConfig {
+      max_transaction_count: 1,
+      ..Default::default()
+    },
+  );
+
+  let e = db
+    .entity_writer::<UndoRemappingTestEntity>()
+    .new_entity(|w| w);
+  let other = db
+    .entity_writer::<UndoRemappingTestEntity>()
+    .new_entity(|w| w);
+
+  history.transaction("delete", || {
+    db.entity_writer::<UndoRemappingTestEntity>()
+      .delete_entity(e);
+  });
+  // recreate and delete again, the remapping is required by the delete transaction
+  history.undo().unwrap();
+  history.redo().unwrap();
+  history.undo().unwrap();
+  history.redo().unwrap();
+  assert_eq!(history.state.lock().handle_remapping.len(), 2);
+
+  // the delete transaction is evicted, no one references the remapping anymore
+  history.transaction("modify", || {
+    db.write::<UndoRemappingTestValue>().write(other, 1);
+  });
+  assert_eq!(history.undo_transaction_names(), ["modify"]);
+  assert!(history.state.lock().handle_remapping.is_empty());
+
+  history.undo().unwrap();
+  assert_eq!(
+    db.read::<UndoRemappingTestValue>().get_value(other),
+    Some(0)
+  );
+}
@@ -5,6 +5,7 @@ pub struct Database {
   pub tables: Arc<RwLock<FastHashMap<EntityId, ArcTable>>>,
   pub entity_meta_watcher: EventSource<ArcTable>,
   pub name_mapping: Arc<RwLock<DBNameMapping>>,
+  pub(crate) foreign_key_references: Arc<RwLock<DBForeignKeyReferences>>,
   pub(crate) enable_internal_validation: bool,
 }
 
@@ -14,6 +15,7 @@ impl Default for Database {
       tables: Default::default(),
       entity_meta_watcher: Default::default(),
       name_mapping: Default::default(),
+      foreign_key_references: Default::default(),
       enable_internal_validation: cfg!(debug_assertions),
     }
   }
@@ -41,6 +43,7 @@ impl Database {
       e_id,
       name,
       self.name_mapping.clone(),
+      self.foreign_key_references.clone(),
       self.enable_internal_validation,
     );
     self.entity_meta_watcher.emit(&table);
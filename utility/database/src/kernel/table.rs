@@ -18,6 +18,7 @@ impl ArcTable {
     type_id: EntityId,
     name: String,
     name_mapping: Arc<RwLock<DBNameMapping>>,
+    foreign_key_references: Arc<RwLock<DBForeignKeyReferences>>,
     enable_internal_validation: bool,
   ) -> Self {
     Self {
@@ -25,6 +26,7 @@ impl ArcTable {
         type_id,
         name,
         name_mapping,
+        foreign_key_references,
         enable_internal_validation,
       )),
     }
@@ -35,6 +37,27 @@ impl ArcTable {
   }
 
   pub fn declare_foreign_key_dyn(&self, semantic: ComponentId, foreign_entity_type_id: EntityId) {
+    self.declare_foreign_key_with_policy_dyn(
+      semantic,
+      foreign_entity_type_id,
+      ForeignKeyDeletePolicy::default(),
+    );
+  }
+
+  /// The policy is enforced by the table writer when the referenced entity is deleted,
+  /// see [ForeignKeyDeletePolicy].
+  pub fn declare_foreign_key_with_policy_dyn(
+    &self,
+    semantic: ComponentId,
+    foreign_entity_type_id: EntityId,
+    policy: ForeignKeyDeletePolicy,
+  ) {
+    self.internal.foreign_key_references.write().register(
+      foreign_entity_type_id,
+      self,
+      semantic,
+      policy,
+    );
     let mut foreign_keys = self.internal.foreign_keys.write();
     self
       .internal
@@ -95,6 +118,10 @@ impl ArcTable {
     &self.internal.short_name
   }
 
+  pub fn entity_id(&self) -> EntityId {
+    self.internal.type_id
+  }
+
   pub fn get_handle_at(&self, index: usize) -> Option<RawEntityHandle> {
     let inner = self.internal.allocator.make_read_holder();
     let handle = inner.get_handle(index)?;
@@ -117,6 +144,9 @@ impl ArcTable {
     for c in components.values() {
       byte_count += c.data.memory_usage_in_bytes()
     }
+    drop(components);
+
+    byte_count += self.indexes_memory_usage_in_bytes();
 
     byte_count
   }
@@ -157,6 +187,11 @@ pub struct Table {
   ///
   /// components id => foreign id
   pub(crate) foreign_keys: RwLock<FastHashMap<ComponentId, EntityId>>,
+  /// Shared by all tables in the database, records who references the entity and
+  /// the delete policy.
+  pub(crate) foreign_key_references: Arc<RwLock<DBForeignKeyReferences>>,
+  /// The secondary indexes of components, keyed by the component and the index type.
+  pub(crate) indexes: RwLock<FastHashMap<(ComponentId, TypeId), Box<dyn ComponentIndexUntyped>>>,
 
   pub(crate) components_meta_watchers: EventSource<ComponentUntyped>,
   pub(crate) foreign_key_meta_watchers: EventSource<(ComponentId, EntityId)>,
@@ -171,6 +206,7 @@ impl Table {
     type_id: EntityId,
     name: String,
     name_mapping: Arc<RwLock<DBNameMapping>>,
+    foreign_key_references: Arc<RwLock<DBForeignKeyReferences>>,
     enable_internal_validation: bool,
   ) -> Self {
     Self {
@@ -180,6 +216,8 @@ impl Table {
       allocator: Default::default(),
       components: Default::default(),
       foreign_keys: Default::default(),
+      foreign_key_references,
+      indexes: Default::default(),
       components_meta_watchers: Default::default(),
       foreign_key_meta_watchers: Default::default(),
       entity_watchers: Default::default(),
@@ -229,10 +267,23 @@ impl<E: EntitySemantic> Clone for TypedArcTable<E> {
 }
 
 impl<E: EntitySemantic> TypedArcTable<E> {
-  pub fn new(type_id: EntityId, name: String, name_mapping: Arc<RwLock<DBNameMapping>>) -> Self {
+  /// The foreign_key_references should be shared by all tables of the database, or the delete
+  /// policies of the foreign keys referencing this table will not be enforced.
+  pub fn new(
+    type_id: EntityId,
+    name: String,
+    name_mapping: Arc<RwLock<DBNameMapping>>,
+    foreign_key_references: Arc<RwLock<DBForeignKeyReferences>>,
+  ) -> Self {
     Self {
       phantom: Default::default(),
-      inner: ArcTable::new(type_id, name, name_mapping, cfg!(debug_assertions)),
+      inner: ArcTable::new(
+        type_id,
+        name,
+        name_mapping,
+        foreign_key_references,
+        cfg!(debug_assertions),
+      ),
     }
   }
 
@@ -275,14 +326,23 @@ impl<E: EntitySemantic> TypedArcTable<E> {
     self
   }
 
-  pub fn declare_foreign_key<S: ForeignKeySemantic<Entity = E>>(mut self) -> Self {
+  pub fn declare_foreign_key<S: ForeignKeySemantic<Entity = E>>(self) -> Self {
+    self.declare_foreign_key_with_policy::<S>(ForeignKeyDeletePolicy::default())
+  }
+
+  pub fn declare_foreign_key_with_policy<S: ForeignKeySemantic<Entity = E>>(
+    mut self,
+    policy: ForeignKeyDeletePolicy,
+  ) -> Self {
     self = self.declare_component_impl::<S>(
       S::ForeignEntity::entity_id().into(),
       init_linear_storage::<S>(),
     );
-    self
-      .inner
-      .declare_foreign_key_dyn(S::component_id(), S::ForeignEntity::entity_id());
+    self.inner.declare_foreign_key_with_policy_dyn(
+      S::component_id(),
+      S::ForeignEntity::entity_id(),
+      policy,
+    );
     self
   }
 
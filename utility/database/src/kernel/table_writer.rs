@@ -21,6 +21,7 @@ impl ArcTable {
 
     TableWriterUntyped {
       type_id: self.internal.type_id,
+      foreign_key_references: self.internal.foreign_key_references.clone(),
       components,
       entity_watchers: self.internal.entity_watchers.clone(),
       allocator: self.internal.allocator.make_write_holder(),
@@ -31,6 +32,7 @@ impl ArcTable {
 pub struct TableWriterUntyped {
   pub(crate) type_id: EntityId,
   pub(crate) allocator: LockWriteGuardHolder<TableAllocator>,
+  pub(crate) foreign_key_references: Arc<RwLock<DBForeignKeyReferences>>,
   /// this change ptr type is ScopedValueChange<()>, the lifetime of the ptr is only valid
   /// in the callback scope.
   entity_watchers: EventSource<EntityChangeMessage>,
@@ -167,9 +169,29 @@ impl TableWriterUntyped {
     handle
   }
 
-  /// note, the referential integrity is not guaranteed and should be guaranteed by the upper level
-  /// implementations
+  /// The delete policies of the foreign keys that reference this entity are enforced, see
+  /// [ForeignKeyDeletePolicy]. If the deletion is restricted, nothing is deleted and the error
+  /// is logged, use [Self::try_delete_entity] to handle the error.
+  ///
+  /// note, for the foreign keys using the default policy, the referential integrity is not
+  /// guaranteed and should be guaranteed by the upper level implementations
   pub fn delete_entity(&mut self, handle: RawEntityHandle) {
+    self.delete_entity_with(handle, &mut [])
+  }
+
+  /// Same as [Self::delete_entity], see [Self::try_delete_entity_with] for the held writers.
+  pub fn delete_entity_with(
+    &mut self,
+    handle: RawEntityHandle,
+    held: &mut [&mut TableWriterUntyped],
+  ) {
+    if let Err(e) = self.try_delete_entity_with(handle, held) {
+      log::error!("{e}");
+    }
+  }
+
+  /// Delete the entity without checking any foreign key delete policy.
+  pub(crate) fn delete_entity_unchecked(&mut self, handle: RawEntityHandle) {
     let cap_before = self.allocator.capacity();
     self.allocator.remove(handle.0).unwrap();
     let cap_after = self.allocator.capacity();
@@ -208,6 +230,19 @@ impl TableWriterImpl {
     }
   }
 
+  /// # Safety
+  ///
+  /// See [ComponentStorageReadWriteView::set_value]
+  pub unsafe fn write_component_by_small_serialize_data(
+    &mut self,
+    idx: RawEntityHandle,
+    src: DatabaseSerializedFieldBufferOrForeignKey,
+  ) {
+    unsafe {
+      self.component.write_by_small_serialize_data(idx, src);
+    }
+  }
+
   /// # Safety
   ///
   /// See [ComponentStorageReadWriteView::set_value_init]
@@ -222,8 +257,9 @@ impl TableWriterImpl {
   /// idx must point to living data
   pub unsafe fn clone_component_value(&mut self, src: RawEntityHandle, dst: RawEntityHandle) {
     unsafe {
+      // the dst is newly created, so init it to make sure the change is always emitted
       let src = self.component.get_unchecked(src);
-      self.write_component(dst, src);
+      self.write_init_component_value(dst, Some(src));
     }
   }
 
@@ -242,3 +278,25 @@ impl TableWriterImpl {
     }
   }
 }
+
+#[test]
+fn test_clone_entity_emits_all_component_values() {
+  declare_entity!(CloneTestEntity);
+  declare_component!(CloneTestEntityValue, CloneTestEntity, u32);
+
+  let db = Database::default();
+  db.declare_entity::<CloneTestEntity>()
+    .declare_component::<CloneTestEntityValue>()
+    .declare_hash_index::<CloneTestEntityValue>();
+
+  let mut writer = db.entity_writer::<CloneTestEntity>();
+  let a = writer.new_entity(|w| w);
+  // the slot of the cloned entity is newly allocated, the value must be initialized and
+  // observed by the watchers even if it equals the default value
+  let b = writer.clone_entity(a);
+  drop(writer);
+
+  let index = db.read_hash_index::<CloneTestEntityValue>();
+  assert_eq!(index.count(&0), 2);
+  assert!(index.query(&0).any(|e| e == b));
+}
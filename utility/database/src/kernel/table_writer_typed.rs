@@ -4,7 +4,7 @@ use crate::*;
 /// creation and modification
 pub struct TableWriter<E: EntitySemantic> {
   phantom: PhantomData<E>, //
-  inner: TableWriterUntyped,
+  pub(crate) inner: TableWriterUntyped,
 }
 
 impl TableWriterUntyped {
@@ -31,6 +31,10 @@ impl<E: EntitySemantic> TableWriter<E> {
     self.inner
   }
 
+  pub fn as_untyped_mut(&mut self) -> &mut TableWriterUntyped {
+    &mut self.inner
+  }
+
   pub fn notify_reserve_changes(&mut self, count: usize) {
     self.inner.notify_reserve_changes(count);
   }
@@ -131,8 +135,7 @@ impl<E: EntitySemantic> TableWriter<E> {
     }
   }
 
-  /// note, the referential integrity is not guaranteed and should be guaranteed by the upper level
-  /// implementations
+  /// See [TableWriterUntyped::delete_entity]
   pub fn delete_entity(&mut self, handle: EntityHandle<E>) {
     self.inner.delete_entity(handle.handle)
   }
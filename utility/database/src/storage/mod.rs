@@ -68,6 +68,12 @@ pub trait DynDataBaseDataType {
   fn shape(&self) -> &'static facet::Shape<'static>;
   /// this function will be removed in the future.
   fn debug_value(&self) -> String;
+
+  /// Clone the data into a type erased box. Unlike the serialization, the clone keeps
+  /// the shared part of the data(for example [ExternalRefPtr]) shared.
+  fn clone_boxed(&self) -> Box<dyn DynDataBaseDataType + Send + Sync>;
+  /// Get the thin ptr of the data, this ptr can be used as the input of the storage write.
+  fn as_data_ptr(&self) -> DataPtr;
 }
 
 impl<T: DataBaseDataType> DynDataBaseDataType for T {
@@ -86,6 +92,14 @@ impl<T: DataBaseDataType> DynDataBaseDataType for T {
   fn debug_value(&self) -> String {
     format!("{:#?}", self)
   }
+
+  fn clone_boxed(&self) -> Box<dyn DynDataBaseDataType + Send + Sync> {
+    Box::new(self.clone())
+  }
+
+  fn as_data_ptr(&self) -> DataPtr {
+    self as *const T as DataPtr
+  }
 }
 
 impl<T> DataBaseDataType for T
@@ -0,0 +1,258 @@
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::*;
+
+/// Records the shared context graph for debugging, per frame:
+///
+/// - every visited shared context and who consumes it
+/// - how many times the shared logic is executed and how long it takes
+/// - whether the shared dual query produces the delta and how large
+///
+/// The recording is disabled by default, the frame boundary is decided by the
+/// caller of [Self::begin_frame].
+#[derive(Clone, Default)]
+pub struct QueryGraphRecorder {
+  enabled: Arc<AtomicBool>,
+  internal: Arc<Mutex<QueryGraphRecorderInternal>>,
+}
+
+#[derive(Default)]
+struct QueryGraphRecorderInternal {
+  frame_index: u64,
+  nodes: FastHashMap<ShareKey, QueryGraphNode>,
+  edges: FastHashSet<(Option<ShareKey>, ShareKey)>,
+  last_frame: Option<Arc<QueryGraphFrame>>,
+}
+
+/// The root consumer, the consumer that is not a shared context.
+pub const QUERY_GRAPH_ROOT_ID: &str = "root";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryGraphNode {
+  pub id: String,
+  pub label: String,
+  /// how many times the shared logic is executed in this frame, each stage is counted.
+  pub execute_count: u32,
+  /// the time spent in the shared logic itself, excluding the spawned task.
+  pub logic_time_in_ms: f32,
+  /// the time from the task spawned to the task finished, including the time waiting
+  /// for the upstream.
+  pub task_time_in_ms: Option<f32>,
+  /// the delta size produced by the shared dual query, None if not a dual query or not
+  /// computed in this frame.
+  pub delta_size: Option<usize>,
+}
+
+/// The edge direction is the data flow direction: from the dependency to the consumer.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryGraphEdge {
+  pub from: String,
+  pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryGraphFrame {
+  pub frame_index: u64,
+  pub nodes: Vec<QueryGraphNode>,
+  pub edges: Vec<QueryGraphEdge>,
+}
+
+fn share_key_id(key: &ShareKey) -> String {
+  match key {
+    ShareKey::TypeId(id) => format!("{:?}", id),
+    ShareKey::Hash(hash) => format!("Hash({:x})", hash),
+  }
+}
+
+impl QueryGraphRecorder {
+  pub fn is_enabled(&self) -> bool {
+    self.enabled.load(Ordering::Relaxed)
+  }
+
+  pub fn set_enabled(&self, enabled: bool) {
+    self.enabled.store(enabled, Ordering::Relaxed);
+    if !enabled {
+      let mut internal = self.internal.lock();
+      internal.nodes.clear();
+      internal.edges.clear();
+    }
+  }
+
+  /// Finish the recording of the current frame, the result can be accessed by
+  /// [Self::last_frame].
+  pub fn begin_frame(&self) {
+    if !self.is_enabled() {
+      return;
+    }
+    let mut internal = self.internal.lock();
+    let internal = &mut *internal;
+
+    let mut nodes: Vec<_> = internal.nodes.drain().map(|(_, v)| v).collect();
+    nodes.sort_by(|a, b| a.label.cmp(&b.label).then_with(|| a.id.cmp(&b.id)));
+
+    let mut edges: Vec<_> = internal
+      .edges
+      .drain()
+      .map(|(consumer, key)| QueryGraphEdge {
+        from: share_key_id(&key),
+        to: consumer
+          .as_ref()
+          .map(share_key_id)
+          .unwrap_or_else(|| QUERY_GRAPH_ROOT_ID.to_string()),
+      })
+      .collect();
+    edges.sort_by(|a, b| a.from.cmp(&b.from).then_with(|| a.to.cmp(&b.to)));
+
+    internal.last_frame = Some(Arc::new(QueryGraphFrame {
+      frame_index: internal.frame_index,
+      nodes,
+      edges,
+    }));
+    internal.frame_index += 1;
+  }
+
+  pub fn last_frame(&self) -> Option<Arc<QueryGraphFrame>> {
+    self.internal.lock().last_frame.clone()
+  }
+
+  fn with_node(&self, key: ShareKey, f: impl FnOnce(&mut QueryGraphNode)) {
+    if !self.is_enabled() {
+      return;
+    }
+    let mut internal = self.internal.lock();
+    if let Some(node) = internal.nodes.get_mut(&key) {
+      f(node);
+    }
+  }
+
+  pub(crate) fn record_dependency(
+    &self,
+    consumer: Option<ShareKey>,
+    key: ShareKey,
+    debug_label: &str,
+  ) {
+    if !self.is_enabled() {
+      return;
+    }
+    let mut internal = self.internal.lock();
+    internal.edges.insert((consumer, key));
+    internal.nodes.entry(key).or_insert_with(|| QueryGraphNode {
+      id: share_key_id(&key),
+      label: debug_label.to_string(),
+      execute_count: 0,
+      logic_time_in_ms: 0.,
+      task_time_in_ms: None,
+      delta_size: None,
+    });
+  }
+
+  pub(crate) fn record_logic_execution(&self, key: ShareKey, time: Duration) {
+    self.with_node(key, |node| {
+      node.execute_count += 1;
+      node.logic_time_in_ms += time.as_secs_f32() * 1000.;
+    });
+  }
+
+  pub(crate) fn record_task(&self, key: ShareKey, time: Duration) {
+    self.with_node(key, |node| {
+      *node.task_time_in_ms.get_or_insert(0.) += time.as_secs_f32() * 1000.;
+    });
+  }
+
+  pub(crate) fn record_delta(&self, key: ShareKey, delta_size: usize) {
+    self.with_node(key, |node| {
+      *node.delta_size.get_or_insert(0) += delta_size;
+    });
+  }
+}
+
+impl QueryGraphFrame {
+  pub fn to_json(&self) -> String {
+    serde_json::to_string_pretty(self).unwrap()
+  }
+
+  /// The executed node is filled, the node produced non empty delta is highlighted.
+  pub fn to_dot(&self) -> String {
+    fn escape(s: &str) -> String {
+      s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    let mut dot = String::new();
+    writeln!(dot, "digraph query_graph {{").unwrap();
+    writeln!(dot, "  rankdir=LR;").unwrap();
+    writeln!(dot, "  node [shape=box];").unwrap();
+    writeln!(dot, "  \"{QUERY_GRAPH_ROOT_ID}\" [shape=ellipse];").unwrap();
+    for node in &self.nodes {
+      let mut label = escape(&node.label);
+      if node.execute_count > 0 {
+        write!(
+          label,
+          "\\nexecuted: {}, {:.3}ms",
+          node.execute_count, node.logic_time_in_ms
+        )
+        .unwrap();
+      }
+      if let Some(task_time) = node.task_time_in_ms {
+        write!(label, "\\ntask: {:.3}ms", task_time).unwrap();
+      }
+      if let Some(delta_size) = node.delta_size {
+        write!(label, "\\ndelta: {}", delta_size).unwrap();
+      }
+
+      let style = match (node.execute_count > 0, node.delta_size.unwrap_or(0) > 0) {
+        (_, true) => ", style=filled, fillcolor=orange",
+        (true, false) => ", style=filled, fillcolor=lightblue",
+        _ => "",
+      };
+      writeln!(dot, "  \"{}\" [label=\"{}\"{}];", node.id, label, style).unwrap();
+    }
+    for edge in &self.edges {
+      writeln!(dot, "  \"{}\" -> \"{}\";", edge.from, edge.to).unwrap();
+    }
+    writeln!(dot, "}}").unwrap();
+    dot
+  }
+}
+
+#[test]
+fn test_query_graph_recorder() {
+  let recorder = QueryGraphRecorder::default();
+  let a = ShareKey::Hash(1);
+  let b = ShareKey::Hash(2);
+
+  // not recorded if disabled
+  recorder.record_dependency(None, a, "a");
+  recorder.begin_frame();
+  assert!(recorder.last_frame().is_none());
+
+  recorder.set_enabled(true);
+  recorder.record_dependency(None, a, "a");
+  recorder.record_dependency(Some(a), b, "b");
+  recorder.record_dependency(Some(a), b, "b");
+  recorder.record_logic_execution(b, Duration::from_millis(2));
+  recorder.record_delta(b, 3);
+  recorder.begin_frame();
+
+  let frame = recorder.last_frame().unwrap();
+  assert_eq!(frame.nodes.len(), 2);
+  assert_eq!(frame.edges.len(), 2);
+  let b_node = &frame.nodes[1];
+  assert_eq!(b_node.label, "b");
+  assert_eq!(b_node.execute_count, 1);
+  assert_eq!(b_node.delta_size, Some(3));
+  assert!(frame.nodes[0].delta_size.is_none());
+
+  let dot = frame.to_dot();
+  assert!(dot.contains("\"Hash(2)\" -> \"Hash(1)\";"));
+  assert!(dot.contains("\"Hash(1)\" -> \"root\";"));
+  let json: serde_json::Value = serde_json::from_str(&frame.to_json()).unwrap();
+  assert_eq!(json["nodes"][1]["delta_size"], 3);
+
+  recorder.begin_frame();
+  assert!(recorder.last_frame().unwrap().nodes.is_empty());
+}
@@ -16,13 +16,16 @@ use futures::FutureExt;
 use futures::stream::*;
 use parking_lot::RwLock;
 pub use query::*;
+use web_time::Instant;
 
 mod frame_allocator;
+mod graph_debug;
 mod task_pool;
 mod use_result;
 mod wake_util;
 
 pub use frame_allocator::*;
+pub use graph_debug::*;
 pub use hook::*;
 pub use task_pool::*;
 pub use use_result::*;
@@ -253,9 +256,14 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
     let consumer_id = self.use_shared_consumer(key, label);
     let result = self.use_shared_compute_internal(
       &|cx| {
+        let graph_recorder = cx.shared_hook_ctx().query_graph_recorder.clone();
         provider
           .use_logic(cx)
-          .map_spawn_stage_in_thread_dual_query(cx, |r| r.materialize_delta())
+          .map_spawn_stage_in_thread_dual_query(cx, move |r| {
+            let r = r.materialize_delta();
+            graph_recorder.record_delta(key, r.delta.len());
+            r
+          })
       },
       key,
       label,
@@ -326,6 +334,16 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
     debug_label: &str,
     consumer_id: u32,
   ) -> UseResult<Arc<dyn Any + Send + Sync>> {
+    let shared_hook_ctx = self.shared_hook_ctx();
+    let recording = shared_hook_ctx.query_graph_recorder.is_enabled();
+    if recording {
+      shared_hook_ctx.query_graph_recorder.record_dependency(
+        shared_hook_ctx.visiting_stack.last().copied(),
+        key,
+        debug_label,
+      );
+    }
+
     let shared_waker = {
       let waker = self.waker().clone();
       let shared = self.shared_hook_ctx().shared.entry(key).or_insert_with(|| {
@@ -349,14 +367,29 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
       *self.waker() = futures::task::waker(shared_waker);
 
       self.enter_shared_ctx(key, debug_label, |cx| {
+        let logic_start = recording.then(Instant::now);
         let result = logic(cx);
+        if let Some(logic_start) = logic_start {
+          let recorder = &cx.shared_hook_ctx().query_graph_recorder;
+          recorder.record_logic_execution(key, logic_start.elapsed());
+        }
 
         let (cx, persist_upstream_task_id) = cx.use_plain_state(|| u32::MAX);
 
         match result {
           UseResult::SpawnStageFuture(future) => {
+            let task_timing = recording.then(|| {
+              let recorder = cx.shared_hook_ctx().query_graph_recorder.clone();
+              (recorder, Instant::now())
+            });
             if let QueryHookStage::SpawnTask { pool, .. } = cx.stage() {
-              let spawned_task_id = pool.install_task_dyn(future);
+              let spawned_task_id = pool.install_task_dyn(async move {
+                let r = future.await;
+                if let Some((recorder, task_start)) = task_timing {
+                  recorder.record_task(key, task_start.elapsed());
+                }
+                r
+              });
               cx.shared_hook_ctx()
                 .task_id_mapping
                 .insert(key, spawned_task_id);
@@ -448,6 +481,7 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
     f: impl FnOnce(&mut Self) -> R,
   ) -> R {
     self.if_inspect(|cx| cx.enter_shared_ctx(&key, debug_label));
+    self.shared_hook_ctx().visiting_stack.push(key);
 
     let shared = self.shared_hook_ctx().shared.get(&key).unwrap().clone();
 
@@ -467,6 +501,7 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
       r
     };
 
+    self.shared_hook_ctx().visiting_stack.pop();
     self.if_inspect(|cx| cx.leave_shared_ctx(&key));
 
     r
@@ -511,16 +546,31 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
     C: DualQueryLike<Key = ManyKey, Value = Value>,
     R: DualQueryLike<Key = ManyKey, Value = OneKey>,
   {
-    let (_, reducer) = self.use_plain_state(|| {
-      let reducer = HierarchyMonoidReducerGroup::<OneKey, ManyKey, Value, _>::new(reduce_logic);
-      Arc::new(RwLock::new(reducer))
-    });
-    let reducer = reducer.clone();
+    self.use_group_reduce(many_side_changes, relation_changes, || {
+      HierarchyMonoidReducerGroup::<OneKey, ManyKey, Value, _>::new(reduce_logic)
+    })
+  }
 
-    // self.if_inspect(|inspector| {
-    //   let bytes = reducer.read().memory_usage_in_bytes();
-    //   inspector.label_memory_usage("hierarchy_reducer", bytes);
-    // });
+  /// Group the many side by the relation and reduce each group by the reducer, for
+  /// example [HierarchyMonoidReducerGroup::new_sum] or [GroupCountReducer].
+  fn use_group_reduce<C, R, OneKey, ManyKey, Value, Output, Reducer>(
+    &mut self,
+    many_side_changes: UseResult<C>,
+    relation_changes: UseResult<R>,
+    create_reducer: impl FnOnce() -> Reducer,
+  ) -> UseResult<impl DualQueryLike<Key = OneKey, Value = Output>>
+  where
+    OneKey: CKey,
+    ManyKey: CKey,
+    Value: CValue,
+    Output: CValue,
+    C: DualQueryLike<Key = ManyKey, Value = Value>,
+    R: DualQueryLike<Key = ManyKey, Value = OneKey>,
+    Reducer: AbstractReducer<OneKey, ManyKey, Value, Output> + Send + Sync + 'static,
+    LockReadGuardHolder<Reducer>: Query<Key = OneKey, Value = Output>,
+  {
+    let (_, reducer) = self.use_plain_state(|| Arc::new(RwLock::new(create_reducer())));
+    let reducer = reducer.clone();
 
     many_side_changes
       .join(relation_changes)
@@ -529,11 +579,7 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
         |(changes, r_change)| changes.has_delta_hint() || r_change.has_delta_hint(),
         move |(changes, r_change)| {
           let mut reducer_ = reducer.write();
-          let delta = reduce_impl(
-            changes,
-            r_change,
-            &mut reducer_ as &mut HierarchyMonoidReducerGroup<OneKey, ManyKey, Value, _>,
-          );
+          let delta = reduce_impl(changes, r_change, &mut *reducer_);
           drop(reducer_);
 
           let view = reducer.make_read_holder();
@@ -543,6 +589,58 @@ pub trait QueryHookCxLike: HooksCxLike + InspectableCx {
       )
   }
 
+  fn use_group_count<C, R, OneKey, ManyKey>(
+    &mut self,
+    many_side_changes: UseResult<C>,
+    relation_changes: UseResult<R>,
+  ) -> UseResult<impl DualQueryLike<Key = OneKey, Value = u32>>
+  where
+    OneKey: CKey,
+    ManyKey: CKey,
+    C: DualQueryLike<Key = ManyKey>,
+    R: DualQueryLike<Key = ManyKey, Value = OneKey>,
+  {
+    self.use_group_reduce(
+      many_side_changes,
+      relation_changes,
+      GroupCountReducer::<OneKey, ManyKey>::default,
+    )
+  }
+
+  /// Select the first `k` entries by the order key, see [TopKQueryCollection]. The `k`
+  /// could be changed between frames.
+  fn use_top_k<C, O>(
+    &mut self,
+    changes: UseResult<C>,
+    k: usize,
+    order_key: impl Fn(&C::Key, &C::Value) -> O + Send + Sync + 'static,
+  ) -> UseResult<impl DualQueryLike<Key = C::Key, Value = C::Value>>
+  where
+    C: DualQueryLike,
+    O: Ord + Clone + Send + Sync + 'static,
+  {
+    let (_, top_k) =
+      self.use_plain_state(|| Arc::new(RwLock::new(TopKQueryCollection::new(k, order_key))));
+    let top_k = top_k.clone();
+    let top_k_ = top_k.clone();
+
+    changes.map_spawn_stage_in_thread(
+      self,
+      // the last k is kept by the collection, the k change alone also updates the selection
+      move |changes| changes.has_delta_hint() || top_k_.read().k() != k,
+      move |changes| {
+        let mut top_k_ = top_k.write();
+        let delta = Arc::new(top_k_.update_with_k(k, changes.delta()));
+        drop(top_k_);
+
+        DualQuery {
+          view: top_k.make_read_holder(),
+          delta,
+        }
+      },
+    )
+  }
+
   /// return (R, if_waked)
   #[track_caller]
   fn run_with_waked_info<R>(&mut self, f: impl FnOnce(&mut Self, bool) -> R) -> (R, bool) {
@@ -604,6 +702,9 @@ pub struct SharedHooksCtx {
   // todo, the reconciler is leaked, not a big issue for now
   delta_query_reconciler: FastHashMap<ShareKey, Arc<dyn DeltaQueryReconciler>>,
   next_consumer: u32,
+  /// the currently executing shared ctx, used to find the consumer of the shared ctx
+  visiting_stack: Vec<ShareKey>,
+  query_graph_recorder: QueryGraphRecorder,
 }
 
 struct SharedHooksCtxUpstreamDropQueue {
@@ -617,6 +718,10 @@ impl SharedConsumerTokenDrop for SharedHooksCtxUpstreamDropQueue {
 }
 
 impl SharedHooksCtx {
+  pub fn query_graph_recorder(&self) -> &QueryGraphRecorder {
+    &self.query_graph_recorder
+  }
+
   pub fn reset_visiting(&mut self) {
     self.task_id_mapping.clear();
     for r in self.delta_query_reconciler.values() {
@@ -25,6 +25,9 @@ pub use fanout::*;
 mod reduce;
 pub use reduce::*;
 
+mod ordered;
+pub use ordered::*;
+
 use crate::*;
 
 #[derive(Clone)]
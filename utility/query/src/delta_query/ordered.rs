@@ -0,0 +1,458 @@
+#[allow(clippy::disallowed_types)] // the ordered iteration requires the ordered map
+use std::collections::BTreeMap;
+
+use crate::*;
+
+/// Keep the entries of a query sorted by the order key extracted from the entry, updated
+/// incrementally from the delta. The entries with the same order key are sorted by the
+/// insertion order.
+///
+/// For the descending order, wrap the order key by [std::cmp::Reverse].
+pub struct OrderedQueryCollection<K, V, O, F> {
+  entries: FastHashMap<K, (V, O, u64)>,
+  #[allow(clippy::disallowed_types)]
+  ordered: BTreeMap<(O, u64), K>,
+  next_seq: u64,
+  order_key: F,
+}
+
+impl<K, V, O, F> OrderedQueryCollection<K, V, O, F>
+where
+  K: CKey,
+  V: CValue,
+  O: Ord + Clone,
+  F: Fn(&K, &V) -> O,
+{
+  pub fn new(order_key: F) -> Self {
+    Self {
+      entries: Default::default(),
+      ordered: Default::default(),
+      next_seq: 0,
+      order_key,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn apply_changes(&mut self, changes: impl Query<Key = K, Value = ValueChange<V>>) {
+    for (k, change) in changes.iter_key_value() {
+      match change {
+        ValueChange::Delta(v, _) => self.insert_or_update(k, v),
+        ValueChange::Remove(_) => self.remove(&k),
+      }
+    }
+  }
+
+  pub fn insert_or_update(&mut self, key: K, value: V) {
+    let order = (self.order_key)(&key, &value);
+    if let Some((previous_value, previous_order, _)) = self.entries.get_mut(&key)
+      && *previous_order == order
+    {
+      // the position is not changed, keep the insertion order
+      *previous_value = value;
+      return;
+    }
+
+    self.remove(&key);
+    let seq = self.next_seq;
+    self.next_seq += 1;
+    self.ordered.insert((order.clone(), seq), key.clone());
+    self.entries.insert(key, (value, order, seq));
+  }
+
+  // remove none exist is allowed
+  pub fn remove(&mut self, key: &K) {
+    if let Some((_, order, seq)) = self.entries.remove(key) {
+      self.ordered.remove(&(order, seq));
+    }
+  }
+
+  pub fn get(&self, key: &K) -> Option<&V> {
+    self.entries.get(key).map(|(v, _, _)| v)
+  }
+
+  /// Iterate the entries by the ascending order.
+  pub fn iter_ordered(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + '_ {
+    self
+      .ordered
+      .values()
+      .map(|k| (k, &self.entries.get(k).unwrap().0))
+  }
+
+  /// The first `n` entries by the ascending order.
+  pub fn iter_first_n(&self, n: usize) -> impl Iterator<Item = (&K, &V)> + '_ {
+    self.iter_ordered().take(n)
+  }
+}
+
+impl<K, V, O, F> Query for LockReadGuardHolder<OrderedQueryCollection<K, V, O, F>>
+where
+  K: CKey,
+  V: CValue,
+  O: Send + Sync + 'static,
+  F: Send + Sync + 'static,
+{
+  type Key = K;
+  type Value = V;
+
+  fn iter_key_value(&self) -> impl Iterator<Item = (Self::Key, Self::Value)> + '_ {
+    self
+      .entries
+      .iter()
+      .map(|(k, (v, _, _))| (k.clone(), v.clone()))
+  }
+
+  fn access(&self, key: &Self::Key) -> Option<Self::Value> {
+    self.entries.get(key).map(|(v, _, _)| v.clone())
+  }
+
+  fn has_item_hint(&self) -> bool {
+    !self.entries.is_empty()
+  }
+}
+
+/// Keep the first `k` entries of a query by the order key, updated incrementally from
+/// the delta. The view of this collection only contains the selected entries, and the
+/// [Self::update] returns the delta of the selection:
+///
+/// - the entry enters the selection is reported as the new insert
+/// - the entry leaves the selection(or removed from the source) is reported as the remove
+/// - the selected entry's value change is reported as the change
+///
+/// The selection is always a prefix of the ordered entries, so only the changed entries and
+/// the entries around the selection boundary are visited when updating.
+///
+/// For example the "nearest N lights", use the distance as the order key.
+pub struct TopKQueryCollection<K, V, O, F> {
+  all: OrderedQueryCollection<K, V, O, F>,
+  selected: FastHashMap<K, V>,
+  /// the order position of the selected entries
+  #[allow(clippy::disallowed_types)]
+  selected_order: BTreeMap<(O, u64), K>,
+  k: usize,
+}
+
+impl<K, V, O, F> TopKQueryCollection<K, V, O, F>
+where
+  K: CKey,
+  V: CValue,
+  O: Ord + Clone,
+  F: Fn(&K, &V) -> O,
+{
+  pub fn new(k: usize, order_key: F) -> Self {
+    Self {
+      all: OrderedQueryCollection::new(order_key),
+      selected: Default::default(),
+      selected_order: Default::default(),
+      k,
+    }
+  }
+
+  /// Change the k, return the delta of the entries that enter or leave the selection at the
+  /// boundary.
+  pub fn set_k(&mut self, k: usize) -> FastHashMap<K, ValueChange<V>> {
+    self.k = k;
+    let mut previous = FastHashMap::default();
+    self.fit_k(&mut previous);
+    self.selection_delta(previous)
+  }
+
+  /// Change the k and apply the changes in one update, the delta covers both the boundary
+  /// change and the source change, so the k change alone also emits the delta.
+  pub fn update_with_k(
+    &mut self,
+    k: usize,
+    changes: impl Query<Key = K, Value = ValueChange<V>>,
+  ) -> FastHashMap<K, ValueChange<V>> {
+    self.k = k;
+    self.update(changes)
+  }
+
+  pub fn k(&self) -> usize {
+    self.k
+  }
+
+  /// All entries of the source, including the unselected.
+  pub fn all(&self) -> &OrderedQueryCollection<K, V, O, F> {
+    &self.all
+  }
+
+  /// Iterate the selected entries by the ascending order.
+  pub fn iter_selected_ordered(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+    self
+      .selected_order
+      .values()
+      .map(|k| (k, self.selected.get(k).unwrap()))
+  }
+
+  pub fn update(
+    &mut self,
+    changes: impl Query<Key = K, Value = ValueChange<V>>,
+  ) -> FastHashMap<K, ValueChange<V>> {
+    // the selected value before the update of all touched entries
+    let mut previous = FastHashMap::<K, Option<V>>::default();
+
+    for (k, change) in changes.iter_key_value() {
+      self.unselect(&k, &mut previous);
+      match change {
+        ValueChange::Delta(v, _) => self.all.insert_or_update(k.clone(), v),
+        ValueChange::Remove(_) => self.all.remove(&k),
+      }
+
+      // the selection is a prefix, the entry in front of the last selected is selected.
+      if let Some((_, order, seq)) = self.all.entries.get(&k)
+        && let Some((last, _)) = self.selected_order.last_key_value()
+        && (order, seq) < (&last.0, &last.1)
+      {
+        let position = (order.clone(), *seq);
+        self.select(k, position, &mut previous);
+      }
+    }
+
+    self.fit_k(&mut previous);
+    self.selection_delta(previous)
+  }
+
+  /// shrink or grow the selection prefix to the k entries
+  fn fit_k(&mut self, previous: &mut FastHashMap<K, Option<V>>) {
+    while self.selected_order.len() > self.k {
+      let (_, k) = self.selected_order.last_key_value().unwrap();
+      let k = k.clone();
+      self.unselect(&k, previous);
+    }
+    while self.selected_order.len() < self.k {
+      let next = match self.selected_order.last_key_value() {
+        Some((last, _)) => self
+          .all
+          .ordered
+          .range((std::ops::Bound::Excluded(last), std::ops::Bound::Unbounded))
+          .next(),
+        None => self.all.ordered.iter().next(),
+      };
+      let Some((position, k)) = next else {
+        break;
+      };
+      let (position, k) = (position.clone(), k.clone());
+      self.select(k, position, previous);
+    }
+  }
+
+  fn selection_delta(&self, previous: FastHashMap<K, Option<V>>) -> FastHashMap<K, ValueChange<V>> {
+    let mut delta = FastHashMap::default();
+    for (k, previous) in previous {
+      let change = match (previous, self.selected.get(&k)) {
+        (None, Some(new)) => ValueChange::Delta(new.clone(), None),
+        (Some(old), None) => ValueChange::Remove(old),
+        (Some(old), Some(new)) if &old != new => ValueChange::Delta(new.clone(), Some(old)),
+        _ => continue,
+      };
+      delta.insert(k, change);
+    }
+    delta
+  }
+
+  fn unselect(&mut self, k: &K, previous: &mut FastHashMap<K, Option<V>>) {
+    if let Some(v) = self.selected.remove(k) {
+      let (_, order, seq) = self.all.entries.get(k).unwrap();
+      self.selected_order.remove(&(order.clone(), *seq));
+      previous.entry(k.clone()).or_insert(Some(v));
+    } else {
+      previous.entry(k.clone()).or_insert(None);
+    }
+  }
+
+  fn select(&mut self, k: K, position: (O, u64), previous: &mut FastHashMap<K, Option<V>>) {
+    let v = self.all.get(&k).unwrap().clone();
+    previous.entry(k.clone()).or_insert(None);
+    self.selected_order.insert(position, k.clone());
+    self.selected.insert(k, v);
+  }
+}
+
+impl<K, V, O, F> Query for LockReadGuardHolder<TopKQueryCollection<K, V, O, F>>
+where
+  K: CKey,
+  V: CValue,
+  O: Send + Sync + 'static,
+  F: Send + Sync + 'static,
+{
+  type Key = K;
+  type Value = V;
+
+  fn iter_key_value(&self) -> impl Iterator<Item = (Self::Key, Self::Value)> + '_ {
+    self.selected.iter().map(|(k, v)| (k.clone(), v.clone()))
+  }
+
+  fn access(&self, key: &Self::Key) -> Option<Self::Value> {
+    self.selected.get(key).cloned()
+  }
+
+  fn has_item_hint(&self) -> bool {
+    !self.selected.is_empty()
+  }
+}
+
+#[test]
+fn test_ordered_query_collection() {
+  let mut ordered = OrderedQueryCollection::new(|_: &u32, v: &i32| *v);
+  ordered.apply_changes(FastHashMap::from_iter([
+    (1u32, ValueChange::Delta(30i32, None)),
+    (2, ValueChange::Delta(10, None)),
+    (3, ValueChange::Delta(20, None)),
+  ]));
+  let keys: Vec<_> = ordered.iter_ordered().map(|(k, _)| *k).collect();
+  assert_eq!(keys, vec![2, 3, 1]);
+
+  ordered.apply_changes(FastHashMap::from_iter([
+    (1u32, ValueChange::Delta(5, Some(30))),
+    (2, ValueChange::Remove(10)),
+  ]));
+  let keys: Vec<_> = ordered.iter_ordered().map(|(k, _)| *k).collect();
+  assert_eq!(keys, vec![1, 3]);
+
+  let ordered = Arc::new(parking_lot::RwLock::new(ordered));
+  validate_query_consistency(&ordered.make_read_holder());
+}
+
+#[test]
+fn test_top_k_query_collection() {
+  let mut top_k = TopKQueryCollection::new(2, |_: &u32, v: &i32| *v);
+
+  let delta = top_k.update(FastHashMap::from_iter([
+    (1u32, ValueChange::Delta(30i32, None)),
+    (2, ValueChange::Delta(10, None)),
+    (3, ValueChange::Delta(20, None)),
+  ]));
+  validate_query_consistency(&delta);
+  assert_eq!(delta.len(), 2);
+  assert_eq!(delta.access(&2), Some(ValueChange::Delta(10, None)));
+  assert_eq!(delta.access(&3), Some(ValueChange::Delta(20, None)));
+
+  // remove a selected entry, the next one enters
+  let delta = top_k.update(FastHashMap::from_iter([(2u32, ValueChange::Remove(10))]));
+  assert_eq!(delta.len(), 2);
+  assert_eq!(delta.access(&2), Some(ValueChange::Remove(10)));
+  assert_eq!(delta.access(&1), Some(ValueChange::Delta(30, None)));
+
+  // the selected value changed but still be selected
+  let delta = top_k.update(FastHashMap::from_iter([(
+    3u32,
+    ValueChange::Delta(25, Some(20)),
+  )]));
+  assert_eq!(delta.len(), 1);
+  assert_eq!(delta.access(&3), Some(ValueChange::Delta(25, Some(20))));
+
+  // the unselected change does not affect the selection
+  let delta = top_k.update(FastHashMap::from_iter([(
+    4u32,
+    ValueChange::Delta(100, None),
+  )]));
+  assert!(delta.is_empty());
+
+  let delta = top_k.set_k(1);
+  assert_eq!(delta.len(), 1);
+  assert_eq!(delta.access(&1), Some(ValueChange::Remove(30)));
+
+  let ordered: Vec<_> = top_k.iter_selected_ordered().map(|(k, _)| *k).collect();
+  assert_eq!(ordered, vec![3]);
+
+  let top_k = Arc::new(parking_lot::RwLock::new(top_k));
+  let view = top_k.make_read_holder();
+  validate_query_consistency(&view);
+  assert_eq!(view.access(&3), Some(25));
+}
+
+#[test]
+fn test_top_k_query_collection_k_change() {
+  let mut top_k = TopKQueryCollection::new(1, |_: &u32, v: &i32| *v);
+  let source = FastHashMap::from_iter([
+    (1u32, ValueChange::Delta(30i32, None)),
+    (2, ValueChange::Delta(10, None)),
+    (3, ValueChange::Delta(20, None)),
+  ]);
+  let delta = top_k.update_with_k(1, source);
+  assert_eq!(delta.len(), 1);
+  assert_eq!(delta.access(&2), Some(ValueChange::Delta(10, None)));
+
+  // only the k is changed, the upstream delta is empty
+  let empty = FastHashMap::<u32, ValueChange<i32>>::default;
+  let delta = top_k.update_with_k(3, empty());
+  assert_eq!(delta.len(), 2);
+  assert_eq!(delta.access(&3), Some(ValueChange::Delta(20, None)));
+  assert_eq!(delta.access(&1), Some(ValueChange::Delta(30, None)));
+  assert_eq!(top_k.k(), 3);
+
+  // shrink the k while the source change moves 3 behind 1, only 3 leaves the selection
+  let delta = top_k.update_with_k(
+    2,
+    FastHashMap::from_iter([(3u32, ValueChange::Delta(40, Some(20)))]),
+  );
+  assert_eq!(delta.len(), 1);
+  assert_eq!(delta.access(&3), Some(ValueChange::Remove(20)));
+  let ordered: Vec<_> = top_k.iter_selected_ordered().map(|(k, _)| *k).collect();
+  assert_eq!(ordered, vec![2, 1]);
+
+  assert!(top_k.update_with_k(2, empty()).is_empty());
+}
+
+#[test]
+fn test_top_k_query_collection_matches_full_selection() {
+  let mut top_k = TopKQueryCollection::new(4, |_: &u32, v: &i32| *v);
+  // the selection maintained by applying the reported delta
+  let mut selection = FastHashMap::<u32, i32>::default();
+  let mut source = FastHashMap::<u32, i32>::default();
+
+  let mut seed = 7u32;
+  let mut random = move |range: u32| {
+    seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+    (seed >> 16) % range
+  };
+
+  for round in 0..200 {
+    let mut changes = FastHashMap::default();
+    for _ in 0..random(4) {
+      let key = random(16);
+      let old = source.get(&key).copied();
+      if random(4) == 0 {
+        if let Some(old) = source.remove(&key) {
+          changes.insert(key, ValueChange::Remove(old));
+        }
+      } else {
+        let value = random(10) as i32;
+        source.insert(key, value);
+        changes.insert(key, ValueChange::Delta(value, old));
+      }
+    }
+    let k = if round % 50 == 49 {
+      random(8) as usize
+    } else {
+      top_k.k()
+    };
+
+    let delta = top_k.update_with_k(k, changes);
+    for (k, change) in delta {
+      match change {
+        ValueChange::Delta(v, old) => assert_eq!(selection.insert(k, v), old),
+        ValueChange::Remove(old) => assert_eq!(selection.remove(&k), Some(old)),
+      }
+    }
+
+    let expected: Vec<_> = top_k
+      .all()
+      .iter_first_n(top_k.k())
+      .map(|(k, v)| (*k, *v))
+      .collect();
+    let selected: Vec<_> = top_k
+      .iter_selected_ordered()
+      .map(|(k, v)| (*k, *v))
+      .collect();
+    assert_eq!(selected, expected);
+    assert_eq!(selection, FastHashMap::from_iter(expected));
+  }
+}
@@ -2,17 +2,23 @@ use hierarchy_reducer::HierarchyMonoidReducer;
 
 use crate::*;
 
-pub fn reduce_impl<OneKey: CKey, ManyKey, ManyValue>(
+pub fn reduce_impl<OneKey: CKey, ManyKey, ManyValue, Output>(
   many: impl DualQueryLike<Key = ManyKey, Value = ManyValue>,
   relation: impl DualQueryLike<Key = ManyKey, Value = OneKey>,
-  states: &mut impl AbstractReducer<OneKey, ManyKey, ManyValue>,
-) -> Arc<FastHashMap<OneKey, ValueChange<ManyValue>>> {
+  states: &mut impl AbstractReducer<OneKey, ManyKey, ManyValue, Output>,
+) -> Arc<FastHashMap<OneKey, ValueChange<Output>>> {
   let (many, many_delta) = many.view_delta();
   let (relation, relation_delta) = relation.view_delta();
 
   for (many_key, relation_change) in relation_delta.iter_key_value() {
     match relation_change {
-      ValueChange::Delta(new_one, _) => {
+      ValueChange::Delta(new_one, old_one) => {
+        // the many side moved to another group
+        if let Some(old_one) = old_one
+          && old_one != new_one
+        {
+          states.notify_remove(&old_one, &many_key);
+        }
         if let Some(many) = many.access(&many_key) {
           states.notify_insert_or_update(new_one.clone(), many_key, many);
         }
@@ -43,16 +49,20 @@ pub fn reduce_impl<OneKey: CKey, ManyKey, ManyValue>(
   Arc::new(states.update())
 }
 
-pub trait AbstractReducer<KOne, K, T> {
+/// Group the many side by the one side key and reduce each group into the `O`.
+pub trait AbstractReducer<KOne, K, T, O = T> {
   // remove none exist is allowed
   fn notify_remove(&mut self, one_key: &KOne, key: &K);
   fn notify_insert_or_update(&mut self, one_key: KOne, key: K, value: T);
-  fn update(&mut self) -> FastHashMap<KOne, ValueChange<T>>;
+  fn update(&mut self) -> FastHashMap<KOne, ValueChange<O>>;
 }
 
 pub struct HierarchyMonoidReducerGroup<KOne, K, T, F> {
   mapping: FastHashMap<KOne, HierarchyMonoidReducer<K, T>>,
-  changed: FastHashSet<KOne>,
+  /// changed group => the value before the first change in this batch. The value must be
+  /// captured before the mutation, because the root of the reducer is the leaf when it has
+  /// only one item, and the root may be moved when the reducer grows.
+  changed: FastHashMap<KOne, Option<T>>,
   reducer: F,
 }
 
@@ -85,12 +95,39 @@ impl<KOne, K, T, F> HierarchyMonoidReducerGroup<KOne, K, T, F> {
   pub fn new(reducer: F) -> Self {
     Self {
       mapping: FastHashMap::default(),
-      changed: FastHashSet::default(),
+      changed: FastHashMap::default(),
       reducer,
     }
   }
 }
 
+pub type GroupReduceFn<T> = fn(T, T) -> T;
+
+/// The common group reducers. The removal is handled by the hierarchy reducer, so the
+/// float sum will not accumulate the error by the add and subtract.
+impl<KOne, K, T> HierarchyMonoidReducerGroup<KOne, K, T, GroupReduceFn<T>> {
+  pub fn new_sum() -> Self
+  where
+    T: std::ops::Add<Output = T>,
+  {
+    Self::new(|a, b| a + b)
+  }
+
+  pub fn new_min() -> Self
+  where
+    T: PartialOrd,
+  {
+    Self::new(|a, b| if b < a { b } else { a })
+  }
+
+  pub fn new_max() -> Self
+  where
+    T: PartialOrd,
+  {
+    Self::new(|a, b| if b > a { b } else { a })
+  }
+}
+
 impl<KOne, K, T, F> AbstractReducer<KOne, K, T> for HierarchyMonoidReducerGroup<KOne, K, T, F>
 where
   KOne: Hash + Eq + Clone,
@@ -101,18 +138,22 @@ where
   // remove none exist is allowed
   fn notify_remove(&mut self, one_key: &KOne, key: &K) {
     if let Some(reducer) = self.mapping.get_mut(one_key) {
+      if !self.changed.contains_key(one_key) {
+        self
+          .changed
+          .insert(one_key.clone(), reducer.current_value().cloned());
+      }
       reducer.notify_remove(key);
-      self.changed.insert(one_key.clone());
     }
   }
 
   fn notify_insert_or_update(&mut self, one_key: KOne, key: K, value: T) {
+    let reducer = self.mapping.entry(one_key.clone()).or_default();
     self
-      .mapping
-      .entry(one_key.clone())
-      .or_default()
-      .notify_insert_or_update(key, value);
-    self.changed.insert(one_key);
+      .changed
+      .entry(one_key)
+      .or_insert_with(|| reducer.current_value().cloned());
+    reducer.notify_insert_or_update(key, value);
   }
 
   fn update(&mut self) -> FastHashMap<KOne, ValueChange<T>> {
@@ -120,11 +161,10 @@ where
     let reducer_ref = &self.reducer;
 
     let mut empty_keys = Vec::new();
-    for one_key in self.changed.drain() {
+    for (one_key, old_v) in self.changed.drain() {
       let Some(reducer) = self.mapping.get_mut(&one_key) else {
         continue;
       };
-      let old_v = reducer.current_value().cloned();
       let new_v = reducer.update(reducer_ref);
 
       match (old_v, new_v) {
@@ -154,3 +194,177 @@ where
     changes
   }
 }
+
+/// Count the many side entities of each group, the empty group is removed.
+pub struct GroupCountReducer<KOne, K> {
+  groups: FastHashMap<KOne, GroupCount<K>>,
+  changed: FastHashSet<KOne>,
+}
+
+struct GroupCount<K> {
+  members: FastHashSet<K>,
+  /// the count reported by the last update, zero if not reported
+  reported: u32,
+}
+
+impl<KOne, K> Default for GroupCountReducer<KOne, K> {
+  fn default() -> Self {
+    Self {
+      groups: Default::default(),
+      changed: Default::default(),
+    }
+  }
+}
+
+impl<KOne: CKey, K: CKey> Query for LockReadGuardHolder<GroupCountReducer<KOne, K>> {
+  type Key = KOne;
+  type Value = u32;
+
+  fn iter_key_value(&self) -> impl Iterator<Item = (Self::Key, Self::Value)> + '_ {
+    self
+      .groups
+      .iter()
+      .map(|(k, v)| (k.clone(), v.members.len() as u32))
+  }
+
+  fn access(&self, key: &Self::Key) -> Option<Self::Value> {
+    self.groups.get(key).map(|v| v.members.len() as u32)
+  }
+
+  fn has_item_hint(&self) -> bool {
+    !self.groups.is_empty()
+  }
+}
+
+impl<KOne, K, T> AbstractReducer<KOne, K, T, u32> for GroupCountReducer<KOne, K>
+where
+  KOne: Hash + Eq + Clone,
+  K: Hash + Eq + Clone,
+{
+  fn notify_remove(&mut self, one_key: &KOne, key: &K) {
+    if let Some(group) = self.groups.get_mut(one_key)
+      && group.members.remove(key)
+    {
+      self.changed.insert(one_key.clone());
+    }
+  }
+
+  fn notify_insert_or_update(&mut self, one_key: KOne, key: K, _: T) {
+    let group = self
+      .groups
+      .entry(one_key.clone())
+      .or_insert_with(|| GroupCount {
+        members: Default::default(),
+        reported: 0,
+      });
+    if group.members.insert(key) {
+      self.changed.insert(one_key);
+    }
+  }
+
+  fn update(&mut self) -> FastHashMap<KOne, ValueChange<u32>> {
+    let mut changes = FastHashMap::default();
+    for one_key in self.changed.drain() {
+      let Some(group) = self.groups.get_mut(&one_key) else {
+        continue;
+      };
+      let count = group.members.len() as u32;
+      let old = group.reported;
+      if count == 0 {
+        if old != 0 {
+          changes.insert(one_key.clone(), ValueChange::Remove(old));
+        }
+        self.groups.remove(&one_key);
+        continue;
+      }
+      if count != old {
+        changes.insert(
+          one_key,
+          ValueChange::Delta(count, (old != 0).then_some(old)),
+        );
+      }
+      group.reported = count;
+    }
+    changes
+  }
+}
+
+#[cfg(test)]
+fn dual<K: CKey, V: CValue>(
+  view: impl IntoIterator<Item = (K, V)>,
+  delta: impl IntoIterator<Item = (K, ValueChange<V>)>,
+) -> impl DualQueryLike<Key = K, Value = V> {
+  DualQuery {
+    view: Arc::new(FastHashMap::from_iter(view)),
+    delta: Arc::new(FastHashMap::from_iter(delta)),
+  }
+}
+
+#[test]
+fn test_group_reducers() {
+  let mut count = GroupCountReducer::default();
+  let mut sum = HierarchyMonoidReducerGroup::new_sum();
+  let mut max = HierarchyMonoidReducerGroup::new_max();
+
+  // many 1, 2 in group 10, many 3 in group 20
+  let values = [(1u32, 1.5f32), (2, 2.), (3, 4.)];
+  let relation = [(1u32, 10u32), (2, 10), (3, 20)];
+  let many = || {
+    dual(
+      values,
+      values.map(|(k, v)| (k, ValueChange::Delta(v, None))),
+    )
+  };
+  let relation_new = || {
+    dual(
+      relation,
+      relation.map(|(k, v)| (k, ValueChange::Delta(v, None))),
+    )
+  };
+
+  let delta = reduce_impl(many(), relation_new(), &mut count);
+  validate_query_consistency(&delta);
+  assert_eq!(delta.access(&10), Some(ValueChange::Delta(2, None)));
+  assert_eq!(delta.access(&20), Some(ValueChange::Delta(1, None)));
+  let delta = reduce_impl(many(), relation_new(), &mut sum);
+  assert_eq!(delta.access(&10), Some(ValueChange::Delta(3.5, None)));
+  let delta = reduce_impl(many(), relation_new(), &mut max);
+  assert_eq!(delta.access(&10), Some(ValueChange::Delta(2., None)));
+
+  // move many 2 into group 20, remove many 3
+  let values = [(1u32, 1.5f32), (2, 2.)];
+  let relation = [(1u32, 10u32), (2, 20)];
+  let many = || dual(values, [(3, ValueChange::Remove(4.))]);
+  let relation_change = || {
+    dual(
+      relation,
+      [
+        (2u32, ValueChange::Delta(20u32, Some(10))),
+        (3, ValueChange::Remove(20)),
+      ],
+    )
+  };
+
+  let delta = reduce_impl(many(), relation_change(), &mut count);
+  validate_query_consistency(&delta);
+  assert_eq!(delta.len(), 1);
+  assert_eq!(delta.access(&10), Some(ValueChange::Delta(1, Some(2))));
+  let delta = reduce_impl(many(), relation_change(), &mut sum);
+  assert_eq!(delta.access(&10), Some(ValueChange::Delta(1.5, Some(3.5))));
+  assert_eq!(delta.access(&20), Some(ValueChange::Delta(2., Some(4.))));
+  let delta = reduce_impl(many(), relation_change(), &mut max);
+  assert_eq!(delta.access(&10), Some(ValueChange::Delta(1.5, Some(2.))));
+  assert_eq!(delta.access(&20), Some(ValueChange::Delta(2., Some(4.))));
+
+  // remove all
+  let many = || dual([], values.map(|(k, v)| (k, ValueChange::Remove(v))));
+  let relation_remove = || dual([], relation.map(|(k, v)| (k, ValueChange::Remove(v))));
+  let delta = reduce_impl(many(), relation_remove(), &mut count);
+  assert_eq!(delta.access(&10), Some(ValueChange::Remove(1)));
+  assert_eq!(delta.access(&20), Some(ValueChange::Remove(1)));
+
+  let count = Arc::new(parking_lot::RwLock::new(count));
+  let view = count.make_read_holder();
+  validate_query_consistency(&view);
+  assert!(!view.has_item_hint());
+}
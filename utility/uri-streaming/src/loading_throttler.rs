@@ -29,6 +29,11 @@ impl<K: CKey, V, URI: Clone> LoadingThrottler<K, V, URI> {
     self.loaded.contains_key(k)
   }
 
+  /// if any request is waiting for the bandwidth or still in loading
+  pub fn has_pending_request(&self) -> bool {
+    !self.waitings.is_empty() || !self.loading_uri.is_empty()
+  }
+
   pub fn request_load(&mut self, k: K, uri: URI, cost: u64) {
     if self.loading_uri.contains_key(&k) {
       // should we assert this case?